@@ -0,0 +1,85 @@
+use conch_parser::ast::builder::EmptyBuilder;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, Parser, PositionTracking};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// A representative mix of keywords, operators, quoting, and substitutions.
+const SCRIPT: &str = r#"#!/bin/sh
+# Process every file given on the command line
+set -eu
+for file in "$@"; do
+    if [ -f "$file" ] && grep -q 'pattern' "$file"; then
+        echo "found in ${file##*/}: $(wc -l < "$file") lines" >> /tmp/log
+    elif test -d "$file"; then
+        case "$file" in
+            *.git) continue ;;
+            *) find "$file" -type f -name '*.sh' | while read -r f; do echo "$f"; done ;;
+        esac
+    else
+        printf '%s\n' "missing $file" 1>&2
+    fi
+done
+count=$((count + 1))
+function_name() {
+    local x=${1:-default} y="$2"
+    until [ "$x" -gt 10 ]; do x=$((x + 1)); done
+    cat <<EOF
+body $x and ${y}
+EOF
+}
+"#;
+
+/// Builds a corpus of roughly 1MB by repeating the sample script.
+fn corpus() -> String {
+    let copies = (1 << 20) / SCRIPT.len() + 1;
+    SCRIPT.repeat(copies)
+}
+
+fn bench_corpus(c: &mut Criterion) {
+    let src = corpus();
+
+    let mut group = c.benchmark_group("corpus_1mb");
+    group.throughput(Throughput::Bytes(src.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("lex", |b| {
+        b.iter(|| Lexer::new(black_box(src.as_str()).chars()).count())
+    });
+
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            let lex = Lexer::new(black_box(src.as_str()).chars());
+            DefaultParser::new(lex)
+                .into_iter()
+                .inspect(|cmd| assert!(cmd.is_ok(), "corpus should parse"))
+                .count()
+        })
+    });
+
+    group.bench_function("parse_byte_offsets", |b| {
+        b.iter(|| {
+            let lex = Lexer::new(black_box(src.as_str()).chars());
+            let mut parser = DefaultParser::new(lex);
+            parser.set_position_tracking(PositionTracking::ByteOffsets);
+            parser
+                .into_iter()
+                .inspect(|cmd| assert!(cmd.is_ok(), "corpus should parse"))
+                .count()
+        })
+    });
+
+    group.bench_function("parse_empty_builder", |b| {
+        b.iter(|| {
+            let lex = Lexer::new(black_box(src.as_str()).chars());
+            Parser::with_builder(lex, EmptyBuilder::new())
+                .into_iter()
+                .inspect(|cmd| assert!(cmd.is_ok(), "corpus should parse"))
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_corpus);
+criterion_main!(benches);
@@ -43,13 +43,15 @@ fn count_echo_top_level(cmd: &ast::TopLevelCommand<String>) -> usize {
             }))
             .map(|cmd| count_echo_listable(&cmd))
             .sum(),
+        // Regions which failed to parse (when recovering from errors) have no AST
+        ast::Command::Unparsed(_) => 0,
     }
 }
 
 fn count_echo_listable(cmd: &ast::DefaultListableCommand) -> usize {
     match cmd {
         ast::ListableCommand::Single(cmd) => count_echo_pipeable(cmd),
-        ast::ListableCommand::Pipe(_, cmds) => cmds.into_iter().map(count_echo_pipeable).sum(),
+        ast::ListableCommand::Pipe(pipeline) => pipeline.commands().map(count_echo_pipeable).sum(),
     }
 }
 
@@ -57,7 +59,8 @@ fn count_echo_pipeable(cmd: &ast::DefaultPipeableCommand) -> usize {
     match cmd {
         ast::PipeableCommand::Simple(cmd) => count_echo_simple(cmd),
         ast::PipeableCommand::Compound(cmd) => count_echo_compound(cmd),
-        ast::PipeableCommand::FunctionDef(_, cmd) => count_echo_compound(cmd),
+        ast::PipeableCommand::FunctionDef(_, _, cmd) => count_echo_compound(cmd),
+        _ => 0,
     }
 }
 
@@ -96,6 +99,8 @@ fn count_echo_compound(cmd: &ast::DefaultCompoundCommand) -> usize {
             .iter()
             .map(|pat| count_echo_top_level_array(&pat.body))
             .sum(),
+
+        _ => 0,
     }
 }
 
@@ -119,6 +124,7 @@ fn count_echo_simple(cmd: &ast::DefaultSimpleCommand) -> usize {
                 get_simple_word_as_string(&words[0])
             }
             ast::Word::DoubleQuoted(_) => None, // Ignore all multi-word double quoted strings
+            ast::Word::Braces(_) => None,       // Brace expansions are never a single word
         })
         .filter(|w| *w == "echo")
         .count()
@@ -0,0 +1,80 @@
+//! Compares `validate::validate` against the reference shells of each dialect
+//! (`dash -n` for `Dialect::Posix`, and `bash -n` for `Dialect::Bash`) over the
+//! `validate::CONFORMANCE_CORPUS`, and prints every source they disagree on.
+//!
+//! The program exits with a failure if the divergences found differ from the
+//! published `validate::DIVERGENCES`, e.g. after a change to the parser or
+//! when run against a different version of a shell, so that the list can be
+//! updated: `cargo run --example conformance`.
+
+use conch_parser::parse::Dialect;
+use conch_parser::validate::{conformance, Divergence, DIVERGENCES};
+
+use std::io::Write;
+use std::process::{self, Command, Stdio};
+
+fn shell(dialect: Dialect) -> &'static str {
+    match dialect {
+        Dialect::Posix => "dash",
+        Dialect::Bash => "bash",
+        Dialect::Mksh => "mksh",
+    }
+}
+
+/// Checks the syntax of the source by running `sh -n` with the source on stdin.
+fn shell_accepts(source: &str, dialect: Dialect) -> bool {
+    let mut child = Command::new(shell(dialect))
+        .arg("-n")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|e| {
+            eprintln!("failed to run {}: {}", shell(dialect), e);
+            process::exit(2);
+        });
+
+    // The shell may exit before reading all of its input
+    let _ = child.stdin.take().unwrap().write_all(source.as_bytes());
+    child.wait().map(|status| status.success()).unwrap_or(false)
+}
+
+fn describe(divergence: &Divergence) -> String {
+    format!(
+        "{:>5}: {:<40} {} {}: {}",
+        format!("{:?}", divergence.dialect),
+        format!("{:?}", divergence.source),
+        shell(divergence.dialect),
+        if divergence.shell_accepts {
+            "accepts"
+        } else {
+            "rejects"
+        },
+        divergence.reason,
+    )
+}
+
+fn main() {
+    let divergences = conformance(shell_accepts);
+    for divergence in &divergences {
+        println!("{}", describe(divergence));
+    }
+
+    let mut stale = false;
+    for divergence in DIVERGENCES {
+        if !divergences.contains(divergence) {
+            stale = true;
+            eprintln!("no longer diverges: {}", describe(divergence));
+        }
+    }
+    for divergence in &divergences {
+        if !DIVERGENCES.contains(divergence) {
+            stale = true;
+            eprintln!("newly diverges: {}", describe(divergence));
+        }
+    }
+
+    if stale {
+        process::exit(1);
+    }
+}
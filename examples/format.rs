@@ -0,0 +1,921 @@
+//! An example of a simple formatter which reprints a script from its AST using
+//! a consistent style: one command per line, four space indentation of nested
+//! bodies, and normalized spacing around operators.
+//!
+//! The parser is configured with `ErrorRecovery::Passthrough`, so any commands
+//! which fail to parse are reprinted verbatim (and reported on stderr) rather
+//! than preventing the rest of the script from being formatted. Note that the
+//! default AST does not retain comments, so they are dropped.
+//!
+//! Heredoc bodies are reprinted verbatim by default. With `--indent-heredocs`,
+//! they are instead indented along with the command they belong to, using `<<-`
+//! (which strips leading tabs from every line of the body). Since the stripped
+//! tabs cannot be distinguished from any the body already starts with, bodies
+//! with lines starting with a tab (or escaped newlines) are still reprinted
+//! verbatim, so that the indentation never changes what the heredoc expands to.
+//! Heredocs are never converted to quoted strings, since the parser does not
+//! support bash's here-strings (`<<<`) which would be needed to parse them back.
+//!
+//! The script is read from stdin and the formatted script is written to stdout,
+//! e.g. `cargo run --example format -- --indent-heredocs < script.sh`.
+
+use conch_parser::ast::builder::FunctionSyntax;
+use conch_parser::ast::{
+    AndOr, Arithmetic, ArithmeticTarget, Braces, Command, ComplexWord, CompoundCommandKind,
+    DefaultAndOrList, DefaultArithmetic, DefaultCompoundCommand, DefaultListableCommand,
+    DefaultParameter, DefaultParameterSubstitution, DefaultPipeableCommand, DefaultRedirect,
+    DefaultSimpleCommand, DefaultSimpleWord, DefaultWord, ListableCommand, Parameter,
+    PipeableCommand, Redirect, RedirectOrCmdWord, RedirectOrEnvVar, SimpleWord, TopLevelCommand,
+    TopLevelWord, Word,
+};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, ErrorRecovery};
+
+use std::env;
+use std::io::{self, Read};
+use std::process;
+
+/// The indentation of each nested level of commands.
+const INDENT: &str = "    ";
+
+/// How heredoc bodies are reprinted.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+enum HeredocStyle {
+    /// Print the body and delimiter at the start of each line, exactly as parsed.
+    #[default]
+    Verbatim,
+    /// Indent the body and delimiter with tabs to the level of the command,
+    /// using `<<-`, unless doing so would change the contents of the body.
+    Indent,
+}
+
+/// A heredoc whose body is printed after the current line.
+#[derive(Debug)]
+struct PendingHeredoc {
+    body: String,
+    delim: String,
+    /// The number of tabs each line of the body is indented by.
+    tabs: usize,
+}
+
+/// Prints commands into a buffer, one line at a time.
+#[derive(Debug, Default)]
+struct Formatter {
+    out: String,
+    /// The current level of indentation.
+    level: usize,
+    /// How heredoc bodies are reprinted.
+    heredoc_style: HeredocStyle,
+    /// Heredoc bodies (and their delimiters) which are printed after the current line.
+    heredocs: Vec<PendingHeredoc>,
+}
+
+impl Formatter {
+    /// Ends the current line, followed by any pending heredoc bodies.
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for heredoc in self.heredocs.drain(..) {
+            let tabs = "\t".repeat(heredoc.tabs);
+            for line in heredoc.body.split_terminator('\n') {
+                if !line.is_empty() {
+                    self.out.push_str(&tabs);
+                }
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+            self.out.push_str(&tabs);
+            self.out.push_str(&heredoc.delim);
+            self.out.push('\n');
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.level {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Prints each command on its own line, one level deeper than the current one.
+    fn body(&mut self, cmds: &[TopLevelCommand<String>]) {
+        self.level += 1;
+        for cmd in cmds {
+            self.indent();
+            self.top_level(cmd);
+            self.newline();
+        }
+        self.level -= 1;
+    }
+
+    /// Prints commands on the current line, separated by semicolons.
+    fn inline(&mut self, cmds: &[TopLevelCommand<String>]) {
+        for (i, cmd) in cmds.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str("; ");
+            }
+            self.top_level(cmd);
+        }
+    }
+
+    fn top_level(&mut self, cmd: &TopLevelCommand<String>) {
+        match cmd.0 {
+            Command::List(ref list) => self.and_or(list),
+            Command::Job(ref list) => {
+                self.and_or(list);
+                self.out.push_str(" &");
+            }
+            Command::Unparsed(ref span) => self.out.push_str(span.text.trim_end_matches('\n')),
+        }
+    }
+
+    fn and_or(&mut self, list: &DefaultAndOrList) {
+        self.listable(&list.first);
+        for and_or in &list.rest {
+            let (op, cmd) = match *and_or {
+                AndOr::And(ref cmd) => (" && ", cmd),
+                AndOr::Or(ref cmd) => (" || ", cmd),
+            };
+            self.out.push_str(op);
+            self.listable(cmd);
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                if pipeline.bang {
+                    self.out.push_str("! ");
+                }
+                for (i, segment) in pipeline.segments.iter().enumerate() {
+                    if i > 0 {
+                        self.out
+                            .push_str(if segment.pipe_stderr { " |& " } else { " | " });
+                    }
+                    self.pipeable(&segment.cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(ref name, syntax, ref body) => {
+                match syntax {
+                    FunctionSyntax::Keyword => {
+                        self.out.push_str("function ");
+                        self.out.push_str(name);
+                        self.out.push(' ');
+                    }
+                    FunctionSyntax::KeywordParens => {
+                        self.out.push_str("function ");
+                        self.out.push_str(name);
+                        self.out.push_str("() ");
+                    }
+                    _ => {
+                        self.out.push_str(name);
+                        self.out.push_str("() ");
+                    }
+                }
+                self.compound(body);
+            }
+            _ => unimplemented!("unsupported command: {:?}", cmd),
+        }
+    }
+
+    fn simple(&mut self, cmd: &DefaultSimpleCommand) {
+        let mut first = true;
+        let mut space = |out: &mut String| {
+            if !first {
+                out.push(' ');
+            }
+            first = false;
+        };
+
+        for item in &cmd.redirects_or_env_vars {
+            space(&mut self.out);
+            match *item {
+                RedirectOrEnvVar::Redirect(ref redirect) => self.redirect(redirect),
+                RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                    self.out.push_str(name);
+                    self.out.push('=');
+                    if let Some(ref value) = *value {
+                        self.word(value);
+                    }
+                }
+            }
+        }
+
+        for item in &cmd.redirects_or_cmd_words {
+            space(&mut self.out);
+            match *item {
+                RedirectOrCmdWord::Redirect(ref redirect) => self.redirect(redirect),
+                RedirectOrCmdWord::CmdWord(ref word) => self.word(word),
+            }
+        }
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand) {
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) => {
+                self.out.push('{');
+                self.newline();
+                self.body(cmds);
+                self.indent();
+                self.out.push('}');
+            }
+
+            CompoundCommandKind::Subshell(ref cmds) => {
+                self.out.push('(');
+                self.newline();
+                self.body(cmds);
+                self.indent();
+                self.out.push(')');
+            }
+
+            CompoundCommandKind::While(ref pair) | CompoundCommandKind::Until(ref pair) => {
+                let is_while = matches!(cmd.kind, CompoundCommandKind::While(_));
+                self.out
+                    .push_str(if is_while { "while " } else { "until " });
+                self.inline(&pair.guard);
+                self.out.push_str("; do");
+                self.newline();
+                self.body(&pair.body);
+                self.indent();
+                self.out.push_str("done");
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                for (i, pair) in conditionals.iter().enumerate() {
+                    if i > 0 {
+                        self.indent();
+                    }
+                    self.out.push_str(if i == 0 { "if " } else { "elif " });
+                    self.inline(&pair.guard);
+                    self.out.push_str("; then");
+                    self.newline();
+                    self.body(&pair.body);
+                }
+
+                if let Some(ref body) = *else_branch {
+                    self.indent();
+                    self.out.push_str("else");
+                    self.newline();
+                    self.body(body);
+                }
+
+                self.indent();
+                self.out.push_str("fi");
+            }
+
+            CompoundCommandKind::For {
+                ref var,
+                ref words,
+                ref body,
+            } => {
+                self.out.push_str("for ");
+                self.out.push_str(var);
+                if let Some(ref words) = *words {
+                    self.out.push_str(" in");
+                    for word in words {
+                        self.out.push(' ');
+                        self.word(word);
+                    }
+                }
+                self.out.push_str("; do");
+                self.newline();
+                self.body(body);
+                self.indent();
+                self.out.push_str("done");
+            }
+
+            CompoundCommandKind::Case { ref word, ref arms } => {
+                self.out.push_str("case ");
+                self.word(word);
+                self.out.push_str(" in");
+                self.newline();
+
+                self.level += 1;
+                for arm in arms {
+                    self.indent();
+                    for (i, pattern) in arm.patterns.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(" | ");
+                        }
+                        self.word(pattern);
+                    }
+                    self.out.push(')');
+                    self.newline();
+                    self.body(&arm.body);
+                    self.level += 1;
+                    self.indent();
+                    self.out.push_str(arm.terminator.as_str());
+                    self.newline();
+                    self.level -= 1;
+                }
+                self.level -= 1;
+
+                self.indent();
+                self.out.push_str("esac");
+            }
+
+            _ => unimplemented!("unsupported command: {:?}", cmd.kind),
+        }
+
+        for redirect in &cmd.io {
+            self.out.push(' ');
+            self.redirect(redirect);
+        }
+    }
+
+    fn redirect(&mut self, redirect: &DefaultRedirect) {
+        if let Some(fd) = redirect.fd() {
+            self.out.push_str(&fd.to_string());
+        }
+
+        let op = match *redirect {
+            Redirect::Read(..) => "<",
+            Redirect::Write(..) => ">",
+            Redirect::ReadWrite(..) => "<>",
+            Redirect::Append(..) => ">>",
+            Redirect::Clobber(..) => ">|",
+            Redirect::DupRead(..) => "<&",
+            Redirect::DupWrite(..) => ">&",
+            Redirect::WriteBoth(..) => "&>",
+            Redirect::AppendBoth(..) => "&>>",
+            Redirect::Heredoc(_, ref body, _) => return self.heredoc(body),
+            _ => unimplemented!("unsupported redirect: {:?}", redirect),
+        };
+
+        self.out.push_str(op);
+        self.word(redirect.word());
+    }
+
+    /// Prints the start of a heredoc, and defers printing its body until the end of the line.
+    fn heredoc(&mut self, body: &TopLevelWord<String>) {
+        let parts = word_parts(body);
+        let literal = parts.iter().all(|part| match *part {
+            Word::Simple(ref w) => w.literal().is_some(),
+            _ => false,
+        });
+
+        let mut text = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            if literal {
+                match simple_parts(part)[0] {
+                    // Escaped newlines join lines, which a quoted delimiter would not
+                    SimpleWord::Escaped(ref s) if s == "\n" => {}
+                    ref w => text.push_str(w.literal().unwrap()),
+                }
+            } else {
+                self.word_part(part, parts.get(i + 1), &mut text);
+            }
+        }
+
+        // Pick a delimiter which cannot be confused with a line of the body
+        let mut delim = String::from("EOF");
+        while text.lines().any(|line| line == delim) {
+            delim.push('_');
+        }
+
+        // Leading tabs would be stripped along with the indentation, and
+        // escaped newlines would join the next line along with its indentation
+        let indent = self.heredoc_style == HeredocStyle::Indent
+            && self.level > 0
+            && !text.lines().any(|line| line.starts_with('\t'))
+            && (literal || !text.contains("\\\n"));
+
+        self.out.push_str(if indent { "<<-" } else { "<<" });
+        if literal {
+            self.out.push_str(&format!("'{}'", delim));
+        } else {
+            self.out.push_str(&delim);
+        }
+        self.heredocs.push(PendingHeredoc {
+            body: text,
+            delim,
+            tabs: if indent { self.level } else { 0 },
+        });
+    }
+
+    fn word(&mut self, word: &TopLevelWord<String>) {
+        let parts = word_parts(word);
+        let mut text = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            self.word_part(part, parts.get(i + 1), &mut text);
+        }
+        self.out.push_str(&text);
+    }
+
+    fn word_part(&self, part: &DefaultWord, next: Option<&DefaultWord>, out: &mut String) {
+        match *part {
+            Word::SingleQuoted(ref s) => {
+                out.push('\'');
+                out.push_str(s);
+                out.push('\'');
+            }
+            Word::DoubleQuoted(ref words) => {
+                out.push('"');
+                for (i, w) in words.iter().enumerate() {
+                    self.simple_word(w, words.get(i + 1), out);
+                }
+                out.push('"');
+            }
+            Word::Simple(ref w) => {
+                let next = next.and_then(|next| match *next {
+                    Word::Simple(ref next) => Some(next),
+                    _ => None,
+                });
+                self.simple_word(w, next, out)
+            }
+            Word::Braces(ref braces) => self.braces(braces, out),
+        }
+    }
+
+    fn braces(&self, braces: &Braces<DefaultWord>, out: &mut String) {
+        let step = match *braces {
+            Braces::Alternatives(ref alternatives) => {
+                out.push('{');
+                for (i, words) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    for (j, part) in words.iter().enumerate() {
+                        self.word_part(part, words.get(j + 1), out);
+                    }
+                }
+                out.push('}');
+                return;
+            }
+            Braces::Numbers {
+                start,
+                end,
+                step,
+                width,
+            } => {
+                out.push_str(&format!("{{{:0w$}..{:0w$}", start, end, w = width));
+                step
+            }
+            Braces::Chars { start, end, step } => {
+                out.push_str(&format!("{{{}..{}", start, end));
+                step
+            }
+            _ => return,
+        };
+
+        if let Some(step) = step {
+            out.push_str(&format!("..{}", step));
+        }
+        out.push('}');
+    }
+
+    /// Prints part of a word. The part which follows it is needed to know
+    /// whether a variable name must be wrapped in braces to delimit it.
+    fn simple_word(
+        &self,
+        word: &DefaultSimpleWord,
+        next: Option<&DefaultSimpleWord>,
+        out: &mut String,
+    ) {
+        match *word {
+            SimpleWord::Literal(ref s) => out.push_str(s),
+            SimpleWord::Escaped(ref s) => {
+                out.push('\\');
+                out.push_str(s);
+            }
+            SimpleWord::Param(Parameter::Var(ref name)) => {
+                let continues_name = next
+                    .and_then(SimpleWord::literal)
+                    .and_then(|s| s.chars().next())
+                    .is_some_and(|c| c == '_' || c.is_ascii_alphanumeric());
+
+                if continues_name {
+                    out.push_str(&format!("${{{}}}", name));
+                } else {
+                    out.push('$');
+                    out.push_str(name);
+                }
+            }
+            SimpleWord::Param(ref p) => out.push_str(&p.to_string()),
+            SimpleWord::Subst(ref subst) => self.subst(subst, out),
+            SimpleWord::Star => out.push('*'),
+            SimpleWord::Question => out.push('?'),
+            SimpleWord::SquareOpen => out.push('['),
+            SimpleWord::SquareClose => out.push(']'),
+            SimpleWord::Tilde => out.push('~'),
+            SimpleWord::TildePrefix(ref login) => {
+                out.push('~');
+                out.push_str(login.as_deref().unwrap_or_default());
+            }
+            SimpleWord::Colon => out.push(':'),
+            _ => unimplemented!("unsupported word: {:?}", word),
+        }
+    }
+
+    fn subst(&self, subst: &DefaultParameterSubstitution, out: &mut String) {
+        use conch_parser::ast::ParameterSubstitution::*;
+
+        let (op, param, word) = match *subst {
+            Command(ref cmds) | ProcessRead(ref cmds) | ProcessWrite(ref cmds) => {
+                // Nested commands are formatted relative to the current indentation
+                let mut nested = Formatter {
+                    level: self.level,
+                    heredoc_style: self.heredoc_style,
+                    ..Formatter::default()
+                };
+                nested.inline(cmds);
+                if !nested.heredocs.is_empty() {
+                    nested.newline();
+                }
+                out.push_str(match *subst {
+                    ProcessRead(_) => "<(",
+                    ProcessWrite(_) => ">(",
+                    _ => "$(",
+                });
+                out.push_str(&nested.out);
+                out.push(')');
+                return;
+            }
+            ReadFile(ref file) => {
+                out.push_str("$(< ");
+                let mut nested = Formatter::default();
+                nested.word(file);
+                out.push_str(&nested.out);
+                out.push(')');
+                return;
+            }
+            Len(ref p) => {
+                out.push_str(&format!("${{#{}}}", param_name(p)));
+                return;
+            }
+            Arith(ref expr) => {
+                out.push_str("$((");
+                if let Some(ref expr) = *expr {
+                    out.push(' ');
+                    arith(expr, 0, out);
+                    out.push(' ');
+                }
+                out.push_str("))");
+                return;
+            }
+            Default(colon, ref p, ref w) => (if colon { ":-" } else { "-" }, p, w),
+            Assign(colon, ref p, ref w) => (if colon { ":=" } else { "=" }, p, w),
+            Error(colon, ref p, ref w) => (if colon { ":?" } else { "?" }, p, w),
+            Alternative(colon, ref p, ref w) => (if colon { ":+" } else { "+" }, p, w),
+            RemoveSmallestSuffix(ref p, ref w) => ("%", p, w),
+            RemoveLargestSuffix(ref p, ref w) => ("%%", p, w),
+            RemoveSmallestPrefix(ref p, ref w) => ("#", p, w),
+            RemoveLargestPrefix(ref p, ref w) => ("##", p, w),
+            ReplaceString(ref p, ref w) => ("/", p, w),
+            ReplaceStringAll(ref p, ref w) => ("//", p, w),
+            Substring(ref p, ref w) => (":", p, w),
+            Lowercase(all, ref p, ref w) => (if all { ",," } else { "," }, p, w),
+            Uppercase(all, ref p, ref w) => (if all { "^^" } else { "^" }, p, w),
+            _ => unimplemented!("unsupported substitution: {:?}", subst),
+        };
+
+        out.push_str("${");
+        out.push_str(&param_name(param));
+        out.push_str(op);
+        if let Some(ref word) = *word {
+            let mut nested = Formatter::default();
+            nested.word(word);
+            out.push_str(&nested.out);
+        }
+        out.push('}');
+    }
+}
+
+/// Returns the parts a word is made up of.
+fn word_parts(word: &TopLevelWord<String>) -> &[DefaultWord] {
+    match word.0 {
+        ComplexWord::Single(ref w) => std::slice::from_ref(w),
+        ComplexWord::Concat(ref words) => words,
+    }
+}
+
+/// Returns the simple words which make up part of a word.
+fn simple_parts(word: &DefaultWord) -> &[DefaultSimpleWord] {
+    match *word {
+        Word::Simple(ref w) => std::slice::from_ref(w),
+        Word::DoubleQuoted(ref words) => words,
+        Word::SingleQuoted(_) | Word::Braces(_) => &[],
+    }
+}
+
+/// Returns the name of a parameter as it appears within `${...}`.
+fn param_name(param: &DefaultParameter) -> String {
+    match *param {
+        Parameter::Var(ref name) => name.clone(),
+        Parameter::Positional(n) => n.to_string(),
+        ref p => p.to_string().trim_start_matches('$').to_owned(),
+    }
+}
+
+/// Prints an arithmetic expression, adding parentheses only where they are
+/// required by the precedence of the enclosing operator.
+fn arith(expr: &DefaultArithmetic, min_prec: u8, out: &mut String) {
+    use conch_parser::ast::Arithmetic::*;
+
+    let (prec, op, lhs, rhs) = match *expr {
+        Var(ref name) => return out.push_str(name),
+        Literal(n) | RadixLiteral(n, ..) => return out.push_str(&n.to_string()),
+        Index(ref name, ref index) => {
+            out.push_str(name);
+            out.push('[');
+            arith(index, 0, out);
+            return out.push(']');
+        }
+        PostIncr(ref target) => {
+            arith_target(target, out);
+            return out.push_str("++");
+        }
+        PostDecr(ref target) => {
+            arith_target(target, out);
+            return out.push_str("--");
+        }
+        PreIncr(ref target) => {
+            out.push_str("++");
+            return arith_target(target, out);
+        }
+        PreDecr(ref target) => {
+            out.push_str("--");
+            return arith_target(target, out);
+        }
+        UnaryPlus(ref e) => return unary("+", e, out),
+        UnaryMinus(ref e) => return unary("-", e, out),
+        LogicalNot(ref e) => return unary("!", e, out),
+        BitwiseNot(ref e) => return unary("~", e, out),
+
+        Sequence(ref exprs) => {
+            let parens = min_prec > 0;
+            if parens {
+                out.push('(');
+            }
+            for (i, e) in exprs.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                arith(e, 1, out);
+            }
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        Assign(ref target, ref value) | AssignOp(_, ref target, ref value) => {
+            let parens = min_prec > 1;
+            if parens {
+                out.push('(');
+            }
+            arith_target(target, out);
+            out.push(' ');
+            match *expr {
+                AssignOp(op, ..) => out.push_str(op.as_str()),
+                _ => out.push('='),
+            }
+            out.push(' ');
+            arith(value, 1, out);
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        Ternary(ref cond, ref then, ref els) => {
+            let parens = min_prec > 2;
+            if parens {
+                out.push('(');
+            }
+            arith(cond, 3, out);
+            out.push_str(" ? ");
+            arith(then, 1, out);
+            out.push_str(" : ");
+            arith(els, 2, out);
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        LogicalOr(ref l, ref r) => (3, "||", l, r),
+        LogicalAnd(ref l, ref r) => (4, "&&", l, r),
+        BitwiseOr(ref l, ref r) => (5, "|", l, r),
+        BitwiseXor(ref l, ref r) => (6, "^", l, r),
+        BitwiseAnd(ref l, ref r) => (7, "&", l, r),
+        Eq(ref l, ref r) => (8, "==", l, r),
+        NotEq(ref l, ref r) => (8, "!=", l, r),
+        Less(ref l, ref r) => (9, "<", l, r),
+        LessEq(ref l, ref r) => (9, "<=", l, r),
+        Great(ref l, ref r) => (9, ">", l, r),
+        GreatEq(ref l, ref r) => (9, ">=", l, r),
+        ShiftLeft(ref l, ref r) => (10, "<<", l, r),
+        ShiftRight(ref l, ref r) => (10, ">>", l, r),
+        Add(ref l, ref r) => (11, "+", l, r),
+        Sub(ref l, ref r) => (11, "-", l, r),
+        Mult(ref l, ref r) => (12, "*", l, r),
+        Div(ref l, ref r) => (12, "/", l, r),
+        Modulo(ref l, ref r) => (12, "%", l, r),
+        Pow(ref l, ref r) => (13, "**", l, r),
+        _ => unimplemented!("unsupported arithmetic: {:?}", expr),
+    };
+
+    // All binary operators are left associative, except for exponentiation
+    let (lhs_prec, rhs_prec) = match *expr {
+        Arithmetic::Pow(..) => (prec + 1, prec),
+        _ => (prec, prec + 1),
+    };
+
+    let parens = prec < min_prec;
+    if parens {
+        out.push('(');
+    }
+    arith(lhs, lhs_prec, out);
+    out.push(' ');
+    out.push_str(op);
+    out.push(' ');
+    arith(rhs, rhs_prec, out);
+    if parens {
+        out.push(')');
+    }
+}
+
+fn arith_target(target: &ArithmeticTarget<String>, out: &mut String) {
+    match *target {
+        ArithmeticTarget::Var(ref name) => out.push_str(name),
+        ArithmeticTarget::Index(ref name, ref index) => {
+            out.push_str(name);
+            out.push('[');
+            arith(index, 0, out);
+            out.push(']');
+        }
+    }
+}
+
+fn unary(op: &str, expr: &DefaultArithmetic, out: &mut String) {
+    out.push_str(op);
+    // Avoid accidentally printing `- -x` as `--x`
+    let mut operand = String::new();
+    arith(expr, 14, &mut operand);
+    if operand.starts_with(op) {
+        out.push(' ');
+    }
+    out.push_str(&operand);
+}
+
+/// Formats a script, returning the formatted script along with descriptions
+/// of any errors in commands which were reprinted verbatim.
+fn format(source: &str, heredoc_style: HeredocStyle) -> (String, Vec<String>) {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_error_recovery(ErrorRecovery::Passthrough);
+
+    let mut fmt = Formatter {
+        heredoc_style,
+        ..Formatter::default()
+    };
+    // Parse errors are recovered from, and the default builder never fails
+    while let Some(cmd) = parser.complete_command().expect("errors are recovered") {
+        fmt.top_level(&cmd);
+        fmt.newline();
+    }
+
+    let errors = parser
+        .take_recovered_errors()
+        .into_iter()
+        .map(|e| e.to_string())
+        .collect();
+    (fmt.out, errors)
+}
+
+fn main() {
+    let mut heredoc_style = HeredocStyle::Verbatim;
+    for arg in env::args().skip(1) {
+        match &*arg {
+            "--indent-heredocs" => heredoc_style = HeredocStyle::Indent,
+            _ => {
+                eprintln!("unknown argument: {}", arg);
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+        eprintln!("failed to read stdin: {}", e);
+        process::exit(2);
+    }
+
+    let (formatted, errors) = format(&source, heredoc_style);
+    print!("{}", formatted);
+
+    for err in &errors {
+        eprintln!("left unformatted: {}", err);
+    }
+    if !errors.is_empty() {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_format_with(style: HeredocStyle, source: &str, expected: &str) {
+        let (formatted, errors) = format(source, style);
+        assert_eq!(errors, Vec::<String>::new());
+        assert_eq!(formatted, expected);
+
+        // Formatting should be idempotent
+        assert_eq!(format(&formatted, style).0, expected);
+    }
+
+    fn assert_format(source: &str, expected: &str) {
+        assert_format_with(HeredocStyle::Verbatim, source, expected)
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_format(
+            "if true;then echo  a&&b;elif ! false;then x=1 y|z;else c&fi",
+            "if true; then\n    echo a && b\nelif ! false; then\n    x=1 y | z\nelse\n    c &\nfi\n",
+        );
+    }
+
+    #[test]
+    fn loops_and_heredocs() {
+        assert_format(
+            "while read -r l;do echo \"$l\"; done <<EOF 2>&1\n$x \\$y\nEOF\nfor i in a b;do cat <<'END'\n$i\nEND\ndone",
+            concat!(
+                "while read -r l; do\n    echo \"$l\"\ndone <<EOF 2>&1\n$x \\$y\nEOF\n",
+                "for i in a b; do\n    cat <<'EOF'\n$i\nEOF\ndone\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn indented_heredocs() {
+        assert_format_with(
+            HeredocStyle::Indent,
+            "cat <<A\ntop\nA\nf() {\nif x; then cat <<'B' >out\nliteral $x\n\nB\nfi\n}",
+            concat!(
+                "cat <<'EOF'\ntop\nEOF\n",
+                "f() {\n    if x; then\n        cat <<-'EOF' >out\n\t\tliteral $x\n\n\t\tEOF\n    fi\n}\n",
+            ),
+        );
+
+        // Indenting these bodies would change their contents
+        assert_format_with(
+            HeredocStyle::Indent,
+            "{ cat <<A\n\ttabbed\nA\ncat <<B\njoined $x \\\nline\nB\n}",
+            "{\n    cat <<'EOF'\n\ttabbed\nEOF\n    cat <<EOF\njoined $x \\\nline\nEOF\n}\n",
+        );
+    }
+
+    #[test]
+    fn escaped_newlines_in_literal_heredocs_are_joined() {
+        assert_format(
+            "cat <<A\njoined \\\nline\nA",
+            "cat <<'EOF'\njoined line\nEOF\n",
+        );
+    }
+
+    #[test]
+    fn functions_and_case() {
+        assert_format(
+            "f(){ case $1 in a|b) y;; *) ( z );; esac; }",
+            concat!(
+                "f() {\n    case $1 in\n        a | b)\n            y\n            ;;\n",
+                "        *)\n            (\n                z\n            )\n            ;;\n    esac\n}\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn words_and_substitutions() {
+        assert_format(
+            "echo ${a}b $a.c ${#x} ${y:-$z} \"$(date;pwd)\" 'q' ~/x",
+            "echo ${a}b $a.c ${#x} ${y:-$z} \"$(date; pwd)\" 'q' ~/x\n",
+        );
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_format(
+            "x=$(( (1+2)*3 - -y ** 2 ** z ? a=b : c ))",
+            "x=$(( (1 + 2) * 3 - -y ** 2 ** z ? a = b : c ))\n",
+        );
+        assert_format("x=$(( (a-b)-(c-d) ))", "x=$(( a - b - (c - d) ))\n");
+    }
+
+    #[test]
+    fn unparsable_commands_are_reprinted_verbatim() {
+        let (formatted, errors) = format("echo  a\n)  oops\necho  b", HeredocStyle::Verbatim);
+        assert_eq!(formatted, "echo a\n)  oops\necho b\n");
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,826 @@
+//! An example of implementing a custom `Builder`. Instead of producing the AST
+//! defined in the `ast` module, the parser's results are directly assembled
+//! into a JSON document which can be consumed by tools written in other languages.
+//!
+//! The script is read from stdin, e.g. `echo 'ls | wc -l' | cargo run --example json_ast`,
+//! and the JSON is written to stdout.
+
+use conch_parser::ast::builder::*;
+use conch_parser::ast::{AndOr, ArithmeticTarget, Braces, Conditional, DefaultArithmetic};
+use conch_parser::ast::{DefaultParameter, PipeSegment, RedirectOrCmdWord, RedirectOrEnvVar};
+use conch_parser::ast::{Span, UnparsedSpan};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::Parser;
+use void::Void;
+
+use std::fmt;
+use std::io::{self, Read};
+use std::process;
+
+/// A minimal JSON document model.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(opt: Option<T>) -> Self {
+        opt.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(v: Vec<T>) -> Self {
+        Json::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Json {
+    /// Writes the document, indenting nested values if `indent` is given.
+    fn write(&self, fmt: &mut fmt::Formatter<'_>, indent: Option<usize>) -> fmt::Result {
+        let (items, open, close) = match *self {
+            Json::Null => return fmt.write_str("null"),
+            Json::Bool(b) => return write!(fmt, "{}", b),
+            Json::Number(n) => return write!(fmt, "{}", n),
+            Json::String(ref s) => return write_str(fmt, s),
+            Json::Array(ref items) => (
+                items.iter().map(|v| (None, v)).collect::<Vec<_>>(),
+                '[',
+                ']',
+            ),
+            Json::Object(ref fields) => (
+                fields.iter().map(|(k, v)| (Some(*k), v)).collect(),
+                '{',
+                '}',
+            ),
+        };
+
+        write!(fmt, "{}", open)?;
+        for (i, (key, value)) in items.iter().enumerate() {
+            if i > 0 {
+                fmt.write_str(",")?;
+            }
+            if let Some(indent) = indent {
+                write!(fmt, "\n{:width$}", "", width = (indent + 1) * 2)?;
+            }
+            if let Some(key) = key {
+                write_str(fmt, key)?;
+                fmt.write_str(if indent.is_some() { ": " } else { ":" })?;
+            }
+            value.write(fmt, indent.map(|i| i + 1))?;
+        }
+        if let (Some(indent), false) = (indent, items.is_empty()) {
+            write!(fmt, "\n{:width$}", "", width = indent * 2)?;
+        }
+        write!(fmt, "{}", close)
+    }
+}
+
+/// Writes a quoted and escaped JSON string.
+fn write_str(fmt: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    fmt.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => fmt.write_str("\\\"")?,
+            '\\' => fmt.write_str("\\\\")?,
+            '\n' => fmt.write_str("\\n")?,
+            '\t' => fmt.write_str("\\t")?,
+            c if c.is_control() => write!(fmt, "\\u{:04x}", c as u32)?,
+            c => write!(fmt, "{}", c)?,
+        }
+    }
+    fmt.write_str("\"")
+}
+
+/// Displays the document compactly, or indented when using `{:#}`.
+impl fmt::Display for Json {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = if fmt.alternate() { Some(0) } else { None };
+        self.write(fmt, indent)
+    }
+}
+
+/// Creates an object describing a node of the given type.
+fn node(kind: &str, mut fields: Vec<(&'static str, Json)>) -> Json {
+    fields.insert(0, ("type", kind.into()));
+    Json::Object(fields)
+}
+
+/// Converts comments into a list of their text, skipping any blank lines.
+fn comments(lines: Vec<Newline>) -> Json {
+    lines
+        .into_iter()
+        .filter_map(|Newline(c)| c)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// A `Builder` which assembles every part of the source into a `Json` value.
+#[derive(Debug, Default)]
+struct JsonBuilder;
+
+impl Builder for JsonBuilder {
+    type Command = Json;
+    type CommandList = Json;
+    type ListableCommand = Json;
+    type PipeableCommand = Json;
+    type CompoundCommand = Json;
+    type Word = Json;
+    type Redirect = Json;
+    type Error = Void;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Json,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let separator = match separator {
+            SeparatorKind::Semi => ";",
+            SeparatorKind::Amp => "&",
+            SeparatorKind::Newline => "\\n",
+            SeparatorKind::Other => "",
+        };
+
+        Ok(node(
+            "command",
+            vec![
+                ("comments", comments(pre_cmd_comments)),
+                ("list", list),
+                ("separator", separator.into()),
+                ("comment", cmd_comment.and_then(|Newline(c)| c).into()),
+            ],
+        ))
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Json,
+        rest: Vec<(Vec<Newline>, AndOr<Json>)>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        if rest.is_empty() {
+            return Ok(first);
+        }
+
+        let mut cmds = vec![first];
+        let mut ops = Vec::new();
+        for (_, and_or) in rest {
+            let (op, cmd) = match and_or {
+                AndOr::And(cmd) => ("&&", cmd),
+                AndOr::Or(cmd) => ("||", cmd),
+            };
+            ops.push(op);
+            cmds.push(cmd);
+        }
+
+        Ok(node(
+            "and_or",
+            vec![("commands", cmds.into()), ("operators", ops.into())],
+        ))
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Json>)>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let mut cmds = cmds.into_iter().map(|(_, seg)| seg.cmd).collect::<Vec<_>>();
+        if cmds.len() == 1 && !bang {
+            return Ok(cmds.pop().unwrap());
+        }
+
+        Ok(node(
+            "pipeline",
+            vec![("bang", bang.into()), ("commands", cmds.into())],
+        ))
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Json, String, Json>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Json, Json>>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let mut assignments = Vec::new();
+        let mut words = Vec::new();
+        let mut redirects = Vec::new();
+
+        for item in redirects_or_env_vars {
+            match item {
+                RedirectOrEnvVar::Redirect(r) => redirects.push(r),
+                RedirectOrEnvVar::EnvVar(name, value) => assignments.push(Json::Object(vec![
+                    ("name", name.into()),
+                    ("value", value.into()),
+                ])),
+            }
+        }
+
+        for item in redirects_or_cmd_words {
+            match item {
+                RedirectOrCmdWord::Redirect(r) => redirects.push(r),
+                RedirectOrCmdWord::CmdWord(w) => words.push(w),
+            }
+        }
+
+        Ok(node(
+            "simple",
+            vec![
+                ("assignments", assignments.into()),
+                ("words", words.into()),
+                ("redirects", redirects.into()),
+            ],
+        ))
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        Ok(compound(
+            "brace",
+            vec![("body", cmds.commands.into())],
+            redirects,
+        ))
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        Ok(compound(
+            "subshell",
+            vec![("body", cmds.commands.into())],
+            redirects,
+        ))
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let kind = match kind {
+            LoopKind::While => "while",
+            LoopKind::Until => "until",
+            _ => "loop",
+        };
+
+        let fields = vec![
+            ("guard", guard_body_pair.guard.commands.into()),
+            ("body", guard_body_pair.body.commands.into()),
+        ];
+        Ok(compound(kind, fields, redirects))
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let conditionals = fragments
+            .conditionals
+            .into_iter()
+            .map(|pair| {
+                Json::Object(vec![
+                    ("guard", pair.guard.commands.into()),
+                    ("body", pair.body.commands.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let fields = vec![
+            ("conditionals", conditionals.into()),
+            ("else", fragments.else_branch.map(|e| e.commands).into()),
+        ];
+        Ok(compound("if", fields, redirects))
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Json, Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let fields = vec![
+            ("var", fragments.var.into()),
+            ("words", fragments.words.map(|(_, words, _)| words).into()),
+            ("body", fragments.body.commands.into()),
+        ];
+        Ok(compound("for", fields, redirects))
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Json, Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let arms = fragments
+            .arms
+            .into_iter()
+            .map(|arm| {
+                Json::Object(vec![
+                    ("patterns", arm.patterns.pattern_alternatives.into()),
+                    ("body", arm.body.commands.into()),
+                    ("terminator", arm.terminator.as_str().into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let fields = vec![("word", fragments.word), ("arms", arms.into())];
+        Ok(compound("case", fields, redirects))
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Json>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let fields = vec![("expr", conditional(expr))];
+        Ok(compound("conditional", fields, redirects))
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Json>,
+        _redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let fields = vec![("expr", expr.as_ref().map_or(Json::Null, arith))];
+        Ok(compound("arith_command", fields, redirects))
+    }
+
+    fn compound_command_into_pipeable(&mut self, cmd: Json) -> Result<Json, Void> {
+        Ok(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        _post_name_comments: Vec<Newline>,
+        body: Json,
+        _span: Span,
+    ) -> Result<Json, Void> {
+        let syntax = match syntax {
+            FunctionSyntax::Posix => "posix",
+            FunctionSyntax::Keyword => "keyword",
+            FunctionSyntax::KeywordParens => "keyword_parens",
+            _ => "unknown",
+        };
+
+        Ok(node(
+            "function",
+            vec![
+                ("name", name.into()),
+                ("syntax", syntax.into()),
+                ("body", body),
+            ],
+        ))
+    }
+
+    fn comments(&mut self, _comments: Vec<Newline>) -> Result<(), Void> {
+        Ok(())
+    }
+
+    fn unparsed(
+        &mut self,
+        _pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Json, Void> {
+        Ok(node("unparsed", vec![("text", span.text.into())]))
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        _span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Void> {
+        Ok(expr)
+    }
+
+    fn word(&mut self, kind: ComplexWordKind<Json>, _span: Span) -> Result<Json, Void> {
+        Ok(complex_word(kind))
+    }
+
+    fn redirect(&mut self, kind: RedirectKind<Json>, _span: Span) -> Result<Json, Void> {
+        let (op, fd, target) = match kind {
+            RedirectKind::Read(fd, w) => ("<", fd, w),
+            RedirectKind::Write(fd, w) => (">", fd, w),
+            RedirectKind::ReadWrite(fd, w) => ("<>", fd, w),
+            RedirectKind::Append(fd, w) => (">>", fd, w),
+            RedirectKind::Clobber(fd, w) => (">|", fd, w),
+            RedirectKind::Heredoc(fd, w, info) => {
+                let op = if info.strip_tabs { "<<-" } else { "<<" };
+                (op, fd, w)
+            }
+            RedirectKind::DupRead(fd, w) => ("<&", fd, w),
+            RedirectKind::DupWrite(fd, w) => (">&", fd, w),
+            RedirectKind::WriteBoth(w) => ("&>", None, w),
+            RedirectKind::AppendBoth(w) => ("&>>", None, w),
+            _ => return Ok(node("unknown", vec![])),
+        };
+
+        Ok(node(
+            "redirect",
+            vec![
+                ("op", op.into()),
+                ("fd", fd.map_or(Json::Null, |fd| Json::Number(fd.into()))),
+                ("target", target),
+            ],
+        ))
+    }
+}
+
+/// Creates a node for a compound command, along with its redirects.
+fn compound(kind: &str, mut fields: Vec<(&'static str, Json)>, redirects: Vec<Json>) -> Json {
+    fields.push(("redirects", redirects.into()));
+    node(kind, fields)
+}
+
+/// Converts a `[[ ... ]]` expression.
+fn conditional(expr: Conditional<Json>) -> Json {
+    let binary = |op: &str, l: Box<Conditional<Json>>, r: Box<Conditional<Json>>| {
+        let fields = vec![
+            ("op", op.into()),
+            ("lhs", conditional(*l)),
+            ("rhs", conditional(*r)),
+        ];
+        node("logical", fields)
+    };
+
+    match expr {
+        Conditional::Word(w) => node("nonempty", vec![("word", w)]),
+        Conditional::Unary(op, w) => node(
+            "unary",
+            vec![("op", format!("-{}", op).into()), ("word", w)],
+        ),
+        Conditional::Binary(l, op, r) => node(
+            "binary",
+            vec![("op", op.as_str().into()), ("lhs", l), ("rhs", r)],
+        ),
+        Conditional::Not(e) => node("not", vec![("expr", conditional(*e))]),
+        Conditional::And(l, r) => binary("&&", l, r),
+        Conditional::Or(l, r) => binary("||", l, r),
+        _ => node("unknown", vec![]),
+    }
+}
+
+/// Converts a word, joining any adjacent literals (the parser may yield
+/// a single literal in several pieces, e.g. `-l` as `-` and `l`).
+fn complex_word(kind: ComplexWordKind<Json>) -> Json {
+    let words = match kind {
+        ComplexWordKind::Single(w) => return word(w),
+        ComplexWordKind::Concat(words) => words,
+    };
+
+    let mut parts = Vec::<WordKind<Json>>::new();
+    for w in words {
+        if let WordKind::Simple(SimpleWordKind::Literal(ref s)) = w {
+            if let Some(WordKind::Simple(SimpleWordKind::Literal(prev))) = parts.last_mut() {
+                prev.push_str(s);
+                continue;
+            }
+        }
+        parts.push(w);
+    }
+
+    if parts.len() == 1 {
+        return word(parts.pop().unwrap());
+    }
+
+    let parts = parts.into_iter().map(word).collect::<Vec<_>>();
+    node("concat", vec![("parts", parts.into())])
+}
+
+fn word(kind: WordKind<Json>) -> Json {
+    match kind {
+        WordKind::Simple(w) => simple_word(w),
+        WordKind::SingleQuoted(s) => node("single_quoted", vec![("value", s.into())]),
+        WordKind::DoubleQuoted(words) => node(
+            "double_quoted",
+            vec![(
+                "parts",
+                words
+                    .into_iter()
+                    .map(simple_word)
+                    .collect::<Vec<_>>()
+                    .into(),
+            )],
+        ),
+        WordKind::Braces(braces) => self::braces(braces),
+    }
+}
+
+fn braces(braces: Braces<WordKind<Json>>) -> Json {
+    let step = |step: Option<i64>| step.map_or(Json::Null, Json::Number);
+
+    match braces {
+        Braces::Alternatives(alternatives) => {
+            let alternatives = alternatives
+                .into_iter()
+                .map(|words| Json::Array(words.into_iter().map(word).collect()))
+                .collect::<Vec<_>>();
+            node("braces", vec![("alternatives", alternatives.into())])
+        }
+        Braces::Numbers {
+            start,
+            end,
+            step: s,
+            width,
+        } => node(
+            "brace_numbers",
+            vec![
+                ("start", Json::Number(start)),
+                ("end", Json::Number(end)),
+                ("step", step(s)),
+                ("width", Json::Number(width as i64)),
+            ],
+        ),
+        Braces::Chars {
+            start,
+            end,
+            step: s,
+        } => node(
+            "brace_chars",
+            vec![
+                ("start", start.to_string().into()),
+                ("end", end.to_string().into()),
+                ("step", step(s)),
+            ],
+        ),
+        _ => node("unknown", vec![]),
+    }
+}
+
+fn simple_word(kind: SimpleWordKind<Json>) -> Json {
+    let glob = |value: &str| node("glob", vec![("value", value.into())]);
+
+    match kind {
+        SimpleWordKind::Literal(s) => node("literal", vec![("value", s.into())]),
+        SimpleWordKind::Escaped(s) => node("escaped", vec![("value", s.into())]),
+        SimpleWordKind::Param(p) => param(&p),
+        SimpleWordKind::Subst(s) => subst(*s),
+        SimpleWordKind::CommandSubst(cmds) => {
+            node("command_subst", vec![("body", cmds.commands.into())])
+        }
+        SimpleWordKind::Star => glob("*"),
+        SimpleWordKind::Question => glob("?"),
+        SimpleWordKind::SquareOpen => glob("["),
+        SimpleWordKind::SquareClose => glob("]"),
+        SimpleWordKind::Tilde => node("tilde", vec![]),
+        SimpleWordKind::TildePrefix(login) => node("tilde_prefix", vec![("login", login.into())]),
+        SimpleWordKind::Colon => node("literal", vec![("value", ":".into())]),
+        _ => node("unknown", vec![]),
+    }
+}
+
+fn param(p: &DefaultParameter) -> Json {
+    node("param", vec![("name", p.to_string().into())])
+}
+
+fn subst(kind: ParameterSubstitutionKind<ComplexWordKind<Json>, Json>) -> Json {
+    use conch_parser::ast::builder::ParameterSubstitutionKind::*;
+
+    let (op, colon, p, w) = match kind {
+        Command(cmds) => return node("command_subst", vec![("body", cmds.commands.into())]),
+        ReadFile(w) => return node("read_file", vec![("file", complex_word(w))]),
+        ProcessRead(cmds) => return node("process_read", vec![("body", cmds.commands.into())]),
+        ProcessWrite(cmds) => return node("process_write", vec![("body", cmds.commands.into())]),
+        Len(p) => return node("length", vec![("param", param(&p))]),
+        Arith(a) => {
+            let expr = a.as_ref().map_or(Json::Null, arith);
+            return node("arith", vec![("expr", expr)]);
+        }
+        Default(colon, p, w) => ("-", Some(colon), p, w),
+        Assign(colon, p, w) => ("=", Some(colon), p, w),
+        Error(colon, p, w) => ("?", Some(colon), p, w),
+        Alternative(colon, p, w) => ("+", Some(colon), p, w),
+        RemoveSmallestSuffix(p, w) => ("%", None, p, w),
+        RemoveLargestSuffix(p, w) => ("%%", None, p, w),
+        RemoveSmallestPrefix(p, w) => ("#", None, p, w),
+        RemoveLargestPrefix(p, w) => ("##", None, p, w),
+        ReplaceString(p, w) => ("/", None, p, w),
+        ReplaceStringAll(p, w) => ("//", None, p, w),
+        Substring(p, w) => (":", None, p, w),
+        Lowercase(all, p, w) => (if all { ",," } else { "," }, None, p, w),
+        Uppercase(all, p, w) => (if all { "^^" } else { "^" }, None, p, w),
+        _ => return node("unknown", vec![]),
+    };
+
+    node(
+        "subst",
+        vec![
+            ("op", op.into()),
+            ("colon", colon.into()),
+            ("param", param(&p)),
+            ("word", w.map(complex_word).into()),
+        ],
+    )
+}
+
+fn arith(expr: &DefaultArithmetic) -> Json {
+    use conch_parser::ast::Arithmetic::*;
+
+    let unary =
+        |op: &str, operand: Json| node("unary", vec![("op", op.into()), ("operand", operand)]);
+    let var = |name: &String| node("var", vec![("name", name.as_str().into())]);
+    let target = |target: &ArithmeticTarget<String>| match *target {
+        ArithmeticTarget::Var(ref name) => var(name),
+        ArithmeticTarget::Index(ref name, ref index) => node(
+            "index",
+            vec![("name", name.as_str().into()), ("index", arith(index))],
+        ),
+    };
+
+    let (op, lhs, rhs) = match *expr {
+        Var(ref name) => return var(name),
+        Index(ref name, ref index) => {
+            return node(
+                "index",
+                vec![("name", name.as_str().into()), ("index", arith(index))],
+            )
+        }
+        Literal(n) | RadixLiteral(n, ..) => return Json::Number(n as i64),
+        PostIncr(ref t) => return unary("x++", target(t)),
+        PostDecr(ref t) => return unary("x--", target(t)),
+        PreIncr(ref t) => return unary("++x", target(t)),
+        PreDecr(ref t) => return unary("--x", target(t)),
+        UnaryPlus(ref e) => return unary("+", arith(e)),
+        UnaryMinus(ref e) => return unary("-", arith(e)),
+        LogicalNot(ref e) => return unary("!", arith(e)),
+        BitwiseNot(ref e) => return unary("~", arith(e)),
+        Ternary(ref cond, ref then, ref els) => {
+            return node(
+                "ternary",
+                vec![
+                    ("cond", arith(cond)),
+                    ("then", arith(then)),
+                    ("else", arith(els)),
+                ],
+            )
+        }
+        Assign(ref t, ref value) => {
+            return node(
+                "assign",
+                vec![("target", target(t)), ("value", arith(value))],
+            )
+        }
+        AssignOp(op, ref t, ref value) => {
+            return node(
+                "assign",
+                vec![
+                    ("op", op.as_str().into()),
+                    ("target", target(t)),
+                    ("value", arith(value)),
+                ],
+            )
+        }
+        Sequence(ref exprs) => {
+            return node(
+                "sequence",
+                vec![("exprs", exprs.iter().map(arith).collect::<Vec<_>>().into())],
+            )
+        }
+        Pow(ref l, ref r) => ("**", l, r),
+        Mult(ref l, ref r) => ("*", l, r),
+        Div(ref l, ref r) => ("/", l, r),
+        Modulo(ref l, ref r) => ("%", l, r),
+        Add(ref l, ref r) => ("+", l, r),
+        Sub(ref l, ref r) => ("-", l, r),
+        ShiftLeft(ref l, ref r) => ("<<", l, r),
+        ShiftRight(ref l, ref r) => (">>", l, r),
+        Less(ref l, ref r) => ("<", l, r),
+        LessEq(ref l, ref r) => ("<=", l, r),
+        Great(ref l, ref r) => (">", l, r),
+        GreatEq(ref l, ref r) => (">=", l, r),
+        Eq(ref l, ref r) => ("==", l, r),
+        NotEq(ref l, ref r) => ("!=", l, r),
+        BitwiseAnd(ref l, ref r) => ("&", l, r),
+        BitwiseXor(ref l, ref r) => ("^", l, r),
+        BitwiseOr(ref l, ref r) => ("|", l, r),
+        LogicalAnd(ref l, ref r) => ("&&", l, r),
+        LogicalOr(ref l, ref r) => ("||", l, r),
+        _ => return node("unknown", vec![]),
+    };
+
+    node(
+        "binary",
+        vec![("op", op.into()), ("lhs", arith(lhs)), ("rhs", arith(rhs))],
+    )
+}
+
+/// Parses a script into a JSON array of its commands.
+fn to_json(source: &str) -> Result<Json, String> {
+    let lex = Lexer::new(source.chars());
+    let cmds = Parser::with_builder(lex, JsonBuilder)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(Json::Array(cmds))
+}
+
+fn main() {
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+        eprintln!("failed to read stdin: {}", e);
+        process::exit(1);
+    }
+
+    match to_json(&source) {
+        Ok(json) => println!("{:#}", json),
+        Err(e) => {
+            eprintln!("parse error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_with_substitutions() {
+        let json = to_json("! echo \"a $b\" | wc -l >out").unwrap();
+        assert_eq!(
+            json.to_string(),
+            concat!(
+                r#"[{"type":"command","comments":[],"list":{"type":"pipeline","bang":true,"commands":["#,
+                r#"{"type":"simple","assignments":[],"words":[{"type":"literal","value":"echo"},"#,
+                r#"{"type":"double_quoted","parts":[{"type":"literal","value":"a "},"#,
+                r#"{"type":"param","name":"${b}"}]}],"redirects":[]},"#,
+                r#"{"type":"simple","assignments":[],"words":[{"type":"literal","value":"wc"},"#,
+                r#"{"type":"literal","value":"-l"}],"redirects":[{"type":"redirect","op":">","fd":null,"#,
+                r#""target":{"type":"literal","value":"out"}}]}]},"separator":"","comment":null}]"#,
+            )
+        );
+    }
+
+    #[test]
+    fn arithmetic_and_compound_commands() {
+        let json = to_json("if true; then x=$((1 + y * 2)); fi; ((y++))").unwrap();
+        let text = json.to_string();
+        assert!(text.contains(r#"{"type":"if","conditionals":[{"guard":"#));
+        assert!(text.contains(r#"{"type":"arith_command","expr":{"type":"unary","op":"x++","#));
+        assert!(text.contains(concat!(
+            r#"{"type":"binary","op":"+","lhs":1,"rhs":{"type":"binary","op":"*","#,
+            r#""lhs":{"type":"var","name":"y"},"rhs":2}}"#
+        )));
+    }
+
+    #[test]
+    fn pretty_printing_escapes_strings() {
+        let json = node(
+            "literal",
+            vec![("value", "a\"b\n".into()), ("empty", Json::Array(vec![]))],
+        );
+        assert_eq!(
+            format!("{:#}", json),
+            "{\n  \"type\": \"literal\",\n  \"value\": \"a\\\"b\\n\",\n  \"empty\": []\n}"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! An example of a small linter which walks the default AST to enforce
+//! two rules, reporting its findings as `analysis::Diagnostic`s:
+//!
+//! * `unquoted-expansion`: parameters or command substitutions used as unquoted
+//!   command arguments, whose values will be split into words and globbed.
+//! * `useless-cat`: pipelines starting with `cat file`, which can instead
+//!   redirect the file into the next command.
+//!
+//! Each argument is treated as the path of a script to lint, or the script is
+//! read from stdin if no paths are given, e.g. `cargo run --example lint -- foo.sh`.
+//! The program exits with a failure if any warnings were reported.
+
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::ast::{
+    AndOr, Command, ComplexWord, DefaultCompoundCommand, DefaultListableCommand,
+    DefaultPipeableCommand, DefaultSimpleCommand, ListableCommand, Parameter,
+    ParameterSubstitution, PipeableCommand, RedirectOrCmdWord, SimpleWord, TopLevelCommand,
+    TopLevelWord, Word,
+};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::DefaultParser;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const UNQUOTED_EXPANSION: &str = "unquoted-expansion";
+const USELESS_CAT: &str = "useless-cat";
+
+/// Collects diagnostics while walking every command of a script.
+#[derive(Debug, Default)]
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn top_level(&mut self, cmd: &TopLevelCommand<String>) {
+        match cmd.0 {
+            Command::Job(ref list) | Command::List(ref list) => {
+                self.listable(&list.first);
+                for and_or in &list.rest {
+                    match *and_or {
+                        AndOr::And(ref cmd) | AndOr::Or(ref cmd) => self.listable(cmd),
+                    }
+                }
+            }
+            // Regions which failed to parse (when recovering from errors) have no AST
+            Command::Unparsed(_) => {}
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand) {
+        let cmds = match *cmd {
+            ListableCommand::Single(ref cmd) => vec![cmd],
+            ListableCommand::Pipe(ref pipeline) => pipeline.commands().collect(),
+        };
+
+        self.check_useless_cat(&cmds);
+        for cmd in cmds {
+            self.pipeable(cmd);
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.check_unquoted_expansions(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(_, _, ref body) => self.compound(body),
+            _ => {}
+        }
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand) {
+        for body in cmd.kind.bodies() {
+            for cmd in body {
+                self.top_level(cmd);
+            }
+        }
+    }
+
+    /// Reports expansions which are subject to field splitting and globbing.
+    fn check_unquoted_expansions(&mut self, cmd: &DefaultSimpleCommand) {
+        for item in &cmd.redirects_or_cmd_words {
+            let word = match *item {
+                RedirectOrCmdWord::CmdWord(ref word) => word,
+                RedirectOrCmdWord::Redirect(_) => continue,
+            };
+
+            for part in word_parts(word) {
+                let expansion = match *part {
+                    // Only unquoted words are split, double quoted ones are kept intact
+                    Word::Simple(SimpleWord::Param(ref p)) if may_split(p) => p.to_string(),
+                    Word::Simple(SimpleWord::Subst(ref subst)) => match **subst {
+                        ParameterSubstitution::Command(_) => String::from("$(...)"),
+                        // Lengths are always plain numbers
+                        ParameterSubstitution::Len(_) => continue,
+                        ref subst => match subst.parameter() {
+                            Some(p) if may_split(p) => p.to_string(),
+                            _ => continue,
+                        },
+                    },
+                    _ => continue,
+                };
+
+                let msg = format!(
+                    "`{}` is not quoted, so its value will be split into words and \
+                     expanded as a glob; quote it as \"{}\" instead",
+                    expansion, expansion
+                );
+                self.diagnostics
+                    .push(Diagnostic::new(Severity::Warning, UNQUOTED_EXPANSION, msg));
+            }
+        }
+    }
+
+    /// Reports pipelines which start with `cat` reading a single file.
+    fn check_useless_cat(&mut self, cmds: &[&DefaultPipeableCommand]) {
+        let first = match cmds {
+            [PipeableCommand::Simple(first), _, ..] => first,
+            _ => return,
+        };
+
+        if !first.redirects_or_env_vars.is_empty() {
+            return;
+        }
+
+        let words = first
+            .redirects_or_cmd_words
+            .iter()
+            .map(|item| match *item {
+                RedirectOrCmdWord::CmdWord(ref word) => literal(word),
+                RedirectOrCmdWord::Redirect(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        if let [Some("cat"), Some(file)] = words[..] {
+            if !file.starts_with('-') {
+                let msg = format!(
+                    "`cat {}` is only used to read a file; redirect it into the \
+                     next command (e.g. `< {}`) instead",
+                    file, file
+                );
+                self.diagnostics
+                    .push(Diagnostic::new(Severity::Info, USELESS_CAT, msg));
+            }
+        }
+    }
+}
+
+/// Returns the parts a word is made up of.
+fn word_parts(word: &TopLevelWord<String>) -> &[conch_parser::ast::DefaultWord] {
+    match word.0 {
+        ComplexWord::Single(ref w) => std::slice::from_ref(w),
+        ComplexWord::Concat(ref words) => words,
+    }
+}
+
+/// Returns the text of a word made up of a single literal.
+fn literal(word: &TopLevelWord<String>) -> Option<&str> {
+    match *word_parts(word) {
+        [Word::Simple(SimpleWord::Literal(ref s))] => Some(s),
+        _ => None,
+    }
+}
+
+/// Checks if the value of a parameter could possibly contain whitespace or globs.
+fn may_split(param: &Parameter<String>) -> bool {
+    !matches!(
+        *param,
+        Parameter::Pound | Parameter::Question | Parameter::Dollar | Parameter::Bang
+    )
+}
+
+/// Parses and lints a script.
+fn lint(source: &str) -> Result<Vec<Diagnostic>, String> {
+    let mut linter = Linter::default();
+    for cmd in DefaultParser::new(Lexer::new(source.chars())) {
+        linter.top_level(&cmd.map_err(|e| e.to_string())?);
+    }
+    Ok(linter.diagnostics)
+}
+
+fn main() {
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+    let sources = if paths.is_empty() {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map(|_| vec![(String::from("<stdin>"), source)])
+    } else {
+        paths
+            .into_iter()
+            .map(|path| fs::read_to_string(&path).map(|source| (path, source)))
+            .collect()
+    };
+
+    let sources = sources.unwrap_or_else(|e| {
+        eprintln!("failed to read script: {}", e);
+        process::exit(2);
+    });
+
+    let mut failed = false;
+    for (path, source) in sources {
+        match lint(&source) {
+            Ok(diagnostics) => {
+                for diagnostic in diagnostics {
+                    failed |= diagnostic.severity >= Severity::Warning;
+                    println!("{}: {}", path, diagnostic);
+                }
+            }
+            Err(e) => {
+                failed = true;
+                println!("{}: failed to parse: {}", path, e);
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        lint(source).unwrap().into_iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn unquoted_expansions() {
+        assert_eq!(
+            codes("echo $foo \"$bar\" $(date) ${#x} $? ${y:-z} '$w'"),
+            vec![UNQUOTED_EXPANSION, UNQUOTED_EXPANSION, UNQUOTED_EXPANSION]
+        );
+        assert_eq!(codes("foo=$bar; echo \"$@\" > $out"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn useless_cat() {
+        assert_eq!(codes("cat file | grep x"), vec![USELESS_CAT]);
+        assert_eq!(codes("cat a b | grep x"), Vec::<&str>::new());
+        assert_eq!(codes("cat -n file | grep x"), Vec::<&str>::new());
+        assert_eq!(codes("cat file"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn nested_commands_are_linted() {
+        let source = "f() { if true; then cat file | wc -l; fi; }\nwhile true; do rm $x; done";
+        assert_eq!(codes(source), vec![USELESS_CAT, UNQUOTED_EXPANSION]);
+    }
+
+    #[test]
+    fn parse_errors_are_reported() {
+        assert!(lint("echo )").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! An example of a multi-threaded service which parses shell scripts on behalf
+//! of its clients. A single configuration (the parser options and a factory
+//! for AST builders) is shared by all workers, and each request is handled by
+//! a fresh parser and builder pair.
+//!
+//! Each argument given to the program is treated as a separate request, e.g.
+//! `cargo run --example parse_service -- 'echo foo' 'if true; then' 'a | b'`
+
+use conch_parser::ast::builder::{ArcBuilder, BuilderFactory};
+use conch_parser::ast::AtomicTopLevelCommand;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{LineIndex, ParserOptions, PositionTracking};
+
+use std::env;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const NUM_WORKERS: usize = 4;
+
+/// The configuration shared by all workers.
+struct Service<F> {
+    options: ParserOptions,
+    factory: F,
+}
+
+impl<F> Service<F>
+where
+    F: BuilderFactory<Builder = ArcBuilder>,
+{
+    /// Parses a script, rendering any error with its line and column.
+    fn handle(&self, script: &str) -> Result<Vec<AtomicTopLevelCommand<Arc<String>>>, String> {
+        let lex = Lexer::new(script.chars());
+        let parser = self.options.parser(lex, &self.factory);
+
+        parser.into_iter().collect::<Result<_, _>>().map_err(|err| {
+            // Positions are only resolved for the (rare) requests which fail
+            let index = LineIndex::new(script);
+            err.map_pos(|pos| index.resolve(pos)).to_string()
+        })
+    }
+}
+
+fn main() {
+    let service = Arc::new(Service {
+        options: ParserOptions {
+            position_tracking: PositionTracking::ByteOffsets,
+            ..ParserOptions::default()
+        },
+        factory: ArcBuilder::default,
+    });
+
+    let (request_tx, request_rx) = mpsc::channel::<(usize, String)>();
+    let (response_tx, response_rx) = mpsc::channel();
+    let request_rx = Arc::new(Mutex::new(request_rx));
+
+    let workers = (0..NUM_WORKERS)
+        .map(|_| {
+            let service = service.clone();
+            let request_rx = request_rx.clone();
+            let response_tx = response_tx.clone();
+
+            thread::spawn(move || loop {
+                let request = request_rx.lock().unwrap().recv();
+                let (id, script) = match request {
+                    Ok(request) => request,
+                    Err(_) => break, // All requests have been handled
+                };
+
+                let response = service.handle(&script);
+                if response_tx.send((id, script, response)).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    drop(response_tx);
+    for request in env::args().skip(1).enumerate() {
+        request_tx.send(request).unwrap();
+    }
+    drop(request_tx);
+
+    // Responses arrive in whatever order the workers finish them
+    let mut responses = response_rx.iter().collect::<Vec<_>>();
+    responses.sort_by_key(|&(id, _, _)| id);
+
+    for (id, script, response) in responses {
+        match response {
+            Ok(cmds) => println!("#{} {:?}: {} command(s)", id, script, cmds.len()),
+            Err(err) => println!("#{} {:?}: error: {}", id, script, err),
+        }
+    }
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
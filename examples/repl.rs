@@ -0,0 +1,144 @@
+//! An example of an interactive prompt which parses commands as they are typed.
+//!
+//! Much like an interactive shell, input which ends in the middle of a command
+//! (e.g. an open quote, a trailing `&&` or `\`, an `if` without its `fi`, or a
+//! heredoc without its delimiter) prompts for a continuation line instead of
+//! being reported as an error.
+//!
+//! Run it with `cargo run --example repl` and exit with Ctrl-D.
+
+use conch_parser::ast::TopLevelCommand;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, ParseError};
+
+use std::io::{self, BufRead, Write};
+
+/// The result of attempting to parse the buffered input.
+#[derive(Debug)]
+enum Input {
+    /// The input was made up of complete commands.
+    Complete(Vec<TopLevelCommand<String>>),
+    /// The input ends in the middle of a command, and needs another line.
+    Incomplete,
+    /// The input is not valid, regardless of what may follow.
+    Invalid(String),
+}
+
+/// Parses the buffered input, checking whether more lines are required.
+fn parse(input: &str) -> Input {
+    // A trailing (unescaped) backslash joins the next line onto this one
+    let line = input.trim_end_matches('\n');
+    let backslashes = line.len() - line.trim_end_matches('\\').len();
+    if backslashes % 2 == 1 {
+        return Input::Incomplete;
+    }
+
+    let mut parser = DefaultParser::new(Lexer::new(input.chars()));
+    match parser.commands().collect() {
+        // The parser treats the end of the input as the end of any pending heredoc
+        Ok(_) if parser.heredoc_reached_eof() => Input::Incomplete,
+        Ok(cmds) => Input::Complete(cmds),
+        Err(ref err) if is_incomplete(err, input.len()) => Input::Incomplete,
+        Err(err) => Input::Invalid(err.to_string()),
+    }
+}
+
+/// Checks if an error was caused by the input ending prematurely.
+fn is_incomplete<E>(err: &ParseError<E>, input_len: usize) -> bool {
+    match *err {
+        // Unmatched delimiters are only reported once all input is consumed
+        ParseError::UnexpectedEOF | ParseError::Unmatched(..) => true,
+        ParseError::IncompleteCmd(_, _, _, kw_pos) => kw_pos.byte >= input_len,
+        _ => false,
+    }
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut input = String::new();
+
+    loop {
+        print!("{}", if input.is_empty() { "$ " } else { "> " });
+        io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+
+        input.push_str(&line);
+        input.push('\n');
+
+        match parse(&input) {
+            Input::Incomplete => continue,
+            Input::Complete(cmds) => {
+                for cmd in cmds {
+                    println!("{:?}", cmd);
+                }
+            }
+            Input::Invalid(err) => println!("error: {}", err),
+        }
+
+        input.clear();
+    }
+
+    println!();
+    if !input.is_empty() {
+        println!("error: unexpected end of input");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_complete(input: &str) -> bool {
+        match parse(input) {
+            Input::Complete(_) => true,
+            Input::Incomplete => false,
+            Input::Invalid(err) => panic!("unexpected error for {:?}: {}", input, err),
+        }
+    }
+
+    #[test]
+    fn complete_input() {
+        assert!(is_complete("echo foo\n"));
+        assert!(is_complete("echo foo \\\\\n"));
+        assert!(is_complete("if true; then\n  foo\nfi\n"));
+        assert!(is_complete("echo 'a\nb'\n"));
+        assert!(is_complete("cat <<EOF\nfoo\nEOF\n"));
+    }
+
+    #[test]
+    fn incomplete_input() {
+        let cases = [
+            "echo foo \\\n",
+            "echo 'foo\n",
+            "echo \"$(foo\n",
+            "foo &&\n",
+            "foo |\n",
+            "if true; then\n",
+            "if true; then foo; else\n",
+            "while true\n",
+            "for x in a b\n",
+            "case x in\n",
+            "f() {\n",
+            "( foo\n",
+            "cat <<EOF\nfoo\n",
+        ];
+
+        for &input in &cases {
+            assert!(!is_complete(input), "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn invalid_input() {
+        match parse("echo )\n") {
+            Input::Invalid(_) => {}
+            input => panic!("unexpected result: {:?}", input),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Static analysis passes over the default AST.
+//!
+//! Each analysis inspects parsed commands (as produced by the `DefaultParser`)
+//! and reports any suspicious constructs it finds as a `Diagnostic`. None of
+//! the analyses evaluate or execute any part of the program.
+//!
+//! All analyses are deterministic: the same input always produces the same
+//! results in the same order (generally the source order of the constructs
+//! involved) across runs and platforms, so their output can safely be diffed.
+//! Results never depend on the iteration order of a hashed container; any
+//! aggregated output is either kept in insertion order or explicitly sorted.
+
+use crate::ast::{
+    AndOr, Command, ComplexWord, CompoundCommandKind, DefaultCompoundCommand,
+    DefaultListableCommand, DefaultParameter, DefaultPipeableCommand, DefaultSimpleCommand,
+    DefaultWord, ListableCommand, PipeableCommand, RedirectOrCmdWord, SimpleWord, TopLevelCommand,
+    TopLevelWord, Word,
+};
+use std::fmt;
+
+pub mod arith;
+pub mod builtins;
+pub mod control_chars;
+pub mod corpus;
+pub mod dialect_diff;
+pub mod expansion_cost;
+pub mod expansions;
+pub mod explain;
+pub mod format_diff;
+pub mod glob;
+pub mod graph;
+pub mod heredocs;
+pub mod jobs;
+pub mod lint;
+pub mod loop_control;
+pub mod posix;
+pub mod quoting;
+pub mod redirects;
+pub mod report;
+pub mod summary;
+pub mod trojan_source;
+
+/// Indicates how serious a reported `Diagnostic` is.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Severity {
+    /// Not necessarily a problem, but worth pointing out.
+    Info,
+    /// A construct which will likely misbehave at runtime.
+    Warning,
+    /// A construct which is almost certainly wrong.
+    Error,
+}
+
+impl Severity {
+    /// Returns the lowercase name of the severity.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+/// A finding reported by an analysis pass.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// A short, stable identifier of the check which produced the finding.
+    pub code: &'static str,
+    /// A human readable description of the finding.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Constructs a new diagnostic.
+    pub fn new<M: Into<String>>(severity: Severity, code: &'static str, message: M) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// A command node encountered while walking the AST.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Node<'a> {
+    /// A simple command, e.g. `foo bar`.
+    Simple(&'a DefaultSimpleCommand),
+    /// A compound command, e.g. `for`, `if`, or a brace group.
+    Compound(&'a DefaultCompoundCommand),
+    /// A function declaration, visited before its body.
+    FunctionDef,
+}
+
+/// Walks all commands (recursing into compound commands and function bodies)
+/// and invokes `f` on every simple and compound command in source order.
+///
+/// Commands nested within word substitutions are not visited.
+pub(crate) fn walk_commands<'a, F>(cmds: &'a [TopLevelCommand<String>], f: &mut F)
+where
+    F: FnMut(Node<'a>),
+{
+    for cmd in cmds {
+        walk_top_level(cmd, f);
+    }
+}
+
+fn walk_top_level<'a, F: FnMut(Node<'a>)>(cmd: &'a TopLevelCommand<String>, f: &mut F) {
+    let list = match cmd.0 {
+        Command::Job(ref list) | Command::List(ref list) => list,
+        Command::Unparsed(_) => return,
+    };
+
+    walk_listable(&list.first, f);
+    for and_or in &list.rest {
+        match *and_or {
+            AndOr::And(ref cmd) | AndOr::Or(ref cmd) => walk_listable(cmd, f),
+        }
+    }
+}
+
+fn walk_listable<'a, F: FnMut(Node<'a>)>(cmd: &'a DefaultListableCommand, f: &mut F) {
+    match *cmd {
+        ListableCommand::Single(ref cmd) => walk_pipeable(cmd, f),
+        ListableCommand::Pipe(ref pipeline) => {
+            for cmd in pipeline.commands() {
+                walk_pipeable(cmd, f);
+            }
+        }
+    }
+}
+
+fn walk_pipeable<'a, F: FnMut(Node<'a>)>(cmd: &'a DefaultPipeableCommand, f: &mut F) {
+    match *cmd {
+        PipeableCommand::Simple(ref cmd) => f(Node::Simple(cmd)),
+        PipeableCommand::Compound(ref cmd) => walk_compound(cmd, f),
+        PipeableCommand::FunctionDef(_, _, ref body) => {
+            f(Node::FunctionDef);
+            walk_compound(body, f);
+        }
+    }
+}
+
+fn walk_compound<'a, F: FnMut(Node<'a>)>(cmd: &'a DefaultCompoundCommand, f: &mut F) {
+    f(Node::Compound(cmd));
+
+    match cmd.kind {
+        CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+            walk_commands(cmds, f)
+        }
+
+        CompoundCommandKind::While(ref gbp) | CompoundCommandKind::Until(ref gbp) => {
+            walk_commands(&gbp.guard, f);
+            walk_commands(&gbp.body, f);
+        }
+
+        CompoundCommandKind::If {
+            ref conditionals,
+            ref else_branch,
+        } => {
+            for gbp in conditionals {
+                walk_commands(&gbp.guard, f);
+                walk_commands(&gbp.body, f);
+            }
+
+            if let Some(ref els) = *else_branch {
+                walk_commands(els, f);
+            }
+        }
+
+        CompoundCommandKind::For { ref body, .. } => walk_commands(body, f),
+
+        CompoundCommandKind::Case { ref arms, .. } => {
+            for arm in arms {
+                walk_commands(&arm.body, f);
+            }
+        }
+
+        CompoundCommandKind::Conditional(_) | CompoundCommandKind::Arithmetic(_) => {}
+    }
+}
+
+/// Returns the command name and arguments of a simple command, skipping any redirects.
+pub(crate) fn cmd_words(cmd: &DefaultSimpleCommand) -> impl Iterator<Item = &TopLevelWord<String>> {
+    cmd.redirects_or_cmd_words.iter().filter_map(|w| match *w {
+        RedirectOrCmdWord::CmdWord(ref w) => Some(w),
+        RedirectOrCmdWord::Redirect(_) => None,
+    })
+}
+
+/// Returns the contents of a word if it is composed entirely out of
+/// literals (quoted or otherwise), or `None` if it contains any expansions.
+pub(crate) fn literal_word(word: &TopLevelWord<String>) -> Option<String> {
+    fn push_simple<P, S>(buf: &mut String, w: &SimpleWord<String, P, S>) -> Option<()> {
+        match *w {
+            SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => buf.push_str(s),
+            SimpleWord::Colon => buf.push(':'),
+            SimpleWord::Tilde
+            | SimpleWord::TildePrefix(_)
+            | SimpleWord::Star
+            | SimpleWord::Question
+            | SimpleWord::SquareOpen
+            | SimpleWord::SquareClose
+            | SimpleWord::Param(_)
+            | SimpleWord::Subst(_) => return None,
+        }
+
+        Some(())
+    }
+
+    fn push_word<P, S>(buf: &mut String, w: &Word<String, SimpleWord<String, P, S>>) -> Option<()> {
+        match *w {
+            Word::Simple(ref w) => push_simple(buf, w),
+            Word::SingleQuoted(ref s) => {
+                buf.push_str(s);
+                Some(())
+            }
+            Word::DoubleQuoted(ref words) => words.iter().try_for_each(|w| push_simple(buf, w)),
+            Word::Braces(_) => None,
+        }
+    }
+
+    let mut buf = String::new();
+    word_parts(word)
+        .iter()
+        .try_for_each(|w| push_word(&mut buf, w))?;
+
+    Some(buf)
+}
+
+/// Renders an approximation of the original source of a word, suitable for
+/// including in diagnostic messages. Substitutions and brace expansions are abbreviated.
+pub(crate) fn display_word(word: &TopLevelWord<String>) -> String {
+    fn push_simple<S>(buf: &mut String, w: &SimpleWord<String, DefaultParameter, S>) {
+        match *w {
+            SimpleWord::Literal(ref s) => buf.push_str(s),
+            SimpleWord::Escaped(ref s) => {
+                buf.push('\\');
+                buf.push_str(s);
+            }
+            SimpleWord::Param(ref p) => buf.push_str(&p.to_string()),
+            SimpleWord::Subst(_) => buf.push_str("${...}"),
+            SimpleWord::Star => buf.push('*'),
+            SimpleWord::Question => buf.push('?'),
+            SimpleWord::SquareOpen => buf.push('['),
+            SimpleWord::SquareClose => buf.push(']'),
+            SimpleWord::Tilde => buf.push('~'),
+            SimpleWord::TildePrefix(ref login) => {
+                buf.push('~');
+                buf.push_str(login.as_deref().unwrap_or_default());
+            }
+            SimpleWord::Colon => buf.push(':'),
+        }
+    }
+
+    let mut buf = String::new();
+    for w in word_parts(word) {
+        match *w {
+            Word::Simple(ref w) => push_simple(&mut buf, w),
+            Word::SingleQuoted(ref s) => {
+                buf.push('\'');
+                buf.push_str(s);
+                buf.push('\'');
+            }
+            Word::DoubleQuoted(ref words) => {
+                buf.push('"');
+                for w in words {
+                    push_simple(&mut buf, w);
+                }
+                buf.push('"');
+            }
+            Word::Braces(_) => buf.push_str("{...}"),
+        }
+    }
+
+    buf
+}
+
+/// Returns the (possibly quoted) parts which make up a word.
+pub(crate) fn word_parts(word: &TopLevelWord<String>) -> &[DefaultWord] {
+    match word.0 {
+        ComplexWord::Single(ref w) => std::slice::from_ref(w),
+        ComplexWord::Concat(ref words) => words,
+    }
+}
@@ -0,0 +1,209 @@
+//! Checks for variables which are evaluated indirectly within arithmetic.
+//!
+//! Variables referenced within an arithmetic context (i.e. `$(( ... ))`
+//! substitutions and `(( ... ))` commands, with or without a leading `$`) whose value is not a plain number are themselves evaluated as
+//! arithmetic expressions, e.g. after `x='y + 1'` the expression `$((x * 2))`
+//! evaluates to `(y + 1) * 2`. Such indirection is easy to miss, and if the
+//! expression (eventually) refers back to the original variable the shell will
+//! fail with an "expression recursion level exceeded" error.
+//!
+//! Only assignments of literal values are considered, and the analysis is
+//! performed without evaluating any control flow.
+
+use super::{cmd_words, literal_word, walk_commands, word_parts};
+use super::{Diagnostic, Node, Severity};
+use crate::ast::{
+    CompoundCommandKind, DefaultArithmetic, DefaultWord, ParameterSubstitution, RedirectOrCmdWord,
+    RedirectOrEnvVar, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use std::collections::BTreeMap;
+
+/// The code reported for variables whose value is evaluated as an arithmetic expression.
+pub const DYNAMIC_EVAL: &str = "arith-dynamic-eval";
+/// The code reported for variables whose value (eventually) refers back to itself.
+pub const RECURSIVE_EVAL: &str = "arith-recursive-eval";
+
+/// Reports any variables referenced within arithmetic which are assigned a
+/// value that is itself an arithmetic expression (rather than a number),
+/// and escalates the report if evaluating the variable would recurse forever.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    let mut exprs = Vec::new();
+    let mut assignments = BTreeMap::new();
+
+    walk_commands(cmds, &mut |node| match node {
+        Node::Simple(cmd) => {
+            let is_assignment = cmd_words(cmd).next().is_none();
+
+            for item in &cmd.redirects_or_env_vars {
+                match *item {
+                    RedirectOrEnvVar::EnvVar(ref var, Some(ref value)) => {
+                        if is_assignment {
+                            match literal_word(value).filter(|v| !is_number(v)) {
+                                Some(value) => assignments.insert(var.as_str(), value),
+                                None => assignments.remove(var.as_str()),
+                            };
+                        }
+                        word_arithmetic(value, &mut exprs);
+                    }
+                    RedirectOrEnvVar::EnvVar(_, None) => {}
+                    RedirectOrEnvVar::Redirect(ref redirect) => {
+                        word_arithmetic(redirect.word(), &mut exprs)
+                    }
+                }
+            }
+
+            for item in &cmd.redirects_or_cmd_words {
+                match *item {
+                    RedirectOrCmdWord::CmdWord(ref word) => word_arithmetic(word, &mut exprs),
+                    RedirectOrCmdWord::Redirect(ref redirect) => {
+                        word_arithmetic(redirect.word(), &mut exprs)
+                    }
+                }
+            }
+        }
+
+        Node::Compound(cmd) => {
+            if let CompoundCommandKind::Arithmetic(Some(ref expr)) = cmd.kind {
+                exprs.push(expr);
+            }
+            for word in cmd.kind.words() {
+                word_arithmetic(word, &mut exprs);
+            }
+            for redirect in &cmd.io {
+                word_arithmetic(redirect.word(), &mut exprs);
+            }
+        }
+
+        Node::FunctionDef => {}
+    });
+
+    let mut reported = Vec::new();
+    let mut diagnostics = Vec::new();
+    for var in exprs.iter().flat_map(|expr| expr.referenced_vars()) {
+        let value = match assignments.get(var.as_str()) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        if reported.contains(&var) {
+            continue;
+        }
+        reported.push(var);
+
+        diagnostics.push(match recursion(var, &assignments) {
+            Some(chain) => {
+                let msg = format!(
+                    "`{}` is evaluated recursively within arithmetic ({}), \
+                     which exceeds the expression recursion level",
+                    var,
+                    chain.join(" -> ")
+                );
+                Diagnostic::new(Severity::Error, RECURSIVE_EVAL, msg)
+            }
+            None => {
+                let msg = format!(
+                    "`{}` is assigned `{}`, which will be evaluated as an \
+                     arithmetic expression when referenced within arithmetic",
+                    var, value
+                );
+                Diagnostic::new(Severity::Info, DYNAMIC_EVAL, msg)
+            }
+        });
+    }
+
+    diagnostics
+}
+
+/// Collects all arithmetic expressions within a word, including ones nested
+/// within double quotes or the words of other parameter substitutions.
+///
+/// Arithmetic within command substitutions is not collected.
+fn word_arithmetic<'a>(word: &'a TopLevelWord<String>, exprs: &mut Vec<&'a DefaultArithmetic>) {
+    for part in word_parts(word) {
+        part_arithmetic(part, exprs);
+    }
+}
+
+fn part_arithmetic<'a>(part: &'a DefaultWord, exprs: &mut Vec<&'a DefaultArithmetic>) {
+    let simple_words = match *part {
+        Word::Simple(ref w) => std::slice::from_ref(w),
+        Word::DoubleQuoted(ref words) => &words[..],
+        Word::SingleQuoted(_) => &[],
+        Word::Braces(ref braces) => {
+            for part in braces.words() {
+                part_arithmetic(part, exprs);
+            }
+            return;
+        }
+    };
+
+    for w in simple_words {
+        if let SimpleWord::Subst(ref subst) = *w {
+            match **subst {
+                ParameterSubstitution::Arith(Some(ref expr)) => exprs.push(expr),
+                ref subst => {
+                    if let Some(word) = subst.word() {
+                        word_arithmetic(word, exprs);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the chain of variables through which `var` refers back to itself, if any.
+fn recursion<'a>(var: &'a str, assignments: &'a BTreeMap<&str, String>) -> Option<Vec<&'a str>> {
+    fn visit<'a>(
+        var: &str,
+        assignments: &'a BTreeMap<&str, String>,
+        chain: &mut Vec<&'a str>,
+        visited: &mut Vec<&'a str>,
+    ) -> bool {
+        let current = chain[chain.len() - 1];
+        let value = match assignments.get(current) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        for name in identifiers(value) {
+            if name == var {
+                chain.push(name);
+                return true;
+            } else if visited.contains(&name) {
+                continue;
+            }
+
+            visited.push(name);
+            chain.push(name);
+            if visit(var, assignments, chain, visited) {
+                return true;
+            }
+            chain.pop();
+        }
+
+        false
+    }
+
+    let mut chain = vec![var];
+    if visit(var, assignments, &mut chain, &mut Vec::new()) {
+        Some(chain)
+    } else {
+        None
+    }
+}
+
+/// Returns the variable names which appear within an expression.
+fn identifiers(expr: &str) -> impl Iterator<Item = &str> {
+    expr.split(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+        .filter(|s| s.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()))
+}
+
+/// Checks if a value is a plain (optionally signed) decimal number.
+/// Empty values are treated as zero by the shell.
+fn is_number(value: &str) -> bool {
+    let value = value.trim();
+    let digits = value
+        .strip_prefix(|c| c == '-' || c == '+')
+        .unwrap_or(value);
+    digits.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,158 @@
+//! Classification of special builtin commands whose behavior affects control flow.
+//!
+//! Simple commands invoking one of the builtins listed in `BUILTINS` (`:`, `.`,
+//! `source`, `break`, `continue`, `return`, `exit`, and `shift`) are classified
+//! as a `SpecialCommand`, with any numeric operand already parsed, so that other
+//! analyses can consume them without re-parsing their arguments.
+//!
+//! Only commands whose name is a literal word are classified, and invocations
+//! through other builtins (e.g. `builtin exit` or `command .`) are not.
+
+use super::{cmd_words, literal_word, walk_commands, Node};
+use crate::ast::{DefaultSimpleCommand, TopLevelCommand, TopLevelWord};
+
+/// The kinds of special builtins which can be classified.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BuiltinKind {
+    /// `:`, which does nothing and succeeds.
+    Null,
+    /// `.` or `source`, which runs a script in the current shell.
+    Source,
+    /// `break [n]`, which exits from `n` enclosing loops.
+    Break,
+    /// `continue [n]`, which resumes the `n`th enclosing loop.
+    Continue,
+    /// `return [n]`, which returns from a function (or sourced script) with status `n`.
+    Return,
+    /// `exit [n]`, which exits the shell with status `n`.
+    Exit,
+    /// `shift [n]`, which drops the first `n` positional parameters.
+    Shift,
+}
+
+impl BuiltinKind {
+    /// Returns the kind of builtin invoked by the given command name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        BUILTINS
+            .iter()
+            .find(|&&(builtin, _)| builtin == name)
+            .map(|&(_, kind)| kind)
+    }
+
+    /// Indicates if the builtin takes a single numeric operand.
+    pub fn takes_number(&self) -> bool {
+        match *self {
+            BuiltinKind::Null | BuiltinKind::Source => false,
+            BuiltinKind::Break
+            | BuiltinKind::Continue
+            | BuiltinKind::Return
+            | BuiltinKind::Exit
+            | BuiltinKind::Shift => true,
+        }
+    }
+}
+
+/// The names of all builtins which are classified, and their kinds.
+pub const BUILTINS: &[(&str, BuiltinKind)] = &[
+    (":", BuiltinKind::Null),
+    (".", BuiltinKind::Source),
+    ("source", BuiltinKind::Source),
+    ("break", BuiltinKind::Break),
+    ("continue", BuiltinKind::Continue),
+    ("return", BuiltinKind::Return),
+    ("exit", BuiltinKind::Exit),
+    ("shift", BuiltinKind::Shift),
+];
+
+/// The numeric operand of a builtin such as `break` or `exit`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operand<'a> {
+    /// No operand was given, so the builtin's default applies (e.g. a single
+    /// loop for `break`, or the status of the last command for `exit`).
+    Omitted,
+    /// A literal decimal number, e.g. the `2` of `break 2`.
+    Number(i64),
+    /// An operand whose value is only known at runtime, e.g. the `$n` of `shift $n`.
+    Dynamic(&'a TopLevelWord<String>),
+    /// A literal operand which is not a valid number, e.g. the `x` of `exit x`.
+    Invalid(&'a TopLevelWord<String>),
+}
+
+/// A simple command which invokes a special builtin.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpecialCommand<'a> {
+    /// The kind of builtin invoked.
+    pub kind: BuiltinKind,
+    /// The command the builtin is invoked by.
+    pub cmd: &'a DefaultSimpleCommand,
+    /// The parsed numeric operand, always `Operand::Omitted` for builtins
+    /// which do not take one (see `BuiltinKind::takes_number`).
+    pub operand: Operand<'a>,
+    /// Any arguments which were not parsed as the operand, e.g. the path and
+    /// arguments of `. script.sh arg`, or the extraneous `b` of `exit a b`.
+    pub args: Vec<&'a TopLevelWord<String>>,
+}
+
+impl<'a> SpecialCommand<'a> {
+    /// Classifies a simple command, returning `None` if it does not invoke a
+    /// builtin listed in `BUILTINS`.
+    ///
+    /// ```
+    /// use conch_parser::analysis::builtins::{BuiltinKind, Operand, SpecialCommand};
+    /// use conch_parser::ast::{Command, ListableCommand, PipeableCommand};
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let mut parser = DefaultParser::new(Lexer::new("break 2".chars()));
+    /// let cmd = parser.complete_command().unwrap().unwrap();
+    /// let simple = match cmd.0 {
+    ///     Command::List(ref list) => match list.first {
+    ///         ListableCommand::Single(PipeableCommand::Simple(ref cmd)) => cmd,
+    ///         _ => unreachable!(),
+    ///     },
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let special = SpecialCommand::classify(simple).unwrap();
+    /// assert_eq!(special.kind, BuiltinKind::Break);
+    /// assert_eq!(special.operand, Operand::Number(2));
+    /// ```
+    pub fn classify(cmd: &'a DefaultSimpleCommand) -> Option<Self> {
+        let mut words = cmd_words(cmd);
+        let kind = BuiltinKind::from_name(&literal_word(words.next()?)?)?;
+
+        let mut operand = Operand::Omitted;
+        if kind.takes_number() {
+            if let Some(word) = words.next() {
+                operand = match literal_word(word) {
+                    Some(lit) => lit
+                        .parse::<i64>()
+                        .map_or(Operand::Invalid(word), Operand::Number),
+                    None => Operand::Dynamic(word),
+                };
+            }
+        }
+
+        Some(SpecialCommand {
+            kind,
+            cmd,
+            operand,
+            args: words.collect(),
+        })
+    }
+}
+
+/// Classifies every simple command which invokes a special builtin (recursing
+/// into compound commands and function bodies), in source order.
+///
+/// Commands nested within word substitutions are not visited.
+pub fn special_commands(cmds: &[TopLevelCommand<String>]) -> Vec<SpecialCommand<'_>> {
+    let mut special = Vec::new();
+    walk_commands(cmds, &mut |node| {
+        if let Node::Simple(cmd) = node {
+            special.extend(SpecialCommand::classify(cmd));
+        }
+    });
+
+    special
+}
@@ -0,0 +1,232 @@
+//! Checks for invisible or control characters embedded in literals.
+//!
+//! Characters such as bidirectional overrides (e.g. U+202E) or zero-width
+//! spaces are not rendered (or are rendered misleadingly) by most editors and
+//! terminals, which allows a script to look different to a reviewer than it
+//! does to the shell. Raw control characters are similarly suspicious, since
+//! they can rewrite what a terminal displays.
+//!
+//! NUL bytes are rejected by the parser by default. If the parser was configured
+//! with `NulHandling::Replace`, any replaced NUL bytes are reported here as well.
+
+use super::{cmd_words, walk_commands, word_parts};
+use super::{Diagnostic, Node, Severity};
+use crate::ast::{
+    DefaultRedirect, DefaultSimpleWord, DefaultWord, RedirectOrCmdWord, RedirectOrEnvVar,
+    SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use crate::parse::NUL_REPLACEMENT;
+use std::fmt::Write;
+
+/// The code reported for raw control characters (other than tabs and newlines)
+/// as well as for NUL bytes replaced by the parser.
+pub const CONTROL_CHAR: &str = "control-char";
+/// The code reported for Unicode bidirectional control characters.
+pub const BIDI_CONTROL: &str = "bidi-control";
+/// The code reported for zero-width (invisible) characters.
+pub const ZERO_WIDTH: &str = "zero-width-char";
+
+/// The kind of a suspicious character, in the order diagnostics are reported.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub(crate) enum Kind {
+    Nul,
+    Control,
+    Bidi,
+    ZeroWidth,
+}
+
+impl Kind {
+    pub(crate) fn classify(c: char) -> Option<Self> {
+        match c {
+            NUL_REPLACEMENT => Some(Kind::Nul),
+            '\t' | '\n' => None,
+            '\u{0}'..='\u{1F}' | '\u{7F}'..='\u{9F}' => Some(Kind::Control),
+            '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{061C}' => Some(Kind::Bidi),
+            '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => Some(Kind::ZeroWidth),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Kind::Nul | Kind::Control => CONTROL_CHAR,
+            Kind::Bidi => BIDI_CONTROL,
+            Kind::ZeroWidth => ZERO_WIDTH,
+        }
+    }
+
+    pub(crate) fn severity(self) -> Severity {
+        match self {
+            Kind::Bidi => Severity::Error,
+            Kind::Nul | Kind::Control | Kind::ZeroWidth => Severity::Warning,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Kind::Nul => "a replaced NUL byte",
+            Kind::Control => "control characters",
+            Kind::Bidi => "bidirectional control characters",
+            Kind::ZeroWidth => "zero-width characters",
+        }
+    }
+}
+
+/// Reports any literal text (quoted or otherwise) which contains raw control
+/// characters, Unicode bidirectional controls, or zero-width characters.
+///
+/// Words within parameter and command substitutions are checked as well.
+/// Each offending literal produces one diagnostic per kind of character found,
+/// naming the code points involved.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_commands(cmds, &mut diagnostics);
+    diagnostics
+}
+
+fn check_commands(cmds: &[TopLevelCommand<String>], diagnostics: &mut Vec<Diagnostic>) {
+    walk_commands(cmds, &mut |node| match node {
+        Node::Simple(cmd) => {
+            for item in &cmd.redirects_or_env_vars {
+                match *item {
+                    RedirectOrEnvVar::Redirect(ref r) => check_redirect(r, diagnostics),
+                    RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                        check_text(name, diagnostics);
+                        if let Some(ref value) = *value {
+                            check_word(value, diagnostics);
+                        }
+                    }
+                }
+            }
+
+            for item in &cmd.redirects_or_cmd_words {
+                if let RedirectOrCmdWord::Redirect(ref r) = *item {
+                    check_redirect(r, diagnostics);
+                }
+            }
+
+            for word in cmd_words(cmd) {
+                check_word(word, diagnostics);
+            }
+        }
+
+        Node::Compound(cmd) => {
+            for r in &cmd.io {
+                check_redirect(r, diagnostics);
+            }
+
+            for word in cmd.kind.words() {
+                check_word(word, diagnostics);
+            }
+        }
+
+        Node::FunctionDef => {}
+    });
+}
+
+fn check_redirect(redirect: &DefaultRedirect, diagnostics: &mut Vec<Diagnostic>) {
+    check_word(redirect.word(), diagnostics)
+}
+
+fn check_word(word: &TopLevelWord<String>, diagnostics: &mut Vec<Diagnostic>) {
+    for part in word_parts(word) {
+        check_part(part, diagnostics);
+    }
+}
+
+fn check_part(part: &DefaultWord, diagnostics: &mut Vec<Diagnostic>) {
+    match *part {
+        Word::Simple(ref w) => check_simple(w, diagnostics),
+        Word::SingleQuoted(ref s) => check_text(s, diagnostics),
+        Word::DoubleQuoted(ref words) => {
+            for w in words {
+                check_simple(w, diagnostics);
+            }
+        }
+        Word::Braces(ref braces) => {
+            for part in braces.words() {
+                check_part(part, diagnostics);
+            }
+        }
+    }
+}
+
+fn check_simple(word: &DefaultSimpleWord, diagnostics: &mut Vec<Diagnostic>) {
+    match *word {
+        SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => check_text(s, diagnostics),
+        SimpleWord::Subst(ref subst) => {
+            if let Some(cmds) = subst.commands() {
+                check_commands(cmds, diagnostics);
+            }
+            if let Some(w) = subst.word() {
+                check_word(w, diagnostics);
+            }
+        }
+
+        SimpleWord::Param(_)
+        | SimpleWord::Star
+        | SimpleWord::Question
+        | SimpleWord::SquareOpen
+        | SimpleWord::SquareClose
+        | SimpleWord::Tilde
+        | SimpleWord::Colon => {}
+        SimpleWord::TildePrefix(ref login) => {
+            if let Some(ref login) = *login {
+                check_text(login, diagnostics);
+            }
+        }
+    }
+}
+
+fn check_text(text: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let mut found: Vec<(Kind, char)> = text
+        .chars()
+        .filter_map(|c| Kind::classify(c).map(|kind| (kind, c)))
+        .collect();
+
+    if found.is_empty() {
+        return;
+    }
+
+    found.sort();
+    found.dedup();
+
+    let escaped = escape(text);
+    let mut i = 0;
+    while i < found.len() {
+        let kind = found[i].0;
+        let mut code_points = String::new();
+        while i < found.len() && found[i].0 == kind {
+            if !code_points.is_empty() {
+                code_points.push_str(", ");
+            }
+            write!(code_points, "U+{:04X}", found[i].1 as u32).unwrap();
+            i += 1;
+        }
+
+        let message = format!(
+            "literal \"{}\" contains {} ({})",
+            escaped,
+            kind.description(),
+            code_points
+        );
+        diagnostics.push(Diagnostic::new(kind.severity(), kind.code(), message));
+    }
+}
+
+/// Renders literal text with all suspicious characters escaped so that
+/// the diagnostic message itself is not subject to the same obfuscation.
+fn escape(text: &str) -> String {
+    let mut buf = String::with_capacity(text.len());
+    for c in text.chars() {
+        match Kind::classify(c) {
+            Some(_) => write!(buf, "\\u{{{:x}}}", c as u32).unwrap(),
+            None => buf.push(c),
+        }
+    }
+    buf
+}
@@ -0,0 +1,820 @@
+//! Construct frequency statistics over a corpus of scripts.
+//!
+//! A `CorpusStats` aggregates how often each kind of construct appears across
+//! any number of scripts, e.g. to decide which features are worth supporting
+//! (or optimizing) based on real-world usage. Constructs are grouped into a
+//! histogram per `Category`, and each entry records both the total number of
+//! occurrences and the number of files in which the construct appears.
+//!
+//! Histogram keys are short, stable names: command kinds are named after the
+//! construct (e.g. `"while"` or `"function"`), while redirects, expansions,
+//! arithmetic operators, and dialect extensions are named after the syntax
+//! which introduces them (e.g. `">>"`, `"${...:-...}"`, `"**"`, or `"|&"`).
+//! Commands nested within substitutions are counted like any other command.
+//!
+//! Reports only depend on the scripts which were added, and not the order they
+//! were added in, so reports from separate runs can be compared or `merge`d.
+
+use super::{literal_word, word_parts};
+use crate::analysis::dialect_diff::DefaultParseError;
+use crate::ast::{
+    AndOr, Arithmetic, ArithmeticTarget, CaseTerminator, Command, ComplexWord, CompoundCommandKind,
+    DefaultArithmetic, DefaultCompoundCommand, DefaultListableCommand, DefaultParameter,
+    DefaultParameterSubstitution, DefaultPipeableCommand, DefaultRedirect, DefaultSimpleCommand,
+    DefaultSimpleWord, DefaultWord, ListableCommand, Parameter, PipeableCommand, Radix, Redirect,
+    RedirectOrCmdWord, RedirectOrEnvVar, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, Dialect, Extension, ParserOptions};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+/// The names of the commands which are counted as builtins: the POSIX special
+/// and regular builtins, along with the builtins commonly provided by bash.
+pub const BUILTIN_NAMES: &[&str] = &[
+    "!",
+    ".",
+    ":",
+    "[",
+    "alias",
+    "bg",
+    "bind",
+    "break",
+    "builtin",
+    "caller",
+    "cd",
+    "command",
+    "compgen",
+    "complete",
+    "compopt",
+    "continue",
+    "declare",
+    "dirs",
+    "disown",
+    "echo",
+    "enable",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fc",
+    "fg",
+    "getopts",
+    "hash",
+    "help",
+    "history",
+    "jobs",
+    "kill",
+    "let",
+    "local",
+    "logout",
+    "mapfile",
+    "newgrp",
+    "popd",
+    "printf",
+    "pushd",
+    "pwd",
+    "read",
+    "readarray",
+    "readonly",
+    "return",
+    "set",
+    "shift",
+    "shopt",
+    "source",
+    "suspend",
+    "test",
+    "times",
+    "trap",
+    "true",
+    "type",
+    "typeset",
+    "ulimit",
+    "umask",
+    "unalias",
+    "unset",
+    "wait",
+];
+
+/// The groups of constructs for which a histogram is collected.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Category {
+    /// Kinds of commands, e.g. `"simple"`, `"pipeline"`, `"for"`, or `"assignment"`.
+    Commands,
+    /// Redirection operators, e.g. `">"` or `"<<"`.
+    Redirects,
+    /// Kinds of word parts, e.g. `"literal"`, `"double-quoted"`, or `"glob"`.
+    Words,
+    /// Parameters and substitutions, e.g. `"$var"`, `"$@"`, or `"$(...)"`.
+    Expansions,
+    /// Arithmetic operands and operators, e.g. `"var"`, `"+"`, or `"?:"`.
+    Arithmetic,
+    /// Invocations of builtins (see `BUILTIN_NAMES`) by their literal name.
+    Builtins,
+    /// Uses of dialect extensions, keyed by `Extension::as_str`.
+    Extensions,
+}
+
+impl Category {
+    /// All categories, in the order they are reported.
+    pub const ALL: [Category; 7] = [
+        Category::Commands,
+        Category::Redirects,
+        Category::Words,
+        Category::Expansions,
+        Category::Arithmetic,
+        Category::Builtins,
+        Category::Extensions,
+    ];
+
+    /// Returns the name of the category as used in reports.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Category::Commands => "commands",
+            Category::Redirects => "redirects",
+            Category::Words => "words",
+            Category::Expansions => "expansions",
+            Category::Arithmetic => "arithmetic",
+            Category::Builtins => "builtins",
+            Category::Extensions => "extensions",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+/// How often a single construct appears within a corpus.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Count {
+    /// The total number of times the construct appears.
+    pub occurrences: u64,
+    /// The number of files in which the construct appears at least once.
+    pub files: u64,
+}
+
+/// The counts of every construct within a category, sorted by key.
+pub type Histogram = BTreeMap<String, Count>;
+
+/// Construct frequencies aggregated over any number of scripts.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorpusStats {
+    /// The number of scripts which were successfully parsed and counted.
+    pub files: u64,
+    /// The number of scripts which failed to parse, and were not counted.
+    pub failed: u64,
+    /// See `Category::Commands`.
+    pub commands: Histogram,
+    /// See `Category::Redirects`.
+    pub redirects: Histogram,
+    /// See `Category::Words`.
+    pub words: Histogram,
+    /// See `Category::Expansions`.
+    pub expansions: Histogram,
+    /// See `Category::Arithmetic`.
+    pub arithmetic: Histogram,
+    /// See `Category::Builtins`.
+    pub builtins: Histogram,
+    /// See `Category::Extensions`.
+    pub extensions: Histogram,
+}
+
+impl CorpusStats {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a script with the specified dialect and counts all of its constructs.
+    ///
+    /// If the script fails to parse, none of its constructs are counted, it is
+    /// tallied as `failed`, and the error is returned.
+    pub fn add_script(&mut self, source: &str, dialect: Dialect) -> Result<(), DefaultParseError> {
+        let options = ParserOptions {
+            dialect,
+            ..ParserOptions::default()
+        };
+
+        let mut parser =
+            DefaultParser::with_options(Lexer::new(source.chars()), Default::default(), options);
+        parser.set_track_keywords(true);
+
+        let cmds = match parser.parse_all() {
+            Ok(cmds) => cmds,
+            Err(e) => {
+                self.failed += 1;
+                return Err(e);
+            }
+        };
+
+        let mut tally = Tally::default();
+        for cmd in &cmds {
+            tally.top_level(cmd);
+        }
+
+        // Function declarations look the same in the AST regardless of
+        // whether the `function` reserved word was used to declare them.
+        for keyword in parser.take_keywords() {
+            if keyword.word == Extension::FunctionKeyword.as_str() {
+                tally.extension(Extension::FunctionKeyword);
+            }
+        }
+
+        self.files += 1;
+        for ((category, key), occurrences) in tally.counts {
+            let count = self.histogram_mut(category).entry(key).or_default();
+            count.occurrences += occurrences;
+            count.files += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the statistics of another (disjoint) corpus to these ones.
+    pub fn merge(&mut self, other: &CorpusStats) {
+        self.files += other.files;
+        self.failed += other.failed;
+
+        for &category in &Category::ALL {
+            for (key, count) in other.histogram(category) {
+                let total = self.histogram_mut(category).entry(key.clone()).or_default();
+                total.occurrences += count.occurrences;
+                total.files += count.files;
+            }
+        }
+    }
+
+    /// Returns the histogram of a category.
+    pub fn histogram(&self, category: Category) -> &Histogram {
+        match category {
+            Category::Commands => &self.commands,
+            Category::Redirects => &self.redirects,
+            Category::Words => &self.words,
+            Category::Expansions => &self.expansions,
+            Category::Arithmetic => &self.arithmetic,
+            Category::Builtins => &self.builtins,
+            Category::Extensions => &self.extensions,
+        }
+    }
+
+    fn histogram_mut(&mut self, category: Category) -> &mut Histogram {
+        match category {
+            Category::Commands => &mut self.commands,
+            Category::Redirects => &mut self.redirects,
+            Category::Words => &mut self.words,
+            Category::Expansions => &mut self.expansions,
+            Category::Arithmetic => &mut self.arithmetic,
+            Category::Builtins => &mut self.builtins,
+            Category::Extensions => &mut self.extensions,
+        }
+    }
+
+    /// Renders the statistics as a JSON object.
+    ///
+    /// The object has the `files` and `failed` counts, and an object for each
+    /// category (named by `Category::as_str`) mapping every key to an object
+    /// with its `occurrences` and `files` counts.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_json<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"files\": {},", self.files)?;
+        writeln!(out, "  \"failed\": {},", self.failed)?;
+
+        for (idx, &category) in Category::ALL.iter().enumerate() {
+            let histogram = self.histogram(category);
+            write!(out, "  \"{}\": {{", category)?;
+
+            for (i, (key, count)) in histogram.iter().enumerate() {
+                out.write_str(if i == 0 { "\n    " } else { ",\n    " })?;
+                write_quoted(out, key)?;
+                write!(
+                    out,
+                    ": {{\"occurrences\": {}, \"files\": {}}}",
+                    count.occurrences, count.files
+                )?;
+            }
+
+            if !histogram.is_empty() {
+                out.write_str("\n  ")?;
+            }
+            let sep = if idx + 1 < Category::ALL.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(out, "}}{}", sep)?;
+        }
+
+        writeln!(out, "}}")
+    }
+}
+
+fn write_quoted<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\t' => out.write_str("\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// The occurrences of each construct within a single script.
+#[derive(Default)]
+struct Tally {
+    counts: BTreeMap<(Category, String), u64>,
+}
+
+impl Tally {
+    fn count(&mut self, category: Category, key: &str) {
+        *self.counts.entry((category, key.to_owned())).or_insert(0) += 1;
+    }
+
+    fn extension(&mut self, ext: Extension) {
+        self.count(Category::Extensions, ext.as_str());
+    }
+
+    fn commands(&mut self, cmds: &[TopLevelCommand<String>]) {
+        for cmd in cmds {
+            self.top_level(cmd);
+        }
+    }
+
+    fn top_level(&mut self, cmd: &TopLevelCommand<String>) {
+        let list = match cmd.0 {
+            Command::Job(ref list) => {
+                self.count(Category::Commands, "background");
+                list
+            }
+            Command::List(ref list) => list,
+            Command::Unparsed(_) => return self.count(Category::Commands, "unparsed"),
+        };
+
+        self.listable(&list.first);
+        for and_or in &list.rest {
+            match *and_or {
+                AndOr::And(ref cmd) => {
+                    self.count(Category::Commands, "and");
+                    self.listable(cmd);
+                }
+                AndOr::Or(ref cmd) => {
+                    self.count(Category::Commands, "or");
+                    self.listable(cmd);
+                }
+            }
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                self.count(Category::Commands, "pipeline");
+                if pipeline.bang {
+                    self.count(Category::Commands, "negation");
+                }
+
+                for segment in &pipeline.segments {
+                    if segment.pipe_stderr {
+                        self.extension(Extension::PipeAmp);
+                    }
+                    self.pipeable(&segment.cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(_, _, ref body) => {
+                self.count(Category::Commands, "function");
+                self.compound(body);
+            }
+        }
+    }
+
+    fn simple(&mut self, cmd: &DefaultSimpleCommand) {
+        self.count(Category::Commands, "simple");
+
+        for item in &cmd.redirects_or_env_vars {
+            match *item {
+                RedirectOrEnvVar::Redirect(ref redirect) => self.redirect(redirect),
+                RedirectOrEnvVar::EnvVar(_, ref value) => {
+                    self.count(Category::Commands, "assignment");
+                    if let Some(ref value) = *value {
+                        self.word(value);
+                    }
+                }
+            }
+        }
+
+        let mut name = None;
+        for item in &cmd.redirects_or_cmd_words {
+            match *item {
+                RedirectOrCmdWord::Redirect(ref redirect) => self.redirect(redirect),
+                RedirectOrCmdWord::CmdWord(ref word) => {
+                    if name.is_none() {
+                        name = Some(word);
+                    }
+                    self.word(word);
+                }
+            }
+        }
+
+        let name = name.and_then(command_name);
+        if let Some(name) = name.as_deref() {
+            if BUILTIN_NAMES.contains(&name) {
+                self.count(Category::Builtins, name);
+            }
+        }
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand) {
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) => {
+                self.count(Category::Commands, "brace");
+                self.commands(cmds);
+            }
+
+            CompoundCommandKind::Subshell(ref cmds) => {
+                self.count(Category::Commands, "subshell");
+                self.commands(cmds);
+            }
+
+            CompoundCommandKind::While(ref gbp) => {
+                self.count(Category::Commands, "while");
+                self.commands(&gbp.guard);
+                self.commands(&gbp.body);
+            }
+
+            CompoundCommandKind::Until(ref gbp) => {
+                self.count(Category::Commands, "until");
+                self.commands(&gbp.guard);
+                self.commands(&gbp.body);
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                self.count(Category::Commands, "if");
+                for (idx, gbp) in conditionals.iter().enumerate() {
+                    if idx > 0 {
+                        self.count(Category::Commands, "elif");
+                    }
+                    self.commands(&gbp.guard);
+                    self.commands(&gbp.body);
+                }
+
+                if let Some(ref els) = *else_branch {
+                    self.count(Category::Commands, "else");
+                    self.commands(els);
+                }
+            }
+
+            CompoundCommandKind::For {
+                ref words,
+                ref body,
+                ..
+            } => {
+                self.count(Category::Commands, "for");
+                for word in words.iter().flatten() {
+                    self.word(word);
+                }
+                self.commands(body);
+            }
+
+            CompoundCommandKind::Case { ref word, ref arms } => {
+                self.count(Category::Commands, "case");
+                self.word(word);
+
+                for arm in arms {
+                    self.count(Category::Commands, "case-arm");
+                    for pattern in &arm.patterns {
+                        self.word(pattern);
+                    }
+                    self.commands(&arm.body);
+
+                    match arm.terminator {
+                        CaseTerminator::Break => {}
+                        CaseTerminator::FallThrough => self.extension(Extension::CaseFallThrough),
+                        CaseTerminator::Continue => self.extension(Extension::CaseContinue),
+                    }
+                }
+            }
+
+            CompoundCommandKind::Conditional(ref cond) => {
+                self.count(Category::Commands, "conditional");
+                self.extension(Extension::DoubleBracket);
+                for word in cond.words() {
+                    self.word(word);
+                }
+            }
+
+            CompoundCommandKind::Arithmetic(ref arith) => {
+                self.count(Category::Commands, "arithmetic");
+                self.extension(Extension::DoubleParen);
+                if let Some(ref arith) = *arith {
+                    self.arith(arith);
+                }
+            }
+        }
+
+        for redirect in &cmd.io {
+            self.redirect(redirect);
+        }
+    }
+
+    fn redirect(&mut self, redirect: &DefaultRedirect) {
+        let (op, word) = match *redirect {
+            Redirect::Read(_, ref w) => ("<", w),
+            Redirect::Write(_, ref w) => (">", w),
+            Redirect::ReadWrite(_, ref w) => ("<>", w),
+            Redirect::Append(_, ref w) => (">>", w),
+            Redirect::Clobber(_, ref w) => (">|", w),
+            Redirect::Heredoc(_, ref w, _) => ("<<", w),
+            Redirect::DupRead(_, ref w) => ("<&", w),
+            Redirect::DupWrite(_, ref w) => (">&", w),
+            Redirect::WriteBoth(ref w) => ("&>", w),
+            Redirect::AppendBoth(ref w) => ("&>>", w),
+        };
+
+        self.count(Category::Redirects, op);
+        self.word(word);
+    }
+
+    fn word(&mut self, word: &TopLevelWord<String>) {
+        match word.0 {
+            ComplexWord::Single(ref w) => self.word_part(w),
+            ComplexWord::Concat(ref words) => {
+                for w in words {
+                    self.word_part(w);
+                }
+            }
+        }
+    }
+
+    fn word_part(&mut self, word: &DefaultWord) {
+        match *word {
+            Word::Simple(ref w) => self.simple_word(w),
+            Word::DoubleQuoted(ref words) => {
+                self.count(Category::Words, "double-quoted");
+                for w in words {
+                    self.simple_word(w);
+                }
+            }
+            Word::SingleQuoted(_) => self.count(Category::Words, "single-quoted"),
+            Word::Braces(ref braces) => {
+                self.count(Category::Words, "brace-expansion");
+                for w in braces.words() {
+                    self.word_part(w);
+                }
+            }
+        }
+    }
+
+    fn simple_word(&mut self, word: &DefaultSimpleWord) {
+        match *word {
+            SimpleWord::Literal(_) | SimpleWord::Colon | SimpleWord::SquareClose => {
+                self.count(Category::Words, "literal")
+            }
+            SimpleWord::Escaped(_) => self.count(Category::Words, "escaped"),
+            SimpleWord::Star | SimpleWord::Question | SimpleWord::SquareOpen => {
+                self.count(Category::Words, "glob")
+            }
+            SimpleWord::Tilde | SimpleWord::TildePrefix(_) => self.count(Category::Words, "tilde"),
+            SimpleWord::Param(ref param) => self.param(param),
+            SimpleWord::Subst(ref subst) => self.subst(subst),
+        }
+    }
+
+    fn param(&mut self, param: &DefaultParameter) {
+        let key = match *param {
+            Parameter::At => "$@",
+            Parameter::Star => "$*",
+            Parameter::Pound => "$#",
+            Parameter::Question => "$?",
+            Parameter::Dash => "$-",
+            Parameter::Dollar => "$$",
+            Parameter::Bang => "$!",
+            Parameter::Positional(_) => "$N",
+            Parameter::Var(_) => "$var",
+        };
+
+        self.count(Category::Expansions, key);
+    }
+
+    fn subst(&mut self, subst: &DefaultParameterSubstitution) {
+        use crate::ast::ParameterSubstitution::*;
+
+        let (key, param, word) = match *subst {
+            Command(ref cmds) => {
+                self.count(Category::Expansions, "$(...)");
+                return self.commands(cmds);
+            }
+            ProcessRead(ref cmds) => {
+                self.count(Category::Expansions, "<(...)");
+                self.extension(Extension::ProcessRead);
+                return self.commands(cmds);
+            }
+            ProcessWrite(ref cmds) => {
+                self.count(Category::Expansions, ">(...)");
+                self.extension(Extension::ProcessWrite);
+                return self.commands(cmds);
+            }
+            ReadFile(ref word) => {
+                self.count(Category::Expansions, "$(<...)");
+                return self.word(word);
+            }
+            Arith(ref arith) => {
+                self.count(Category::Expansions, "$((...))");
+                if let Some(ref arith) = *arith {
+                    self.arith(arith);
+                }
+                return;
+            }
+            Len(ref param) => ("${#...}", param, &None),
+
+            Default(colon, ref p, ref w) => {
+                (if colon { "${...:-...}" } else { "${...-...}" }, p, w)
+            }
+            Assign(colon, ref p, ref w) => (if colon { "${...:=...}" } else { "${...=...}" }, p, w),
+            Error(colon, ref p, ref w) => (if colon { "${...:?...}" } else { "${...?...}" }, p, w),
+            Alternative(colon, ref p, ref w) => {
+                (if colon { "${...:+...}" } else { "${...+...}" }, p, w)
+            }
+            Lowercase(all, ref p, ref w) => (if all { "${...,,...}" } else { "${...,...}" }, p, w),
+            Uppercase(all, ref p, ref w) => (if all { "${...^^...}" } else { "${...^...}" }, p, w),
+
+            RemoveSmallestSuffix(ref p, ref w) => ("${...%...}", p, w),
+            RemoveLargestSuffix(ref p, ref w) => ("${...%%...}", p, w),
+            RemoveSmallestPrefix(ref p, ref w) => ("${...#...}", p, w),
+            RemoveLargestPrefix(ref p, ref w) => ("${...##...}", p, w),
+            ReplaceString(ref p, ref w) => ("${.../...}", p, w),
+            ReplaceStringAll(ref p, ref w) => ("${...//...}", p, w),
+            Substring(ref p, ref w) => ("${...:...}", p, w),
+        };
+
+        self.count(Category::Expansions, key);
+        self.param(param);
+        if let Some(ref word) = *word {
+            self.word(word);
+        }
+    }
+
+    fn arith(&mut self, arith: &DefaultArithmetic) {
+        use crate::ast::Arithmetic::*;
+
+        let key = match *arith {
+            Var(_) => "var",
+            Index(_, ref index) => {
+                self.extension(Extension::ArithmeticIndex);
+                self.arith(index);
+                "index"
+            }
+            Literal(_) => "literal",
+            RadixLiteral(_, radix, _) => {
+                if let Radix::Explicit(_) = radix {
+                    self.extension(Extension::ArithmeticBase);
+                }
+                "radix"
+            }
+
+            PostIncr(ref target) | PostDecr(ref target) | PreIncr(ref target)
+            | PreDecr(ref target) => {
+                self.arith_target(target);
+                match *arith {
+                    PostIncr(_) => "x++",
+                    PostDecr(_) => "x--",
+                    PreIncr(_) => "++x",
+                    _ => "--x",
+                }
+            }
+
+            UnaryPlus(ref e) | UnaryMinus(ref e) | LogicalNot(ref e) | BitwiseNot(ref e) => {
+                self.arith(e);
+                match *arith {
+                    UnaryPlus(_) => "+x",
+                    UnaryMinus(_) => "-x",
+                    LogicalNot(_) => "!",
+                    _ => "~",
+                }
+            }
+
+            Pow(ref l, ref r)
+            | Mult(ref l, ref r)
+            | Div(ref l, ref r)
+            | Modulo(ref l, ref r)
+            | Add(ref l, ref r)
+            | Sub(ref l, ref r)
+            | ShiftLeft(ref l, ref r)
+            | ShiftRight(ref l, ref r)
+            | Less(ref l, ref r)
+            | LessEq(ref l, ref r)
+            | Great(ref l, ref r)
+            | GreatEq(ref l, ref r)
+            | Eq(ref l, ref r)
+            | NotEq(ref l, ref r)
+            | BitwiseAnd(ref l, ref r)
+            | BitwiseXor(ref l, ref r)
+            | BitwiseOr(ref l, ref r)
+            | LogicalAnd(ref l, ref r)
+            | LogicalOr(ref l, ref r) => {
+                self.arith(l);
+                self.arith(r);
+                binary_operator(arith)
+            }
+
+            Ternary(ref guard, ref then, ref els) => {
+                self.arith(guard);
+                self.arith(then);
+                self.arith(els);
+                "?:"
+            }
+            Assign(ref target, ref value) => {
+                self.arith_target(target);
+                self.arith(value);
+                "="
+            }
+            AssignOp(op, ref target, ref value) => {
+                self.arith_target(target);
+                self.arith(value);
+                op.as_str()
+            }
+            Sequence(ref exprs) => {
+                for e in exprs {
+                    self.arith(e);
+                }
+                ","
+            }
+        };
+
+        self.count(Category::Arithmetic, key);
+    }
+
+    fn arith_target(&mut self, target: &ArithmeticTarget<String>) {
+        if let ArithmeticTarget::Index(_, ref index) = *target {
+            self.extension(Extension::ArithmeticIndex);
+            self.arith(index);
+        }
+    }
+}
+
+/// Returns the name of a command if it is a literal, including `[` which is
+/// otherwise parsed as a glob character.
+fn command_name(word: &TopLevelWord<String>) -> Option<String> {
+    let parts = word_parts(word);
+    let brackets = parts
+        .iter()
+        .all(|w| matches!(*w, Word::Simple(SimpleWord::SquareOpen)));
+
+    if brackets {
+        Some("[".repeat(parts.len()))
+    } else {
+        literal_word(word)
+    }
+}
+
+fn binary_operator(arith: &DefaultArithmetic) -> &'static str {
+    match *arith {
+        Arithmetic::Pow(..) => "**",
+        Arithmetic::Mult(..) => "*",
+        Arithmetic::Div(..) => "/",
+        Arithmetic::Modulo(..) => "%",
+        Arithmetic::Add(..) => "+",
+        Arithmetic::Sub(..) => "-",
+        Arithmetic::ShiftLeft(..) => "<<",
+        Arithmetic::ShiftRight(..) => ">>",
+        Arithmetic::Less(..) => "<",
+        Arithmetic::LessEq(..) => "<=",
+        Arithmetic::Great(..) => ">",
+        Arithmetic::GreatEq(..) => ">=",
+        Arithmetic::Eq(..) => "==",
+        Arithmetic::NotEq(..) => "!=",
+        Arithmetic::BitwiseAnd(..) => "&",
+        Arithmetic::BitwiseXor(..) => "^",
+        Arithmetic::BitwiseOr(..) => "|",
+        Arithmetic::LogicalAnd(..) => "&&",
+        Arithmetic::LogicalOr(..) => "||",
+        _ => unreachable!("not a binary operator"),
+    }
+}
@@ -0,0 +1,181 @@
+//! Comparison of how the same source is parsed under different dialects.
+//!
+//! This is useful when migrating scripts between dialects (e.g. from bash to
+//! POSIX sh) in order to find out which constructs will be rejected or
+//! interpreted differently by the target dialect.
+
+use crate::ast::TopLevelCommand;
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, Dialect, ParseError};
+use std::fmt;
+use void::Void;
+
+/// The parse error type produced when parsing with the `DefaultParser`.
+pub type DefaultParseError = ParseError<Void>;
+
+/// A structural difference between parsing the same source under two dialects.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DialectDifference {
+    /// The complete command at the specified index (counting from the start of
+    /// the source) was successfully parsed by both dialects, but differently.
+    Command {
+        /// The index of the complete command within the source.
+        index: usize,
+        /// The command as parsed by the first dialect.
+        left: TopLevelCommand<String>,
+        /// The command as parsed by the second dialect.
+        right: TopLevelCommand<String>,
+    },
+    /// Only one of the dialects parsed a complete command at the specified
+    /// index, while the other reached the end of the source (e.g. because it
+    /// parsed the preceding source as a single command rather than two).
+    /// No further commands are compared after the end of either source.
+    CommandCount {
+        /// The index of the complete command within the source.
+        index: usize,
+        /// The command as parsed by the first dialect, if any.
+        left: Option<TopLevelCommand<String>>,
+        /// The command as parsed by the second dialect, if any.
+        right: Option<TopLevelCommand<String>>,
+    },
+    /// Parsing failed under at least one of the dialects, and did not fail in
+    /// the same way under the other. No further commands are compared after
+    /// an error is encountered.
+    Error {
+        /// The index of the complete command which failed to parse.
+        index: usize,
+        /// The error produced by the first dialect, if any.
+        left: Option<DefaultParseError>,
+        /// The error produced by the second dialect, if any.
+        right: Option<DefaultParseError>,
+    },
+}
+
+impl fmt::Display for DialectDifference {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DialectDifference::Command { index, .. } => {
+                write!(fmt, "command #{} is parsed differently", index + 1)
+            }
+
+            DialectDifference::CommandCount {
+                index, ref left, ..
+            } => {
+                let dialect = if left.is_some() { "first" } else { "second" };
+                write!(
+                    fmt,
+                    "command #{} is only parsed by the {} dialect",
+                    index + 1,
+                    dialect
+                )
+            }
+
+            DialectDifference::Error {
+                index,
+                ref left,
+                ref right,
+            } => {
+                write!(fmt, "command #{}: ", index + 1)?;
+                match (left, right) {
+                    (Some(l), Some(r)) => write!(fmt, "{} (vs. {})", l, r),
+                    (Some(e), None) => write!(fmt, "{} (first dialect only)", e),
+                    (None, Some(e)) => write!(fmt, "{} (second dialect only)", e),
+                    (None, None) => fmt.write_str("no error"),
+                }
+            }
+        }
+    }
+}
+
+/// The result of comparing how a source is parsed under two dialects.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DialectComparison {
+    /// The first dialect which was compared.
+    pub left: Dialect,
+    /// The second dialect which was compared.
+    pub right: Dialect,
+    /// All differences found, in source order.
+    pub differences: Vec<DialectDifference>,
+}
+
+impl DialectComparison {
+    /// Returns `true` if the source is parsed identically by both dialects.
+    pub fn is_identical(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Parses the provided source under two dialects and reports any structural
+/// differences between the two results.
+///
+/// Commands are compared pairwise in source order, until either source is
+/// exhausted or a parse error is encountered by either dialect (since the parser
+/// cannot meaningfully continue past an error).
+pub fn compare(source: &str, left: Dialect, right: Dialect) -> DialectComparison {
+    let mut left_parser = parser(source, left);
+    let mut right_parser = parser(source, right);
+    let mut differences = Vec::new();
+
+    let mut index = 0;
+    loop {
+        let l = left_parser.complete_command();
+        let r = right_parser.complete_command();
+
+        match (l, r) {
+            (Ok(None), Ok(None)) => break,
+
+            (Ok(Some(l)), Ok(Some(r))) => {
+                if l != r {
+                    differences.push(DialectDifference::Command {
+                        index,
+                        left: l,
+                        right: r,
+                    });
+                }
+            }
+
+            (Err(l), Err(r)) => {
+                if l != r {
+                    differences.push(DialectDifference::Error {
+                        index,
+                        left: Some(l),
+                        right: Some(r),
+                    });
+                }
+                break;
+            }
+
+            (Ok(l), Ok(r)) => {
+                differences.push(DialectDifference::CommandCount {
+                    index,
+                    left: l,
+                    right: r,
+                });
+                break;
+            }
+
+            (l, r) => {
+                differences.push(DialectDifference::Error {
+                    index,
+                    left: l.err(),
+                    right: r.err(),
+                });
+                break;
+            }
+        }
+
+        index += 1;
+    }
+
+    DialectComparison {
+        left,
+        right,
+        differences,
+    }
+}
+
+fn parser(source: &str, dialect: Dialect) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_dialect(dialect);
+    parser
+}
@@ -0,0 +1,348 @@
+//! Estimates of how much words may grow when expanded, for reviewing scripts
+//! from untrusted sources for "expansion bombs".
+//!
+//! Two kinds of growth are estimated for every word:
+//!
+//! * Brace expansion (a bash extension) produces the cartesian product of all
+//!   brace groups in a word, e.g. `{a,b}{c,d}` expands to four words, and each
+//!   additional group multiplies their number. Sequences such as `{1..1000000}`
+//!   can be equally large on their own.
+//! * Every parameter or substitution in a word is replaced with its value, so
+//!   a word such as `$x$x$x$x` grows linearly with the number of expansions it
+//!   repeats, which compounds when the value is itself built up this way.
+//!
+//! Only the words of the script itself are estimated: values are never known
+//! statically, and words within command substitutions are not visited.
+
+use super::{display_word, walk_commands, word_parts};
+use super::{Diagnostic, Node, Severity};
+use crate::ast::{
+    Braces, CompoundCommandKind, DefaultWord, Redirect, RedirectOrCmdWord, RedirectOrEnvVar,
+    SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use std::convert::TryFrom;
+
+/// The code reported for words whose brace expansion produces too many words.
+pub const BRACE_BOMB: &str = "expansion-brace-bomb";
+/// The code reported for words repeating too many parameters or substitutions.
+pub const REPEATED_EXPANSION: &str = "expansion-repeated";
+
+/// The thresholds above which the expansion of a word is reported.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Limits {
+    /// The most words a single word may expand to via brace expansion.
+    pub max_fields: u64,
+    /// The most parameters or substitutions a single word may contain.
+    pub max_expansions: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_fields: 10_000,
+            max_expansions: 32,
+        }
+    }
+}
+
+/// The estimated expansion cost of a word.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ExpansionCost {
+    /// The number of words the word expands to via brace expansion
+    /// (saturating at `u64::MAX`), one if it has no brace expansions.
+    pub fields: u64,
+    /// The number of parameters or substitutions in the word (including
+    /// within double quotes), each of which is replaced with its value.
+    pub expansions: usize,
+}
+
+/// A part of a word relevant to brace expansion.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Piece {
+    /// An unquoted literal character.
+    Char(char),
+    /// Anything else which is kept intact by brace expansion, e.g. quoted
+    /// or escaped characters, or parameters.
+    Opaque,
+}
+
+/// Estimates the expansion cost of a word.
+///
+/// ```
+/// use conch_parser::analysis::expansion_cost::word_cost;
+/// use conch_parser::ast::{Command, ListableCommand, PipeableCommand, RedirectOrCmdWord};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::DefaultParser;
+///
+/// let mut parser = DefaultParser::new(Lexer::new("echo {a,b}{1..10}$x$x".chars()));
+/// let cmd = parser.complete_command().unwrap().unwrap();
+/// let word = match cmd.0 {
+///     Command::List(ref list) => match list.first {
+///         ListableCommand::Single(PipeableCommand::Simple(ref cmd)) => {
+///             match cmd.redirects_or_cmd_words[1] {
+///                 RedirectOrCmdWord::CmdWord(ref word) => word.clone(),
+///                 _ => unreachable!(),
+///             }
+///         }
+///         _ => unreachable!(),
+///     },
+///     _ => unreachable!(),
+/// };
+///
+/// let cost = word_cost(&word);
+/// assert_eq!(cost.fields, 20);
+/// assert_eq!(cost.expansions, 2);
+/// ```
+pub fn word_cost(word: &TopLevelWord<String>) -> ExpansionCost {
+    fn simple<P, S>(pieces: &mut Vec<Piece>, expansions: &mut usize, w: &SimpleWord<String, P, S>) {
+        match *w {
+            SimpleWord::Literal(ref s) => pieces.extend(s.chars().map(Piece::Char)),
+            SimpleWord::Colon => pieces.push(Piece::Char(':')),
+            SimpleWord::Param(_) | SimpleWord::Subst(_) => {
+                *expansions += 1;
+                pieces.push(Piece::Opaque);
+            }
+            SimpleWord::Escaped(_)
+            | SimpleWord::Star
+            | SimpleWord::Question
+            | SimpleWord::SquareOpen
+            | SimpleWord::SquareClose
+            | SimpleWord::Tilde
+            | SimpleWord::TildePrefix(_) => pieces.push(Piece::Opaque),
+        }
+    }
+
+    fn part(pieces: &mut Vec<Piece>, expansions: &mut usize, w: &DefaultWord) {
+        match *w {
+            Word::Simple(ref w) => simple(pieces, expansions, w),
+            Word::SingleQuoted(_) => pieces.push(Piece::Opaque),
+            Word::DoubleQuoted(ref words) => {
+                // Quoted braces are not expanded, so only the expansions matter
+                *expansions += words
+                    .iter()
+                    .filter(|w| matches!(w, SimpleWord::Param(_) | SimpleWord::Subst(_)))
+                    .count();
+                pieces.push(Piece::Opaque);
+            }
+            Word::Braces(ref braces) => {
+                // Brace expansions parsed by the parser are estimated
+                // exactly as if they had been left as literals
+                let (step, sequence) = match *braces {
+                    Braces::Alternatives(ref alternatives) => {
+                        pieces.push(Piece::Char('{'));
+                        for (i, words) in alternatives.iter().enumerate() {
+                            if i > 0 {
+                                pieces.push(Piece::Char(','));
+                            }
+                            for w in words {
+                                part(pieces, expansions, w);
+                            }
+                        }
+                        pieces.push(Piece::Char('}'));
+                        return;
+                    }
+                    Braces::Numbers {
+                        start, end, step, ..
+                    } => (step, format!("{}..{}", start, end)),
+                    Braces::Chars { start, end, step } => (step, format!("{}..{}", start, end)),
+                };
+
+                pieces.push(Piece::Char('{'));
+                pieces.extend(sequence.chars().map(Piece::Char));
+                if let Some(step) = step {
+                    pieces.extend(format!("..{}", step).chars().map(Piece::Char));
+                }
+                pieces.push(Piece::Char('}'));
+            }
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut expansions = 0;
+    for w in word_parts(word) {
+        part(&mut pieces, &mut expansions, w);
+    }
+
+    ExpansionCost {
+        fields: sequence(&pieces),
+        expansions,
+    }
+}
+
+/// Returns the number of words a sequence of pieces expands to.
+fn sequence(pieces: &[Piece]) -> u64 {
+    let mut fields = 1u64;
+    let mut i = 0;
+    while i < pieces.len() {
+        if pieces[i] == Piece::Char('{') {
+            if let Some((end, count)) = brace(pieces, i) {
+                fields = fields.saturating_mul(count);
+                i = end + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    fields
+}
+
+/// Returns the position of the closing brace of the brace expansion starting
+/// at `start`, along with the number of words it expands to. Returns `None`
+/// if the brace does not start a valid brace expansion.
+fn brace(pieces: &[Piece], start: usize) -> Option<(usize, u64)> {
+    let mut depth = 0;
+    let mut commas = vec![start];
+    let mut end = None;
+    for (i, piece) in pieces.iter().enumerate().skip(start + 1) {
+        match *piece {
+            Piece::Char('{') => depth += 1,
+            Piece::Char('}') if depth > 0 => depth -= 1,
+            Piece::Char('}') => {
+                end = Some(i);
+                break;
+            }
+            Piece::Char(',') if depth == 0 => commas.push(i),
+            _ => {}
+        }
+    }
+
+    let end = end?;
+    if commas.len() == 1 {
+        return range(&pieces[start + 1..end]).map(|count| (end, count));
+    }
+
+    commas.push(end);
+    let count = commas
+        .windows(2)
+        .map(|w| sequence(&pieces[w[0] + 1..w[1]]))
+        .fold(0u64, u64::saturating_add);
+
+    Some((end, count))
+}
+
+/// Returns the number of words a sequence expression (e.g. `1..10`, `a..z`,
+/// or `0..100..5`) expands to, or `None` if it is not a valid sequence.
+fn range(pieces: &[Piece]) -> Option<u64> {
+    let text = pieces
+        .iter()
+        .map(|piece| match *piece {
+            Piece::Char(c) => Some(c),
+            Piece::Opaque => None,
+        })
+        .collect::<Option<String>>()?;
+
+    let parts = text.split("..").collect::<Vec<_>>();
+    let (from, to, step) = match parts[..] {
+        [from, to] => (from, to, 1),
+        [from, to, step] => (from, to, step.parse::<i128>().ok()?.unsigned_abs().max(1)),
+        _ => return None,
+    };
+
+    // Both bounds must either be integers, or single letters
+    let bound = |s: &str| match s.parse::<i128>() {
+        Ok(n) => Some((false, n)),
+        Err(_) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => Some((true, i128::from(c as u8))),
+                _ => None,
+            }
+        }
+    };
+
+    let ((from_letter, from), (to_letter, to)) = (bound(from)?, bound(to)?);
+    if from_letter != to_letter {
+        return None;
+    }
+
+    let span = (to - from).unsigned_abs();
+    Some(u64::try_from(span / step + 1).unwrap_or(u64::MAX))
+}
+
+/// Reports any words whose expansion cost exceeds the default `Limits`.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    check_with_limits(cmds, Limits::default())
+}
+
+/// Reports any words whose expansion cost exceeds the specified limits.
+///
+/// The words of commands, assignments, redirections, `for` loops, and `case`
+/// commands are all estimated.
+pub fn check_with_limits(cmds: &[TopLevelCommand<String>], limits: Limits) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    walk_commands(cmds, &mut |node| {
+        let mut words = Vec::new();
+        match node {
+            Node::Simple(cmd) => {
+                for item in &cmd.redirects_or_env_vars {
+                    match *item {
+                        RedirectOrEnvVar::EnvVar(_, Some(ref word)) => words.push(word),
+                        RedirectOrEnvVar::Redirect(ref redirect) => {
+                            words.extend(redirect_word(redirect))
+                        }
+                        RedirectOrEnvVar::EnvVar(_, None) => {}
+                    }
+                }
+
+                for item in &cmd.redirects_or_cmd_words {
+                    match *item {
+                        RedirectOrCmdWord::CmdWord(ref word) => words.push(word),
+                        RedirectOrCmdWord::Redirect(ref redirect) => {
+                            words.extend(redirect_word(redirect))
+                        }
+                    }
+                }
+            }
+
+            Node::Compound(cmd) => match cmd.kind {
+                CompoundCommandKind::For {
+                    words: Some(ref for_words),
+                    ..
+                } => words.extend(for_words),
+                CompoundCommandKind::Case { ref word, .. } => words.push(word),
+                _ => {}
+            },
+
+            Node::FunctionDef => {}
+        }
+
+        for word in words {
+            check_word(word, limits, &mut diagnostics);
+        }
+    });
+
+    diagnostics
+}
+
+/// Returns the word of a redirect which is subject to expansion.
+///
+/// Heredoc bodies are not subject to brace expansion, and commonly contain
+/// many more expansions than any single word would, so they are skipped.
+fn redirect_word(redirect: &Redirect<TopLevelWord<String>>) -> Option<&TopLevelWord<String>> {
+    match *redirect {
+        Redirect::Heredoc(..) => None,
+        _ => Some(redirect.word()),
+    }
+}
+
+fn check_word(word: &TopLevelWord<String>, limits: Limits, diagnostics: &mut Vec<Diagnostic>) {
+    let cost = word_cost(word);
+    if cost.fields > limits.max_fields {
+        let msg = format!(
+            "brace expansion of `{}` produces {} words",
+            display_word(word),
+            cost.fields
+        );
+        diagnostics.push(Diagnostic::new(Severity::Warning, BRACE_BOMB, msg));
+    }
+
+    if cost.expansions > limits.max_expansions {
+        let msg = format!(
+            "`{}` repeats {} expansions, and grows with the length of each value",
+            display_word(word),
+            cost.expansions
+        );
+        diagnostics.push(Diagnostic::new(Severity::Warning, REPEATED_EXPANSION, msg));
+    }
+}
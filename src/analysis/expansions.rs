@@ -0,0 +1,576 @@
+//! Enumeration of every expansion in a script, in the order the shell evaluates them.
+//!
+//! A simple command is evaluated as POSIX specifies: the command name and
+//! arguments are expanded first, followed by the targets of any redirections,
+//! and finally the values of any variable assignments. Within a word, tilde
+//! expansion comes first, followed by parameter expansions and substitutions
+//! from left to right, and finally pathname expansion of the resulting fields.
+//!
+//! The redirections of a compound command are performed before any of the
+//! commands within it are run, and the word of a `for` or `case` command is
+//! expanded before its body (followed by each `case` pattern in turn).
+//!
+//! Expansions nested within another expansion (e.g. the `$y` of `${x:-$y}`,
+//! or the commands of a `$(...)`) are enumerated immediately after the
+//! expansion which encloses them. Since the script is never executed, every
+//! expansion is enumerated exactly once: the bodies of loops are visited a
+//! single time, the bodies of functions are visited where they are declared,
+//! and expansions which may be skipped at runtime (e.g. the `$y` of
+//! `${x:-$y}`, or the body of an `if` whose guard fails) are still included.
+
+use super::word_parts;
+use crate::ast::{
+    AndOr, Command, CompoundCommandKind, DefaultArithmetic, DefaultCompoundCommand,
+    DefaultListableCommand, DefaultParameter, DefaultParameterSubstitution, DefaultPipeableCommand,
+    DefaultRedirect, DefaultSimpleCommand, DefaultSimpleWord, DefaultWord, ListableCommand,
+    Parameter, ParameterSubstitution, PipeableCommand, RedirectOrCmdWord, RedirectOrEnvVar,
+    SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+
+/// The kinds of expansions which can be enumerated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExpansionKind<'a> {
+    /// Tilde expansion, e.g. `~` or `~user`.
+    Tilde,
+    /// A parameter, e.g. `$x`, `${x}`, or `$@`.
+    Parameter(&'a DefaultParameter),
+    /// A parameter substitution, e.g. `${x:-word}` or `${#x}`. This includes
+    /// `$(< file)`, whose file is the word of the substitution.
+    ParameterSubstitution(&'a DefaultParameterSubstitution),
+    /// A command substitution, e.g. `$(cmd)` or `` `cmd` ``.
+    CommandSubstitution(&'a [TopLevelCommand<String>]),
+    /// A process substitution, e.g. `<(cmd)` or `>(cmd)`, holding either a
+    /// `ParameterSubstitution::ProcessRead` or `ParameterSubstitution::ProcessWrite`.
+    ProcessSubstitution(&'a DefaultParameterSubstitution),
+    /// An arithmetic expansion, e.g. `$(( x + 1 ))`, or `None` if it is empty.
+    Arithmetic(Option<&'a DefaultArithmetic>),
+    /// Pathname expansion of a word containing unquoted `*`, `?`, or `[`.
+    ///
+    /// Unquoted parameters and substitutions in command words are subject to
+    /// pathname expansion as well, regardless of whether one is reported here.
+    Pathname,
+}
+
+/// Where the word containing an expansion appears.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WordContext<'a> {
+    /// The command name (at index zero) or an argument of a simple command.
+    Argument(usize),
+    /// The value assigned to a variable, e.g. the `$y` in `x=$y cmd`.
+    Assignment(&'a str),
+    /// The target of a redirection, or the body of a heredoc.
+    Redirect(&'a DefaultRedirect),
+    /// One of the words a `for` loop iterates over, along with the loop's variable.
+    ForWord(&'a str),
+    /// The word matched by a `case` command.
+    CaseWord,
+    /// One of the patterns of a `case` arm.
+    CasePattern,
+    /// An operand of a `[[ ... ]]` conditional command.
+    Conditional,
+}
+
+impl WordContext<'_> {
+    /// Indicates if words in this context are split into fields and
+    /// subject to pathname expansion.
+    pub fn is_split(&self) -> bool {
+        match *self {
+            WordContext::Argument(_) | WordContext::ForWord(_) => true,
+            WordContext::Assignment(_)
+            | WordContext::Redirect(_)
+            | WordContext::CaseWord
+            | WordContext::CasePattern
+            | WordContext::Conditional => false,
+        }
+    }
+}
+
+/// A single expansion, along with the context it appears in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Expansion<'a> {
+    /// The kind of the expansion.
+    pub kind: ExpansionKind<'a>,
+    /// The word the expansion appears in. For expansions within a parameter
+    /// substitution, this is the word containing the substitution, while words
+    /// within a command substitution are part of their own commands.
+    pub word: &'a TopLevelWord<String>,
+    /// Where the word appears.
+    pub context: WordContext<'a>,
+    /// Indicates if the expansion appears within double quotes, and its
+    /// result is therefore never split into fields or pathname expanded.
+    pub quoted: bool,
+    /// The number of expansions enclosing this one, zero if it appears
+    /// directly within a word of the script.
+    pub depth: usize,
+}
+
+/// The result of expanding `$@` or `$*`, see `Expansion::positional_parameters`.
+///
+/// Any text surrounding the expansion within its word (e.g. the `x` and `y` of
+/// `x"$@"y`) is attached to the first and last fields, respectively.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PositionalExpansion {
+    /// The parameters expand into separate fields, of which there may be none.
+    Fields(Vec<String>),
+    /// The parameters are joined into a single string.
+    Joined(String),
+}
+
+impl PositionalExpansion {
+    /// Returns the resulting fields, where a joined string is a single field.
+    pub fn into_fields(self) -> Vec<String> {
+        match self {
+            PositionalExpansion::Fields(fields) => fields,
+            PositionalExpansion::Joined(s) => vec![s],
+        }
+    }
+}
+
+impl Expansion<'_> {
+    /// Expands `$@` or `$*` (including `${@}` and `${*}`) with the specified
+    /// positional parameters and value of `IFS` (or `None` if it is unset),
+    /// or returns `None` if this is an expansion of anything else.
+    ///
+    /// In words which are split into fields (see `WordContext::is_split`):
+    ///
+    /// * `"$@"` expands to one field per parameter, or no fields at all.
+    /// * `"$*"` expands to a single field of the parameters joined by the
+    ///   first character of `IFS` (a space if unset, nothing if empty).
+    /// * `$@` and `$*` expand to every (non-empty) parameter split into fields
+    ///   by `IFS` (see `split_fields`), which is usually a mistake.
+    ///
+    /// In any other word (e.g. the value of an assignment), both quoted and
+    /// unquoted `$*` are joined as described above, while `$@` is always
+    /// joined by a space, as bash does (POSIX leaves this unspecified).
+    ///
+    /// ```
+    /// use conch_parser::analysis::expansions::{expansions, PositionalExpansion};
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let cmds = DefaultParser::new(Lexer::new("cp \"$@\" $@ \"$*\"".chars()))
+    ///     .into_iter()
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// let args = ["my file", "b"];
+    /// let expanded = expansions(&cmds)
+    ///     .filter_map(|e| e.positional_parameters(&args, Some(" \t\n")))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     expanded,
+    ///     vec![
+    ///         PositionalExpansion::Fields(vec!["my file".into(), "b".into()]),
+    ///         PositionalExpansion::Fields(vec!["my".into(), "file".into(), "b".into()]),
+    ///         PositionalExpansion::Joined("my file b".into()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn positional_parameters(
+        &self,
+        args: &[&str],
+        ifs: Option<&str>,
+    ) -> Option<PositionalExpansion> {
+        let star = match self.kind {
+            ExpansionKind::Parameter(&Parameter::At) => false,
+            ExpansionKind::Parameter(&Parameter::Star) => true,
+            _ => return None,
+        };
+
+        let joined = |separator: Option<char>| {
+            let mut joined = String::new();
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    joined.extend(separator);
+                }
+                joined.push_str(arg);
+            }
+            PositionalExpansion::Joined(joined)
+        };
+
+        let expanded = if !self.context.is_split() {
+            joined(if star { join_separator(ifs) } else { Some(' ') })
+        } else if !self.quoted {
+            PositionalExpansion::Fields(
+                args.iter().flat_map(|arg| split_fields(arg, ifs)).collect(),
+            )
+        } else if star {
+            joined(join_separator(ifs))
+        } else {
+            PositionalExpansion::Fields(args.iter().map(|&arg| arg.to_owned()).collect())
+        };
+
+        Some(expanded)
+    }
+}
+
+/// Returns the character which joins the fields of `"$*"` for a value of `IFS`.
+fn join_separator(ifs: Option<&str>) -> Option<char> {
+    match ifs {
+        Some(ifs) => ifs.chars().next(),
+        None => Some(' '),
+    }
+}
+
+/// Splits the result of an unquoted expansion into fields, as the shell would
+/// with the specified value of `IFS` (or `None` if it is unset).
+///
+/// Runs of `IFS` whitespace (spaces, tabs, and newlines) separate fields, and
+/// are ignored at the start and end of the value. Every other `IFS` character
+/// (along with any adjacent `IFS` whitespace) separates fields on its own, so
+/// that consecutive ones delimit empty fields. No field is split if `IFS` is
+/// empty, in which case only an empty value results in no fields at all.
+///
+/// ```
+/// use conch_parser::analysis::expansions::split_fields;
+///
+/// assert_eq!(split_fields("  a  b ", None), vec!["a", "b"]);
+/// assert_eq!(split_fields("a::b:", Some(":")), vec!["a", "", "b"]);
+/// assert_eq!(split_fields("a : b", Some(" :")), vec!["a", "b"]);
+/// assert_eq!(split_fields("a b", Some("")), vec!["a b"]);
+/// ```
+pub fn split_fields(value: &str, ifs: Option<&str>) -> Vec<String> {
+    let ifs = ifs.unwrap_or(" \t\n");
+    let is_delim = |c: char| ifs.contains(c);
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\n') && is_delim(c);
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut rest = value.trim_start_matches(is_space);
+
+    while let Some(c) = rest.chars().next() {
+        if !is_delim(c) {
+            field.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        // At most one other delimiter (surrounded by any whitespace) ends the field
+        let after_space = rest.trim_start_matches(is_space);
+        rest = match after_space.chars().next() {
+            Some(d) if is_delim(d) => &after_space[d.len_utf8()..],
+            _ => after_space,
+        }
+        .trim_start_matches(is_space);
+        fields.push(std::mem::take(&mut field));
+    }
+
+    if !field.is_empty() {
+        fields.push(field);
+    }
+    fields
+}
+
+/// Returns every expansion in the specified commands (recursing into compound
+/// commands, function bodies, and substitutions), in evaluation order.
+///
+/// ```
+/// use conch_parser::analysis::expansions::{expansions, ExpansionKind, WordContext};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::DefaultParser;
+///
+/// let cmds = DefaultParser::new(Lexer::new("x=$a echo ~/$b *.txt >\"$c\"".chars()))
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// let kinds = expansions(&cmds)
+///     .map(|e| match (e.kind, e.context) {
+///         (ExpansionKind::Tilde, _) => String::from("~"),
+///         (ExpansionKind::Parameter(p), _) if e.quoted => format!("\"{}\"", p),
+///         (ExpansionKind::Parameter(p), _) => p.to_string(),
+///         (ExpansionKind::Pathname, WordContext::Argument(i)) => format!("glob({})", i),
+///         _ => unreachable!(),
+///     })
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(kinds, vec!["~", "${b}", "glob(2)", "\"${c}\"", "${a}"]);
+/// ```
+pub fn expansions(cmds: &[TopLevelCommand<String>]) -> impl Iterator<Item = Expansion<'_>> {
+    let mut collector = Collector {
+        expansions: Vec::new(),
+        depth: 0,
+    };
+    collector.commands(cmds);
+    collector.expansions.into_iter()
+}
+
+#[derive(Debug)]
+struct Collector<'a> {
+    expansions: Vec<Expansion<'a>>,
+    /// The number of expansions enclosing the commands currently visited.
+    depth: usize,
+}
+
+impl<'a> Collector<'a> {
+    fn commands(&mut self, cmds: &'a [TopLevelCommand<String>]) {
+        for cmd in cmds {
+            let list = match cmd.0 {
+                Command::Job(ref list) | Command::List(ref list) => list,
+                Command::Unparsed(_) => continue,
+            };
+
+            self.listable(&list.first);
+            for and_or in &list.rest {
+                match *and_or {
+                    AndOr::And(ref cmd) | AndOr::Or(ref cmd) => self.listable(cmd),
+                }
+            }
+        }
+    }
+
+    fn listable(&mut self, cmd: &'a DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                for cmd in pipeline.commands() {
+                    self.pipeable(cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &'a DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(_, _, ref body) => self.compound(body),
+        }
+    }
+
+    fn simple(&mut self, cmd: &'a DefaultSimpleCommand) {
+        let mut redirects = Vec::new();
+        let mut assignments = Vec::new();
+        for item in &cmd.redirects_or_env_vars {
+            match *item {
+                RedirectOrEnvVar::Redirect(ref redirect) => redirects.push(redirect),
+                RedirectOrEnvVar::EnvVar(ref name, Some(ref word)) => {
+                    assignments.push((name, word))
+                }
+                RedirectOrEnvVar::EnvVar(_, None) => {}
+            }
+        }
+
+        let mut index = 0;
+        for item in &cmd.redirects_or_cmd_words {
+            match *item {
+                RedirectOrCmdWord::Redirect(ref redirect) => redirects.push(redirect),
+                RedirectOrCmdWord::CmdWord(ref word) => {
+                    self.word(word, WordContext::Argument(index));
+                    index += 1;
+                }
+            }
+        }
+
+        for redirect in redirects {
+            self.redirect(redirect);
+        }
+
+        for (name, word) in assignments {
+            self.word(word, WordContext::Assignment(name));
+        }
+    }
+
+    fn redirect(&mut self, redirect: &'a DefaultRedirect) {
+        self.word(redirect.word(), WordContext::Redirect(redirect));
+    }
+
+    fn compound(&mut self, cmd: &'a DefaultCompoundCommand) {
+        for redirect in &cmd.io {
+            self.redirect(redirect);
+        }
+
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+                self.commands(cmds)
+            }
+
+            CompoundCommandKind::While(ref gbp) | CompoundCommandKind::Until(ref gbp) => {
+                self.commands(&gbp.guard);
+                self.commands(&gbp.body);
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                for gbp in conditionals {
+                    self.commands(&gbp.guard);
+                    self.commands(&gbp.body);
+                }
+
+                if let Some(ref els) = *else_branch {
+                    self.commands(els);
+                }
+            }
+
+            CompoundCommandKind::For {
+                ref var,
+                ref words,
+                ref body,
+            } => {
+                for word in words.iter().flatten() {
+                    self.word(word, WordContext::ForWord(var));
+                }
+                self.commands(body);
+            }
+
+            CompoundCommandKind::Case { ref word, ref arms } => {
+                self.word(word, WordContext::CaseWord);
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        self.word(pattern, WordContext::CasePattern);
+                    }
+                    self.commands(&arm.body);
+                }
+            }
+
+            CompoundCommandKind::Conditional(ref cond) => {
+                for word in cond.words() {
+                    self.word(word, WordContext::Conditional);
+                }
+            }
+
+            CompoundCommandKind::Arithmetic(_) => {}
+        }
+    }
+
+    fn word(&mut self, word: &'a TopLevelWord<String>, context: WordContext<'a>) {
+        let parts = word_parts(word);
+        let is_assignment = matches!(context, WordContext::Assignment(_));
+
+        // Tildes are only expanded at the start of a word, or after
+        // any unquoted `:` within the value of an assignment
+        let mut tilde_allowed = true;
+        for part in parts {
+            // Tilde prefixes which were marked by the parser are always expanded
+            let is_prefix = matches!(*part, Word::Simple(SimpleWord::TildePrefix(_)));
+            let is_tilde = matches!(*part, Word::Simple(SimpleWord::Tilde));
+            if is_prefix || (is_tilde && tilde_allowed) {
+                self.push(ExpansionKind::Tilde, word, context, false, 0);
+            }
+
+            tilde_allowed = is_assignment && matches!(*part, Word::Simple(SimpleWord::Colon));
+        }
+
+        for part in parts {
+            self.word_part(part, word, context, false, 0);
+        }
+
+        fn has_glob(part: &DefaultWord) -> bool {
+            match *part {
+                Word::Simple(ref w) => w.is_glob(),
+                Word::Braces(ref braces) => braces.words().any(has_glob),
+                Word::DoubleQuoted(_) | Word::SingleQuoted(_) => false,
+            }
+        }
+
+        let has_glob = parts.iter().any(has_glob);
+
+        if has_glob && context.is_split() {
+            self.push(ExpansionKind::Pathname, word, context, false, 0);
+        }
+    }
+
+    fn word_part(
+        &mut self,
+        part: &'a DefaultWord,
+        word: &'a TopLevelWord<String>,
+        context: WordContext<'a>,
+        quoted: bool,
+        depth: usize,
+    ) {
+        match *part {
+            Word::Simple(ref w) => self.simple_word(w, word, context, quoted, depth),
+            Word::DoubleQuoted(ref words) => {
+                for w in words {
+                    self.simple_word(w, word, context, true, depth);
+                }
+            }
+            Word::SingleQuoted(_) => {}
+            Word::Braces(ref braces) => {
+                for part in braces.words() {
+                    self.word_part(part, word, context, quoted, depth);
+                }
+            }
+        }
+    }
+
+    fn simple_word(
+        &mut self,
+        w: &'a DefaultSimpleWord,
+        word: &'a TopLevelWord<String>,
+        context: WordContext<'a>,
+        quoted: bool,
+        depth: usize,
+    ) {
+        match *w {
+            SimpleWord::Param(ref p) => {
+                self.push(ExpansionKind::Parameter(p), word, context, quoted, depth)
+            }
+            SimpleWord::Subst(ref subst) => {
+                self.subst(subst, word, context, quoted, depth);
+            }
+            SimpleWord::Literal(_)
+            | SimpleWord::Escaped(_)
+            | SimpleWord::Star
+            | SimpleWord::Question
+            | SimpleWord::SquareOpen
+            | SimpleWord::SquareClose
+            | SimpleWord::Tilde
+            | SimpleWord::TildePrefix(_)
+            | SimpleWord::Colon => {}
+        }
+    }
+
+    fn subst(
+        &mut self,
+        subst: &'a DefaultParameterSubstitution,
+        word: &'a TopLevelWord<String>,
+        context: WordContext<'a>,
+        quoted: bool,
+        depth: usize,
+    ) {
+        let kind = match *subst {
+            ParameterSubstitution::Command(ref cmds) => ExpansionKind::CommandSubstitution(cmds),
+            ParameterSubstitution::Arith(ref arith) => ExpansionKind::Arithmetic(arith.as_ref()),
+            ParameterSubstitution::ProcessRead(_) | ParameterSubstitution::ProcessWrite(_) => {
+                ExpansionKind::ProcessSubstitution(subst)
+            }
+            _ => ExpansionKind::ParameterSubstitution(subst),
+        };
+        self.push(kind, word, context, quoted, depth);
+
+        if let Some(cmds) = subst.commands() {
+            // Words within the substitution are quoted independently of it
+            let outer = self.depth;
+            self.depth += depth + 1;
+            self.commands(cmds);
+            self.depth = outer;
+        }
+
+        if let Some(inner) = subst.word() {
+            for part in word_parts(inner) {
+                self.word_part(part, word, context, quoted, depth + 1);
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        kind: ExpansionKind<'a>,
+        word: &'a TopLevelWord<String>,
+        context: WordContext<'a>,
+        quoted: bool,
+        depth: usize,
+    ) {
+        self.expansions.push(Expansion {
+            kind,
+            word,
+            context,
+            quoted,
+            depth: self.depth + depth,
+        });
+    }
+}
@@ -0,0 +1,400 @@
+//! Explanations of the syntax found at a location within a script.
+//!
+//! `explain` answers "what is this syntax?" for the innermost construct which
+//! encloses a span of the source: which kind of AST node it becomes, which
+//! section of the POSIX shell grammar (if any) defines it, any extensions of
+//! the POSIX grammar it makes use of, and thus which dialects accept it.
+//!
+//! Every construct is described by an entry of a static metadata table (see
+//! `CONSTRUCTS`), whose stable `id`s and POSIX sections can be used by
+//! documentation tooling to link to further reading. Whether an individual
+//! node uses an extension is determined per node, e.g. a `case` command is
+//! part of the POSIX grammar, but one with a `;&` arm is not.
+//!
+//! The source is parsed with the (most permissive) bash dialect. Words are
+//! explained as a whole, unless the span falls within a `${...}` or `$((...))`
+//! substitution, which are explained on their own (along with any words nested
+//! within them, although not any nested commands). If the source fails to
+//! parse, only the constructs parsed before the error can be explained.
+
+use crate::ast::builder::{ComplexWordKind, Event, EventLog, LoopKind, RedirectKind};
+use crate::ast::builder::{SeparatorKind, SimpleWordKind, WordKind};
+use crate::ast::visit::{walk_arithmetic, Visitor};
+use crate::ast::{Arithmetic, ArithmeticTarget, CaseTerminator, DefaultArithmetic, Radix, Span};
+use crate::lexer::Lexer;
+use crate::parse::{Dialect, Extension, Parser};
+use std::fmt;
+
+/// Static metadata describing a kind of construct, see `CONSTRUCTS`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Construct {
+    /// A short, stable identifier of the construct, e.g. `"for-loop"`.
+    pub id: &'static str,
+    /// A human readable name of the construct, e.g. `"for loop"`.
+    pub name: &'static str,
+    /// The AST node the construct is parsed into, e.g. `"CompoundCommandKind::For"`.
+    pub node: &'static str,
+    /// The section of the POSIX shell command language which defines the
+    /// construct (e.g. `"2.9.4.2"`), or `None` if it is not part of POSIX.
+    pub posix_section: Option<&'static str>,
+    /// The extension of the POSIX grammar which introduces the construct, if any.
+    pub extension: Option<Extension>,
+}
+
+impl Construct {
+    /// Returns a link to the section of the POSIX standard which defines the construct, if any.
+    ///
+    /// ```
+    /// use conch_parser::analysis::explain::construct;
+    ///
+    /// assert_eq!(
+    ///     construct("for-loop").unwrap().posix_url().unwrap(),
+    ///     "https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_02"
+    /// );
+    /// assert_eq!(construct("conditional-command").unwrap().posix_url(), None);
+    /// ```
+    pub fn posix_url(&self) -> Option<String> {
+        let section = self.posix_section?;
+        let tag = section
+            .split('.')
+            .skip(1)
+            .map(|n| format!("_{:02}", n.parse::<u8>().unwrap_or(0)))
+            .collect::<String>();
+
+        Some(format!(
+            "https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18{}",
+            tag
+        ))
+    }
+}
+
+macro_rules! constructs {
+    ($($id:expr, $name:expr, $node:expr, $section:expr, $ext:expr;)*) => {
+        &[$(Construct {
+            id: $id,
+            name: $name,
+            node: $node,
+            posix_section: $section,
+            extension: $ext,
+        }),*]
+    };
+}
+
+/// The metadata of every construct which can be explained.
+pub const CONSTRUCTS: &[Construct] = constructs! {
+    "simple-command", "simple command", "SimpleCommand", Some("2.9.1"), None;
+    "pipeline", "pipeline", "ListableCommand::Pipe", Some("2.9.2"), None;
+    "and-or-list", "AND-OR list", "AndOrList", Some("2.9.3"), None;
+    "async-list", "asynchronous list", "Command::Job", Some("2.9.3"), None;
+    "brace-group", "brace group", "CompoundCommandKind::Brace", Some("2.9.4.1"), None;
+    "subshell", "subshell", "CompoundCommandKind::Subshell", Some("2.9.4.1"), None;
+    "for-loop", "for loop", "CompoundCommandKind::For", Some("2.9.4.2"), None;
+    "case", "case command", "CompoundCommandKind::Case", Some("2.9.4.3"), None;
+    "if", "if command", "CompoundCommandKind::If", Some("2.9.4.4"), None;
+    "while-loop", "while loop", "CompoundCommandKind::While", Some("2.9.4.5"), None;
+    "until-loop", "until loop", "CompoundCommandKind::Until", Some("2.9.4.6"), None;
+    "conditional-command", "conditional command", "CompoundCommandKind::Conditional", None,
+        Some(Extension::DoubleBracket);
+    "function-definition", "function definition", "PipeableCommand::FunctionDef",
+        Some("2.9.5"), None;
+    "redirect-input", "input redirection", "Redirect::Read", Some("2.7.1"), None;
+    "redirect-output", "output redirection", "Redirect::Write", Some("2.7.2"), None;
+    "redirect-append", "appending redirection", "Redirect::Append", Some("2.7.3"), None;
+    "heredoc", "here-document", "Redirect::Heredoc", Some("2.7.4"), None;
+    "dup-input", "input duplication", "Redirect::DupRead", Some("2.7.5"), None;
+    "dup-output", "output duplication", "Redirect::DupWrite", Some("2.7.6"), None;
+    "redirect-read-write", "read-write redirection", "Redirect::ReadWrite", Some("2.7.7"), None;
+    "redirect-both", "standard output and error redirection", "Redirect::WriteBoth", None,
+        Some(Extension::RedirectBoth);
+    "word", "word", "TopLevelWord", Some("2.6"), None;
+    "single-quotes", "single-quoted string", "Word::SingleQuoted", Some("2.2.2"), None;
+    "double-quotes", "double-quoted string", "Word::DoubleQuoted", Some("2.2.3"), None;
+    "brace-expansion", "brace expansion", "Word::Braces", None, None;
+    "tilde-expansion", "tilde expansion", "SimpleWord::Tilde", Some("2.6.1"), None;
+    "parameter", "parameter expansion", "SimpleWord::Param", Some("2.6.2"), None;
+    "parameter-substitution", "parameter substitution", "SimpleWord::Subst", Some("2.6.2"), None;
+    "parameter-substitution-extension", "parameter substitution", "SimpleWord::Subst", None,
+        None;
+    "command-substitution", "command substitution", "ParameterSubstitution::Command",
+        Some("2.6.3"), None;
+    "read-file", "file contents substitution", "ParameterSubstitution::ReadFile", None, None;
+    "arithmetic-expansion", "arithmetic expansion", "ParameterSubstitution::Arith",
+        Some("2.6.4"), None;
+    "process-read", "process substitution (reading)", "ParameterSubstitution::ProcessRead", None,
+        Some(Extension::ProcessRead);
+    "process-write", "process substitution (writing)", "ParameterSubstitution::ProcessWrite",
+        None, Some(Extension::ProcessWrite);
+};
+
+/// Returns the metadata of a construct by its `id`.
+pub fn construct(id: &str) -> Option<&'static Construct> {
+    CONSTRUCTS.iter().find(|c| c.id == id)
+}
+
+/// What the construct at a location within a script is, see `explain`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Explanation {
+    /// The metadata of the construct.
+    pub construct: &'static Construct,
+    /// The location of the construct within the source.
+    pub span: Span,
+    /// Every extension of the POSIX grammar the construct uses, including the
+    /// one which introduces the construct itself, if any.
+    pub extensions: Vec<Extension>,
+    /// The dialects which accept the construct as it was written.
+    pub dialects: Vec<Dialect>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.construct.name)?;
+        match self.construct.posix_section {
+            Some(section) if self.extensions.is_empty() => write!(fmt, " (POSIX {})", section),
+            None if self.extensions.is_empty() => fmt.write_str(" (not part of POSIX)"),
+            _ => {
+                let exts = self.extensions.iter().map(|ext| format!("`{}`", ext));
+                write!(fmt, " (extension: {})", exts.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+/// Explains the innermost construct of the source which encloses a span, or
+/// returns `None` if there is no such construct (e.g. within a comment).
+///
+/// An empty span explains the construct which starts at (or contains) its position.
+///
+/// ```
+/// use conch_parser::analysis::explain::explain;
+/// use conch_parser::ast::Span;
+/// use conch_parser::parse::{Dialect, Extension, SourcePos};
+///
+/// let src = "a |& b; case $x in y) z;& esac";
+/// let at = |byte| {
+///     let pos = SourcePos { byte, line: 1, col: byte + 1 };
+///     Span { start: pos, end: pos }
+/// };
+///
+/// let pipeline = explain(src, at(2)).unwrap();
+/// assert_eq!(pipeline.construct.id, "pipeline");
+/// assert_eq!(pipeline.extensions, vec![Extension::PipeAmp]);
+/// assert_eq!(pipeline.dialects, vec![Dialect::Bash]);
+/// assert_eq!(pipeline.to_string(), "pipeline (extension: `|&`)");
+///
+/// let word = explain(src, at(13)).unwrap();
+/// assert_eq!(word.construct.id, "parameter");
+/// assert_eq!(word.to_string(), "parameter expansion (POSIX 2.6.2)");
+///
+/// let case = explain(src, at(8)).unwrap();
+/// assert_eq!(case.construct.id, "case");
+/// assert_eq!(case.dialects, vec![Dialect::Bash, Dialect::Mksh]);
+/// ```
+pub fn explain(source: &str, span: Span) -> Option<Explanation> {
+    let mut log = EventLog::new();
+    // Any constructs parsed before an error can still be explained
+    let _ = Parser::with_builder(Lexer::new(source.chars()), &mut log).parse_all();
+
+    let encloses = |node: &Span| {
+        node.start.byte <= span.start.byte
+            && span.end.byte <= node.end.byte
+            && (span.start.byte < node.end.byte || span.end.byte > span.start.byte)
+    };
+    let len = |node: &Span| node.end.byte - node.start.byte;
+
+    // Nested nodes are built before the nodes enclosing them, thus the first
+    // of several nodes with the same span is the innermost one
+    let mut best: Option<(&'static str, Span, Vec<Extension>)> = None;
+    for event in log.events() {
+        let (id, node_span, extensions) = match classify(source, event) {
+            Some(classified) => classified,
+            None => continue,
+        };
+
+        let innermost = match best {
+            Some((_, ref best_span, _)) => len(&node_span) < len(best_span),
+            None => true,
+        };
+
+        if innermost && encloses(&node_span) {
+            best = Some((id, node_span, extensions));
+        }
+    }
+
+    let (id, span, mut extensions) = best?;
+    let construct = construct(id).expect("every classified construct has metadata");
+    if let Some(ext) = construct.extension {
+        extensions.insert(0, ext);
+    }
+
+    let dialects = [Dialect::Posix, Dialect::Bash, Dialect::Mksh]
+        .iter()
+        .cloned()
+        .filter(|d| extensions.iter().all(|&ext| d.supports(ext)))
+        .collect();
+
+    Some(Explanation {
+        construct,
+        span,
+        extensions,
+        dialects,
+    })
+}
+
+/// Determines the construct built by an event, along with its span and any
+/// extensions it uses (other than the one which introduces the construct).
+fn classify(source: &str, event: &Event) -> Option<(&'static str, Span, Vec<Extension>)> {
+    let mut extensions = Vec::new();
+
+    let (id, span) = match *event {
+        Event::CompleteCommand {
+            separator: SeparatorKind::Amp,
+            span,
+            ..
+        } => ("async-list", span),
+        Event::AndOrList { ref rest, span, .. } if !rest.is_empty() => ("and-or-list", span),
+        Event::Pipeline {
+            bang,
+            ref cmds,
+            span,
+        } if bang || cmds.len() > 1 => {
+            if cmds.iter().any(|(_, segment)| segment.pipe_stderr) {
+                extensions.push(Extension::PipeAmp);
+            }
+            ("pipeline", span)
+        }
+        Event::SimpleCommand { span, .. } => ("simple-command", span),
+        Event::BraceGroup { span, .. } => ("brace-group", span),
+        Event::Subshell { span, .. } => ("subshell", span),
+        Event::LoopCommand {
+            kind: LoopKind::While,
+            span,
+            ..
+        } => ("while-loop", span),
+        Event::LoopCommand {
+            kind: LoopKind::Until,
+            span,
+            ..
+        } => ("until-loop", span),
+        Event::IfCommand { span, .. } => ("if", span),
+        Event::ForCommand { span, .. } => ("for-loop", span),
+        Event::CaseCommand {
+            ref fragments,
+            span,
+            ..
+        } => {
+            for arm in &fragments.arms {
+                let ext = match arm.terminator {
+                    CaseTerminator::Break => continue,
+                    CaseTerminator::FallThrough => Extension::CaseFallThrough,
+                    CaseTerminator::Continue => Extension::CaseContinue,
+                };
+                if !extensions.contains(&ext) {
+                    extensions.push(ext);
+                }
+            }
+            ("case", span)
+        }
+        Event::ConditionalCommand { span, .. } => ("conditional-command", span),
+        Event::ArithmeticCommand { span, .. } => ("arithmetic-command", span),
+        Event::FunctionDeclaration { span, .. } => {
+            let keyword = Extension::FunctionKeyword;
+            if source[span.start.byte..].starts_with(keyword.as_str()) {
+                extensions.push(keyword);
+            }
+            ("function-definition", span)
+        }
+        Event::Arithmetic { ref expr, span } => {
+            if let Some(ref expr) = *expr {
+                let mut arith = ArithmeticExtensions(&mut extensions);
+                arith.visit_arithmetic(expr);
+            }
+            ("arithmetic-expansion", span)
+        }
+        Event::ParameterSubstitution { ref kind, span } => {
+            use crate::ast::builder::ParameterSubstitutionKind::*;
+
+            match *kind {
+                Substring(..) | ReplaceString(..) | ReplaceStringAll(..) | Lowercase(..)
+                | Uppercase(..) => ("parameter-substitution-extension", span),
+                _ => ("parameter-substitution", span),
+            }
+        }
+        Event::Word { ref kind, span } => (word_construct(kind)?, span),
+        Event::Redirect { ref kind, span } => {
+            let id = match *kind {
+                RedirectKind::Read(..) => "redirect-input",
+                RedirectKind::Write(..) | RedirectKind::Clobber(..) => "redirect-output",
+                RedirectKind::Append(..) => "redirect-append",
+                RedirectKind::Heredoc(..) => "heredoc",
+                RedirectKind::DupRead(..) => "dup-input",
+                RedirectKind::DupWrite(..) => "dup-output",
+                RedirectKind::ReadWrite(..) => "redirect-read-write",
+                RedirectKind::WriteBoth(_) | RedirectKind::AppendBoth(_) => "redirect-both",
+            };
+            (id, span)
+        }
+        _ => return None,
+    };
+
+    Some((id, span, extensions))
+}
+
+/// Determines the construct of a word, or `None` if it consists of an arithmetic
+/// or `${...}` substitution, which is explained by an event of its own.
+fn word_construct<C>(kind: &ComplexWordKind<C>) -> Option<&'static str> {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    let word = match *kind {
+        ComplexWordKind::Single(ref word) => word,
+        ComplexWordKind::Concat(_) => return Some("word"),
+    };
+
+    let simple = match *word {
+        WordKind::Simple(ref simple) => simple,
+        WordKind::SingleQuoted(_) => return Some("single-quotes"),
+        WordKind::DoubleQuoted(_) => return Some("double-quotes"),
+        WordKind::Braces(_) => return Some("brace-expansion"),
+    };
+
+    let id = match *simple {
+        SimpleWordKind::Tilde | SimpleWordKind::TildePrefix(_) => "tilde-expansion",
+        SimpleWordKind::Param(_) => "parameter",
+        SimpleWordKind::Subst(ref subst) => match **subst {
+            Command(_) => "command-substitution",
+            ReadFile(_) => "read-file",
+            ProcessRead(_) => "process-read",
+            ProcessWrite(_) => "process-write",
+            _ => return None,
+        },
+        SimpleWordKind::CommandSubst(_) => "command-substitution",
+        _ => "word",
+    };
+
+    Some(id)
+}
+
+/// Collects the extensions used within an arithmetic expression.
+struct ArithmeticExtensions<'a>(&'a mut Vec<Extension>);
+
+impl Visitor for ArithmeticExtensions<'_> {
+    fn visit_arithmetic(&mut self, arith: &DefaultArithmetic) {
+        let ext = match *arith {
+            Arithmetic::RadixLiteral(_, Radix::Explicit(_), _) => Some(Extension::ArithmeticBase),
+            Arithmetic::Index(..)
+            | Arithmetic::PostIncr(ArithmeticTarget::Index(..))
+            | Arithmetic::PostDecr(ArithmeticTarget::Index(..))
+            | Arithmetic::PreIncr(ArithmeticTarget::Index(..))
+            | Arithmetic::PreDecr(ArithmeticTarget::Index(..))
+            | Arithmetic::Assign(ArithmeticTarget::Index(..), _)
+            | Arithmetic::AssignOp(_, ArithmeticTarget::Index(..), _) => {
+                Some(Extension::ArithmeticIndex)
+            }
+            _ => None,
+        };
+
+        if let Some(ext) = ext.filter(|ext| !self.0.contains(ext)) {
+            self.0.push(ext);
+        }
+        walk_arithmetic(self, arith);
+    }
+}
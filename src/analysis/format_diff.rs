@@ -0,0 +1,261 @@
+//! Classification of the changes between two versions of a script.
+//!
+//! This is useful for deciding whether a change to a script is merely a
+//! reformatting (e.g. to automatically approve the output of a formatter), or
+//! whether it may change what the script does.
+//!
+//! Both versions are tokenized and parsed (with the default bash dialect), and
+//! the change is classified by the most significant level at which they differ:
+//!
+//! * `ChangeLevel::Trivia`: only whitespace, comments, blank lines, or line
+//!   continuations differ.
+//! * `ChangeLevel::Tokens`: other tokens differ, but both versions parse to
+//!   the same commands, e.g. `foo; bar` and `foo` followed by `bar` on the next line.
+//! * `ChangeLevel::Structure`: the versions parse to different commands, or at
+//!   least one of them fails to parse.
+
+use crate::ast::builder::FunctionSyntax;
+use crate::ast::visit::{
+    walk_compound_command_mut, walk_pipeable_command_mut, walk_pipeline_mut, walk_redirect_mut,
+    DefaultPipeline, VisitorMut,
+};
+use crate::ast::{
+    DefaultCompoundCommand, DefaultPipeableCommand, DefaultRedirect, PipeableCommand, Redirect,
+    Span, TopLevelCommand,
+};
+use crate::highlight::{semantic_tokens, SemanticTokenType};
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, SourcePos};
+use crate::token::Token;
+
+/// How significantly two versions of a script differ, ordered from least to most significant.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum ChangeLevel {
+    /// The sources are identical.
+    Identical,
+    /// Only whitespace, comments, blank lines, or line continuations differ.
+    Trivia,
+    /// Other tokens differ, but both versions parse to the same commands.
+    Tokens,
+    /// The versions parse to different commands, or at least one fails to parse.
+    Structure,
+}
+
+/// The result of comparing two versions of a script, see `compare`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Change {
+    /// How significantly the versions differ.
+    pub level: ChangeLevel,
+    /// The span of the first differing token within the old version, or an
+    /// empty span at its end if it has fewer tokens. `None` if identical.
+    pub old: Option<Span>,
+    /// The span of the first differing token within the new version, or an
+    /// empty span at its end if it has fewer tokens. `None` if identical.
+    pub new: Option<Span>,
+}
+
+impl Change {
+    /// Checks if the versions differ in formatting (or comments) at most.
+    pub fn is_formatting_only(&self) -> bool {
+        self.level <= ChangeLevel::Trivia
+    }
+}
+
+/// A token along with its location in the source.
+type SpannedToken = (Token, Span);
+
+/// Tokenizes a source, recording the location of every token.
+fn tokenize(source: &str) -> (Vec<SpannedToken>, SourcePos) {
+    let mut pos = SourcePos::new();
+    let tokens = Lexer::new(source.chars())
+        .map(|token| {
+            let start = pos;
+            pos.advance(&token);
+            (token, Span { start, end: pos })
+        })
+        .collect();
+
+    (tokens, pos)
+}
+
+/// Removes whitespace, comments, line continuations, and redundant newlines,
+/// leaving the tokens which may be meaningful to the parser.
+fn significant(source: &str, tokens: &[SpannedToken]) -> Vec<SpannedToken> {
+    let comments = semantic_tokens(source)
+        .into_iter()
+        .filter(|t| t.token_type == SemanticTokenType::Comment)
+        .map(|t| t.start.byte..t.end.byte)
+        .collect::<Vec<_>>();
+    let in_comment = |span: &Span| comments.iter().any(|c| c.contains(&span.start.byte));
+
+    let mut result: Vec<SpannedToken> = Vec::new();
+    let mut iter = tokens.iter().peekable();
+    while let Some(&(ref token, span)) = iter.next() {
+        match *token {
+            Token::Whitespace(_) => continue,
+            _ if in_comment(&span) => continue,
+            Token::Backslash => {
+                if let Some(&&(Token::Newline, _)) = iter.peek() {
+                    iter.next();
+                    continue;
+                }
+            }
+            Token::Newline => match result.last() {
+                None | Some(&(Token::Newline, _)) => continue,
+                Some(_) => {}
+            },
+            _ => {}
+        }
+
+        result.push((token.clone(), span));
+    }
+
+    if let Some(&(Token::Newline, _)) = result.last() {
+        result.pop();
+    }
+    result
+}
+
+/// Returns the spans of the first pair of tokens which differ, if any.
+fn first_difference(
+    old: &[SpannedToken],
+    old_end: SourcePos,
+    new: &[SpannedToken],
+    new_end: SourcePos,
+) -> Option<(Span, Span)> {
+    let at_end = |pos| Span {
+        start: pos,
+        end: pos,
+    };
+    let span_at = |tokens: &[SpannedToken], i: usize, end| {
+        tokens.get(i).map_or_else(|| at_end(end), |&(_, span)| span)
+    };
+
+    let common = old.iter().zip(new).take_while(|(o, n)| o.0 == n.0).count();
+
+    if common == old.len() && common == new.len() {
+        None
+    } else {
+        Some((span_at(old, common, old_end), span_at(new, common, new_end)))
+    }
+}
+
+/// Erases the locations recorded within commands (and the raw lines of heredoc
+/// bodies), which differ whenever anything before them is reformatted.
+struct LocationEraser;
+
+impl VisitorMut for LocationEraser {
+    fn visit_pipeline(&mut self, pipeline: &mut DefaultPipeline) {
+        for segment in &mut pipeline.segments {
+            segment.pipe = None;
+        }
+        walk_pipeline_mut(self, pipeline);
+    }
+
+    fn visit_compound_command(&mut self, cmd: &mut DefaultCompoundCommand) {
+        cmd.io_span = None;
+        walk_compound_command_mut(self, cmd);
+    }
+
+    fn visit_redirect(&mut self, redirect: &mut DefaultRedirect) {
+        if let Redirect::Heredoc(_, _, ref mut info) = *redirect {
+            info.lines.clear();
+        }
+        walk_redirect_mut(self, redirect);
+    }
+}
+
+/// Erases everything recorded within commands which depends on how they were
+/// formatted, so that commands can be compared structurally.
+pub(crate) fn erase_locations(cmds: &mut Vec<TopLevelCommand<String>>) {
+    LocationEraser.visit_commands(cmds);
+}
+
+/// Erases the syntax with which functions were declared, since `function f { :; }`
+/// and `f() { :; }` only differ in their tokens.
+struct FunctionSyntaxEraser;
+
+impl VisitorMut for FunctionSyntaxEraser {
+    fn visit_pipeable_command(&mut self, cmd: &mut DefaultPipeableCommand) {
+        if let PipeableCommand::FunctionDef(_, ref mut syntax, _) = *cmd {
+            *syntax = FunctionSyntax::Posix;
+        }
+        walk_pipeable_command_mut(self, cmd);
+    }
+}
+
+/// Checks if both versions parse successfully into the same commands.
+fn same_commands(old: &str, new: &str) -> bool {
+    let parse = |source: &str| {
+        DefaultParser::new(Lexer::new(source.chars()))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map(|mut cmds| {
+                erase_locations(&mut cmds);
+                FunctionSyntaxEraser.visit_commands(&mut cmds);
+                cmds
+            })
+    };
+
+    match (parse(old), parse(new)) {
+        (Ok(old), Ok(new)) => old == new,
+        _ => false,
+    }
+}
+
+/// Compares two versions of a script, classifying how significantly they
+/// differ and locating the first token at which they do.
+///
+/// The located token is the first significant one which differs (ignoring
+/// trivia), unless only trivia differs (or the significant tokens happen to
+/// be equal while the commands differ, e.g. `"a" "b"` and `"a""b"`), in which
+/// case it is the first token of any kind which differs.
+///
+/// ```
+/// use conch_parser::analysis::format_diff::{compare, ChangeLevel};
+///
+/// let old = "if true; then echo hi; fi";
+/// let new = "# greet\nif true\nthen\n    echo hi # loudly\nfi\n";
+/// assert_eq!(compare(old, new).level, ChangeLevel::Tokens);
+/// assert_eq!(compare(old, "if  true; then echo hi; fi # done").level, ChangeLevel::Trivia);
+///
+/// let change = compare(old, "if true; then echo bye; fi");
+/// assert_eq!(change.level, ChangeLevel::Structure);
+/// assert_eq!(change.new.unwrap().start.byte, 19);
+/// ```
+pub fn compare(old: &str, new: &str) -> Change {
+    if old == new {
+        return Change {
+            level: ChangeLevel::Identical,
+            old: None,
+            new: None,
+        };
+    }
+
+    let (old_tokens, old_end) = tokenize(old);
+    let (new_tokens, new_end) = tokenize(new);
+    let significant_difference = first_difference(
+        &significant(old, &old_tokens),
+        old_end,
+        &significant(new, &new_tokens),
+        new_end,
+    );
+
+    let level = if !same_commands(old, new) {
+        ChangeLevel::Structure
+    } else if significant_difference.is_some() {
+        ChangeLevel::Tokens
+    } else {
+        ChangeLevel::Trivia
+    };
+
+    let (old_span, new_span) = significant_difference
+        .or_else(|| first_difference(&old_tokens, old_end, &new_tokens, new_end))
+        .map_or((None, None), |(old, new)| (Some(old), Some(new)));
+
+    Change {
+        level,
+        old: old_span,
+        new: new_span,
+    }
+}
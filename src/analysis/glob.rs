@@ -0,0 +1,152 @@
+//! Checks for pathname expansions (globs) with surprising side effects.
+//!
+//! An unquoted `*`, `?` or `[...]` within a word is subject to pathname
+//! expansion. When handed to a command which irreversibly modifies files
+//! this can easily touch more than intended (especially if the glob follows
+//! a parameter which may be empty, e.g. `rm -rf $dir/*`). If nothing matches
+//! the pattern, the shell will instead pass the pattern through verbatim,
+//! unless `nullglob` or `failglob` is enabled.
+
+use super::{cmd_words, display_word, literal_word, walk_commands, word_parts};
+use super::{Diagnostic, Node, Severity};
+use crate::ast::{CompoundCommandKind, SimpleWord, TopLevelCommand, TopLevelWord, Word};
+
+/// The code reported for globs passed to a destructive command.
+pub const DESTRUCTIVE_GLOB: &str = "glob-destructive";
+/// The code reported for globs which may not match any files.
+pub const UNMATCHED_GLOB: &str = "glob-unmatched";
+
+/// A command which irreversibly modifies the files it is given.
+#[derive(Debug, Clone, Copy)]
+struct Destructive {
+    /// The name of the command.
+    name: &'static str,
+    /// Whether the command is only considered destructive when it
+    /// operates recursively (e.g. `chmod -R`).
+    only_recursive: bool,
+}
+
+/// The commands considered destructive by this check.
+const DESTRUCTIVE_COMMANDS: &[Destructive] = &[
+    Destructive {
+        name: "rm",
+        only_recursive: false,
+    },
+    Destructive {
+        name: "chmod",
+        only_recursive: true,
+    },
+    Destructive {
+        name: "chown",
+        only_recursive: false,
+    },
+];
+
+/// Reports any unquoted globs passed as arguments to destructive commands
+/// (e.g. `rm`, `chmod -R`, `chown`), as well as any globs which will be left
+/// unexpanded if they fail to match any files.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    walk_commands(cmds, &mut |node| match node {
+        Node::Simple(cmd) => {
+            let mut words = cmd_words(cmd);
+            let name = match words.next().and_then(literal_word) {
+                Some(name) => name,
+                None => return,
+            };
+
+            let args: Vec<_> = words.collect();
+            let destructive = destructive_command(&name, &args);
+
+            for arg in args.into_iter().filter(|arg| has_unquoted_glob(arg)) {
+                diagnostics.push(match destructive {
+                    Some(cmd) => destructive_glob(cmd, arg),
+                    None => unmatched_glob(arg),
+                });
+            }
+        }
+
+        Node::Compound(cmd) => {
+            if let CompoundCommandKind::For {
+                words: Some(ref words),
+                ..
+            } = cmd.kind
+            {
+                let globs = words.iter().filter(|w| has_unquoted_glob(w));
+                diagnostics.extend(globs.map(unmatched_glob));
+            }
+        }
+
+        Node::FunctionDef => {}
+    });
+
+    diagnostics
+}
+
+/// Returns the name of the command if it is destructive when invoked with the specified arguments.
+fn destructive_command(name: &str, args: &[&TopLevelWord<String>]) -> Option<&'static str> {
+    // Commands invoked via an absolute or relative path, e.g. `/bin/rm`
+    let name = name.rsplit('/').next().unwrap_or(name);
+
+    let cmd = DESTRUCTIVE_COMMANDS.iter().find(|cmd| cmd.name == name)?;
+    if cmd.only_recursive && !args.iter().any(|arg| is_recursive_flag(arg)) {
+        return None;
+    }
+
+    Some(cmd.name)
+}
+
+fn is_recursive_flag(word: &TopLevelWord<String>) -> bool {
+    match literal_word(word) {
+        Some(ref flag) if flag == "--recursive" => true,
+        Some(ref flag) if flag.starts_with('-') && !flag.starts_with("--") => flag.contains('R'),
+        _ => false,
+    }
+}
+
+/// Checks if a word contains a `*`, `?`, or `[` which is subject to pathname expansion.
+fn has_unquoted_glob(word: &TopLevelWord<String>) -> bool {
+    word_parts(word).iter().any(|w| {
+        matches!(
+            w,
+            Word::Simple(SimpleWord::Star)
+                | Word::Simple(SimpleWord::Question)
+                | Word::Simple(SimpleWord::SquareOpen)
+        )
+    })
+}
+
+/// Checks if a word begins with an unquoted parameter or substitution, e.g. `$dir/*`.
+fn starts_with_expansion(word: &TopLevelWord<String>) -> bool {
+    matches!(
+        word_parts(word).first(),
+        Some(Word::Simple(SimpleWord::Param(_))) | Some(Word::Simple(SimpleWord::Subst(_)))
+    )
+}
+
+fn destructive_glob(cmd: &str, word: &TopLevelWord<String>) -> Diagnostic {
+    let mut msg = format!(
+        "unquoted glob `{}` passed to destructive command `{}`",
+        display_word(word),
+        cmd
+    );
+
+    if starts_with_expansion(word) {
+        msg.push_str(
+            "; if the leading expansion is empty, the glob will match from the root directory",
+        );
+    }
+
+    Diagnostic::new(Severity::Warning, DESTRUCTIVE_GLOB, msg)
+}
+
+fn unmatched_glob(word: &TopLevelWord<String>) -> Diagnostic {
+    let msg = format!(
+        "glob `{}` is passed through unexpanded if nothing matches; \
+         quote it, or enable `nullglob` or `failglob`",
+        display_word(word)
+    );
+
+    Diagnostic::new(Severity::Info, UNMATCHED_GLOB, msg)
+}
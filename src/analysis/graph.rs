@@ -0,0 +1,602 @@
+//! Export of the file inclusion and function call graphs of a script codebase.
+//!
+//! A `ScriptGraph` collects the functions declared, the functions called, and
+//! the files sourced (via `.` or `source`) by any number of scripts, and can
+//! render either the file inclusion graph or the function call graph as DOT
+//! (e.g. for Graphviz) or as JSON. Each node carries the file it belongs to,
+//! the dialect the file was parsed with, and the span of its declaration.
+//!
+//! Exported graphs only depend on the order in which scripts were added: nodes
+//! are listed by file, then in source order, and edges in source order.
+
+use crate::analysis::dialect_diff::DefaultParseError;
+use crate::ast::builder::{ComplexWordKind, Event, EventLog, NodeId, SimpleWordKind, WordKind};
+use crate::ast::{RedirectOrCmdWord, Span};
+use crate::lexer::Lexer;
+use crate::parse::{Dialect, Parser, ParserOptions};
+use std::fmt::{self, Write};
+
+/// A function declared by a script.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Function {
+    /// The name of the function.
+    pub name: String,
+    /// The span of the entire declaration, including its body.
+    pub span: Span,
+}
+
+/// A file sourced by a script, e.g. `. ./lib.sh` or `source lib.sh`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Include {
+    /// The path of the sourced file, exactly as it appears in the script.
+    pub path: String,
+    /// The span of the command which sources the file.
+    pub span: Span,
+}
+
+/// A simple command whose name is a literal, which may call a function.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Call {
+    /// The name of the command.
+    pub name: String,
+    /// The index (within `ScriptFile::functions`) of the innermost function
+    /// whose body contains the command, or `None` if it is run at the top level.
+    pub caller: Option<usize>,
+    /// The span of the command.
+    pub span: Span,
+}
+
+/// The declarations and references collected from a single script.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScriptFile {
+    /// The path of the script.
+    pub path: String,
+    /// The dialect the script was parsed with.
+    pub dialect: Dialect,
+    /// All function declarations, in source order.
+    pub functions: Vec<Function>,
+    /// All files sourced with a literal path, in source order. Files sourced
+    /// via an expansion (e.g. `. "$dir/lib.sh"`) cannot be determined statically.
+    pub includes: Vec<Include>,
+    /// All commands with a literal name, in source order.
+    pub calls: Vec<Call>,
+}
+
+/// The graph which should be exported from a `ScriptGraph`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum GraphKind {
+    /// Files, connected to the files they source. Sourced paths which do
+    /// not match any known script are included as unresolved nodes.
+    Includes,
+    /// Functions (and scripts, for calls made at the top level), connected
+    /// to the functions they call.
+    Calls,
+}
+
+impl GraphKind {
+    /// Returns the lowercase name of the graph.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            GraphKind::Includes => "includes",
+            GraphKind::Calls => "calls",
+        }
+    }
+}
+
+/// The relationships between the files and functions of several scripts.
+///
+/// ```
+/// use conch_parser::analysis::graph::{GraphKind, ScriptGraph};
+/// use conch_parser::parse::Dialect;
+///
+/// let mut graph = ScriptGraph::new();
+/// graph.add_script("main.sh", ". ./lib.sh\ngreet world", Dialect::Bash).unwrap();
+/// graph.add_script("lib.sh", "greet() { echo \"hello $1\"; }", Dialect::Posix).unwrap();
+///
+/// let dot = graph.to_dot(GraphKind::Includes);
+/// assert!(dot.contains("n0 -> n1"));
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ScriptGraph {
+    files: Vec<ScriptFile>,
+}
+
+impl ScriptGraph {
+    /// Constructs a new, empty, graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a script and adds its declarations and references to the graph.
+    ///
+    /// Scripts may be added in any order, since sourced files and called
+    /// functions are only resolved when the graph is exported. Scripts which
+    /// fail to parse are not added.
+    pub fn add_script<P: Into<String>>(
+        &mut self,
+        path: P,
+        source: &str,
+        dialect: Dialect,
+    ) -> Result<(), DefaultParseError> {
+        let mut log = EventLog::new();
+        let options = ParserOptions {
+            dialect,
+            ..ParserOptions::default()
+        };
+
+        let mut parser = Parser::with_options(Lexer::new(source.chars()), &mut log, options);
+        while parser.complete_command()?.is_some() {}
+
+        self.files.push(collect(path.into(), dialect, log.events()));
+        Ok(())
+    }
+
+    /// Returns all scripts added to the graph, in the order they were added.
+    pub fn files(&self) -> &[ScriptFile] {
+        &self.files
+    }
+
+    /// Returns the index of the script sourced by an include of the specified file.
+    ///
+    /// Relative paths are looked up relative to the directory of the sourcing
+    /// script first, and then relative to the directory the scripts were
+    /// collected from (i.e. as the paths of the added scripts are written).
+    pub fn resolve_include(&self, file: usize, include: &Include) -> Option<usize> {
+        let candidates = if include.path.starts_with('/') {
+            vec![normalize(&include.path)]
+        } else {
+            let dir = match self.files[file].path.rfind('/') {
+                Some(idx) => &self.files[file].path[..=idx],
+                None => "",
+            };
+
+            vec![
+                normalize(&format!("{}{}", dir, include.path)),
+                normalize(&include.path),
+            ]
+        };
+
+        candidates.iter().find_map(|candidate| {
+            self.files
+                .iter()
+                .position(|f| normalize(&f.path) == *candidate)
+        })
+    }
+
+    /// Returns the script and function indices of the function invoked by a
+    /// call within the specified file, if any.
+    ///
+    /// A function declared in the same file takes precedence, followed by
+    /// those declared in any (transitively) sourced file in the order they
+    /// are sourced. Functions declared by scripts which are not sourced are
+    /// not considered, since they are not visible to the caller.
+    pub fn resolve_call(&self, file: usize, call: &Call) -> Option<(usize, usize)> {
+        let mut visited = vec![file];
+        let mut idx = 0;
+
+        while idx < visited.len() {
+            let current = visited[idx];
+            let script = &self.files[current];
+
+            // The last declaration wins if a function is redeclared
+            if let Some(func) = script.functions.iter().rposition(|f| f.name == call.name) {
+                return Some((current, func));
+            }
+
+            for include in &script.includes {
+                if let Some(included) = self.resolve_include(current, include) {
+                    if !visited.contains(&included) {
+                        visited.push(included);
+                    }
+                }
+            }
+
+            idx += 1;
+        }
+
+        None
+    }
+
+    /// Renders the specified graph in the DOT language.
+    ///
+    /// Nodes are labeled with their name, and record their file, dialect, and
+    /// declaration span as attributes. Edges are labeled with the line (and
+    /// column) of each command which introduced them.
+    pub fn to_dot(&self, kind: GraphKind) -> String {
+        let (nodes, edges) = self.nodes_and_edges(kind);
+        let mut out = String::new();
+        write_dot(&mut out, kind, &nodes, &edges).expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Renders the specified graph as a JSON object with `nodes` and `edges` arrays.
+    ///
+    /// Every node has an `id`, `kind` (`"file"`, `"function"`, or `"unresolved"`),
+    /// `name`, `file`, `dialect`, and `span` (which is `null` for files), while every
+    /// edge has the `from` and `to` node ids, and the `spans` of the commands which
+    /// introduced it.
+    pub fn to_json(&self, kind: GraphKind) -> String {
+        let (nodes, edges) = self.nodes_and_edges(kind);
+        let mut out = String::new();
+        write_json(&mut out, kind, &nodes, &edges).expect("writing to a String cannot fail");
+        out
+    }
+
+    fn nodes_and_edges(&self, kind: GraphKind) -> (Vec<GraphNode<'_>>, Vec<GraphEdge>) {
+        let mut nodes: Vec<_> = self
+            .files
+            .iter()
+            .map(|f| GraphNode {
+                kind: "file",
+                name: &f.path,
+                file: &f.path,
+                dialect: Some(f.dialect),
+                span: None,
+            })
+            .collect();
+        let mut edges = Vec::new();
+
+        match kind {
+            GraphKind::Includes => {
+                for (idx, file) in self.files.iter().enumerate() {
+                    for include in &file.includes {
+                        let to = match self.resolve_include(idx, include) {
+                            Some(to) => to,
+                            None => find_or_push(
+                                &mut nodes,
+                                GraphNode {
+                                    kind: "unresolved",
+                                    name: &include.path,
+                                    file: &include.path,
+                                    dialect: None,
+                                    span: None,
+                                },
+                            ),
+                        };
+
+                        add_edge(&mut edges, idx, to, include.span);
+                    }
+                }
+            }
+
+            GraphKind::Calls => {
+                let mut offsets = Vec::with_capacity(self.files.len());
+                for file in &self.files {
+                    offsets.push(nodes.len());
+                    nodes.extend(file.functions.iter().map(|func| GraphNode {
+                        kind: "function",
+                        name: &func.name,
+                        file: &file.path,
+                        dialect: Some(file.dialect),
+                        span: Some(func.span),
+                    }));
+                }
+
+                for (idx, file) in self.files.iter().enumerate() {
+                    for call in &file.calls {
+                        if let Some((callee_file, callee)) = self.resolve_call(idx, call) {
+                            let from = match call.caller {
+                                Some(caller) => offsets[idx] + caller,
+                                None => idx,
+                            };
+
+                            add_edge(&mut edges, from, offsets[callee_file] + callee, call.span);
+                        }
+                    }
+                }
+            }
+        }
+
+        (nodes, edges)
+    }
+}
+
+/// A node of an exported graph.
+#[derive(Debug, PartialEq, Eq)]
+struct GraphNode<'a> {
+    kind: &'static str,
+    name: &'a str,
+    file: &'a str,
+    dialect: Option<Dialect>,
+    span: Option<Span>,
+}
+
+/// An edge of an exported graph, along with the spans of every
+/// command which introduced it.
+#[derive(Debug)]
+struct GraphEdge {
+    from: usize,
+    to: usize,
+    spans: Vec<Span>,
+}
+
+fn find_or_push<'a>(nodes: &mut Vec<GraphNode<'a>>, node: GraphNode<'a>) -> usize {
+    match nodes.iter().position(|n| *n == node) {
+        Some(idx) => idx,
+        None => {
+            nodes.push(node);
+            nodes.len() - 1
+        }
+    }
+}
+
+fn add_edge(edges: &mut Vec<GraphEdge>, from: usize, to: usize, span: Span) {
+    match edges.iter_mut().find(|e| e.from == from && e.to == to) {
+        Some(edge) => edge.spans.push(span),
+        None => edges.push(GraphEdge {
+            from,
+            to,
+            spans: vec![span],
+        }),
+    }
+}
+
+/// Collects the functions, includes, and calls out of the events of a parsed script.
+fn collect(path: String, dialect: Dialect, events: &[Event]) -> ScriptFile {
+    let mut functions = Vec::new();
+    let mut includes = Vec::new();
+    let mut calls = Vec::new();
+
+    for event in events {
+        match *event {
+            Event::FunctionDeclaration { ref name, span, .. } => functions.push(Function {
+                name: name.clone(),
+                span,
+            }),
+
+            Event::SimpleCommand {
+                ref redirects_or_cmd_words,
+                span,
+                ..
+            } => {
+                let mut words = redirects_or_cmd_words.iter().filter_map(|w| match *w {
+                    RedirectOrCmdWord::CmdWord(id) => Some(literal(events, id)),
+                    RedirectOrCmdWord::Redirect(_) => None,
+                });
+
+                let name = match words.next() {
+                    Some(Some(name)) => name,
+                    _ => continue,
+                };
+
+                if name == "." || name == "source" {
+                    if let Some(Some(path)) = words.next() {
+                        includes.push(Include { path, span });
+                    }
+                } else {
+                    calls.push(Call {
+                        name,
+                        caller: None,
+                        span,
+                    });
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    // Function bodies are built before their declarations, so
+    // callers can only be determined once all declarations are known
+    for call in &mut calls {
+        call.caller = functions
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| contains(f.span, call.span))
+            .max_by_key(|(_, f)| f.span.start.byte)
+            .map(|(idx, _)| idx);
+    }
+
+    ScriptFile {
+        path,
+        dialect,
+        functions,
+        includes,
+        calls,
+    }
+}
+
+fn contains(outer: Span, inner: Span) -> bool {
+    outer.start.byte <= inner.start.byte && inner.end.byte <= outer.end.byte
+}
+
+/// Returns the contents of a recorded word if it is composed entirely out of
+/// literals (quoted or otherwise), or `None` if it contains any expansions.
+fn literal(events: &[Event], id: NodeId) -> Option<String> {
+    fn push_simple(buf: &mut String, w: &SimpleWordKind<NodeId>) -> Option<()> {
+        match *w {
+            SimpleWordKind::Literal(ref s) | SimpleWordKind::Escaped(ref s) => buf.push_str(s),
+            SimpleWordKind::Colon => buf.push(':'),
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    fn push_word(buf: &mut String, w: &WordKind<NodeId>) -> Option<()> {
+        match *w {
+            WordKind::Simple(ref w) => push_simple(buf, w),
+            WordKind::SingleQuoted(ref s) => {
+                buf.push_str(s);
+                Some(())
+            }
+            WordKind::DoubleQuoted(ref words) => words.iter().try_for_each(|w| push_simple(buf, w)),
+            WordKind::Braces(_) => None,
+        }
+    }
+
+    let kind = match events.get(id.0) {
+        Some(Event::Word { ref kind, .. }) => kind,
+        _ => return None,
+    };
+
+    let mut buf = String::new();
+    match *kind {
+        ComplexWordKind::Single(ref w) => push_word(&mut buf, w)?,
+        ComplexWordKind::Concat(ref words) => {
+            words.iter().try_for_each(|w| push_word(&mut buf, w))?
+        }
+    }
+
+    Some(buf)
+}
+
+/// Lexically normalizes a path by removing any empty or `.` components,
+/// and resolving `..` components against their preceding component.
+fn normalize(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                Some(&last) if last != ".." => {
+                    parts.pop();
+                }
+                _ if absolute => {}
+                _ => parts.push(".."),
+            },
+            part => parts.push(part),
+        }
+    }
+
+    let joined = parts.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Writes a quoted string, escaping quotes, backslashes, and control characters.
+fn write_quoted<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\t' => out.write_str("\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_dot<W: Write>(
+    out: &mut W,
+    kind: GraphKind,
+    nodes: &[GraphNode<'_>],
+    edges: &[GraphEdge],
+) -> fmt::Result {
+    writeln!(out, "digraph {} {{", kind.as_str())?;
+
+    for (idx, node) in nodes.iter().enumerate() {
+        write!(out, "    n{} [label=", idx)?;
+        write_quoted(out, node.name)?;
+        if node.kind == "function" {
+            out.write_str(", shape=box, file=")?;
+            write_quoted(out, node.file)?;
+        }
+        if let Some(dialect) = node.dialect {
+            write!(out, ", dialect=\"{}\"", dialect)?;
+        }
+        if let Some(span) = node.span {
+            write!(
+                out,
+                ", span=\"{}:{}-{}:{}\"",
+                span.start.line, span.start.col, span.end.line, span.end.col
+            )?;
+        }
+        if node.kind == "unresolved" {
+            out.write_str(", style=dashed")?;
+        }
+        writeln!(out, "];")?;
+    }
+
+    for edge in edges {
+        let lines = edge
+            .spans
+            .iter()
+            .map(|s| format!("{}:{}", s.start.line, s.start.col))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            out,
+            "    n{} -> n{} [label=\"{}\"];",
+            edge.from, edge.to, lines
+        )?;
+    }
+
+    writeln!(out, "}}")
+}
+
+fn write_span<W: Write>(out: &mut W, span: Span) -> fmt::Result {
+    write!(
+        out,
+        "{{\"start\": {{\"byte\": {}, \"line\": {}, \"col\": {}}}, \
+         \"end\": {{\"byte\": {}, \"line\": {}, \"col\": {}}}}}",
+        span.start.byte,
+        span.start.line,
+        span.start.col,
+        span.end.byte,
+        span.end.line,
+        span.end.col
+    )
+}
+
+fn write_json<W: Write>(
+    out: &mut W,
+    kind: GraphKind,
+    nodes: &[GraphNode<'_>],
+    edges: &[GraphEdge],
+) -> fmt::Result {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"graph\": \"{}\",", kind.as_str())?;
+
+    writeln!(out, "  \"nodes\": [")?;
+    for (idx, node) in nodes.iter().enumerate() {
+        write!(
+            out,
+            "    {{\"id\": \"n{}\", \"kind\": \"{}\", \"name\": ",
+            idx, node.kind
+        )?;
+        write_quoted(out, node.name)?;
+        out.write_str(", \"file\": ")?;
+        write_quoted(out, node.file)?;
+        match node.dialect {
+            Some(dialect) => write!(out, ", \"dialect\": \"{}\"", dialect)?,
+            None => out.write_str(", \"dialect\": null")?,
+        }
+        out.write_str(", \"span\": ")?;
+        match node.span {
+            Some(span) => write_span(out, span)?,
+            None => out.write_str("null")?,
+        }
+        let sep = if idx + 1 < nodes.len() { "," } else { "" };
+        writeln!(out, "}}{}", sep)?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"edges\": [")?;
+    for (idx, edge) in edges.iter().enumerate() {
+        write!(
+            out,
+            "    {{\"from\": \"n{}\", \"to\": \"n{}\", \"spans\": [",
+            edge.from, edge.to
+        )?;
+        for (i, span) in edge.spans.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            write_span(out, *span)?;
+        }
+        let sep = if idx + 1 < edges.len() { "," } else { "" };
+        writeln!(out, "]}}{}", sep)?;
+    }
+    writeln!(out, "  ]")?;
+
+    writeln!(out, "}}")
+}
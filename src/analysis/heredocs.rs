@@ -0,0 +1,208 @@
+//! Extracts the heredocs embedded within a script.
+//!
+//! Scripts frequently carry whole configuration files as heredocs (e.g. an
+//! nginx site or a systemd unit written out with `cat >unit <<'EOF'`). A
+//! `HeredocLibrary` collects every heredoc of a script, including those nested
+//! within command substitutions or function bodies, so that tools validating
+//! such payloads can look them up by delimiter or location instead of walking
+//! the redirects of every command themselves.
+//!
+//! ```
+//! use conch_parser::analysis::heredocs::HeredocLibrary;
+//!
+//! let src = "cat >site.conf <<'EOF'\nserver {\n\tlisten 80;\n}\nEOF\n\
+//!            f() {\n\tcat <<-END\n\t\thello $USER\n\tEND\n}\n";
+//! let library = HeredocLibrary::from_source(src);
+//! assert_eq!(library.len(), 2);
+//!
+//! let conf = library.get("EOF").next().unwrap();
+//! assert_eq!(conf.body, "server {\n\tlisten 80;\n}\n");
+//! assert!(!conf.expands());
+//!
+//! let greeting = library.get("END").next().unwrap();
+//! assert_eq!(greeting.body, "hello $USER\n");
+//! assert!(greeting.expands() && greeting.has_expansions);
+//! assert_eq!(greeting.span.start.line, 7);
+//! ```
+//!
+//! The source is parsed with the (most permissive) bash dialect. If it fails
+//! to parse, only the heredocs parsed before the error are collected.
+
+use crate::ast::builder::{
+    ComplexWordKind, Event, EventLog, NodeId, RedirectKind, SimpleWordKind, WordKind,
+};
+use crate::ast::{HeredocInfo, Span};
+use crate::lexer::Lexer;
+use crate::parse::Parser;
+use std::collections::BTreeSet;
+use std::slice;
+
+/// A heredoc found within a script, see `HeredocLibrary`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Heredoc {
+    /// The file descriptor the body is provided to, if explicitly specified.
+    pub fd: Option<u16>,
+    /// The delimiter which terminates the body, with any quoting removed.
+    pub delimiter: String,
+    /// The location of the redirect (e.g. `<<EOF`) within the source,
+    /// excluding the body.
+    pub span: Span,
+    /// Whether any part of the delimiter was quoted, in which case the body
+    /// is not subject to any expansions.
+    pub quoted: bool,
+    /// Whether the heredoc was introduced with `<<-`.
+    pub strip_tabs: bool,
+    /// Whether the body contains any parameter, arithmetic, or command
+    /// substitutions which will be expanded. Always `false` if `quoted`.
+    pub has_expansions: bool,
+    /// The body as provided to the command, before any expansions: every line
+    /// is terminated by a newline, and leading tabs are stripped if `strip_tabs`.
+    pub body: String,
+}
+
+impl Heredoc {
+    /// Checks if the body is subject to expansions, i.e. the delimiter was unquoted.
+    pub fn expands(&self) -> bool {
+        !self.quoted
+    }
+
+    /// Creates a heredoc from its parsed parts.
+    fn new(fd: Option<u16>, info: &HeredocInfo, span: Span, has_expansions: bool) -> Self {
+        let body = info
+            .lines
+            .iter()
+            .map(|line| {
+                let line = if info.strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    line
+                };
+                format!("{}\n", line)
+            })
+            .collect();
+
+        Heredoc {
+            fd,
+            delimiter: info.delimiter.clone(),
+            span,
+            quoted: info.quoted,
+            strip_tabs: info.strip_tabs,
+            has_expansions: has_expansions && !info.quoted,
+            body,
+        }
+    }
+}
+
+/// Every heredoc within a script, in source order.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct HeredocLibrary {
+    /// The heredocs, sorted by the start of their redirect.
+    heredocs: Vec<Heredoc>,
+}
+
+impl HeredocLibrary {
+    /// Collects every heredoc within the source.
+    pub fn from_source(source: &str) -> Self {
+        let mut log = EventLog::new();
+        // Any heredocs parsed before an error can still be collected
+        let _ = Parser::with_builder(Lexer::new(source.chars()), &mut log).parse_all();
+
+        let events = log.events();
+        let mut heredocs = events
+            .iter()
+            .filter_map(|event| match *event {
+                Event::Redirect {
+                    kind: RedirectKind::Heredoc(fd, body, ref info),
+                    span,
+                } => Some(Heredoc::new(fd, info, span, expands(events, body))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Heredocs nested within a command are built before the command's own
+        heredocs.sort_by_key(|heredoc| heredoc.span.start.byte);
+        HeredocLibrary { heredocs }
+    }
+
+    /// Returns the number of heredocs in the library.
+    pub fn len(&self) -> usize {
+        self.heredocs.len()
+    }
+
+    /// Checks if the library contains no heredocs.
+    pub fn is_empty(&self) -> bool {
+        self.heredocs.is_empty()
+    }
+
+    /// Returns every heredoc in source order.
+    pub fn iter(&self) -> slice::Iter<'_, Heredoc> {
+        self.heredocs.iter()
+    }
+
+    /// Returns the heredocs terminated by a specific delimiter, in source order.
+    pub fn get<'a>(&'a self, delimiter: &'a str) -> impl Iterator<Item = &'a Heredoc> + 'a {
+        self.iter()
+            .filter(move |heredoc| heredoc.delimiter == delimiter)
+    }
+
+    /// Returns the heredoc whose redirect encloses a byte offset of the source, if any.
+    pub fn at(&self, byte: usize) -> Option<&Heredoc> {
+        self.iter()
+            .find(|heredoc| heredoc.span.start.byte <= byte && byte < heredoc.span.end.byte)
+    }
+
+    /// Returns every distinct delimiter used within the script, sorted by name.
+    pub fn delimiters(&self) -> BTreeSet<&str> {
+        self.iter()
+            .map(|heredoc| heredoc.delimiter.as_str())
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a HeredocLibrary {
+    type Item = &'a Heredoc;
+    type IntoIter = slice::Iter<'a, Heredoc>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Checks if the heredoc body built by an event contains any expansions.
+fn expands(events: &[Event], body: NodeId) -> bool {
+    match events.get(body.0) {
+        Some(Event::Word { kind, .. }) => match *kind {
+            ComplexWordKind::Single(ref w) => word_expands(w),
+            ComplexWordKind::Concat(ref ws) => ws.iter().any(word_expands),
+        },
+        _ => false,
+    }
+}
+
+/// Checks if a word contains any parameter, arithmetic, or command substitutions.
+fn word_expands(word: &WordKind<NodeId>) -> bool {
+    match *word {
+        WordKind::Simple(ref w) => simple_expands(w),
+        WordKind::DoubleQuoted(ref ws) => ws.iter().any(simple_expands),
+        WordKind::SingleQuoted(_) => false,
+        WordKind::Braces(ref braces) => braces.words().any(word_expands),
+    }
+}
+
+/// Checks if a simple word is a parameter, arithmetic, or command substitution.
+fn simple_expands(word: &SimpleWordKind<NodeId>) -> bool {
+    match *word {
+        SimpleWordKind::Param(_) | SimpleWordKind::Subst(_) | SimpleWordKind::CommandSubst(_) => {
+            true
+        }
+        SimpleWordKind::Literal(_)
+        | SimpleWordKind::Escaped(_)
+        | SimpleWordKind::Star
+        | SimpleWordKind::Question
+        | SimpleWordKind::SquareOpen
+        | SimpleWordKind::SquareClose
+        | SimpleWordKind::Tilde
+        | SimpleWordKind::TildePrefix(_)
+        | SimpleWordKind::Colon => false,
+    }
+}
@@ -0,0 +1,335 @@
+//! Checks for misuse of background jobs.
+//!
+//! Commands run in the background (via `&`) are correlated with any subsequent
+//! `wait` (or `fg`) invocations, including ones which refer to a specific job
+//! via `$!`, a variable which was assigned the value of `$!`, or a job spec
+//! such as `%1`. Jobs which are not waited for (or explicitly `disown`ed) may
+//! still be running (or may be killed) when the script exits.
+//!
+//! The analysis is performed in source order without evaluating any control
+//! flow, thus invocations within function bodies and loops are treated
+//! conservatively.
+
+use super::{cmd_words, display_word, literal_word, word_parts};
+use super::{Diagnostic, Severity};
+use crate::ast::{
+    AndOr, AndOrList, Command, CompoundCommandKind, DefaultCompoundCommand, DefaultListableCommand,
+    DefaultParameter, DefaultPipeableCommand, DefaultSimpleCommand, ListableCommand, Parameter,
+    PipeableCommand, RedirectOrEnvVar, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+
+/// The code reported for background jobs which are never waited for.
+pub const UNWAITED_JOB: &str = "job-unwaited";
+/// The code reported for `wait` or `fg` invocations without any prior background jobs.
+pub const WAIT_WITHOUT_JOB: &str = "job-wait-without-job";
+
+/// Reports any commands run in the background which are never waited for,
+/// as well as any `wait` or `fg` invocations which occur before any command
+/// has been run in the background.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    checker.commands(cmds, false);
+
+    let unwaited = checker.jobs.iter().filter(|job| !job.handled);
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.extend(unwaited.map(|job| {
+        let msg = format!(
+            "background job `{}` is never waited for; \
+             the script may exit before it completes",
+            job.name
+        );
+        Diagnostic::new(Severity::Warning, UNWAITED_JOB, msg)
+    }));
+
+    diagnostics
+}
+
+/// A command which was run in the background.
+#[derive(Debug)]
+struct Job {
+    /// A short description of the job for diagnostics.
+    name: String,
+    /// Any variables which were assigned the PID of the job.
+    pid_vars: Vec<String>,
+    /// Whether the job was waited for, or otherwise handled.
+    handled: bool,
+}
+
+#[derive(Debug, Default)]
+struct Checker {
+    jobs: Vec<Job>,
+    diagnostics: Vec<Diagnostic>,
+    /// The number of commands which wait for jobs encountered so far.
+    waits: usize,
+    /// The names of any functions which wait for jobs.
+    waiting_functions: Vec<String>,
+}
+
+impl Checker {
+    /// Checks a list of commands. Commands which are `deferred` may not run
+    /// in the order they appear in (e.g. function bodies or loops).
+    fn commands(&mut self, cmds: &[TopLevelCommand<String>], deferred: bool) {
+        for cmd in cmds {
+            match cmd.0 {
+                Command::List(ref list) => self.and_or(list, deferred),
+                Command::Job(ref list) => {
+                    self.and_or(list, deferred);
+                    self.jobs.push(Job {
+                        name: job_name(&list.first),
+                        pid_vars: Vec::new(),
+                        handled: false,
+                    });
+                }
+                Command::Unparsed(_) => {}
+            }
+        }
+    }
+
+    fn and_or(&mut self, list: &AndOrList<DefaultListableCommand>, deferred: bool) {
+        self.listable(&list.first, deferred);
+        for and_or in &list.rest {
+            match *and_or {
+                AndOr::And(ref cmd) | AndOr::Or(ref cmd) => self.listable(cmd, deferred),
+            }
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand, deferred: bool) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd, deferred),
+            ListableCommand::Pipe(ref pipeline) => {
+                for cmd in pipeline.commands() {
+                    self.pipeable(cmd, deferred);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand, deferred: bool) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd, deferred),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd, deferred),
+            PipeableCommand::FunctionDef(ref name, _, ref body) => {
+                let waits = self.waits;
+                self.compound(body, true);
+                if self.waits > waits {
+                    self.waiting_functions.push(name.clone());
+                }
+            }
+        }
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand, deferred: bool) {
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+                self.commands(cmds, deferred)
+            }
+
+            CompoundCommandKind::While(ref gbp) | CompoundCommandKind::Until(ref gbp) => {
+                self.loop_body(|checker| {
+                    checker.commands(&gbp.guard, true);
+                    checker.commands(&gbp.body, true);
+                });
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                for gbp in conditionals {
+                    self.commands(&gbp.guard, deferred);
+                    self.commands(&gbp.body, deferred);
+                }
+
+                if let Some(ref els) = *else_branch {
+                    self.commands(els, deferred);
+                }
+            }
+
+            CompoundCommandKind::For { ref body, .. } => {
+                self.loop_body(|checker| checker.commands(body, true))
+            }
+
+            CompoundCommandKind::Case { ref arms, .. } => {
+                for arm in arms {
+                    self.commands(&arm.body, deferred);
+                }
+            }
+
+            CompoundCommandKind::Conditional(_) | CompoundCommandKind::Arithmetic(_) => {}
+        }
+    }
+
+    /// Checks the body of a loop. Any jobs started within the loop are considered
+    /// to be waited for if the loop waits for jobs, since the next iteration will.
+    fn loop_body<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        let jobs = self.jobs.len();
+        let waits = self.waits;
+        f(self);
+
+        if self.waits > waits {
+            for job in &mut self.jobs[jobs..] {
+                job.handled = true;
+            }
+        }
+    }
+
+    fn simple(&mut self, cmd: &DefaultSimpleCommand, deferred: bool) {
+        let mut words = cmd_words(cmd);
+        let name = match words.next() {
+            Some(word) => literal_word(word),
+            None => {
+                self.assignments(cmd);
+                return;
+            }
+        };
+
+        let operands: Vec<_> = words
+            .filter(|w| literal_word(w).filter(|w| w.starts_with('-')).is_none())
+            .collect();
+
+        match name.as_deref() {
+            Some("wait") => self.wait("wait", &operands, deferred, false),
+            Some("fg") => self.wait("fg", &operands, deferred, true),
+            Some("disown") => self.wait("disown", &operands, true, true),
+            Some(name) if self.waiting_functions.iter().any(|f| f == name) => self.handle_all(),
+            _ => {}
+        }
+    }
+
+    /// Tracks assignments of `$!`, e.g. `pid=$!`.
+    fn assignments(&mut self, cmd: &DefaultSimpleCommand) {
+        for item in &cmd.redirects_or_env_vars {
+            if let RedirectOrEnvVar::EnvVar(ref var, Some(ref value)) = *item {
+                for job in &mut self.jobs {
+                    job.pid_vars.retain(|v| v != var);
+                }
+
+                if let Some(&Parameter::Bang) = parameter(value) {
+                    if let Some(job) = self.jobs.last_mut() {
+                        job.pid_vars.push(var.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles a command which waits for jobs. If no operands are given, all jobs
+    /// are waited for, unless `current_only` is set, in which case only the most
+    /// recent job is.
+    fn wait(
+        &mut self,
+        cmd: &str,
+        operands: &[&TopLevelWord<String>],
+        deferred: bool,
+        current_only: bool,
+    ) {
+        self.waits += 1;
+        if self.jobs.is_empty() {
+            if !deferred {
+                let msg = format!(
+                    "`{}` is called before any commands are run in the background",
+                    cmd
+                );
+                self.diagnostics
+                    .push(Diagnostic::new(Severity::Warning, WAIT_WITHOUT_JOB, msg));
+            }
+            return;
+        }
+
+        if operands.is_empty() {
+            if current_only {
+                self.handle_last(0);
+            } else {
+                self.handle_all();
+            }
+            return;
+        }
+
+        for operand in operands {
+            if let Some(param) = parameter(operand) {
+                match *param {
+                    Parameter::Bang => self.handle_last(0),
+                    Parameter::Var(ref var) => {
+                        let mut found = false;
+                        for job in self.jobs.iter_mut().filter(|j| j.pid_vars.contains(var)) {
+                            job.handled = true;
+                            found = true;
+                        }
+
+                        // The variable could have been assigned in a way we
+                        // don't track (e.g. collected into a list of PIDs)
+                        if !found {
+                            self.handle_all();
+                        }
+                    }
+                    _ => self.handle_all(),
+                }
+                continue;
+            }
+
+            match literal_word(operand).as_deref() {
+                Some("%%") | Some("%+") | Some("%") => self.handle_last(0),
+                Some("%-") => self.handle_last(1),
+                Some(spec) if spec.starts_with('%') => match spec[1..].parse::<usize>() {
+                    Ok(n) if n > 0 && n <= self.jobs.len() => self.jobs[n - 1].handled = true,
+                    _ => self.handle_all(),
+                },
+                _ => self.handle_all(),
+            }
+        }
+    }
+
+    /// Marks the job `n` places before the most recent one as handled.
+    fn handle_last(&mut self, n: usize) {
+        let len = self.jobs.len();
+        if n < len {
+            self.jobs[len - 1 - n].handled = true;
+        }
+    }
+
+    fn handle_all(&mut self) {
+        for job in &mut self.jobs {
+            job.handled = true;
+        }
+    }
+}
+
+/// Returns the parameter a word consists of, e.g. `$!` or `"$pid"`.
+fn parameter(word: &TopLevelWord<String>) -> Option<&DefaultParameter> {
+    match word_parts(word) {
+        [Word::Simple(SimpleWord::Param(ref p))] => Some(p),
+        [Word::DoubleQuoted(ref words)] => match words.as_slice() {
+            [SimpleWord::Param(ref p)] => Some(p),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Describes a background job by the name of its first command.
+fn job_name(cmd: &DefaultListableCommand) -> String {
+    let first = match *cmd {
+        ListableCommand::Single(ref cmd) => cmd,
+        ListableCommand::Pipe(ref pipeline) => &pipeline.segments[0].cmd,
+    };
+
+    match *first {
+        PipeableCommand::Simple(ref cmd) => match cmd_words(cmd).next() {
+            Some(word) => display_word(word),
+            None => String::from("assignment"),
+        },
+        PipeableCommand::Compound(ref cmd) => String::from(match cmd.kind {
+            CompoundCommandKind::Brace(_) => "{ ... }",
+            CompoundCommandKind::Subshell(_) => "( ... )",
+            CompoundCommandKind::While(_) => "while",
+            CompoundCommandKind::Until(_) => "until",
+            CompoundCommandKind::If { .. } => "if",
+            CompoundCommandKind::For { .. } => "for",
+            CompoundCommandKind::Case { .. } => "case",
+            CompoundCommandKind::Conditional(_) => "[[ ... ]]",
+            CompoundCommandKind::Arithmetic(_) => "(( ... ))",
+        }),
+        PipeableCommand::FunctionDef(ref name, ..) => name.clone(),
+    }
+}
@@ -0,0 +1,466 @@
+//! A pluggable set of lints which check for common scripting mistakes.
+//!
+//! A `Linter` runs any number of `Lint`s over the same parsed commands and
+//! collects their diagnostics. Besides the built-in lints defined here, any
+//! of the other analyses of this crate (e.g. `glob::check`), or any closure
+//! taking the parsed commands and returning diagnostics, can be added as a lint.
+//!
+//! The built-in lints are:
+//!
+//! * `UnquotedExpansion`: an unquoted parameter or command substitution passed
+//!   as a command argument, which is subject to field splitting and pathname expansion.
+//! * `UncheckedCd`: a `cd` whose failure is not handled, e.g. via `cd dir || exit`,
+//!   which leaves the commands which follow running in the wrong directory.
+//! * `UselessCat`: a `cat` of a single file into a pipeline, which could
+//!   be a redirection of the next command's input instead.
+//! * `ExitStatusComparison`: a test which compares `$?`, rather than
+//!   checking the status of the command directly, e.g. `if cmd; then`.
+
+use super::{cmd_words, display_word, literal_word, walk_commands, word_parts};
+use super::{Diagnostic, Node, Severity};
+use crate::ast::{
+    AndOr, Command, CompoundCommandKind, Conditional, DefaultCompoundCommand,
+    DefaultListableCommand, DefaultPipeableCommand, DefaultSimpleCommand, DefaultWord,
+    ListableCommand, Parameter, ParameterSubstitution, PipeableCommand, RedirectOrCmdWord,
+    SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use std::fmt;
+
+/// The code reported by `UnquotedExpansion`.
+pub const UNQUOTED_EXPANSION: &str = "lint-unquoted-expansion";
+/// The code reported by `UncheckedCd`.
+pub const UNCHECKED_CD: &str = "lint-unchecked-cd";
+/// The code reported by `UselessCat`.
+pub const USELESS_CAT: &str = "lint-useless-cat";
+/// The code reported by `ExitStatusComparison`.
+pub const EXIT_STATUS_COMPARISON: &str = "lint-exit-status-comparison";
+
+/// A check which can be run over parsed commands by a `Linter`.
+///
+/// Implemented for any closure which takes the parsed commands and returns
+/// diagnostics, such as the `check` functions of the other analyses.
+pub trait Lint {
+    /// Returns the diagnostics found within the commands, in source order.
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic>;
+}
+
+impl<F> Lint for F
+where
+    F: Fn(&[TopLevelCommand<String>]) -> Vec<Diagnostic>,
+{
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        self(cmds)
+    }
+}
+
+/// Runs a set of lints over parsed commands.
+///
+/// ```
+/// use conch_parser::analysis::glob;
+/// use conch_parser::analysis::lint::{Linter, UNCHECKED_CD};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::DefaultParser;
+///
+/// let src = "cd build; rm -rf *";
+/// let cmds = DefaultParser::new(Lexer::new(src.chars()))
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// let mut linter = Linter::with_builtin_lints();
+/// linter.add(glob::check);
+///
+/// let diagnostics = linter.check(&cmds);
+/// assert_eq!(diagnostics.len(), 2);
+/// assert_eq!(diagnostics[0].code, UNCHECKED_CD);
+/// assert_eq!(diagnostics[1].code, glob::DESTRUCTIVE_GLOB);
+/// ```
+#[derive(Default)]
+pub struct Linter {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl Linter {
+    /// Constructs a linter without any lints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a linter with all of the built-in lints of this module.
+    pub fn with_builtin_lints() -> Self {
+        let mut linter = Self::new();
+        linter
+            .add(UnquotedExpansion)
+            .add(UncheckedCd)
+            .add(UselessCat)
+            .add(ExitStatusComparison);
+        linter
+    }
+
+    /// Adds a lint, which runs after all previously added lints.
+    pub fn add<L: Lint + 'static>(&mut self, lint: L) -> &mut Self {
+        self.lints.push(Box::new(lint));
+        self
+    }
+
+    /// Runs every lint over the commands, returning their diagnostics in the
+    /// order the lints were added.
+    pub fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        self.lints
+            .iter()
+            .flat_map(|lint| lint.check(cmds))
+            .collect()
+    }
+
+    /// Returns the number of lints which will be run.
+    pub fn len(&self) -> usize {
+        self.lints.len()
+    }
+
+    /// Checks if no lints will be run.
+    pub fn is_empty(&self) -> bool {
+        self.lints.is_empty()
+    }
+}
+
+impl Lint for Linter {
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        Linter::check(self, cmds)
+    }
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Linter")
+            .field("lints", &self.lints.len())
+            .finish()
+    }
+}
+
+/// Reports unquoted parameters (e.g. `$file`) or command substitutions
+/// (e.g. `$(ls)`) within command arguments, whose values are split into
+/// fields and pathname expanded.
+///
+/// Special parameters which always expand to a number (e.g. `$?` or `$#`),
+/// as well as lengths and arithmetic substitutions, are not reported. Neither
+/// are assignments passed to declaration commands (e.g. `local x=$y`), nor
+/// an unquoted `$@`, which is reported by `quoting::check` instead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UnquotedExpansion;
+
+impl Lint for UnquotedExpansion {
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        walk_commands(cmds, &mut |node| {
+            let cmd = match node {
+                Node::Simple(cmd) => cmd,
+                Node::Compound(_) | Node::FunctionDef => return,
+            };
+
+            let mut words = cmd_words(cmd).peekable();
+            let declaration = match words.peek() {
+                Some(&name) => is_one_of(name, DECLARATION_COMMANDS),
+                None => false,
+            };
+
+            for word in words {
+                if declaration && is_assignment(word) {
+                    continue;
+                }
+
+                if word_parts(word).iter().any(is_unquoted_expansion) {
+                    let msg = format!(
+                        "unquoted expansion in `{}` is subject to field splitting \
+                         and pathname expansion; double quote it",
+                        display_word(word)
+                    );
+                    diagnostics.push(Diagnostic::new(Severity::Warning, UNQUOTED_EXPANSION, msg));
+                }
+            }
+        });
+
+        diagnostics
+    }
+}
+
+/// Commands whose `name=value` arguments are not split into fields.
+const DECLARATION_COMMANDS: &[&str] = &["declare", "export", "local", "readonly", "typeset"];
+
+/// Checks if a word looks like an assignment, i.e. starts with a literal containing a `=`.
+fn is_assignment(word: &TopLevelWord<String>) -> bool {
+    match word_parts(word).first() {
+        Some(Word::Simple(SimpleWord::Literal(ref s))) => s.contains('='),
+        _ => false,
+    }
+}
+
+fn is_unquoted_expansion(word: &DefaultWord) -> bool {
+    match *word {
+        Word::Simple(SimpleWord::Param(ref param)) => match *param {
+            Parameter::Star | Parameter::Positional(_) | Parameter::Var(_) => true,
+            Parameter::At
+            | Parameter::Pound
+            | Parameter::Question
+            | Parameter::Dash
+            | Parameter::Dollar
+            | Parameter::Bang => false,
+        },
+        Word::Simple(SimpleWord::Subst(ref subst)) => !matches!(
+            **subst,
+            ParameterSubstitution::Len(_) | ParameterSubstitution::Arith(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Reports any `cd` whose failure is not handled, i.e. which is neither
+/// followed by `&&` or `||`, nor part of the guard of an `if`, `while`, or `until`.
+///
+/// A `cd` within a pipeline is not reported, since it does not affect the
+/// rest of the script either way.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UncheckedCd;
+
+impl Lint for UncheckedCd {
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        walk_listables(cmds, false, &mut |cmd, handled| {
+            let cmd = match *cmd {
+                ListableCommand::Single(PipeableCommand::Simple(ref cmd)) => cmd,
+                _ => return,
+            };
+
+            if !handled && command_name(cmd).as_deref() == Some("cd") {
+                let msg = "`cd` may fail, leaving the commands which follow running \
+                           in the wrong directory; use `cd ... || exit`";
+                diagnostics.push(Diagnostic::new(Severity::Warning, UNCHECKED_CD, msg));
+            }
+        });
+
+        diagnostics
+    }
+}
+
+/// Reports a pipeline which starts by `cat`ing a single file, e.g.
+/// `cat file | grep foo`, which could be written as `grep foo <file`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UselessCat;
+
+impl Lint for UselessCat {
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        walk_listables(cmds, false, &mut |cmd, _| {
+            let cat = match *cmd {
+                ListableCommand::Pipe(ref pipeline) => match pipeline.commands().next() {
+                    Some(PipeableCommand::Simple(ref cmd)) => cmd,
+                    _ => return,
+                },
+                ListableCommand::Single(_) => return,
+            };
+
+            let has_redirects = !cat.redirects_or_env_vars.is_empty()
+                || cat.redirects_or_cmd_words.iter().any(|word| match *word {
+                    RedirectOrCmdWord::Redirect(_) => true,
+                    RedirectOrCmdWord::CmdWord(_) => false,
+                });
+            if has_redirects || command_name(cat).as_deref() != Some("cat") {
+                return;
+            }
+
+            let file = match *cmd_words(cat).skip(1).collect::<Vec<_>>() {
+                [file] if !is_option(file) => file,
+                _ => return,
+            };
+
+            let msg = format!(
+                "`cat {0}` only feeds a single file into the pipeline; \
+                 redirect the input of the next command instead, e.g. `cmd <{0}`",
+                display_word(file)
+            );
+            diagnostics.push(Diagnostic::new(Severity::Info, USELESS_CAT, msg));
+        });
+
+        diagnostics
+    }
+}
+
+/// Reports tests which compare the exit status of the previous command,
+/// e.g. `[ $? -eq 0 ]` or `[[ $? != 0 ]]`, which can be written more
+/// robustly by checking the command directly, e.g. `if cmd; then`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExitStatusComparison;
+
+impl Lint for ExitStatusComparison {
+    fn check(&self, cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        walk_commands(cmds, &mut |node| {
+            let compared = match node {
+                Node::Simple(cmd) => {
+                    let mut words = cmd_words(cmd);
+                    // `[` is parsed as a glob character rather than a literal
+                    let is_test = match words.next() {
+                        Some(name) => {
+                            is_one_of(name, &["test"])
+                                || matches!(
+                                    word_parts(name),
+                                    [Word::Simple(SimpleWord::SquareOpen)]
+                                )
+                        }
+                        None => false,
+                    };
+                    let args = words.collect::<Vec<_>>();
+
+                    is_test
+                        && args.iter().any(|&w| is_exit_status(w))
+                        && args.iter().any(|&w| is_one_of(w, COMPARISONS))
+                }
+                Node::Compound(cmd) => match cmd.kind {
+                    CompoundCommandKind::Conditional(ref expr) => compares_exit_status(expr),
+                    _ => false,
+                },
+                Node::FunctionDef => false,
+            };
+
+            if compared {
+                let msg = "comparing `$?` is an indirect way of checking the status \
+                           of the previous command; check the command directly, \
+                           e.g. `if cmd; then`";
+                diagnostics.push(Diagnostic::new(Severity::Info, EXIT_STATUS_COMPARISON, msg));
+            }
+        });
+
+        diagnostics
+    }
+}
+
+/// The comparison operators of `[` and `test`.
+const COMPARISONS: &[&str] = &["=", "==", "!=", "-eq", "-ne", "-lt", "-le", "-gt", "-ge"];
+
+/// Checks if a word is exactly `$?`, quoted or otherwise.
+fn is_exit_status(word: &TopLevelWord<String>) -> bool {
+    match *word_parts(word) {
+        [Word::Simple(SimpleWord::Param(Parameter::Question))] => true,
+        [Word::DoubleQuoted(ref words)] => {
+            matches!(words[..], [SimpleWord::Param(Parameter::Question)])
+        }
+        _ => false,
+    }
+}
+
+fn compares_exit_status(expr: &Conditional<TopLevelWord<String>>) -> bool {
+    match *expr {
+        Conditional::Binary(ref left, _, ref right) => {
+            is_exit_status(left) || is_exit_status(right)
+        }
+        Conditional::Not(ref expr) => compares_exit_status(expr),
+        Conditional::And(ref left, ref right) | Conditional::Or(ref left, ref right) => {
+            compares_exit_status(left) || compares_exit_status(right)
+        }
+        Conditional::Word(_) | Conditional::Unary(..) => false,
+    }
+}
+
+/// Checks if a word is a literal equal to any of the given strings.
+fn is_one_of(word: &TopLevelWord<String>, strs: &[&str]) -> bool {
+    match literal_word(word) {
+        Some(word) => strs.contains(&&*word),
+        None => false,
+    }
+}
+
+/// Checks if a word is a literal starting with a `-`, e.g. `-n`.
+fn is_option(word: &TopLevelWord<String>) -> bool {
+    match literal_word(word) {
+        Some(word) => word.starts_with('-'),
+        None => false,
+    }
+}
+
+/// Returns the name of a simple command, if it is a literal.
+fn command_name(cmd: &DefaultSimpleCommand) -> Option<String> {
+    cmd_words(cmd).next().and_then(literal_word)
+}
+
+/// Walks all commands (recursing into compound commands and function bodies)
+/// and invokes `f` on every listable command (i.e. a pipeline or a single
+/// command) in source order, along with whether its status is checked: it is
+/// followed by `&&` or `||`, or is within a guard or a checked compound command.
+///
+/// Commands nested within word substitutions are not visited.
+fn walk_listables<'a, F>(cmds: &'a [TopLevelCommand<String>], checked: bool, f: &mut F)
+where
+    F: FnMut(&'a DefaultListableCommand, bool),
+{
+    for cmd in cmds {
+        let list = match cmd.0 {
+            Command::Job(ref list) | Command::List(ref list) => list,
+            Command::Unparsed(_) => continue,
+        };
+
+        let rest = list.rest.iter().map(|and_or| match *and_or {
+            AndOr::And(ref cmd) | AndOr::Or(ref cmd) => cmd,
+        });
+        let mut listables = Some(&list.first).into_iter().chain(rest).peekable();
+
+        while let Some(listable) = listables.next() {
+            let handled = checked || listables.peek().is_some();
+            f(listable, handled);
+
+            match *listable {
+                ListableCommand::Single(ref cmd) => walk_pipeable(cmd, handled, f),
+                ListableCommand::Pipe(ref pipeline) => {
+                    for cmd in pipeline.commands() {
+                        walk_pipeable(cmd, handled, f);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn walk_pipeable<'a, F>(cmd: &'a DefaultPipeableCommand, checked: bool, f: &mut F)
+where
+    F: FnMut(&'a DefaultListableCommand, bool),
+{
+    match *cmd {
+        PipeableCommand::Simple(_) => {}
+        PipeableCommand::Compound(ref cmd) => walk_compound(cmd, checked, f),
+        PipeableCommand::FunctionDef(_, _, ref body) => walk_compound(body, false, f),
+    }
+}
+
+fn walk_compound<'a, F>(cmd: &'a DefaultCompoundCommand, checked: bool, f: &mut F)
+where
+    F: FnMut(&'a DefaultListableCommand, bool),
+{
+    match cmd.kind {
+        CompoundCommandKind::While(ref gbp) | CompoundCommandKind::Until(ref gbp) => {
+            walk_listables(&gbp.guard, true, f);
+            walk_listables(&gbp.body, checked, f);
+        }
+
+        CompoundCommandKind::If {
+            ref conditionals,
+            ref else_branch,
+        } => {
+            for gbp in conditionals {
+                walk_listables(&gbp.guard, true, f);
+                walk_listables(&gbp.body, checked, f);
+            }
+
+            if let Some(ref els) = *else_branch {
+                walk_listables(els, checked, f);
+            }
+        }
+
+        ref kind => {
+            for body in kind.bodies() {
+                walk_listables(body, checked, f);
+            }
+        }
+    }
+}
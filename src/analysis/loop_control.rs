@@ -0,0 +1,206 @@
+//! Checks for control flow builtins used outside of the context they apply to.
+//!
+//! Each `break` and `continue` is checked against the loops which enclose it,
+//! and each `return` against the functions which enclose it. Loops enclosing
+//! a function declaration do not enclose its body, since the function may be
+//! invoked from anywhere. Commands within subshells are checked as if they
+//! were run by the parent shell.
+//!
+//! The builtins are recognized via `builtins::SpecialCommand`, so invocations
+//! whose name is not a literal word (or which go through `builtin` or
+//! `command`) are not checked.
+
+use super::builtins::{BuiltinKind, Operand, SpecialCommand};
+use super::{Diagnostic, Severity};
+use crate::ast::{
+    AndOr, Command, CompoundCommandKind, DefaultCompoundCommand, DefaultListableCommand,
+    DefaultPipeableCommand, ListableCommand, PipeableCommand, TopLevelCommand,
+};
+use crate::parse::Dialect;
+
+/// The code reported for `break` or `continue` outside of any loop.
+pub const OUTSIDE_LOOP: &str = "loop-control-outside-loop";
+/// The code reported for `break n` or `continue n` where `n` is not a valid loop count,
+/// or exceeds the number of enclosing loops.
+pub const COUNT_OUT_OF_RANGE: &str = "loop-control-count-out-of-range";
+/// The code reported for `return` outside of any function.
+pub const RETURN_OUTSIDE_FUNCTION: &str = "return-outside-function";
+
+/// Reports any `break` or `continue` invocations outside of a loop, or whose
+/// loop count exceeds the number of loops enclosing them.
+///
+/// Invocations of `return` outside of a function are reported for the `Bash`
+/// dialect only, where they fail unless the script is sourced. POSIX leaves
+/// their behavior unspecified instead (e.g. `dash` treats them like `exit`).
+pub fn check(cmds: &[TopLevelCommand<String>], dialect: Dialect) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        dialect,
+        loops: Vec::new(),
+        functions: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.commands(cmds);
+    checker.diagnostics
+}
+
+#[derive(Debug)]
+struct Checker {
+    dialect: Dialect,
+    /// The kinds of the loops enclosing the current command, outermost first.
+    loops: Vec<&'static str>,
+    /// The names of the functions enclosing the current command, outermost first.
+    functions: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn commands(&mut self, cmds: &[TopLevelCommand<String>]) {
+        for cmd in cmds {
+            let list = match cmd.0 {
+                Command::Job(ref list) | Command::List(ref list) => list,
+                Command::Unparsed(_) => continue,
+            };
+
+            self.listable(&list.first);
+            for and_or in &list.rest {
+                match *and_or {
+                    AndOr::And(ref cmd) | AndOr::Or(ref cmd) => self.listable(cmd),
+                }
+            }
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                for cmd in pipeline.commands() {
+                    self.pipeable(cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => {
+                if let Some(special) = SpecialCommand::classify(cmd) {
+                    self.special(&special);
+                }
+            }
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(ref name, _, ref body) => {
+                let loops = std::mem::take(&mut self.loops);
+                self.functions.push(name.clone());
+                self.compound(body);
+                self.functions.pop();
+                self.loops = loops;
+            }
+        }
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand) {
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+                self.commands(cmds)
+            }
+
+            CompoundCommandKind::While(ref gbp) | CompoundCommandKind::Until(ref gbp) => {
+                let kind = match cmd.kind {
+                    CompoundCommandKind::While(_) => "while",
+                    _ => "until",
+                };
+
+                self.loops.push(kind);
+                self.commands(&gbp.guard);
+                self.commands(&gbp.body);
+                self.loops.pop();
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                for gbp in conditionals {
+                    self.commands(&gbp.guard);
+                    self.commands(&gbp.body);
+                }
+
+                if let Some(ref els) = *else_branch {
+                    self.commands(els);
+                }
+            }
+
+            CompoundCommandKind::For { ref body, .. } => {
+                self.loops.push("for");
+                self.commands(body);
+                self.loops.pop();
+            }
+
+            CompoundCommandKind::Case { ref arms, .. } => {
+                for arm in arms {
+                    self.commands(&arm.body);
+                }
+            }
+
+            CompoundCommandKind::Conditional(_) | CompoundCommandKind::Arithmetic(_) => {}
+        }
+    }
+
+    fn special(&mut self, special: &SpecialCommand<'_>) {
+        let name = match special.kind {
+            BuiltinKind::Break => "break",
+            BuiltinKind::Continue => "continue",
+            BuiltinKind::Return => return self.ret(),
+            _ => return,
+        };
+
+        if self.loops.is_empty() {
+            let msg = match self.functions.last() {
+                Some(function) => format!(
+                    "`{}` is not within a loop in the body of function `{}`",
+                    name, function
+                ),
+                None => format!("`{}` is not within a loop", name),
+            };
+            self.diagnostics
+                .push(Diagnostic::new(Severity::Error, OUTSIDE_LOOP, msg));
+            return;
+        }
+
+        match special.operand {
+            Operand::Number(n) if n < 1 => {
+                let msg = format!(
+                    "`{} {}` is invalid, the loop count must be at least 1",
+                    name, n
+                );
+                self.diagnostics
+                    .push(Diagnostic::new(Severity::Error, COUNT_OUT_OF_RANGE, msg));
+            }
+            Operand::Number(n) if n > self.loops.len() as i64 => {
+                let msg = format!(
+                    "`{} {}` exceeds the {} enclosing loop(s): `{}`",
+                    name,
+                    n,
+                    self.loops.len(),
+                    self.loops.join("` > `"),
+                );
+                self.diagnostics
+                    .push(Diagnostic::new(Severity::Warning, COUNT_OUT_OF_RANGE, msg));
+            }
+            _ => {}
+        }
+    }
+
+    fn ret(&mut self) {
+        if self.dialect == Dialect::Bash && self.functions.is_empty() {
+            let msg = "`return` is not within a function, \
+                       and will fail unless the script is sourced";
+            self.diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                RETURN_OUTSIDE_FUNCTION,
+                msg,
+            ));
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Best-effort conversion of bash extensions into POSIX equivalents.
+//!
+//! Only constructs which have a safe, mechanical POSIX equivalent are
+//! rewritten (see `Conversions`), e.g. `cmd &>out` becomes `cmd >out 2>&1`,
+//! and `[[ $x == y ]]` becomes `[ "$x" = "y" ]`. Conditional commands are
+//! only converted if they consist of a single test (optionally negated) whose
+//! behavior is identical under `test`: the right side of `==` and `!=` must
+//! not contain patterns or unquoted expansions (which would be matched as
+//! patterns), and numeric comparisons must only compare integers or parameters.
+//!
+//! Any extensions which remain after the conversion are reported as
+//! `Diagnostic`s, so that they can be rewritten by hand. Printing the
+//! converted AST (see `ast::printer`) yields the converted source.
+
+use super::{literal_word, word_parts, Diagnostic, Severity};
+use crate::ast::printer::ToShellString;
+use crate::ast::visit::{
+    walk_arithmetic_mut, walk_compound_command_kind_mut, walk_compound_command_mut,
+    walk_parameter_substitution_mut, walk_pattern_body_pair_mut, walk_pipeable_command_mut,
+    walk_pipeline_mut, walk_redirect_mut, walk_simple_command_mut, walk_word_mut,
+    DefaultPatternBodyPair, DefaultPipeline, VisitorMut,
+};
+use crate::ast::{
+    AndOrList, Arithmetic, ArithmeticTarget, BinaryTest, CaseTerminator, Command, ComplexWord,
+    CompoundCommandKind, Conditional, DefaultArithmetic, DefaultCompoundCommand,
+    DefaultCompoundCommandKind, DefaultConditional, DefaultParameterSubstitution,
+    DefaultPipeableCommand, DefaultRedirect, DefaultSimpleCommand, DefaultSimpleWord,
+    ListableCommand, PipeableCommand, Radix, Redirect, RedirectOrCmdWord, RedirectOrEnvVar,
+    SimpleCommand, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+
+/// The code reported for extensions which were not converted.
+pub const UNCONVERTED: &str = "posix-unconverted";
+
+/// The letters of the unary operators supported by the POSIX `test` utility.
+const POSIX_UNARY_TESTS: &str = "bcdefghLnprSstuwxz";
+
+/// The extensions which should be converted by `convert`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Conversions {
+    /// Converts simple `[[ ... ]]` commands into `[ ... ]`.
+    pub conditionals: bool,
+    /// Converts `&>file` into `>file 2>&1`, and `&>>file` into `>>file 2>&1`.
+    pub redirect_both: bool,
+    /// Converts `cmd1 |& cmd2` into `cmd1 2>&1 | cmd2`.
+    pub pipe_stderr: bool,
+    /// Converts `$(< file)` into `$(cat file)`.
+    pub read_file: bool,
+}
+
+impl Default for Conversions {
+    /// Enables all conversions.
+    fn default() -> Self {
+        Conversions {
+            conditionals: true,
+            redirect_both: true,
+            pipe_stderr: true,
+            read_file: true,
+        }
+    }
+}
+
+/// The outcome of converting a script with `convert`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Conversion {
+    /// The number of constructs which were converted.
+    pub converted: usize,
+    /// The extensions which were left as is, in source order.
+    pub unconverted: Vec<Diagnostic>,
+}
+
+/// Rewrites the enabled `conversions` in place, and reports any extensions
+/// which remain (including any whose conversion was not enabled).
+pub fn convert(cmds: &mut Vec<TopLevelCommand<String>>, conversions: Conversions) -> Conversion {
+    let mut converter = Converter {
+        conversions,
+        result: Conversion::default(),
+    };
+    converter.visit_commands(cmds);
+    converter.result
+}
+
+#[derive(Debug)]
+struct Converter {
+    conversions: Conversions,
+    result: Conversion,
+}
+
+impl Converter {
+    fn report<M: Into<String>>(&mut self, message: M) {
+        self.result
+            .unconverted
+            .push(Diagnostic::new(Severity::Warning, UNCONVERTED, message));
+    }
+
+    /// Replaces any `&>` or `&>>` redirects with their POSIX equivalents.
+    fn convert_redirects<T, F, G>(&mut self, items: &mut Vec<T>, as_redirect: F, wrap: G)
+    where
+        F: Fn(&T) -> Option<&DefaultRedirect>,
+        G: Fn(DefaultRedirect) -> T,
+    {
+        if !self.conversions.redirect_both {
+            return;
+        }
+
+        let mut i = 0;
+        while i < items.len() {
+            let replacement = match as_redirect(&items[i]) {
+                Some(Redirect::WriteBoth(word)) => Redirect::Write(None, word.clone()),
+                Some(Redirect::AppendBoth(word)) => Redirect::Append(None, word.clone()),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            items[i] = wrap(replacement);
+            items.insert(i + 1, wrap(dup_stderr()));
+            self.result.converted += 1;
+            i += 2;
+        }
+    }
+}
+
+impl VisitorMut for Converter {
+    fn visit_pipeline(&mut self, pipeline: &mut DefaultPipeline) {
+        walk_pipeline_mut(self, pipeline);
+
+        for i in 1..pipeline.segments.len() {
+            if !pipeline.segments[i].pipe_stderr {
+                continue;
+            }
+
+            // The standard error of the command before the pipe is redirected,
+            // after any of its own redirects (unlike with `2>&1 |`)
+            let converted = self.conversions.pipe_stderr
+                && match pipeline.segments[i - 1].cmd {
+                    PipeableCommand::Simple(ref mut cmd) => {
+                        let redirect = RedirectOrCmdWord::Redirect(dup_stderr());
+                        cmd.redirects_or_cmd_words.push(redirect);
+                        true
+                    }
+                    PipeableCommand::Compound(ref mut cmd) => {
+                        cmd.io.push(dup_stderr());
+                        true
+                    }
+                    PipeableCommand::FunctionDef(..) => false,
+                };
+
+            if converted {
+                pipeline.segments[i].pipe_stderr = false;
+                self.result.converted += 1;
+            } else {
+                self.report("`|&` pipes cannot be converted");
+            }
+        }
+    }
+
+    fn visit_pipeable_command(&mut self, cmd: &mut DefaultPipeableCommand) {
+        let test = match *cmd {
+            PipeableCommand::Compound(ref compound) if self.conversions.conditionals => {
+                match compound.kind {
+                    CompoundCommandKind::Conditional(ref cond) => {
+                        test_args(cond).map(|args| (args, compound.io.clone()))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        if let Some((args, io)) = test {
+            let words = Some(literal("["))
+                .into_iter()
+                .chain(args)
+                .chain(Some(literal("]")))
+                .map(RedirectOrCmdWord::CmdWord);
+            let redirects = io.into_iter().map(RedirectOrCmdWord::Redirect);
+
+            *cmd = PipeableCommand::Simple(Box::new(SimpleCommand {
+                redirects_or_env_vars: Vec::new(),
+                redirects_or_cmd_words: words.chain(redirects).collect(),
+            }));
+            self.result.converted += 1;
+        }
+
+        walk_pipeable_command_mut(self, cmd);
+    }
+
+    fn visit_simple_command(&mut self, cmd: &mut DefaultSimpleCommand) {
+        self.convert_redirects(
+            &mut cmd.redirects_or_env_vars,
+            |item| match *item {
+                RedirectOrEnvVar::Redirect(ref redirect) => Some(redirect),
+                RedirectOrEnvVar::EnvVar(..) => None,
+            },
+            RedirectOrEnvVar::Redirect,
+        );
+        self.convert_redirects(
+            &mut cmd.redirects_or_cmd_words,
+            |item| match *item {
+                RedirectOrCmdWord::Redirect(ref redirect) => Some(redirect),
+                RedirectOrCmdWord::CmdWord(_) => None,
+            },
+            RedirectOrCmdWord::Redirect,
+        );
+        walk_simple_command_mut(self, cmd);
+    }
+
+    fn visit_compound_command(&mut self, cmd: &mut DefaultCompoundCommand) {
+        self.convert_redirects(&mut cmd.io, |redirect| Some(redirect), |redirect| redirect);
+        walk_compound_command_mut(self, cmd);
+    }
+
+    fn visit_compound_command_kind(&mut self, kind: &mut DefaultCompoundCommandKind) {
+        if let CompoundCommandKind::Conditional(ref cond) = *kind {
+            self.report(format!(
+                "`[[ {} ]]` cannot be converted to `[ ... ]`",
+                cond.to_shell_string()
+            ));
+        }
+
+        walk_compound_command_kind_mut(self, kind);
+    }
+
+    fn visit_pattern_body_pair(&mut self, pair: &mut DefaultPatternBodyPair) {
+        match pair.terminator {
+            CaseTerminator::Break => {}
+            CaseTerminator::FallThrough => self.report("`;&` case terminators cannot be converted"),
+            CaseTerminator::Continue => self.report("`;;&` case terminators cannot be converted"),
+        }
+
+        walk_pattern_body_pair_mut(self, pair);
+    }
+
+    fn visit_redirect(&mut self, redirect: &mut DefaultRedirect) {
+        match *redirect {
+            Redirect::WriteBoth(_) => self.report("`&>` redirects were not converted"),
+            Redirect::AppendBoth(_) => self.report("`&>>` redirects were not converted"),
+            _ => {}
+        }
+
+        walk_redirect_mut(self, redirect);
+    }
+
+    fn visit_word(&mut self, word: &mut TopLevelWord<String>) {
+        let parts = word_parts(word);
+        for (i, part) in parts.iter().enumerate() {
+            let is_dollar = match *part {
+                Word::Simple(SimpleWord::Literal(ref s)) => s.ends_with('$'),
+                _ => false,
+            };
+
+            if let (true, Some(Word::SingleQuoted(s))) = (is_dollar, parts.get(i + 1)) {
+                self.report(format!(
+                    "`$'{}'` quoting cannot be converted, consider \"$(printf '{}')\" instead \
+                     (which strips any trailing newlines)",
+                    s, s
+                ));
+            }
+        }
+
+        walk_word_mut(self, word);
+    }
+
+    fn visit_parameter_substitution(&mut self, subst: &mut DefaultParameterSubstitution) {
+        use crate::ast::ParameterSubstitution::*;
+
+        let unsupported = match *subst {
+            ReadFile(ref file) if self.conversions.read_file => {
+                let cat = simple_command(vec![literal("cat"), file.clone()]);
+                *subst = Command(vec![cat]);
+                self.result.converted += 1;
+                None
+            }
+            ReadFile(_) => Some("`$(< file)` substitutions were not converted"),
+            ProcessRead(_) => Some("`<(...)` process substitutions cannot be converted"),
+            ProcessWrite(_) => Some("`>(...)` process substitutions cannot be converted"),
+            Substring(..) => Some("`${x:offset}` substrings cannot be converted"),
+            ReplaceString(..) | ReplaceStringAll(..) => {
+                Some("`${x/pattern/string}` replacements cannot be converted")
+            }
+            Lowercase(..) => Some("`${x,}` lowercase conversions cannot be converted"),
+            Uppercase(..) => Some("`${x^}` uppercase conversions cannot be converted"),
+            _ => None,
+        };
+
+        if let Some(message) = unsupported {
+            self.report(message);
+        }
+
+        walk_parameter_substitution_mut(self, subst);
+    }
+
+    fn visit_arithmetic(&mut self, arith: &mut DefaultArithmetic) {
+        let unsupported = match *arith {
+            Arithmetic::Pow(..) => Some("`**`"),
+            Arithmetic::PreIncr(_) | Arithmetic::PostIncr(_) => Some("`++`"),
+            Arithmetic::PreDecr(_) | Arithmetic::PostDecr(_) => Some("`--`"),
+            Arithmetic::Index(..)
+            | Arithmetic::Assign(ArithmeticTarget::Index(..), _)
+            | Arithmetic::AssignOp(_, ArithmeticTarget::Index(..), _) => Some("array indexing"),
+            Arithmetic::RadixLiteral(_, Radix::Explicit(_), _) => Some("`base#digits` literals"),
+            _ => None,
+        };
+
+        if let Some(what) = unsupported {
+            self.report(format!("{} within arithmetic cannot be converted", what));
+        }
+
+        walk_arithmetic_mut(self, arith);
+    }
+}
+
+/// Returns the arguments of a `test` command equivalent to a conditional
+/// expression, if there is one.
+fn test_args(cond: &DefaultConditional) -> Option<Vec<TopLevelWord<String>>> {
+    match *cond {
+        Conditional::Word(ref word) => Some(vec![literal("-n"), quoted(word)?]),
+
+        Conditional::Unary(op, ref word) if POSIX_UNARY_TESTS.contains(op) => {
+            Some(vec![literal(&format!("-{}", op)), quoted(word)?])
+        }
+
+        Conditional::Binary(ref lhs, op, ref rhs) => {
+            let op = match op {
+                BinaryTest::Match if is_plain_string(rhs) => "=",
+                BinaryTest::NotMatch if is_plain_string(rhs) => "!=",
+                BinaryTest::NumEq
+                | BinaryTest::NumNotEq
+                | BinaryTest::NumLess
+                | BinaryTest::NumLessEq
+                | BinaryTest::NumGreater
+                | BinaryTest::NumGreaterEq
+                    if is_integer(lhs) && is_integer(rhs) =>
+                {
+                    op.as_str()
+                }
+                _ => return None,
+            };
+
+            Some(vec![quoted(lhs)?, literal(op), quoted(rhs)?])
+        }
+
+        Conditional::Not(ref cond) => match **cond {
+            Conditional::Word(_) | Conditional::Unary(..) | Conditional::Binary(..) => {
+                let mut args = vec![literal("!")];
+                args.extend(test_args(cond)?);
+                Some(args)
+            }
+            _ => None,
+        },
+
+        _ => None,
+    }
+}
+
+/// Checks if a word (on the right side of `==` or `!=`) is compared as a
+/// plain string, rather than matched as a pattern.
+fn is_plain_string(word: &TopLevelWord<String>) -> bool {
+    word_parts(word).iter().all(|part| match *part {
+        Word::Simple(SimpleWord::Literal(_))
+        | Word::Simple(SimpleWord::Escaped(_))
+        | Word::Simple(SimpleWord::Colon) => true,
+        Word::Simple(_) | Word::Braces(_) => false,
+        Word::SingleQuoted(_) | Word::DoubleQuoted(_) => true,
+    })
+}
+
+/// Checks if a word is an integer literal or a single parameter, whose value
+/// `test` can compare numerically (unlike an arithmetic expression).
+fn is_integer(word: &TopLevelWord<String>) -> bool {
+    if let Some(literal) = literal_word(word) {
+        let digits = literal.strip_prefix('-').unwrap_or(&literal);
+        return !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+    }
+
+    match *word_parts(word) {
+        [Word::Simple(SimpleWord::Param(_))] => true,
+        [Word::DoubleQuoted(ref words)] => matches!(**words, [SimpleWord::Param(_)]),
+        _ => false,
+    }
+}
+
+/// Returns a word which expands to the same string as `word` does within
+/// `[[ ... ]]`, but which is not subject to field splitting or globbing, by
+/// double quoting any unquoted parts. Returns `None` if the word is subject
+/// to tilde or brace expansion, neither of which happen within double quotes.
+fn quoted(word: &TopLevelWord<String>) -> Option<TopLevelWord<String>> {
+    let mut parts = Vec::new();
+    for part in word_parts(word) {
+        let simple = match *part {
+            Word::Simple(ref simple) => quoted_simple(simple)?,
+            Word::SingleQuoted(_) | Word::DoubleQuoted(_) => {
+                parts.push(part.clone());
+                continue;
+            }
+            // Brace expansions cannot be quoted without being disabled
+            Word::Braces(_) => return None,
+        };
+
+        match parts.last_mut() {
+            Some(&mut Word::DoubleQuoted(ref mut words)) => words.push(simple),
+            _ => parts.push(Word::DoubleQuoted(vec![simple])),
+        }
+    }
+
+    let word = match parts.len() {
+        0 => ComplexWord::Single(Word::DoubleQuoted(Vec::new())),
+        1 => ComplexWord::Single(parts.pop().unwrap()),
+        _ => ComplexWord::Concat(parts),
+    };
+    Some(TopLevelWord(word))
+}
+
+/// Returns the equivalent of an unquoted part of a word within double quotes.
+fn quoted_simple(word: &DefaultSimpleWord) -> Option<DefaultSimpleWord> {
+    let literal = |s: &str| Some(SimpleWord::Literal(String::from(s)));
+
+    match *word {
+        // Within double quotes, backslashes only escape a few characters
+        SimpleWord::Escaped(ref s) if !matches!(&**s, "$" | "`" | "\"" | "\\") => literal(s),
+        SimpleWord::Star => literal("*"),
+        SimpleWord::Question => literal("?"),
+        SimpleWord::SquareOpen => literal("["),
+        SimpleWord::SquareClose => literal("]"),
+        SimpleWord::Tilde | SimpleWord::TildePrefix(_) => None,
+        ref word => Some(word.clone()),
+    }
+}
+
+fn literal(s: &str) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(
+        String::from(s),
+    ))))
+}
+
+/// Returns a redirect of standard error to standard output, i.e. `2>&1`.
+fn dup_stderr() -> DefaultRedirect {
+    Redirect::DupWrite(Some(2), literal("1"))
+}
+
+fn simple_command(words: Vec<TopLevelWord<String>>) -> TopLevelCommand<String> {
+    let cmd = SimpleCommand {
+        redirects_or_env_vars: Vec::new(),
+        redirects_or_cmd_words: words.into_iter().map(RedirectOrCmdWord::CmdWord).collect(),
+    };
+
+    TopLevelCommand(Command::List(AndOrList {
+        first: ListableCommand::Single(PipeableCommand::Simple(Box::new(cmd))),
+        rest: Vec::new(),
+    }))
+}
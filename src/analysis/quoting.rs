@@ -0,0 +1,294 @@
+//! Checks for quoting styles which do not match the contents being quoted.
+//!
+//! Double quotes which contain neither expansions nor escapes behave exactly
+//! like single quotes, which make it clear to a reader that nothing within
+//! them is expanded. Conversely, single quotes which contain what looks like a
+//! parameter expansion (e.g. `'$HOME/bin'`) are usually a mistake, since the
+//! expansion is passed through verbatim instead of being expanded. Finally,
+//! an unquoted `$@` splits every positional parameter into fields (and pathname
+//! expands them), instead of passing each one along as is like `"$@"` does
+//! (see `expansions::Expansion::positional_parameters`).
+//!
+//! Each finding comes with a replacement for the offending quoted part, which
+//! can be applied to the AST in place via `fix`. Printing the fixed AST (see
+//! `ast::printer`) yields the corrected source.
+
+use super::{cmd_words, literal_word, Diagnostic, Severity};
+use crate::ast::printer::ToShellString;
+use crate::ast::visit::{
+    walk_simple_command, walk_simple_command_mut, walk_word_part, walk_word_part_mut, Visitor,
+    VisitorMut,
+};
+use crate::ast::{
+    ComplexWord, DefaultSimpleCommand, DefaultSimpleWord, DefaultWord, Parameter, SimpleWord,
+    TopLevelCommand, TopLevelWord, Word,
+};
+
+/// The code reported for double quotes which could be single quotes.
+pub const PREFER_SINGLE_QUOTES: &str = "quote-prefer-single";
+/// The code reported for single quotes which contain a parameter expansion.
+pub const SINGLE_QUOTED_EXPANSION: &str = "quote-single-expansion";
+/// The code reported for an unquoted `$@`.
+pub const UNQUOTED_AT: &str = "quote-unquoted-at";
+
+/// Commands which interpret (some of) their arguments as code of their own,
+/// where single quoted expansions are intentionally left for them to expand.
+const CODE_COMMANDS: &[&str] = &[
+    "awk", "bash", "dash", "eval", "gawk", "jq", "ksh", "mawk", "nawk", "perl", "python",
+    "python3", "ruby", "sed", "sh", "ssh", "su", "trap", "zsh",
+];
+
+/// A finding along with the suggested replacement of the quoted word part.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Suggestion {
+    /// The finding describing the problem.
+    pub diagnostic: Diagnostic,
+    /// The quoted part of a word, as it appears in the AST.
+    pub original: DefaultWord,
+    /// The part which should replace `original`.
+    pub replacement: DefaultWord,
+}
+
+/// Reports any double quoted strings without expansions or escapes, any single
+/// quoted strings which contain a parameter expansion, and any unquoted `$@`.
+///
+/// Single quoted arguments of commands which evaluate their arguments as code
+/// (e.g. `awk`, `sed`, `sh -c`, or `trap`) are not reported.
+pub fn check(cmds: &[TopLevelCommand<String>]) -> Vec<Diagnostic> {
+    suggest(cmds).into_iter().map(|s| s.diagnostic).collect()
+}
+
+/// Same as `check`, but also returns the replacement for each reported word part.
+pub fn suggest(cmds: &[TopLevelCommand<String>]) -> Vec<Suggestion> {
+    let mut collector = Collector {
+        in_code_command: false,
+        suggestions: Vec::new(),
+    };
+    collector.visit_commands(cmds);
+    collector.suggestions
+}
+
+/// Replaces every word part reported by `suggest` with its replacement,
+/// returning the number of parts which were replaced.
+pub fn fix(cmds: &mut Vec<TopLevelCommand<String>>) -> usize {
+    let mut fixer = Fixer {
+        in_code_command: false,
+        fixed: 0,
+    };
+    fixer.visit_commands(cmds);
+    fixer.fixed
+}
+
+#[derive(Debug)]
+struct Collector {
+    in_code_command: bool,
+    suggestions: Vec<Suggestion>,
+}
+
+impl Visitor for Collector {
+    fn visit_simple_command(&mut self, cmd: &DefaultSimpleCommand) {
+        let outer = self.in_code_command;
+        self.in_code_command = is_code_command(cmd);
+        walk_simple_command(self, cmd);
+        self.in_code_command = outer;
+    }
+
+    fn visit_word_part(&mut self, word: &DefaultWord) {
+        if let Some(suggestion) = suggest_part(word, self.in_code_command) {
+            self.suggestions.push(suggestion);
+        }
+
+        walk_word_part(self, word);
+    }
+}
+
+#[derive(Debug)]
+struct Fixer {
+    in_code_command: bool,
+    fixed: usize,
+}
+
+impl VisitorMut for Fixer {
+    fn visit_simple_command(&mut self, cmd: &mut DefaultSimpleCommand) {
+        let outer = self.in_code_command;
+        self.in_code_command = is_code_command(cmd);
+        walk_simple_command_mut(self, cmd);
+        self.in_code_command = outer;
+    }
+
+    fn visit_word_part(&mut self, word: &mut DefaultWord) {
+        if let Some(suggestion) = suggest_part(word, self.in_code_command) {
+            *word = suggestion.replacement;
+            self.fixed += 1;
+        }
+
+        walk_word_part_mut(self, word);
+    }
+}
+
+/// Checks if a simple command evaluates its arguments as code, e.g. `awk` or `sh`.
+fn is_code_command(cmd: &DefaultSimpleCommand) -> bool {
+    let name = match cmd_words(cmd).next().and_then(literal_word) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    // Commands invoked via an absolute or relative path, e.g. `/bin/sh`
+    let name = name.rsplit('/').next().unwrap_or(&name);
+    CODE_COMMANDS.contains(&name)
+}
+
+fn suggest_part(word: &DefaultWord, in_code_command: bool) -> Option<Suggestion> {
+    match *word {
+        Word::DoubleQuoted(ref words) => {
+            let text = unexpanded_text(words)?;
+            let replacement = Word::SingleQuoted(text);
+            let message = format!(
+                "{} contains no expansions or escapes, use {} instead",
+                render(word),
+                render(&replacement)
+            );
+
+            Some(Suggestion {
+                diagnostic: Diagnostic::new(Severity::Info, PREFER_SINGLE_QUOTES, message),
+                original: word.clone(),
+                replacement,
+            })
+        }
+
+        Word::SingleQuoted(ref text) if !in_code_command => {
+            let words = expand_text(text)?;
+            let name = words.iter().find_map(|w| match *w {
+                SimpleWord::Param(Parameter::Var(ref name)) => Some(name.clone()),
+                _ => None,
+            })?;
+
+            let replacement = Word::DoubleQuoted(words);
+            let message = format!(
+                "${} is not expanded within single quotes in {}, use {} if it should be",
+                name,
+                render(word),
+                render(&replacement)
+            );
+
+            Some(Suggestion {
+                diagnostic: Diagnostic::new(Severity::Warning, SINGLE_QUOTED_EXPANSION, message),
+                original: word.clone(),
+                replacement,
+            })
+        }
+
+        Word::Simple(SimpleWord::Param(Parameter::At)) => {
+            let replacement = Word::DoubleQuoted(vec![SimpleWord::Param(Parameter::At)]);
+            let message = format!(
+                "{} splits every positional parameter into fields, use {} to keep each one intact",
+                render(word),
+                render(&replacement)
+            );
+
+            Some(Suggestion {
+                diagnostic: Diagnostic::new(Severity::Warning, UNQUOTED_AT, message),
+                original: word.clone(),
+                replacement,
+            })
+        }
+
+        Word::SingleQuoted(_) | Word::Simple(_) | Word::Braces(_) => None,
+    }
+}
+
+/// Returns the contents of double quotes if they can be single quoted instead,
+/// i.e. they are non-empty literals which do not contain a single quote.
+fn unexpanded_text(words: &[DefaultSimpleWord]) -> Option<String> {
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut text = String::new();
+    for w in words {
+        match *w {
+            SimpleWord::Literal(ref s) => text.push_str(s),
+            _ => return None,
+        }
+    }
+
+    if text.contains('\'') {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Splits single quoted text into the parts of an equivalent double quoted
+/// string in which any `$name` or `${name}` is expanded. Returns `None` if the
+/// text does not contain any such expansion.
+fn expand_text(text: &str) -> Option<Vec<DefaultSimpleWord>> {
+    fn is_name_start(c: char) -> bool {
+        c == '_' || c.is_ascii_alphabetic()
+    }
+
+    fn is_name(s: &str) -> bool {
+        s.starts_with(is_name_start) && s.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+    }
+
+    fn flush(literal: &mut String, words: &mut Vec<DefaultSimpleWord>) {
+        if !literal.is_empty() {
+            words.push(SimpleWord::Literal(std::mem::take(literal)));
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut literal = String::new();
+    let mut expanded = false;
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+
+        if c == '$' {
+            let braced = after
+                .strip_prefix('{')
+                .and_then(|s| s.find('}').map(|end| (&s[..end], &s[end + 1..])))
+                .filter(|&(name, _)| is_name(name));
+
+            let bare = if after.starts_with(is_name_start) {
+                let end = after
+                    .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+                    .unwrap_or(after.len());
+                Some((&after[..end], &after[end..]))
+            } else {
+                None
+            };
+
+            if let Some((name, remaining)) = braced.or(bare) {
+                flush(&mut literal, &mut words);
+                words.push(SimpleWord::Param(Parameter::Var(name.to_owned())));
+                expanded = true;
+                rest = remaining;
+                continue;
+            }
+        }
+
+        match c {
+            '$' | '`' | '"' | '\\' => {
+                flush(&mut literal, &mut words);
+                words.push(SimpleWord::Escaped(c.to_string()));
+            }
+            _ => literal.push(c),
+        }
+
+        rest = after;
+    }
+
+    flush(&mut literal, &mut words);
+
+    if expanded {
+        Some(words)
+    } else {
+        None
+    }
+}
+
+fn render(word: &DefaultWord) -> String {
+    TopLevelWord(ComplexWord::Single(word.clone())).to_shell_string()
+}
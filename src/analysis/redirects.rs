@@ -0,0 +1,198 @@
+//! Computes the redirections in force for commands nested within compound commands.
+//!
+//! The redirections of a compound command (e.g. the `>log` of `done >log`, or
+//! the `2>&1` of `} 2>&1`) are performed before any of the commands within it
+//! are run, and thus apply to every nested command unless the nested command
+//! redirects the same file descriptor itself. Since the redirections of the
+//! innermost command are performed last, they take precedence.
+//!
+//! The body of a function does not inherit the redirections of the compound
+//! commands which enclose its declaration, since the function may be invoked
+//! from anywhere, although it does inherit the redirections of the body itself
+//! (e.g. the `>log` of `f() { ...; } >log`). The pipes connecting the commands
+//! of a pipeline are not modeled: they are set up before any redirections of
+//! the commands themselves, but after those of any enclosing compound commands.
+//! Commands nested within word substitutions are not visited.
+
+use crate::ast::{
+    AndOr, Command, DefaultCompoundCommand, DefaultListableCommand, DefaultPipeableCommand,
+    DefaultRedirect, DefaultSimpleCommand, ListableCommand, PipeableCommand, Redirect,
+    RedirectOrCmdWord, RedirectOrEnvVar, TopLevelCommand,
+};
+
+/// A command whose redirections were computed, see `RedirectScope`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScopedCommand<'a> {
+    /// A simple command, e.g. `foo bar`.
+    Simple(&'a DefaultSimpleCommand),
+    /// A compound command (e.g. `for`, `if`, or a brace group) nested within another.
+    Compound(&'a DefaultCompoundCommand),
+}
+
+/// The redirections in force for a command nested within at least one
+/// compound command which has redirections, see `redirect_scopes`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RedirectScope<'a> {
+    /// The command the redirections apply to.
+    pub command: ScopedCommand<'a>,
+    /// The redirections of the enclosing compound commands, in the order
+    /// they are performed (i.e. outermost first).
+    pub inherited: Vec<&'a DefaultRedirect>,
+    /// The redirections of the command itself, in source order.
+    pub own: Vec<&'a DefaultRedirect>,
+}
+
+impl<'a> RedirectScope<'a> {
+    /// Returns every redirection in force for the command, in the order they
+    /// are performed: the inherited ones first, followed by its own.
+    pub fn redirects(&self) -> impl Iterator<Item = &'a DefaultRedirect> + '_ {
+        self.inherited.iter().chain(&self.own).cloned()
+    }
+
+    /// Returns the redirection which determines where a file descriptor ends up
+    /// (i.e. the last one performed which affects it), or `None` if the file
+    /// descriptor is left as it was when the outermost command started.
+    ///
+    /// Duplications (e.g. `2>&1`) are returned as is rather than followed to
+    /// the redirection of the file descriptor they duplicate.
+    pub fn target(&self, fd: u16) -> Option<&'a DefaultRedirect> {
+        let mut latest_first = self.own.iter().rev().chain(self.inherited.iter().rev());
+        latest_first.find(|redirect| affects(redirect, fd)).cloned()
+    }
+}
+
+/// Checks if a redirection (re)opens a given file descriptor.
+fn affects(redirect: &DefaultRedirect, fd: u16) -> bool {
+    let default = match *redirect {
+        Redirect::Read(..)
+        | Redirect::ReadWrite(..)
+        | Redirect::Heredoc(..)
+        | Redirect::DupRead(..) => 0,
+        Redirect::Write(..)
+        | Redirect::Append(..)
+        | Redirect::Clobber(..)
+        | Redirect::DupWrite(..) => 1,
+        Redirect::WriteBoth(_) | Redirect::AppendBoth(_) => return fd == 1 || fd == 2,
+    };
+
+    redirect.fd().unwrap_or(default) == fd
+}
+
+/// Reports the redirections in force for every simple and compound command
+/// nested within a compound command which has redirections, in source order.
+///
+/// ```
+/// use conch_parser::analysis::redirects::redirect_scopes;
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::DefaultParser;
+///
+/// let src = "while read line; do echo \"$line\" 2>&1; done <input >output";
+/// let cmds = DefaultParser::new(Lexer::new(src.chars()))
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// let scopes = redirect_scopes(&cmds);
+/// assert_eq!(scopes.len(), 2);
+/// assert_eq!(scopes[1].inherited.len(), 2);
+/// assert_eq!(scopes[1].own.len(), 1);
+/// assert_eq!(scopes[1].target(0), Some(scopes[1].inherited[0]));
+/// assert_eq!(scopes[1].target(2), Some(scopes[1].own[0]));
+/// ```
+pub fn redirect_scopes(cmds: &[TopLevelCommand<String>]) -> Vec<RedirectScope<'_>> {
+    let mut walker = Walker {
+        stack: Vec::new(),
+        scopes: Vec::new(),
+    };
+    walker.commands(cmds);
+    walker.scopes
+}
+
+#[derive(Debug)]
+struct Walker<'a> {
+    /// The redirections of the compound commands enclosing the current
+    /// command, outermost first.
+    stack: Vec<&'a DefaultRedirect>,
+    scopes: Vec<RedirectScope<'a>>,
+}
+
+impl<'a> Walker<'a> {
+    fn commands(&mut self, cmds: &'a [TopLevelCommand<String>]) {
+        for cmd in cmds {
+            let list = match cmd.0 {
+                Command::Job(ref list) | Command::List(ref list) => list,
+                Command::Unparsed(_) => continue,
+            };
+
+            self.listable(&list.first);
+            for and_or in &list.rest {
+                match *and_or {
+                    AndOr::And(ref cmd) | AndOr::Or(ref cmd) => self.listable(cmd),
+                }
+            }
+        }
+    }
+
+    fn listable(&mut self, cmd: &'a DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                for cmd in pipeline.commands() {
+                    self.pipeable(cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &'a DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(_, _, ref body) => {
+                let stack = std::mem::take(&mut self.stack);
+                self.compound(body);
+                self.stack = stack;
+            }
+        }
+    }
+
+    fn simple(&mut self, cmd: &'a DefaultSimpleCommand) {
+        if self.stack.is_empty() {
+            return;
+        }
+
+        let before = cmd.redirects_or_env_vars.iter().filter_map(|r| match *r {
+            RedirectOrEnvVar::Redirect(ref r) => Some(r),
+            RedirectOrEnvVar::EnvVar(..) => None,
+        });
+        let after = cmd.redirects_or_cmd_words.iter().filter_map(|r| match *r {
+            RedirectOrCmdWord::Redirect(ref r) => Some(r),
+            RedirectOrCmdWord::CmdWord(_) => None,
+        });
+
+        self.scopes.push(RedirectScope {
+            command: ScopedCommand::Simple(cmd),
+            inherited: self.stack.clone(),
+            own: before.chain(after).collect(),
+        });
+    }
+
+    fn compound(&mut self, cmd: &'a DefaultCompoundCommand) {
+        if !self.stack.is_empty() {
+            self.scopes.push(RedirectScope {
+                command: ScopedCommand::Compound(cmd),
+                inherited: self.stack.clone(),
+                own: cmd.io.iter().collect(),
+            });
+        }
+
+        let depth = self.stack.len();
+        self.stack.extend(&cmd.io);
+
+        for body in cmd.kind.bodies() {
+            self.commands(body);
+        }
+
+        self.stack.truncate(depth);
+    }
+}
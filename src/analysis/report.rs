@@ -0,0 +1,273 @@
+//! Aggregation of parse results and diagnostics over many scripts.
+//!
+//! Batch validators which process a large number of scripts can record the
+//! outcome of each parse (along with the diagnostics of any analyses) into a
+//! single `ParseReport`, and render a summary table of the results. The summary
+//! only depends on the recorded counts, and lists its rows in a fixed order.
+
+use super::summary::DiagnosticsSummary;
+use super::{walk_commands, Diagnostic, Node, Severity};
+use crate::ast::{CompoundCommandKind, TopLevelCommand};
+use crate::parse::{ParseError, PartialParse};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// An aggregate of the diagnostics and parsed constructs of multiple scripts.
+///
+/// The summary table of the report can be rendered via its `Display` implementation.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ParseReport {
+    /// The number of scripts recorded.
+    scripts: usize,
+    /// The number of scripts which failed to parse.
+    failed: usize,
+    /// All recorded diagnostics, in the order they were recorded.
+    diagnostics: Vec<Diagnostic>,
+    /// The number of occurrences of each kind of construct which was parsed.
+    constructs: BTreeMap<&'static str, usize>,
+}
+
+impl ParseReport {
+    /// Constructs a new, empty, report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the result of parsing an entire script.
+    ///
+    /// Any successfully parsed commands contribute to the construct statistics,
+    /// while a parse error is recorded as an error diagnostic.
+    pub fn record_script<E: fmt::Display>(
+        &mut self,
+        result: &Result<Vec<TopLevelCommand<String>>, ParseError<E>>,
+    ) {
+        self.scripts += 1;
+
+        match *result {
+            Ok(ref cmds) => self.record_commands(cmds),
+            Err(ref err) => {
+                self.failed += 1;
+                self.record_error(err);
+            }
+        }
+    }
+
+    /// Records the outcome of parsing an entire script with error recovery,
+    /// see `Parser::parse_with_recovery`.
+    ///
+    /// Every recovered command contributes to the construct statistics, and
+    /// every encountered error is recorded as an error diagnostic. The script
+    /// counts as failed if any errors were encountered.
+    pub fn record_partial(&mut self, parsed: &PartialParse<TopLevelCommand<String>>) {
+        self.scripts += 1;
+        self.record_commands(&parsed.commands);
+
+        if !parsed.errors.is_empty() {
+            self.failed += 1;
+            for err in &parsed.errors {
+                self.record_error(err);
+            }
+        }
+    }
+
+    /// Records the constructs present in a list of successfully parsed commands.
+    pub fn record_commands(&mut self, cmds: &[TopLevelCommand<String>]) {
+        let constructs = &mut self.constructs;
+        walk_commands(cmds, &mut |node| {
+            let name = match node {
+                Node::Simple(_) => "simple command",
+                Node::FunctionDef => "function",
+                Node::Compound(cmd) => match cmd.kind {
+                    CompoundCommandKind::Brace(_) => "brace group",
+                    CompoundCommandKind::Subshell(_) => "subshell",
+                    CompoundCommandKind::While(_) => "while",
+                    CompoundCommandKind::Until(_) => "until",
+                    CompoundCommandKind::If { .. } => "if",
+                    CompoundCommandKind::For { .. } => "for",
+                    CompoundCommandKind::Case { .. } => "case",
+                    CompoundCommandKind::Conditional(_) => "conditional",
+                    CompoundCommandKind::Arithmetic(_) => "arithmetic",
+                },
+            };
+
+            *constructs.entry(name).or_insert(0) += 1;
+        });
+    }
+
+    /// Records a parse error as an error diagnostic.
+    pub fn record_error<E: fmt::Display>(&mut self, err: &ParseError<E>) {
+        self.diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            error_code(err),
+            err.to_string(),
+        ));
+    }
+
+    /// Records the diagnostics reported by an analysis.
+    pub fn record_diagnostics<I: IntoIterator<Item = Diagnostic>>(&mut self, diagnostics: I) {
+        self.diagnostics.extend(diagnostics);
+    }
+
+    /// Merges the contents of another report into this one, e.g. when
+    /// scripts were processed in parallel into separate reports.
+    ///
+    /// The diagnostics of `other` are appended after those already recorded,
+    /// so reports merged in a nondeterministic order (e.g. as parallel workers
+    /// finish) should be normalized with `sort_diagnostics` before comparison.
+    pub fn merge(&mut self, other: ParseReport) {
+        self.scripts += other.scripts;
+        self.failed += other.failed;
+        self.diagnostics.extend(other.diagnostics);
+
+        for (name, count) in other.constructs {
+            *self.constructs.entry(name).or_insert(0) += count;
+        }
+    }
+
+    /// Sorts the recorded diagnostics by severity (most severe first), code,
+    /// and message, so that the contents of a report do not depend on the
+    /// order in which scripts were recorded or reports were merged.
+    pub fn sort_diagnostics(&mut self) {
+        self.diagnostics.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.code.cmp(b.code))
+                .then_with(|| a.message.cmp(&b.message))
+        });
+    }
+
+    /// Returns the number of scripts recorded.
+    pub fn scripts(&self) -> usize {
+        self.scripts
+    }
+
+    /// Returns the number of recorded scripts which failed to parse.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Returns all recorded diagnostics.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the number of recorded diagnostics with the specified severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+
+    /// Returns the number of recorded diagnostics with the specified code.
+    pub fn count_code(&self, code: &str) -> usize {
+        self.diagnostics.iter().filter(|d| d.code == code).count()
+    }
+
+    /// Returns the number of recorded diagnostics with each severity and code,
+    /// which an `ExitPolicy` can decide the outcome of a batch run from.
+    pub fn summary(&self) -> DiagnosticsSummary {
+        self.diagnostics.iter().collect()
+    }
+
+    /// Returns the number of times a construct (e.g. `"for"`, `"function"`,
+    /// or `"simple command"`) appeared in successfully parsed scripts.
+    pub fn construct_count(&self, construct: &str) -> usize {
+        self.constructs.get(construct).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of occurrences of each construct, sorted by name.
+    pub fn constructs(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.constructs.iter().map(|(&name, &count)| (name, count))
+    }
+}
+
+/// Writes a two column table with a header and right aligned counts.
+fn write_table<'a, I>(fmt: &mut fmt::Formatter<'_>, header: &str, rows: I) -> fmt::Result
+where
+    I: IntoIterator<Item = (&'a str, usize)>,
+{
+    let rows: Vec<_> = rows.into_iter().collect();
+    let name_width = rows
+        .iter()
+        .map(|&(name, _)| name.len())
+        .chain(Some(header.len()))
+        .max()
+        .unwrap_or(0);
+    let count_width = rows
+        .iter()
+        .map(|&(_, count)| count.to_string().len())
+        .chain(Some("count".len()))
+        .max()
+        .unwrap_or(0);
+
+    writeln!(
+        fmt,
+        "{:<nw$}  {:>cw$}",
+        header,
+        "count",
+        nw = name_width,
+        cw = count_width
+    )?;
+    for (name, count) in rows {
+        writeln!(
+            fmt,
+            "{:<nw$}  {:>cw$}",
+            name,
+            count,
+            nw = name_width,
+            cw = count_width
+        )?;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(fmt, "scripts: {} ({} failed)", self.scripts, self.failed)?;
+
+        writeln!(fmt)?;
+        let severities = [Severity::Error, Severity::Warning, Severity::Info];
+        write_table(
+            fmt,
+            "severity",
+            severities.iter().map(|&s| (s.as_str(), self.count(s))),
+        )?;
+
+        let mut codes = BTreeMap::new();
+        for d in &self.diagnostics {
+            *codes.entry(d.code).or_insert(0) += 1;
+        }
+
+        if !codes.is_empty() {
+            writeln!(fmt)?;
+            write_table(fmt, "code", codes)?;
+        }
+
+        if !self.constructs.is_empty() {
+            writeln!(fmt)?;
+            write_table(fmt, "construct", self.constructs())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the diagnostic code under which a parse error is reported.
+pub(crate) fn error_code<E>(err: &ParseError<E>) -> &'static str {
+    match *err {
+        ParseError::BadFd(..) => "parse-bad-fd",
+        ParseError::BadIdent(..) => "parse-bad-ident",
+        ParseError::BadSubst(..) => "parse-bad-subst",
+        ParseError::Unmatched(..) => "parse-unmatched",
+        ParseError::IncompleteCmd(..) => "parse-incomplete-cmd",
+        ParseError::Unexpected(..) => "parse-unexpected",
+        ParseError::UnexpectedEOF => "parse-unexpected-eof",
+        ParseError::Extension(..) => "parse-extension",
+        ParseError::NulByte(..) => "parse-nul-byte",
+        ParseError::BadNumber(..) => "parse-bad-number",
+        ParseError::NumberOverflow(..) => "parse-number-overflow",
+        ParseError::Custom(_) => "parse-custom",
+    }
+}
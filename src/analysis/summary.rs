@@ -0,0 +1,270 @@
+//! Quick statistics over reported diagnostics, and policies turning them into
+//! a pass or fail decision.
+//!
+//! Tools which embed the analyses (e.g. a linter run as part of a CI job) need
+//! to map whatever was reported onto a single outcome, such as the exit code of
+//! the process. A `DiagnosticsSummary` counts diagnostics by severity and code,
+//! and an `ExitPolicy` checks those counts against its configured thresholds, so
+//! that every embedder applies them the same way.
+//!
+//! ```
+//! use conch_parser::analysis::summary::{DiagnosticsSummary, ExitPolicy};
+//! use conch_parser::analysis::{Diagnostic, Severity};
+//!
+//! let diagnostics = vec![
+//!     Diagnostic::new(Severity::Warning, "some-warning", "first"),
+//!     Diagnostic::new(Severity::Warning, "some-warning", "second"),
+//!     Diagnostic::new(Severity::Info, "some-info", "third"),
+//! ];
+//!
+//! let summary = diagnostics.iter().collect::<DiagnosticsSummary>();
+//! assert_eq!(summary.to_string(), "2 warnings, 1 info");
+//!
+//! let mut policy = ExitPolicy::default();
+//! assert_eq!(policy.exit_code(&summary), 0);
+//!
+//! policy.max_warnings = Some(1);
+//! assert_eq!(policy.exit_code(&summary), 1);
+//! ```
+
+use super::{Diagnostic, Severity};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::iter::FromIterator;
+
+/// The number of diagnostics reported with each severity and code.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct DiagnosticsSummary {
+    /// The number of diagnostics with each severity.
+    severities: BTreeMap<Severity, usize>,
+    /// The number of diagnostics with each code.
+    codes: BTreeMap<&'static str, usize>,
+}
+
+impl DiagnosticsSummary {
+    /// Constructs a new, empty, summary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a single diagnostic.
+    pub fn record(&mut self, diagnostic: &Diagnostic) {
+        *self.severities.entry(diagnostic.severity).or_insert(0) += 1;
+        *self.codes.entry(diagnostic.code).or_insert(0) += 1;
+    }
+
+    /// Adds the counts of another summary to this one.
+    pub fn merge(&mut self, other: DiagnosticsSummary) {
+        for (severity, count) in other.severities {
+            *self.severities.entry(severity).or_insert(0) += count;
+        }
+
+        for (code, count) in other.codes {
+            *self.codes.entry(code).or_insert(0) += count;
+        }
+    }
+
+    /// Returns the total number of diagnostics counted.
+    pub fn total(&self) -> usize {
+        self.severities.values().sum()
+    }
+
+    /// Checks if no diagnostics were counted.
+    pub fn is_empty(&self) -> bool {
+        self.severities.is_empty()
+    }
+
+    /// Returns the number of diagnostics with the specified severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.severities.get(&severity).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of diagnostics at least as severe as the specified severity.
+    pub fn count_at_least(&self, severity: Severity) -> usize {
+        self.severities
+            .range(severity..)
+            .map(|(_, &count)| count)
+            .sum()
+    }
+
+    /// Returns the number of diagnostics with the specified code.
+    pub fn count_code(&self, code: &str) -> usize {
+        self.codes.get(code).copied().unwrap_or(0)
+    }
+
+    /// Returns the most serious severity of any diagnostic, if any were counted.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.severities.keys().next_back().copied()
+    }
+
+    /// Returns the number of diagnostics with each code, sorted by code.
+    pub fn codes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.codes.iter().map(|(&code, &count)| (code, count))
+    }
+}
+
+impl<'a> Extend<&'a Diagnostic> for DiagnosticsSummary {
+    fn extend<I: IntoIterator<Item = &'a Diagnostic>>(&mut self, iter: I) {
+        for diagnostic in iter {
+            self.record(diagnostic);
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a Diagnostic> for DiagnosticsSummary {
+    fn from_iter<I: IntoIterator<Item = &'a Diagnostic>>(iter: I) -> Self {
+        let mut summary = Self::new();
+        summary.extend(iter);
+        summary
+    }
+}
+
+/// Displays the counts of each severity, most severe first, e.g.
+/// `1 error, 2 warnings`, omitting any severities which were not reported.
+impl fmt::Display for DiagnosticsSummary {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return fmt.write_str("no diagnostics");
+        }
+
+        for (i, (&severity, &count)) in self.severities.iter().rev().enumerate() {
+            if i > 0 {
+                fmt.write_str(", ")?;
+            }
+
+            let plural = count != 1 && severity != Severity::Info;
+            write!(
+                fmt,
+                "{} {}{}",
+                count,
+                severity,
+                if plural { "s" } else { "" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The thresholds a `DiagnosticsSummary` must stay within to pass, and the
+/// process exit code to use if it does not.
+///
+/// By default, a summary fails if (and only if) any errors were reported.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExitPolicy {
+    /// The least serious severity which fails on its own, or `None` if
+    /// diagnostics should never fail by their severity alone.
+    pub fail_on: Option<Severity>,
+    /// The most warnings which may be reported before failing, or `None` for no limit.
+    pub max_warnings: Option<usize>,
+    /// Codes which fail if reported at all, regardless of their severity.
+    pub fail_codes: BTreeSet<String>,
+    /// The exit code to use for a failing summary.
+    pub failure_exit_code: i32,
+}
+
+impl Default for ExitPolicy {
+    fn default() -> Self {
+        ExitPolicy {
+            fail_on: Some(Severity::Error),
+            max_warnings: None,
+            fail_codes: BTreeSet::new(),
+            failure_exit_code: 1,
+        }
+    }
+}
+
+/// A reason for which an `ExitPolicy` fails a `DiagnosticsSummary`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PolicyViolation {
+    /// Diagnostics at least as severe as `ExitPolicy::fail_on` were reported.
+    Severity {
+        /// The severity configured by the policy.
+        severity: Severity,
+        /// The number of diagnostics at least as severe.
+        count: usize,
+    },
+    /// More warnings than `ExitPolicy::max_warnings` were reported.
+    TooManyWarnings {
+        /// The number of warnings reported.
+        count: usize,
+        /// The most warnings allowed by the policy.
+        max: usize,
+    },
+    /// One of the `ExitPolicy::fail_codes` was reported.
+    Code {
+        /// The code which was reported.
+        code: String,
+        /// The number of diagnostics with the code.
+        count: usize,
+    },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PolicyViolation::Severity { severity, count } => write!(
+                fmt,
+                "{} diagnostic(s) of severity {} or higher reported",
+                count, severity
+            ),
+            PolicyViolation::TooManyWarnings { count, max } => write!(
+                fmt,
+                "{} warning(s) reported, at most {} allowed",
+                count, max
+            ),
+            PolicyViolation::Code { ref code, count } => {
+                write!(fmt, "{} diagnostic(s) with code {} reported", count, code)
+            }
+        }
+    }
+}
+
+impl ExitPolicy {
+    /// Returns every threshold of the policy which the summary exceeds, in a
+    /// fixed order: severity first, then warnings, then codes sorted by name.
+    pub fn violations(&self, summary: &DiagnosticsSummary) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+
+        if let Some(severity) = self.fail_on {
+            let count = summary.count_at_least(severity);
+            if count > 0 {
+                violations.push(PolicyViolation::Severity { severity, count });
+            }
+        }
+
+        if let Some(max) = self.max_warnings {
+            let count = summary.count(Severity::Warning);
+            if count > max {
+                violations.push(PolicyViolation::TooManyWarnings { count, max });
+            }
+        }
+
+        for code in &self.fail_codes {
+            let count = summary.count_code(code);
+            if count > 0 {
+                violations.push(PolicyViolation::Code {
+                    code: code.clone(),
+                    count,
+                });
+            }
+        }
+
+        violations
+    }
+
+    /// Checks if the summary stays within all thresholds of the policy.
+    pub fn passes(&self, summary: &DiagnosticsSummary) -> bool {
+        self.violations(summary).is_empty()
+    }
+
+    /// Returns the process exit code for the summary: zero if it passes,
+    /// or `failure_exit_code` otherwise.
+    pub fn exit_code(&self, summary: &DiagnosticsSummary) -> i32 {
+        if self.passes(summary) {
+            0
+        } else {
+            self.failure_exit_code
+        }
+    }
+}
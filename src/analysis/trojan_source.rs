@@ -0,0 +1,217 @@
+//! Source level checks for "trojan source" style obfuscation.
+//!
+//! Unlike the other analyses, this check operates on the raw source rather than
+//! the parsed AST, since comments (a favorite hiding place for bidirectional
+//! overrides) never make it into the AST. The source is tokenized with the
+//! `Lexer`, and each finding reports whether it occurred within code, a comment,
+//! or a quoted string (as determined by `highlight::semantic_tokens`), along
+//! with its exact span.
+//!
+//! Thus parameter names and the contents of command substitutions are treated
+//! as code (even when nested within double quotes), while the literal text of
+//! heredoc bodies is treated as quoted text.
+
+use super::control_chars::Kind;
+use super::{Diagnostic, Severity};
+use crate::highlight::{semantic_tokens, SemanticToken, SemanticTokenType};
+use crate::lexer::Lexer;
+use crate::parse::SourcePos;
+use crate::token::Token::*;
+use std::fmt;
+
+/// The code reported for non-ASCII characters in code which look like ASCII ones.
+pub const CONFUSABLE: &str = "confusable-char";
+
+/// The syntactic context in which a finding was located.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TextContext {
+    /// Unquoted text which the shell will interpret, e.g. command names,
+    /// arguments, or variable names.
+    Code,
+    /// Text within a comment.
+    Comment,
+    /// Text within single or double quotes.
+    Quoted,
+}
+
+impl TextContext {
+    /// Returns the lowercase name of the context.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TextContext::Code => "code",
+            TextContext::Comment => "comment",
+            TextContext::Quoted => "quoted string",
+        }
+    }
+}
+
+impl fmt::Display for TextContext {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+/// A suspicious character found in the source.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceFinding {
+    /// The reported diagnostic.
+    pub diagnostic: Diagnostic,
+    /// The context in which the character was found.
+    pub context: TextContext,
+    /// The position of the offending character.
+    pub start: SourcePos,
+    /// The position immediately after the offending character.
+    pub end: SourcePos,
+}
+
+impl fmt::Display for SourceFinding {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}: {}", self.start, self.diagnostic)
+    }
+}
+
+/// Scans the source for bidirectional control characters, zero-width and
+/// other invisible characters, and non-ASCII characters in code which are
+/// easily confused with ASCII ones (e.g. a Cyrillic `е` in `еcho`).
+///
+/// Confusable characters are only reported within code, since they are
+/// perfectly legitimate within comments and strings.
+pub fn scan(source: &str) -> Vec<SourceFinding> {
+    let mut findings = Vec::new();
+    let regions = semantic_tokens(source);
+    let mut regions = regions.iter().peekable();
+
+    for (tok, span) in Lexer::new(source.chars()).spanned() {
+        let text = match tok {
+            Name(ref s) | Literal(ref s) | Whitespace(ref s) => s,
+            _ => continue,
+        };
+
+        let mut pos = span.start;
+        for c in text.chars() {
+            let start = pos;
+            pos.advance_char(c);
+
+            // Regions are sorted and never overlap, so skip past any which
+            // end before this character
+            while regions.peek().is_some_and(|r| r.end.byte <= start.byte) {
+                regions.next();
+            }
+
+            let context = match regions.peek() {
+                Some(region) if region.start.byte <= start.byte => context_of(region),
+                _ => TextContext::Code,
+            };
+
+            if let Some(diagnostic) = check_char(c, context) {
+                findings.push(SourceFinding {
+                    diagnostic,
+                    context,
+                    start,
+                    end: pos,
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+/// Returns the context of the text covered by a semantic token.
+fn context_of(region: &SemanticToken) -> TextContext {
+    match region.token_type {
+        SemanticTokenType::Comment => TextContext::Comment,
+        SemanticTokenType::String => TextContext::Quoted,
+        _ => TextContext::Code,
+    }
+}
+
+fn check_char(c: char, context: TextContext) -> Option<Diagnostic> {
+    if let Some(kind) = Kind::classify(c) {
+        let what = match kind {
+            // A replacement character in the raw source is most likely
+            // the result of a botched decoding rather than an attack.
+            Kind::Nul => return None,
+            Kind::Control => "control character",
+            Kind::Bidi => "bidirectional control character",
+            Kind::ZeroWidth => "zero-width character",
+        };
+
+        let message = format!("{} U+{:04X} in {}", what, c as u32, context);
+        return Some(Diagnostic::new(kind.severity(), kind.code(), message));
+    }
+
+    if context == TextContext::Code {
+        if let Some(ascii) = confusable(c) {
+            let message = format!(
+                "`{}` (U+{:04X}) in code looks like `{}`",
+                c, c as u32, ascii
+            );
+            return Some(Diagnostic::new(Severity::Warning, CONFUSABLE, message));
+        }
+    }
+
+    None
+}
+
+/// Returns the ASCII character which the specified character can be
+/// easily mistaken for, if any.
+fn confusable(c: char) -> Option<char> {
+    let ascii = match c {
+        // Cyrillic
+        'а' => 'a',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'о' => 'o',
+        'р' => 'p',
+        'ѕ' => 's',
+        'у' => 'y',
+        'х' => 'x',
+        'А' => 'A',
+        'В' => 'B',
+        'С' => 'C',
+        'Е' => 'E',
+        'Н' => 'H',
+        'І' => 'I',
+        'Ј' => 'J',
+        'К' => 'K',
+        'М' => 'M',
+        'О' => 'O',
+        'Р' => 'P',
+        'Ѕ' => 'S',
+        'Т' => 'T',
+        'Х' => 'X',
+        // Greek
+        'α' => 'a',
+        'ι' => 'i',
+        'ν' => 'v',
+        'ο' => 'o',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Χ' => 'X',
+        'Υ' => 'Y',
+        // Dashes which look like an option prefix
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+        // Fullwidth forms
+        '\u{FF01}'..='\u{FF5E}' => {
+            return std::char::from_u32(c as u32 - 0xFF01 + 0x21);
+        }
+        _ => return None,
+    };
+
+    Some(ascii)
+}
@@ -1,9 +1,40 @@
 //! Defines abstract representations of the shell source.
+//!
+//! # Stability
+//!
+//! Enums which are expected to grow as more of the shell grammar becomes supported
+//! (such as `SimpleWord`, `ParameterSubstitution`, `Redirect`, `PipeableCommand`,
+//! `CompoundCommandKind`, `Parameter`, and `Arithmetic`) are marked as
+//! `#[non_exhaustive]`, so that new variants can be introduced without breaking
+//! consumers. The same applies to the corresponding `builder` kinds which are
+//! handed to `Builder` implementations.
+//!
+//! Code which previously matched on these enums exhaustively will need to add a
+//! wildcard arm. Where possible, prefer the accessor helpers which work across all
+//! variants (e.g. `ParameterSubstitution::word`, `Redirect::fd`, or
+//! `CompoundCommandKind::bodies`) over matching on every variant, since they will
+//! continue to handle any variants added in the future.
+//!
+//! # Serialization
+//!
+//! With the `serde` feature enabled, all AST nodes (along with the `builder`
+//! kinds and fragments) implement `Serialize` and `Deserialize`. The top level
+//! wrappers (e.g. `TopLevelCommand` and `TopLevelWord`) are serialized as the
+//! node they wrap, and function bodies shared via `Rc` or `Arc` are serialized
+//! by value (and thus no longer shared after a round trip).
+use crate::ast::builder::{FunctionSyntax, Newline};
+use crate::parse::SourcePos;
+use std::convert::Infallible;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::{fmt, ops};
 
 pub mod builder;
+pub mod case_modification;
+pub mod printer;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod visit;
 
 /// Type alias for the default `Parameter` representation.
 pub type DefaultParameter = Parameter<String>;
@@ -12,6 +43,8 @@ pub type DefaultParameter = Parameter<String>;
 ///
 /// Generic over the representation of variable names.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Parameter<T> {
     /// $@
     At,
@@ -46,9 +79,13 @@ pub type DefaultParameterSubstitution = ParameterSubstitution<
 /// Generic over the representations of parameters, shell words and
 /// commands, and arithmetic expansions.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum ParameterSubstitution<P, W, C, A> {
     /// Returns the standard output of running a command, e.g. `$(cmd)`
     Command(Vec<C>),
+    /// Returns the contents of a file without running any command, e.g. `$(< file)`
+    ReadFile(W),
     /// Returns the length of the value of a parameter, e.g. `${#param}`
     Len(P),
     /// Returns the resulting value of an arithmetic subsitution, e.g. `$(( x++ ))`
@@ -92,6 +129,12 @@ pub enum ParameterSubstitution<P, W, C, A> {
     Lowercase(bool, P, Option<W>),
     /// Uppercase the matched value, e.g. `${param^^pattern}
     Uppercase(bool, P, Option<W>),
+    /// Returns a path from which the standard output of running a command
+    /// can be read, e.g. `<(cmd)`
+    ProcessRead(Vec<C>),
+    /// Returns a path whose contents are written to the standard input of
+    /// running a command, e.g. `>(cmd)`
+    ProcessWrite(Vec<C>),
 }
 
 /// A type alias for the default hiearchy for representing shell words.
@@ -109,6 +152,7 @@ pub type DefaultComplexWord = ComplexWord<DefaultWord>;
 ///
 /// Generic over the representation of a whitespace delimited word.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComplexWord<W> {
     /// Several distinct words concatenated together.
     Concat(Vec<W>),
@@ -123,6 +167,7 @@ pub type DefaultWord = Word<String, DefaultSimpleWord>;
 ///
 /// Generic over the representation of single-quoted literals, and non-quoted words.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Word<L, W> {
     /// A regular word.
     Simple(W),
@@ -131,6 +176,45 @@ pub enum Word<L, W> {
     /// List of words concatenated within single quotes. Virtually
     /// identical as a literal, but makes a distinction between the two.
     SingleQuoted(L),
+    /// A brace expansion, e.g. `{a,b}` or `{1..10}`, which is only recognized
+    /// with `BraceExpansion::Structured` (see `ParserOptions::brace_expansion`).
+    Braces(Braces<Word<L, W>>),
+}
+
+/// A brace expansion within an unquoted word, which generates a word for each
+/// of its alternatives (or each element of its sequence), e.g. `a{b,c}d` expands
+/// to `abd acd`, and `{1..3}` expands to `1 2 3`.
+///
+/// Generic over the representation of the words making up each alternative.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Braces<W> {
+    /// A comma separated list of at least two alternatives, each of which is a
+    /// concatenation of (possibly no) words, e.g. `{a,"b c",d{e,f},}`.
+    Alternatives(Vec<Vec<W>>),
+    /// A sequence of integers, e.g. `{1..10}` or `{10..1..3}`.
+    Numbers {
+        /// The first number of the sequence.
+        start: i64,
+        /// The last number of the sequence, which is included if the step reaches it.
+        end: i64,
+        /// The increment between numbers, e.g. `3` for `{1..10..3}`.
+        step: Option<i64>,
+        /// The number of characters every number is padded to with zeros, if
+        /// either bound is written with a leading zero (e.g. `2` for `{01..10}`),
+        /// otherwise zero.
+        width: usize,
+    },
+    /// A sequence of ASCII letters, e.g. `{a..z}` or `{Z..A..2}`.
+    Chars {
+        /// The first letter of the sequence.
+        start: char,
+        /// The last letter of the sequence, which is included if the step reaches it.
+        end: char,
+        /// The increment between letters, e.g. `2` for `{a..z..2}`.
+        step: Option<i64>,
+    },
 }
 
 /// Type alias for the default `SimpleWord` representation.
@@ -141,6 +225,8 @@ pub type DefaultSimpleWord =
 ///
 /// Generic over the representation of a literals, parameters, and substitutions.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum SimpleWord<L, P, S> {
     /// A non-special literal word.
     Literal(L),
@@ -163,6 +249,11 @@ pub enum SimpleWord<L, P, S> {
     Tilde,
     /// Represents `:`, useful for handling tilde expansions.
     Colon,
+    /// A tilde prefix which is subject to tilde expansion, e.g. the `~` of `~/bin`
+    /// or the `~user` of `~user/bin`, along with the login name (if any) which
+    /// follows the tilde. Only produced with `TildeExpansion::Prefixes` (see
+    /// `ParserOptions::tilde_expansion`).
+    TildePrefix(Option<L>),
 }
 
 /// Type alias for the default `Redirect` representation.
@@ -172,6 +263,8 @@ pub type DefaultRedirect = Redirect<TopLevelWord<String>>;
 ///
 /// Generic over the representation of a shell word.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Redirect<W> {
     /// Open a file for reading, e.g. `[n]< file`.
     Read(Option<u16>, W),
@@ -183,16 +276,42 @@ pub enum Redirect<W> {
     Append(Option<u16>, W),
     /// Open a file for writing, failing if the `noclobber` shell option is set, e.g. `[n]>| file`.
     Clobber(Option<u16>, W),
-    /// Lines contained in the source that should be provided by as input to a file descriptor.
-    Heredoc(Option<u16>, W),
+    /// Lines contained in the source that should be provided by as input to a file descriptor,
+    /// e.g. `[n]<< delim` or `[n]<<- delim`, along with how the heredoc was written.
+    Heredoc(Option<u16>, W, HeredocInfo),
     /// Duplicate a file descriptor for reading, e.g. `[n]<& [n|-]`.
     DupRead(Option<u16>, W),
     /// Duplicate a file descriptor for writing, e.g. `[n]>& [n|-]`.
     DupWrite(Option<u16>, W),
+    /// Open a file for writing after truncating, and redirect both standard output
+    /// and standard error to it, e.g. `&> file` (a bash extension).
+    WriteBoth(W),
+    /// Open a file for writing, appending to the end, and redirect both standard
+    /// output and standard error to it, e.g. `&>> file` (a bash extension).
+    AppendBoth(W),
+}
+
+/// Describes how a heredoc was written in the source.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeredocInfo {
+    /// The delimiter which terminates the body, with any quoting removed.
+    pub delimiter: String,
+    /// Whether any part of the delimiter was quoted (e.g. `<<'EOF'` or `<<\EOF`),
+    /// in which case the body is not subject to any expansions.
+    pub quoted: bool,
+    /// Whether the heredoc was introduced with `<<-`, in which case any leading
+    /// tabs are stripped from each line of the body (and the delimiter line).
+    pub strip_tabs: bool,
+    /// The lines of the body exactly as they appear in the source, before any
+    /// tabs are stripped, and without their terminating newlines. The line
+    /// holding the delimiter is not included.
+    pub lines: Vec<String>,
 }
 
 /// A grouping of guard and body commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GuardBodyPair<C> {
     /// The guard commands, which if successful, should lead to the
     /// execution of the body commands.
@@ -203,11 +322,41 @@ pub struct GuardBodyPair<C> {
 
 /// A grouping of patterns and body commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PatternBodyPair<W, C> {
     /// Pattern alternatives to match against.
     pub patterns: Vec<W>,
     /// The body commands to execute if the pattern matches.
     pub body: Vec<C>,
+    /// How execution proceeds once the body commands have been run.
+    pub terminator: CaseTerminator,
+}
+
+/// The operator which terminates an arm of a `case` command.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaseTerminator {
+    /// `;;`, no further arms are considered. Also used for a
+    /// final arm which does not have a terminator at all.
+    #[default]
+    Break,
+    /// `;&`, the body of the next arm is run without checking its patterns,
+    /// a bash extension.
+    FallThrough,
+    /// `;;&`, the patterns of the following arms continue to be checked,
+    /// a bash extension.
+    Continue,
+}
+
+impl CaseTerminator {
+    /// Returns the source text of the operator.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CaseTerminator::Break => ";;",
+            CaseTerminator::FallThrough => ";&",
+            CaseTerminator::Continue => ";;&",
+        }
+    }
 }
 
 /// Type alias for the default `Command` representation.
@@ -215,12 +364,41 @@ pub type DefaultCommand = Command<DefaultAndOrList>;
 
 /// Represents any valid shell command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command<T> {
     /// A command that runs asynchronously, that is, the shell will not wait
     /// for it to exit before running the next command, e.g. `foo &`.
     Job(T),
     /// A list of and/or commands, e.g. `foo && bar || baz`.
     List(T),
+    /// A region of the source which could not be parsed, retained verbatim.
+    ///
+    /// Only produced if the parser is set to recover from errors,
+    /// see `parse::ErrorRecovery` for more details.
+    Unparsed(UnparsedSpan),
+}
+
+/// A region of the source which could not be parsed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnparsedSpan {
+    /// The raw text of the region, exactly as it appears in the source
+    /// (including the newline which terminates it, if any).
+    pub text: String,
+    /// The position of the start of the region.
+    pub start: SourcePos,
+    /// The position immediately after the end of the region.
+    pub end: SourcePos,
+}
+
+/// A region of the source between two positions.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    /// The position of the start of the region.
+    pub start: SourcePos,
+    /// The position immediately after the end of the region.
+    pub end: SourcePos,
 }
 
 /// A type alias over an and/or list of conventional shell commands.
@@ -256,6 +434,7 @@ pub type AtomicShellPipeableCommand<T, W, C> = PipeableCommand<
 
 /// A command which conditionally runs based on the exit status of the previous command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AndOr<T> {
     /// A compound command which should run only if the previously run command succeeded.
     And(T),
@@ -267,7 +446,14 @@ pub enum AndOr<T> {
 pub type DefaultAndOrList = AndOrList<DefaultListableCommand>;
 
 /// A nonempty list of `AndOr` commands, e.g. `foo && bar || baz`.
+///
+/// Mirroring the POSIX grammar, `&&` and `||` have equal precedence and are
+/// left associative: each command in `rest` is conditioned on the exit status
+/// of everything which precedes it. Thus `foo && bar || baz` runs as
+/// `(foo && bar) || baz`, and an arbitrarily long chain is represented without
+/// any nesting.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AndOrList<T> {
     /// The first command that always runs.
     pub first: T,
@@ -280,18 +466,40 @@ pub type DefaultListableCommand = ListableCommand<DefaultPipeableCommand>;
 
 /// Commands that can be used within an and/or list.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ListableCommand<T> {
     /// A chain of concurrent commands where the standard output of the
     /// previous becomes the standard input of the next, e.g.
     /// `[!] foo | bar | baz`.
-    ///
-    /// The bool indicates if a logical negation of the last command's status
-    /// should be returned.
-    Pipe(bool, Vec<T>),
+    Pipe(Pipeline<T>),
     /// A single command not part of a pipeline.
     Single(T),
 }
 
+/// A chain of one or more commands connected by pipes, e.g. `[!] foo | bar |& baz`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pipeline<T> {
+    /// Indicates if a logical negation of the last command's status should be returned.
+    pub bang: bool,
+    /// The (nonempty) commands of the pipeline, in the order they appear.
+    pub segments: Vec<PipeSegment<T>>,
+}
+
+/// A single command within a pipeline, along with the pipe which precedes it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipeSegment<T> {
+    /// The location of the `|` or `|&` operator preceding this command,
+    /// or `None` if this is the first command of the pipeline.
+    pub pipe: Option<Span>,
+    /// Indicates the preceding operator was `|&` (a bash extension), meaning the
+    /// standard error of the previous command should also be piped into this one.
+    pub pipe_stderr: bool,
+    /// The command itself.
+    pub cmd: T,
+}
+
 /// Type alias for the default `PipeableCommand` representation.
 pub type DefaultPipeableCommand =
     ShellPipeableCommand<String, TopLevelWord<String>, TopLevelCommand<String>>;
@@ -301,6 +509,8 @@ pub type DefaultPipeableCommand =
 /// Generic over the representations of function names, simple commands,
 /// compound commands, and function bodies.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum PipeableCommand<N, S, C, F> {
     /// The simplest possible command: an executable with arguments,
     /// environment variable assignments, and redirections.
@@ -308,8 +518,9 @@ pub enum PipeableCommand<N, S, C, F> {
     /// A class of commands where redirection is applied to a command group.
     Compound(C),
     /// A function definition, associating a name with a group of commands,
-    /// e.g. `function foo() { echo foo function; }`.
-    FunctionDef(N, F),
+    /// e.g. `function foo() { echo foo function; }`, along with the syntax
+    /// used to declare it.
+    FunctionDef(N, FunctionSyntax, F),
 }
 
 /// A type alias for the default hiearchy for representing compound shell commands.
@@ -324,11 +535,17 @@ pub type DefaultCompoundCommand =
 /// Generic over the representation of a type of compound command, and the
 /// representation of a redirect.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompoundCommand<T, R> {
     /// The specific kind of compound command.
     pub kind: T,
     /// Any redirections to be applied to the entire compound command
     pub io: Vec<R>,
+    /// The location of the redirections in the source, or `None` if there are none.
+    ///
+    /// The span covers the redirection operators and their targets, e.g. `>out <<EOF`
+    /// in `done >out <<EOF`, but not the bodies of any heredocs which follow.
+    pub io_span: Option<Span>,
 }
 
 /// Type alias for the default `CompoundCommandKind` representation.
@@ -339,6 +556,8 @@ pub type DefaultCompoundCommandKind =
 ///
 /// Generic over the representation of shell words and commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum CompoundCommandKind<V, W, C> {
     /// A group of commands that should be executed in the current environment.
     Brace(Vec<C>),
@@ -362,6 +581,12 @@ pub enum CompoundCommandKind<V, W, C> {
         /// The variable to bind to each of the specified words.
         var: V,
         /// The words to bind to the specified variable one by one.
+        ///
+        /// If the `in` clause was omitted entirely (e.g. `for x do ...; done` or
+        /// `for x; do ...; done`) this will be `None`, and the loop should iterate
+        /// over the positional parameters (i.e. `"$@"`). An explicitly empty list
+        /// (e.g. `for x in; do ...; done`) is represented as `Some(vec![])` and
+        /// will not run the body at all.
         words: Option<Vec<W>>,
         /// The body to run with the variable binding.
         body: Vec<C>,
@@ -375,6 +600,203 @@ pub enum CompoundCommandKind<V, W, C> {
         /// The arms to match against.
         arms: Vec<PatternBodyPair<W, C>>,
     },
+    /// A `[[ ... ]]` conditional command (a bash extension), which evaluates
+    /// a conditional expression and succeeds if it is true.
+    Conditional(Conditional<W>),
+    /// A `(( ... ))` arithmetic command (a bash extension), which evaluates an
+    /// arithmetic expression and succeeds if its value is non-zero. The expression
+    /// is `None` if it is empty, e.g. `(( ))`.
+    Arithmetic(Option<Arithmetic<V>>),
+}
+
+/// Type alias for the default `Conditional` representation.
+pub type DefaultConditional = Conditional<TopLevelWord<String>>;
+
+/// An expression within a `[[ ... ]]` conditional command.
+///
+/// Unlike the arguments of the `test` (or `[`) builtin, the words of the
+/// expression do not undergo field splitting or pathname expansion, and the
+/// right hand side of `==` and `!=` is matched as a pattern.
+///
+/// `&&` binds more tightly than `||`, and both are left associative, so
+/// `a || b && c` is represented as `Or(a, And(b, c))`. Parentheses used to
+/// group subexpressions are not retained, as they are implied by the structure.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Conditional<W> {
+    /// A word which is tested for being non-empty, e.g. `[[ $x ]]`.
+    Word(W),
+    /// A unary test such as `-f file` or `-z $x`, along with the letter of
+    /// its operator (e.g. `'f'` for `-f`).
+    Unary(char, W),
+    /// A binary test such as `$a == b*` or `$n -lt 10`.
+    Binary(W, BinaryTest, W),
+    /// The negation of an expression, e.g. `! -d dir`.
+    Not(Box<Conditional<W>>),
+    /// True if both expressions are true, e.g. `a && b`.
+    And(Box<Conditional<W>>, Box<Conditional<W>>),
+    /// True if either expression is true, e.g. `a || b`.
+    Or(Box<Conditional<W>>, Box<Conditional<W>>),
+}
+
+/// The operator of a binary test within a `[[ ... ]]` conditional command.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum BinaryTest {
+    /// `==` (or `=`), the left side matches the pattern on the right side.
+    Match,
+    /// `!=`, the left side does not match the pattern on the right side.
+    NotMatch,
+    /// `=~`, the left side matches the extended regular expression on the right side.
+    RegexMatch,
+    /// `<`, the left side sorts before the right side.
+    Less,
+    /// `>`, the left side sorts after the right side.
+    Greater,
+    /// `-eq`, the arithmetic values of both sides are equal.
+    NumEq,
+    /// `-ne`, the arithmetic values of both sides are not equal.
+    NumNotEq,
+    /// `-lt`, the arithmetic value of the left side is less than that of the right side.
+    NumLess,
+    /// `-le`, the arithmetic value of the left side is at most that of the right side.
+    NumLessEq,
+    /// `-gt`, the arithmetic value of the left side is greater than that of the right side.
+    NumGreater,
+    /// `-ge`, the arithmetic value of the left side is at least that of the right side.
+    NumGreaterEq,
+    /// `-nt`, the file on the left side is newer than the one on the right side.
+    NewerThan,
+    /// `-ot`, the file on the left side is older than the one on the right side.
+    OlderThan,
+    /// `-ef`, both sides refer to the same file.
+    SameFile,
+}
+
+impl BinaryTest {
+    /// All binary test operators.
+    pub const ALL: [BinaryTest; 14] = [
+        BinaryTest::Match,
+        BinaryTest::NotMatch,
+        BinaryTest::RegexMatch,
+        BinaryTest::Less,
+        BinaryTest::Greater,
+        BinaryTest::NumEq,
+        BinaryTest::NumNotEq,
+        BinaryTest::NumLess,
+        BinaryTest::NumLessEq,
+        BinaryTest::NumGreater,
+        BinaryTest::NumGreaterEq,
+        BinaryTest::NewerThan,
+        BinaryTest::OlderThan,
+        BinaryTest::SameFile,
+    ];
+
+    /// Returns the source text of the operator.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            BinaryTest::Match => "==",
+            BinaryTest::NotMatch => "!=",
+            BinaryTest::RegexMatch => "=~",
+            BinaryTest::Less => "<",
+            BinaryTest::Greater => ">",
+            BinaryTest::NumEq => "-eq",
+            BinaryTest::NumNotEq => "-ne",
+            BinaryTest::NumLess => "-lt",
+            BinaryTest::NumLessEq => "-le",
+            BinaryTest::NumGreater => "-gt",
+            BinaryTest::NumGreaterEq => "-ge",
+            BinaryTest::NewerThan => "-nt",
+            BinaryTest::OlderThan => "-ot",
+            BinaryTest::SameFile => "-ef",
+        }
+    }
+
+    /// Returns the operator written as `op`, if any. Both `=` and `==` are
+    /// recognized as `BinaryTest::Match`.
+    pub fn from_op(op: &str) -> Option<Self> {
+        if op == "=" {
+            return Some(BinaryTest::Match);
+        }
+
+        BinaryTest::ALL
+            .iter()
+            .find(|test| test.as_str() == op)
+            .cloned()
+    }
+}
+
+impl fmt::Display for BinaryTest {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+/// The letters of all unary test operators recognized within a `[[ ... ]]`
+/// conditional command, e.g. `f` for `-f`.
+pub const UNARY_TESTS: &str = "abcdefghknoprstuvwxzGLNORS";
+
+impl<W> Conditional<W> {
+    /// Returns all words of the expression in source order.
+    pub fn words(&self) -> Vec<&W> {
+        fn collect<'a, W>(cond: &'a Conditional<W>, words: &mut Vec<&'a W>) {
+            match *cond {
+                Conditional::Word(ref w) | Conditional::Unary(_, ref w) => words.push(w),
+                Conditional::Binary(ref l, _, ref r) => {
+                    words.push(l);
+                    words.push(r);
+                }
+                Conditional::Not(ref c) => collect(c, words),
+                Conditional::And(ref l, ref r) | Conditional::Or(ref l, ref r) => {
+                    collect(l, words);
+                    collect(r, words);
+                }
+            }
+        }
+
+        let mut words = Vec::new();
+        collect(self, &mut words);
+        words
+    }
+
+    /// Converts every word of the expression.
+    pub fn map<X, F>(self, f: &mut F) -> Conditional<X>
+    where
+        F: FnMut(W) -> X,
+    {
+        match self.try_map(&mut |w| Ok::<_, Infallible>(f(w))) {
+            Ok(cond) => cond,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Converts every word of the expression, stopping at the first error.
+    pub fn try_map<X, E, F>(self, f: &mut F) -> Result<Conditional<X>, E>
+    where
+        F: FnMut(W) -> Result<X, E>,
+    {
+        let cond = match self {
+            Conditional::Word(w) => Conditional::Word(f(w)?),
+            Conditional::Unary(op, w) => Conditional::Unary(op, f(w)?),
+            Conditional::Binary(l, op, r) => {
+                let l = f(l)?;
+                Conditional::Binary(l, op, f(r)?)
+            }
+            Conditional::Not(c) => Conditional::Not(Box::new(c.try_map(f)?)),
+            Conditional::And(l, r) => {
+                let l = l.try_map(f)?;
+                Conditional::And(Box::new(l), Box::new(r.try_map(f)?))
+            }
+            Conditional::Or(l, r) => {
+                let l = l.try_map(f)?;
+                Conditional::Or(Box::new(l), Box::new(r.try_map(f)?))
+            }
+        };
+
+        Ok(cond)
+    }
 }
 
 /// Represents a parsed redirect or a defined environment variable at the start
@@ -385,6 +807,7 @@ pub enum CompoundCommandKind<V, W, C> {
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RedirectOrEnvVar<R, V, W> {
     /// A parsed redirect before a command was encountered.
     Redirect(R),
@@ -399,6 +822,7 @@ pub enum RedirectOrEnvVar<R, V, W> {
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RedirectOrCmdWord<R, W> {
     /// A parsed redirect after a command was encountered.
     Redirect(R),
@@ -413,8 +837,13 @@ pub type DefaultSimpleCommand =
 /// The simplest possible command: an executable with arguments,
 /// environment variable assignments, and redirections.
 ///
+/// Redirections are kept interleaved with the words and assignments they appear
+/// between, since their relative order is significant (e.g. `>out cmd 2>&1` and
+/// `cmd 2>&1 >out` send stderr to different places).
+///
 /// Generic over representations of variable names, shell words, and redirects.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimpleCommand<V, W, R> {
     /// Redirections or environment variables that occur before any command
     /// in the order they were parsed.
@@ -430,23 +859,37 @@ pub type DefaultArithmetic = Arithmetic<String>;
 ///
 /// Generic over the representation of a variable name.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Arithmetic<T> {
     /// The value of a variable, e.g. `$var` or `var`.
+    ///
+    /// Note that if the variable's value is itself an arithmetic expression
+    /// (e.g. after `var='x + 1'`) it is evaluated recursively at runtime.
     Var(T),
-    /// A numeric literal such as `42` or `0xdeadbeef`.
+    /// The value of an element of an indexed array, e.g. `arr[i + 1]`.
+    Index(T, Box<Arithmetic<T>>),
+    /// A decimal numeric literal such as `42`.
     Literal(isize),
+    /// A numeric literal written in a base other than ten, such as `0xdeadbeef`,
+    /// `017`, or `2#1010`, along with the base it was written in and its source
+    /// text (e.g. `0XFF` or `10#0042`), which is printed as long as it still
+    /// denotes the same value in the same base.
+    RadixLiteral(isize, Radix, String),
     /// `left ** right`.
     Pow(Box<Arithmetic<T>>, Box<Arithmetic<T>>),
-    /// Returns the current value of a variable,
+    /// Returns the current value of a variable or array element,
     /// and then increments its value immediately after, e.g. `var++`
-    PostIncr(T),
-    /// Returns the current value of a variable,
+    PostIncr(ArithmeticTarget<T>),
+    /// Returns the current value of a variable or array element,
     /// and then decrements its value immediately after, e.g. `var--`
-    PostDecr(T),
-    /// Increments the value of a variable and returns the new value, e.g. `++var`.
-    PreIncr(T),
-    /// Decrements the value of a variable and returns the new value, e.g. `--var`.
-    PreDecr(T),
+    PostDecr(ArithmeticTarget<T>),
+    /// Increments the value of a variable or array element and returns
+    /// the new value, e.g. `++var`.
+    PreIncr(ArithmeticTarget<T>),
+    /// Decrements the value of a variable or array element and returns
+    /// the new value, e.g. `--var`.
+    PreDecr(ArithmeticTarget<T>),
     /// Ensures the sign of the underlying result is positive, e.g. `+(1-2)`.
     UnaryPlus(Box<Arithmetic<T>>),
     /// Ensures the sign of the underlying result is negative, e.g. `-(1+2)`.
@@ -493,17 +936,229 @@ pub enum Arithmetic<T> {
     LogicalOr(Box<Arithmetic<T>>, Box<Arithmetic<T>>),
     /// `first ? second : third`
     Ternary(Box<Arithmetic<T>>, Box<Arithmetic<T>>, Box<Arithmetic<T>>),
-    /// Assigns the value of an underlying expression to a
-    /// variable and returns the value, e.g. `x = 5`, or `x += 2`.
-    Assign(T, Box<Arithmetic<T>>),
+    /// Assigns the value of an underlying expression to a variable or
+    /// array element and returns the value, e.g. `x = 5`.
+    Assign(ArithmeticTarget<T>, Box<Arithmetic<T>>),
+    /// Applies an operator to the current value of a variable or array element
+    /// and the value of an underlying expression, assigns the result back, and
+    /// returns it, e.g. `x += 2` or `arr[i] <<= 1`.
+    AssignOp(AssignOperator, ArithmeticTarget<T>, Box<Arithmetic<T>>),
     /// `expr[, expr[, ...]]`
     Sequence(Vec<Arithmetic<T>>),
 }
 
+impl<T> Arithmetic<T> {
+    /// Returns the variables whose values are read when evaluating the
+    /// expression (including by increments, decrements, or compound assignments),
+    /// in the order in which they would be evaluated.
+    pub fn referenced_vars(&self) -> Vec<&T> {
+        let mut vars = Vec::new();
+        self.visit_vars(&mut |var, assigned| {
+            if !assigned {
+                vars.push(var)
+            }
+        });
+        vars
+    }
+
+    /// Returns the variables which may be modified by evaluating the expression
+    /// (i.e. the targets of any assignments, increments, or decrements), in the
+    /// order in which they would be evaluated.
+    ///
+    /// Note that the target of a compound assignment such as `x += 1` is also
+    /// returned by `referenced_vars`, since its current value is read as well.
+    pub fn assigned_vars(&self) -> Vec<&T> {
+        let mut vars = Vec::new();
+        self.visit_vars(&mut |var, assigned| {
+            if assigned {
+                vars.push(var)
+            }
+        });
+        vars
+    }
+
+    /// Invokes `f` on every variable which is read or assigned (as indicated by
+    /// the second argument) in evaluation order.
+    fn visit_vars<'a, F: FnMut(&'a T, bool)>(&'a self, f: &mut F) {
+        use self::Arithmetic::*;
+
+        match *self {
+            Literal(_) | RadixLiteral(..) => {}
+            Var(ref var) => f(var, false),
+
+            Index(ref var, ref index) => {
+                index.visit_vars(f);
+                f(var, false);
+            }
+
+            PostIncr(ref target) | PostDecr(ref target) | PreIncr(ref target)
+            | PreDecr(ref target) => {
+                target.visit_index_vars(f);
+                f(target.name(), false);
+                f(target.name(), true);
+            }
+
+            UnaryPlus(ref expr)
+            | UnaryMinus(ref expr)
+            | LogicalNot(ref expr)
+            | BitwiseNot(ref expr) => expr.visit_vars(f),
+
+            Pow(ref left, ref right)
+            | Mult(ref left, ref right)
+            | Div(ref left, ref right)
+            | Modulo(ref left, ref right)
+            | Add(ref left, ref right)
+            | Sub(ref left, ref right)
+            | ShiftLeft(ref left, ref right)
+            | ShiftRight(ref left, ref right)
+            | Less(ref left, ref right)
+            | LessEq(ref left, ref right)
+            | Great(ref left, ref right)
+            | GreatEq(ref left, ref right)
+            | Eq(ref left, ref right)
+            | NotEq(ref left, ref right)
+            | BitwiseAnd(ref left, ref right)
+            | BitwiseXor(ref left, ref right)
+            | BitwiseOr(ref left, ref right)
+            | LogicalAnd(ref left, ref right)
+            | LogicalOr(ref left, ref right) => {
+                left.visit_vars(f);
+                right.visit_vars(f);
+            }
+
+            Ternary(ref guard, ref body, ref els) => {
+                guard.visit_vars(f);
+                body.visit_vars(f);
+                els.visit_vars(f);
+            }
+
+            Assign(ref target, ref value) => {
+                target.visit_index_vars(f);
+                value.visit_vars(f);
+                f(target.name(), true);
+            }
+
+            AssignOp(_, ref target, ref value) => {
+                target.visit_index_vars(f);
+                f(target.name(), false);
+                value.visit_vars(f);
+                f(target.name(), true);
+            }
+
+            Sequence(ref exprs) => {
+                for expr in exprs {
+                    expr.visit_vars(f);
+                }
+            }
+        }
+    }
+}
+
+/// The variable or array element modified by an arithmetic assignment,
+/// increment, or decrement.
+///
+/// Generic over the representation of a variable name.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArithmeticTarget<T> {
+    /// A variable, e.g. `x` in `x += 1`.
+    Var(T),
+    /// An element of an indexed array, e.g. `arr[i]` in `arr[i]++`.
+    Index(T, Box<Arithmetic<T>>),
+}
+
+impl<T> ArithmeticTarget<T> {
+    /// Returns the name of the variable or array being modified.
+    pub fn name(&self) -> &T {
+        match *self {
+            ArithmeticTarget::Var(ref name) | ArithmeticTarget::Index(ref name, _) => name,
+        }
+    }
+
+    /// Invokes `f` on every variable read by the index of an array element.
+    fn visit_index_vars<'a, F: FnMut(&'a T, bool)>(&'a self, f: &mut F) {
+        if let ArithmeticTarget::Index(_, ref index) = *self {
+            index.visit_vars(f);
+        }
+    }
+}
+
+/// The operator of a compound arithmetic assignment, e.g. the `+=` of `x += 1`.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AssignOperator {
+    /// `*=`
+    Mult,
+    /// `/=`
+    Div,
+    /// `%=`
+    Modulo,
+    /// `+=`
+    Add,
+    /// `-=`
+    Sub,
+    /// `<<=`
+    ShiftLeft,
+    /// `>>=`
+    ShiftRight,
+    /// `&=`
+    BitwiseAnd,
+    /// `^=`
+    BitwiseXor,
+    /// `|=`
+    BitwiseOr,
+}
+
+impl AssignOperator {
+    /// Returns the source text of the operator.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            AssignOperator::Mult => "*=",
+            AssignOperator::Div => "/=",
+            AssignOperator::Modulo => "%=",
+            AssignOperator::Add => "+=",
+            AssignOperator::Sub => "-=",
+            AssignOperator::ShiftLeft => "<<=",
+            AssignOperator::ShiftRight => ">>=",
+            AssignOperator::BitwiseAnd => "&=",
+            AssignOperator::BitwiseXor => "^=",
+            AssignOperator::BitwiseOr => "|=",
+        }
+    }
+}
+
+/// The base in which an arithmetic numeric literal is written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Radix {
+    /// A literal without any prefix, e.g. `42`.
+    Decimal,
+    /// A literal with a leading zero, e.g. `017`.
+    Octal,
+    /// A literal with a `0x` or `0X` prefix, e.g. `0x1f`.
+    Hex,
+    /// A literal with an explicit base between 2 and 64, e.g. `2#1010` or `16#ff`.
+    Explicit(u8),
+}
+
+impl Radix {
+    /// Returns the numeric base of the radix.
+    pub fn base(&self) -> u8 {
+        match *self {
+            Radix::Decimal => 10,
+            Radix::Octal => 8,
+            Radix::Hex => 16,
+            Radix::Explicit(base) => base,
+        }
+    }
+}
+
 macro_rules! impl_top_level_cmd {
     ($(#[$attr:meta])* pub struct $Cmd:ident, $CmdList:ident, $Word:ident) => {
         $(#[$attr])*
         #[derive(Debug, PartialEq, Eq, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
         pub struct $Cmd<T>(pub Command<$CmdList<T, $Word<T>, $Cmd<T>>>);
 
         impl<T> ops::Deref for $Cmd<T> {
@@ -561,6 +1216,8 @@ macro_rules! impl_top_level_word {
     ($(#[$attr:meta])* pub struct $Word:ident, $Cmd:ident) => {
         $(#[$attr])*
         #[derive(Debug, PartialEq, Eq, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
         pub struct $Word<T>(pub ShellWord<T, $Word<T>, $Cmd<T>>);
 
         impl<T> ops::Deref for $Word<T> {
@@ -611,6 +1268,328 @@ impl_top_level_word! {
     AtomicTopLevelCommand
 }
 
+/// A top-level representation of a shell command which retains the comments
+/// around it. Uses `Rc` wrappers for function declarations.
+///
+/// Built by `builder::CommentedDefaultBuilder`, and otherwise equivalent to
+/// `TopLevelCommand`. Each comment is kept as a `Newline`, where a `None`
+/// represents a blank line, so that formatters can reproduce them faithfully.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommentedTopLevelCommand<T> {
+    /// Any comments or blank lines which appear before the command.
+    pub pre_cmd_comments: Vec<Newline>,
+    /// The command itself.
+    pub command: Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>,
+    /// Any comments which appear after each `&&` or `||` of the command, but
+    /// before the pipeline which follows it, matching the `rest` of its `AndOrList`.
+    pub and_or_comments: Vec<Vec<Newline>>,
+    /// A comment which appears on the same line after the command, if any.
+    pub cmd_comment: Option<Newline>,
+    /// Any comments which appear after the last command of a compound command's
+    /// body, but before its closing reserved word or token (e.g. `}` or `done`).
+    pub post_cmd_comments: Vec<Newline>,
+}
+
+impl<T> ops::Deref for CommentedTopLevelCommand<T> {
+    type Target = Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.command
+    }
+}
+
+impl<T> ops::DerefMut for CommentedTopLevelCommand<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.command
+    }
+}
+
+impl<T: PartialEq<T>>
+    PartialEq<Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>>
+    for CommentedTopLevelCommand<T>
+{
+    fn eq(
+        &self,
+        other: &Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>,
+    ) -> bool {
+        &self.command == other
+    }
+}
+
+impl<T> From<Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>>
+    for CommentedTopLevelCommand<T>
+{
+    fn from(
+        command: Command<CommandList<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>,
+    ) -> Self {
+        CommentedTopLevelCommand {
+            pre_cmd_comments: Vec::new(),
+            command,
+            and_or_comments: Vec::new(),
+            cmd_comment: None,
+            post_cmd_comments: Vec::new(),
+        }
+    }
+}
+
+impl_top_level_word! {
+    /// A top-level representation of a shell word whose commands retain their
+    /// comments. Uses `Rc` wrappers for function declarations.
+    pub struct CommentedTopLevelWord,
+    CommentedTopLevelCommand
+}
+
+impl<P, W, C, A> ParameterSubstitution<P, W, C, A> {
+    /// Returns the parameter the substitution operates on, if any.
+    pub fn parameter(&self) -> Option<&P> {
+        use self::ParameterSubstitution::*;
+
+        match *self {
+            Command(_) | ReadFile(_) | Arith(_) | ProcessRead(_) | ProcessWrite(_) => None,
+            Len(ref p)
+            | Default(_, ref p, _)
+            | Assign(_, ref p, _)
+            | Error(_, ref p, _)
+            | Alternative(_, ref p, _)
+            | RemoveSmallestSuffix(ref p, _)
+            | RemoveLargestSuffix(ref p, _)
+            | RemoveSmallestPrefix(ref p, _)
+            | RemoveLargestPrefix(ref p, _)
+            | ReplaceString(ref p, _)
+            | ReplaceStringAll(ref p, _)
+            | Substring(ref p, _)
+            | Lowercase(_, ref p, _)
+            | Uppercase(_, ref p, _) => Some(p),
+        }
+    }
+
+    /// Returns the word operand of the substitution, if any, e.g. the `word`
+    /// in `${param:-word}` or the `file` in `$(< file)`.
+    pub fn word(&self) -> Option<&W> {
+        use self::ParameterSubstitution::*;
+
+        match *self {
+            Command(_) | Len(_) | Arith(_) | ProcessRead(_) | ProcessWrite(_) => None,
+            ReadFile(ref w) => Some(w),
+            Default(_, _, ref w)
+            | Assign(_, _, ref w)
+            | Error(_, _, ref w)
+            | Alternative(_, _, ref w)
+            | RemoveSmallestSuffix(_, ref w)
+            | RemoveLargestSuffix(_, ref w)
+            | RemoveSmallestPrefix(_, ref w)
+            | RemoveLargestPrefix(_, ref w)
+            | ReplaceString(_, ref w)
+            | ReplaceStringAll(_, ref w)
+            | Substring(_, ref w)
+            | Lowercase(_, _, ref w)
+            | Uppercase(_, _, ref w) => w.as_ref(),
+        }
+    }
+
+    /// Returns the commands of a command or process substitution, e.g. `$(cmd)` or `<(cmd)`.
+    pub fn commands(&self) -> Option<&[C]> {
+        match *self {
+            ParameterSubstitution::Command(ref cmds)
+            | ParameterSubstitution::ProcessRead(ref cmds)
+            | ParameterSubstitution::ProcessWrite(ref cmds) => Some(cmds),
+            _ => None,
+        }
+    }
+}
+
+impl<L, P, S> SimpleWord<L, P, S> {
+    /// Returns the literal contents of the word, if it is a (possibly escaped) literal.
+    pub fn literal(&self) -> Option<&L> {
+        match *self {
+            SimpleWord::Literal(ref l) | SimpleWord::Escaped(ref l) => Some(l),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the word has a special meaning in pathname expansions
+    /// (i.e. `*`, `?`, `[`, or `]`).
+    pub fn is_glob(&self) -> bool {
+        matches!(
+            *self,
+            SimpleWord::Star
+                | SimpleWord::Question
+                | SimpleWord::SquareOpen
+                | SimpleWord::SquareClose
+        )
+    }
+}
+
+impl<W> Braces<W> {
+    /// Returns every word of the alternatives, in order, or nothing for a sequence.
+    pub fn words(&self) -> impl Iterator<Item = &W> {
+        let alternatives = match *self {
+            Braces::Alternatives(ref alternatives) => &alternatives[..],
+            Braces::Numbers { .. } | Braces::Chars { .. } => &[],
+        };
+
+        alternatives.iter().flatten()
+    }
+
+    /// Returns a mutable reference to every word of the alternatives, in order,
+    /// or nothing for a sequence.
+    pub fn words_mut(&mut self) -> impl Iterator<Item = &mut W> {
+        let alternatives = match *self {
+            Braces::Alternatives(ref mut alternatives) => &mut alternatives[..],
+            Braces::Numbers { .. } | Braces::Chars { .. } => &mut [],
+        };
+
+        alternatives.iter_mut().flatten()
+    }
+
+    /// Converts every word of the alternatives.
+    pub fn map<X, F>(self, f: &mut F) -> Braces<X>
+    where
+        F: FnMut(W) -> X,
+    {
+        match self.try_map(&mut |w| Ok::<_, Infallible>(f(w))) {
+            Ok(braces) => braces,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Converts every word of the alternatives, stopping at the first error.
+    pub fn try_map<X, E, F>(self, f: &mut F) -> Result<Braces<X>, E>
+    where
+        F: FnMut(W) -> Result<X, E>,
+    {
+        let braces = match self {
+            Braces::Alternatives(alternatives) => Braces::Alternatives(
+                alternatives
+                    .into_iter()
+                    .map(|words| words.into_iter().map(&mut *f).collect())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Braces::Numbers {
+                start,
+                end,
+                step,
+                width,
+            } => Braces::Numbers {
+                start,
+                end,
+                step,
+                width,
+            },
+            Braces::Chars { start, end, step } => Braces::Chars { start, end, step },
+        };
+
+        Ok(braces)
+    }
+}
+
+impl<W> Redirect<W> {
+    /// Returns the file descriptor being redirected, if one was explicitly specified.
+    pub fn fd(&self) -> Option<u16> {
+        match *self {
+            Redirect::Read(fd, _)
+            | Redirect::Write(fd, _)
+            | Redirect::ReadWrite(fd, _)
+            | Redirect::Append(fd, _)
+            | Redirect::Clobber(fd, _)
+            | Redirect::Heredoc(fd, _, _)
+            | Redirect::DupRead(fd, _)
+            | Redirect::DupWrite(fd, _) => fd,
+            Redirect::WriteBoth(_) | Redirect::AppendBoth(_) => None,
+        }
+    }
+
+    /// Returns the target of the redirect (e.g. a file name, a file descriptor,
+    /// or the body of a heredoc).
+    pub fn word(&self) -> &W {
+        match *self {
+            Redirect::Read(_, ref w)
+            | Redirect::Write(_, ref w)
+            | Redirect::ReadWrite(_, ref w)
+            | Redirect::Append(_, ref w)
+            | Redirect::Clobber(_, ref w)
+            | Redirect::Heredoc(_, ref w, _)
+            | Redirect::DupRead(_, ref w)
+            | Redirect::DupWrite(_, ref w)
+            | Redirect::WriteBoth(ref w)
+            | Redirect::AppendBoth(ref w) => w,
+        }
+    }
+
+    /// Returns how the heredoc was written, if the redirect is a heredoc.
+    pub fn heredoc_info(&self) -> Option<&HeredocInfo> {
+        match *self {
+            Redirect::Heredoc(_, _, ref info) => Some(info),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Pipeline<T> {
+    /// Returns an iterator over the commands of the pipeline, in order.
+    pub fn commands(&self) -> impl Iterator<Item = &T> {
+        self.segments.iter().map(|segment| &segment.cmd)
+    }
+}
+
+impl<T> PipeSegment<T> {
+    /// Creates the first segment of a pipeline, which has no preceding pipe.
+    pub fn first(cmd: T) -> Self {
+        PipeSegment {
+            pipe: None,
+            pipe_stderr: false,
+            cmd,
+        }
+    }
+}
+
+impl<V, W, C> CompoundCommandKind<V, W, C> {
+    /// Returns all lists of commands nested directly within the compound
+    /// command (e.g. guards, bodies, and branches) in source order.
+    pub fn bodies(&self) -> Vec<&[C]> {
+        use self::CompoundCommandKind::*;
+
+        match *self {
+            Brace(ref cmds) | Subshell(ref cmds) | For { body: ref cmds, .. } => vec![cmds],
+            While(ref gbp) | Until(ref gbp) => vec![&gbp.guard, &gbp.body],
+            If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                let mut bodies = Vec::with_capacity(conditionals.len() * 2 + 1);
+                for gbp in conditionals {
+                    bodies.push(&*gbp.guard);
+                    bodies.push(&*gbp.body);
+                }
+                if let Some(ref els) = *else_branch {
+                    bodies.push(els);
+                }
+                bodies
+            }
+            Case { ref arms, .. } => arms.iter().map(|arm| &*arm.body).collect(),
+            Conditional(_) | Arithmetic(_) => Vec::new(),
+        }
+    }
+
+    /// Returns all words directly within the compound command, e.g.
+    /// the words of a `for` loop, or the word and patterns of a `case` command.
+    pub fn words(&self) -> Vec<&W> {
+        match *self {
+            CompoundCommandKind::For {
+                words: Some(ref words),
+                ..
+            } => words.iter().collect(),
+            CompoundCommandKind::Case { ref word, ref arms } => {
+                let patterns = arms.iter().flat_map(|arm| &arm.patterns);
+                Some(word).into_iter().chain(patterns).collect()
+            }
+            CompoundCommandKind::Conditional(ref cond) => cond.words(),
+            _ => Vec::new(),
+        }
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Parameter<T> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         use self::Parameter::*;
@@ -9,16 +9,39 @@
 //! the `Builder` trait for your AST. Otherwise you can provide the `DefaultBuilder`
 //! struct to the parser if you wish to use the default AST implementation.
 
-use crate::ast::{AndOr, DefaultArithmetic, DefaultParameter, RedirectOrCmdWord, RedirectOrEnvVar};
+use crate::ast::{
+    AndOr, Braces, CaseTerminator, Conditional, DefaultArithmetic, DefaultParameter, HeredocInfo,
+    PipeSegment, RedirectOrCmdWord, RedirectOrEnvVar, Span, UnparsedSpan,
+};
+use std::convert::Infallible;
 
+mod command_hooks;
+mod commented_builder;
+mod dedup_builder;
 mod default_builder;
+mod dyn_builder;
 mod empty_builder;
+mod event_log;
+mod outline_builder;
+mod profiling_builder;
+mod word_builder;
 
+pub use self::command_hooks::{CommandHook, CommandHooks};
+pub use self::commented_builder::CommentedDefaultBuilder;
+pub use self::dedup_builder::{DedupBuilder, DedupStats, StringPool};
 pub use self::default_builder::*;
+pub use self::dyn_builder::{AnyNode, DynBuilder, DynError, ErasedBuilder, NodeMismatch};
 pub use self::empty_builder::EmptyBuilder;
+pub use self::event_log::{Event, EventLog, NodeId, ReplayError};
+pub use self::outline_builder::{OutlineBuilder, OutlineItem, OutlineKind};
+pub use self::profiling_builder::{
+    BuilderProfile, Callback, CallbackStats, CallbackTiming, ProfilingBuilder,
+};
+pub use self::word_builder::WordBuilder;
 
 /// An indicator to the builder of how complete commands are separated.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SeparatorKind {
     /// A semicolon appears between commands, normally indicating a sequence.
     Semi,
@@ -33,6 +56,8 @@ pub enum SeparatorKind {
 
 /// An indicator to the builder whether a `while` or `until` command was parsed.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum LoopKind {
     /// A `while` command was parsed, normally indicating the loop's body should be run
     /// while the guard's exit status is successful.
@@ -42,8 +67,39 @@ pub enum LoopKind {
     Until,
 }
 
+/// An indicator to the builder which syntax was used to declare a function.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FunctionSyntax {
+    /// The POSIX syntax was used, e.g. `foo() { ... }`.
+    Posix,
+    /// The `function` keyword was used without parentheses, e.g. `function foo { ... }`.
+    Keyword,
+    /// The `function` keyword was used along with parentheses, e.g. `function foo() { ... }`.
+    KeywordParens,
+}
+
+/// A nested context entered by the parser, see `Builder::enter_context`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ContextKind {
+    /// The body of a function declaration with the given name, e.g. `foo() { ... }`.
+    Function(String),
+    /// The commands of a subshell, e.g. `( ... )`.
+    Subshell,
+    /// The commands of a brace group, e.g. `{ ... }`.
+    BraceGroup,
+    /// The commands of a command substitution, e.g. `$( ... )` or `` `...` ``.
+    CommandSubstitution,
+    /// The commands of a process substitution, e.g. `<( ... )` or `>( ... )`.
+    ProcessSubstitution,
+}
+
 /// A grouping of a list of commands and any comments trailing after the commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandGroup<C> {
     /// The sequential list of commands.
     pub commands: Vec<C>,
@@ -53,6 +109,7 @@ pub struct CommandGroup<C> {
 
 /// A grouping of guard and body commands, and any comments they may have.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GuardBodyPairGroup<C> {
     /// The guard commands, which if successful, should lead to the
     /// execution of the body commands.
@@ -63,6 +120,7 @@ pub struct GuardBodyPairGroup<C> {
 
 /// Parsed fragments relating to a shell `if` command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfFragments<C> {
     /// A list of conditionals branches.
     pub conditionals: Vec<GuardBodyPairGroup<C>>,
@@ -72,6 +130,7 @@ pub struct IfFragments<C> {
 
 /// Parsed fragments relating to a shell `for` command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForFragments<W, C> {
     /// The name of the variable to which each of the words will be bound.
     pub var: String,
@@ -79,6 +138,10 @@ pub struct ForFragments<W, C> {
     pub var_comment: Option<Newline>,
     /// Any comments after the variable declaration, a group of words to
     /// iterator over, and comment defined on the same line as the words.
+    ///
+    /// This is `None` if the `in` clause was omitted (meaning the loop should
+    /// iterate over the positional parameters), which is distinct from an `in`
+    /// clause with no words (meaning the loop should not iterate at all).
     pub words: Option<(Vec<Newline>, Vec<W>, Option<Newline>)>,
     /// Any comments that appear after the `words` declaration (if it exists),
     /// but before the body of commands.
@@ -89,6 +152,7 @@ pub struct ForFragments<W, C> {
 
 /// Parsed fragments relating to a shell `case` command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CaseFragments<W, C> {
     /// The word to be matched against.
     pub word: W,
@@ -108,18 +172,22 @@ pub struct CaseFragments<W, C> {
 /// Each arm has a number of pattern alternatives, and a body
 /// of commands to run if any pattern matches.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CaseArm<W, C> {
     /// The patterns which correspond to this case arm.
     pub patterns: CasePatternFragments<W>,
     /// The body of commands to run if any pattern matches.
     pub body: CommandGroup<C>,
+    /// The operator which terminates the arm, e.g. `;;` or `;&`.
+    pub terminator: CaseTerminator,
     /// A comment appearing at the end of the arm declaration,
-    /// i.e. after `;;` but on the same line.
+    /// i.e. after its terminator but on the same line.
     pub arm_comment: Option<Newline>,
 }
 
 /// Parsed fragments relating to patterns in a shell `case` command.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CasePatternFragments<W> {
     /// Comments appearing after a previous arm, but before the start of a pattern.
     pub pre_pattern_comments: Vec<Newline>,
@@ -131,6 +199,7 @@ pub struct CasePatternFragments<W> {
 
 /// An indicator to the builder what kind of complex word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComplexWordKind<C> {
     /// Several distinct words concatenated together.
     Concat(Vec<WordKind<C>>),
@@ -140,6 +209,7 @@ pub enum ComplexWordKind<C> {
 
 /// An indicator to the builder what kind of word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WordKind<C> {
     /// A regular word.
     Simple(SimpleWordKind<C>),
@@ -148,10 +218,18 @@ pub enum WordKind<C> {
     /// List of words concatenated within single quotes. Virtually
     /// identical as a literal, but makes a distinction between the two.
     SingleQuoted(String),
+    /// A brace expansion, e.g. `{a,b}` or `{1..10}`.
+    Braces(Braces<WordKind<C>>),
 }
 
+/// A parameter substitution found within a word, whose operand (if any) is a word
+/// of its own, e.g. the `word` of `${param:-word}`.
+pub type SubstitutionKind<C> = ParameterSubstitutionKind<ComplexWordKind<C>, C>;
+
 /// An indicator to the builder what kind of simple word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum SimpleWordKind<C> {
     /// A non-special literal word.
     Literal(String),
@@ -176,10 +254,15 @@ pub enum SimpleWordKind<C> {
     Tilde,
     /// Represents `:`, useful for handling tilde expansions.
     Colon,
+    /// A tilde prefix which is subject to tilde expansion, e.g. `~` or `~user`,
+    /// along with the login name (if any) which follows the tilde.
+    TildePrefix(Option<String>),
 }
 
 /// Represents redirecting a command's file descriptors.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum RedirectKind<W> {
     /// Open a file for reading, e.g. `[n]< file`.
     Read(Option<u16>, W),
@@ -191,19 +274,80 @@ pub enum RedirectKind<W> {
     Append(Option<u16>, W),
     /// Open a file for writing, failing if the `noclobber` shell option is set, e.g. `[n]>| file`.
     Clobber(Option<u16>, W),
-    /// Lines contained in the source that should be provided by as input to a file descriptor.
-    Heredoc(Option<u16>, W),
+    /// Lines contained in the source that should be provided by as input to a file descriptor,
+    /// along with how the heredoc was written.
+    Heredoc(Option<u16>, W, HeredocInfo),
     /// Duplicate a file descriptor for reading, e.g. `[n]<& [n|-]`.
     DupRead(Option<u16>, W),
     /// Duplicate a file descriptor for writing, e.g. `[n]>& [n|-]`.
     DupWrite(Option<u16>, W),
+    /// Open a file for writing after truncating, and redirect both standard output
+    /// and standard error to it, e.g. `&> file` (a bash extension).
+    WriteBoth(W),
+    /// Open a file for writing, appending to the end, and redirect both standard
+    /// output and standard error to it, e.g. `&>> file` (a bash extension).
+    AppendBoth(W),
+}
+
+impl<W> RedirectKind<W> {
+    /// Returns the file descriptor being redirected, if one was explicitly specified.
+    pub fn fd(&self) -> Option<u16> {
+        match *self {
+            RedirectKind::Read(fd, _)
+            | RedirectKind::Write(fd, _)
+            | RedirectKind::ReadWrite(fd, _)
+            | RedirectKind::Append(fd, _)
+            | RedirectKind::Clobber(fd, _)
+            | RedirectKind::Heredoc(fd, _, _)
+            | RedirectKind::DupRead(fd, _)
+            | RedirectKind::DupWrite(fd, _) => fd,
+            RedirectKind::WriteBoth(_) | RedirectKind::AppendBoth(_) => None,
+        }
+    }
+
+    /// Returns the target of the redirect (e.g. a file name, a file descriptor,
+    /// or the body of a heredoc).
+    pub fn word(&self) -> &W {
+        match *self {
+            RedirectKind::Read(_, ref w)
+            | RedirectKind::Write(_, ref w)
+            | RedirectKind::ReadWrite(_, ref w)
+            | RedirectKind::Append(_, ref w)
+            | RedirectKind::Clobber(_, ref w)
+            | RedirectKind::Heredoc(_, ref w, _)
+            | RedirectKind::DupRead(_, ref w)
+            | RedirectKind::DupWrite(_, ref w)
+            | RedirectKind::WriteBoth(ref w)
+            | RedirectKind::AppendBoth(ref w) => w,
+        }
+    }
+
+    /// Splits the redirect into the file descriptor being redirected (if one
+    /// was explicitly specified) and its target, discarding the kind of redirect.
+    pub fn into_parts(self) -> (Option<u16>, W) {
+        match self {
+            RedirectKind::Read(fd, w)
+            | RedirectKind::Write(fd, w)
+            | RedirectKind::ReadWrite(fd, w)
+            | RedirectKind::Append(fd, w)
+            | RedirectKind::Clobber(fd, w)
+            | RedirectKind::Heredoc(fd, w, _)
+            | RedirectKind::DupRead(fd, w)
+            | RedirectKind::DupWrite(fd, w) => (fd, w),
+            RedirectKind::WriteBoth(w) | RedirectKind::AppendBoth(w) => (None, w),
+        }
+    }
 }
 
 /// Represents the type of parameter that was parsed
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum ParameterSubstitutionKind<W, C> {
     /// Returns the standard output of running a command, e.g. `$(cmd)`
     Command(CommandGroup<C>),
+    /// Returns the contents of a file without running any command, e.g. `$(< file)`
+    ReadFile(W),
     /// Returns the length of the value of a parameter, e.g. ${#param}
     Len(DefaultParameter),
     /// Returns the resulting value of an arithmetic subsitution, e.g. `$(( x++ ))`
@@ -247,6 +391,57 @@ pub enum ParameterSubstitutionKind<W, C> {
     Lowercase(bool, DefaultParameter, Option<W>),
     /// Uppercase the matched value, e.g. `${param,,pattern}
     Uppercase(bool, DefaultParameter, Option<W>),
+    /// Returns a path from which the standard output of running a command
+    /// can be read, e.g. `<(cmd)`
+    ProcessRead(CommandGroup<C>),
+    /// Returns a path whose contents are written to the standard input of
+    /// running a command, e.g. `>(cmd)`
+    ProcessWrite(CommandGroup<C>),
+}
+
+impl<W, C> ParameterSubstitutionKind<W, C> {
+    /// Converts the operand word (if any) of the substitution.
+    pub fn map<X, F>(self, f: &mut F) -> ParameterSubstitutionKind<X, C>
+    where
+        F: FnMut(W) -> X,
+    {
+        match self.try_map(&mut |w| Ok::<_, Infallible>(f(w))) {
+            Ok(subst) => subst,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Converts the operand word (if any) of the substitution, stopping at the first error.
+    pub fn try_map<X, E, F>(self, f: &mut F) -> Result<ParameterSubstitutionKind<X, C>, E>
+    where
+        F: FnMut(W) -> Result<X, E>,
+    {
+        use self::ParameterSubstitutionKind::*;
+
+        let mut map = |w: Option<W>| w.map(&mut *f).transpose();
+        let subst = match self {
+            Command(c) => Command(c),
+            ReadFile(w) => ReadFile(f(w)?),
+            Len(p) => Len(p),
+            Arith(a) => Arith(a),
+            Default(c, p, w) => Default(c, p, map(w)?),
+            Assign(c, p, w) => Assign(c, p, map(w)?),
+            Error(c, p, w) => Error(c, p, map(w)?),
+            Alternative(c, p, w) => Alternative(c, p, map(w)?),
+            RemoveSmallestSuffix(p, w) => RemoveSmallestSuffix(p, map(w)?),
+            RemoveLargestSuffix(p, w) => RemoveLargestSuffix(p, map(w)?),
+            RemoveSmallestPrefix(p, w) => RemoveSmallestPrefix(p, map(w)?),
+            RemoveLargestPrefix(p, w) => RemoveLargestPrefix(p, map(w)?),
+            ReplaceString(p, w) => ReplaceString(p, map(w)?),
+            ReplaceStringAll(p, w) => ReplaceStringAll(p, map(w)?),
+            Substring(p, w) => Substring(p, map(w)?),
+            Lowercase(c, p, w) => Lowercase(c, p, map(w)?),
+            Uppercase(c, p, w) => Uppercase(c, p, map(w)?),
+            ProcessRead(c) => ProcessRead(c),
+            ProcessWrite(c) => ProcessWrite(c),
+        };
+        Ok(subst)
+    }
 }
 
 /// Represents a parsed newline, more specifically, the presense of a comment
@@ -255,8 +450,28 @@ pub enum ParameterSubstitutionKind<W, C> {
 /// Since shell comments are usually treated as a newline, they can be present
 /// anywhere a newline can be as well. Thus if it is desired to retain comments
 /// they can be optionally attached to a parsed newline.
+///
+/// When serialized, a newline without a comment is represented by an empty
+/// string, which keeps it distinct from a missing (i.e. `None`) newline.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Newline(pub Option<String>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Newline(
+    #[cfg_attr(feature = "serde", serde(with = "newline_comment"))] pub Option<String>,
+);
+
+#[cfg(feature = "serde")]
+mod newline_comment {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(comment: &Option<String>, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(comment.as_deref().unwrap_or(""))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+        let comment = String::deserialize(d)?;
+        Ok(Some(comment).filter(|c| !c.is_empty()))
+    }
+}
 
 /// A trait which defines an interface which the parser defined in the `parse` module
 /// uses to delegate Abstract Syntax Tree creation. The methods defined here correspond
@@ -288,12 +503,14 @@ pub trait Builder {
     /// * list: an and/or list of commands previously generated by the same builder
     /// * separator: indicates how the command was delimited
     /// * cmd_comment: a comment that appears at the end of the command
+    /// * span: the location of the command in the source, including any `;` or `&` separator
     fn complete_command(
         &mut self,
         pre_cmd_comments: Vec<Newline>,
         list: Self::CommandList,
         separator: SeparatorKind,
         cmd_comment: Option<Newline>,
+        span: Span,
     ) -> Result<Self::Command, Self::Error>;
 
     /// Invoked when multiple commands are parsed which are separated by `&&` or `||`.
@@ -303,10 +520,12 @@ pub trait Builder {
     /// # Arguments
     /// * first: the first command before any `&&` or `||` separator
     /// * rest: A collection of comments after the last separator and the next command.
+    /// * span: the location of the entire list in the source
     fn and_or_list(
         &mut self,
         first: Self::ListableCommand,
         rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
     ) -> Result<Self::CommandList, Self::Error>;
 
     /// Invoked when a pipeline of commands is parsed.
@@ -317,11 +536,13 @@ pub trait Builder {
     /// * bang: the presence of a `!` at the start of the pipeline, typically indicating
     /// that the pipeline's exit status should be logically inverted.
     /// * cmds: a collection of tuples which are any comments appearing after a pipe token, followed
-    /// by the command itself, all in the order they were parsed
+    /// by the command itself and the pipe preceding it, all in the order they were parsed
+    /// * span: the location of the entire pipeline in the source, including any `!`
     fn pipeline(
         &mut self,
         bang: bool,
-        cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
     ) -> Result<Self::ListableCommand, Self::Error>;
 
     /// Invoked when the "simplest" possible command is parsed: an executable with arguments.
@@ -329,10 +550,12 @@ pub trait Builder {
     /// # Arguments
     /// * redirects_or_env_vars: redirections or environment variables that occur before any command
     /// * redirects_or_cmd_words: redirections or any command or argument
+    /// * span: the location of the entire command in the source, excluding the bodies of any heredocs
     fn simple_command(
         &mut self,
         redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
         redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
     ) -> Result<Self::PipeableCommand, Self::Error>;
 
     /// Invoked when a non-zero number of commands were parsed between balanced curly braces.
@@ -341,10 +564,14 @@ pub trait Builder {
     /// # Arguments
     /// * cmds: the commands that were parsed between braces
     /// * redirects: any redirects to be applied over the **entire** group of commands
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn brace_group(
         &mut self,
         cmds: CommandGroup<Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
     /// Invoked when a non-zero number of commands were parsed between balanced parentheses.
@@ -354,10 +581,14 @@ pub trait Builder {
     /// # Arguments
     /// * cmds: the commands that were parsed between parens
     /// * redirects: any redirects to be applied over the **entire** group of commands
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn subshell(
         &mut self,
         cmds: CommandGroup<Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
     /// Invoked when a loop command like `while` or `until` is parsed.
@@ -368,11 +599,15 @@ pub trait Builder {
     /// * guard: commands that determine how long the loop will run for
     /// * body: commands to be run every iteration of the loop
     /// * redirects: any redirects to be applied over **all** commands part of the loop
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn loop_command(
         &mut self,
         kind: LoopKind,
         guard_body_pair: GuardBodyPairGroup<Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
     /// Invoked when an `if` conditional command is parsed.
@@ -383,10 +618,14 @@ pub trait Builder {
     /// # Arguments
     /// * fragments: parsed fragments relating to a shell `if` command.
     /// * redirects: any redirects to be applied over **all** commands within the `if` command
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn if_command(
         &mut self,
         fragments: IfFragments<Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
     /// Invoked when a `for` command is parsed.
@@ -397,10 +636,14 @@ pub trait Builder {
     /// # Arguments
     /// * fragments: parsed fragments relating to a shell `for` command.
     /// * redirects: any redirects to be applied over **all** commands within the `for` command
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn for_command(
         &mut self,
         fragments: ForFragments<Self::Word, Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
     /// Invoked when a `case` command is parsed.
@@ -409,12 +652,91 @@ pub trait Builder {
     /// # Arguments
     /// * fragments: parsed fragments relating to a shell `case` command.
     /// * redirects: any redirects to be applied over **all** commands part of the `case` block
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
     fn case_command(
         &mut self,
         fragments: CaseFragments<Self::Word, Self::Command>,
         redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
+    /// Invoked when a `[[ ... ]]` conditional command is parsed (a bash extension).
+    /// Typically this command succeeds if its expression evaluates to true.
+    ///
+    /// By default the command is lowered into a brace group holding a simple
+    /// command whose words are those of the expression, e.g. `{ [[ -n $x ]]; }`,
+    /// so builders without a representation of conditional commands keep working.
+    ///
+    /// # Arguments
+    /// * expr: the conditional expression between the brackets.
+    /// * redirects: any redirects to be applied over the command
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let mut words = vec![literal_word(self, "[[", span)?];
+        conditional_words(self, expr, span, &mut words)?;
+        words.push(literal_word(self, "]]", span)?);
+
+        let words = words.into_iter().map(RedirectOrCmdWord::CmdWord).collect();
+        let cmd = self.simple_command(Vec::new(), words, span)?;
+        let cmd = single_command(self, Vec::new(), cmd, span)?;
+
+        let cmds = CommandGroup {
+            commands: vec![cmd],
+            trailing_comments: Vec::new(),
+        };
+        self.brace_group(cmds, redirects, redirects_span, span)
+    }
+
+    /// Invoked when a `(( ... ))` arithmetic command is parsed (a bash extension).
+    /// Typically this command succeeds if its expression evaluates to a non-zero value.
+    ///
+    /// By default the command is lowered into a brace group holding the equivalent
+    /// `let` command, e.g. `{ let 'x += 1'; }` (or `{ let 0; }` if the expression is
+    /// empty), so builders without a representation of arithmetic commands keep working.
+    /// The expression is always a `DefaultArithmetic`, see `Builder::arithmetic`.
+    ///
+    /// # Arguments
+    /// * expr: the expression between the parens, or `None` if it is empty, e.g. `(( ))`
+    /// * redirects: any redirects to be applied over the command
+    /// * redirects_span: the location of the redirects in the source, if there are any
+    /// * span: the location of the entire command in the source, including any redirects
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let expr = match expr {
+            Some(expr) => WordKind::SingleQuoted(expr.to_string()),
+            None => WordKind::Simple(SimpleWordKind::Literal(String::from("0"))),
+        };
+
+        let words = vec![
+            literal_word(self, "let", span)?,
+            self.word(ComplexWordKind::Single(expr), span)?,
+        ];
+
+        let words = words.into_iter().map(RedirectOrCmdWord::CmdWord).collect();
+        let cmd = self.simple_command(Vec::new(), words, span)?;
+        let cmd = single_command(self, Vec::new(), cmd, span)?;
+
+        let cmds = CommandGroup {
+            commands: vec![cmd],
+            trailing_comments: Vec::new(),
+        };
+        self.brace_group(cmds, redirects, redirects_span, span)
+    }
+
     /// Bridges the gap between a `PipeableCommand` and a `CompoundCommand` since
     /// `CompoundCommand`s are typically `PipeableCommand`s as well.
     ///
@@ -431,13 +753,17 @@ pub trait Builder {
     ///
     /// # Arguments
     /// * name: the name of the function to be created
+    /// * syntax: the syntax used to declare the function
     /// * post_name_comments: any comments appearing after the function name but before the body
     /// * body: commands to be run when the function is invoked
+    /// * span: the location of the entire declaration in the source, including its body
     fn function_declaration(
         &mut self,
         name: String,
+        syntax: FunctionSyntax,
         post_name_comments: Vec<Newline>,
         body: Self::CompoundCommand,
+        span: Span,
     ) -> Result<Self::PipeableCommand, Self::Error>;
 
     /// Invoked when only comments are parsed with no commands following.
@@ -448,17 +774,143 @@ pub trait Builder {
     /// * comments: the parsed comments
     fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error>;
 
+    /// Invoked when the parser is set to recover from errors, and has skipped
+    /// over a region of the source which could not be parsed as a complete command.
+    ///
+    /// # Arguments
+    /// * pre_cmd_comments: any comments that appear before the start of the region
+    /// * span: the raw text of the region, and where it appears in the source
+    ///
+    /// Note that the builder may have already been invoked for any parts of the
+    /// region which were successfully parsed before the error was encountered.
+    ///
+    /// By default the region is lowered into an empty simple command (which
+    /// does nothing), so builders without a representation of unparsed regions
+    /// keep working, though the text of the region is discarded.
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        let span = Span {
+            start: span.start,
+            end: span.end,
+        };
+        let cmd = self.simple_command(Vec::new(), Vec::new(), span)?;
+        single_command(self, pre_cmd_comments, cmd, span)
+    }
+
+    /// Invoked when an arithmetic substitution is parsed, before the word containing it.
+    ///
+    /// The returned expression (which may be rewritten by the builder) is the
+    /// one provided to the builder as part of the containing word.
+    ///
+    /// Returns the expression unchanged by default.
+    ///
+    /// Unlike most other nodes, there is no associated type for arithmetic expressions:
+    /// the parser builds the expression tree itself, and it is carried within the
+    /// `ParameterSubstitutionKind::Arith` of the containing word (much like parameters
+    /// are always a `DefaultParameter`), so a generic representation would add a type
+    /// parameter to every word kind. Builders with their own representation should
+    /// convert the expression when it is passed to `Builder::word` (or to
+    /// `Builder::arithmetic_command`) instead.
+    ///
+    /// # Arguments
+    /// * expr: the parsed expression, or `None` if the substitution is empty, e.g. `$(( ))`
+    /// * span: the location of the substitution in the source, including the `$((` and `))`
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        let _ = span;
+        Ok(expr)
+    }
+
+    /// Invoked when a parameter substitution within curly braces (e.g. `${x:-word}`,
+    /// `${x%%pattern}`, or `${#x}`) is parsed, before the word containing it.
+    ///
+    /// The returned substitution (which may be rewritten by the builder) is the
+    /// one provided to the builder as part of the containing word. Substitutions
+    /// nested within the operand word of another (e.g. `${x:-${y}}`) are passed
+    /// to the builder first. Plain parameters (e.g. `${x}`) are not substitutions,
+    /// and are only provided as part of their word. Returns the substitution
+    /// unchanged by default.
+    ///
+    /// # Arguments
+    /// * kind: the parsed substitution, including the operand word (if any)
+    /// * span: the location of the substitution in the source, including the `${` and `}`
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        let _ = span;
+        Ok(kind)
+    }
+
     /// Invoked when a word is parsed.
     ///
+    /// The builder is free to represent the word however it sees fit (via
+    /// `Builder::Word`). Builders which would rather assemble their words from
+    /// individual literals, quoted segments, parameters, and substitutions can
+    /// implement `WordBuilder` and delegate to `WordBuilder::build_word`.
+    ///
     /// # Arguments
     /// * kind: the type of word that was parsed
-    fn word(&mut self, kind: ComplexWordKind<Self::Command>) -> Result<Self::Word, Self::Error>;
+    /// * span: the location of the word in the source
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error>;
 
     /// Invoked when a redirect is parsed.
     ///
+    /// The builder is free to represent the redirect however it sees fit (via
+    /// `Builder::Redirect`), since the parser only ever hands the result back to
+    /// the builder as part of a command. The `fd` and `word` helpers of the kind
+    /// are useful for representations which do not distinguish every kind.
+    ///
     /// # Arguments
     /// * kind: the type of redirect that was parsed
-    fn redirect(&mut self, kind: RedirectKind<Self::Word>) -> Result<Self::Redirect, Self::Error>;
+    /// * span: the location of the redirect in the source, excluding the body of a heredoc
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error>;
+
+    /// Invoked when the parser starts parsing the body of a nested context (e.g.
+    /// a function or a subshell), before any of the commands within it are built.
+    ///
+    /// Every context which is entered is later exited via `Builder::exit_context`,
+    /// (including when the parser recovers from an error within it, see
+    /// `parse::ErrorRecovery`), unless parsing is aborted with an error. Contexts
+    /// may be nested, and are always exited in the reverse order they were entered.
+    ///
+    /// This allows stateful builders (e.g. ones maintaining symbol tables or scopes)
+    /// to keep track of where they are while the AST is being built, instead of
+    /// reconstructing the nesting from the finished tree. Does nothing by default.
+    ///
+    /// # Arguments
+    /// * kind: the context which was entered
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        let _ = kind;
+        Ok(())
+    }
+
+    /// Invoked when the parser finishes parsing the body of a nested context
+    /// previously entered via `Builder::enter_context`, before the node holding
+    /// the body (e.g. the function declaration or subshell) is built.
+    /// Does nothing by default.
+    ///
+    /// # Arguments
+    /// * kind: the context which was exited
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        let _ = kind;
+        Ok(())
+    }
 }
 
 macro_rules! impl_builder_body {
@@ -478,48 +930,56 @@ macro_rules! impl_builder_body {
             list: Self::CommandList,
             separator: SeparatorKind,
             cmd_comment: Option<Newline>,
+            span: Span,
         ) -> Result<Self::Command, Self::Error> {
-            (**self).complete_command(pre_cmd_comments, list, separator, cmd_comment)
+            (**self).complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
         }
 
         fn and_or_list(
             &mut self,
             first: Self::ListableCommand,
             rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+            span: Span,
         ) -> Result<Self::CommandList, Self::Error> {
-            (**self).and_or_list(first, rest)
+            (**self).and_or_list(first, rest, span)
         }
 
         fn pipeline(
             &mut self,
             bang: bool,
-            cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>,
+            cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+            span: Span,
         ) -> Result<Self::ListableCommand, Self::Error> {
-            (**self).pipeline(bang, cmds)
+            (**self).pipeline(bang, cmds, span)
         }
 
         fn simple_command(
             &mut self,
             redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
             redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+            span: Span,
         ) -> Result<Self::PipeableCommand, Self::Error> {
-            (**self).simple_command(redirects_or_env_vars, redirects_or_cmd_words)
+            (**self).simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
         }
 
         fn brace_group(
             &mut self,
             cmds: CommandGroup<Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).brace_group(cmds, redirects)
+            (**self).brace_group(cmds, redirects, redirects_span, span)
         }
 
         fn subshell(
             &mut self,
             cmds: CommandGroup<Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).subshell(cmds, redirects)
+            (**self).subshell(cmds, redirects, redirects_span, span)
         }
 
         fn loop_command(
@@ -527,32 +987,60 @@ macro_rules! impl_builder_body {
             kind: LoopKind,
             guard_body_pair: GuardBodyPairGroup<Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).loop_command(kind, guard_body_pair, redirects)
+            (**self).loop_command(kind, guard_body_pair, redirects, redirects_span, span)
         }
 
         fn if_command(
             &mut self,
             fragments: IfFragments<Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).if_command(fragments, redirects)
+            (**self).if_command(fragments, redirects, redirects_span, span)
         }
 
         fn for_command(
             &mut self,
             fragments: ForFragments<Self::Word, Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).for_command(fragments, redirects)
+            (**self).for_command(fragments, redirects, redirects_span, span)
         }
 
         fn case_command(
             &mut self,
             fragments: CaseFragments<Self::Word, Self::Command>,
             redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
+        ) -> Result<Self::CompoundCommand, Self::Error> {
+            (**self).case_command(fragments, redirects, redirects_span, span)
+        }
+
+        fn conditional_command(
+            &mut self,
+            expr: Conditional<Self::Word>,
+            redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
+        ) -> Result<Self::CompoundCommand, Self::Error> {
+            (**self).conditional_command(expr, redirects, redirects_span, span)
+        }
+
+        fn arithmetic_command(
+            &mut self,
+            expr: Option<DefaultArithmetic>,
+            redirects: Vec<Self::Redirect>,
+            redirects_span: Option<Span>,
+            span: Span,
         ) -> Result<Self::CompoundCommand, Self::Error> {
-            (**self).case_command(fragments, redirects)
+            (**self).arithmetic_command(expr, redirects, redirects_span, span)
         }
 
         fn compound_command_into_pipeable(
@@ -565,32 +1053,151 @@ macro_rules! impl_builder_body {
         fn function_declaration(
             &mut self,
             name: String,
+            syntax: FunctionSyntax,
             post_name_comments: Vec<Newline>,
             body: Self::CompoundCommand,
+            span: Span,
         ) -> Result<Self::PipeableCommand, Self::Error> {
-            (**self).function_declaration(name, post_name_comments, body)
+            (**self).function_declaration(name, syntax, post_name_comments, body, span)
         }
 
         fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
             (**self).comments(comments)
         }
 
+        fn unparsed(
+            &mut self,
+            pre_cmd_comments: Vec<Newline>,
+            span: UnparsedSpan,
+        ) -> Result<Self::Command, Self::Error> {
+            (**self).unparsed(pre_cmd_comments, span)
+        }
+
+        fn arithmetic(
+            &mut self,
+            expr: Option<DefaultArithmetic>,
+            span: Span,
+        ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+            (**self).arithmetic(expr, span)
+        }
+
+        fn parameter_substitution(
+            &mut self,
+            kind: SubstitutionKind<Self::Command>,
+            span: Span,
+        ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+            (**self).parameter_substitution(kind, span)
+        }
+
         fn word(
             &mut self,
             kind: ComplexWordKind<Self::Command>,
+            span: Span,
         ) -> Result<Self::Word, Self::Error> {
-            (**self).word(kind)
+            (**self).word(kind, span)
         }
 
         fn redirect(
             &mut self,
             kind: RedirectKind<Self::Word>,
+            span: Span,
         ) -> Result<Self::Redirect, Self::Error> {
-            (**self).redirect(kind)
+            (**self).redirect(kind, span)
+        }
+
+        fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+            (**self).enter_context(kind)
+        }
+
+        fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+            (**self).exit_context(kind)
         }
     };
 }
 
+/// Builds a word consisting of a single literal, for lowering nodes into
+/// simple commands in the default implementations of `Builder` methods.
+fn literal_word<B: Builder + ?Sized>(
+    builder: &mut B,
+    literal: &str,
+    span: Span,
+) -> Result<B::Word, B::Error> {
+    let kind = WordKind::Simple(SimpleWordKind::Literal(String::from(literal)));
+    builder.word(ComplexWordKind::Single(kind), span)
+}
+
+/// Builds a complete command out of a single command, as if it was parsed on its own.
+fn single_command<B: Builder + ?Sized>(
+    builder: &mut B,
+    pre_cmd_comments: Vec<Newline>,
+    cmd: B::PipeableCommand,
+    span: Span,
+) -> Result<B::Command, B::Error> {
+    let segment = PipeSegment {
+        pipe: None,
+        pipe_stderr: false,
+        cmd,
+    };
+    let cmd = builder.pipeline(false, vec![(Vec::new(), segment)], span)?;
+    let list = builder.and_or_list(cmd, Vec::new(), span)?;
+    builder.complete_command(pre_cmd_comments, list, SeparatorKind::Other, None, span)
+}
+
+/// Appends the words of a `[[ ... ]]` expression, grouping nested `&&` and `||`
+/// expressions within parentheses to retain their precedence.
+fn conditional_words<B: Builder + ?Sized>(
+    builder: &mut B,
+    expr: Conditional<B::Word>,
+    span: Span,
+    words: &mut Vec<B::Word>,
+) -> Result<(), B::Error> {
+    fn grouped<B: Builder + ?Sized>(
+        builder: &mut B,
+        expr: Conditional<B::Word>,
+        span: Span,
+        words: &mut Vec<B::Word>,
+    ) -> Result<(), B::Error> {
+        match expr {
+            expr @ Conditional::And(..) | expr @ Conditional::Or(..) => {
+                words.push(literal_word(builder, "(", span)?);
+                conditional_words(builder, expr, span, words)?;
+                words.push(literal_word(builder, ")", span)?);
+                Ok(())
+            }
+            expr => conditional_words(builder, expr, span, words),
+        }
+    }
+
+    let (left, op, right) = match expr {
+        Conditional::Word(w) => {
+            words.push(w);
+            return Ok(());
+        }
+        Conditional::Unary(op, w) => {
+            words.push(literal_word(builder, &format!("-{}", op), span)?);
+            words.push(w);
+            return Ok(());
+        }
+        Conditional::Binary(left, op, right) => {
+            words.push(left);
+            words.push(literal_word(builder, op.as_str(), span)?);
+            words.push(right);
+            return Ok(());
+        }
+        Conditional::Not(expr) => {
+            words.push(literal_word(builder, "!", span)?);
+            return grouped(builder, *expr, span, words);
+        }
+        Conditional::And(left, right) => (left, "&&", right),
+        Conditional::Or(left, right) => (left, "||", right),
+    };
+
+    grouped(builder, *left, span, words)?;
+    words.push(literal_word(builder, op, span)?);
+    grouped(builder, *right, span, words)?;
+    Ok(())
+}
+
 impl<'a, T: Builder + ?Sized> Builder for &'a mut T {
     impl_builder_body!(T);
 }
@@ -598,3 +1205,30 @@ impl<'a, T: Builder + ?Sized> Builder for &'a mut T {
 impl<T: Builder + ?Sized> Builder for Box<T> {
     impl_builder_body!(T);
 }
+
+/// A trait for creating new AST builders on demand, e.g. one for each
+/// parser when parsing many sources concurrently.
+///
+/// Any function or closure which returns a `Builder` is a factory, so
+/// builders which implement `Default` can simply use `B::default`. Factories
+/// which are `Send + Sync` (along with `ParserOptions`) can be shared across
+/// threads, even if the builders they create are not.
+pub trait BuilderFactory {
+    /// The type of builder which is created.
+    type Builder: Builder;
+
+    /// Creates a new builder, ready to be used by a new parser.
+    fn new_builder(&self) -> Self::Builder;
+}
+
+impl<F, B> BuilderFactory for F
+where
+    F: Fn() -> B + ?Sized,
+    B: Builder,
+{
+    type Builder = B;
+
+    fn new_builder(&self) -> B {
+        self()
+    }
+}
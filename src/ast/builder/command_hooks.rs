@@ -0,0 +1,394 @@
+use crate::ast::builder::*;
+use crate::ast::{
+    AndOr, ComplexWord, Conditional, DefaultArithmetic, PipeSegment, RedirectOrCmdWord,
+    RedirectOrEnvVar, ShellWord, SimpleCommand, SimpleWord, Span, UnparsedSpan, Word,
+};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+/// A callback registered with `CommandHooks`, which is given each simple
+/// command invoking the command name it was registered for.
+pub type CommandHook<'a, B> = Box<
+    dyn FnMut(
+            &SimpleCommand<String, <B as Builder>::Word, <B as Builder>::Redirect>,
+            Span,
+        ) -> Result<(), <B as Builder>::Error>
+        + 'a,
+>;
+
+/// A `Builder` adapter which invokes user supplied callbacks for simple
+/// commands invoking specific command names (e.g. `git` or `kubectl`).
+///
+/// Each callback is given the simple command (as fragments built by the
+/// wrapped builder), along with its location, before it is handed over to
+/// the wrapped builder. This allows domain specific checks (e.g. validating
+/// arguments against a schema) to run in the same pass as parsing, instead
+/// of requiring a separate traversal of the AST afterwards.
+///
+/// A callback can abort parsing by returning an error, which is surfaced by
+/// the parser as a `ParseError::Custom`. Since builders such as `StringBuilder`
+/// cannot fail, callbacks meant to report problems without aborting should
+/// record them elsewhere (e.g. in a collection borrowed by the closure).
+///
+/// Only commands whose name is a literal word (quoted or otherwise) are
+/// matched, e.g. `git`, `'git'`, or `\git`, but not `$git` or `"$(which git)"`.
+/// Commands invoked indirectly (e.g. `command git` or `xargs git`) are
+/// matched by the name of the outer command instead.
+///
+/// ```
+/// use conch_parser::ast::builder::{CommandHooks, RcBuilder};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let mut pushes = 0;
+/// {
+///     let mut hooks = CommandHooks::new(RcBuilder::new());
+///     hooks.on("git", |cmd, _span| {
+///         if cmd.redirects_or_cmd_words.len() > 1 {
+///             pushes += 1;
+///         }
+///         Ok(())
+///     });
+///
+///     let lexer = Lexer::new("git push; git; echo git push".chars());
+///     let cmds = Parser::with_builder(lexer, &mut hooks)
+///         .parse_all()
+///         .unwrap();
+///     assert_eq!(cmds.len(), 3);
+/// }
+/// assert_eq!(pushes, 1);
+/// ```
+pub struct CommandHooks<'a, B: Builder> {
+    builder: B,
+    hooks: HashMap<String, Vec<CommandHook<'a, B>>>,
+}
+
+impl<'a, B: Builder> CommandHooks<'a, B> {
+    /// Wraps a builder, without any callbacks registered yet.
+    pub fn new(builder: B) -> Self {
+        CommandHooks {
+            builder,
+            hooks: HashMap::new(),
+        }
+    }
+
+    /// Registers a callback for all simple commands invoking the specified
+    /// command name. Several callbacks may be registered for the same name,
+    /// in which case they are invoked in the order they were registered.
+    pub fn on<N, F>(&mut self, name: N, hook: F) -> &mut Self
+    where
+        N: Into<String>,
+        F: FnMut(&SimpleCommand<String, B::Word, B::Redirect>, Span) -> Result<(), B::Error> + 'a,
+    {
+        self.hooks
+            .entry(name.into())
+            .or_default()
+            .push(Box::new(hook));
+        self
+    }
+
+    /// Returns a reference to the wrapped builder.
+    pub fn get_ref(&self) -> &B {
+        &self.builder
+    }
+
+    /// Returns a mutable reference to the wrapped builder.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+
+    /// Unwraps the wrapped builder, discarding all registered callbacks.
+    pub fn into_inner(self) -> B {
+        self.builder
+    }
+}
+
+impl<'a, B: Builder + fmt::Debug> fmt::Debug for CommandHooks<'a, B> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.hooks.keys().collect();
+        names.sort();
+
+        fmt.debug_struct("CommandHooks")
+            .field("builder", &self.builder)
+            .field("hooks", &names)
+            .finish()
+    }
+}
+
+/// Returns the contents of a word if it is composed entirely out of literals.
+fn literal_name<T: Borrow<String>, W, C>(word: &ShellWord<T, W, C>) -> Option<String> {
+    fn push_simple<T: Borrow<String>, P, S>(
+        buf: &mut String,
+        w: &SimpleWord<T, P, S>,
+    ) -> Option<()> {
+        match *w {
+            SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => buf.push_str(s.borrow()),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    let words = match *word {
+        ComplexWord::Single(ref w) => ::std::slice::from_ref(w),
+        ComplexWord::Concat(ref ws) => &ws[..],
+    };
+
+    let mut buf = String::new();
+    for word in words {
+        match *word {
+            Word::Simple(ref w) => push_simple(&mut buf, w)?,
+            Word::SingleQuoted(ref s) => buf.push_str(s.borrow()),
+            Word::DoubleQuoted(ref ws) => {
+                for w in ws {
+                    push_simple(&mut buf, w)?;
+                }
+            }
+            Word::Braces(_) => return None,
+        }
+    }
+
+    Some(buf)
+}
+
+impl<'a, B, T, W, C> Builder for CommandHooks<'a, B>
+where
+    B: Builder,
+    B::Word: Deref<Target = ShellWord<T, W, C>>,
+    T: Borrow<String>,
+{
+    type Command = B::Command;
+    type CommandList = B::CommandList;
+    type ListableCommand = B::ListableCommand;
+    type PipeableCommand = B::PipeableCommand;
+    type CompoundCommand = B::CompoundCommand;
+    type Word = B::Word;
+    type Redirect = B::Redirect;
+    type Error = B::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.builder
+            .complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.builder.and_or_list(first, rest, span)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.builder.pipeline(bang, cmds, span)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let first_word = redirects_or_cmd_words.iter().find_map(|w| match *w {
+            RedirectOrCmdWord::CmdWord(ref w) => Some(w),
+            RedirectOrCmdWord::Redirect(_) => None,
+        });
+
+        let hooks = match first_word.and_then(|w| literal_name(w)) {
+            Some(name) => self.hooks.get_mut(&name),
+            None => None,
+        };
+
+        let (redirects_or_env_vars, redirects_or_cmd_words) = match hooks {
+            Some(hooks) => {
+                let cmd = SimpleCommand {
+                    redirects_or_env_vars,
+                    redirects_or_cmd_words,
+                };
+
+                for hook in hooks {
+                    hook(&cmd, span)?;
+                }
+
+                (cmd.redirects_or_env_vars, cmd.redirects_or_cmd_words)
+            }
+            None => (redirects_or_env_vars, redirects_or_cmd_words),
+        };
+
+        self.builder
+            .simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .brace_group(cmds, redirects, redirects_span, span)
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder.subshell(cmds, redirects, redirects_span, span)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .loop_command(kind, guard_body_pair, redirects, redirects_span, span)
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .if_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .for_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .case_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .conditional_command(expr, redirects, redirects_span, span)
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .arithmetic_command(expr, redirects, redirects_span, span)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder
+            .function_declaration(name, syntax, post_name_comments, body, span)
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.builder.comments(comments)
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        self.builder.unparsed(pre_cmd_comments, span)
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.builder.arithmetic(expr, span)
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.builder.parameter_substitution(kind, span)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.builder.word(kind, span)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.builder.redirect(kind, span)
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.enter_context(kind)
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.exit_context(kind)
+    }
+}
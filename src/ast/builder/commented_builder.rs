@@ -0,0 +1,349 @@
+use crate::ast::builder::*;
+use crate::ast::*;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use void::Void;
+
+type CommentedCoreBuilder<T> = CoreBuilder<
+    T,
+    CommentedTopLevelWord<T>,
+    CommentedTopLevelCommand<T>,
+    Rc<ShellCompoundCommand<T, CommentedTopLevelWord<T>, CommentedTopLevelCommand<T>>>,
+>;
+
+/// A `Builder` implementation which builds shell commands using the
+/// (non-atomic) AST definitions in the `ast` module, while retaining the
+/// comments around each command.
+///
+/// Comments are attached to the `CommentedTopLevelCommand` they belong to:
+/// those on the lines before a command, those after any of its `&&` or `||`
+/// operators, and the one on the same line after it. Comments which trail the
+/// last command of a compound command's body are attached to that command.
+///
+/// Any comments at the very end of the input do not belong to a command, and
+/// are instead retained by the builder until taken with `take_trailing_comments`.
+///
+/// Note that comments which appear elsewhere (e.g. between the commands of a
+/// pipeline, or between the arms of a `case` command) are still discarded.
+///
+/// ```
+/// use conch_parser::ast::builder::{CommentedDefaultBuilder, Newline};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let src = "# greet\necho hello # inline\n# the end\n";
+/// let mut builder = CommentedDefaultBuilder::<String>::new();
+/// let cmds = Parser::with_builder(Lexer::new(src.chars()), &mut builder)
+///     .parse_all()
+///     .unwrap();
+///
+/// assert_eq!(cmds[0].pre_cmd_comments, vec![Newline(Some("# greet".into()))]);
+/// assert_eq!(cmds[0].cmd_comment, Some(Newline(Some("# inline".into()))));
+/// assert_eq!(
+///     builder.take_trailing_comments(),
+///     vec![Newline(Some("# the end".into()))]
+/// );
+/// ```
+pub struct CommentedDefaultBuilder<T> {
+    builder: CommentedCoreBuilder<T>,
+    and_or_comments: Vec<Vec<Newline>>,
+    trailing_comments: Vec<Newline>,
+}
+
+impl<T> CommentedDefaultBuilder<T> {
+    /// Constructs a builder.
+    pub fn new() -> Self {
+        CommentedDefaultBuilder {
+            builder: CoreBuilder::new(),
+            and_or_comments: Vec::new(),
+            trailing_comments: Vec::new(),
+        }
+    }
+
+    /// Takes any comments which were found at the end of the input, after the
+    /// last command, leaving none behind.
+    pub fn take_trailing_comments(&mut self) -> Vec<Newline> {
+        mem::take(&mut self.trailing_comments)
+    }
+}
+
+impl<T> fmt::Debug for CommentedDefaultBuilder<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("CommentedDefaultBuilder")
+            .field("trailing_comments", &self.trailing_comments)
+            .finish()
+    }
+}
+
+impl<T> Default for CommentedDefaultBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for CommentedDefaultBuilder<T> {
+    fn clone(&self) -> Self {
+        CommentedDefaultBuilder {
+            builder: self.builder,
+            and_or_comments: self.and_or_comments.clone(),
+            trailing_comments: self.trailing_comments.clone(),
+        }
+    }
+}
+
+/// Moves any comments trailing a group of commands onto its last command.
+fn attach_trailing_comments<T>(group: &mut CommandGroup<CommentedTopLevelCommand<T>>) {
+    if let Some(last) = group.commands.last_mut() {
+        last.post_cmd_comments.append(&mut group.trailing_comments);
+    }
+}
+
+impl<T: From<String>> Builder for CommentedDefaultBuilder<T> {
+    type Command = CommentedTopLevelCommand<T>;
+    type CommandList = AndOrList<Self::ListableCommand>;
+    type ListableCommand = ListableCommand<Self::PipeableCommand>;
+    type PipeableCommand = ShellPipeableCommand<T, Self::Word, Self::Command>;
+    type CompoundCommand = ShellCompoundCommand<T, Self::Word, Self::Command>;
+    type Word = CommentedTopLevelWord<T>;
+    type Redirect = Redirect<Self::Word>;
+    type Error = Void;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        let mut cmd = self.builder.complete_command(
+            pre_cmd_comments.clone(),
+            list,
+            separator,
+            cmd_comment.clone(),
+            span,
+        )?;
+
+        cmd.pre_cmd_comments = pre_cmd_comments;
+        cmd.and_or_comments = mem::take(&mut self.and_or_comments);
+        // A plain newline after the command is not a comment worth retaining
+        cmd.cmd_comment = cmd_comment.filter(|comment| comment.0.is_some());
+        Ok(cmd)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        // The list is always completed as a command right after it is built
+        self.and_or_comments = rest
+            .iter()
+            .map(|(comments, _)| comments.clone())
+            .collect();
+        self.builder.and_or_list(first, rest, span)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.builder.pipeline(bang, cmds, span)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder
+            .simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
+    }
+
+    fn brace_group(
+        &mut self,
+        mut cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        attach_trailing_comments(&mut cmds);
+        self.builder
+            .brace_group(cmds, redirects, redirects_span, span)
+    }
+
+    fn subshell(
+        &mut self,
+        mut cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        attach_trailing_comments(&mut cmds);
+        self.builder.subshell(cmds, redirects, redirects_span, span)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        mut guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        attach_trailing_comments(&mut guard_body_pair.guard);
+        attach_trailing_comments(&mut guard_body_pair.body);
+        self.builder
+            .loop_command(kind, guard_body_pair, redirects, redirects_span, span)
+    }
+
+    fn if_command(
+        &mut self,
+        mut fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        for conditional in &mut fragments.conditionals {
+            attach_trailing_comments(&mut conditional.guard);
+            attach_trailing_comments(&mut conditional.body);
+        }
+        if let Some(ref mut els) = fragments.else_branch {
+            attach_trailing_comments(els);
+        }
+
+        self.builder
+            .if_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn for_command(
+        &mut self,
+        mut fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        attach_trailing_comments(&mut fragments.body);
+        self.builder
+            .for_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn case_command(
+        &mut self,
+        mut fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        for arm in &mut fragments.arms {
+            attach_trailing_comments(&mut arm.body);
+        }
+
+        self.builder
+            .case_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .conditional_command(expr, redirects, redirects_span, span)
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .arithmetic_command(expr, redirects, redirects_span, span)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder
+            .function_declaration(name, syntax, post_name_comments, body, span)
+    }
+
+    fn comments(&mut self, mut comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.trailing_comments.append(&mut comments);
+        Ok(())
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        // Drop any comments of a list which could not be completed
+        self.and_or_comments.clear();
+
+        let mut cmd = self.builder.unparsed(pre_cmd_comments.clone(), span)?;
+        cmd.pre_cmd_comments = pre_cmd_comments;
+        Ok(cmd)
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.builder.arithmetic(expr, span)
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.builder.parameter_substitution(kind, span)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.builder.word(kind, span)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.builder.redirect(kind, span)
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.enter_context(kind)
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.exit_context(kind)
+    }
+}
@@ -0,0 +1,460 @@
+use crate::ast::builder::*;
+use crate::ast::{
+    AndOr, ComplexWord, Conditional, DefaultArithmetic, Parameter, PipeSegment, RedirectOrCmdWord,
+    RedirectOrEnvVar, ShellWord, SimpleWord, Span, UnparsedSpan, Word,
+};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::DerefMut;
+
+/// Statistics on the literals shared by a `StringPool` (or a `DedupBuilder`).
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct DedupStats {
+    /// The number of literals which were considered for sharing.
+    pub literals: usize,
+    /// The number of literals which were replaced by an identical one built earlier.
+    pub shared: usize,
+    /// The total length (in bytes) of the literals which were shared.
+    pub shared_bytes: usize,
+}
+
+/// A pool of literals, which interns identical literals into one allocation.
+///
+/// This is only worthwhile for literals which are reference counted (e.g.
+/// `Rc<String>` or `Arc<String>`), where cloning a literal shares its contents.
+/// A pool can be handed from one `DedupBuilder` to the next (see
+/// `DedupBuilder::with_pool` and `DedupBuilder::into_pool`), so literals are
+/// shared between many scripts as well.
+///
+/// ```
+/// use conch_parser::ast::builder::StringPool;
+/// use std::rc::Rc;
+///
+/// let mut pool = StringPool::new();
+/// let mut a = Rc::new(String::from("foo"));
+/// let mut b = Rc::new(String::from("foo"));
+/// pool.intern(&mut a);
+/// pool.intern(&mut b);
+///
+/// assert!(Rc::ptr_eq(&a, &b));
+/// assert_eq!(pool.len(), 1);
+/// assert_eq!(pool.stats().shared, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StringPool<T> {
+    min_len: usize,
+    literals: HashSet<T>,
+    stats: DedupStats,
+}
+
+impl<T: Eq + Hash> Default for StringPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Eq + Hash> StringPool<T> {
+    /// Creates an empty pool, which interns all non-empty literals.
+    pub fn new() -> Self {
+        Self::with_min_len(1)
+    }
+
+    /// Creates an empty pool, which only interns literals which are at least `min_len` bytes long.
+    pub fn with_min_len(min_len: usize) -> Self {
+        StringPool {
+            min_len,
+            literals: HashSet::new(),
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Returns statistics on the literals shared so far.
+    pub fn stats(&self) -> DedupStats {
+        self.stats
+    }
+
+    /// Returns the number of distinct literals retained by the pool.
+    pub fn len(&self) -> usize {
+        self.literals.len()
+    }
+
+    /// Checks if the pool does not retain any literals.
+    pub fn is_empty(&self) -> bool {
+        self.literals.is_empty()
+    }
+
+    /// Releases every literal retained by the pool, keeping its statistics.
+    pub fn clear(&mut self) {
+        self.literals.clear()
+    }
+}
+
+impl<T> StringPool<T>
+where
+    T: Borrow<String> + Clone + Eq + Hash,
+{
+    /// Replaces the literal with a clone of an identical one interned earlier,
+    /// or retains a clone of it for sharing later. Literals shorter than the
+    /// minimum length of the pool are left as they are.
+    pub fn intern(&mut self, literal: &mut T) {
+        let len = (*literal).borrow().len();
+        if len < self.min_len {
+            return;
+        }
+
+        self.stats.literals += 1;
+        match self.literals.get::<T>(literal) {
+            Some(shared) => {
+                *literal = shared.clone();
+                self.stats.shared += 1;
+                self.stats.shared_bytes += len;
+            }
+            None => {
+                self.literals.insert(literal.clone());
+            }
+        }
+    }
+}
+
+/// A `Builder` adapter which shares identical literals between the words
+/// built by another builder.
+///
+/// Every unquoted or single quoted literal of a word, and the name of every
+/// variable accessed by it (e.g. `$foo`), is interned in a `StringPool`. This
+/// is only worthwhile for builders whose literals are reference counted (e.g.
+/// `RcBuilder` or `ArcBuilder`), where cloning a literal shares its contents,
+/// and can considerably reduce the memory used by generated scripts which
+/// repeat the same arguments many times over.
+///
+/// Literals shorter than the configured minimum length are never shared, nor
+/// are those nested within parameter substitutions, e.g. `${x:-literal}`.
+///
+/// ```
+/// use conch_parser::ast::builder::{DedupBuilder, RcBuilder};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let mut builder = DedupBuilder::new(RcBuilder::new());
+/// let lexer = Lexer::new("cp file dest; cp file dest".chars());
+/// let cmds = Parser::with_builder(lexer, &mut builder)
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(cmds.len(), 2);
+/// assert_eq!(builder.stats().literals, 6);
+/// assert_eq!(builder.stats().shared, 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DedupBuilder<B, T> {
+    builder: B,
+    pool: StringPool<T>,
+}
+
+impl<B, T: Eq + Hash> DedupBuilder<B, T> {
+    /// Wraps a builder, sharing all of its non-empty literals.
+    pub fn new(builder: B) -> Self {
+        Self::with_min_len(builder, 1)
+    }
+
+    /// Wraps a builder, sharing only its literals which are at least `min_len` bytes long.
+    pub fn with_min_len(builder: B, min_len: usize) -> Self {
+        Self::with_pool(builder, StringPool::with_min_len(min_len))
+    }
+
+    /// Wraps a builder, sharing its literals with those already in the pool
+    /// (e.g. the literals of previously parsed scripts).
+    pub fn with_pool(builder: B, pool: StringPool<T>) -> Self {
+        DedupBuilder { builder, pool }
+    }
+
+    /// Returns statistics on the literals shared so far.
+    pub fn stats(&self) -> DedupStats {
+        self.pool.stats()
+    }
+
+    /// Returns a reference to the pool of literals.
+    pub fn pool(&self) -> &StringPool<T> {
+        &self.pool
+    }
+
+    /// Unwraps the pool of literals, discarding the wrapped builder.
+    pub fn into_pool(self) -> StringPool<T> {
+        self.pool
+    }
+
+    /// Returns a reference to the wrapped builder.
+    pub fn get_ref(&self) -> &B {
+        &self.builder
+    }
+
+    /// Returns a mutable reference to the wrapped builder.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+
+    /// Unwraps the wrapped builder, discarding any literals retained for sharing.
+    pub fn into_inner(self) -> B {
+        self.builder
+    }
+}
+
+impl<B, T> DedupBuilder<B, T>
+where
+    T: Borrow<String> + Clone + Eq + Hash,
+{
+    fn share_word<W, C>(&mut self, word: &mut ShellWord<T, W, C>) {
+        let words = match *word {
+            ComplexWord::Single(ref mut w) => ::std::slice::from_mut(w),
+            ComplexWord::Concat(ref mut ws) => &mut ws[..],
+        };
+
+        for word in words {
+            self.share_part(word);
+        }
+    }
+
+    fn share_part<S>(&mut self, word: &mut Word<T, SimpleWord<T, Parameter<T>, S>>) {
+        match *word {
+            Word::Simple(ref mut w) => self.share_simple(w),
+            Word::SingleQuoted(ref mut s) => self.pool.intern(s),
+            Word::DoubleQuoted(ref mut ws) => {
+                for w in ws {
+                    self.share_simple(w);
+                }
+            }
+            Word::Braces(ref mut braces) => {
+                for w in braces.words_mut() {
+                    self.share_part(w);
+                }
+            }
+        }
+    }
+
+    fn share_simple<S>(&mut self, word: &mut SimpleWord<T, Parameter<T>, S>) {
+        match *word {
+            SimpleWord::Literal(ref mut s) | SimpleWord::Param(Parameter::Var(ref mut s)) => {
+                self.pool.intern(s)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<B, T, W, C> Builder for DedupBuilder<B, T>
+where
+    B: Builder,
+    B::Word: DerefMut<Target = ShellWord<T, W, C>>,
+    T: Borrow<String> + Clone + Eq + Hash,
+{
+    type Command = B::Command;
+    type CommandList = B::CommandList;
+    type ListableCommand = B::ListableCommand;
+    type PipeableCommand = B::PipeableCommand;
+    type CompoundCommand = B::CompoundCommand;
+    type Word = B::Word;
+    type Redirect = B::Redirect;
+    type Error = B::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.builder
+            .complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.builder.and_or_list(first, rest, span)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.builder.pipeline(bang, cmds, span)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder
+            .simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .brace_group(cmds, redirects, redirects_span, span)
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder.subshell(cmds, redirects, redirects_span, span)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .loop_command(kind, guard_body_pair, redirects, redirects_span, span)
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .if_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .for_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .case_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .conditional_command(expr, redirects, redirects_span, span)
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.builder
+            .arithmetic_command(expr, redirects, redirects_span, span)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder
+            .function_declaration(name, syntax, post_name_comments, body, span)
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.builder.comments(comments)
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        self.builder.unparsed(pre_cmd_comments, span)
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.builder.arithmetic(expr, span)
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.builder.parameter_substitution(kind, span)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        let mut word = self.builder.word(kind, span)?;
+        self.share_word(&mut *word);
+        Ok(word)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.builder.redirect(kind, span)
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.enter_context(kind)
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.exit_context(kind)
+    }
+}
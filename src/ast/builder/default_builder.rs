@@ -72,84 +72,120 @@ macro_rules! default_builder {
                                 pre_cmd_comments: Vec<Newline>,
                                 list: Self::CommandList,
                                 separator: SeparatorKind,
-                                cmd_comment: Option<Newline>)
+                                cmd_comment: Option<Newline>,
+                                span: Span)
                 -> Result<Self::Command, Self::Error>
             {
-                self.0.complete_command(pre_cmd_comments, list, separator, cmd_comment)
+                self.0.complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
             }
 
             fn and_or_list(&mut self,
                       first: Self::ListableCommand,
-                      rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>)
+                      rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+                      span: Span)
                 -> Result<Self::CommandList, Self::Error>
             {
-                self.0.and_or_list(first, rest)
+                self.0.and_or_list(first, rest, span)
             }
 
             fn pipeline(&mut self,
                         bang: bool,
-                        cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>)
+                        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+                        span: Span)
                 -> Result<Self::ListableCommand, Self::Error>
             {
-                self.0.pipeline(bang, cmds)
+                self.0.pipeline(bang, cmds, span)
             }
 
             fn simple_command(
                 &mut self,
                 redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-                redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>
+                redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+                span: Span
             ) -> Result<Self::PipeableCommand, Self::Error>
             {
-                self.0.simple_command(redirects_or_env_vars, redirects_or_cmd_words)
+                self.0.simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
             }
 
             fn brace_group(&mut self,
                            cmds: CommandGroup<Self::Command>,
-                           redirects: Vec<Self::Redirect>)
+                           redirects: Vec<Self::Redirect>,
+                           redirects_span: Option<Span>,
+                           span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.brace_group(cmds, redirects)
+                self.0.brace_group(cmds, redirects, redirects_span, span)
             }
 
             fn subshell(&mut self,
                         cmds: CommandGroup<Self::Command>,
-                        redirects: Vec<Self::Redirect>)
+                        redirects: Vec<Self::Redirect>,
+                        redirects_span: Option<Span>,
+                        span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.subshell(cmds, redirects)
+                self.0.subshell(cmds, redirects, redirects_span, span)
             }
 
             fn loop_command(&mut self,
                             kind: LoopKind,
                             guard_body_pair: GuardBodyPairGroup<Self::Command>,
-                            redirects: Vec<Self::Redirect>)
+                            redirects: Vec<Self::Redirect>,
+                            redirects_span: Option<Span>,
+                            span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.loop_command(kind, guard_body_pair, redirects)
+                self.0.loop_command(kind, guard_body_pair, redirects, redirects_span, span)
             }
 
             fn if_command(&mut self,
                           fragments: IfFragments<Self::Command>,
-                          redirects: Vec<Self::Redirect>)
+                          redirects: Vec<Self::Redirect>,
+                          redirects_span: Option<Span>,
+                          span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.if_command(fragments, redirects)
+                self.0.if_command(fragments, redirects, redirects_span, span)
             }
 
             fn for_command(&mut self,
                            fragments: ForFragments<Self::Word, Self::Command>,
-                           redirects: Vec<Self::Redirect>)
+                           redirects: Vec<Self::Redirect>,
+                           redirects_span: Option<Span>,
+                           span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.for_command(fragments, redirects)
+                self.0.for_command(fragments, redirects, redirects_span, span)
             }
 
             fn case_command(&mut self,
                             fragments: CaseFragments<Self::Word, Self::Command>,
-                            redirects: Vec<Self::Redirect>)
+                            redirects: Vec<Self::Redirect>,
+                            redirects_span: Option<Span>,
+                            span: Span)
                 -> Result<Self::CompoundCommand, Self::Error>
             {
-                self.0.case_command(fragments, redirects)
+                self.0.case_command(fragments, redirects, redirects_span, span)
+            }
+
+            fn conditional_command(&mut self,
+                                   expr: Conditional<Self::Word>,
+                                   redirects: Vec<Self::Redirect>,
+                                   redirects_span: Option<Span>,
+                                   span: Span)
+                -> Result<Self::CompoundCommand, Self::Error>
+            {
+                self.0.conditional_command(expr, redirects, redirects_span, span)
+            }
+
+            fn arithmetic_command(&mut self,
+                                  expr: Option<DefaultArithmetic>,
+                                  redirects: Vec<Self::Redirect>,
+                                  redirects_span: Option<Span>,
+                                  span: Span)
+                -> Result<Self::CompoundCommand, Self::Error>
+            {
+                self.0.arithmetic_command(expr, redirects, redirects_span, span)
             }
 
             fn compound_command_into_pipeable(&mut self,
@@ -161,11 +197,13 @@ macro_rules! default_builder {
 
             fn function_declaration(&mut self,
                                     name: String,
+                                    syntax: FunctionSyntax,
                                     post_name_comments: Vec<Newline>,
-                                    body: Self::CompoundCommand)
+                                    body: Self::CompoundCommand,
+                                    span: Span)
                 -> Result<Self::PipeableCommand, Self::Error>
             {
-                self.0.function_declaration(name, post_name_comments, body)
+                self.0.function_declaration(name, syntax, post_name_comments, body, span)
             }
 
             fn comments(&mut self,
@@ -175,18 +213,52 @@ macro_rules! default_builder {
                 self.0.comments(comments)
             }
 
+            fn unparsed(&mut self,
+                        pre_cmd_comments: Vec<Newline>,
+                        span: UnparsedSpan)
+                -> Result<Self::Command, Self::Error>
+            {
+                self.0.unparsed(pre_cmd_comments, span)
+            }
+
+            fn arithmetic(&mut self,
+                          expr: Option<DefaultArithmetic>,
+                          span: Span)
+                -> Result<Option<DefaultArithmetic>, Self::Error>
+            {
+                self.0.arithmetic(expr, span)
+            }
+
+            fn parameter_substitution(&mut self,
+                                      kind: SubstitutionKind<Self::Command>,
+                                      span: Span)
+                -> Result<SubstitutionKind<Self::Command>, Self::Error>
+            {
+                self.0.parameter_substitution(kind, span)
+            }
+
             fn word(&mut self,
-                    kind: ComplexWordKind<Self::Command>)
+                    kind: ComplexWordKind<Self::Command>,
+                    span: Span)
                 -> Result<Self::Word, Self::Error>
             {
-                self.0.word(kind)
+                self.0.word(kind, span)
             }
 
             fn redirect(&mut self,
-                        kind: RedirectKind<Self::Word>)
+                        kind: RedirectKind<Self::Word>,
+                        span: Span)
                 -> Result<Self::Redirect, Self::Error>
             {
-                self.0.redirect(kind)
+                self.0.redirect(kind, span)
+            }
+
+            fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+                self.0.enter_context(kind)
+            }
+
+            fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+                self.0.exit_context(kind)
             }
         }
     };
@@ -293,6 +365,7 @@ where
         list: Self::CommandList,
         separator: SeparatorKind,
         _cmd_comment: Option<Newline>,
+        _span: Span,
     ) -> Result<Self::Command, Self::Error> {
         let cmd = match separator {
             SeparatorKind::Semi | SeparatorKind::Other | SeparatorKind::Newline => {
@@ -309,6 +382,7 @@ where
         &mut self,
         first: Self::ListableCommand,
         rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        _span: Span,
     ) -> Result<Self::CommandList, Self::Error> {
         Ok(AndOrList {
             first,
@@ -321,19 +395,20 @@ where
     fn pipeline(
         &mut self,
         bang: bool,
-        cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        _span: Span,
     ) -> Result<Self::ListableCommand, Self::Error> {
         debug_assert_eq!(cmds.is_empty(), false);
-        let mut cmds: Vec<_> = cmds.into_iter().map(|(_, c)| c).collect();
+        let mut segments: Vec<_> = cmds.into_iter().map(|(_, c)| c).collect();
 
         // Pipe is the only AST node which allows for a status
         // negation, so we are forced to use it even if we have a single
         // command. Otherwise there is no need to wrap it further.
-        if bang || cmds.len() > 1 {
-            cmds.shrink_to_fit();
-            Ok(ListableCommand::Pipe(bang, cmds))
+        if bang || segments.len() > 1 {
+            segments.shrink_to_fit();
+            Ok(ListableCommand::Pipe(Pipeline { bang, segments }))
         } else {
-            Ok(ListableCommand::Single(cmds.pop().unwrap()))
+            Ok(ListableCommand::Single(segments.pop().unwrap().cmd))
         }
     }
 
@@ -342,6 +417,7 @@ where
         &mut self,
         redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
         mut redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        _span: Span,
     ) -> Result<Self::PipeableCommand, Self::Error> {
         let redirects_or_env_vars = redirects_or_env_vars
             .into_iter()
@@ -364,6 +440,8 @@ where
         &mut self,
         cmd_group: CommandGroup<Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let mut cmds = cmd_group.commands;
         cmds.shrink_to_fit();
@@ -371,6 +449,7 @@ where
         Ok(CompoundCommand {
             kind: CompoundCommandKind::Brace(cmds),
             io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -379,6 +458,8 @@ where
         &mut self,
         cmd_group: CommandGroup<Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let mut cmds = cmd_group.commands;
         cmds.shrink_to_fit();
@@ -386,6 +467,7 @@ where
         Ok(CompoundCommand {
             kind: CompoundCommandKind::Subshell(cmds),
             io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -395,6 +477,8 @@ where
         kind: LoopKind,
         guard_body_pair: GuardBodyPairGroup<Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let mut guard = guard_body_pair.guard.commands;
         let mut body = guard_body_pair.body.commands;
@@ -413,6 +497,7 @@ where
         Ok(CompoundCommand {
             kind: loop_cmd,
             io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -421,6 +506,8 @@ where
         &mut self,
         fragments: IfFragments<Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let IfFragments {
             conditionals,
@@ -457,6 +544,7 @@ where
                 else_branch,
             },
             io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -465,6 +553,8 @@ where
         &mut self,
         fragments: ForFragments<Self::Word, Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let words = fragments.words.map(|(_, mut words, _)| {
             words.shrink_to_fit();
@@ -482,6 +572,7 @@ where
                 body,
             },
             io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -490,6 +581,8 @@ where
         &mut self,
         fragments: CaseFragments<Self::Word, Self::Command>,
         mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
     ) -> Result<Self::CompoundCommand, Self::Error> {
         let arms = fragments
             .arms
@@ -501,7 +594,11 @@ where
                 let mut body = arm.body.commands;
                 body.shrink_to_fit();
 
-                PatternBodyPair { patterns, body }
+                PatternBodyPair {
+                    patterns,
+                    body,
+                    terminator: arm.terminator,
+                }
             })
             .collect();
 
@@ -512,6 +609,39 @@ where
                 arms,
             },
             io: redirects,
+            io_span: redirects_span,
+        })
+    }
+
+    /// Constructs a `CompoundCommand::Conditional` node with the provided inputs.
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        redirects.shrink_to_fit();
+        Ok(CompoundCommand {
+            kind: CompoundCommandKind::Conditional(expr),
+            io: redirects,
+            io_span: redirects_span,
+        })
+    }
+
+    /// Constructs a `CompoundCommand::Arithmetic` node with the provided inputs.
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        mut redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        _span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        redirects.shrink_to_fit();
+        Ok(CompoundCommand {
+            kind: CompoundCommandKind::Arithmetic(expr.map(map_arith)),
+            io: redirects,
+            io_span: redirects_span,
         })
     }
 
@@ -527,10 +657,16 @@ where
     fn function_declaration(
         &mut self,
         name: String,
+        syntax: FunctionSyntax,
         _post_name_comments: Vec<Newline>,
         body: Self::CompoundCommand,
+        _span: Span,
     ) -> Result<Self::PipeableCommand, Self::Error> {
-        Ok(PipeableCommand::FunctionDef(name.into(), body.into()))
+        Ok(PipeableCommand::FunctionDef(
+            name.into(),
+            syntax,
+            body.into(),
+        ))
     }
 
     /// Ignored by the builder.
@@ -538,59 +674,39 @@ where
         Ok(())
     }
 
+    /// Constructs a `Command::Unparsed` node with the provided inputs.
+    fn unparsed(
+        &mut self,
+        _pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        Ok(Command::Unparsed(span).into())
+    }
+
+    /// Returns the provided expression unchanged.
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        _span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        Ok(expr)
+    }
+
     /// Constructs a `ast::Word` from the provided input.
-    fn word(&mut self, kind: ComplexWordKind<Self::Command>) -> Result<Self::Word, Self::Error> {
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        _span: Span,
+    ) -> Result<Self::Word, Self::Error> {
         macro_rules! map {
             ($pat:expr) => {
                 match $pat {
-                    Some(w) => Some(self.word(w)?),
+                    Some(w) => Some(self.word(w, _span)?),
                     None => None,
                 }
             };
         }
 
-        fn map_arith<T: From<String>>(kind: DefaultArithmetic) -> Arithmetic<T> {
-            use crate::ast::Arithmetic::*;
-            match kind {
-                Var(v) => Var(v.into()),
-                Literal(l) => Literal(l),
-                Pow(a, b) => Pow(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                PostIncr(p) => PostIncr(p.into()),
-                PostDecr(p) => PostDecr(p.into()),
-                PreIncr(p) => PreIncr(p.into()),
-                PreDecr(p) => PreDecr(p.into()),
-                UnaryPlus(a) => UnaryPlus(Box::new(map_arith(*a))),
-                UnaryMinus(a) => UnaryMinus(Box::new(map_arith(*a))),
-                LogicalNot(a) => LogicalNot(Box::new(map_arith(*a))),
-                BitwiseNot(a) => BitwiseNot(Box::new(map_arith(*a))),
-                Mult(a, b) => Mult(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Div(a, b) => Div(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Modulo(a, b) => Modulo(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Add(a, b) => Add(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Sub(a, b) => Sub(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                ShiftLeft(a, b) => ShiftLeft(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                ShiftRight(a, b) => ShiftRight(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Less(a, b) => Less(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LessEq(a, b) => LessEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Great(a, b) => Great(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                GreatEq(a, b) => GreatEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Eq(a, b) => Eq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                NotEq(a, b) => NotEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseAnd(a, b) => BitwiseAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseXor(a, b) => BitwiseXor(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseOr(a, b) => BitwiseOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LogicalAnd(a, b) => LogicalAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LogicalOr(a, b) => LogicalOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Ternary(a, b, c) => Ternary(
-                    Box::new(map_arith(*a)),
-                    Box::new(map_arith(*b)),
-                    Box::new(map_arith(*c)),
-                ),
-                Assign(v, a) => Assign(v.into(), Box::new(map_arith(*a))),
-                Sequence(ariths) => Sequence(ariths.into_iter().map(map_arith).collect()),
-            }
-        }
-
         let map_param = |kind: DefaultParameter| -> Parameter<T> {
             use crate::ast::Parameter::*;
             match kind {
@@ -619,6 +735,9 @@ where
                 SimpleWordKind::SquareClose => SimpleWord::SquareClose,
                 SimpleWordKind::Tilde => SimpleWord::Tilde,
                 SimpleWordKind::Colon => SimpleWord::Colon,
+                SimpleWordKind::TildePrefix(login) => {
+                    SimpleWord::TildePrefix(login.map(Into::into))
+                }
 
                 SimpleWordKind::CommandSubst(c) => {
                     SimpleWord::Subst(Box::new(ParameterSubstitution::Command(c.commands)))
@@ -631,6 +750,7 @@ where
                     let subst = match s {
                         Len(p) => ParameterSubstitution::Len(map_param(p)),
                         Command(c) => ParameterSubstitution::Command(c.commands),
+                        ReadFile(w) => ParameterSubstitution::ReadFile(self.word(w, _span)?),
                         Arith(a) => ParameterSubstitution::Arith(a.map(map_arith)),
                         Default(c, p, w) => {
                             ParameterSubstitution::Default(c, map_param(p), map!(w))
@@ -667,6 +787,8 @@ where
                         Uppercase(c, p, w) => {
                             ParameterSubstitution::Uppercase(c, map_param(p), map!(w))
                         }
+                        ProcessRead(c) => ParameterSubstitution::ProcessRead(c.commands),
+                        ProcessWrite(c) => ParameterSubstitution::ProcessWrite(c.commands),
                     };
                     SimpleWord::Subst(Box::new(subst))
                 }
@@ -674,25 +796,32 @@ where
             Ok(simple)
         };
 
-        let mut map_word = |kind| {
+        fn map_word<C, T, S, E, F>(kind: WordKind<C>, map_simple: &mut F) -> Result<Word<T, S>, E>
+        where
+            T: From<String>,
+            F: FnMut(SimpleWordKind<C>) -> Result<S, E>,
+        {
             let word = match kind {
                 WordKind::Simple(s) => Word::Simple(map_simple(s)?),
                 WordKind::SingleQuoted(s) => Word::SingleQuoted(s.into()),
                 WordKind::DoubleQuoted(v) => Word::DoubleQuoted(
                     v.into_iter()
-                        .map(&mut map_simple)
+                        .map(&mut *map_simple)
                         .collect::<Result<Vec<_>, _>>()?,
                 ),
+                WordKind::Braces(b) => {
+                    Word::Braces(b.try_map(&mut |w| map_word(w, map_simple))?)
+                }
             };
             Ok(word)
-        };
+        }
 
         let word = match compress(kind) {
-            ComplexWordKind::Single(s) => ComplexWord::Single(map_word(s)?),
+            ComplexWordKind::Single(s) => ComplexWord::Single(map_word(s, &mut map_simple)?),
             ComplexWordKind::Concat(words) => ComplexWord::Concat(
                 words
                     .into_iter()
-                    .map(map_word)
+                    .map(|w| map_word(w, &mut map_simple))
                     .collect::<Result<Vec<_>, _>>()?,
             ),
         };
@@ -701,16 +830,22 @@ where
     }
 
     /// Constructs a `ast::Redirect` from the provided input.
-    fn redirect(&mut self, kind: RedirectKind<Self::Word>) -> Result<Self::Redirect, Self::Error> {
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        _span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
         let io = match kind {
             RedirectKind::Read(fd, path) => Redirect::Read(fd, path),
             RedirectKind::Write(fd, path) => Redirect::Write(fd, path),
             RedirectKind::ReadWrite(fd, path) => Redirect::ReadWrite(fd, path),
             RedirectKind::Append(fd, path) => Redirect::Append(fd, path),
             RedirectKind::Clobber(fd, path) => Redirect::Clobber(fd, path),
-            RedirectKind::Heredoc(fd, body) => Redirect::Heredoc(fd, body),
+            RedirectKind::Heredoc(fd, body, info) => Redirect::Heredoc(fd, body, info),
             RedirectKind::DupRead(src, dst) => Redirect::DupRead(src, dst),
             RedirectKind::DupWrite(src, dst) => Redirect::DupWrite(src, dst),
+            RedirectKind::WriteBoth(path) => Redirect::WriteBoth(path),
+            RedirectKind::AppendBoth(path) => Redirect::AppendBoth(path),
         };
 
         Ok(io)
@@ -773,7 +908,7 @@ where
     }
 }
 
-fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
+pub(super) fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
     use crate::ast::builder::ComplexWordKind::*;
     use crate::ast::builder::SimpleWordKind::*;
     use crate::ast::builder::WordKind::*;
@@ -808,13 +943,35 @@ fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
         }
     }
 
+    // Brace expansions are never coalesced with their neighbors,
+    // but the words of each of their alternatives are
+    fn compress_braces<C>(word: WordKind<C>) -> WordKind<C> {
+        match word {
+            Braces(crate::ast::Braces::Alternatives(alternatives)) => {
+                let alternatives = alternatives
+                    .into_iter()
+                    .map(|words| {
+                        Coalesce::new(words, coalesce_word)
+                            .map(compress_braces)
+                            .collect()
+                    })
+                    .collect();
+                Braces(crate::ast::Braces::Alternatives(alternatives))
+            }
+            word => word,
+        }
+    }
+
     match word {
         Single(s) => Single(match s {
             s @ Simple(_) | s @ SingleQuoted(_) => s,
             DoubleQuoted(v) => DoubleQuoted(Coalesce::new(v, coalesce_simple).collect()),
+            s @ Braces(_) => compress_braces(s),
         }),
         Concat(v) => {
-            let mut body: Vec<_> = Coalesce::new(v.into_iter(), coalesce_word).collect();
+            let mut body: Vec<_> = Coalesce::new(v, coalesce_word)
+                .map(compress_braces)
+                .collect();
             if body.len() == 1 {
                 Single(body.pop().unwrap())
             } else {
@@ -823,3 +980,57 @@ fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
         }
     }
 }
+
+/// Converts the variables of an arithmetic expression into another representation.
+fn map_arith<T: From<String>>(kind: DefaultArithmetic) -> Arithmetic<T> {
+    use crate::ast::Arithmetic::*;
+    match kind {
+        Var(v) => Var(v.into()),
+        Index(v, i) => Index(v.into(), Box::new(map_arith(*i))),
+        Literal(l) => Literal(l),
+        RadixLiteral(l, radix, text) => RadixLiteral(l, radix, text),
+        Pow(a, b) => Pow(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        PostIncr(t) => PostIncr(map_arith_target(t)),
+        PostDecr(t) => PostDecr(map_arith_target(t)),
+        PreIncr(t) => PreIncr(map_arith_target(t)),
+        PreDecr(t) => PreDecr(map_arith_target(t)),
+        UnaryPlus(a) => UnaryPlus(Box::new(map_arith(*a))),
+        UnaryMinus(a) => UnaryMinus(Box::new(map_arith(*a))),
+        LogicalNot(a) => LogicalNot(Box::new(map_arith(*a))),
+        BitwiseNot(a) => BitwiseNot(Box::new(map_arith(*a))),
+        Mult(a, b) => Mult(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Div(a, b) => Div(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Modulo(a, b) => Modulo(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Add(a, b) => Add(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Sub(a, b) => Sub(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        ShiftLeft(a, b) => ShiftLeft(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        ShiftRight(a, b) => ShiftRight(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Less(a, b) => Less(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LessEq(a, b) => LessEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Great(a, b) => Great(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        GreatEq(a, b) => GreatEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Eq(a, b) => Eq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        NotEq(a, b) => NotEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseAnd(a, b) => BitwiseAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseXor(a, b) => BitwiseXor(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseOr(a, b) => BitwiseOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LogicalAnd(a, b) => LogicalAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LogicalOr(a, b) => LogicalOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Ternary(a, b, c) => Ternary(
+            Box::new(map_arith(*a)),
+            Box::new(map_arith(*b)),
+            Box::new(map_arith(*c)),
+        ),
+        Assign(t, a) => Assign(map_arith_target(t), Box::new(map_arith(*a))),
+        AssignOp(op, t, a) => AssignOp(op, map_arith_target(t), Box::new(map_arith(*a))),
+        Sequence(ariths) => Sequence(ariths.into_iter().map(map_arith).collect()),
+    }
+}
+
+/// Converts the variables of an assignment target into another representation.
+fn map_arith_target<T: From<String>>(target: ArithmeticTarget<String>) -> ArithmeticTarget<T> {
+    match target {
+        ArithmeticTarget::Var(v) => ArithmeticTarget::Var(v.into()),
+        ArithmeticTarget::Index(v, i) => ArithmeticTarget::Index(v.into(), Box::new(map_arith(*i))),
+    }
+}
@@ -0,0 +1,703 @@
+use crate::ast::builder::*;
+use crate::ast::{
+    AndOr, Conditional, DefaultArithmetic, PipeSegment, RedirectOrCmdWord, RedirectOrEnvVar, Span,
+    UnparsedSpan,
+};
+use std::any::{self, Any};
+use std::error::Error;
+use std::fmt;
+
+/// A type erased node built by a `DynBuilder`.
+pub type AnyNode = Box<dyn Any>;
+
+/// A type erased error returned by a `DynBuilder`.
+pub type DynError = Box<dyn Error + Send + Sync>;
+
+/// A `Builder` trait object whose node and error types have all been erased.
+///
+/// This allows builders to be chosen at runtime (e.g. supplied by a plugin)
+/// without the parser, or the code driving it, knowing their concrete types.
+/// Any `Builder` can be turned into one via `ErasedBuilder`, and a boxed
+/// `DynBuilder` is itself a `Builder` which can be handed to the parser.
+pub type DynBuilder = dyn Builder<
+    Command = AnyNode,
+    CommandList = AnyNode,
+    ListableCommand = AnyNode,
+    PipeableCommand = AnyNode,
+    CompoundCommand = AnyNode,
+    Word = AnyNode,
+    Redirect = AnyNode,
+    Error = DynError,
+>;
+
+/// An error returned by an `ErasedBuilder` if it is given a node which was
+/// not built by the builder it wraps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct NodeMismatch {
+    /// The name of the node type which was expected.
+    pub expected: &'static str,
+}
+
+impl fmt::Display for NodeMismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "expected a node of type `{}`", self.expected)
+    }
+}
+
+impl Error for NodeMismatch {}
+
+/// A `Builder` adapter which erases the node and error types of another builder.
+///
+/// Nodes built by the wrapped builder are boxed up as `AnyNode`s, and are
+/// unboxed again before being passed back into it. The commands produced by
+/// the parser can be downcast back into the wrapped builder's `Command` type.
+///
+/// ```
+/// use conch_parser::ast::builder::{DynBuilder, ErasedBuilder, StringBuilder};
+/// use conch_parser::ast::TopLevelCommand;
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::{DefaultParser, Parser};
+///
+/// // e.g. a builder supplied by a plugin, whose concrete type is unknown to the host
+/// let builder: Box<DynBuilder> = ErasedBuilder::new(StringBuilder::new()).boxed();
+///
+/// let lexer = Lexer::new("echo foo".chars());
+/// let cmd = Parser::with_builder(lexer, builder)
+///     .complete_command()
+///     .unwrap()
+///     .unwrap();
+///
+/// let expected = DefaultParser::new(Lexer::new("echo foo".chars()))
+///     .complete_command()
+///     .unwrap();
+/// let cmd = cmd.downcast::<TopLevelCommand<String>>().unwrap();
+/// assert_eq!(Some(*cmd), expected);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ErasedBuilder<B> {
+    builder: B,
+}
+
+impl<B> ErasedBuilder<B> {
+    /// Wraps a builder, erasing its node and error types.
+    pub fn new(builder: B) -> Self {
+        ErasedBuilder { builder }
+    }
+
+    /// Returns a reference to the wrapped builder.
+    pub fn get_ref(&self) -> &B {
+        &self.builder
+    }
+
+    /// Returns a mutable reference to the wrapped builder.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+
+    /// Unwraps the wrapped builder.
+    pub fn into_inner(self) -> B {
+        self.builder
+    }
+}
+
+impl<B> ErasedBuilder<B>
+where
+    B: Builder + 'static,
+    B::Command: 'static,
+    B::CommandList: 'static,
+    B::ListableCommand: 'static,
+    B::PipeableCommand: 'static,
+    B::CompoundCommand: 'static,
+    B::Word: 'static,
+    B::Redirect: 'static,
+    B::Error: Error + Send + Sync + 'static,
+{
+    /// Boxes up the adapter as a `DynBuilder` trait object.
+    pub fn boxed(self) -> Box<DynBuilder> {
+        Box::new(self)
+    }
+}
+
+/// Unboxes a node built by the wrapped builder.
+fn node<T: 'static>(node: AnyNode) -> Result<T, DynError> {
+    match node.downcast::<T>() {
+        Ok(node) => Ok(*node),
+        Err(_) => Err(Box::new(NodeMismatch {
+            expected: any::type_name::<T>(),
+        })),
+    }
+}
+
+fn nodes<T: 'static>(nodes: Vec<AnyNode>) -> Result<Vec<T>, DynError> {
+    nodes.into_iter().map(node).collect()
+}
+
+fn command_group<C: 'static>(group: CommandGroup<AnyNode>) -> Result<CommandGroup<C>, DynError> {
+    Ok(CommandGroup {
+        commands: nodes(group.commands)?,
+        trailing_comments: group.trailing_comments,
+    })
+}
+
+fn guard_body_pair<C: 'static>(
+    pair: GuardBodyPairGroup<AnyNode>,
+) -> Result<GuardBodyPairGroup<C>, DynError> {
+    Ok(GuardBodyPairGroup {
+        guard: command_group(pair.guard)?,
+        body: command_group(pair.body)?,
+    })
+}
+
+fn complex_word_kind<C: 'static>(
+    kind: ComplexWordKind<AnyNode>,
+) -> Result<ComplexWordKind<C>, DynError> {
+    let kind = match kind {
+        ComplexWordKind::Single(w) => ComplexWordKind::Single(word_kind(w)?),
+        ComplexWordKind::Concat(ws) => ComplexWordKind::Concat(
+            ws.into_iter()
+                .map(word_kind)
+                .collect::<Result<_, DynError>>()?,
+        ),
+    };
+
+    Ok(kind)
+}
+
+fn word_kind<C: 'static>(kind: WordKind<AnyNode>) -> Result<WordKind<C>, DynError> {
+    let kind = match kind {
+        WordKind::Simple(s) => WordKind::Simple(simple_word_kind(s)?),
+        WordKind::SingleQuoted(s) => WordKind::SingleQuoted(s),
+        WordKind::DoubleQuoted(v) => WordKind::DoubleQuoted(
+            v.into_iter()
+                .map(simple_word_kind)
+                .collect::<Result<_, DynError>>()?,
+        ),
+        WordKind::Braces(b) => WordKind::Braces(b.try_map(&mut word_kind)?),
+    };
+
+    Ok(kind)
+}
+
+fn simple_word_kind<C: 'static>(
+    kind: SimpleWordKind<AnyNode>,
+) -> Result<SimpleWordKind<C>, DynError> {
+    let kind = match kind {
+        SimpleWordKind::Literal(s) => SimpleWordKind::Literal(s),
+        SimpleWordKind::Param(p) => SimpleWordKind::Param(p),
+        SimpleWordKind::Subst(s) => SimpleWordKind::Subst(Box::new(subst(*s)?)),
+        SimpleWordKind::CommandSubst(c) => SimpleWordKind::CommandSubst(command_group(c)?),
+        SimpleWordKind::Escaped(s) => SimpleWordKind::Escaped(s),
+        SimpleWordKind::Star => SimpleWordKind::Star,
+        SimpleWordKind::Question => SimpleWordKind::Question,
+        SimpleWordKind::SquareOpen => SimpleWordKind::SquareOpen,
+        SimpleWordKind::SquareClose => SimpleWordKind::SquareClose,
+        SimpleWordKind::Tilde => SimpleWordKind::Tilde,
+        SimpleWordKind::Colon => SimpleWordKind::Colon,
+        SimpleWordKind::TildePrefix(login) => SimpleWordKind::TildePrefix(login),
+    };
+
+    Ok(kind)
+}
+
+fn subst<C: 'static>(
+    kind: ParameterSubstitutionKind<ComplexWordKind<AnyNode>, AnyNode>,
+) -> Result<ParameterSubstitutionKind<ComplexWordKind<C>, C>, DynError> {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    macro_rules! map {
+        ($word:expr) => {
+            $word.map(complex_word_kind).transpose()?
+        };
+    }
+
+    let kind = match kind {
+        Command(c) => Command(command_group(c)?),
+        ReadFile(w) => ReadFile(complex_word_kind(w)?),
+        Len(p) => Len(p),
+        Arith(a) => Arith(a),
+        Default(c, p, w) => Default(c, p, map!(w)),
+        Assign(c, p, w) => Assign(c, p, map!(w)),
+        Error(c, p, w) => Error(c, p, map!(w)),
+        Alternative(c, p, w) => Alternative(c, p, map!(w)),
+        RemoveSmallestSuffix(p, w) => RemoveSmallestSuffix(p, map!(w)),
+        RemoveLargestSuffix(p, w) => RemoveLargestSuffix(p, map!(w)),
+        RemoveSmallestPrefix(p, w) => RemoveSmallestPrefix(p, map!(w)),
+        RemoveLargestPrefix(p, w) => RemoveLargestPrefix(p, map!(w)),
+        ReplaceString(p, w) => ReplaceString(p, map!(w)),
+        ReplaceStringAll(p, w) => ReplaceStringAll(p, map!(w)),
+        Substring(p, w) => Substring(p, map!(w)),
+        Lowercase(c, p, w) => Lowercase(c, p, map!(w)),
+        Uppercase(c, p, w) => Uppercase(c, p, map!(w)),
+        ProcessRead(c) => ProcessRead(command_group(c)?),
+        ProcessWrite(c) => ProcessWrite(command_group(c)?),
+    };
+
+    Ok(kind)
+}
+
+/// Boxes up any commands (built by the wrapped builder) within a substitution.
+fn erase_subst<C: 'static>(kind: SubstitutionKind<C>) -> SubstitutionKind<AnyNode> {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    fn group<C: 'static>(group: CommandGroup<C>) -> CommandGroup<AnyNode> {
+        CommandGroup {
+            commands: group
+                .commands
+                .into_iter()
+                .map(|c| -> AnyNode { Box::new(c) })
+                .collect(),
+            trailing_comments: group.trailing_comments,
+        }
+    }
+
+    fn simple<C: 'static>(kind: SimpleWordKind<C>) -> SimpleWordKind<AnyNode> {
+        match kind {
+            SimpleWordKind::Literal(s) => SimpleWordKind::Literal(s),
+            SimpleWordKind::Param(p) => SimpleWordKind::Param(p),
+            SimpleWordKind::Subst(s) => SimpleWordKind::Subst(Box::new(erase_subst(*s))),
+            SimpleWordKind::CommandSubst(c) => SimpleWordKind::CommandSubst(group(c)),
+            SimpleWordKind::Escaped(s) => SimpleWordKind::Escaped(s),
+            SimpleWordKind::Star => SimpleWordKind::Star,
+            SimpleWordKind::Question => SimpleWordKind::Question,
+            SimpleWordKind::SquareOpen => SimpleWordKind::SquareOpen,
+            SimpleWordKind::SquareClose => SimpleWordKind::SquareClose,
+            SimpleWordKind::Tilde => SimpleWordKind::Tilde,
+            SimpleWordKind::Colon => SimpleWordKind::Colon,
+            SimpleWordKind::TildePrefix(login) => SimpleWordKind::TildePrefix(login),
+        }
+    }
+
+    fn word<C: 'static>(kind: WordKind<C>) -> WordKind<AnyNode> {
+        match kind {
+            WordKind::Simple(s) => WordKind::Simple(simple(s)),
+            WordKind::SingleQuoted(s) => WordKind::SingleQuoted(s),
+            WordKind::DoubleQuoted(v) => {
+                WordKind::DoubleQuoted(v.into_iter().map(simple).collect())
+            }
+            WordKind::Braces(b) => WordKind::Braces(b.map(&mut word)),
+        }
+    }
+
+    fn complex<C: 'static>(kind: ComplexWordKind<C>) -> ComplexWordKind<AnyNode> {
+        match kind {
+            ComplexWordKind::Single(w) => ComplexWordKind::Single(word(w)),
+            ComplexWordKind::Concat(ws) => {
+                ComplexWordKind::Concat(ws.into_iter().map(word).collect())
+            }
+        }
+    }
+
+    match kind {
+        Command(c) => Command(group(c)),
+        ReadFile(w) => ReadFile(complex(w)),
+        Len(p) => Len(p),
+        Arith(a) => Arith(a),
+        Default(c, p, w) => Default(c, p, w.map(complex)),
+        Assign(c, p, w) => Assign(c, p, w.map(complex)),
+        Error(c, p, w) => Error(c, p, w.map(complex)),
+        Alternative(c, p, w) => Alternative(c, p, w.map(complex)),
+        RemoveSmallestSuffix(p, w) => RemoveSmallestSuffix(p, w.map(complex)),
+        RemoveLargestSuffix(p, w) => RemoveLargestSuffix(p, w.map(complex)),
+        RemoveSmallestPrefix(p, w) => RemoveSmallestPrefix(p, w.map(complex)),
+        RemoveLargestPrefix(p, w) => RemoveLargestPrefix(p, w.map(complex)),
+        ReplaceString(p, w) => ReplaceString(p, w.map(complex)),
+        ReplaceStringAll(p, w) => ReplaceStringAll(p, w.map(complex)),
+        Substring(p, w) => Substring(p, w.map(complex)),
+        Lowercase(c, p, w) => Lowercase(c, p, w.map(complex)),
+        Uppercase(c, p, w) => Uppercase(c, p, w.map(complex)),
+        ProcessRead(c) => ProcessRead(group(c)),
+        ProcessWrite(c) => ProcessWrite(group(c)),
+    }
+}
+
+fn redirect_kind<W: 'static>(kind: RedirectKind<AnyNode>) -> Result<RedirectKind<W>, DynError> {
+    let kind = match kind {
+        RedirectKind::Read(fd, w) => RedirectKind::Read(fd, node(w)?),
+        RedirectKind::Write(fd, w) => RedirectKind::Write(fd, node(w)?),
+        RedirectKind::ReadWrite(fd, w) => RedirectKind::ReadWrite(fd, node(w)?),
+        RedirectKind::Append(fd, w) => RedirectKind::Append(fd, node(w)?),
+        RedirectKind::Clobber(fd, w) => RedirectKind::Clobber(fd, node(w)?),
+        RedirectKind::Heredoc(fd, w, info) => RedirectKind::Heredoc(fd, node(w)?, info),
+        RedirectKind::DupRead(fd, w) => RedirectKind::DupRead(fd, node(w)?),
+        RedirectKind::DupWrite(fd, w) => RedirectKind::DupWrite(fd, node(w)?),
+        RedirectKind::WriteBoth(w) => RedirectKind::WriteBoth(node(w)?),
+        RedirectKind::AppendBoth(w) => RedirectKind::AppendBoth(node(w)?),
+    };
+
+    Ok(kind)
+}
+
+impl<B> ErasedBuilder<B>
+where
+    B: Builder,
+    B::Command: 'static,
+    B::CommandList: 'static,
+    B::ListableCommand: 'static,
+    B::PipeableCommand: 'static,
+    B::CompoundCommand: 'static,
+    B::Word: 'static,
+    B::Redirect: 'static,
+    B::Error: Error + Send + Sync + 'static,
+{
+    /// Boxes up a result of the wrapped builder.
+    fn erase<T: 'static>(result: Result<T, B::Error>) -> Result<AnyNode, DynError> {
+        match result {
+            Ok(node) => Ok(Box::new(node)),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+}
+
+impl<B> Builder for ErasedBuilder<B>
+where
+    B: Builder,
+    B::Command: 'static,
+    B::CommandList: 'static,
+    B::ListableCommand: 'static,
+    B::PipeableCommand: 'static,
+    B::CompoundCommand: 'static,
+    B::Word: 'static,
+    B::Redirect: 'static,
+    B::Error: Error + Send + Sync + 'static,
+{
+    type Command = AnyNode;
+    type CommandList = AnyNode;
+    type ListableCommand = AnyNode;
+    type PipeableCommand = AnyNode;
+    type CompoundCommand = AnyNode;
+    type Word = AnyNode;
+    type Redirect = AnyNode;
+    type Error = DynError;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        let list = node(list)?;
+        Self::erase(self.builder.complete_command(
+            pre_cmd_comments,
+            list,
+            separator,
+            cmd_comment,
+            span,
+        ))
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        let first = node(first)?;
+        let rest = rest
+            .into_iter()
+            .map(|(comments, and_or)| {
+                let and_or = match and_or {
+                    AndOr::And(cmd) => AndOr::And(node(cmd)?),
+                    AndOr::Or(cmd) => AndOr::Or(node(cmd)?),
+                };
+                Ok((comments, and_or))
+            })
+            .collect::<Result<_, DynError>>()?;
+
+        Self::erase(self.builder.and_or_list(first, rest, span))
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        let cmds = cmds
+            .into_iter()
+            .map(|(comments, segment)| {
+                let segment = PipeSegment {
+                    pipe: segment.pipe,
+                    pipe_stderr: segment.pipe_stderr,
+                    cmd: node(segment.cmd)?,
+                };
+                Ok((comments, segment))
+            })
+            .collect::<Result<_, DynError>>()?;
+
+        Self::erase(self.builder.pipeline(bang, cmds, span))
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let redirects_or_env_vars = redirects_or_env_vars
+            .into_iter()
+            .map(|r| match r {
+                RedirectOrEnvVar::Redirect(r) => Ok(RedirectOrEnvVar::Redirect(node(r)?)),
+                RedirectOrEnvVar::EnvVar(name, value) => {
+                    Ok(RedirectOrEnvVar::EnvVar(name, value.map(node).transpose()?))
+                }
+            })
+            .collect::<Result<_, DynError>>()?;
+
+        let redirects_or_cmd_words = redirects_or_cmd_words
+            .into_iter()
+            .map(|r| match r {
+                RedirectOrCmdWord::Redirect(r) => Ok(RedirectOrCmdWord::Redirect(node(r)?)),
+                RedirectOrCmdWord::CmdWord(w) => Ok(RedirectOrCmdWord::CmdWord(node(w)?)),
+            })
+            .collect::<Result<_, DynError>>()?;
+
+        Self::erase(self.builder.simple_command(
+            redirects_or_env_vars,
+            redirects_or_cmd_words,
+            span,
+        ))
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmds = command_group(cmds)?;
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .brace_group(cmds, redirects, redirects_span, span),
+        )
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmds = command_group(cmds)?;
+        let redirects = nodes(redirects)?;
+        Self::erase(self.builder.subshell(cmds, redirects, redirects_span, span))
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let guard_body_pair = self::guard_body_pair(guard_body_pair)?;
+        let redirects = nodes(redirects)?;
+        Self::erase(self.builder.loop_command(
+            kind,
+            guard_body_pair,
+            redirects,
+            redirects_span,
+            span,
+        ))
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let fragments = IfFragments {
+            conditionals: fragments
+                .conditionals
+                .into_iter()
+                .map(guard_body_pair)
+                .collect::<Result<_, _>>()?,
+            else_branch: fragments.else_branch.map(command_group).transpose()?,
+        };
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .if_command(fragments, redirects, redirects_span, span),
+        )
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let words = match fragments.words {
+            Some((comments, words, comment)) => Some((comments, nodes(words)?, comment)),
+            None => None,
+        };
+
+        let fragments = ForFragments {
+            var: fragments.var,
+            var_comment: fragments.var_comment,
+            words,
+            pre_body_comments: fragments.pre_body_comments,
+            body: command_group(fragments.body)?,
+        };
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .for_command(fragments, redirects, redirects_span, span),
+        )
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let arms = fragments
+            .arms
+            .into_iter()
+            .map(|arm| {
+                Ok(CaseArm {
+                    patterns: CasePatternFragments {
+                        pre_pattern_comments: arm.patterns.pre_pattern_comments,
+                        pattern_alternatives: nodes(arm.patterns.pattern_alternatives)?,
+                        pattern_comment: arm.patterns.pattern_comment,
+                    },
+                    body: command_group(arm.body)?,
+                    terminator: arm.terminator,
+                    arm_comment: arm.arm_comment,
+                })
+            })
+            .collect::<Result<_, DynError>>()?;
+
+        let fragments = CaseFragments {
+            word: node(fragments.word)?,
+            post_word_comments: fragments.post_word_comments,
+            in_comment: fragments.in_comment,
+            arms,
+            post_arms_comments: fragments.post_arms_comments,
+        };
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .case_command(fragments, redirects, redirects_span, span),
+        )
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let expr = expr.try_map(&mut node)?;
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .conditional_command(expr, redirects, redirects_span, span),
+        )
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let redirects = nodes(redirects)?;
+        Self::erase(
+            self.builder
+                .arithmetic_command(expr, redirects, redirects_span, span),
+        )
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let cmd = node(cmd)?;
+        Self::erase(self.builder.compound_command_into_pipeable(cmd))
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let body = node(body)?;
+        Self::erase(
+            self.builder
+                .function_declaration(name, syntax, post_name_comments, body, span),
+        )
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.builder.comments(comments).map_err(|e| e.into())
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        Self::erase(self.builder.unparsed(pre_cmd_comments, span))
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.builder.arithmetic(expr, span).map_err(|e| e.into())
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        let kind = subst(kind)?;
+        match self.builder.parameter_substitution(kind, span) {
+            Ok(kind) => Ok(erase_subst::<B::Command>(kind)),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        let kind = complex_word_kind(kind)?;
+        Self::erase(self.builder.word(kind, span))
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        let kind = redirect_kind(kind)?;
+        Self::erase(self.builder.redirect(kind, span))
+    }
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.enter_context(kind).map_err(|e| e.into())
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.exit_context(kind).map_err(|e| e.into())
+    }
+}
@@ -0,0 +1,1206 @@
+use crate::ast::builder::*;
+use crate::ast::{
+    AndOr, Conditional, DefaultArithmetic, PipeSegment, RedirectOrCmdWord, RedirectOrEnvVar, Span,
+    UnparsedSpan,
+};
+use std::error::Error;
+use std::fmt;
+use void::Void;
+
+/// A handle to a node built while recording an `EventLog`.
+///
+/// The handle is the index of the `Event` (within its log) which built the node.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(pub usize);
+
+/// A single recorded `Builder` callback, with any previously built
+/// nodes replaced by the `NodeId`s which identify them.
+///
+/// Each variant corresponds to the identically named `Builder` method.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum Event {
+    /// A `Builder::complete_command` callback.
+    CompleteCommand {
+        pre_cmd_comments: Vec<Newline>,
+        list: NodeId,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    },
+    /// A `Builder::and_or_list` callback.
+    AndOrList {
+        first: NodeId,
+        rest: Vec<(Vec<Newline>, AndOr<NodeId>)>,
+        span: Span,
+    },
+    /// A `Builder::pipeline` callback.
+    Pipeline {
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<NodeId>)>,
+        span: Span,
+    },
+    /// A `Builder::simple_command` callback.
+    SimpleCommand {
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<NodeId, String, NodeId>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<NodeId, NodeId>>,
+        span: Span,
+    },
+    /// A `Builder::brace_group` callback.
+    BraceGroup {
+        cmds: CommandGroup<NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::subshell` callback.
+    Subshell {
+        cmds: CommandGroup<NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::loop_command` callback.
+    LoopCommand {
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::if_command` callback.
+    IfCommand {
+        fragments: IfFragments<NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::for_command` callback.
+    ForCommand {
+        fragments: ForFragments<NodeId, NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::case_command` callback.
+    CaseCommand {
+        fragments: CaseFragments<NodeId, NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::conditional_command` callback.
+    ConditionalCommand {
+        expr: Conditional<NodeId>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::arithmetic_command` callback.
+    ArithmeticCommand {
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<NodeId>,
+        redirects_span: Option<Span>,
+        span: Span,
+    },
+    /// A `Builder::compound_command_into_pipeable` callback.
+    CompoundCommandIntoPipeable { cmd: NodeId },
+    /// A `Builder::function_declaration` callback.
+    FunctionDeclaration {
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: NodeId,
+        span: Span,
+    },
+    /// A `Builder::comments` callback.
+    Comments { comments: Vec<Newline> },
+    /// A `Builder::unparsed` callback.
+    Unparsed {
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    },
+    /// A `Builder::arithmetic` callback.
+    Arithmetic {
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    },
+    /// A `Builder::parameter_substitution` callback.
+    ParameterSubstitution {
+        kind: SubstitutionKind<NodeId>,
+        span: Span,
+    },
+    /// A `Builder::word` callback.
+    Word {
+        kind: ComplexWordKind<NodeId>,
+        span: Span,
+    },
+    /// A `Builder::redirect` callback.
+    Redirect {
+        kind: RedirectKind<NodeId>,
+        span: Span,
+    },
+    /// A `Builder::enter_context` callback.
+    EnterContext { kind: ContextKind },
+    /// A `Builder::exit_context` callback.
+    ExitContext { kind: ContextKind },
+}
+
+/// A `Builder` which records the sequence of callbacks made by the parser,
+/// allowing them to be replayed into any other `Builder` at a later time.
+///
+/// This makes it possible to build several different AST representations
+/// out of a single parse, or to cache the results of a parse independently
+/// of the AST representation eventually used.
+///
+/// ```
+/// use conch_parser::ast::builder::{EventLog, RcBuilder, StringBuilder};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let mut log = EventLog::new();
+/// let lexer = Lexer::new("echo foo; echo bar".chars());
+/// let recorded = Parser::with_builder(lexer, &mut log)
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(recorded.len(), 2);
+///
+/// let strings = log.replay(&mut StringBuilder::new()).unwrap();
+/// let rcs = log.replay(&mut RcBuilder::new()).unwrap();
+/// assert_eq!(strings.len(), 2);
+/// assert_eq!(rcs.len(), 2);
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventLog {
+    events: Vec<Event>,
+}
+
+impl EventLog {
+    /// Constructs a new, empty, log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all events recorded so far, in the order they were received.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Consumes the log and returns its events.
+    pub fn into_events(self) -> Vec<Event> {
+        self.events
+    }
+
+    /// Replays all recorded events into another builder, returning any
+    /// complete commands which were not nested within another command,
+    /// in the order they were originally parsed.
+    ///
+    /// Logs which were not produced by recording callbacks of the parser (e.g.
+    /// ones which were modified after being deserialized) may be inconsistent,
+    /// in which case replaying stops at the first invalid event with an error.
+    pub fn replay<B: Builder>(
+        &self,
+        builder: &mut B,
+    ) -> Result<Vec<B::Command>, ReplayError<B::Error>> {
+        let mut replayer = Replayer {
+            builder,
+            nodes: Vec::with_capacity(self.events.len()),
+            arithmetic: Vec::new(),
+            substitutions: Vec::new(),
+        };
+
+        for event in &self.events {
+            let node = replayer.replay(event.clone())?;
+            replayer.nodes.push(node);
+        }
+
+        let cmds = replayer
+            .nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                Some(Node::Command(cmd)) => Some(cmd),
+                _ => None,
+            })
+            .collect();
+
+        Ok(cmds)
+    }
+
+    /// Records an event and returns the `NodeId` which identifies its result.
+    fn push(&mut self, event: Event) -> Result<NodeId, Void> {
+        self.events.push(event);
+        Ok(NodeId(self.events.len() - 1))
+    }
+}
+
+impl From<Vec<Event>> for EventLog {
+    fn from(events: Vec<Event>) -> Self {
+        EventLog { events }
+    }
+}
+
+/// An error which occurred while replaying an `EventLog` into another builder.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReplayError<E> {
+    /// An event referred to a node which does not exist, has already been
+    /// consumed by another event, or was of an unexpected type.
+    InvalidNode(NodeId),
+    /// The word (or substitution) built by the identified event contains an
+    /// arithmetic or `${...}` substitution which no earlier event has built.
+    MissingSubstitution(NodeId),
+    /// The target builder returned an error.
+    Custom(E),
+}
+
+impl<E> From<E> for ReplayError<E> {
+    fn from(err: E) -> Self {
+        ReplayError::Custom(err)
+    }
+}
+
+impl<E: Error + 'static> Error for ReplayError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReplayError::InvalidNode(_) | ReplayError::MissingSubstitution(_) => None,
+            ReplayError::Custom(ref e) => Some(e),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ReplayError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ReplayError::InvalidNode(id) => write!(
+                fmt,
+                "invalid event log: node {} does not exist, or was already consumed",
+                id.0
+            ),
+            ReplayError::MissingSubstitution(id) => write!(
+                fmt,
+                "invalid event log: event {} refers to a substitution which was never built",
+                id.0
+            ),
+            ReplayError::Custom(ref e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+type ReplayResult<T, E> = Result<T, ReplayError<E>>;
+
+impl Builder for EventLog {
+    type Command = NodeId;
+    type CommandList = NodeId;
+    type ListableCommand = NodeId;
+    type PipeableCommand = NodeId;
+    type CompoundCommand = NodeId;
+    type Word = NodeId;
+    type Redirect = NodeId;
+    type Error = Void;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.push(Event::CompleteCommand {
+            pre_cmd_comments,
+            list,
+            separator,
+            cmd_comment,
+            span,
+        })
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.push(Event::AndOrList { first, rest, span })
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.push(Event::Pipeline { bang, cmds, span })
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.push(Event::SimpleCommand {
+            redirects_or_env_vars,
+            redirects_or_cmd_words,
+            span,
+        })
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::BraceGroup {
+            cmds,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::Subshell {
+            cmds,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::LoopCommand {
+            kind,
+            guard_body_pair,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::IfCommand {
+            fragments,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::ForCommand {
+            fragments,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::CaseCommand {
+            fragments,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::ConditionalCommand {
+            expr,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.push(Event::ArithmeticCommand {
+            expr,
+            redirects,
+            redirects_span,
+            span,
+        })
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.push(Event::CompoundCommandIntoPipeable { cmd })
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.push(Event::FunctionDeclaration {
+            name,
+            syntax,
+            post_name_comments,
+            body,
+            span,
+        })
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.push(Event::Comments { comments })?;
+        Ok(())
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        self.push(Event::Unparsed {
+            pre_cmd_comments,
+            span,
+        })
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.push(Event::Arithmetic {
+            expr: expr.clone(),
+            span,
+        })?;
+        Ok(expr)
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.push(Event::ParameterSubstitution {
+            kind: kind.clone(),
+            span,
+        })?;
+        Ok(kind)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.push(Event::Word { kind, span })
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.push(Event::Redirect { kind, span })
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.push(Event::EnterContext { kind })?;
+        Ok(())
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.push(Event::ExitContext { kind })?;
+        Ok(())
+    }
+}
+
+/// A node built by the target builder while replaying an event log.
+enum Node<B: Builder> {
+    Command(B::Command),
+    CommandList(B::CommandList),
+    ListableCommand(B::ListableCommand),
+    PipeableCommand(B::PipeableCommand),
+    CompoundCommand(B::CompoundCommand),
+    Word(B::Word),
+    Redirect(B::Redirect),
+}
+
+/// Tracks the nodes built by the target builder while replaying an event log.
+struct Replayer<'a, B: Builder> {
+    builder: &'a mut B,
+    /// The node built by each replayed event, if any, indexed by `NodeId`.
+    /// Nodes are taken out as they are consumed by subsequent events.
+    nodes: Vec<Option<Node<B>>>,
+    /// The expressions returned by the target builder for any arithmetic
+    /// substitutions whose words have not been replayed yet.
+    ///
+    /// Any words nested within a word (e.g. within a command substitution) are
+    /// always built before it, so the expressions of a word are always the
+    /// last ones remaining by the time it is replayed.
+    arithmetic: Vec<Option<DefaultArithmetic>>,
+    /// The substitutions returned by the target builder for any `${...}`
+    /// substitutions which have not been consumed by a word (or another
+    /// substitution) yet, maintained in the same manner as `arithmetic`.
+    substitutions: Vec<SubstitutionKind<B::Command>>,
+}
+
+macro_rules! take_node {
+    ($fn_name:ident, $variant:ident) => {
+        fn $fn_name(&mut self, id: NodeId) -> ReplayResult<B::$variant, B::Error> {
+            match self.nodes.get_mut(id.0).and_then(Option::take) {
+                Some(Node::$variant(node)) => Ok(node),
+                _ => Err(ReplayError::InvalidNode(id)),
+            }
+        }
+    };
+}
+
+impl<'a, B: Builder> Replayer<'a, B> {
+    take_node!(command, Command);
+    take_node!(command_list, CommandList);
+    take_node!(listable_command, ListableCommand);
+    take_node!(pipeable_command, PipeableCommand);
+    take_node!(compound_command, CompoundCommand);
+    take_node!(word, Word);
+    take_node!(redirect, Redirect);
+
+    /// Returns the `NodeId` of the event currently being replayed.
+    fn current(&self) -> NodeId {
+        NodeId(self.nodes.len())
+    }
+
+    fn redirects(&mut self, redirects: Vec<NodeId>) -> ReplayResult<Vec<B::Redirect>, B::Error> {
+        redirects.into_iter().map(|id| self.redirect(id)).collect()
+    }
+
+    fn command_group(
+        &mut self,
+        group: CommandGroup<NodeId>,
+    ) -> ReplayResult<CommandGroup<B::Command>, B::Error> {
+        Ok(CommandGroup {
+            commands: group
+                .commands
+                .into_iter()
+                .map(|id| self.command(id))
+                .collect::<Result<_, _>>()?,
+            trailing_comments: group.trailing_comments,
+        })
+    }
+
+    fn guard_body_pair(
+        &mut self,
+        pair: GuardBodyPairGroup<NodeId>,
+    ) -> ReplayResult<GuardBodyPairGroup<B::Command>, B::Error> {
+        Ok(GuardBodyPairGroup {
+            guard: self.command_group(pair.guard)?,
+            body: self.command_group(pair.body)?,
+        })
+    }
+
+    fn complex_word_kind(
+        &mut self,
+        kind: ComplexWordKind<NodeId>,
+    ) -> ReplayResult<ComplexWordKind<B::Command>, B::Error> {
+        let kind = match kind {
+            ComplexWordKind::Single(w) => ComplexWordKind::Single(self.word_kind(w)?),
+            ComplexWordKind::Concat(ws) => ComplexWordKind::Concat(
+                ws.into_iter()
+                    .map(|w| self.word_kind(w))
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+
+        Ok(kind)
+    }
+
+    fn word_kind(
+        &mut self,
+        kind: WordKind<NodeId>,
+    ) -> ReplayResult<WordKind<B::Command>, B::Error> {
+        let kind = match kind {
+            WordKind::Simple(s) => WordKind::Simple(self.simple_word_kind(s)?),
+            WordKind::SingleQuoted(s) => WordKind::SingleQuoted(s),
+            WordKind::DoubleQuoted(v) => WordKind::DoubleQuoted(
+                v.into_iter()
+                    .map(|s| self.simple_word_kind(s))
+                    .collect::<Result<_, _>>()?,
+            ),
+            WordKind::Braces(b) => WordKind::Braces(b.try_map(&mut |w| self.word_kind(w))?),
+        };
+
+        Ok(kind)
+    }
+
+    fn simple_word_kind(
+        &mut self,
+        kind: SimpleWordKind<NodeId>,
+    ) -> ReplayResult<SimpleWordKind<B::Command>, B::Error> {
+        let kind = match kind {
+            SimpleWordKind::Literal(s) => SimpleWordKind::Literal(s),
+            SimpleWordKind::Param(p) => SimpleWordKind::Param(p),
+            SimpleWordKind::Subst(s) => SimpleWordKind::Subst(Box::new(self.subst(*s)?)),
+            SimpleWordKind::CommandSubst(c) => SimpleWordKind::CommandSubst(self.command_group(c)?),
+            SimpleWordKind::Escaped(s) => SimpleWordKind::Escaped(s),
+            SimpleWordKind::Star => SimpleWordKind::Star,
+            SimpleWordKind::Question => SimpleWordKind::Question,
+            SimpleWordKind::SquareOpen => SimpleWordKind::SquareOpen,
+            SimpleWordKind::SquareClose => SimpleWordKind::SquareClose,
+            SimpleWordKind::Tilde => SimpleWordKind::Tilde,
+            SimpleWordKind::Colon => SimpleWordKind::Colon,
+            SimpleWordKind::TildePrefix(login) => SimpleWordKind::TildePrefix(login),
+        };
+
+        Ok(kind)
+    }
+
+    fn subst(
+        &mut self,
+        kind: SubstitutionKind<NodeId>,
+    ) -> ReplayResult<SubstitutionKind<B::Command>, B::Error> {
+        if !is_braced(&kind) {
+            return self.subst_parts(kind);
+        }
+
+        match self.substitutions.pop() {
+            Some(subst) => Ok(subst),
+            None => Err(ReplayError::MissingSubstitution(self.current())),
+        }
+    }
+
+    /// Replays the words and commands nested within a substitution.
+    fn subst_parts(
+        &mut self,
+        kind: SubstitutionKind<NodeId>,
+    ) -> ReplayResult<SubstitutionKind<B::Command>, B::Error> {
+        use crate::ast::builder::ParameterSubstitutionKind::*;
+
+        macro_rules! map {
+            ($word:expr) => {
+                $word.map(|w| self.complex_word_kind(w)).transpose()?
+            };
+        }
+
+        let kind = match kind {
+            Command(c) => Command(self.command_group(c)?),
+            ReadFile(w) => ReadFile(self.complex_word_kind(w)?),
+            Len(p) => Len(p),
+            Arith(_) => match self.arithmetic.pop() {
+                Some(a) => Arith(a),
+                None => return Err(ReplayError::MissingSubstitution(self.current())),
+            },
+            Default(c, p, w) => Default(c, p, map!(w)),
+            Assign(c, p, w) => Assign(c, p, map!(w)),
+            Error(c, p, w) => Error(c, p, map!(w)),
+            Alternative(c, p, w) => Alternative(c, p, map!(w)),
+            RemoveSmallestSuffix(p, w) => RemoveSmallestSuffix(p, map!(w)),
+            RemoveLargestSuffix(p, w) => RemoveLargestSuffix(p, map!(w)),
+            RemoveSmallestPrefix(p, w) => RemoveSmallestPrefix(p, map!(w)),
+            RemoveLargestPrefix(p, w) => RemoveLargestPrefix(p, map!(w)),
+            ReplaceString(p, w) => ReplaceString(p, map!(w)),
+            ReplaceStringAll(p, w) => ReplaceStringAll(p, map!(w)),
+            Substring(p, w) => Substring(p, map!(w)),
+            Lowercase(c, p, w) => Lowercase(c, p, map!(w)),
+            Uppercase(c, p, w) => Uppercase(c, p, map!(w)),
+            ProcessRead(c) => ProcessRead(self.command_group(c)?),
+            ProcessWrite(c) => ProcessWrite(self.command_group(c)?),
+        };
+
+        Ok(kind)
+    }
+
+    fn redirect_kind(
+        &mut self,
+        kind: RedirectKind<NodeId>,
+    ) -> ReplayResult<RedirectKind<B::Word>, B::Error> {
+        let kind = match kind {
+            RedirectKind::Read(fd, w) => RedirectKind::Read(fd, self.word(w)?),
+            RedirectKind::Write(fd, w) => RedirectKind::Write(fd, self.word(w)?),
+            RedirectKind::ReadWrite(fd, w) => RedirectKind::ReadWrite(fd, self.word(w)?),
+            RedirectKind::Append(fd, w) => RedirectKind::Append(fd, self.word(w)?),
+            RedirectKind::Clobber(fd, w) => RedirectKind::Clobber(fd, self.word(w)?),
+            RedirectKind::Heredoc(fd, w, info) => RedirectKind::Heredoc(fd, self.word(w)?, info),
+            RedirectKind::DupRead(fd, w) => RedirectKind::DupRead(fd, self.word(w)?),
+            RedirectKind::DupWrite(fd, w) => RedirectKind::DupWrite(fd, self.word(w)?),
+            RedirectKind::WriteBoth(w) => RedirectKind::WriteBoth(self.word(w)?),
+            RedirectKind::AppendBoth(w) => RedirectKind::AppendBoth(self.word(w)?),
+        };
+
+        Ok(kind)
+    }
+
+    /// Substitutions are visited in order, so reverse the expressions and
+    /// substitutions of a word, leaving the first ones at the top of the stacks.
+    fn prepare_pending(&mut self, kind: &ComplexWordKind<NodeId>) {
+        let (arithmetic, substitutions) = pending_count(kind);
+        let first = self.arithmetic.len().saturating_sub(arithmetic);
+        self.arithmetic[first..].reverse();
+        let first = self.substitutions.len().saturating_sub(substitutions);
+        self.substitutions[first..].reverse();
+    }
+
+    /// Replays a single event into the target builder, returning the node it built, if any.
+    fn replay(&mut self, event: Event) -> ReplayResult<Option<Node<B>>, B::Error> {
+        let node = match event {
+            Event::CompleteCommand {
+                pre_cmd_comments,
+                list,
+                separator,
+                cmd_comment,
+                span,
+            } => {
+                let list = self.command_list(list)?;
+                Node::Command(self.builder.complete_command(
+                    pre_cmd_comments,
+                    list,
+                    separator,
+                    cmd_comment,
+                    span,
+                )?)
+            }
+
+            Event::AndOrList { first, rest, span } => {
+                let first = self.listable_command(first)?;
+                let rest = rest
+                    .into_iter()
+                    .map(|(comments, and_or)| {
+                        let and_or = match and_or {
+                            AndOr::And(id) => AndOr::And(self.listable_command(id)?),
+                            AndOr::Or(id) => AndOr::Or(self.listable_command(id)?),
+                        };
+                        Ok((comments, and_or))
+                    })
+                    .collect::<Result<_, ReplayError<_>>>()?;
+
+                Node::CommandList(self.builder.and_or_list(first, rest, span)?)
+            }
+
+            Event::Pipeline { bang, cmds, span } => {
+                let cmds = cmds
+                    .into_iter()
+                    .map(|(comments, segment)| {
+                        let segment = PipeSegment {
+                            pipe: segment.pipe,
+                            pipe_stderr: segment.pipe_stderr,
+                            cmd: self.pipeable_command(segment.cmd)?,
+                        };
+                        Ok((comments, segment))
+                    })
+                    .collect::<Result<_, ReplayError<_>>>()?;
+
+                Node::ListableCommand(self.builder.pipeline(bang, cmds, span)?)
+            }
+
+            Event::SimpleCommand {
+                redirects_or_env_vars,
+                redirects_or_cmd_words,
+                span,
+            } => {
+                let redirects_or_env_vars = redirects_or_env_vars
+                    .into_iter()
+                    .map(|r| match r {
+                        RedirectOrEnvVar::Redirect(id) => {
+                            Ok(RedirectOrEnvVar::Redirect(self.redirect(id)?))
+                        }
+                        RedirectOrEnvVar::EnvVar(name, value) => {
+                            let value = value.map(|id| self.word(id)).transpose()?;
+                            Ok(RedirectOrEnvVar::EnvVar(name, value))
+                        }
+                    })
+                    .collect::<Result<_, ReplayError<_>>>()?;
+
+                let redirects_or_cmd_words = redirects_or_cmd_words
+                    .into_iter()
+                    .map(|r| match r {
+                        RedirectOrCmdWord::Redirect(id) => {
+                            Ok(RedirectOrCmdWord::Redirect(self.redirect(id)?))
+                        }
+                        RedirectOrCmdWord::CmdWord(id) => {
+                            Ok(RedirectOrCmdWord::CmdWord(self.word(id)?))
+                        }
+                    })
+                    .collect::<Result<_, ReplayError<_>>>()?;
+
+                Node::PipeableCommand(self.builder.simple_command(
+                    redirects_or_env_vars,
+                    redirects_or_cmd_words,
+                    span,
+                )?)
+            }
+
+            Event::BraceGroup {
+                cmds,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let cmds = self.command_group(cmds)?;
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.brace_group(
+                    cmds,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::Subshell {
+                cmds,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let cmds = self.command_group(cmds)?;
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.subshell(
+                    cmds,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::LoopCommand {
+                kind,
+                guard_body_pair,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let guard_body_pair = self.guard_body_pair(guard_body_pair)?;
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.loop_command(
+                    kind,
+                    guard_body_pair,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::IfCommand {
+                fragments,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let fragments = IfFragments {
+                    conditionals: fragments
+                        .conditionals
+                        .into_iter()
+                        .map(|gbp| self.guard_body_pair(gbp))
+                        .collect::<Result<_, _>>()?,
+                    else_branch: fragments
+                        .else_branch
+                        .map(|els| self.command_group(els))
+                        .transpose()?,
+                };
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.if_command(
+                    fragments,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::ForCommand {
+                fragments,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let words = match fragments.words {
+                    Some((comments, words, comment)) => {
+                        let words = words
+                            .into_iter()
+                            .map(|id| self.word(id))
+                            .collect::<Result<_, _>>()?;
+                        Some((comments, words, comment))
+                    }
+                    None => None,
+                };
+
+                let fragments = ForFragments {
+                    var: fragments.var,
+                    var_comment: fragments.var_comment,
+                    words,
+                    pre_body_comments: fragments.pre_body_comments,
+                    body: self.command_group(fragments.body)?,
+                };
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.for_command(
+                    fragments,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::CaseCommand {
+                fragments,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let word = self.word(fragments.word)?;
+                let arms = fragments
+                    .arms
+                    .into_iter()
+                    .map(|arm| {
+                        Ok(CaseArm {
+                            patterns: CasePatternFragments {
+                                pre_pattern_comments: arm.patterns.pre_pattern_comments,
+                                pattern_alternatives: arm
+                                    .patterns
+                                    .pattern_alternatives
+                                    .into_iter()
+                                    .map(|id| self.word(id))
+                                    .collect::<Result<_, _>>()?,
+                                pattern_comment: arm.patterns.pattern_comment,
+                            },
+                            body: self.command_group(arm.body)?,
+                            terminator: arm.terminator,
+                            arm_comment: arm.arm_comment,
+                        })
+                    })
+                    .collect::<Result<_, ReplayError<_>>>()?;
+
+                let fragments = CaseFragments {
+                    word,
+                    post_word_comments: fragments.post_word_comments,
+                    in_comment: fragments.in_comment,
+                    arms,
+                    post_arms_comments: fragments.post_arms_comments,
+                };
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.case_command(
+                    fragments,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::ConditionalCommand {
+                expr,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let expr = expr.try_map(&mut |id| self.word(id))?;
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.conditional_command(
+                    expr,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::ArithmeticCommand {
+                expr,
+                redirects,
+                redirects_span,
+                span,
+            } => {
+                let redirects = self.redirects(redirects)?;
+                Node::CompoundCommand(self.builder.arithmetic_command(
+                    expr,
+                    redirects,
+                    redirects_span,
+                    span,
+                )?)
+            }
+
+            Event::CompoundCommandIntoPipeable { cmd } => {
+                let cmd = self.compound_command(cmd)?;
+                Node::PipeableCommand(self.builder.compound_command_into_pipeable(cmd)?)
+            }
+
+            Event::FunctionDeclaration {
+                name,
+                syntax,
+                post_name_comments,
+                body,
+                span,
+            } => {
+                let body = self.compound_command(body)?;
+                Node::PipeableCommand(self.builder.function_declaration(
+                    name,
+                    syntax,
+                    post_name_comments,
+                    body,
+                    span,
+                )?)
+            }
+
+            Event::Comments { comments } => {
+                self.builder.comments(comments)?;
+                return Ok(None);
+            }
+
+            Event::Unparsed {
+                pre_cmd_comments,
+                span,
+            } => Node::Command(self.builder.unparsed(pre_cmd_comments, span)?),
+
+            Event::Arithmetic { expr, span } => {
+                let expr = self.builder.arithmetic(expr, span)?;
+                self.arithmetic.push(expr);
+                return Ok(None);
+            }
+
+            Event::ParameterSubstitution { kind, span } => {
+                if let Some(word) = subst_word(&kind) {
+                    self.prepare_pending(word);
+                }
+                let kind = self.subst_parts(kind)?;
+                let kind = self.builder.parameter_substitution(kind, span)?;
+                self.substitutions.push(kind);
+                return Ok(None);
+            }
+
+            Event::Word { kind, span } => {
+                self.prepare_pending(&kind);
+                let kind = self.complex_word_kind(kind)?;
+                Node::Word(self.builder.word(kind, span)?)
+            }
+
+            Event::Redirect { kind, span } => {
+                let kind = self.redirect_kind(kind)?;
+                Node::Redirect(self.builder.redirect(kind, span)?)
+            }
+
+            Event::EnterContext { kind } => {
+                self.builder.enter_context(kind)?;
+                return Ok(None);
+            }
+
+            Event::ExitContext { kind } => {
+                self.builder.exit_context(kind)?;
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(node))
+    }
+}
+
+/// Checks if a substitution was written as `${...}` and was thus passed
+/// to `Builder::parameter_substitution` by the parser.
+fn is_braced(kind: &SubstitutionKind<NodeId>) -> bool {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    !matches!(
+        *kind,
+        Command(_) | ReadFile(_) | Arith(_) | ProcessRead(_) | ProcessWrite(_)
+    )
+}
+
+/// Returns the word nested within a substitution, if any.
+fn subst_word(kind: &SubstitutionKind<NodeId>) -> Option<&ComplexWordKind<NodeId>> {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    match *kind {
+        Command(_) | Len(_) | Arith(_) | ProcessRead(_) | ProcessWrite(_) => None,
+        ReadFile(ref w) => Some(w),
+        Default(_, _, ref w)
+        | Assign(_, _, ref w)
+        | Error(_, _, ref w)
+        | Alternative(_, _, ref w)
+        | Lowercase(_, _, ref w)
+        | Uppercase(_, _, ref w)
+        | RemoveSmallestSuffix(_, ref w)
+        | RemoveLargestSuffix(_, ref w)
+        | RemoveSmallestPrefix(_, ref w)
+        | RemoveLargestPrefix(_, ref w)
+        | ReplaceString(_, ref w)
+        | ReplaceStringAll(_, ref w)
+        | Substring(_, ref w) => w.as_ref(),
+    }
+}
+
+/// Counts the arithmetic and `${...}` substitutions within a word, excluding
+/// any within command substitutions (which are words of their own) or nested
+/// within `${...}` substitutions (which are replayed as events of their own).
+fn pending_count(kind: &ComplexWordKind<NodeId>) -> (usize, usize) {
+    use crate::ast::builder::ParameterSubstitutionKind::*;
+
+    fn simple(kind: &SimpleWordKind<NodeId>) -> (usize, usize) {
+        match *kind {
+            SimpleWordKind::Subst(ref subst) => match **subst {
+                Arith(_) => (1, 0),
+                ReadFile(ref w) => pending_count(w),
+                ref subst if is_braced(subst) => (0, 1),
+                _ => (0, 0),
+            },
+            _ => (0, 0),
+        }
+    }
+
+    fn word(kind: &WordKind<NodeId>) -> (usize, usize) {
+        match *kind {
+            WordKind::Simple(ref s) => simple(s),
+            WordKind::SingleQuoted(_) => (0, 0),
+            WordKind::DoubleQuoted(ref v) => v.iter().map(simple).fold((0, 0), add),
+            WordKind::Braces(ref b) => b.words().map(word).fold((0, 0), add),
+        }
+    }
+
+    fn add(a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
+        (a.0 + b.0, a.1 + b.1)
+    }
+
+    match *kind {
+        ComplexWordKind::Single(ref w) => word(w),
+        ComplexWordKind::Concat(ref ws) => ws.iter().map(word).fold((0, 0), add),
+    }
+}
@@ -0,0 +1,437 @@
+use crate::ast::builder::*;
+use crate::ast::{AndOr, Conditional, DefaultArithmetic, PipeSegment, Span, UnparsedSpan};
+use crate::ast::{RedirectOrCmdWord, RedirectOrEnvVar};
+use crate::parse::SourcePos;
+
+/// The kind of an entry in a script's outline.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum OutlineKind {
+    /// A function declaration, named after the function.
+    Function,
+    /// A top-level brace group, e.g. `{ ...; }`.
+    BraceGroup,
+    /// A top-level subshell, e.g. `( ... )`.
+    Subshell,
+    /// A top-level `while` loop.
+    While,
+    /// A top-level `until` loop.
+    Until,
+    /// A top-level `for` loop, named after its variable.
+    For,
+    /// A top-level `if` command.
+    If,
+    /// A top-level `case` command.
+    Case,
+    /// A heredoc, named after its delimiter.
+    Heredoc,
+}
+
+/// An entry in a script's outline, e.g. for editor breadcrumbs or a list of document symbols.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutlineItem {
+    /// What kind of construct the entry represents.
+    pub kind: OutlineKind,
+    /// The name of a function, the variable of a `for` loop, the delimiter of
+    /// a heredoc, or the reserved word which introduces any other command.
+    pub name: String,
+    /// The region of the source covered by the construct. For heredocs this
+    /// only includes the redirect itself, and not the body.
+    pub span: Span,
+    /// The entries nested within this one, in source order.
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    fn contains(&self, pos: SourcePos) -> bool {
+        self.span.start.byte <= pos.byte && pos.byte < self.span.end.byte
+    }
+
+    /// Returns the path of (possibly nested) entries which enclose a position,
+    /// starting with the outermost one.
+    pub fn path_to(items: &[OutlineItem], pos: SourcePos) -> Vec<&OutlineItem> {
+        let mut path = Vec::new();
+        let mut items = items;
+        while let Some(item) = items.iter().find(|item| item.contains(pos)) {
+            path.push(item);
+            items = &item.children;
+        }
+        path
+    }
+}
+
+/// A `Builder` adapter which records an outline of the script parsed with
+/// another builder.
+///
+/// The outline holds every function declaration, every compound command which
+/// is not nested within another command, and every heredoc, along with their
+/// spans. Compound commands nested within a function declaration or another
+/// compound command are omitted, though any entries within them are kept as
+/// children of the closest enclosing entry.
+///
+/// ```
+/// use conch_parser::ast::builder::{DefaultBuilder, OutlineBuilder, OutlineKind};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let src = "main() {\n  if true; then cat <<EOF; fi\nhello\nEOF\n}\nfor x in a b; do echo $x; done";
+/// let mut builder = OutlineBuilder::new(DefaultBuilder::<String>::new());
+/// Parser::with_builder(Lexer::new(src.chars()), &mut builder)
+///     .parse_all()
+///     .unwrap();
+///
+/// let outline = builder.outline();
+/// assert_eq!(outline.len(), 2);
+/// assert_eq!(outline[0].kind, OutlineKind::Function);
+/// assert_eq!(outline[0].name, "main");
+/// assert_eq!(outline[0].children[0].kind, OutlineKind::Heredoc);
+/// assert_eq!(outline[0].children[0].name, "EOF");
+/// assert_eq!(outline[1].kind, OutlineKind::For);
+/// assert_eq!(outline[1].name, "x");
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutlineBuilder<B> {
+    builder: B,
+    items: Vec<OutlineItem>,
+}
+
+impl<B> OutlineBuilder<B> {
+    /// Wraps a builder, starting with an empty outline.
+    pub fn new(builder: B) -> Self {
+        OutlineBuilder {
+            builder,
+            items: Vec::new(),
+        }
+    }
+
+    /// Returns the outline of all commands parsed so far.
+    pub fn outline(&self) -> &[OutlineItem] {
+        &self.items
+    }
+
+    /// Takes the outline of all commands parsed so far, leaving it empty.
+    pub fn take_outline(&mut self) -> Vec<OutlineItem> {
+        ::std::mem::take(&mut self.items)
+    }
+
+    /// Returns a reference to the wrapped builder.
+    pub fn get_ref(&self) -> &B {
+        &self.builder
+    }
+
+    /// Returns a mutable reference to the wrapped builder.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+
+    /// Unwraps the wrapped builder, discarding the outline.
+    pub fn into_inner(self) -> B {
+        self.builder
+    }
+
+    /// Removes all entries which start at or after `start` and replaces any
+    /// compound commands among them with their own children.
+    ///
+    /// Commands are always built after everything nested within them, so any
+    /// such entries are always at the end of the list.
+    fn take_nested(&mut self, start: SourcePos) -> Vec<OutlineItem> {
+        let first = self
+            .items
+            .iter()
+            .rposition(|item| item.span.start.byte < start.byte)
+            .map_or(0, |i| i + 1);
+
+        let mut nested = Vec::new();
+        for item in self.items.drain(first..) {
+            match item.kind {
+                OutlineKind::Function | OutlineKind::Heredoc => nested.push(item),
+                _ => nested.extend(item.children),
+            }
+        }
+        nested
+    }
+
+    fn push(&mut self, kind: OutlineKind, name: String, span: Span) {
+        let children = self.take_nested(span.start);
+        self.items.push(OutlineItem {
+            kind,
+            name,
+            span,
+            children,
+        });
+    }
+
+    /// Drops any compound commands nested within a command which is not an outline entry.
+    fn flatten(&mut self, span: Span) {
+        let nested = self.take_nested(span.start);
+        self.items.extend(nested);
+    }
+}
+
+impl<B: Builder> Builder for OutlineBuilder<B> {
+    type Command = B::Command;
+    type CommandList = B::CommandList;
+    type ListableCommand = B::ListableCommand;
+    type PipeableCommand = B::PipeableCommand;
+    type CompoundCommand = B::CompoundCommand;
+    type Word = B::Word;
+    type Redirect = B::Redirect;
+    type Error = B::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.builder
+            .complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.builder.and_or_list(first, rest, span)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.builder.pipeline(bang, cmds, span)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let cmd =
+            self.builder
+                .simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)?;
+        self.flatten(span);
+        Ok(cmd)
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .brace_group(cmds, redirects, redirects_span, span)?;
+        self.push(OutlineKind::BraceGroup, String::from("{"), span);
+        Ok(cmd)
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .subshell(cmds, redirects, redirects_span, span)?;
+        self.push(OutlineKind::Subshell, String::from("("), span);
+        Ok(cmd)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd =
+            self.builder
+                .loop_command(kind, guard_body_pair, redirects, redirects_span, span)?;
+        match kind {
+            LoopKind::While => self.push(OutlineKind::While, String::from("while"), span),
+            LoopKind::Until => self.push(OutlineKind::Until, String::from("until"), span),
+        }
+        Ok(cmd)
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .if_command(fragments, redirects, redirects_span, span)?;
+        self.push(OutlineKind::If, String::from("if"), span);
+        Ok(cmd)
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let var = fragments.var.clone();
+        let cmd = self
+            .builder
+            .for_command(fragments, redirects, redirects_span, span)?;
+        self.push(OutlineKind::For, var, span);
+        Ok(cmd)
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .case_command(fragments, redirects, redirects_span, span)?;
+        self.push(OutlineKind::Case, String::from("case"), span);
+        Ok(cmd)
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .conditional_command(expr, redirects, redirects_span, span)?;
+        self.flatten(span);
+        Ok(cmd)
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let cmd = self
+            .builder
+            .arithmetic_command(expr, redirects, redirects_span, span)?;
+        self.flatten(span);
+        Ok(cmd)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.builder.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        let cmd = self.builder.function_declaration(
+            name.clone(),
+            syntax,
+            post_name_comments,
+            body,
+            span,
+        )?;
+        self.push(OutlineKind::Function, name, span);
+        Ok(cmd)
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.builder.comments(comments)
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        // Drop any entries built before the parser gave up on the region
+        let first = self
+            .items
+            .iter()
+            .rposition(|item| item.span.start.byte < span.start.byte)
+            .map_or(0, |i| i + 1);
+        self.items.truncate(first);
+
+        self.builder.unparsed(pre_cmd_comments, span)
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.builder.arithmetic(expr, span)
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.builder.parameter_substitution(kind, span)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.builder.word(kind, span)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        let delimiter = match kind {
+            RedirectKind::Heredoc(_, _, ref info) => Some(info.delimiter.clone()),
+            _ => None,
+        };
+
+        let redirect = self.builder.redirect(kind, span)?;
+        if let Some(delimiter) = delimiter {
+            self.push(OutlineKind::Heredoc, delimiter, span);
+        }
+        Ok(redirect)
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.enter_context(kind)
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.builder.exit_context(kind)
+    }
+}
@@ -0,0 +1,545 @@
+use crate::ast::builder::*;
+use crate::ast::{
+    AndOr, Conditional, DefaultArithmetic, PipeSegment, RedirectOrCmdWord, RedirectOrEnvVar, Span,
+    UnparsedSpan,
+};
+use std::cmp::Reverse;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A `Builder` callback whose time is measured by a `ProfilingBuilder`.
+///
+/// Each variant corresponds to the identically named `Builder` method.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[allow(missing_docs)]
+pub enum Callback {
+    CompleteCommand,
+    AndOrList,
+    Pipeline,
+    SimpleCommand,
+    BraceGroup,
+    Subshell,
+    LoopCommand,
+    IfCommand,
+    ForCommand,
+    CaseCommand,
+    ConditionalCommand,
+    ArithmeticCommand,
+    CompoundCommandIntoPipeable,
+    FunctionDeclaration,
+    Comments,
+    Unparsed,
+    Arithmetic,
+    ParameterSubstitution,
+    Word,
+    Redirect,
+    EnterContext,
+    ExitContext,
+}
+
+impl Callback {
+    /// Every callback, in the order they are declared by the `Builder` trait.
+    pub const ALL: [Callback; 22] = [
+        Callback::CompleteCommand,
+        Callback::AndOrList,
+        Callback::Pipeline,
+        Callback::SimpleCommand,
+        Callback::BraceGroup,
+        Callback::Subshell,
+        Callback::LoopCommand,
+        Callback::IfCommand,
+        Callback::ForCommand,
+        Callback::CaseCommand,
+        Callback::ConditionalCommand,
+        Callback::ArithmeticCommand,
+        Callback::CompoundCommandIntoPipeable,
+        Callback::FunctionDeclaration,
+        Callback::Comments,
+        Callback::Unparsed,
+        Callback::Arithmetic,
+        Callback::ParameterSubstitution,
+        Callback::Word,
+        Callback::Redirect,
+        Callback::EnterContext,
+        Callback::ExitContext,
+    ];
+
+    /// Returns the name of the corresponding `Builder` method, e.g. `simple_command`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Callback::CompleteCommand => "complete_command",
+            Callback::AndOrList => "and_or_list",
+            Callback::Pipeline => "pipeline",
+            Callback::SimpleCommand => "simple_command",
+            Callback::BraceGroup => "brace_group",
+            Callback::Subshell => "subshell",
+            Callback::LoopCommand => "loop_command",
+            Callback::IfCommand => "if_command",
+            Callback::ForCommand => "for_command",
+            Callback::CaseCommand => "case_command",
+            Callback::ConditionalCommand => "conditional_command",
+            Callback::ArithmeticCommand => "arithmetic_command",
+            Callback::CompoundCommandIntoPipeable => "compound_command_into_pipeable",
+            Callback::FunctionDeclaration => "function_declaration",
+            Callback::Comments => "comments",
+            Callback::Unparsed => "unparsed",
+            Callback::Arithmetic => "arithmetic",
+            Callback::ParameterSubstitution => "parameter_substitution",
+            Callback::Word => "word",
+            Callback::Redirect => "redirect",
+            Callback::EnterContext => "enter_context",
+            Callback::ExitContext => "exit_context",
+        }
+    }
+}
+
+impl fmt::Display for Callback {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.name())
+    }
+}
+
+/// The aggregate time spent within a single kind of callback.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CallbackStats {
+    /// The number of times the callback was invoked.
+    pub calls: usize,
+    /// The total time spent within the callback.
+    pub total: Duration,
+    /// The longest time spent within a single invocation of the callback.
+    pub max: Duration,
+}
+
+/// A single timed invocation of a callback, see `ProfilingBuilder::timeline`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CallbackTiming {
+    /// The callback which was invoked.
+    pub callback: Callback,
+    /// When the callback was invoked, relative to when profiling started.
+    pub start: Duration,
+    /// The time spent within the callback.
+    pub elapsed: Duration,
+}
+
+/// A breakdown of the time spent parsing versus within the callbacks of
+/// the wrapped builder, see `ProfilingBuilder::profile`.
+///
+/// Displays as a report listing the time spent by the parser and the builder,
+/// followed by a line for each callback which was invoked, slowest first.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BuilderProfile {
+    /// The time from when profiling started until the most recent callback returned.
+    pub elapsed: Duration,
+    /// The total time spent within the callbacks of the wrapped builder.
+    pub builder: Duration,
+    /// The statistics of every callback which was invoked at least once,
+    /// slowest (by total time) first.
+    pub callbacks: Vec<(Callback, CallbackStats)>,
+}
+
+impl BuilderProfile {
+    /// Returns the time spent outside of the wrapped builder, i.e. lexing and parsing.
+    pub fn parser(&self) -> Duration {
+        self.elapsed.checked_sub(self.builder).unwrap_or_default()
+    }
+
+    /// Returns the statistics of a callback, if it was invoked.
+    pub fn callback(&self, callback: Callback) -> Option<&CallbackStats> {
+        self.callbacks
+            .iter()
+            .find(|&&(c, _)| c == callback)
+            .map(|(_, stats)| stats)
+    }
+}
+
+impl fmt::Display for BuilderProfile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |d: Duration| {
+            if self.elapsed == Duration::default() {
+                0.0
+            } else {
+                d.as_secs_f64() * 100.0 / self.elapsed.as_secs_f64()
+            }
+        };
+
+        write!(fmt, "total:   {:?}", self.elapsed)?;
+        let parser = self.parser();
+        write!(fmt, "\nparser:  {:?} ({:.1}%)", parser, percent(parser))?;
+        let builder = self.builder;
+        write!(fmt, "\nbuilder: {:?} ({:.1}%)", builder, percent(builder))?;
+
+        for &(callback, ref stats) in &self.callbacks {
+            write!(
+                fmt,
+                "\n  {}: {:?} ({:.1}%) over {} calls, at most {:?}",
+                callback,
+                stats.total,
+                percent(stats.total),
+                stats.calls,
+                stats.max
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A `Builder` adapter which measures the time spent within each callback
+/// of another builder, versus the time spent lexing and parsing.
+///
+/// Profiling starts when the adapter is created (or reset), thus it should
+/// be created right before parsing begins. Everything which happens between
+/// the callbacks of the wrapped builder is attributed to the parser.
+///
+/// When created via `with_timeline` the adapter also records the time of every
+/// callback in the order they were invoked, which corresponds one-to-one with
+/// the events recorded by an `EventLog` if one is wrapped.
+///
+/// ```
+/// use conch_parser::ast::builder::{Callback, ProfilingBuilder, StringBuilder};
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::Parser;
+///
+/// let mut builder = ProfilingBuilder::new(StringBuilder::new());
+/// let lexer = Lexer::new("echo foo; echo bar".chars());
+/// let cmds = Parser::with_builder(lexer, &mut builder)
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// let profile = builder.profile();
+/// assert_eq!(cmds.len(), 2);
+/// assert_eq!(profile.callback(Callback::SimpleCommand).unwrap().calls, 2);
+/// assert!(profile.builder <= profile.elapsed);
+/// println!("{}", profile);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProfilingBuilder<B> {
+    builder: B,
+    started: Instant,
+    elapsed: Duration,
+    stats: [CallbackStats; Callback::ALL.len()],
+    timeline: Option<Vec<CallbackTiming>>,
+}
+
+impl<B> ProfilingBuilder<B> {
+    /// Wraps a builder, aggregating the time spent within each of its callbacks.
+    pub fn new(builder: B) -> Self {
+        ProfilingBuilder {
+            builder,
+            started: Instant::now(),
+            elapsed: Duration::default(),
+            stats: Default::default(),
+            timeline: None,
+        }
+    }
+
+    /// Wraps a builder, aggregating the time spent within each of its callbacks
+    /// as well as recording the timing of every individual invocation.
+    pub fn with_timeline(builder: B) -> Self {
+        ProfilingBuilder {
+            timeline: Some(Vec::new()),
+            ..Self::new(builder)
+        }
+    }
+
+    /// Returns a breakdown of the time spent so far.
+    pub fn profile(&self) -> BuilderProfile {
+        let mut callbacks = Callback::ALL
+            .iter()
+            .zip(&self.stats)
+            .filter(|&(_, stats)| stats.calls > 0)
+            .map(|(&callback, &stats)| (callback, stats))
+            .collect::<Vec<_>>();
+        callbacks.sort_by_key(|&(_, stats)| Reverse(stats.total));
+
+        BuilderProfile {
+            elapsed: self.elapsed,
+            builder: self.stats.iter().map(|stats| stats.total).sum(),
+            callbacks,
+        }
+    }
+
+    /// Returns the timing of every callback invoked so far, in the order they
+    /// were invoked, or an empty slice if the adapter was not created via `with_timeline`.
+    pub fn timeline(&self) -> &[CallbackTiming] {
+        self.timeline.as_ref().map_or(&[], |timeline| &timeline[..])
+    }
+
+    /// Discards everything measured so far and restarts profiling from now.
+    pub fn reset(&mut self) {
+        self.started = Instant::now();
+        self.elapsed = Duration::default();
+        self.stats = Default::default();
+        if let Some(ref mut timeline) = self.timeline {
+            timeline.clear();
+        }
+    }
+
+    /// Returns a reference to the wrapped builder.
+    pub fn get_ref(&self) -> &B {
+        &self.builder
+    }
+
+    /// Returns a mutable reference to the wrapped builder.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+
+    /// Unwraps the wrapped builder, discarding any measurements.
+    pub fn into_inner(self) -> B {
+        self.builder
+    }
+
+    fn time<T, F>(&mut self, callback: Callback, f: F) -> T
+    where
+        F: FnOnce(&mut B) -> T,
+    {
+        let start = Instant::now();
+        let ret = f(&mut self.builder);
+        let end = Instant::now();
+
+        let elapsed = end - start;
+        let stats = &mut self.stats[callback as usize];
+        stats.calls += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+
+        if let Some(ref mut timeline) = self.timeline {
+            timeline.push(CallbackTiming {
+                callback,
+                start: start - self.started,
+                elapsed,
+            });
+        }
+
+        self.elapsed = end - self.started;
+        ret
+    }
+}
+
+impl<B: Builder> Builder for ProfilingBuilder<B> {
+    type Command = B::Command;
+    type CommandList = B::CommandList;
+    type ListableCommand = B::ListableCommand;
+    type PipeableCommand = B::PipeableCommand;
+    type CompoundCommand = B::CompoundCommand;
+    type Word = B::Word;
+    type Redirect = B::Redirect;
+    type Error = B::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.time(Callback::CompleteCommand, |b| {
+            b.complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
+        })
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.time(Callback::AndOrList, |b| b.and_or_list(first, rest, span))
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.time(Callback::Pipeline, |b| b.pipeline(bang, cmds, span))
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.time(Callback::SimpleCommand, |b| {
+            b.simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
+        })
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::BraceGroup, |b| {
+            b.brace_group(cmds, redirects, redirects_span, span)
+        })
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::Subshell, |b| {
+            b.subshell(cmds, redirects, redirects_span, span)
+        })
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::LoopCommand, |b| {
+            b.loop_command(kind, guard_body_pair, redirects, redirects_span, span)
+        })
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::IfCommand, |b| {
+            b.if_command(fragments, redirects, redirects_span, span)
+        })
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::ForCommand, |b| {
+            b.for_command(fragments, redirects, redirects_span, span)
+        })
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::CaseCommand, |b| {
+            b.case_command(fragments, redirects, redirects_span, span)
+        })
+    }
+
+    fn conditional_command(
+        &mut self,
+        expr: Conditional<Self::Word>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::ConditionalCommand, |b| {
+            b.conditional_command(expr, redirects, redirects_span, span)
+        })
+    }
+
+    fn arithmetic_command(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.time(Callback::ArithmeticCommand, |b| {
+            b.arithmetic_command(expr, redirects, redirects_span, span)
+        })
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.time(Callback::CompoundCommandIntoPipeable, |b| {
+            b.compound_command_into_pipeable(cmd)
+        })
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.time(Callback::FunctionDeclaration, |b| {
+            b.function_declaration(name, syntax, post_name_comments, body, span)
+        })
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.time(Callback::Comments, |b| b.comments(comments))
+    }
+
+    fn unparsed(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        span: UnparsedSpan,
+    ) -> Result<Self::Command, Self::Error> {
+        self.time(Callback::Unparsed, |b| b.unparsed(pre_cmd_comments, span))
+    }
+
+    fn arithmetic(
+        &mut self,
+        expr: Option<DefaultArithmetic>,
+        span: Span,
+    ) -> Result<Option<DefaultArithmetic>, Self::Error> {
+        self.time(Callback::Arithmetic, |b| b.arithmetic(expr, span))
+    }
+
+    fn parameter_substitution(
+        &mut self,
+        kind: SubstitutionKind<Self::Command>,
+        span: Span,
+    ) -> Result<SubstitutionKind<Self::Command>, Self::Error> {
+        self.time(Callback::ParameterSubstitution, |b| {
+            b.parameter_substitution(kind, span)
+        })
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.time(Callback::Word, |b| b.word(kind, span))
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.time(Callback::Redirect, |b| b.redirect(kind, span))
+    }
+
+    fn enter_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.time(Callback::EnterContext, |b| b.enter_context(kind))
+    }
+
+    fn exit_context(&mut self, kind: ContextKind) -> Result<(), Self::Error> {
+        self.time(Callback::ExitContext, |b| b.exit_context(kind))
+    }
+}
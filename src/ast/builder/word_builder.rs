@@ -0,0 +1,190 @@
+use crate::ast::builder::default_builder::compress;
+use crate::ast::builder::*;
+use crate::ast::{Braces, DefaultParameter};
+
+/// An interface for assembling words from their individual parts, for builders
+/// which would rather not convert every `ComplexWordKind` by hand.
+///
+/// Each method corresponds to a part of a word, and returns the builder's own
+/// representation of that part, which `WordBuilder::concat` then joins into a
+/// complete word. Words nested within a word (e.g. the operand of a parameter
+/// substitution) are assembled via the same callbacks, so custom ASTs only need
+/// a single representation of words.
+///
+/// A `Builder` can implement this trait (using its `Builder::Command` as `C`),
+/// and delegate `Builder::word` to `WordBuilder::build_word`. Adjacent literals
+/// are merged before they are handed to the builder, just like the
+/// `DefaultBuilder` does.
+///
+/// ```
+/// use conch_parser::ast::builder::{ComplexWordKind, SimpleWordKind, WordBuilder, WordKind};
+/// use conch_parser::ast::{Braces, DefaultParameter};
+/// use conch_parser::ast::builder::ParameterSubstitutionKind;
+///
+/// // Renders words as (roughly) shell source
+/// struct Render;
+///
+/// impl WordBuilder<()> for Render {
+///     type Part = String;
+///     type Word = String;
+///     type Error = ();
+///
+///     fn literal(&mut self, literal: String) -> Result<String, ()> {
+///         Ok(literal)
+///     }
+///
+///     fn single_quoted(&mut self, contents: String) -> Result<String, ()> {
+///         Ok(format!("'{}'", contents))
+///     }
+///
+///     fn double_quoted(&mut self, parts: Vec<String>) -> Result<String, ()> {
+///         Ok(format!("\"{}\"", parts.concat()))
+///     }
+///
+///     fn param(&mut self, param: DefaultParameter) -> Result<String, ()> {
+///         Ok(param.to_string())
+///     }
+///
+///     fn subst(&mut self, _: ParameterSubstitutionKind<String, ()>) -> Result<String, ()> {
+///         Err(())
+///     }
+///
+///     fn braces(&mut self, _: Braces<String>) -> Result<String, ()> {
+///         Err(())
+///     }
+///
+///     fn concat(&mut self, parts: Vec<String>) -> Result<String, ()> {
+///         Ok(parts.concat())
+///     }
+/// }
+///
+/// let word = ComplexWordKind::Concat(vec![
+///     WordKind::Simple(SimpleWordKind::Literal("foo".into())),
+///     WordKind::Simple(SimpleWordKind::Literal("bar".into())),
+///     WordKind::SingleQuoted("baz".into()),
+///     WordKind::DoubleQuoted(vec![SimpleWordKind::Param(DefaultParameter::Dollar)]),
+/// ]);
+///
+/// assert_eq!(Render.build_word(word), Ok(String::from("foobar'baz'\"$$\"")));
+/// ```
+pub trait WordBuilder<C> {
+    /// The type which represents a part of a word, e.g. a literal or a parameter.
+    type Part;
+    /// The type which represents a complete word.
+    type Word;
+    /// A type for returning custom build errors.
+    type Error;
+
+    /// Invoked for an unquoted (or double quoted) literal, e.g. `foo`.
+    fn literal(&mut self, literal: String) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked for a token which is treated as a literal because it was
+    /// escaped, e.g. the `"` of `\"`. Builds a regular literal by default.
+    fn escaped(&mut self, escaped: String) -> Result<Self::Part, Self::Error> {
+        self.literal(escaped)
+    }
+
+    /// Invoked for the tokens which are only special in some contexts: `*`,
+    /// `?`, `[`, and `]` (useful for handling pattern expansions), and `~` and
+    /// `:` (useful for handling tilde expansions). Builds a regular literal
+    /// of the token by default.
+    fn special(&mut self, token: &'static str) -> Result<Self::Part, Self::Error> {
+        self.literal(String::from(token))
+    }
+
+    /// Invoked for a tilde prefix which is subject to tilde expansion, along
+    /// with the login name (if any) which follows the tilde (see
+    /// `parse::TildeExpansion`). Builds a regular literal of the prefix by default.
+    fn tilde_prefix(&mut self, login: Option<String>) -> Result<Self::Part, Self::Error> {
+        let mut prefix = String::from("~");
+        prefix.extend(login);
+        self.literal(prefix)
+    }
+
+    /// Invoked for the contents of a single quoted segment, e.g. `'foo'`.
+    fn single_quoted(&mut self, contents: String) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked for a double quoted segment, with the parts it is made of, e.g. `"foo $bar"`.
+    fn double_quoted(&mut self, parts: Vec<Self::Part>) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked for the access of a parameter, e.g. `$foo` or `$$`.
+    fn param(&mut self, param: DefaultParameter) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked for a parameter substitution, e.g. `${param:-word}`, whose
+    /// operand (if any) has already been built.
+    fn subst(
+        &mut self,
+        subst: ParameterSubstitutionKind<Self::Word, C>,
+    ) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked for a backquoted command substitution, e.g. \`echo foo\`.
+    /// Builds a `ParameterSubstitutionKind::Command` by default.
+    fn command_subst(&mut self, cmds: CommandGroup<C>) -> Result<Self::Part, Self::Error> {
+        self.subst(ParameterSubstitutionKind::Command(cmds))
+    }
+
+    /// Invoked for a brace expansion, e.g. `{a,b}` or `{1..10}`, with every
+    /// alternative already built.
+    fn braces(&mut self, braces: Braces<Self::Part>) -> Result<Self::Part, Self::Error>;
+
+    /// Invoked with all parts of a word, in order, once they have been built.
+    /// A word always consists of at least one part.
+    fn concat(&mut self, parts: Vec<Self::Part>) -> Result<Self::Word, Self::Error>;
+
+    /// Assembles a word from its parts via the other callbacks.
+    fn build_word(&mut self, kind: ComplexWordKind<C>) -> Result<Self::Word, Self::Error> {
+        let parts = match compress(kind) {
+            ComplexWordKind::Single(word) => vec![build_part(self, word)?],
+            ComplexWordKind::Concat(words) => words
+                .into_iter()
+                .map(|word| build_part(self, word))
+                .collect::<Result<_, _>>()?,
+        };
+
+        self.concat(parts)
+    }
+}
+
+fn build_part<C, B>(builder: &mut B, kind: WordKind<C>) -> Result<B::Part, B::Error>
+where
+    B: WordBuilder<C> + ?Sized,
+{
+    match kind {
+        WordKind::Simple(s) => build_simple(builder, s),
+        WordKind::SingleQuoted(s) => builder.single_quoted(s),
+        WordKind::DoubleQuoted(parts) => {
+            let parts = parts
+                .into_iter()
+                .map(|s| build_simple(builder, s))
+                .collect::<Result<_, _>>()?;
+            builder.double_quoted(parts)
+        }
+        WordKind::Braces(braces) => {
+            let braces = braces.try_map(&mut |w| build_part(builder, w))?;
+            builder.braces(braces)
+        }
+    }
+}
+
+fn build_simple<C, B>(builder: &mut B, kind: SimpleWordKind<C>) -> Result<B::Part, B::Error>
+where
+    B: WordBuilder<C> + ?Sized,
+{
+    match kind {
+        SimpleWordKind::Literal(s) => builder.literal(s),
+        SimpleWordKind::Escaped(s) => builder.escaped(s),
+        SimpleWordKind::Param(p) => builder.param(p),
+        SimpleWordKind::Subst(s) => {
+            let subst = s.try_map(&mut |w| builder.build_word(w))?;
+            builder.subst(subst)
+        }
+        SimpleWordKind::CommandSubst(c) => builder.command_subst(c),
+        SimpleWordKind::Star => builder.special("*"),
+        SimpleWordKind::Question => builder.special("?"),
+        SimpleWordKind::SquareOpen => builder.special("["),
+        SimpleWordKind::SquareClose => builder.special("]"),
+        SimpleWordKind::Tilde => builder.special("~"),
+        SimpleWordKind::Colon => builder.special(":"),
+        SimpleWordKind::TildePrefix(login) => builder.tilde_prefix(login),
+    }
+}
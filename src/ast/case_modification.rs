@@ -0,0 +1,228 @@
+//! Static evaluation of the bash case modification expansions.
+//!
+//! The `${param^pattern}`, `${param^^pattern}`, `${param,pattern}`, and
+//! `${param,,pattern}` substitutions (see `ParameterSubstitution::Uppercase` and
+//! `ParameterSubstitution::Lowercase`) change the case of the characters of a
+//! value which match a pattern. Bash converts each character according to the
+//! `LC_CTYPE` of the running shell, thus a tool which evaluates them on its own
+//! (e.g. to fold a known value into a literal) would otherwise produce output
+//! which depends on the locale of the host it runs on.
+//!
+//! `CaseMapping` makes that choice explicit instead: by default only ASCII
+//! letters are converted (matching bash in the `C` and `POSIX` locales), and
+//! all other characters are left as they are. `CaseMapping::Unicode` converts
+//! every character using the locale-independent mappings of the Unicode
+//! standard, as bash does in a UTF-8 locale, except that a character may be
+//! converted into several characters (e.g. `ß` is uppercased to `SS`).
+//!
+//! The printer is not affected by any of this: it always prints case
+//! modifications exactly as they were parsed, operator and pattern included.
+//!
+//! ```
+//! use conch_parser::ast::case_modification::CaseMapping;
+//! use conch_parser::ast::{DefaultParameterSubstitution, Parameter};
+//!
+//! let upper = DefaultParameterSubstitution::Uppercase(true, Parameter::Var("x".into()), None);
+//! assert_eq!(upper.modify_case("straße", CaseMapping::Ascii).unwrap(), "STRAßE");
+//! assert_eq!(upper.modify_case("straße", CaseMapping::Unicode).unwrap(), "STRASSE");
+//! ```
+
+use crate::ast::{
+    ComplexWord, DefaultParameterSubstitution, DefaultWord, ParameterSubstitution, SimpleWord, Word,
+};
+
+/// How the case of characters is converted by case modification expansions.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaseMapping {
+    /// Only convert ASCII letters, leaving all other characters unchanged.
+    #[default]
+    Ascii,
+    /// Convert any character which has a case, using the full (and possibly
+    /// multi-character) mappings of the Unicode standard.
+    Unicode,
+}
+
+impl CaseMapping {
+    /// Appends the uppercase form of a character to a string.
+    pub fn push_upper(self, c: char, out: &mut String) {
+        match self {
+            CaseMapping::Ascii => out.push(c.to_ascii_uppercase()),
+            CaseMapping::Unicode => out.extend(c.to_uppercase()),
+        }
+    }
+
+    /// Appends the lowercase form of a character to a string.
+    pub fn push_lower(self, c: char, out: &mut String) {
+        match self {
+            CaseMapping::Ascii => out.push(c.to_ascii_lowercase()),
+            CaseMapping::Unicode => out.extend(c.to_lowercase()),
+        }
+    }
+}
+
+impl DefaultParameterSubstitution {
+    /// Applies a case modification substitution to the value of its parameter.
+    ///
+    /// Returns `None` if the substitution is not a case modification, or if its
+    /// pattern cannot be evaluated statically. Only patterns made up of a single
+    /// (possibly quoted) character, `?`, `*`, or a bracket expression (e.g.
+    /// `[a-f]` or `[!xyz]`) of literal characters and ranges are supported.
+    ///
+    /// ```
+    /// use conch_parser::ast::case_modification::CaseMapping;
+    /// use conch_parser::ast::{ComplexWord, SimpleWord, TopLevelWord, Word};
+    /// use conch_parser::parse::{parse_word, Dialect};
+    ///
+    /// let word = parse_word("${x,,[A-C]}", Dialect::Bash).unwrap();
+    /// let subst = match word {
+    ///     TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Subst(s)))) => s,
+    ///     w => panic!("unexpected word: {:?}", w),
+    /// };
+    /// assert_eq!(subst.modify_case("ABCDE", CaseMapping::Ascii).unwrap(), "abcDE");
+    /// ```
+    pub fn modify_case(&self, value: &str, mapping: CaseMapping) -> Option<String> {
+        let (upper, all, pattern) = match *self {
+            ParameterSubstitution::Uppercase(all, _, ref pattern) => (true, all, pattern),
+            ParameterSubstitution::Lowercase(all, _, ref pattern) => (false, all, pattern),
+            _ => return None,
+        };
+
+        let pattern = match *pattern {
+            Some(ref word) => CharPattern::parse(word)?,
+            None => CharPattern::Any,
+        };
+
+        let mut out = String::with_capacity(value.len());
+        for (i, c) in value.chars().enumerate() {
+            if (i > 0 && !all) || !pattern.matches(c) {
+                out.push(c);
+            } else if upper {
+                mapping.push_upper(c, &mut out);
+            } else {
+                mapping.push_lower(c, &mut out);
+            }
+        }
+
+        Some(out)
+    }
+}
+
+/// A pattern which is matched against a single character.
+#[derive(Debug)]
+enum CharPattern {
+    /// Matches any character, e.g. `?` or `*`.
+    Any,
+    /// Matches (or if negated, does not match) any of the inclusive ranges.
+    Ranges(bool, Vec<(char, char)>),
+}
+
+/// A single piece of a pattern, with any quoting removed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Piece {
+    Char(char),
+    Any,
+    SquareOpen,
+    SquareClose,
+}
+
+impl CharPattern {
+    /// Parses a pattern word, if it can be evaluated statically.
+    fn parse(word: &ComplexWord<DefaultWord>) -> Option<Self> {
+        let mut pieces = Vec::new();
+        match *word {
+            ComplexWord::Single(ref w) => pattern_pieces(w, &mut pieces)?,
+            ComplexWord::Concat(ref ws) => {
+                for w in ws {
+                    pattern_pieces(w, &mut pieces)?;
+                }
+            }
+        }
+
+        match *pieces.as_slice() {
+            [Piece::Any] => Some(CharPattern::Any),
+            [Piece::Char(c)] => Some(CharPattern::Ranges(false, vec![(c, c)])),
+            [Piece::SquareOpen, ref inner @ .., Piece::SquareClose] => {
+                let mut chars = Vec::with_capacity(inner.len());
+                for piece in inner {
+                    match *piece {
+                        Piece::Char(c) => chars.push(c),
+                        _ => return None,
+                    }
+                }
+
+                let negated = matches!(chars.first(), Some('!') | Some('^'));
+                let mut rest = &chars[usize::from(negated)..];
+                let mut ranges = Vec::new();
+                while let Some(&start) = rest.first() {
+                    match *rest {
+                        [_, '-', end, ..] => {
+                            ranges.push((start, end));
+                            rest = &rest[3..];
+                        }
+                        _ => {
+                            ranges.push((start, start));
+                            rest = &rest[1..];
+                        }
+                    }
+                }
+
+                if ranges.is_empty() {
+                    return None;
+                }
+
+                Some(CharPattern::Ranges(negated, ranges))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if a character matches the pattern.
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            CharPattern::Any => true,
+            CharPattern::Ranges(negated, ref ranges) => {
+                let found = ranges.iter().any(|&(start, end)| start <= c && c <= end);
+                found != negated
+            }
+        }
+    }
+}
+
+/// Appends the pieces of a pattern word, or returns `None` if the word
+/// contains any expansions which cannot be evaluated statically.
+fn pattern_pieces(word: &DefaultWord, pieces: &mut Vec<Piece>) -> Option<()> {
+    match *word {
+        Word::Simple(ref w) => {
+            let piece = match *w {
+                SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => {
+                    pieces.extend(s.chars().map(Piece::Char));
+                    return Some(());
+                }
+                SimpleWord::Star | SimpleWord::Question => Piece::Any,
+                SimpleWord::SquareOpen => Piece::SquareOpen,
+                SimpleWord::SquareClose => Piece::SquareClose,
+                SimpleWord::Tilde => Piece::Char('~'),
+                SimpleWord::Colon => Piece::Char(':'),
+                SimpleWord::Param(_) | SimpleWord::Subst(_) | SimpleWord::TildePrefix(_) => {
+                    return None
+                }
+            };
+            pieces.push(piece);
+        }
+        Word::SingleQuoted(ref s) => pieces.extend(s.chars().map(Piece::Char)),
+        Word::DoubleQuoted(ref ws) => {
+            for w in ws {
+                match *w {
+                    SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => {
+                        pieces.extend(s.chars().map(Piece::Char))
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        Word::Braces(_) => return None,
+    }
+
+    Some(())
+}
@@ -0,0 +1,1003 @@
+//! Regenerates shell source from the default AST.
+//!
+//! The printed source is valid, runnable shell which parses back into the
+//! same AST it was printed from (apart from the locations of any nodes, and
+//! the raw lines of heredocs whose bodies are printed differently than they
+//! were written), so scripts can be rewritten programmatically by parsing them,
+//! transforming the AST, and printing the result.
+//!
+//! The original formatting of the source is not retained: commands are
+//! printed one per line, the bodies of compound commands are indented by
+//! four spaces, and whitespace around operators is normalized. Redirections
+//! are printed in the same position relative to the words of their command,
+//! with the same operator and file descriptor they were written with, and
+//! function definitions are printed in the syntax they were declared with (e.g.
+//! `function foo { ...; }` or `foo() { ...; }`). Since the default AST does not
+//! retain comments, they are dropped as well (see `builder::CommentedDefaultBuilder`
+//! for an AST which does). Heredoc bodies are printed after the line of the
+//! command they belong to, exactly as they were written (see `HeredocInfo`)
+//! unless the body no longer matches its source, in which case it is printed
+//! verbatim using a delimiter which does not appear within the body.
+//!
+//! Any commands which failed to parse (see `parse::ErrorRecovery`) are
+//! printed exactly as they appeared in the source.
+//!
+//! Arithmetic and conditional expressions can also be printed on their own,
+//! e.g. for generating `$(( ... ))` or `[[ ... ]]` snippets, either via
+//! `ToShellString` (or `Display`) for the bare expression, or with
+//! `arithmetic_command` and `conditional_command` for a complete command.
+//! Either way, parentheses are only added where the precedence of the
+//! operators requires them, and numeric literals are printed exactly as they
+//! were written (e.g. `0XFF` or `10#0042`), or in the base they were written in
+//! if their value has since been changed.
+
+use crate::ast::builder::FunctionSyntax;
+use crate::ast::{
+    AndOr, ArithmeticTarget, Braces, Command, ComplexWord, CompoundCommandKind, Conditional,
+    DefaultAndOrList, DefaultArithmetic, DefaultCompoundCommand, DefaultConditional,
+    DefaultListableCommand, DefaultParameter, DefaultParameterSubstitution, DefaultPipeableCommand,
+    DefaultRedirect, DefaultSimpleCommand, DefaultSimpleWord, DefaultWord, HeredocInfo,
+    ListableCommand, Parameter, PipeableCommand, Radix, Redirect, RedirectOrCmdWord,
+    RedirectOrEnvVar, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use crate::parse::arith_literal;
+use std::fmt;
+use std::mem;
+
+/// The indentation of each nested level of commands.
+const INDENT: &str = "    ";
+
+/// Conversion of an AST node back into shell source.
+pub trait ToShellString {
+    /// Returns shell source which parses back into `self`.
+    fn to_shell_string(&self) -> String;
+}
+
+impl ToShellString for TopLevelCommand<String> {
+    /// Prints the command on a single line, without a trailing newline unless
+    /// it is followed by the bodies of any of its heredocs.
+    fn to_shell_string(&self) -> String {
+        let mut printer = Printer::default();
+        printer.top_level(self);
+        if !printer.heredocs.is_empty() {
+            printer.newline();
+        }
+        printer.out
+    }
+}
+
+impl ToShellString for [TopLevelCommand<String>] {
+    /// Prints each command on its own line.
+    fn to_shell_string(&self) -> String {
+        let mut printer = Printer::default();
+        for cmd in self {
+            printer.top_level(cmd);
+            printer.newline();
+        }
+        printer.out
+    }
+}
+
+impl ToShellString for TopLevelWord<String> {
+    fn to_shell_string(&self) -> String {
+        let mut out = String::new();
+        Printer::default().word(self, &mut out);
+        out
+    }
+}
+
+impl ToShellString for DefaultArithmetic {
+    fn to_shell_string(&self) -> String {
+        let mut out = String::new();
+        arith(self, 0, &mut out);
+        out
+    }
+}
+
+impl ToShellString for DefaultConditional {
+    /// Prints the expression without the surrounding `[[` and `]]`.
+    fn to_shell_string(&self) -> String {
+        let mut out = String::new();
+        Printer::default().conditional(self, 0, &mut out);
+        out
+    }
+}
+
+/// Returns an arithmetic command which evaluates an expression, e.g. `(( x += 1 ))`.
+pub fn arithmetic_command(expr: &DefaultArithmetic) -> String {
+    let mut out = String::from("(( ");
+    arith(expr, 0, &mut out);
+    out.push_str(" ))");
+    out
+}
+
+/// Returns a conditional command which tests an expression, e.g. `[[ -n $x ]]`.
+pub fn conditional_command(cond: &DefaultConditional) -> String {
+    let mut out = String::from("[[ ");
+    Printer::default().conditional(cond, 0, &mut out);
+    out.push_str(" ]]");
+    out
+}
+
+impl fmt::Display for TopLevelCommand<String> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.to_shell_string())
+    }
+}
+
+impl fmt::Display for TopLevelWord<String> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.to_shell_string())
+    }
+}
+
+impl fmt::Display for DefaultArithmetic {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.to_shell_string())
+    }
+}
+
+impl fmt::Display for DefaultConditional {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.to_shell_string())
+    }
+}
+
+/// A heredoc whose body is printed after the current line.
+#[derive(Debug)]
+struct PendingHeredoc {
+    body: String,
+    delim: String,
+}
+
+/// Prints commands into a buffer, one line at a time.
+#[derive(Debug, Default)]
+struct Printer {
+    out: String,
+    /// The current level of indentation.
+    level: usize,
+    /// Heredoc bodies (and their delimiters) which are printed after the current line.
+    heredocs: Vec<PendingHeredoc>,
+    /// Whether case patterns must be opened with a `(`, since the parser
+    /// skips past balanced parentheses when looking for a pending heredoc body.
+    open_patterns: bool,
+}
+
+impl Printer {
+    /// Returns a printer for commands nested within a word at the current level.
+    fn nested(&self) -> Printer {
+        Printer {
+            level: self.level,
+            open_patterns: self.open_patterns || !self.heredocs.is_empty(),
+            ..Printer::default()
+        }
+    }
+
+    /// Ends the current line, followed by any pending heredoc bodies.
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for heredoc in self.heredocs.drain(..) {
+            self.out.push_str(&heredoc.body);
+            self.out.push_str(&heredoc.delim);
+            self.out.push('\n');
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.level {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Prints each command on its own line, one level deeper than the current one.
+    fn body(&mut self, cmds: &[TopLevelCommand<String>]) {
+        self.level += 1;
+        for cmd in cmds {
+            self.indent();
+            self.top_level(cmd);
+            self.newline();
+        }
+        self.level -= 1;
+    }
+
+    /// Prints commands on the current line, each one terminated by a `;` (or
+    /// the `&` of a job). The terminator of the last command is omitted
+    /// unless `trailing` is set.
+    fn inline(&mut self, cmds: &[TopLevelCommand<String>], trailing: bool) {
+        for (i, cmd) in cmds.iter().enumerate() {
+            if i > 0 {
+                self.out.push(' ');
+            }
+            self.top_level(cmd);
+
+            let is_job = matches!(cmd.0, Command::Job(_));
+            if !is_job && (trailing || i + 1 < cmds.len()) {
+                self.out.push(';');
+            }
+        }
+    }
+
+    fn top_level(&mut self, cmd: &TopLevelCommand<String>) {
+        match cmd.0 {
+            Command::List(ref list) => self.and_or(list),
+            Command::Job(ref list) => {
+                self.and_or(list);
+                self.out.push_str(" &");
+            }
+            Command::Unparsed(ref span) => self.out.push_str(span.text.trim_end_matches('\n')),
+        }
+    }
+
+    fn and_or(&mut self, list: &DefaultAndOrList) {
+        self.listable(&list.first);
+        for and_or in &list.rest {
+            let (op, cmd) = match *and_or {
+                AndOr::And(ref cmd) => (" && ", cmd),
+                AndOr::Or(ref cmd) => (" || ", cmd),
+            };
+            self.out.push_str(op);
+            self.listable(cmd);
+        }
+    }
+
+    fn listable(&mut self, cmd: &DefaultListableCommand) {
+        match *cmd {
+            ListableCommand::Single(ref cmd) => self.pipeable(cmd),
+            ListableCommand::Pipe(ref pipeline) => {
+                if pipeline.bang {
+                    self.out.push_str("! ");
+                }
+                for (i, segment) in pipeline.segments.iter().enumerate() {
+                    if i > 0 {
+                        self.out
+                            .push_str(if segment.pipe_stderr { " |& " } else { " | " });
+                    }
+                    self.pipeable(&segment.cmd);
+                }
+            }
+        }
+    }
+
+    fn pipeable(&mut self, cmd: &DefaultPipeableCommand) {
+        match *cmd {
+            PipeableCommand::Simple(ref cmd) => self.simple(cmd),
+            PipeableCommand::Compound(ref cmd) => self.compound(cmd),
+            PipeableCommand::FunctionDef(ref name, syntax, ref body) => {
+                match syntax {
+                    FunctionSyntax::Posix => {
+                        self.out.push_str(name);
+                        self.out.push_str("() ");
+                    }
+                    FunctionSyntax::Keyword => {
+                        self.out.push_str("function ");
+                        self.out.push_str(name);
+                        self.out.push(' ');
+                    }
+                    FunctionSyntax::KeywordParens => {
+                        self.out.push_str("function ");
+                        self.out.push_str(name);
+                        self.out.push_str("() ");
+                    }
+                }
+                self.compound(body);
+            }
+        }
+    }
+
+    fn simple(&mut self, cmd: &DefaultSimpleCommand) {
+        let mut out = String::new();
+        let mut first = true;
+        let mut space = |out: &mut String| {
+            if !first {
+                out.push(' ');
+            }
+            first = false;
+        };
+
+        for item in &cmd.redirects_or_env_vars {
+            space(&mut out);
+            match *item {
+                RedirectOrEnvVar::Redirect(ref redirect) => self.redirect(redirect, &mut out),
+                RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                    out.push_str(name);
+                    out.push('=');
+                    if let Some(ref value) = *value {
+                        self.word(value, &mut out);
+                    }
+                }
+            }
+        }
+
+        for item in &cmd.redirects_or_cmd_words {
+            space(&mut out);
+            match *item {
+                RedirectOrCmdWord::Redirect(ref redirect) => self.redirect(redirect, &mut out),
+                RedirectOrCmdWord::CmdWord(ref word) => self.word(word, &mut out),
+            }
+        }
+
+        self.out.push_str(&out);
+    }
+
+    fn compound(&mut self, cmd: &DefaultCompoundCommand) {
+        match cmd.kind {
+            CompoundCommandKind::Brace(ref cmds) => {
+                self.out.push('{');
+                self.newline();
+                self.body(cmds);
+                self.indent();
+                self.out.push('}');
+            }
+
+            CompoundCommandKind::Subshell(ref cmds) => {
+                // The bodies of any pending heredocs may only start after the
+                // subshell is closed, since the parser skips past its contents.
+                let pending = mem::take(&mut self.heredocs);
+                let open_patterns = self.open_patterns;
+                self.open_patterns |= !pending.is_empty();
+                self.out.push('(');
+                self.newline();
+                self.body(cmds);
+                self.indent();
+                self.out.push(')');
+                self.open_patterns = open_patterns;
+                self.heredocs.splice(0..0, pending);
+            }
+
+            CompoundCommandKind::While(ref pair) | CompoundCommandKind::Until(ref pair) => {
+                let is_while = matches!(cmd.kind, CompoundCommandKind::While(_));
+                self.out
+                    .push_str(if is_while { "while " } else { "until " });
+                self.inline(&pair.guard, true);
+                self.out.push_str(" do");
+                self.newline();
+                self.body(&pair.body);
+                self.indent();
+                self.out.push_str("done");
+            }
+
+            CompoundCommandKind::If {
+                ref conditionals,
+                ref else_branch,
+            } => {
+                for (i, pair) in conditionals.iter().enumerate() {
+                    if i > 0 {
+                        self.indent();
+                    }
+                    self.out.push_str(if i == 0 { "if " } else { "elif " });
+                    self.inline(&pair.guard, true);
+                    self.out.push_str(" then");
+                    self.newline();
+                    self.body(&pair.body);
+                }
+
+                if let Some(ref body) = *else_branch {
+                    self.indent();
+                    self.out.push_str("else");
+                    self.newline();
+                    self.body(body);
+                }
+
+                self.indent();
+                self.out.push_str("fi");
+            }
+
+            CompoundCommandKind::For {
+                ref var,
+                ref words,
+                ref body,
+            } => {
+                self.out.push_str("for ");
+                self.out.push_str(var);
+                if let Some(ref words) = *words {
+                    let mut out = String::from(" in");
+                    for word in words {
+                        out.push(' ');
+                        self.word(word, &mut out);
+                    }
+                    self.out.push_str(&out);
+                }
+                self.out.push_str("; do");
+                self.newline();
+                self.body(body);
+                self.indent();
+                self.out.push_str("done");
+            }
+
+            CompoundCommandKind::Case { ref word, ref arms } => {
+                let mut out = String::from("case ");
+                self.word(word, &mut out);
+                out.push_str(" in");
+                self.out.push_str(&out);
+                self.newline();
+
+                self.level += 1;
+                for arm in arms {
+                    self.indent();
+                    let mut out = String::new();
+                    if self.open_patterns {
+                        out.push('(');
+                    }
+                    for (i, pattern) in arm.patterns.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(" | ");
+                        }
+                        self.word(pattern, &mut out);
+                    }
+                    out.push(')');
+                    self.out.push_str(&out);
+                    self.newline();
+                    self.body(&arm.body);
+                    self.level += 1;
+                    self.indent();
+                    self.out.push_str(arm.terminator.as_str());
+                    self.newline();
+                    self.level -= 1;
+                }
+                self.level -= 1;
+
+                self.indent();
+                self.out.push_str("esac");
+            }
+
+            CompoundCommandKind::Conditional(ref cond) => {
+                let mut out = String::from("[[ ");
+                self.conditional(cond, 0, &mut out);
+                out.push_str(" ]]");
+                self.out.push_str(&out);
+            }
+
+            CompoundCommandKind::Arithmetic(ref expr) => match *expr {
+                Some(ref expr) => self.out.push_str(&arithmetic_command(expr)),
+                None => self.out.push_str("(( ))"),
+            },
+        }
+
+        let mut out = String::new();
+        for redirect in &cmd.io {
+            out.push(' ');
+            self.redirect(redirect, &mut out);
+        }
+        self.out.push_str(&out);
+    }
+
+    /// Prints a conditional expression, adding parentheses only where they
+    /// are required by the precedence of the enclosing operator.
+    fn conditional(&mut self, cond: &DefaultConditional, min_prec: u8, out: &mut String) {
+        let (prec, op, lhs, rhs) = match *cond {
+            Conditional::Word(ref word) => return self.word(word, out),
+            Conditional::Unary(op, ref word) => {
+                out.push('-');
+                out.push(op);
+                out.push(' ');
+                return self.word(word, out);
+            }
+            Conditional::Binary(ref lhs, op, ref rhs) => {
+                self.word(lhs, out);
+                out.push(' ');
+                out.push_str(op.as_str());
+                out.push(' ');
+                return self.word(rhs, out);
+            }
+            Conditional::Not(ref cond) => {
+                out.push_str("! ");
+                return self.conditional(cond, 2, out);
+            }
+            Conditional::Or(ref l, ref r) => (0, "||", l, r),
+            Conditional::And(ref l, ref r) => (1, "&&", l, r),
+        };
+
+        let parens = prec < min_prec;
+        if parens {
+            out.push_str("( ");
+        }
+        self.conditional(lhs, prec, out);
+        out.push(' ');
+        out.push_str(op);
+        out.push(' ');
+        self.conditional(rhs, prec + 1, out);
+        if parens {
+            out.push_str(" )");
+        }
+    }
+
+    fn redirect(&mut self, redirect: &DefaultRedirect, out: &mut String) {
+        if let Some(fd) = redirect.fd() {
+            out.push_str(&fd.to_string());
+        }
+
+        let op = match *redirect {
+            Redirect::Read(..) => "<",
+            Redirect::Write(..) => ">",
+            Redirect::ReadWrite(..) => "<>",
+            Redirect::Append(..) => ">>",
+            Redirect::Clobber(..) => ">|",
+            Redirect::DupRead(..) => "<&",
+            Redirect::DupWrite(..) => ">&",
+            Redirect::WriteBoth(..) => "&>",
+            Redirect::AppendBoth(..) => "&>>",
+            Redirect::Heredoc(_, ref body, ref info) => return self.heredoc(body, info, out),
+        };
+
+        out.push_str(op);
+        let start = out.len();
+        self.word(redirect.word(), out);
+
+        // Avoid a process substitution being mistaken for part of the operator, e.g. `> >(foo)`
+        if out[start..].starts_with(['<', '>']) {
+            out.insert(start, ' ');
+        }
+    }
+
+    /// Prints the start of a heredoc, and defers printing its body until the end of the line.
+    fn heredoc(&mut self, body: &TopLevelWord<String>, info: &HeredocInfo, out: &mut String) {
+        // A body without any expansions (or escapes) is printed with a quoted
+        // delimiter so that it is never expanded, regardless of its contents.
+        let literal = match body.0 {
+            ComplexWord::Single(Word::Simple(SimpleWord::Literal(ref s))) => Some(s),
+            _ => None,
+        };
+
+        let mut text = match literal {
+            Some(s) => s.clone(),
+            None => {
+                let mut text = String::new();
+                self.word(body, &mut text);
+                text
+            }
+        };
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        // Heredocs whose body still matches their source are printed exactly as written
+        let mut stripped = String::new();
+        let mut raw = String::new();
+        for line in &info.lines {
+            let line_stripped = if info.strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            stripped.push_str(line_stripped);
+            stripped.push('\n');
+            raw.push_str(line);
+            raw.push('\n');
+        }
+
+        let delim_printable = if info.quoted {
+            !info.delimiter.contains('\'')
+        } else {
+            !info.delimiter.contains(char::is_whitespace)
+        };
+
+        if !info.delimiter.is_empty() && delim_printable && stripped == text {
+            let delim = info.delimiter.clone();
+            out.push_str(if info.strip_tabs { "<<-" } else { "<<" });
+            if info.quoted {
+                out.push('\'');
+                out.push_str(&delim);
+                out.push('\'');
+            } else {
+                out.push_str(&delim);
+            }
+
+            self.heredocs.push(PendingHeredoc { body: raw, delim });
+            return;
+        }
+
+        // Otherwise pick a delimiter which cannot be confused with a line of the
+        // body, preferring the original one if it is a plain word
+        let is_plain =
+            |s: &str| !s.is_empty() && s.chars().all(|c| c == '_' || c.is_ascii_alphanumeric());
+        let mut delim = if is_plain(&info.delimiter) {
+            info.delimiter.clone()
+        } else {
+            String::from("EOF")
+        };
+        while text.lines().any(|line| line == delim) {
+            delim.push('_');
+        }
+
+        let strip_tabs = info.strip_tabs && !text.lines().any(|line| line.starts_with('\t'));
+        out.push_str(if strip_tabs { "<<-" } else { "<<" });
+        if literal.is_some() {
+            out.push('\'');
+            out.push_str(&delim);
+            out.push('\'');
+        } else {
+            out.push_str(&delim);
+        }
+
+        self.heredocs.push(PendingHeredoc { body: text, delim });
+    }
+
+    fn word(&mut self, word: &TopLevelWord<String>, out: &mut String) {
+        let parts = match word.0 {
+            ComplexWord::Single(ref w) => std::slice::from_ref(w),
+            ComplexWord::Concat(ref words) => &words[..],
+        };
+
+        for (i, part) in parts.iter().enumerate() {
+            self.word_part(part, parts.get(i + 1), out);
+        }
+    }
+
+    fn word_part(&mut self, part: &DefaultWord, next: Option<&DefaultWord>, out: &mut String) {
+        match *part {
+            Word::SingleQuoted(ref s) => {
+                out.push('\'');
+                out.push_str(s);
+                out.push('\'');
+            }
+            Word::DoubleQuoted(ref words) => {
+                out.push('"');
+                for (i, w) in words.iter().enumerate() {
+                    self.simple_word(w, words.get(i + 1), out);
+                }
+                out.push('"');
+            }
+            Word::Simple(ref w) => {
+                let next = next.and_then(|next| match *next {
+                    Word::Simple(ref next) => Some(next),
+                    _ => None,
+                });
+                self.simple_word(w, next, out)
+            }
+            Word::Braces(ref braces) => self.braces(braces, out),
+        }
+    }
+
+    fn braces(&mut self, braces: &Braces<DefaultWord>, out: &mut String) {
+        out.push('{');
+        let step = match *braces {
+            Braces::Alternatives(ref alternatives) => {
+                for (i, words) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    for (j, part) in words.iter().enumerate() {
+                        self.word_part(part, words.get(j + 1), out);
+                    }
+                }
+                None
+            }
+            Braces::Numbers {
+                start,
+                end,
+                step,
+                width,
+            } => {
+                out.push_str(&format!("{:0w$}..{:0w$}", start, end, w = width));
+                step
+            }
+            Braces::Chars { start, end, step } => {
+                out.push(start);
+                out.push_str("..");
+                out.push(end);
+                step
+            }
+        };
+
+        if let Some(step) = step {
+            out.push_str("..");
+            out.push_str(&step.to_string());
+        }
+        out.push('}');
+    }
+
+    /// Prints part of a word. The part which follows it is needed to know
+    /// whether a variable name must be wrapped in braces to delimit it.
+    fn simple_word(
+        &mut self,
+        word: &DefaultSimpleWord,
+        next: Option<&DefaultSimpleWord>,
+        out: &mut String,
+    ) {
+        match *word {
+            SimpleWord::Literal(ref s) => out.push_str(s),
+            SimpleWord::Escaped(ref s) => {
+                out.push('\\');
+                out.push_str(s);
+            }
+            SimpleWord::Param(Parameter::Var(ref name)) => {
+                let next_char = next
+                    .and_then(SimpleWord::literal)
+                    .and_then(|s| s.chars().next());
+                let continues_name =
+                    matches!(next_char, Some(c) if c == '_' || c.is_ascii_alphanumeric());
+
+                if continues_name {
+                    out.push_str("${");
+                    out.push_str(name);
+                    out.push('}');
+                } else {
+                    out.push('$');
+                    out.push_str(name);
+                }
+            }
+            SimpleWord::Param(ref p) => out.push_str(&p.to_string()),
+            SimpleWord::Subst(ref subst) => self.subst(subst, out),
+            SimpleWord::Star => out.push('*'),
+            SimpleWord::Question => out.push('?'),
+            SimpleWord::SquareOpen => out.push('['),
+            SimpleWord::SquareClose => out.push(']'),
+            SimpleWord::Tilde => out.push('~'),
+            SimpleWord::TildePrefix(ref login) => {
+                out.push('~');
+                out.push_str(login.as_deref().unwrap_or_default());
+            }
+            SimpleWord::Colon => out.push(':'),
+        }
+    }
+
+    fn subst(&mut self, subst: &DefaultParameterSubstitution, out: &mut String) {
+        use crate::ast::ParameterSubstitution::*;
+
+        let (op, param, word) = match *subst {
+            Command(ref cmds) | ProcessRead(ref cmds) | ProcessWrite(ref cmds) => {
+                let mut nested = self.nested();
+                nested.inline(cmds, false);
+                if !nested.heredocs.is_empty() {
+                    nested.newline();
+                }
+
+                out.push_str(match *subst {
+                    ProcessRead(_) => "<(",
+                    ProcessWrite(_) => ">(",
+                    _ => "$(",
+                });
+                // Avoid `$((` being mistaken for an arithmetic substitution
+                if nested.out.starts_with('(') {
+                    out.push(' ');
+                }
+                out.push_str(&nested.out);
+                out.push(')');
+                return;
+            }
+            ReadFile(ref file) => {
+                out.push_str("$(< ");
+                self.word(file, out);
+                out.push(')');
+                return;
+            }
+            Len(ref p) => {
+                out.push_str("${#");
+                out.push_str(&param_name(p));
+                out.push('}');
+                return;
+            }
+            Arith(ref expr) => {
+                out.push_str("$((");
+                if let Some(ref expr) = *expr {
+                    out.push(' ');
+                    arith(expr, 0, out);
+                    out.push(' ');
+                }
+                out.push_str("))");
+                return;
+            }
+            Default(colon, ref p, ref w) => (if colon { ":-" } else { "-" }, p, w),
+            Assign(colon, ref p, ref w) => (if colon { ":=" } else { "=" }, p, w),
+            Error(colon, ref p, ref w) => (if colon { ":?" } else { "?" }, p, w),
+            Alternative(colon, ref p, ref w) => (if colon { ":+" } else { "+" }, p, w),
+            RemoveSmallestSuffix(ref p, ref w) => ("%", p, w),
+            RemoveLargestSuffix(ref p, ref w) => ("%%", p, w),
+            RemoveSmallestPrefix(ref p, ref w) => ("#", p, w),
+            RemoveLargestPrefix(ref p, ref w) => ("##", p, w),
+            ReplaceString(ref p, ref w) => ("/", p, w),
+            ReplaceStringAll(ref p, ref w) => ("//", p, w),
+            Substring(ref p, ref w) => (":", p, w),
+            Lowercase(all, ref p, ref w) => (if all { ",," } else { "," }, p, w),
+            Uppercase(all, ref p, ref w) => (if all { "^^" } else { "^" }, p, w),
+        };
+
+        out.push_str("${");
+        out.push_str(&param_name(param));
+        out.push_str(op);
+        if let Some(ref word) = *word {
+            self.word(word, out);
+        }
+        out.push('}');
+    }
+}
+
+/// Returns the name of a parameter as it appears within `${...}`.
+fn param_name(param: &DefaultParameter) -> String {
+    match *param {
+        Parameter::Var(ref name) => name.clone(),
+        Parameter::Positional(n) => n.to_string(),
+        ref p => p.to_string()[1..].to_owned(),
+    }
+}
+
+/// Prints a numeric literal exactly as it was written, or in the base it was
+/// written in if its text no longer matches its value (or base).
+fn radix_literal(value: isize, radix: Radix, text: &str, out: &mut String) {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ@_";
+
+    if let Some((parsed, parsed_radix, _)) = arith_literal(text) {
+        if parsed == value && parsed_radix == radix {
+            return out.push_str(text);
+        }
+    }
+
+    match radix {
+        Radix::Decimal => return out.push_str(&value.to_string()),
+        Radix::Octal => out.push('0'),
+        Radix::Hex => out.push_str("0x"),
+        Radix::Explicit(base) => {
+            out.push_str(&base.to_string());
+            out.push('#');
+        }
+    }
+
+    // Literals which overflowed while parsing have wrapped around, and
+    // wrap around to the same value if their digits are parsed again.
+    let base = radix.base() as u64;
+    let mut value = value as i64 as u64;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % base) as usize]);
+        value /= base;
+        if value == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    out.extend(digits.into_iter().map(char::from));
+}
+
+/// Prints an arithmetic expression, adding parentheses only where they are
+/// required by the precedence of the enclosing operator.
+fn arith(expr: &DefaultArithmetic, min_prec: u8, out: &mut String) {
+    use crate::ast::Arithmetic::*;
+
+    let (prec, op, lhs, rhs) = match *expr {
+        Var(ref name) => return out.push_str(name),
+        Literal(n) => return out.push_str(&n.to_string()),
+        RadixLiteral(n, radix, ref text) => return radix_literal(n, radix, text, out),
+        Index(ref name, ref index) => {
+            out.push_str(name);
+            out.push('[');
+            arith(index, 0, out);
+            return out.push(']');
+        }
+        PostIncr(ref target) => {
+            arith_target(target, out);
+            return out.push_str("++");
+        }
+        PostDecr(ref target) => {
+            arith_target(target, out);
+            return out.push_str("--");
+        }
+        PreIncr(ref target) => {
+            out.push_str("++");
+            return arith_target(target, out);
+        }
+        PreDecr(ref target) => {
+            out.push_str("--");
+            return arith_target(target, out);
+        }
+        UnaryPlus(ref e) => return unary("+", e, out),
+        UnaryMinus(ref e) => return unary("-", e, out),
+        LogicalNot(ref e) => return unary("!", e, out),
+        BitwiseNot(ref e) => return unary("~", e, out),
+
+        Sequence(ref exprs) => {
+            let parens = min_prec > 0;
+            if parens {
+                out.push('(');
+            }
+            for (i, e) in exprs.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                arith(e, 1, out);
+            }
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        Assign(ref target, ref value) | AssignOp(_, ref target, ref value) => {
+            let parens = min_prec > 1;
+            if parens {
+                out.push('(');
+            }
+            arith_target(target, out);
+            out.push(' ');
+            match *expr {
+                AssignOp(op, ..) => out.push_str(op.as_str()),
+                _ => out.push('='),
+            }
+            out.push(' ');
+            arith(value, 1, out);
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        Ternary(ref cond, ref then, ref els) => {
+            let parens = min_prec > 2;
+            if parens {
+                out.push('(');
+            }
+            arith(cond, 3, out);
+            out.push_str(" ? ");
+            arith(then, 1, out);
+            out.push_str(" : ");
+            arith(els, 2, out);
+            if parens {
+                out.push(')');
+            }
+            return;
+        }
+
+        LogicalOr(ref l, ref r) => (3, "||", l, r),
+        LogicalAnd(ref l, ref r) => (4, "&&", l, r),
+        BitwiseOr(ref l, ref r) => (5, "|", l, r),
+        BitwiseXor(ref l, ref r) => (6, "^", l, r),
+        BitwiseAnd(ref l, ref r) => (7, "&", l, r),
+        Eq(ref l, ref r) => (8, "==", l, r),
+        NotEq(ref l, ref r) => (8, "!=", l, r),
+        Less(ref l, ref r) => (9, "<", l, r),
+        LessEq(ref l, ref r) => (9, "<=", l, r),
+        Great(ref l, ref r) => (9, ">", l, r),
+        GreatEq(ref l, ref r) => (9, ">=", l, r),
+        ShiftLeft(ref l, ref r) => (10, "<<", l, r),
+        ShiftRight(ref l, ref r) => (10, ">>", l, r),
+        Add(ref l, ref r) => (11, "+", l, r),
+        Sub(ref l, ref r) => (11, "-", l, r),
+        Mult(ref l, ref r) => (12, "*", l, r),
+        Div(ref l, ref r) => (12, "/", l, r),
+        Modulo(ref l, ref r) => (12, "%", l, r),
+        Pow(ref l, ref r) => (13, "**", l, r),
+    };
+
+    // All binary operators are left associative, except for exponentiation
+    let (lhs_prec, rhs_prec) = match *expr {
+        Pow(..) => (prec + 1, prec),
+        _ => (prec, prec + 1),
+    };
+
+    let parens = prec < min_prec;
+    if parens {
+        out.push('(');
+    }
+    arith(lhs, lhs_prec, out);
+    out.push(' ');
+    out.push_str(op);
+    out.push(' ');
+    arith(rhs, rhs_prec, out);
+    if parens {
+        out.push(')');
+    }
+}
+
+/// Prints the variable or array element modified by an assignment or increment.
+fn arith_target(target: &ArithmeticTarget<String>, out: &mut String) {
+    match *target {
+        ArithmeticTarget::Var(ref name) => out.push_str(name),
+        ArithmeticTarget::Index(ref name, ref index) => {
+            out.push_str(name);
+            out.push('[');
+            arith(index, 0, out);
+            out.push(']');
+        }
+    }
+}
+
+fn unary(op: &str, expr: &DefaultArithmetic, out: &mut String) {
+    out.push_str(op);
+    // Avoid accidentally printing `- -x` as `--x` (or `+ --x` as `+--x`)
+    let mut operand = String::new();
+    arith(expr, 14, &mut operand);
+    if operand.starts_with(op) || matches!(op, "+" | "-") && operand.starts_with(['+', '-']) {
+        out.push(' ');
+    }
+    out.push_str(&operand);
+}
@@ -0,0 +1,708 @@
+//! `proptest` strategies for generating well-formed ASTs.
+//!
+//! The generated trees mirror the shape of the ASTs produced by the parser
+//! (and the `DefaultBuilder`), so they can be used to property-test code which
+//! consumes or transforms parsed scripts. For example:
+//!
+//! * names (of variables, functions, `for` loops, etc.) are valid identifiers
+//!   which are never reserved words
+//! * pipelines, and the bodies of compound commands, are never empty
+//!   (except for the body of a `case` arm, which may be)
+//! * concatenated words have at least two parts, and never two adjacent literals
+//!   (nor two adjacent parts quoted the same way)
+//! * parameter substitutions are never ambiguous, e.g. `${#-}` is only ever
+//!   generated as the length of `$-`, and never as the default of `$#`
+//! * constructs which are not accepted by the requested `Dialect` (e.g. `|&`
+//!   pipes, `$(< file)` substitutions, `;&` case arms, or explicit arithmetic
+//!   bases when generating POSIX trees) are never generated
+//!
+//! The positions of any spans within the trees (e.g. `PipeSegment::pipe`) are
+//! placeholders which do not correspond to any source.
+//!
+//! All strategies are designed to shrink well: alternatives are ordered from
+//! simplest to most complex (e.g. a literal word before a substitution, or a
+//! simple command before a compound one), and nested commands shrink towards
+//! their leaves.
+//!
+//! ```
+//! use conch_parser::ast::strategy;
+//! use conch_parser::parse::Dialect;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     // Annotate the function with `#[test]` within a test module instead
+//!     fn commands_are_cloneable(cmd in strategy::command(Dialect::Posix)) {
+//!         prop_assert_eq!(cmd.clone(), cmd);
+//!     }
+//! }
+//!
+//! commands_are_cloneable();
+//! ```
+//!
+//! Commands, words, and arithmetic expressions also implement `Arbitrary`
+//! using these strategies, where the parameter is the `Dialect` to generate
+//! (e.g. `any::<TopLevelCommand<String>>()` for the default dialect, or
+//! `any_with::<TopLevelCommand<String>>(Dialect::Posix)`). See
+//! `testing::check_round_trip` for checking that generated commands survive
+//! being printed and parsed again.
+//!
+//! This module is only available with the `proptest` feature enabled.
+
+use crate::ast::builder::FunctionSyntax;
+use crate::ast::*;
+use crate::parse::{Dialect, Extension, SourcePos};
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use std::rc::Rc;
+
+/// Words which are never generated as names (or command words), since they
+/// would be treated as reserved words by the parser.
+const RESERVED_WORDS: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in", "select",
+    "then", "until", "while",
+];
+
+/// The depth of nested commands (within compound commands or command substitutions).
+const COMMAND_DEPTH: u32 = 3;
+/// The (approximate) number of nested commands to aim for.
+const COMMAND_SIZE: u32 = 24;
+/// The depth of nested arithmetic expressions.
+const ARITHMETIC_DEPTH: u32 = 4;
+
+/// A placeholder span for nodes which record the location of some syntax.
+fn span() -> Span {
+    Span {
+        start: SourcePos::new(),
+        end: SourcePos::new(),
+    }
+}
+
+/// Generates variable names, e.g. `foo` or `_x1`.
+pub fn name() -> BoxedStrategy<String> {
+    "[a-z_][a-z0-9_]{0,4}"
+        .prop_filter("names cannot be reserved words", |name| {
+            !RESERVED_WORDS.contains(&&**name)
+        })
+        .boxed()
+}
+
+/// Generates literals which do not require any quoting, e.g. `foo` or `./a-b`.
+fn literal() -> BoxedStrategy<String> {
+    "[a-z0-9_./-]{1,5}"
+        .prop_filter("literals cannot be reserved words", |lit| {
+            !RESERVED_WORDS.contains(&&**lit)
+        })
+        .boxed()
+}
+
+/// Generates parameters, e.g. `$foo`, `$1`, or `$@`.
+pub fn parameter() -> BoxedStrategy<DefaultParameter> {
+    prop_oneof![
+        name().prop_map(Parameter::Var),
+        (0u32..10).prop_map(Parameter::Positional),
+        Just(Parameter::At),
+        Just(Parameter::Star),
+        Just(Parameter::Pound),
+        Just(Parameter::Question),
+        Just(Parameter::Dash),
+        Just(Parameter::Dollar),
+        Just(Parameter::Bang),
+    ]
+    .boxed()
+}
+
+/// Spells a numeric literal in the specified base, with some extra leading zeros,
+/// and optionally an uppercase prefix and digits (where the base allows), e.g. `0X01F`.
+fn radix_literal_text(value: isize, radix: Radix, zeros: usize, upper: bool) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ@_";
+
+    let mut text = match radix {
+        Radix::Decimal => String::new(),
+        Radix::Octal => String::from("0"),
+        Radix::Hex if upper => String::from("0X"),
+        Radix::Hex => String::from("0x"),
+        Radix::Explicit(base) => format!("{}#", base),
+    };
+    text.push_str(&"0".repeat(zeros));
+
+    let base = radix.base() as usize;
+    let mut value = value as usize;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from(DIGITS[value % base]));
+        value /= base;
+        if value == 0 {
+            break;
+        }
+    }
+
+    let digits = digits.into_iter().rev().collect::<String>();
+    if upper && base <= 36 {
+        text.push_str(&digits.to_uppercase());
+    } else {
+        text.push_str(&digits);
+    }
+    text
+}
+
+/// The constructor of a binary arithmetic expression, e.g. `Arithmetic::Add`.
+type BinaryOp = fn(Box<DefaultArithmetic>, Box<DefaultArithmetic>) -> DefaultArithmetic;
+
+/// Generates arithmetic expressions, e.g. `x + 1` or `y++`.
+pub fn arithmetic(dialect: Dialect) -> BoxedStrategy<DefaultArithmetic> {
+    let radix = if dialect.supports(Extension::ArithmeticBase) {
+        prop_oneof![
+            Just(Radix::Octal),
+            Just(Radix::Hex),
+            (2u8..=64).prop_map(Radix::Explicit),
+        ]
+        .boxed()
+    } else {
+        prop_oneof![Just(Radix::Octal), Just(Radix::Hex)].boxed()
+    };
+
+    let leaf = prop_oneof![
+        (0isize..1000).prop_map(Arithmetic::Literal),
+        name().prop_map(Arithmetic::Var),
+        (0isize..1000, radix, 0usize..3, any::<bool>()).prop_map(|(value, radix, zeros, upper)| {
+            let text = radix_literal_text(value, radix, zeros, upper);
+            Arithmetic::RadixLiteral(value, radix, text)
+        }),
+        name().prop_map(|var| Arithmetic::PostIncr(ArithmeticTarget::Var(var))),
+        name().prop_map(|var| Arithmetic::PostDecr(ArithmeticTarget::Var(var))),
+        name().prop_map(|var| Arithmetic::PreIncr(ArithmeticTarget::Var(var))),
+        name().prop_map(|var| Arithmetic::PreDecr(ArithmeticTarget::Var(var))),
+    ];
+
+    leaf.prop_recursive(ARITHMETIC_DEPTH, 16, 3, move |inner| {
+        use crate::ast::Arithmetic::*;
+
+        let unary = prop_oneof![
+            inner.clone().prop_map(|e| UnaryPlus(Box::new(e))),
+            inner.clone().prop_map(|e| UnaryMinus(Box::new(e))),
+            inner.clone().prop_map(|e| LogicalNot(Box::new(e))),
+            inner.clone().prop_map(|e| BitwiseNot(Box::new(e))),
+        ];
+
+        let binary_ops: Vec<BinaryOp> = vec![
+            Add, Sub, Mult, Div, Modulo, Pow, ShiftLeft, ShiftRight, Less, LessEq, Great, GreatEq,
+            Eq, NotEq, BitwiseAnd, BitwiseXor, BitwiseOr, LogicalAnd, LogicalOr,
+        ];
+        let binary = (
+            proptest::sample::select(binary_ops),
+            inner.clone(),
+            inner.clone(),
+        )
+            .prop_map(|(op, left, right)| op(Box::new(left), Box::new(right)));
+
+        let ternary = (inner.clone(), inner.clone(), inner.clone())
+            .prop_map(|(g, b, e)| Ternary(Box::new(g), Box::new(b), Box::new(e)));
+
+        let target = if dialect.supports(Extension::ArithmeticIndex) {
+            prop_oneof![
+                name().prop_map(ArithmeticTarget::Var),
+                (name(), inner.clone())
+                    .prop_map(|(var, e)| ArithmeticTarget::Index(var, Box::new(e))),
+            ]
+            .boxed()
+        } else {
+            name().prop_map(ArithmeticTarget::Var).boxed()
+        };
+
+        let assign_ops = vec![
+            AssignOperator::Mult,
+            AssignOperator::Div,
+            AssignOperator::Modulo,
+            AssignOperator::Add,
+            AssignOperator::Sub,
+            AssignOperator::ShiftLeft,
+            AssignOperator::ShiftRight,
+            AssignOperator::BitwiseAnd,
+            AssignOperator::BitwiseXor,
+            AssignOperator::BitwiseOr,
+        ];
+        let assign = prop_oneof![
+            (target.clone(), inner.clone()).prop_map(|(t, e)| Assign(t, Box::new(e))),
+            (proptest::sample::select(assign_ops), target, inner.clone())
+                .prop_map(|(op, t, e)| AssignOp(op, t, Box::new(e))),
+        ];
+        let sequence = vec(inner.clone(), 2..4).prop_map(Sequence);
+
+        if dialect.supports(Extension::ArithmeticIndex) {
+            let incr_ops: Vec<fn(ArithmeticTarget<String>) -> DefaultArithmetic> =
+                vec![PostIncr, PostDecr, PreIncr, PreDecr];
+            let incr = (proptest::sample::select(incr_ops), name(), inner.clone())
+                .prop_map(|(op, var, e)| op(ArithmeticTarget::Index(var, Box::new(e))));
+            let index = (name(), inner).prop_map(|(var, e)| Index(var, Box::new(e)));
+            prop_oneof![unary, binary, ternary, assign, sequence, index, incr].boxed()
+        } else {
+            prop_oneof![unary, binary, ternary, assign, sequence].boxed()
+        }
+    })
+    .boxed()
+}
+
+/// Generates simple words which can appear within double quotes, though
+/// process substitutions are only generated if the words are not `quoted`.
+fn quotable_simple_word(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+    quoted: bool,
+) -> BoxedStrategy<DefaultSimpleWord> {
+    let escaped = proptest::sample::select(vec!["$", "`", "\"", "\\"])
+        .prop_map(|s| SimpleWord::Escaped(String::from(s)));
+
+    prop_oneof![
+        4 => literal().prop_map(SimpleWord::Literal),
+        2 => parameter().prop_map(SimpleWord::Param),
+        1 => escaped,
+        2 => substitution(dialect, cmds, quoted).prop_map(|s| SimpleWord::Subst(Box::new(s))),
+    ]
+    .boxed()
+}
+
+/// Generates any simple words, including those representing glob patterns.
+fn simple_word(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<DefaultSimpleWord> {
+    prop_oneof![
+        8 => quotable_simple_word(dialect, cmds, false),
+        1 => Just(SimpleWord::Star),
+        1 => Just(SimpleWord::Question),
+        1 => Just(SimpleWord::SquareOpen),
+        1 => Just(SimpleWord::SquareClose),
+        1 => Just(SimpleWord::Tilde),
+        1 => Just(SimpleWord::Colon),
+    ]
+    .boxed()
+}
+
+/// Generates words which consist of a single (possibly quoted) part.
+fn word_part(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<DefaultWord> {
+    prop_oneof![
+        4 => simple_word(dialect, cmds.clone()).prop_map(Word::Simple),
+        1 => "[a-z0-9 ]{0,5}".prop_map(Word::SingleQuoted),
+        1 => vec(quotable_simple_word(dialect, cmds, true), 0..3)
+            .prop_map(|parts| Word::DoubleQuoted(merge_literals(parts))),
+    ]
+    .boxed()
+}
+
+/// Merges adjacent literals within double quotes, the same way the parser would.
+fn merge_literals(parts: Vec<DefaultSimpleWord>) -> Vec<DefaultSimpleWord> {
+    let mut merged: Vec<DefaultSimpleWord> = Vec::with_capacity(parts.len());
+    for part in parts {
+        if let (Some(SimpleWord::Literal(prev)), SimpleWord::Literal(lit)) =
+            (merged.last_mut(), &part)
+        {
+            prev.push_str(lit);
+            continue;
+        }
+        merged.push(part);
+    }
+    merged
+}
+
+/// Combines word parts the same way the parser would, i.e. merging adjacent
+/// literals (and quoted parts), and only concatenating words with multiple parts.
+fn complex_word(parts: Vec<DefaultWord>) -> TopLevelWord<String> {
+    let mut merged: Vec<DefaultWord> = Vec::with_capacity(parts.len());
+    for part in parts {
+        match (merged.last_mut(), &part) {
+            (
+                Some(Word::Simple(SimpleWord::Literal(prev))),
+                Word::Simple(SimpleWord::Literal(lit)),
+            )
+            | (Some(Word::SingleQuoted(prev)), Word::SingleQuoted(lit)) => {
+                prev.push_str(lit);
+                continue;
+            }
+            (Some(Word::DoubleQuoted(prev)), Word::DoubleQuoted(parts)) => {
+                let parts = prev.drain(..).chain(parts.iter().cloned()).collect();
+                *prev = merge_literals(parts);
+                continue;
+            }
+            _ => {}
+        }
+        merged.push(part);
+    }
+
+    if merged.len() == 1 {
+        TopLevelWord(ComplexWord::Single(merged.pop().unwrap()))
+    } else {
+        TopLevelWord(ComplexWord::Concat(merged))
+    }
+}
+
+/// Generates words, whose command substitutions (if any) are generated by `cmds`.
+fn word_with(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<TopLevelWord<String>> {
+    vec(word_part(dialect, cmds), 1..4)
+        .prop_map(complex_word)
+        .boxed()
+}
+
+/// Generates shell words, e.g. `foo`, `"$bar"baz`, or `${x:-$(y)}`.
+pub fn word(dialect: Dialect) -> BoxedStrategy<TopLevelWord<String>> {
+    word_with(dialect, Some(command(dialect)))
+}
+
+/// Generates parameter substitutions. Any words within them never contain
+/// further substitutions, while `cmds` (if any) generates command substitutions
+/// (and process substitutions in dialects which support them, unless `quoted`).
+fn substitution(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+    quoted: bool,
+) -> BoxedStrategy<DefaultParameterSubstitution> {
+    use crate::ast::ParameterSubstitution::*;
+
+    let required_word = || {
+        let part = prop_oneof![
+            literal().prop_map(|lit| Word::Simple(SimpleWord::Literal(lit))),
+            parameter().prop_map(|p| Word::Simple(SimpleWord::Param(p))),
+        ];
+        vec(part, 1..3).prop_map(complex_word)
+    };
+    let leaf_word = || option::of(required_word());
+    // `${#...}` is always parsed as the length of the parameter which follows
+    let param = || {
+        parameter().prop_filter("`$#` is only used for its length", |p| {
+            *p != Parameter::Pound
+        })
+    };
+    // A pattern starting with a `/` would be mistaken for the `//` operator
+    let pattern = leaf_word().prop_filter("patterns cannot start with `/`", |word| {
+        let first = match word {
+            Some(TopLevelWord(ComplexWord::Single(part))) => part,
+            Some(TopLevelWord(ComplexWord::Concat(parts))) => &parts[0],
+            None => return true,
+        };
+        !matches!(first, Word::Simple(SimpleWord::Literal(lit)) if lit.starts_with('/'))
+    });
+    // Substring offsets are only recognized if they start with a number
+    let offset = (0u8..20).prop_map(|n| {
+        TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(
+            n.to_string(),
+        ))))
+    });
+
+    let posix = prop_oneof![
+        parameter().prop_map(Len),
+        option::of(arithmetic(dialect)).prop_map(Arith),
+        (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Default(c, p, w)),
+        (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Assign(c, p, w)),
+        (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Error(c, p, w)),
+        (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Alternative(c, p, w)),
+        (param(), leaf_word()).prop_map(|(p, w)| RemoveSmallestSuffix(p, w)),
+        (param(), leaf_word()).prop_map(|(p, w)| RemoveLargestSuffix(p, w)),
+        (param(), leaf_word()).prop_map(|(p, w)| RemoveSmallestPrefix(p, w)),
+        (param(), leaf_word()).prop_map(|(p, w)| RemoveLargestPrefix(p, w)),
+    ]
+    .boxed();
+
+    let substitution = match dialect {
+        Dialect::Posix => posix,
+        Dialect::Bash | Dialect::Mksh => prop_oneof![
+            4 => posix,
+            1 => required_word().prop_map(ReadFile),
+            1 => (param(), pattern).prop_map(|(p, w)| ReplaceString(p, w)),
+            1 => (param(), leaf_word()).prop_map(|(p, w)| ReplaceStringAll(p, w)),
+            1 => (param(), offset).prop_map(|(p, w)| Substring(p, Some(w))),
+            1 => (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Lowercase(c, p, w)),
+            1 => (any::<bool>(), param(), leaf_word()).prop_map(|(c, p, w)| Uppercase(c, p, w)),
+        ]
+        .boxed(),
+    };
+
+    match cmds {
+        Some(cmds) if !quoted && dialect.supports(Extension::ProcessRead) => prop_oneof![
+            6 => substitution,
+            2 => vec(cmds.clone(), 0..3).prop_map(Command),
+            1 => vec(cmds.clone(), 0..3).prop_map(ProcessRead),
+            1 => vec(cmds, 0..3).prop_map(ProcessWrite),
+        ]
+        .boxed(),
+        Some(cmds) => prop_oneof![
+            3 => substitution,
+            1 => vec(cmds, 0..3).prop_map(Command),
+        ]
+        .boxed(),
+        None => substitution,
+    }
+}
+
+/// Generates redirects, e.g. `2>&1` or `<file`.
+fn redirect(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<DefaultRedirect> {
+    let fd = || option::of(0u16..10);
+    let target = word_with(dialect, cmds);
+    let dup_target = prop_oneof![
+        (0u16..10).prop_map(|fd| fd.to_string()),
+        Just(String::from("-"))
+    ]
+    .prop_map(|fd| TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(fd)))))
+    .boxed();
+    // Bodies are generated along with how the parser describes a heredoc with a quoted delimiter
+    let heredoc_body = "([a-z0-9 ]{0,8}\n){0,3}".prop_map(|body| {
+        let info = HeredocInfo {
+            delimiter: String::from("EOF"),
+            quoted: true,
+            strip_tabs: false,
+            lines: body.lines().map(String::from).collect(),
+        };
+        let body = TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(body))));
+        (body, info)
+    });
+
+    let posix = prop_oneof![
+        (fd(), target.clone()).prop_map(|(fd, w)| Redirect::Read(fd, w)),
+        (fd(), target.clone()).prop_map(|(fd, w)| Redirect::Write(fd, w)),
+        (fd(), target.clone()).prop_map(|(fd, w)| Redirect::ReadWrite(fd, w)),
+        (fd(), target.clone()).prop_map(|(fd, w)| Redirect::Append(fd, w)),
+        (fd(), target.clone()).prop_map(|(fd, w)| Redirect::Clobber(fd, w)),
+        (fd(), heredoc_body).prop_map(|(fd, (w, info))| Redirect::Heredoc(fd, w, info)),
+        (fd(), dup_target.clone()).prop_map(|(fd, w)| Redirect::DupRead(fd, w)),
+        (fd(), dup_target).prop_map(|(fd, w)| Redirect::DupWrite(fd, w)),
+    ]
+    .boxed();
+
+    if dialect.supports(Extension::RedirectBoth) {
+        prop_oneof![
+            8 => posix,
+            1 => target.clone().prop_map(Redirect::WriteBoth),
+            1 => target.prop_map(Redirect::AppendBoth),
+        ]
+        .boxed()
+    } else {
+        posix
+    }
+}
+
+/// Generates (non-empty) simple commands, e.g. `foo=bar baz qux >out`.
+fn simple_command(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<DefaultSimpleCommand> {
+    let word = word_with(dialect, cmds.clone());
+    let redirect = redirect(dialect, cmds);
+
+    let env_var = prop_oneof![
+        (name(), option::of(word.clone())).prop_map(|(n, w)| RedirectOrEnvVar::EnvVar(n, w)),
+        redirect.clone().prop_map(RedirectOrEnvVar::Redirect),
+    ]
+    .boxed();
+
+    // The command name is always a plain literal, so it cannot be mistaken
+    // for an assignment (or a reserved word)
+    let cmd_word = prop_oneof![
+        3 => word.clone().prop_map(RedirectOrCmdWord::CmdWord),
+        1 => redirect.prop_map(RedirectOrCmdWord::Redirect),
+    ];
+    let cmd_words = (literal(), vec(cmd_word, 0..4))
+        .prop_map(|(name, mut rest)| {
+            let name = TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(name))));
+            rest.insert(0, RedirectOrCmdWord::CmdWord(name));
+            rest
+        })
+        .boxed();
+
+    prop_oneof![
+        cmd_words.clone().prop_map(|cmd_words| SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: cmd_words,
+        }),
+        (vec(env_var.clone(), 1..3), cmd_words).prop_map(|(env_vars, cmd_words)| {
+            SimpleCommand {
+                redirects_or_env_vars: env_vars,
+                redirects_or_cmd_words: cmd_words,
+            }
+        }),
+        vec(env_var, 1..3).prop_map(|env_vars| SimpleCommand {
+            redirects_or_env_vars: env_vars,
+            redirects_or_cmd_words: vec![],
+        }),
+    ]
+    .boxed()
+}
+
+/// Generates compound commands whose bodies are generated by `cmds`.
+fn compound_command(
+    dialect: Dialect,
+    cmds: BoxedStrategy<TopLevelCommand<String>>,
+) -> BoxedStrategy<DefaultCompoundCommand> {
+    let body = || vec(cmds.clone(), 1..3);
+    let guard_body_pair =
+        || (body(), body()).prop_map(|(guard, body)| GuardBodyPair { guard, body });
+    let word = || word_with(dialect, Some(cmds.clone()));
+
+    let mut terminators = vec![CaseTerminator::Break];
+    if dialect.supports(Extension::CaseFallThrough) {
+        terminators.push(CaseTerminator::FallThrough);
+    }
+    if dialect.supports(Extension::CaseContinue) {
+        terminators.push(CaseTerminator::Continue);
+    }
+    let terminator = proptest::sample::select(terminators);
+
+    let arm = (vec(word(), 1..3), vec(cmds.clone(), 0..3), terminator).prop_map(
+        |(patterns, body, terminator)| PatternBodyPair {
+            patterns,
+            body,
+            terminator,
+        },
+    );
+
+    let kind = prop_oneof![
+        body().prop_map(CompoundCommandKind::Brace),
+        body().prop_map(CompoundCommandKind::Subshell),
+        guard_body_pair().prop_map(CompoundCommandKind::While),
+        guard_body_pair().prop_map(CompoundCommandKind::Until),
+        (vec(guard_body_pair(), 1..3), option::of(body())).prop_map(
+            |(conditionals, else_branch)| CompoundCommandKind::If {
+                conditionals,
+                else_branch,
+            }
+        ),
+        (name(), option::of(vec(word(), 0..3)), body())
+            .prop_map(|(var, words, body)| CompoundCommandKind::For { var, words, body }),
+        (word(), vec(arm, 0..3)).prop_map(|(word, arms)| CompoundCommandKind::Case { word, arms }),
+    ];
+
+    (kind, vec(redirect(dialect, Some(cmds.clone())), 0..2))
+        .prop_map(|(kind, io)| CompoundCommand {
+            kind,
+            io_span: if io.is_empty() { None } else { Some(span()) },
+            io,
+        })
+        .boxed()
+}
+
+/// Generates commands which can appear within a pipeline.
+fn pipeable_command(
+    dialect: Dialect,
+    cmds: Option<BoxedStrategy<TopLevelCommand<String>>>,
+) -> BoxedStrategy<DefaultPipeableCommand> {
+    let simple = simple_command(dialect, cmds.clone())
+        .prop_map(|cmd| PipeableCommand::Simple(Box::new(cmd)));
+
+    match cmds {
+        None => simple.boxed(),
+        Some(cmds) => {
+            let compound = compound_command(dialect, cmds.clone());
+            let function_body = compound_command(dialect, cmds);
+
+            let mut syntaxes = vec![FunctionSyntax::Posix];
+            if dialect.supports(Extension::FunctionKeyword) {
+                syntaxes.push(FunctionSyntax::Keyword);
+                syntaxes.push(FunctionSyntax::KeywordParens);
+            }
+            let syntax = proptest::sample::select(syntaxes);
+
+            prop_oneof![
+                4 => simple,
+                2 => compound.prop_map(|cmd| PipeableCommand::Compound(Box::new(cmd))),
+                1 => (name(), syntax, function_body).prop_map(|(name, syntax, body)| {
+                    PipeableCommand::FunctionDef(name, syntax, Rc::new(body))
+                }),
+            ]
+            .boxed()
+        }
+    }
+}
+
+/// Generates top-level commands out of pipeable commands.
+fn top_level_command(
+    dialect: Dialect,
+    pipeable: BoxedStrategy<DefaultPipeableCommand>,
+) -> BoxedStrategy<TopLevelCommand<String>> {
+    let pipe_stderr = if dialect.supports(Extension::PipeAmp) {
+        any::<bool>().boxed()
+    } else {
+        Just(false).boxed()
+    };
+
+    let pipeline = (any::<bool>(), vec((pipeable, pipe_stderr), 1..4))
+        .prop_map(|(bang, cmds)| {
+            if !bang && cmds.len() == 1 {
+                let (cmd, _) = cmds.into_iter().next().unwrap();
+                return ListableCommand::Single(cmd);
+            }
+
+            let segments = cmds
+                .into_iter()
+                .enumerate()
+                .map(|(i, (cmd, pipe_stderr))| PipeSegment {
+                    pipe: if i == 0 { None } else { Some(span()) },
+                    pipe_stderr: i > 0 && pipe_stderr,
+                    cmd,
+                })
+                .collect();
+
+            ListableCommand::Pipe(Pipeline { bang, segments })
+        })
+        .boxed();
+
+    let and_or = prop_oneof![
+        pipeline.clone().prop_map(AndOr::And),
+        pipeline.clone().prop_map(AndOr::Or),
+    ];
+
+    let list = (pipeline, vec(and_or, 0..3))
+        .prop_map(|(first, rest)| AndOrList { first, rest })
+        .boxed();
+
+    prop_oneof![
+        3 => list.clone().prop_map(Command::List),
+        1 => list.prop_map(Command::Job),
+    ]
+    .prop_map(TopLevelCommand)
+    .boxed()
+}
+
+/// Generates top-level commands, e.g. `foo | bar && baz &` or
+/// `if foo; then bar; fi`, which may contain nested commands.
+pub fn command(dialect: Dialect) -> BoxedStrategy<TopLevelCommand<String>> {
+    let leaf = top_level_command(dialect, pipeable_command(dialect, None));
+
+    leaf.prop_recursive(COMMAND_DEPTH, COMMAND_SIZE, 3, move |inner| {
+        top_level_command(dialect, pipeable_command(dialect, Some(inner)))
+    })
+    .boxed()
+}
+
+impl Arbitrary for TopLevelCommand<String> {
+    type Parameters = Dialect;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(dialect: Dialect) -> Self::Strategy {
+        command(dialect)
+    }
+}
+
+impl Arbitrary for TopLevelWord<String> {
+    type Parameters = Dialect;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(dialect: Dialect) -> Self::Strategy {
+        word(dialect)
+    }
+}
+
+impl Arbitrary for DefaultArithmetic {
+    type Parameters = Dialect;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(dialect: Dialect) -> Self::Strategy {
+        arithmetic(dialect)
+    }
+}
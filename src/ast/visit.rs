@@ -0,0 +1,1010 @@
+//! Traversal of the default AST.
+//!
+//! The `Visitor` trait has a method for every kind of node in the default AST,
+//! each of which by default recurses into the children of the node by calling
+//! the corresponding `walk_*` function. Implementations only need to override
+//! the methods for the nodes they are interested in, and can call the `walk_*`
+//! function from within an override to continue recursing into the children of
+//! a node (or skip doing so to prune the traversal).
+//!
+//! All nodes are visited in source order, including the words and redirects of
+//! commands, and the commands nested within substitutions. `VisitorMut` (along
+//! with the `walk_*_mut` functions) is the equivalent for mutably traversing
+//! the AST in place, e.g. to rewrite words or commands.
+//!
+//! ```
+//! # use conch_parser::ast::DefaultParameter;
+//! # use conch_parser::ast::Parameter;
+//! # use conch_parser::ast::visit::Visitor;
+//! # use conch_parser::lexer::Lexer;
+//! # use conch_parser::parse::DefaultParser;
+//! struct Vars(Vec<String>);
+//!
+//! impl Visitor for Vars {
+//!     fn visit_parameter(&mut self, param: &DefaultParameter) {
+//!         if let Parameter::Var(ref name) = *param {
+//!             self.0.push(name.clone());
+//!         }
+//!     }
+//! }
+//!
+//! let source = "echo $a >\"$b\"; for x in $(cat $c); do :; done";
+//! let cmds = DefaultParser::new(Lexer::new(source.chars())).parse_all().unwrap();
+//!
+//! let mut vars = Vars(Vec::new());
+//! vars.visit_commands(&cmds);
+//! assert_eq!(vars.0, vec!["a", "b", "c"]);
+//! ```
+
+use crate::ast::{
+    AndOr, Arithmetic, ArithmeticTarget, Command, ComplexWord, CompoundCommandKind, Conditional,
+    DefaultAndOrList, DefaultArithmetic, DefaultCompoundCommand, DefaultCompoundCommandKind,
+    DefaultConditional, DefaultListableCommand, DefaultParameter, DefaultParameterSubstitution,
+    DefaultPipeableCommand, DefaultRedirect, DefaultSimpleCommand, DefaultSimpleWord, DefaultWord,
+    GuardBodyPair, ListableCommand, ParameterSubstitution, PatternBodyPair, PipeableCommand,
+    Pipeline, Redirect, RedirectOrCmdWord, RedirectOrEnvVar, SimpleWord, TopLevelCommand,
+    TopLevelWord, UnparsedSpan, Word,
+};
+use std::rc::Rc;
+
+/// Type alias for the default `Pipeline` representation.
+pub type DefaultPipeline = Pipeline<DefaultPipeableCommand>;
+
+/// Type alias for the default `GuardBodyPair` representation.
+pub type DefaultGuardBodyPair = GuardBodyPair<TopLevelCommand<String>>;
+
+/// Type alias for the default `PatternBodyPair` representation.
+pub type DefaultPatternBodyPair = PatternBodyPair<TopLevelWord<String>, TopLevelCommand<String>>;
+
+/// A traversal over a borrowed default AST.
+///
+/// See the module documentation for details.
+pub trait Visitor {
+    /// Visits a list of commands, e.g. an entire script or the body of a
+    /// compound command or command substitution.
+    fn visit_commands(&mut self, cmds: &[TopLevelCommand<String>]) {
+        walk_commands(self, cmds)
+    }
+
+    /// Visits a top level command.
+    fn visit_top_level_command(&mut self, cmd: &TopLevelCommand<String>) {
+        walk_top_level_command(self, cmd)
+    }
+
+    /// Visits the source of a command which failed to parse (see `parse::ErrorRecovery`).
+    fn visit_unparsed(&mut self, _unparsed: &UnparsedSpan) {}
+
+    /// Visits a list of and/or commands.
+    fn visit_and_or_list(&mut self, list: &DefaultAndOrList) {
+        walk_and_or_list(self, list)
+    }
+
+    /// Visits a command which can be part of an and/or list.
+    fn visit_listable_command(&mut self, cmd: &DefaultListableCommand) {
+        walk_listable_command(self, cmd)
+    }
+
+    /// Visits a pipeline of (possibly negated) commands.
+    fn visit_pipeline(&mut self, pipeline: &DefaultPipeline) {
+        walk_pipeline(self, pipeline)
+    }
+
+    /// Visits a command which can be part of a pipeline.
+    fn visit_pipeable_command(&mut self, cmd: &DefaultPipeableCommand) {
+        walk_pipeable_command(self, cmd)
+    }
+
+    /// Visits a simple command.
+    fn visit_simple_command(&mut self, cmd: &DefaultSimpleCommand) {
+        walk_simple_command(self, cmd)
+    }
+
+    /// Visits a variable assignment preceding a simple command.
+    fn visit_assignment(&mut self, name: &str, value: Option<&TopLevelWord<String>>) {
+        walk_assignment(self, name, value)
+    }
+
+    /// Visits a function declaration.
+    fn visit_function_def(&mut self, name: &str, body: &DefaultCompoundCommand) {
+        walk_function_def(self, name, body)
+    }
+
+    /// Visits a compound command, along with any redirects applied to it.
+    fn visit_compound_command(&mut self, cmd: &DefaultCompoundCommand) {
+        walk_compound_command(self, cmd)
+    }
+
+    /// Visits the specific kind of a compound command.
+    fn visit_compound_command_kind(&mut self, kind: &DefaultCompoundCommandKind) {
+        walk_compound_command_kind(self, kind)
+    }
+
+    /// Visits the guard and body of a loop, or a branch of an `if` command.
+    fn visit_guard_body_pair(&mut self, pair: &DefaultGuardBodyPair) {
+        walk_guard_body_pair(self, pair)
+    }
+
+    /// Visits an arm of a `case` command.
+    fn visit_pattern_body_pair(&mut self, pair: &DefaultPatternBodyPair) {
+        walk_pattern_body_pair(self, pair)
+    }
+
+    /// Visits the expression of a `[[ ... ]]` command, or a subexpression of one.
+    fn visit_conditional(&mut self, cond: &DefaultConditional) {
+        walk_conditional(self, cond)
+    }
+
+    /// Visits a redirect, including the body of a heredoc.
+    fn visit_redirect(&mut self, redirect: &DefaultRedirect) {
+        walk_redirect(self, redirect)
+    }
+
+    /// Visits a complete word.
+    fn visit_word(&mut self, word: &TopLevelWord<String>) {
+        walk_word(self, word)
+    }
+
+    /// Visits a (possibly quoted) part of a word.
+    fn visit_word_part(&mut self, word: &DefaultWord) {
+        walk_word_part(self, word)
+    }
+
+    /// Visits a literal, expansion, or special character within a word.
+    fn visit_simple_word(&mut self, word: &DefaultSimpleWord) {
+        walk_simple_word(self, word)
+    }
+
+    /// Visits a parameter, whether it is expanded directly or within a substitution.
+    fn visit_parameter(&mut self, _param: &DefaultParameter) {}
+
+    /// Visits a parameter, command, or arithmetic substitution.
+    fn visit_parameter_substitution(&mut self, subst: &DefaultParameterSubstitution) {
+        walk_parameter_substitution(self, subst)
+    }
+
+    /// Visits an arithmetic expression.
+    fn visit_arithmetic(&mut self, arith: &DefaultArithmetic) {
+        walk_arithmetic(self, arith)
+    }
+}
+
+/// Visits every command in a list.
+pub fn walk_commands<V: Visitor + ?Sized>(v: &mut V, cmds: &[TopLevelCommand<String>]) {
+    for cmd in cmds {
+        v.visit_top_level_command(cmd);
+    }
+}
+
+/// Visits the and/or list of a command, or its unparsed source.
+pub fn walk_top_level_command<V: Visitor + ?Sized>(v: &mut V, cmd: &TopLevelCommand<String>) {
+    match cmd.0 {
+        Command::Job(ref list) | Command::List(ref list) => v.visit_and_or_list(list),
+        Command::Unparsed(ref unparsed) => v.visit_unparsed(unparsed),
+    }
+}
+
+/// Visits every command of an and/or list.
+pub fn walk_and_or_list<V: Visitor + ?Sized>(v: &mut V, list: &DefaultAndOrList) {
+    v.visit_listable_command(&list.first);
+    for and_or in &list.rest {
+        match *and_or {
+            AndOr::And(ref cmd) | AndOr::Or(ref cmd) => v.visit_listable_command(cmd),
+        }
+    }
+}
+
+/// Visits a pipeline or a single command.
+pub fn walk_listable_command<V: Visitor + ?Sized>(v: &mut V, cmd: &DefaultListableCommand) {
+    match *cmd {
+        ListableCommand::Pipe(ref pipeline) => v.visit_pipeline(pipeline),
+        ListableCommand::Single(ref cmd) => v.visit_pipeable_command(cmd),
+    }
+}
+
+/// Visits every command of a pipeline.
+pub fn walk_pipeline<V: Visitor + ?Sized>(v: &mut V, pipeline: &DefaultPipeline) {
+    for segment in &pipeline.segments {
+        v.visit_pipeable_command(&segment.cmd);
+    }
+}
+
+/// Visits a simple command, compound command, or function declaration.
+pub fn walk_pipeable_command<V: Visitor + ?Sized>(v: &mut V, cmd: &DefaultPipeableCommand) {
+    match *cmd {
+        PipeableCommand::Simple(ref cmd) => v.visit_simple_command(cmd),
+        PipeableCommand::Compound(ref cmd) => v.visit_compound_command(cmd),
+        PipeableCommand::FunctionDef(ref name, _, ref body) => v.visit_function_def(name, body),
+    }
+}
+
+/// Visits the assignments, redirects, and words of a simple command in source order.
+pub fn walk_simple_command<V: Visitor + ?Sized>(v: &mut V, cmd: &DefaultSimpleCommand) {
+    for item in &cmd.redirects_or_env_vars {
+        match *item {
+            RedirectOrEnvVar::Redirect(ref redirect) => v.visit_redirect(redirect),
+            RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                v.visit_assignment(name, value.as_ref())
+            }
+        }
+    }
+
+    for item in &cmd.redirects_or_cmd_words {
+        match *item {
+            RedirectOrCmdWord::Redirect(ref redirect) => v.visit_redirect(redirect),
+            RedirectOrCmdWord::CmdWord(ref word) => v.visit_word(word),
+        }
+    }
+}
+
+/// Visits the value of an assignment, if any.
+pub fn walk_assignment<V: Visitor + ?Sized>(
+    v: &mut V,
+    _name: &str,
+    value: Option<&TopLevelWord<String>>,
+) {
+    if let Some(value) = value {
+        v.visit_word(value);
+    }
+}
+
+/// Visits the body of a function declaration.
+pub fn walk_function_def<V: Visitor + ?Sized>(
+    v: &mut V,
+    _name: &str,
+    body: &DefaultCompoundCommand,
+) {
+    v.visit_compound_command(body);
+}
+
+/// Visits the kind of a compound command, followed by its redirects.
+pub fn walk_compound_command<V: Visitor + ?Sized>(v: &mut V, cmd: &DefaultCompoundCommand) {
+    v.visit_compound_command_kind(&cmd.kind);
+    for redirect in &cmd.io {
+        v.visit_redirect(redirect);
+    }
+}
+
+/// Visits the words and nested commands of a compound command in source order.
+pub fn walk_compound_command_kind<V: Visitor + ?Sized>(
+    v: &mut V,
+    kind: &DefaultCompoundCommandKind,
+) {
+    match *kind {
+        CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+            v.visit_commands(cmds)
+        }
+
+        CompoundCommandKind::While(ref pair) | CompoundCommandKind::Until(ref pair) => {
+            v.visit_guard_body_pair(pair)
+        }
+
+        CompoundCommandKind::If {
+            ref conditionals,
+            ref else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_guard_body_pair(pair);
+            }
+
+            if let Some(ref els) = *else_branch {
+                v.visit_commands(els);
+            }
+        }
+
+        CompoundCommandKind::For {
+            ref words,
+            ref body,
+            ..
+        } => {
+            for word in words.iter().flatten() {
+                v.visit_word(word);
+            }
+            v.visit_commands(body);
+        }
+
+        CompoundCommandKind::Case { ref word, ref arms } => {
+            v.visit_word(word);
+            for arm in arms {
+                v.visit_pattern_body_pair(arm);
+            }
+        }
+
+        CompoundCommandKind::Conditional(ref cond) => v.visit_conditional(cond),
+
+        CompoundCommandKind::Arithmetic(ref arith) => {
+            if let Some(ref arith) = *arith {
+                v.visit_arithmetic(arith);
+            }
+        }
+    }
+}
+
+/// Visits the guard, followed by the body.
+pub fn walk_guard_body_pair<V: Visitor + ?Sized>(v: &mut V, pair: &DefaultGuardBodyPair) {
+    v.visit_commands(&pair.guard);
+    v.visit_commands(&pair.body);
+}
+
+/// Visits the patterns, followed by the body.
+pub fn walk_pattern_body_pair<V: Visitor + ?Sized>(v: &mut V, pair: &DefaultPatternBodyPair) {
+    for pattern in &pair.patterns {
+        v.visit_word(pattern);
+    }
+    v.visit_commands(&pair.body);
+}
+
+/// Visits the words and subexpressions of a conditional expression.
+pub fn walk_conditional<V: Visitor + ?Sized>(v: &mut V, cond: &DefaultConditional) {
+    match *cond {
+        Conditional::Word(ref word) | Conditional::Unary(_, ref word) => v.visit_word(word),
+        Conditional::Binary(ref lhs, _, ref rhs) => {
+            v.visit_word(lhs);
+            v.visit_word(rhs);
+        }
+        Conditional::Not(ref cond) => v.visit_conditional(cond),
+        Conditional::And(ref lhs, ref rhs) | Conditional::Or(ref lhs, ref rhs) => {
+            v.visit_conditional(lhs);
+            v.visit_conditional(rhs);
+        }
+    }
+}
+
+/// Visits the target of a redirect (or the body of a heredoc).
+pub fn walk_redirect<V: Visitor + ?Sized>(v: &mut V, redirect: &DefaultRedirect) {
+    match *redirect {
+        Redirect::Read(_, ref word)
+        | Redirect::Write(_, ref word)
+        | Redirect::ReadWrite(_, ref word)
+        | Redirect::Append(_, ref word)
+        | Redirect::Clobber(_, ref word)
+        | Redirect::Heredoc(_, ref word, _)
+        | Redirect::DupRead(_, ref word)
+        | Redirect::DupWrite(_, ref word)
+        | Redirect::WriteBoth(ref word)
+        | Redirect::AppendBoth(ref word) => v.visit_word(word),
+    }
+}
+
+/// Visits every part of a word.
+pub fn walk_word<V: Visitor + ?Sized>(v: &mut V, word: &TopLevelWord<String>) {
+    match word.0 {
+        ComplexWord::Single(ref word) => v.visit_word_part(word),
+        ComplexWord::Concat(ref words) => {
+            for word in words {
+                v.visit_word_part(word);
+            }
+        }
+    }
+}
+
+/// Visits the contents of a word part (including the words of the alternatives
+/// of a brace expansion), unless it is single quoted.
+pub fn walk_word_part<V: Visitor + ?Sized>(v: &mut V, word: &DefaultWord) {
+    match *word {
+        Word::Simple(ref word) => v.visit_simple_word(word),
+        Word::DoubleQuoted(ref words) => {
+            for word in words {
+                v.visit_simple_word(word);
+            }
+        }
+        Word::SingleQuoted(_) => {}
+        Word::Braces(ref braces) => {
+            for word in braces.words() {
+                v.visit_word_part(word);
+            }
+        }
+    }
+}
+
+/// Visits the parameter or substitution of a word, if any.
+pub fn walk_simple_word<V: Visitor + ?Sized>(v: &mut V, word: &DefaultSimpleWord) {
+    match *word {
+        SimpleWord::Param(ref param) => v.visit_parameter(param),
+        SimpleWord::Subst(ref subst) => v.visit_parameter_substitution(subst),
+        SimpleWord::Literal(_)
+        | SimpleWord::Escaped(_)
+        | SimpleWord::Star
+        | SimpleWord::Question
+        | SimpleWord::SquareOpen
+        | SimpleWord::SquareClose
+        | SimpleWord::Tilde
+        | SimpleWord::TildePrefix(_)
+        | SimpleWord::Colon => {}
+    }
+}
+
+/// Visits the parameter, word, commands, or arithmetic expression of a substitution.
+pub fn walk_parameter_substitution<V: Visitor + ?Sized>(
+    v: &mut V,
+    subst: &DefaultParameterSubstitution,
+) {
+    use self::ParameterSubstitution::*;
+
+    let (param, word) = match *subst {
+        Command(ref cmds) | ProcessRead(ref cmds) | ProcessWrite(ref cmds) => {
+            return v.visit_commands(cmds)
+        }
+        ReadFile(ref word) => return v.visit_word(word),
+        Arith(ref arith) => {
+            if let Some(ref arith) = *arith {
+                v.visit_arithmetic(arith);
+            }
+            return;
+        }
+        Len(ref param) => return v.visit_parameter(param),
+
+        Default(_, ref param, ref word)
+        | Assign(_, ref param, ref word)
+        | Error(_, ref param, ref word)
+        | Alternative(_, ref param, ref word)
+        | Lowercase(_, ref param, ref word)
+        | Uppercase(_, ref param, ref word)
+        | RemoveSmallestSuffix(ref param, ref word)
+        | RemoveLargestSuffix(ref param, ref word)
+        | RemoveSmallestPrefix(ref param, ref word)
+        | RemoveLargestPrefix(ref param, ref word)
+        | ReplaceString(ref param, ref word)
+        | ReplaceStringAll(ref param, ref word)
+        | Substring(ref param, ref word) => (param, word),
+    };
+
+    v.visit_parameter(param);
+    if let Some(ref word) = *word {
+        v.visit_word(word);
+    }
+}
+
+/// Visits the operands of an arithmetic expression.
+pub fn walk_arithmetic<V: Visitor + ?Sized>(v: &mut V, arith: &DefaultArithmetic) {
+    use self::Arithmetic::*;
+
+    match *arith {
+        Var(_) | Literal(_) | RadixLiteral(..) => {}
+
+        PostIncr(ref target) | PostDecr(ref target) | PreIncr(ref target) | PreDecr(ref target) => {
+            if let ArithmeticTarget::Index(_, ref index) = *target {
+                v.visit_arithmetic(index);
+            }
+        }
+
+        Assign(ref target, ref expr) | AssignOp(_, ref target, ref expr) => {
+            if let ArithmeticTarget::Index(_, ref index) = *target {
+                v.visit_arithmetic(index);
+            }
+            v.visit_arithmetic(expr);
+        }
+
+        Index(_, ref expr)
+        | UnaryPlus(ref expr)
+        | UnaryMinus(ref expr)
+        | LogicalNot(ref expr)
+        | BitwiseNot(ref expr) => v.visit_arithmetic(expr),
+
+        Pow(ref left, ref right)
+        | Mult(ref left, ref right)
+        | Div(ref left, ref right)
+        | Modulo(ref left, ref right)
+        | Add(ref left, ref right)
+        | Sub(ref left, ref right)
+        | ShiftLeft(ref left, ref right)
+        | ShiftRight(ref left, ref right)
+        | Less(ref left, ref right)
+        | LessEq(ref left, ref right)
+        | Great(ref left, ref right)
+        | GreatEq(ref left, ref right)
+        | Eq(ref left, ref right)
+        | NotEq(ref left, ref right)
+        | BitwiseAnd(ref left, ref right)
+        | BitwiseXor(ref left, ref right)
+        | BitwiseOr(ref left, ref right)
+        | LogicalAnd(ref left, ref right)
+        | LogicalOr(ref left, ref right) => {
+            v.visit_arithmetic(left);
+            v.visit_arithmetic(right);
+        }
+
+        Ternary(ref guard, ref then, ref els) => {
+            v.visit_arithmetic(guard);
+            v.visit_arithmetic(then);
+            v.visit_arithmetic(els);
+        }
+
+        Sequence(ref exprs) => {
+            for expr in exprs {
+                v.visit_arithmetic(expr);
+            }
+        }
+    }
+}
+
+/// A traversal which can modify the default AST in place.
+///
+/// Identical to `Visitor`, except that nodes are visited mutably. Since the
+/// bodies of function declarations are shared via `Rc`, visiting a body
+/// which is shared with another AST will (cheaply) clone it first.
+pub trait VisitorMut {
+    /// Visits a list of commands, e.g. an entire script or the body of a
+    /// compound command or command substitution.
+    fn visit_commands(&mut self, cmds: &mut Vec<TopLevelCommand<String>>) {
+        walk_commands_mut(self, cmds)
+    }
+
+    /// Visits a top level command.
+    fn visit_top_level_command(&mut self, cmd: &mut TopLevelCommand<String>) {
+        walk_top_level_command_mut(self, cmd)
+    }
+
+    /// Visits the source of a command which failed to parse (see `parse::ErrorRecovery`).
+    fn visit_unparsed(&mut self, _unparsed: &mut UnparsedSpan) {}
+
+    /// Visits a list of and/or commands.
+    fn visit_and_or_list(&mut self, list: &mut DefaultAndOrList) {
+        walk_and_or_list_mut(self, list)
+    }
+
+    /// Visits a command which can be part of an and/or list.
+    fn visit_listable_command(&mut self, cmd: &mut DefaultListableCommand) {
+        walk_listable_command_mut(self, cmd)
+    }
+
+    /// Visits a pipeline of (possibly negated) commands.
+    fn visit_pipeline(&mut self, pipeline: &mut DefaultPipeline) {
+        walk_pipeline_mut(self, pipeline)
+    }
+
+    /// Visits a command which can be part of a pipeline.
+    fn visit_pipeable_command(&mut self, cmd: &mut DefaultPipeableCommand) {
+        walk_pipeable_command_mut(self, cmd)
+    }
+
+    /// Visits a simple command.
+    fn visit_simple_command(&mut self, cmd: &mut DefaultSimpleCommand) {
+        walk_simple_command_mut(self, cmd)
+    }
+
+    /// Visits a variable assignment preceding a simple command.
+    fn visit_assignment(&mut self, name: &mut String, value: Option<&mut TopLevelWord<String>>) {
+        walk_assignment_mut(self, name, value)
+    }
+
+    /// Visits a function declaration.
+    fn visit_function_def(&mut self, name: &mut String, body: &mut DefaultCompoundCommand) {
+        walk_function_def_mut(self, name, body)
+    }
+
+    /// Visits a compound command, along with any redirects applied to it.
+    fn visit_compound_command(&mut self, cmd: &mut DefaultCompoundCommand) {
+        walk_compound_command_mut(self, cmd)
+    }
+
+    /// Visits the specific kind of a compound command.
+    fn visit_compound_command_kind(&mut self, kind: &mut DefaultCompoundCommandKind) {
+        walk_compound_command_kind_mut(self, kind)
+    }
+
+    /// Visits the guard and body of a loop, or a branch of an `if` command.
+    fn visit_guard_body_pair(&mut self, pair: &mut DefaultGuardBodyPair) {
+        walk_guard_body_pair_mut(self, pair)
+    }
+
+    /// Visits an arm of a `case` command.
+    fn visit_pattern_body_pair(&mut self, pair: &mut DefaultPatternBodyPair) {
+        walk_pattern_body_pair_mut(self, pair)
+    }
+
+    /// Visits the expression of a `[[ ... ]]` command, or a subexpression of one.
+    fn visit_conditional(&mut self, cond: &mut DefaultConditional) {
+        walk_conditional_mut(self, cond)
+    }
+
+    /// Visits a redirect, including the body of a heredoc.
+    fn visit_redirect(&mut self, redirect: &mut DefaultRedirect) {
+        walk_redirect_mut(self, redirect)
+    }
+
+    /// Visits a complete word.
+    fn visit_word(&mut self, word: &mut TopLevelWord<String>) {
+        walk_word_mut(self, word)
+    }
+
+    /// Visits a (possibly quoted) part of a word.
+    fn visit_word_part(&mut self, word: &mut DefaultWord) {
+        walk_word_part_mut(self, word)
+    }
+
+    /// Visits a literal, expansion, or special character within a word.
+    fn visit_simple_word(&mut self, word: &mut DefaultSimpleWord) {
+        walk_simple_word_mut(self, word)
+    }
+
+    /// Visits a parameter, whether it is expanded directly or within a substitution.
+    fn visit_parameter(&mut self, _param: &mut DefaultParameter) {}
+
+    /// Visits a parameter, command, or arithmetic substitution.
+    fn visit_parameter_substitution(&mut self, subst: &mut DefaultParameterSubstitution) {
+        walk_parameter_substitution_mut(self, subst)
+    }
+
+    /// Visits an arithmetic expression.
+    fn visit_arithmetic(&mut self, arith: &mut DefaultArithmetic) {
+        walk_arithmetic_mut(self, arith)
+    }
+}
+
+/// Visits every command in a list.
+pub fn walk_commands_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    cmds: &mut Vec<TopLevelCommand<String>>,
+) {
+    for cmd in cmds {
+        v.visit_top_level_command(cmd);
+    }
+}
+
+/// Visits the and/or list of a command, or its unparsed source.
+pub fn walk_top_level_command_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    cmd: &mut TopLevelCommand<String>,
+) {
+    match cmd.0 {
+        Command::Job(ref mut list) | Command::List(ref mut list) => v.visit_and_or_list(list),
+        Command::Unparsed(ref mut unparsed) => v.visit_unparsed(unparsed),
+    }
+}
+
+/// Visits every command of an and/or list.
+pub fn walk_and_or_list_mut<V: VisitorMut + ?Sized>(v: &mut V, list: &mut DefaultAndOrList) {
+    v.visit_listable_command(&mut list.first);
+    for and_or in &mut list.rest {
+        match *and_or {
+            AndOr::And(ref mut cmd) | AndOr::Or(ref mut cmd) => v.visit_listable_command(cmd),
+        }
+    }
+}
+
+/// Visits a pipeline or a single command.
+pub fn walk_listable_command_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    cmd: &mut DefaultListableCommand,
+) {
+    match *cmd {
+        ListableCommand::Pipe(ref mut pipeline) => v.visit_pipeline(pipeline),
+        ListableCommand::Single(ref mut cmd) => v.visit_pipeable_command(cmd),
+    }
+}
+
+/// Visits every command of a pipeline.
+pub fn walk_pipeline_mut<V: VisitorMut + ?Sized>(v: &mut V, pipeline: &mut DefaultPipeline) {
+    for segment in &mut pipeline.segments {
+        v.visit_pipeable_command(&mut segment.cmd);
+    }
+}
+
+/// Visits a simple command, compound command, or function declaration.
+pub fn walk_pipeable_command_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    cmd: &mut DefaultPipeableCommand,
+) {
+    match *cmd {
+        PipeableCommand::Simple(ref mut cmd) => v.visit_simple_command(cmd),
+        PipeableCommand::Compound(ref mut cmd) => v.visit_compound_command(cmd),
+        PipeableCommand::FunctionDef(ref mut name, _, ref mut body) => {
+            v.visit_function_def(name, Rc::make_mut(body))
+        }
+    }
+}
+
+/// Visits the assignments, redirects, and words of a simple command in source order.
+pub fn walk_simple_command_mut<V: VisitorMut + ?Sized>(v: &mut V, cmd: &mut DefaultSimpleCommand) {
+    for item in &mut cmd.redirects_or_env_vars {
+        match *item {
+            RedirectOrEnvVar::Redirect(ref mut redirect) => v.visit_redirect(redirect),
+            RedirectOrEnvVar::EnvVar(ref mut name, ref mut value) => {
+                v.visit_assignment(name, value.as_mut())
+            }
+        }
+    }
+
+    for item in &mut cmd.redirects_or_cmd_words {
+        match *item {
+            RedirectOrCmdWord::Redirect(ref mut redirect) => v.visit_redirect(redirect),
+            RedirectOrCmdWord::CmdWord(ref mut word) => v.visit_word(word),
+        }
+    }
+}
+
+/// Visits the value of an assignment, if any.
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    _name: &mut String,
+    value: Option<&mut TopLevelWord<String>>,
+) {
+    if let Some(value) = value {
+        v.visit_word(value);
+    }
+}
+
+/// Visits the body of a function declaration.
+pub fn walk_function_def_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    _name: &mut String,
+    body: &mut DefaultCompoundCommand,
+) {
+    v.visit_compound_command(body);
+}
+
+/// Visits the kind of a compound command, followed by its redirects.
+pub fn walk_compound_command_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    cmd: &mut DefaultCompoundCommand,
+) {
+    v.visit_compound_command_kind(&mut cmd.kind);
+    for redirect in &mut cmd.io {
+        v.visit_redirect(redirect);
+    }
+}
+
+/// Visits the words and nested commands of a compound command in source order.
+pub fn walk_compound_command_kind_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    kind: &mut DefaultCompoundCommandKind,
+) {
+    match *kind {
+        CompoundCommandKind::Brace(ref mut cmds) | CompoundCommandKind::Subshell(ref mut cmds) => {
+            v.visit_commands(cmds)
+        }
+
+        CompoundCommandKind::While(ref mut pair) | CompoundCommandKind::Until(ref mut pair) => {
+            v.visit_guard_body_pair(pair)
+        }
+
+        CompoundCommandKind::If {
+            ref mut conditionals,
+            ref mut else_branch,
+        } => {
+            for pair in conditionals {
+                v.visit_guard_body_pair(pair);
+            }
+
+            if let Some(ref mut els) = *else_branch {
+                v.visit_commands(els);
+            }
+        }
+
+        CompoundCommandKind::For {
+            ref mut words,
+            ref mut body,
+            ..
+        } => {
+            for word in words.iter_mut().flatten() {
+                v.visit_word(word);
+            }
+            v.visit_commands(body);
+        }
+
+        CompoundCommandKind::Case {
+            ref mut word,
+            ref mut arms,
+        } => {
+            v.visit_word(word);
+            for arm in arms {
+                v.visit_pattern_body_pair(arm);
+            }
+        }
+
+        CompoundCommandKind::Conditional(ref mut cond) => v.visit_conditional(cond),
+
+        CompoundCommandKind::Arithmetic(ref mut arith) => {
+            if let Some(ref mut arith) = *arith {
+                v.visit_arithmetic(arith);
+            }
+        }
+    }
+}
+
+/// Visits the guard, followed by the body.
+pub fn walk_guard_body_pair_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    pair: &mut DefaultGuardBodyPair,
+) {
+    v.visit_commands(&mut pair.guard);
+    v.visit_commands(&mut pair.body);
+}
+
+/// Visits the patterns, followed by the body.
+pub fn walk_pattern_body_pair_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    pair: &mut DefaultPatternBodyPair,
+) {
+    for pattern in &mut pair.patterns {
+        v.visit_word(pattern);
+    }
+    v.visit_commands(&mut pair.body);
+}
+
+/// Visits the words and subexpressions of a conditional expression.
+pub fn walk_conditional_mut<V: VisitorMut + ?Sized>(v: &mut V, cond: &mut DefaultConditional) {
+    match *cond {
+        Conditional::Word(ref mut word) | Conditional::Unary(_, ref mut word) => v.visit_word(word),
+        Conditional::Binary(ref mut lhs, _, ref mut rhs) => {
+            v.visit_word(lhs);
+            v.visit_word(rhs);
+        }
+        Conditional::Not(ref mut cond) => v.visit_conditional(cond),
+        Conditional::And(ref mut lhs, ref mut rhs) | Conditional::Or(ref mut lhs, ref mut rhs) => {
+            v.visit_conditional(lhs);
+            v.visit_conditional(rhs);
+        }
+    }
+}
+
+/// Visits the target of a redirect (or the body of a heredoc).
+pub fn walk_redirect_mut<V: VisitorMut + ?Sized>(v: &mut V, redirect: &mut DefaultRedirect) {
+    match *redirect {
+        Redirect::Read(_, ref mut word)
+        | Redirect::Write(_, ref mut word)
+        | Redirect::ReadWrite(_, ref mut word)
+        | Redirect::Append(_, ref mut word)
+        | Redirect::Clobber(_, ref mut word)
+        | Redirect::Heredoc(_, ref mut word, _)
+        | Redirect::DupRead(_, ref mut word)
+        | Redirect::DupWrite(_, ref mut word)
+        | Redirect::WriteBoth(ref mut word)
+        | Redirect::AppendBoth(ref mut word) => v.visit_word(word),
+    }
+}
+
+/// Visits every part of a word.
+pub fn walk_word_mut<V: VisitorMut + ?Sized>(v: &mut V, word: &mut TopLevelWord<String>) {
+    match word.0 {
+        ComplexWord::Single(ref mut word) => v.visit_word_part(word),
+        ComplexWord::Concat(ref mut words) => {
+            for word in words {
+                v.visit_word_part(word);
+            }
+        }
+    }
+}
+
+/// Visits the contents of a word part (including the words of the alternatives
+/// of a brace expansion), unless it is single quoted.
+pub fn walk_word_part_mut<V: VisitorMut + ?Sized>(v: &mut V, word: &mut DefaultWord) {
+    match *word {
+        Word::Simple(ref mut word) => v.visit_simple_word(word),
+        Word::DoubleQuoted(ref mut words) => {
+            for word in words {
+                v.visit_simple_word(word);
+            }
+        }
+        Word::SingleQuoted(_) => {}
+        Word::Braces(ref mut braces) => {
+            for word in braces.words_mut() {
+                v.visit_word_part(word);
+            }
+        }
+    }
+}
+
+/// Visits the parameter or substitution of a word, if any.
+pub fn walk_simple_word_mut<V: VisitorMut + ?Sized>(v: &mut V, word: &mut DefaultSimpleWord) {
+    match *word {
+        SimpleWord::Param(ref mut param) => v.visit_parameter(param),
+        SimpleWord::Subst(ref mut subst) => v.visit_parameter_substitution(subst),
+        SimpleWord::Literal(_)
+        | SimpleWord::Escaped(_)
+        | SimpleWord::Star
+        | SimpleWord::Question
+        | SimpleWord::SquareOpen
+        | SimpleWord::SquareClose
+        | SimpleWord::Tilde
+        | SimpleWord::TildePrefix(_)
+        | SimpleWord::Colon => {}
+    }
+}
+
+/// Visits the parameter, word, commands, or arithmetic expression of a substitution.
+pub fn walk_parameter_substitution_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    subst: &mut DefaultParameterSubstitution,
+) {
+    use self::ParameterSubstitution::*;
+
+    let (param, word) = match *subst {
+        Command(ref mut cmds) | ProcessRead(ref mut cmds) | ProcessWrite(ref mut cmds) => {
+            return v.visit_commands(cmds)
+        }
+        ReadFile(ref mut word) => return v.visit_word(word),
+        Arith(ref mut arith) => {
+            if let Some(ref mut arith) = *arith {
+                v.visit_arithmetic(arith);
+            }
+            return;
+        }
+        Len(ref mut param) => return v.visit_parameter(param),
+
+        Default(_, ref mut param, ref mut word)
+        | Assign(_, ref mut param, ref mut word)
+        | Error(_, ref mut param, ref mut word)
+        | Alternative(_, ref mut param, ref mut word)
+        | Lowercase(_, ref mut param, ref mut word)
+        | Uppercase(_, ref mut param, ref mut word)
+        | RemoveSmallestSuffix(ref mut param, ref mut word)
+        | RemoveLargestSuffix(ref mut param, ref mut word)
+        | RemoveSmallestPrefix(ref mut param, ref mut word)
+        | RemoveLargestPrefix(ref mut param, ref mut word)
+        | ReplaceString(ref mut param, ref mut word)
+        | ReplaceStringAll(ref mut param, ref mut word)
+        | Substring(ref mut param, ref mut word) => (param, word),
+    };
+
+    v.visit_parameter(param);
+    if let Some(ref mut word) = *word {
+        v.visit_word(word);
+    }
+}
+
+/// Visits the operands of an arithmetic expression.
+pub fn walk_arithmetic_mut<V: VisitorMut + ?Sized>(v: &mut V, arith: &mut DefaultArithmetic) {
+    use self::Arithmetic::*;
+
+    match *arith {
+        Var(_) | Literal(_) | RadixLiteral(..) => {}
+
+        PostIncr(ref mut target)
+        | PostDecr(ref mut target)
+        | PreIncr(ref mut target)
+        | PreDecr(ref mut target) => {
+            if let ArithmeticTarget::Index(_, ref mut index) = *target {
+                v.visit_arithmetic(index);
+            }
+        }
+
+        Assign(ref mut target, ref mut expr) | AssignOp(_, ref mut target, ref mut expr) => {
+            if let ArithmeticTarget::Index(_, ref mut index) = *target {
+                v.visit_arithmetic(index);
+            }
+            v.visit_arithmetic(expr);
+        }
+
+        Index(_, ref mut expr)
+        | UnaryPlus(ref mut expr)
+        | UnaryMinus(ref mut expr)
+        | LogicalNot(ref mut expr)
+        | BitwiseNot(ref mut expr) => v.visit_arithmetic(expr),
+
+        Pow(ref mut left, ref mut right)
+        | Mult(ref mut left, ref mut right)
+        | Div(ref mut left, ref mut right)
+        | Modulo(ref mut left, ref mut right)
+        | Add(ref mut left, ref mut right)
+        | Sub(ref mut left, ref mut right)
+        | ShiftLeft(ref mut left, ref mut right)
+        | ShiftRight(ref mut left, ref mut right)
+        | Less(ref mut left, ref mut right)
+        | LessEq(ref mut left, ref mut right)
+        | Great(ref mut left, ref mut right)
+        | GreatEq(ref mut left, ref mut right)
+        | Eq(ref mut left, ref mut right)
+        | NotEq(ref mut left, ref mut right)
+        | BitwiseAnd(ref mut left, ref mut right)
+        | BitwiseXor(ref mut left, ref mut right)
+        | BitwiseOr(ref mut left, ref mut right)
+        | LogicalAnd(ref mut left, ref mut right)
+        | LogicalOr(ref mut left, ref mut right) => {
+            v.visit_arithmetic(left);
+            v.visit_arithmetic(right);
+        }
+
+        Ternary(ref mut guard, ref mut then, ref mut els) => {
+            v.visit_arithmetic(guard);
+            v.visit_arithmetic(then);
+            v.visit_arithmetic(els);
+        }
+
+        Sequence(ref mut exprs) => {
+            for expr in exprs {
+                v.visit_arithmetic(expr);
+            }
+        }
+    }
+}
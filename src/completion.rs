@@ -0,0 +1,451 @@
+//! Context for completing the word at the end of an incomplete script.
+//!
+//! The script is expected to end at the cursor (i.e. any text after the cursor
+//! should be removed before computing its context). It is parsed using
+//! `IncompleteWords::Partial`, so that expansions, quotes, and compound commands
+//! which have been opened but not yet closed (e.g. the `${HO` of `echo ${HO`)
+//! are still available as partial nodes, rather than failing to parse.
+
+use crate::ast::builder::DefaultBuilder;
+use crate::ast::CompoundCommandKind::{Brace, Case, For, If, Subshell, Until, While};
+use crate::ast::{
+    AndOr, Command, ComplexWord, DefaultCompoundCommand, DefaultParameter, DefaultSimpleCommand,
+    DefaultSimpleWord, DefaultWord, GuardBodyPair, ListableCommand, Parameter,
+    ParameterSubstitution, PipeableCommand, RedirectOrCmdWord, RedirectOrEnvVar, SimpleWord,
+    TopLevelCommand, TopLevelWord, Word,
+};
+use crate::lexer::Lexer;
+use crate::parse::{IncompleteWords, ParseError, Parser, ParserOptions};
+use crate::token::Token;
+use void::Void;
+
+/// The kind of word being completed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CompletionKind {
+    /// The name of a command, e.g. `ec` in `foo; ec`.
+    Command,
+    /// An argument of a command, or the target of a redirect, e.g. `fo` in `echo fo`.
+    Argument,
+    /// The name of a parameter, e.g. `HO` in `echo $HO` or `echo ${HO`.
+    Variable,
+}
+
+/// Describes the word at the end of a script, and what has been typed of it so far.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompletionContext {
+    /// The kind of word being completed.
+    pub kind: CompletionKind,
+    /// The text of the word typed so far, with any quotes and escapes removed.
+    pub prefix: String,
+    /// The opening delimiters of any expansions (or double quotes) which have yet
+    /// to be closed, outermost first, e.g. `[ParenOpen, CurlyOpen]` for `echo $(cat ${HO`.
+    /// Compound commands which have yet to be closed are not included.
+    pub open: Vec<Token>,
+    /// The innermost expansion which has yet to be closed, as parsed so far,
+    /// e.g. `Param(Var("HO"))` for `echo ${HO`.
+    pub expansion: Option<DefaultSimpleWord>,
+}
+
+impl CompletionContext {
+    fn new(kind: CompletionKind, prefix: &str) -> Self {
+        CompletionContext {
+            kind,
+            prefix: String::from(prefix),
+            open: Vec::new(),
+            expansion: None,
+        }
+    }
+}
+
+/// Computes the context of the word at the end of the source, if it can be completed.
+///
+/// ```
+/// use conch_parser::ast::{Parameter, SimpleWord};
+/// use conch_parser::completion::{completion_context, CompletionKind};
+/// use conch_parser::token::Token;
+///
+/// let context = completion_context("echo ${HO").unwrap();
+/// assert_eq!(context.kind, CompletionKind::Variable);
+/// assert_eq!(context.prefix, "HO");
+/// assert_eq!(context.open, vec![Token::CurlyOpen]);
+/// assert_eq!(
+///     context.expansion,
+///     Some(SimpleWord::Param(Parameter::Var(String::from("HO"))))
+/// );
+/// ```
+///
+/// The word may also be nested within double quotes, backticks, or compound
+/// commands which have yet to be closed, e.g. the `ec` of `if true; then ec`.
+///
+/// No context is returned if the source cannot be parsed (even partially), or
+/// if the end of the source is not part of a word which can be completed (e.g.
+/// if it ends with a closing brace or a quoted string).
+pub fn completion_context(source: &str) -> Option<CompletionContext> {
+    let options = ParserOptions {
+        incomplete_words: IncompleteWords::Partial,
+        ..ParserOptions::default()
+    };
+    let builder = DefaultBuilder::<String>::new();
+    let mut parser = Parser::with_options(Lexer::new(source.chars()), builder, options);
+
+    // A trailing backslash escapes whatever is typed next, so the cursor is
+    // at the start of a new word in `echo \`
+    let backslashes = source.len() - source.trim_end_matches('\\').len();
+    let typed = if backslashes % 2 == 1 {
+        &source[..source.len() - 1]
+    } else {
+        source
+    };
+
+    let at_separator = typed
+        .trim_end_matches([' ', '\t'])
+        .ends_with([';', '&', '|', '(', '\n']);
+    let at_new_word = typed.ends_with(char::is_whitespace);
+
+    let mut last = None;
+    loop {
+        match parser.complete_command() {
+            Ok(Some(cmd)) => last = Some(cmd),
+            Ok(None) => break,
+            // A command is expected after a trailing `&&`, `|`, `if`, `{`, etc.
+            Err(ParseError::UnexpectedEOF)
+            | Err(ParseError::IncompleteCmd(..))
+            | Err(ParseError::Unmatched(Token::CurlyOpen, _))
+            | Err(ParseError::Unmatched(Token::ParenOpen, _))
+                if at_separator || at_new_word || typed.is_empty() =>
+            {
+                return Some(CompletionContext::new(CompletionKind::Command, ""))
+            }
+            Err(_) => return None,
+        }
+    }
+
+    // Expansions and compound commands are closed (and recorded) from the innermost outwards
+    let mut open = parser.take_recovered_errors();
+    open.reverse();
+
+    if open.is_empty() && (at_separator || typed.is_empty()) {
+        return Some(CompletionContext::new(CompletionKind::Command, ""));
+    }
+
+    Completer {
+        source,
+        open: &open,
+        at_separator,
+        at_new_word,
+    }
+    .command(last.as_ref()?)
+}
+
+/// Walks down to the word at the end of the source.
+struct Completer<'a> {
+    source: &'a str,
+    /// The expansions and compound commands which are still open at the current depth.
+    open: &'a [ParseError<Void>],
+    /// Whether the source ends after a command separator.
+    at_separator: bool,
+    /// Whether the source ends at the start of a new word.
+    at_new_word: bool,
+}
+
+impl Completer<'_> {
+    /// Returns a completer for the contents of the outermost open expansion or command.
+    fn inner(&self) -> Self {
+        Completer {
+            open: &self.open[1..],
+            ..*self
+        }
+    }
+
+    fn commands(&self, cmds: &[TopLevelCommand<String>]) -> Option<CompletionContext> {
+        match cmds.last() {
+            Some(cmd) => self.command(cmd),
+            None => Some(CompletionContext::new(CompletionKind::Command, "")),
+        }
+    }
+
+    fn command(&self, cmd: &TopLevelCommand<String>) -> Option<CompletionContext> {
+        let list = match cmd.0 {
+            Command::List(ref list) => list,
+            Command::Job(_) | Command::Unparsed(_) => return None,
+        };
+
+        // The cursor is at the start of a new command
+        if self.open.is_empty() && self.at_separator {
+            return Some(CompletionContext::new(CompletionKind::Command, ""));
+        }
+
+        let last = match list.rest.last() {
+            Some(&AndOr::And(ref cmd)) | Some(&AndOr::Or(ref cmd)) => cmd,
+            None => &list.first,
+        };
+
+        let cmd = match *last {
+            ListableCommand::Single(ref cmd) => cmd,
+            ListableCommand::Pipe(ref pipeline) => pipeline.commands().last()?,
+        };
+
+        match *cmd {
+            PipeableCommand::Simple(ref simple) => self.simple(simple),
+            PipeableCommand::Compound(ref compound) => self.compound(compound),
+            PipeableCommand::FunctionDef(_, _, ref body) => self.compound(body),
+        }
+    }
+
+    fn simple(&self, simple: &DefaultSimpleCommand) -> Option<CompletionContext> {
+        let num_words = simple
+            .redirects_or_cmd_words
+            .iter()
+            .filter(|item| matches!(**item, RedirectOrCmdWord::CmdWord(_)))
+            .count();
+
+        // The cursor is at the start of a new word
+        if self.open.is_empty() && self.at_new_word {
+            let kind = if num_words == 0 {
+                CompletionKind::Command
+            } else {
+                CompletionKind::Argument
+            };
+            return Some(CompletionContext::new(kind, ""));
+        }
+
+        match simple.redirects_or_cmd_words.last() {
+            Some(RedirectOrCmdWord::CmdWord(word)) => {
+                let kind = if num_words == 1 {
+                    CompletionKind::Command
+                } else {
+                    CompletionKind::Argument
+                };
+                self.word(word, kind)
+            }
+            Some(RedirectOrCmdWord::Redirect(redirect)) => {
+                self.word(redirect.word(), CompletionKind::Argument)
+            }
+            None => match *simple.redirects_or_env_vars.last()? {
+                RedirectOrEnvVar::Redirect(ref redirect) => {
+                    self.word(redirect.word(), CompletionKind::Argument)
+                }
+                RedirectOrEnvVar::EnvVar(_, Some(ref value)) => {
+                    self.word(value, CompletionKind::Argument)
+                }
+                RedirectOrEnvVar::EnvVar(_, None) => {
+                    Some(CompletionContext::new(CompletionKind::Argument, ""))
+                }
+            },
+        }
+    }
+
+    /// Descends into a compound command which is still open, i.e. whose closing
+    /// reserved word (or brace/parenthesis) has yet to be typed.
+    fn compound(&self, compound: &DefaultCompoundCommand) -> Option<CompletionContext> {
+        if !compound.io.is_empty() || self.open.is_empty() {
+            return None;
+        }
+
+        let inner = self.inner();
+        match (&compound.kind, self.open.first()?) {
+            (Brace(cmds), ParseError::Unmatched(Token::CurlyOpen, _))
+            | (Subshell(cmds), ParseError::Unmatched(Token::ParenOpen, _)) => inner.commands(cmds),
+            (While(pair), ParseError::IncompleteCmd(..))
+            | (Until(pair), ParseError::IncompleteCmd(..)) => inner.guard_body(pair),
+            (
+                If {
+                    conditionals,
+                    else_branch,
+                },
+                ParseError::IncompleteCmd(..),
+            ) => match else_branch {
+                Some(cmds) => inner.commands(cmds),
+                None => inner.guard_body(conditionals.last()?),
+            },
+            (For { words, body, .. }, ParseError::IncompleteCmd(..)) => match words {
+                // The cursor is still within the words of the loop
+                Some(words) if body.is_empty() && !inner.at_separator => {
+                    if inner.open.is_empty() && inner.at_new_word {
+                        return Some(CompletionContext::new(CompletionKind::Argument, ""));
+                    }
+                    inner.word(words.last()?, CompletionKind::Argument)
+                }
+                _ => inner.commands(body),
+            },
+            (Case { arms, .. }, ParseError::IncompleteCmd(..)) => {
+                inner.commands(&arms.last()?.body)
+            }
+            _ => None,
+        }
+    }
+
+    /// Descends into the body of a guarded command, or into its guard if the
+    /// input ended before its body.
+    fn guard_body(
+        &self,
+        pair: &GuardBodyPair<TopLevelCommand<String>>,
+    ) -> Option<CompletionContext> {
+        if pair.body.is_empty() {
+            self.commands(&pair.guard)
+        } else {
+            self.commands(&pair.body)
+        }
+    }
+
+    fn word(&self, word: &TopLevelWord<String>, kind: CompletionKind) -> Option<CompletionContext> {
+        let parts = match word.0 {
+            ComplexWord::Single(ref w) => std::slice::from_ref(w),
+            ComplexWord::Concat(ref words) => &words[..],
+        };
+        let (last, rest) = parts.split_last()?;
+
+        match self.open.first() {
+            None => {}
+            Some(&ParseError::Unmatched(Token::DoubleQuote, _)) => {
+                let fragments = match *last {
+                    Word::DoubleQuoted(ref fragments) => fragments,
+                    _ => return None,
+                };
+
+                let mut context = self.inner().double_quoted(rest, fragments, kind)?;
+                context.open.insert(0, Token::DoubleQuote);
+                return Some(context);
+            }
+            Some(_) => match *last {
+                Word::Simple(ref expansion) => return self.expansion(expansion, kind),
+                _ => return None,
+            },
+        }
+
+        if let Word::Simple(ref last) = *last {
+            if let Some(context) = self.trailing_parameter(last) {
+                return Some(context);
+            }
+        }
+
+        // The cursor must be within the word, rather than after a closing quote
+        if self.source.ends_with(['\'', '"']) {
+            return None;
+        }
+
+        Some(CompletionContext {
+            prefix: literal(parts)?,
+            ..CompletionContext::new(kind, "")
+        })
+    }
+
+    /// Completes the contents of double quotes which have yet to be closed, which
+    /// follow the `preceding` parts of the word.
+    fn double_quoted(
+        &self,
+        preceding: &[DefaultWord],
+        fragments: &[DefaultSimpleWord],
+        kind: CompletionKind,
+    ) -> Option<CompletionContext> {
+        if !self.open.is_empty() {
+            return self.expansion(fragments.last()?, kind);
+        }
+
+        if let Some(context) = fragments.last().and_then(|w| self.trailing_parameter(w)) {
+            return Some(context);
+        }
+
+        let mut prefix = literal(preceding)?;
+        for fragment in fragments {
+            prefix.push_str(simple_literal(fragment)?);
+        }
+
+        Some(CompletionContext {
+            prefix,
+            ..CompletionContext::new(kind, "")
+        })
+    }
+
+    /// Completes an unbraced parameter (or a lone `$`) at the end of the source.
+    fn trailing_parameter(&self, word: &DefaultSimpleWord) -> Option<CompletionContext> {
+        match *word {
+            SimpleWord::Param(Parameter::Var(ref name))
+                if self.source.ends_with(&format!("${}", name)) =>
+            {
+                Some(CompletionContext::new(CompletionKind::Variable, name))
+            }
+            SimpleWord::Literal(ref lit) if lit.ends_with('$') && self.source.ends_with('$') => {
+                Some(CompletionContext::new(CompletionKind::Variable, ""))
+            }
+            _ => None,
+        }
+    }
+
+    /// Descends into the outermost expansion which is still open.
+    fn expansion(
+        &self,
+        expansion: &DefaultSimpleWord,
+        kind: CompletionKind,
+    ) -> Option<CompletionContext> {
+        let delim = match *self.open.first()? {
+            ParseError::Unmatched(ref delim, _) => delim,
+            _ => return None,
+        };
+
+        let inner = self.inner();
+        let mut context = match (expansion, delim) {
+            (SimpleWord::Param(param), Token::CurlyOpen) => variable(param),
+            (SimpleWord::Subst(subst), Token::CurlyOpen) => match **subst {
+                ParameterSubstitution::Len(ref param) => variable(param),
+                ref subst => match subst.word() {
+                    Some(word) => inner.word(word, kind)?,
+                    None => CompletionContext::new(CompletionKind::Argument, ""),
+                },
+            },
+            (SimpleWord::Subst(subst), Token::ParenOpen)
+            | (SimpleWord::Subst(subst), Token::Backtick) => inner.commands(subst.commands()?)?,
+            _ => return None,
+        };
+
+        if context.expansion.is_none() {
+            context.expansion = Some(expansion.clone());
+        }
+        context.open.insert(0, delim.clone());
+        Some(context)
+    }
+}
+
+/// Returns the text of the parts of a word typed so far, with quotes removed.
+fn literal(parts: &[DefaultWord]) -> Option<String> {
+    let mut prefix = String::new();
+    for part in parts {
+        match *part {
+            Word::SingleQuoted(ref s) => prefix.push_str(s),
+            Word::DoubleQuoted(ref words) => {
+                for w in words {
+                    prefix.push_str(simple_literal(w)?);
+                }
+            }
+            Word::Simple(ref w) => prefix.push_str(simple_literal(w)?),
+            Word::Braces(_) => return None,
+        }
+    }
+    Some(prefix)
+}
+
+fn variable(param: &DefaultParameter) -> CompletionContext {
+    let prefix = match *param {
+        Parameter::Var(ref name) => name.clone(),
+        ref param => param.to_string().trim_start_matches('$').to_owned(),
+    };
+
+    CompletionContext {
+        prefix,
+        ..CompletionContext::new(CompletionKind::Variable, "")
+    }
+}
+
+/// Returns the text of a simple word which does not need to be expanded.
+fn simple_literal(word: &DefaultSimpleWord) -> Option<&str> {
+    match *word {
+        SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => Some(s),
+        SimpleWord::Star => Some("*"),
+        SimpleWord::Question => Some("?"),
+        SimpleWord::SquareOpen => Some("["),
+        SimpleWord::SquareClose => Some("]"),
+        SimpleWord::Tilde => Some("~"),
+        SimpleWord::Colon => Some(":"),
+        _ => None,
+    }
+}
@@ -0,0 +1,165 @@
+//! Groups the reserved words which delimit compound commands, for folding and indentation.
+//!
+//! Editors can fold each part of a compound command separately (e.g. every branch
+//! of an `if` command) and indent its body, given the spans of the reserved words
+//! which delimit its parts: the `then`, `elif`, `else`, and `fi` of an `if` command,
+//! the `in` (if any), `do`, and `done` of a loop, and the `in` and `esac` of a
+//! `case` command. Brace groups (including the bodies of functions) are delimited
+//! by their `{` and `}`.
+//!
+//! The groups are assembled from the reserved words the parser has recognized as
+//! such (see `Parser::set_track_keywords`), thus words such as the `done` of
+//! `echo done` are never mistaken for reserved words.
+//!
+//! ```
+//! use conch_parser::folding::keyword_groups_of;
+//!
+//! let src = "if a; then\n  b\nelif c; then\n  d\nelse\n  e\nfi";
+//! let groups = keyword_groups_of(src);
+//! assert_eq!(groups.len(), 1);
+//!
+//! let words: Vec<_> = groups[0].keywords.iter().map(|kw| &*kw.word).collect();
+//! assert_eq!(words, ["if", "then", "elif", "then", "else", "fi"]);
+//!
+//! let sections: Vec<_> = groups[0]
+//!     .sections()
+//!     .into_iter()
+//!     .map(|span| &src[span.start.byte..span.end.byte])
+//!     .collect();
+//! assert_eq!(sections[1], "then\n  b\n");
+//! assert_eq!(sections[4], "else\n  e\n");
+//! ```
+
+use crate::ast::Span;
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, ErrorRecovery, Keyword};
+
+/// The reserved words which delimit the parts of a single compound command.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KeywordGroup {
+    /// The reserved words of the command in source order, starting with the one
+    /// which opens it (e.g. `if`) and ending with the one which closes it (e.g. `fi`),
+    /// unless the command is incomplete. Reserved words of nested commands are
+    /// part of their own group.
+    pub keywords: Vec<Keyword>,
+}
+
+impl KeywordGroup {
+    /// Returns the reserved word which opens the command, e.g. `if` or `while`.
+    pub fn opener(&self) -> &str {
+        &self.keywords[0].word
+    }
+
+    /// Checks if the command is closed by its matching reserved word, e.g. `fi`.
+    pub fn is_closed(&self) -> bool {
+        let last = &self.keywords[self.keywords.len() - 1].word;
+        self.keywords.len() > 1 && Some(&**last) == closer(self.opener())
+    }
+
+    /// Returns the span covered by the command, from the start of its opening
+    /// reserved word to the end of its last one (e.g. its closing reserved word).
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.keywords[0].span.start,
+            end: self.keywords[self.keywords.len() - 1].span.end,
+        }
+    }
+
+    /// Returns the sections of the command delimited by its reserved words: each
+    /// section starts at one reserved word and ends where the next one starts,
+    /// e.g. `if a; then b; else c; fi` has the sections `if a; `, `then b; `,
+    /// and `else c; `.
+    ///
+    /// Folding each section separately keeps the reserved word which follows it
+    /// (e.g. the `elif` of the next branch) visible.
+    pub fn sections(&self) -> Vec<Span> {
+        self.keywords
+            .windows(2)
+            .map(|pair| Span {
+                start: pair[0].span.start,
+                end: pair[1].span.start,
+            })
+            .collect()
+    }
+}
+
+/// Returns the reserved word which closes a command opened by another.
+fn closer(opener: &str) -> Option<&'static str> {
+    match opener {
+        "if" => Some("fi"),
+        "while" | "until" | "for" | "select" => Some("done"),
+        "case" => Some("esac"),
+        "{" => Some("}"),
+        _ => None,
+    }
+}
+
+/// Checks if a reserved word delimits a part of a command opened by another.
+fn is_part_of(word: &str, opener: &str) -> bool {
+    match opener {
+        "if" => matches!(word, "then" | "elif" | "else"),
+        "while" | "until" => word == "do",
+        "for" | "select" => matches!(word, "in" | "do"),
+        "case" => word == "in",
+        _ => false,
+    }
+}
+
+/// Groups the reserved words recognized by the parser (see `Parser::take_keywords`)
+/// by the compound command they belong to. Groups are ordered by the position of
+/// their opening reserved word, thus enclosing commands precede nested ones.
+///
+/// Reserved words which do not delimit the parts of a compound command (e.g. `!`,
+/// `function`, or `[[`) are ignored. If the parser recovered from an error (see
+/// `ErrorRecovery`), closing reserved words without a matching opening one are
+/// ignored as well, and any commands which remain open are not closed.
+pub fn keyword_groups(keywords: &[Keyword]) -> Vec<KeywordGroup> {
+    let mut keywords = keywords.iter().collect::<Vec<_>>();
+    keywords.sort_by_key(|kw| kw.span.start.byte);
+
+    let mut groups: Vec<KeywordGroup> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+
+    for kw in keywords {
+        if closer(&kw.word).is_some() {
+            open.push(groups.len());
+            groups.push(KeywordGroup {
+                keywords: vec![kw.clone()],
+            });
+            continue;
+        }
+
+        if let Some(&top) = open.last() {
+            if is_part_of(&kw.word, groups[top].opener()) {
+                groups[top].keywords.push(kw.clone());
+                continue;
+            }
+        }
+
+        let closes = open
+            .iter()
+            .rposition(|&i| closer(groups[i].opener()) == Some(&*kw.word));
+        if let Some(pos) = closes {
+            groups[open[pos]].keywords.push(kw.clone());
+            open.truncate(pos);
+        }
+    }
+
+    groups
+}
+
+/// Parses the source, and groups every reserved word recognized by the parser
+/// by the compound command it belongs to (see `keyword_groups`).
+///
+/// Commands which fail to parse are skipped (see `ErrorRecovery::Passthrough`),
+/// though the reserved words recognized before the error are retained.
+pub fn keyword_groups_of(source: &str) -> Vec<KeywordGroup> {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_error_recovery(ErrorRecovery::Passthrough);
+    parser.set_track_keywords(true);
+
+    // Parse errors are recovered from, but just in case
+    while let Ok(Some(_)) = parser.complete_command() {}
+
+    keyword_groups(&parser.take_keywords())
+}
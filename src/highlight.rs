@@ -0,0 +1,963 @@
+//! Semantic tokens for syntax highlighting.
+//!
+//! The highlighter operates on the raw source (tokenized with the `Lexer`)
+//! rather than the parsed AST, so that it can be used on incomplete or invalid
+//! scripts (e.g. while they are being edited), and so that comments are retained.
+//!
+//! Expansions are highlighted wherever the shell would perform them, including
+//! within double quoted strings and unquoted heredoc bodies. Such expansions are
+//! emitted as tokens nested within the surrounding string: the string token is
+//! split around them so that no two tokens overlap, and modifiers indicate the
+//! context in which each token appears (e.g. `TokenModifiers::QUOTED`). The
+//! contents of command substitutions are highlighted as regular code, without
+//! any modifiers of their surroundings.
+//!
+//! Tokens are yielded in source order and never span multiple lines, matching
+//! the expectations of the Language Server Protocol (see `encode_lsp`).
+//!
+//! Reserved words are highlighted exactly where the parser interprets them as
+//! such (see `Parser::set_track_keywords`), e.g. the `done` of `echo done` is
+//! an argument rather than a keyword. Within commands which fail to parse, the
+//! reserved words following the error are approximated instead.
+
+use crate::ast::Command;
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, ErrorRecovery, SourcePos};
+use crate::token::Token;
+use crate::token::Token::*;
+use std::ops;
+
+/// The type of a semantic token.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SemanticTokenType {
+    /// A reserved word, e.g. `if`, `do`, or `esac`.
+    Keyword,
+    /// A comment, including the leading `#`.
+    Comment,
+    /// Quoted text, heredoc bodies and their delimiters.
+    String,
+    /// A named variable, e.g. `$foo` or the `foo` in `${foo:-bar}`.
+    Variable,
+    /// A positional or special parameter, e.g. `$1`, `$@`, or `$?`.
+    Parameter,
+    /// Expansion delimiters (e.g. `${`, `}`, or `$(`), operators within
+    /// parameter substitutions and arithmetic, redirections, and control
+    /// operators such as `|` or `&&`.
+    Operator,
+    /// A numeric literal within an arithmetic context.
+    Number,
+}
+
+impl SemanticTokenType {
+    /// All token types, in the order of their LSP legend indices.
+    pub const ALL: [SemanticTokenType; 7] = [
+        SemanticTokenType::Keyword,
+        SemanticTokenType::Comment,
+        SemanticTokenType::String,
+        SemanticTokenType::Variable,
+        SemanticTokenType::Parameter,
+        SemanticTokenType::Operator,
+        SemanticTokenType::Number,
+    ];
+
+    /// Returns the name of the corresponding standard LSP token type.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SemanticTokenType::Keyword => "keyword",
+            SemanticTokenType::Comment => "comment",
+            SemanticTokenType::String => "string",
+            SemanticTokenType::Variable => "variable",
+            SemanticTokenType::Parameter => "parameter",
+            SemanticTokenType::Operator => "operator",
+            SemanticTokenType::Number => "number",
+        }
+    }
+
+    /// Returns the index of the token type within `SemanticTokenType::ALL`.
+    pub fn index(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// A set of modifiers describing the context a semantic token appears in.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TokenModifiers(u32);
+
+impl TokenModifiers {
+    /// No modifiers.
+    pub const NONE: TokenModifiers = TokenModifiers(0);
+    /// The token appears within single or double quotes.
+    pub const QUOTED: TokenModifiers = TokenModifiers(1);
+    /// The token appears within a heredoc (including its delimiters).
+    pub const HEREDOC: TokenModifiers = TokenModifiers(1 << 1);
+    /// The token appears within an arithmetic context, e.g. `$(( ... ))`.
+    pub const ARITHMETIC: TokenModifiers = TokenModifiers(1 << 2);
+
+    /// The names of all modifiers, in the order of their LSP legend bits.
+    pub const NAMES: [&'static str; 3] = ["quoted", "heredoc", "arithmetic"];
+
+    /// Returns the LSP bit set representation of the modifiers.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if no modifiers are set.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all of the `other` modifiers are set.
+    pub fn contains(&self, other: TokenModifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for TokenModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        TokenModifiers(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for TokenModifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A highlighted region of the source, which never spans multiple lines.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SemanticToken {
+    /// The type of the token.
+    pub token_type: SemanticTokenType,
+    /// The context in which the token appears.
+    pub modifiers: TokenModifiers,
+    /// The position of the start of the token.
+    pub start: SourcePos,
+    /// The position immediately after the end of the token.
+    pub end: SourcePos,
+}
+
+/// Computes the semantic tokens of the source, in source order.
+///
+/// Regions of the source which do not warrant any highlighting (e.g. command
+/// names, unquoted arguments, or whitespace) are not covered by any token.
+pub fn semantic_tokens(source: &str) -> Vec<SemanticToken> {
+    let mut toks = Vec::new();
+    let mut pos = Vec::new();
+    let mut spanned = Lexer::new(source.chars()).spanned();
+    for (tok, span) in spanned.by_ref() {
+        toks.push(tok);
+        pos.push(span.start);
+    }
+    pos.push(spanned.pos());
+
+    let (keywords, unparsed) = parse_keywords(source);
+    let mut highlighter = Highlighter {
+        toks,
+        pos,
+        i: 0,
+        out: Vec::new(),
+        heredocs: Vec::new(),
+        keywords,
+        unparsed,
+    };
+    highlighter.code(CodeEnd::Eof);
+    highlighter.out
+}
+
+/// Parses the source, returning the byte offsets of all words interpreted as
+/// reserved words (in ascending order), along with the byte ranges of any
+/// commands which failed to parse.
+fn parse_keywords(source: &str) -> (Vec<usize>, Vec<ops::Range<usize>>) {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_error_recovery(ErrorRecovery::Passthrough);
+    parser.set_track_keywords(true);
+
+    let mut unparsed = Vec::new();
+    loop {
+        match parser.complete_command() {
+            Ok(Some(cmd)) => {
+                if let Command::Unparsed(ref span) = cmd.0 {
+                    unparsed.push(span.start.byte..span.end.byte);
+                }
+            }
+            Ok(None) => break,
+            // Parse errors are recovered from, but just in case
+            Err(_) => {
+                unparsed.push(parser.pos().byte..usize::MAX);
+                break;
+            }
+        }
+    }
+
+    let mut keywords = parser
+        .take_keywords()
+        .into_iter()
+        .map(|kw| kw.span.start.byte)
+        .collect::<Vec<_>>();
+    keywords.sort_unstable();
+    (keywords, unparsed)
+}
+
+/// Encodes the tokens in the relative format used by the LSP `textDocument/semanticTokens`
+/// response, using the legends of `SemanticTokenType::ALL` and `TokenModifiers::NAMES`.
+///
+/// Each token is encoded as five integers: the line delta from the previous token,
+/// the start character (relative to the previous token if on the same line), the
+/// length, the token type index, and the modifier bits. Characters are counted
+/// in UTF-16 code units, as required by the protocol.
+pub fn encode_lsp(source: &str, tokens: &[SemanticToken]) -> Vec<u32> {
+    let utf16_len = |start: usize, end: usize| {
+        source
+            .get(start..end)
+            .map_or(0, |s| s.encode_utf16().count() as u32)
+    };
+
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for tok in tokens {
+        let line = tok.start.line as u32 - 1;
+        let line_start = tok.start.byte - (tok.start.col - 1);
+        let start = utf16_len(line_start, tok.start.byte);
+
+        let delta_start = if line == prev_line {
+            start - prev_start
+        } else {
+            start
+        };
+
+        data.extend_from_slice(&[
+            line - prev_line,
+            delta_start,
+            utf16_len(tok.start.byte, tok.end.byte),
+            tok.token_type.index(),
+            tok.modifiers.bits(),
+        ]);
+
+        prev_line = line;
+        prev_start = start;
+    }
+
+    data
+}
+
+/// Indicates what terminates a region of code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum CodeEnd {
+    /// The end of the source.
+    Eof,
+    /// An unmatched `)`, e.g. the end of a `$( ... )` substitution.
+    Paren,
+    /// A backtick, e.g. the end of a `` `...` `` substitution.
+    Backtick,
+}
+
+/// A heredoc whose body has yet to be highlighted.
+#[derive(Debug)]
+struct Heredoc {
+    delim: String,
+    quoted: bool,
+    strip_tabs: bool,
+}
+
+/// Properties of a word which was highlighted as code.
+#[derive(Debug, Default)]
+struct WordInfo {
+    /// The text of the word if it does not contain any quotes, escapes, or expansions.
+    plain: Option<String>,
+    /// Whether the word is a variable assignment, e.g. `foo=bar`.
+    assignment: bool,
+}
+
+#[derive(Debug)]
+struct Highlighter {
+    toks: Vec<Token>,
+    /// The start position of each token, followed by the end of the source.
+    pos: Vec<SourcePos>,
+    /// The index of the next token to process.
+    i: usize,
+    out: Vec<SemanticToken>,
+    heredocs: Vec<Heredoc>,
+    /// The byte offsets of all words the parser interpreted as reserved words.
+    keywords: Vec<usize>,
+    /// The byte ranges of all commands which failed to parse.
+    unparsed: Vec<ops::Range<usize>>,
+}
+
+impl Highlighter {
+    fn peek(&self) -> Option<&Token> {
+        self.toks.get(self.i)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.toks.get(self.i + offset)
+    }
+
+    /// Emits a token covering the region between the starts of the tokens at
+    /// the specified indices. Strings and comments are merged into the previous
+    /// token if they are adjacent and identical in type, since they may consist
+    /// of several lexer tokens.
+    fn emit(
+        &mut self,
+        token_type: SemanticTokenType,
+        modifiers: TokenModifiers,
+        from: usize,
+        to: usize,
+    ) {
+        let (start, end) = (self.pos[from], self.pos[to]);
+        if start.byte == end.byte {
+            return;
+        }
+
+        let mergeable = matches!(
+            token_type,
+            SemanticTokenType::String | SemanticTokenType::Comment
+        );
+
+        if let Some(last) = self.out.last_mut().filter(|_| mergeable) {
+            if last.token_type == token_type
+                && last.modifiers == modifiers
+                && last.end.byte == start.byte
+                && last.end.line == start.line
+            {
+                last.end = end;
+                return;
+            }
+        }
+
+        self.out.push(SemanticToken {
+            token_type,
+            modifiers,
+            start,
+            end,
+        });
+    }
+
+    /// Emits the next `n` tokens as a single semantic token and advances past them.
+    fn emit_next(&mut self, token_type: SemanticTokenType, modifiers: TokenModifiers, n: usize) {
+        let from = self.i;
+        self.i = (self.i + n).min(self.toks.len());
+        self.emit(token_type, modifiers, from, self.i);
+    }
+
+    /// Emits the next token, unless it is a newline, and advances past it.
+    fn emit_text(&mut self, token_type: SemanticTokenType, modifiers: TokenModifiers) {
+        if self.peek() != Some(&Newline) {
+            self.emit(token_type, modifiers, self.i, self.i + 1);
+        }
+        self.i += 1;
+    }
+
+    /// Highlights a list of commands until the specified terminator (which is left unconsumed).
+    fn code(&mut self, end: CodeEnd) {
+        let mut cmd_pos = true;
+        let mut depth = 0usize;
+        // The number of words seen since a `for`, `case`, or `select` keyword
+        let mut header_words = None;
+
+        loop {
+            let tok = match self.peek() {
+                Some(tok) => tok,
+                None => return,
+            };
+
+            match *tok {
+                Newline => {
+                    self.i += 1;
+                    cmd_pos = true;
+                    self.heredoc_bodies();
+                }
+
+                Whitespace(_) => self.i += 1,
+
+                Pound => self.comment(),
+
+                ParenClose if depth == 0 && end == CodeEnd::Paren => return,
+                Backtick if end == CodeEnd::Backtick => return,
+
+                ParenOpen if cmd_pos && is_arithmetic_parens(&self.toks[self.i..]) => {
+                    self.emit_next(SemanticTokenType::Operator, TokenModifiers::NONE, 2);
+                    self.arithmetic(TokenModifiers::ARITHMETIC);
+                    self.close_arithmetic(TokenModifiers::NONE);
+                    cmd_pos = false;
+                }
+
+                // The `&>` and `&>>` redirections (bash extensions) rather than a `&` separator
+                Amp if matches!(self.toks.get(self.i + 1), Some(&Great) | Some(&DGreat)) => {
+                    self.emit_next(SemanticTokenType::Operator, TokenModifiers::NONE, 2);
+                }
+
+                ParenOpen | ParenClose | Semi | Amp | Pipe | AndIf | OrIf | DSemi => {
+                    match *tok {
+                        ParenOpen => depth += 1,
+                        ParenClose => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+
+                    self.emit_next(SemanticTokenType::Operator, TokenModifiers::NONE, 1);
+                    cmd_pos = true;
+                }
+
+                Less | Great | DGreat | GreatAnd | LessAnd | Clobber | LessGreat => {
+                    self.emit_next(SemanticTokenType::Operator, TokenModifiers::NONE, 1);
+                }
+
+                DLess | DLessDash => {
+                    let strip_tabs = *tok == DLessDash;
+                    self.emit_next(SemanticTokenType::Operator, TokenModifiers::NONE, 1);
+                    self.heredoc_delimiter(strip_tabs);
+                }
+
+                _ => {
+                    let start = self.i;
+                    let info = self.word(TokenModifiers::NONE, end);
+                    if self.i == start {
+                        // Make sure we always make progress on unexpected input
+                        self.i += 1;
+                        continue;
+                    }
+
+                    let plain = info.plain.as_deref();
+                    let byte = self.pos[start].byte;
+                    let keyword = if self.keywords.binary_search(&byte).is_ok() {
+                        plain
+                    } else if self.unparsed.iter().any(|r| r.contains(&byte)) {
+                        // `in` and `do` are only keywords following the first word of a
+                        // `for`, `case`, or `select` header, e.g. `for x in ...` or `for x do`
+                        let in_header =
+                            header_words == Some(1) && matches!(plain, Some("in" | "do"));
+                        plain.filter(|w| (cmd_pos || in_header) && is_keyword(w))
+                    } else {
+                        None
+                    };
+
+                    if let Some(kw) = keyword {
+                        self.emit(
+                            SemanticTokenType::Keyword,
+                            TokenModifiers::NONE,
+                            start,
+                            self.i,
+                        );
+
+                        cmd_pos = starts_command(kw);
+                        header_words = match kw {
+                            "for" | "case" | "select" => Some(0),
+                            _ => None,
+                        };
+                    } else {
+                        cmd_pos = cmd_pos && info.assignment;
+                        header_words = header_words.map(|n| n + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Highlights a comment up to (but not including) the end of the line.
+    fn comment(&mut self) {
+        while let Some(tok) = self.peek() {
+            if *tok == Newline {
+                break;
+            }
+            self.emit_text(SemanticTokenType::Comment, TokenModifiers::NONE);
+        }
+    }
+
+    /// Highlights a single (unquoted) word, stopping at any word delimiter.
+    fn word(&mut self, modifiers: TokenModifiers, end: CodeEnd) -> WordInfo {
+        let start = self.i;
+        let mut plain = Some(String::new());
+
+        while let Some(tok) = self.peek() {
+            if tok.is_word_delimiter() || (*tok == Backtick && end == CodeEnd::Backtick) {
+                break;
+            }
+
+            match *tok {
+                SingleQuote => self.single_quoted(modifiers),
+                DoubleQuote => self.double_quoted(modifiers),
+                Backslash => self.i += 2,
+                Dollar | ParamPositional(_) | Backtick => self.expansion(modifiers),
+                ref t => {
+                    if let Some(ref mut plain) = plain {
+                        plain.push_str(t.as_str());
+                    }
+                    self.i += 1;
+                    continue;
+                }
+            }
+
+            plain = None;
+        }
+
+        self.i = self.i.min(self.toks.len());
+        let assignment = matches!(self.toks.get(start..self.i), Some(&[Name(_), Equals, ..]));
+
+        WordInfo { plain, assignment }
+    }
+
+    fn single_quoted(&mut self, modifiers: TokenModifiers) {
+        let modifiers = modifiers | TokenModifiers::QUOTED;
+        self.emit_text(SemanticTokenType::String, modifiers);
+
+        while let Some(tok) = self.peek() {
+            let closed = *tok == SingleQuote;
+            self.emit_text(SemanticTokenType::String, modifiers);
+            if closed {
+                break;
+            }
+        }
+    }
+
+    fn double_quoted(&mut self, modifiers: TokenModifiers) {
+        let modifiers = modifiers | TokenModifiers::QUOTED;
+        self.emit_text(SemanticTokenType::String, modifiers);
+
+        while let Some(tok) = self.peek() {
+            match *tok {
+                DoubleQuote => {
+                    self.emit_text(SemanticTokenType::String, modifiers);
+                    break;
+                }
+
+                Backslash => {
+                    self.emit_text(SemanticTokenType::String, modifiers);
+                    if self.peek().is_some() {
+                        self.emit_text(SemanticTokenType::String, modifiers);
+                    }
+                }
+
+                Dollar | ParamPositional(_) | Backtick => self.expansion(modifiers),
+                _ => self.emit_text(SemanticTokenType::String, modifiers),
+            }
+        }
+    }
+
+    /// Highlights an expansion starting with a `$` or a backtick. If the `$` does not
+    /// start a valid expansion it is treated as a literal (i.e. part of a string if
+    /// quoted, and not highlighted otherwise).
+    fn expansion(&mut self, modifiers: TokenModifiers) {
+        let literal = if modifiers.contains(TokenModifiers::QUOTED)
+            || modifiers.contains(TokenModifiers::HEREDOC)
+        {
+            Some(SemanticTokenType::String)
+        } else {
+            None
+        };
+
+        match (self.peek(), self.peek_at(1), self.peek_at(2)) {
+            (Some(&ParamPositional(_)), _, _) => {
+                self.emit_next(SemanticTokenType::Parameter, modifiers, 1)
+            }
+
+            (Some(&Backtick), _, _) => {
+                self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+                self.code(CodeEnd::Backtick);
+                self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+            }
+
+            (Some(&Dollar), Some(&CurlyOpen), _) => self.parameter_substitution(modifiers),
+
+            (Some(&Dollar), Some(&ParenOpen), Some(&ParenOpen))
+                if is_arithmetic_parens(&self.toks[self.i + 1..]) =>
+            {
+                self.emit_next(SemanticTokenType::Operator, modifiers, 3);
+                self.arithmetic(modifiers | TokenModifiers::ARITHMETIC);
+                self.close_arithmetic(modifiers);
+            }
+
+            (Some(&Dollar), Some(&ParenOpen), _) => {
+                self.emit_next(SemanticTokenType::Operator, modifiers, 2);
+                self.code(CodeEnd::Paren);
+                self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+            }
+
+            (Some(&Dollar), Some(&Name(_)), _) => {
+                self.emit_next(SemanticTokenType::Variable, modifiers, 2)
+            }
+
+            (Some(&Dollar), Some(t), _) if is_special_param(t) => {
+                self.emit_next(SemanticTokenType::Parameter, modifiers, 2)
+            }
+
+            _ => match literal {
+                Some(token_type) => self.emit_text(token_type, modifiers),
+                None => self.i += 1,
+            },
+        }
+    }
+
+    /// Highlights a `${ ... }` substitution, starting at the `$`.
+    fn parameter_substitution(&mut self, modifiers: TokenModifiers) {
+        self.emit_next(SemanticTokenType::Operator, modifiers, 2);
+
+        // A length prefix, e.g. `${#foo}`, or an indirection, e.g. `${!foo}`
+        if let (Some(&Pound), Some(&Name(_))) | (Some(&Bang), Some(&Name(_))) =
+            (self.peek(), self.peek_at(1))
+        {
+            self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+        }
+
+        match self.peek() {
+            Some(&Name(_)) => self.emit_next(SemanticTokenType::Variable, modifiers, 1),
+            Some(Literal(s)) if s.bytes().all(|b| b.is_ascii_digit()) => {
+                self.emit_next(SemanticTokenType::Parameter, modifiers, 1)
+            }
+            Some(t) if is_special_param(t) => {
+                self.emit_next(SemanticTokenType::Parameter, modifiers, 1)
+            }
+            _ => {}
+        }
+
+        let operator = self
+            .toks
+            .get(self.i..)
+            .unwrap_or(&[])
+            .iter()
+            .take_while(|t| {
+                matches!(
+                    **t,
+                    Colon
+                        | Dash
+                        | Equals
+                        | Question
+                        | Plus
+                        | Percent
+                        | Pound
+                        | Slash
+                        | Caret
+                        | Comma
+                )
+            })
+            .count();
+        self.emit_next(SemanticTokenType::Operator, modifiers, operator);
+
+        // The word, e.g. `bar` in `${foo:-bar}`, up to the matching `}`
+        let mut depth = 0usize;
+        while let Some(tok) = self.peek() {
+            match *tok {
+                CurlyClose if depth == 0 => {
+                    self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+                    return;
+                }
+
+                CurlyOpen | CurlyClose => {
+                    if *tok == CurlyOpen {
+                        depth += 1;
+                    } else {
+                        depth -= 1;
+                    }
+                    self.word_text(modifiers);
+                }
+
+                SingleQuote if !modifiers.contains(TokenModifiers::QUOTED) => {
+                    self.single_quoted(modifiers)
+                }
+                DoubleQuote if !modifiers.contains(TokenModifiers::QUOTED) => {
+                    self.double_quoted(modifiers)
+                }
+
+                Backslash => {
+                    self.word_text(modifiers);
+                    if self.peek().is_some() {
+                        self.word_text(modifiers);
+                    }
+                }
+
+                Dollar | ParamPositional(_) | Backtick => self.expansion(modifiers),
+                _ => self.word_text(modifiers),
+            }
+        }
+    }
+
+    /// Highlights literal text within the word of a parameter substitution,
+    /// which is only considered a string if the substitution itself is quoted.
+    fn word_text(&mut self, modifiers: TokenModifiers) {
+        if modifiers.contains(TokenModifiers::QUOTED) || modifiers.contains(TokenModifiers::HEREDOC)
+        {
+            self.emit_text(SemanticTokenType::String, modifiers);
+        } else {
+            self.i += 1;
+        }
+    }
+
+    /// Highlights an arithmetic expression up to (but not including) the closing `))`.
+    fn arithmetic(&mut self, modifiers: TokenModifiers) {
+        let mut depth = 0usize;
+
+        while let Some(tok) = self.peek() {
+            match *tok {
+                ParenClose if depth == 0 => return,
+                ParenOpen => {
+                    depth += 1;
+                    self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+                }
+                ParenClose => {
+                    depth -= 1;
+                    self.emit_next(SemanticTokenType::Operator, modifiers, 1);
+                }
+
+                Whitespace(_) | Newline => self.i += 1,
+                Name(_) => self.emit_next(SemanticTokenType::Variable, modifiers, 1),
+                Literal(ref s) if s.starts_with(|c: char| c.is_ascii_digit()) => {
+                    // Literals with an explicit base, e.g. `16#ff`, span several tokens
+                    let len = match self.peek_at(1) {
+                        Some(&Pound) => {
+                            2 + self.toks[self.i + 2..]
+                                .iter()
+                                .take_while(|t| matches!(**t, Name(_) | Literal(_) | At))
+                                .count()
+                        }
+                        _ => 1,
+                    };
+                    self.emit_next(SemanticTokenType::Number, modifiers, len)
+                }
+
+                Dollar | ParamPositional(_) | Backtick => self.expansion(modifiers),
+
+                SingleQuote | DoubleQuote | Backslash | Literal(_) => self.i += 1,
+
+                // Multi-character operators such as `<=` or `+=` consist of several tokens
+                _ => {
+                    let operator = self.toks[self.i..]
+                        .iter()
+                        .take_while(|t| is_arithmetic_operator(t))
+                        .count();
+                    self.emit_next(SemanticTokenType::Operator, modifiers, operator.max(1));
+                }
+            }
+        }
+    }
+
+    /// Highlights the closing `))` of an arithmetic context, if present.
+    fn close_arithmetic(&mut self, modifiers: TokenModifiers) {
+        let parens = match (self.peek(), self.peek_at(1)) {
+            (Some(&ParenClose), Some(&ParenClose)) => 2,
+            (Some(&ParenClose), _) => 1,
+            _ => 0,
+        };
+        self.emit_next(SemanticTokenType::Operator, modifiers, parens);
+    }
+
+    /// Highlights the delimiter following a `<<` or `<<-` operator, and queues up
+    /// the heredoc body to be highlighted after the current line.
+    fn heredoc_delimiter(&mut self, strip_tabs: bool) {
+        while let Some(&Whitespace(_)) = self.peek() {
+            self.i += 1;
+        }
+
+        let modifiers = TokenModifiers::HEREDOC;
+        let mut delim = String::new();
+        let mut quoted = false;
+
+        while let Some(tok) = self.peek() {
+            if tok.is_word_delimiter() {
+                break;
+            }
+
+            match *tok {
+                SingleQuote | DoubleQuote => {
+                    let quote = tok.clone();
+                    quoted = true;
+                    self.emit_text(SemanticTokenType::String, modifiers);
+
+                    while let Some(tok) = self.peek() {
+                        if *tok == quote {
+                            break;
+                        }
+
+                        if *tok == Backslash && quote == DoubleQuote {
+                            self.emit_text(SemanticTokenType::String, modifiers);
+                        }
+
+                        if let Some(tok) = self.peek() {
+                            delim.push_str(tok.as_str());
+                        }
+                        self.emit_text(SemanticTokenType::String, modifiers);
+                    }
+
+                    self.emit_text(SemanticTokenType::String, modifiers);
+                }
+
+                Backslash => {
+                    quoted = true;
+                    self.emit_text(SemanticTokenType::String, modifiers);
+                    if let Some(tok) = self.peek() {
+                        delim.push_str(tok.as_str());
+                        self.emit_text(SemanticTokenType::String, modifiers);
+                    }
+                }
+
+                _ => {
+                    delim.push_str(tok.as_str());
+                    self.emit_text(SemanticTokenType::String, modifiers);
+                }
+            }
+        }
+
+        self.i = self.i.min(self.toks.len());
+        if !delim.is_empty() {
+            self.heredocs.push(Heredoc {
+                delim,
+                quoted,
+                strip_tabs,
+            });
+        }
+    }
+
+    /// Highlights the bodies of any pending heredocs, starting at the beginning of a line.
+    fn heredoc_bodies(&mut self) {
+        let heredocs = std::mem::take(&mut self.heredocs);
+        let modifiers = TokenModifiers::HEREDOC;
+
+        for heredoc in heredocs {
+            while self.peek().is_some() {
+                let line_end = self.toks[self.i..]
+                    .iter()
+                    .position(|t| *t == Newline)
+                    .map_or(self.toks.len(), |n| self.i + n);
+
+                let line = self.toks[self.i..line_end]
+                    .iter()
+                    .map(Token::as_str)
+                    .collect::<String>();
+                let line = if heredoc.strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    &line
+                };
+
+                if line == heredoc.delim || line.strip_suffix('\r') == Some(heredoc.delim.as_str())
+                {
+                    while self.i < line_end {
+                        self.emit_text(SemanticTokenType::String, modifiers);
+                    }
+                    self.i = (line_end + 1).min(self.toks.len());
+                    break;
+                }
+
+                while let Some(tok) = self.peek() {
+                    match *tok {
+                        Newline => {
+                            self.i += 1;
+                            break;
+                        }
+
+                        Dollar | ParamPositional(_) | Backtick if !heredoc.quoted => {
+                            self.expansion(modifiers)
+                        }
+
+                        Backslash if !heredoc.quoted => {
+                            self.emit_text(SemanticTokenType::String, modifiers);
+                            if self.peek().is_some() {
+                                self.emit_text(SemanticTokenType::String, modifiers);
+                            }
+                        }
+
+                        _ => self.emit_text(SemanticTokenType::String, modifiers),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if the tokens start with a `((` whose matching paren is
+/// immediately followed by a `)`, i.e. an arithmetic context rather than
+/// nested subshells such as `((a) | b)`.
+fn is_arithmetic_parens(toks: &[Token]) -> bool {
+    if !matches!(toks, [ParenOpen, ParenOpen, ..]) {
+        return false;
+    }
+
+    let mut depth = 0usize;
+    let mut iter = toks[2..].iter();
+    while let Some(tok) = iter.next() {
+        match *tok {
+            ParenOpen => depth += 1,
+            ParenClose if depth > 0 => depth -= 1,
+            ParenClose => return iter.next() == Some(&ParenClose),
+
+            SingleQuote | DoubleQuote | Backtick => loop {
+                match iter.next() {
+                    Some(t) if t == tok => break,
+                    Some(&Backslash) if *tok != SingleQuote => {
+                        iter.next();
+                    }
+                    Some(_) => {}
+                    None => return true,
+                }
+            },
+
+            Backslash => {
+                iter.next();
+            }
+
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Returns `true` if the token may be a part of an arithmetic operator.
+fn is_arithmetic_operator(tok: &Token) -> bool {
+    !matches!(
+        *tok,
+        ParenOpen
+            | ParenClose
+            | Whitespace(_)
+            | Newline
+            | Name(_)
+            | Literal(_)
+            | Dollar
+            | ParamPositional(_)
+            | Backtick
+            | SingleQuote
+            | DoubleQuote
+            | Backslash
+            | SquareOpen
+            | SquareClose
+    )
+}
+
+/// Returns `true` if the token following a `$` denotes a special parameter.
+fn is_special_param(tok: &Token) -> bool {
+    matches!(*tok, At | Star | Pound | Question | Dash | Dollar | Bang)
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "then"
+            | "else"
+            | "elif"
+            | "fi"
+            | "do"
+            | "done"
+            | "case"
+            | "esac"
+            | "while"
+            | "until"
+            | "for"
+            | "in"
+            | "select"
+            | "function"
+            | "time"
+            | "{"
+            | "}"
+            | "!"
+            | "[["
+            | "]]"
+    )
+}
+
+/// Returns `true` if a command may immediately follow the keyword.
+fn starts_command(keyword: &str) -> bool {
+    matches!(
+        keyword,
+        "if" | "then" | "else" | "elif" | "do" | "while" | "until" | "time" | "{" | "!"
+    )
+}
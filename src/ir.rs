@@ -0,0 +1,474 @@
+//! A simplified representation of commands for analyzers and interpreters.
+//!
+//! The AST mirrors the grammar of the shell closely (e.g. a command is a
+//! `Command` of an `AndOrList` of `ListableCommand`s of `PipeableCommand`s), so
+//! that the source can be reproduced faithfully. Tools which only care about
+//! what a script does can instead `lower` the AST into a single `Stmt` tree,
+//! which removes the syntactic distinctions that make no semantic difference:
+//!
+//! * The layers of the AST are collapsed into `Stmt`, and `&&`/`||` lists become
+//!   left associative `Stmt::And` and `Stmt::Or` nodes (e.g. `a && b || c` is
+//!   lowered as `Or(And(a, b), c)`).
+//! * Command lists are flattened into a single `Stmt::Seq`, including the bodies
+//!   of brace groups without redirections, while a list of a single command is
+//!   lowered as the command itself.
+//! * Every redirection names the file descriptor it applies to, duplications and
+//!   closures share `RedirectOp::Dup`, and shorthands are expanded: `&>file` becomes
+//!   `>file 2>&1`, and `a |& b` redirects `2>&1` for `a` after its own redirections.
+//! * An `elif` becomes a nested `Stmt::If` within the else branch, an `until` loop
+//!   becomes a `Stmt::While` loop with a negated guard, and a `for` loop without
+//!   an `in` clause iterates over `"$@"`.
+//! * Negations are pushed down where doing so preserves the exit status: `! ! a`
+//!   becomes `a`, the negation of a list negates its last command,
+//!   and within `[[ ... ]]` negations are moved towards (and absorbed into) the
+//!   tests themselves, e.g. `[[ ! (-n $a && $b == c) ]]` becomes `[[ -z $a || $b != c ]]`.
+//!
+//! Words are kept as they appear in the AST, and the lowered representation
+//! does not retain any comments, thus it cannot be converted back into the
+//! exact source. Commands nested within words (e.g. command substitutions)
+//! are not lowered.
+
+use crate::ast::{
+    AndOr, BinaryTest, CaseTerminator, Command, ComplexWord, CompoundCommandKind, Conditional,
+    DefaultArithmetic, DefaultCompoundCommand, DefaultListableCommand, DefaultPipeableCommand, DefaultRedirect,
+    DefaultSimpleCommand, ListableCommand, Parameter, PipeableCommand, Redirect, RedirectOrCmdWord,
+    RedirectOrEnvVar, SimpleWord, TopLevelCommand, TopLevelWord, UnparsedSpan, Word,
+};
+use std::mem;
+
+/// The representation of words within lowered commands.
+pub type IrWord = TopLevelWord<String>;
+
+/// A lowered command, see the module documentation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Stmt {
+    /// Runs a simple command, or performs its assignments and redirections
+    /// if it has no words.
+    Exec(Exec),
+    /// Runs every command in order, in the current environment.
+    Seq(Vec<Stmt>),
+    /// Runs the command in a subshell environment.
+    Subshell(Box<Stmt>),
+    /// Runs the command asynchronously, e.g. `a &`.
+    Background(Box<Stmt>),
+    /// Runs the commands concurrently, connecting the standard output of each to
+    /// the standard input of the next.
+    Pipeline(Vec<Stmt>),
+    /// Runs the second command only if the first one succeeds.
+    And(Box<Stmt>, Box<Stmt>),
+    /// Runs the second command only if the first one fails.
+    Or(Box<Stmt>, Box<Stmt>),
+    /// Runs the command, inverting its exit status.
+    Not(Box<Stmt>),
+    /// Performs the redirections (in order) before running the command, and
+    /// restores the affected file descriptors afterwards.
+    Redirected(Box<Stmt>, Vec<Redirection>),
+    /// Runs the `then` branch if the guard succeeds, or the `else` branch otherwise.
+    If {
+        /// The guard of the conditional.
+        guard: Box<Stmt>,
+        /// The command to run if the guard succeeds.
+        then: Box<Stmt>,
+        /// The command to run if the guard fails, if any.
+        otherwise: Option<Box<Stmt>>,
+    },
+    /// Runs the body as long as the guard succeeds.
+    While {
+        /// The guard of the loop.
+        guard: Box<Stmt>,
+        /// The body of the loop.
+        body: Box<Stmt>,
+    },
+    /// Runs the body once for each word, after assigning it to the variable.
+    For {
+        /// The name of the loop variable.
+        var: String,
+        /// The words to iterate over.
+        words: Vec<IrWord>,
+        /// The body of the loop.
+        body: Box<Stmt>,
+    },
+    /// Runs the body of the first arm with a pattern that matches the word.
+    Case {
+        /// The word to match.
+        word: IrWord,
+        /// The arms to match against, in order.
+        arms: Vec<CaseArm>,
+    },
+    /// Evaluates a `[[ ... ]]` expression.
+    Test(Conditional<IrWord>),
+    /// Evaluates a `(( ... ))` expression, or `None` if it is empty.
+    Arith(Option<DefaultArithmetic>),
+    /// Defines a function.
+    Function {
+        /// The name of the function.
+        name: String,
+        /// The body of the function.
+        body: Box<Stmt>,
+    },
+    /// A region of the source which failed to parse, see `ErrorRecovery::Passthrough`.
+    Unparsed(UnparsedSpan),
+}
+
+/// A simple command with its redirections separated from its words.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exec {
+    /// The variables assigned for the command (or the current environment
+    /// if there are no words), in order, along with their values.
+    pub assignments: Vec<(String, Option<IrWord>)>,
+    /// The name of the command followed by its arguments.
+    pub words: Vec<IrWord>,
+    /// The redirections of the command, in the order they are performed.
+    pub redirects: Vec<Redirection>,
+}
+
+/// A normalized redirection of a single file descriptor.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redirection {
+    /// The file descriptor being redirected.
+    pub fd: u16,
+    /// What the file descriptor is redirected to.
+    pub op: RedirectOp,
+    /// The target of the redirection: a path, the file descriptor to
+    /// duplicate (or `-` to close it), or the body of a heredoc.
+    pub target: IrWord,
+}
+
+/// The kind of a `Redirection`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum RedirectOp {
+    /// Opens the target for reading, e.g. `<file`.
+    Read,
+    /// Opens the target for writing, truncating it, e.g. `>file`.
+    Write,
+    /// Opens the target for writing, even if `noclobber` is set, e.g. `>|file`.
+    Clobber,
+    /// Opens the target for appending, e.g. `>>file`.
+    Append,
+    /// Opens the target for reading and writing, e.g. `<>file`.
+    ReadWrite,
+    /// Reads from the body of a heredoc, e.g. `<<EOF`.
+    Heredoc,
+    /// Duplicates (or closes) another file descriptor, e.g. `2>&1` or `<&-`.
+    Dup,
+}
+
+/// An arm of a `Stmt::Case`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseArm {
+    /// The patterns of the arm.
+    pub patterns: Vec<IrWord>,
+    /// The body of the arm.
+    pub body: Box<Stmt>,
+    /// What happens after the body has run.
+    pub terminator: CaseTerminator,
+}
+
+/// Lowers a list of commands (e.g. an entire script) into a single statement.
+pub fn lower(cmds: &[TopLevelCommand<String>]) -> Stmt {
+    let mut stmts = Vec::new();
+    for cmd in cmds {
+        push_stmt(&mut stmts, lower_command(cmd));
+    }
+
+    if stmts.len() == 1 {
+        stmts.pop().unwrap()
+    } else {
+        Stmt::Seq(stmts)
+    }
+}
+
+/// Lowers a single command.
+pub fn lower_command(cmd: &TopLevelCommand<String>) -> Stmt {
+    match cmd.0 {
+        Command::List(ref list) => lower_and_or(&list.first, &list.rest),
+        Command::Job(ref list) => Stmt::Background(Box::new(lower_and_or(&list.first, &list.rest))),
+        Command::Unparsed(ref span) => Stmt::Unparsed(span.clone()),
+    }
+}
+
+/// Appends a statement to a list, flattening it if it is a list itself.
+fn push_stmt(stmts: &mut Vec<Stmt>, stmt: Stmt) {
+    match stmt {
+        Stmt::Seq(inner) => stmts.extend(inner),
+        stmt => stmts.push(stmt),
+    }
+}
+
+fn lower_and_or(first: &DefaultListableCommand, rest: &[AndOr<DefaultListableCommand>]) -> Stmt {
+    rest.iter()
+        .fold(lower_listable(first), |lhs, and_or| match *and_or {
+            AndOr::And(ref cmd) => Stmt::And(Box::new(lhs), Box::new(lower_listable(cmd))),
+            AndOr::Or(ref cmd) => Stmt::Or(Box::new(lhs), Box::new(lower_listable(cmd))),
+        })
+}
+
+fn lower_listable(cmd: &DefaultListableCommand) -> Stmt {
+    let pipeline = match *cmd {
+        ListableCommand::Single(ref cmd) => return lower_pipeable(cmd),
+        ListableCommand::Pipe(ref pipeline) => pipeline,
+    };
+
+    let mut stages = pipeline
+        .segments
+        .iter()
+        .map(|segment| lower_pipeable(&segment.cmd))
+        .collect::<Vec<_>>();
+
+    // `a |& b` is shorthand for `a 2>&1 | b`, performed after any redirections of `a`
+    for (i, segment) in pipeline.segments.iter().enumerate().skip(1) {
+        if segment.pipe_stderr {
+            let stage = &mut stages[i - 1];
+            let dup = dup(2, 1);
+            match *stage {
+                Stmt::Exec(ref mut exec) => exec.redirects.push(dup),
+                Stmt::Redirected(_, ref mut redirects) => redirects.push(dup),
+                _ => {
+                    let inner = mem::replace(stage, Stmt::Seq(Vec::new()));
+                    *stage = Stmt::Redirected(Box::new(inner), vec![dup]);
+                }
+            }
+        }
+    }
+
+    let stmt = if stages.len() == 1 {
+        stages.pop().unwrap()
+    } else {
+        Stmt::Pipeline(stages)
+    };
+
+    if pipeline.bang {
+        negate(stmt)
+    } else {
+        stmt
+    }
+}
+
+fn lower_pipeable(cmd: &DefaultPipeableCommand) -> Stmt {
+    match *cmd {
+        PipeableCommand::Simple(ref simple) => Stmt::Exec(lower_simple(simple)),
+        PipeableCommand::Compound(ref compound) => lower_compound(compound),
+        PipeableCommand::FunctionDef(ref name, _, ref body) => Stmt::Function {
+            name: name.clone(),
+            body: Box::new(lower_compound(body)),
+        },
+    }
+}
+
+fn lower_simple(cmd: &DefaultSimpleCommand) -> Exec {
+    let mut exec = Exec {
+        assignments: Vec::new(),
+        words: Vec::new(),
+        redirects: Vec::new(),
+    };
+
+    for item in &cmd.redirects_or_env_vars {
+        match *item {
+            RedirectOrEnvVar::Redirect(ref redirect) => {
+                lower_redirect(redirect, &mut exec.redirects)
+            }
+            RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                exec.assignments.push((name.clone(), value.clone()))
+            }
+        }
+    }
+
+    for item in &cmd.redirects_or_cmd_words {
+        match *item {
+            RedirectOrCmdWord::Redirect(ref redirect) => {
+                lower_redirect(redirect, &mut exec.redirects)
+            }
+            RedirectOrCmdWord::CmdWord(ref word) => exec.words.push(word.clone()),
+        }
+    }
+
+    exec
+}
+
+fn lower_compound(cmd: &DefaultCompoundCommand) -> Stmt {
+    let stmt = match cmd.kind {
+        CompoundCommandKind::Brace(ref cmds) => lower(cmds),
+        CompoundCommandKind::Subshell(ref cmds) => Stmt::Subshell(Box::new(lower(cmds))),
+        CompoundCommandKind::While(ref pair) => Stmt::While {
+            guard: Box::new(lower(&pair.guard)),
+            body: Box::new(lower(&pair.body)),
+        },
+        CompoundCommandKind::Until(ref pair) => Stmt::While {
+            guard: Box::new(negate(lower(&pair.guard))),
+            body: Box::new(lower(&pair.body)),
+        },
+        CompoundCommandKind::If {
+            ref conditionals,
+            ref else_branch,
+        } => {
+            let otherwise = else_branch.as_ref().map(|cmds| Box::new(lower(cmds)));
+            conditionals
+                .iter()
+                .rev()
+                .fold(otherwise, |otherwise, pair| {
+                    Some(Box::new(Stmt::If {
+                        guard: Box::new(lower(&pair.guard)),
+                        then: Box::new(lower(&pair.body)),
+                        otherwise,
+                    }))
+                })
+                .map_or(Stmt::Seq(Vec::new()), |stmt| *stmt)
+        }
+        CompoundCommandKind::For {
+            ref var,
+            ref words,
+            ref body,
+        } => Stmt::For {
+            var: var.clone(),
+            words: words.clone().unwrap_or_else(|| vec![quoted_at()]),
+            body: Box::new(lower(body)),
+        },
+        CompoundCommandKind::Case { ref word, ref arms } => Stmt::Case {
+            word: word.clone(),
+            arms: arms
+                .iter()
+                .map(|arm| CaseArm {
+                    patterns: arm.patterns.clone(),
+                    body: Box::new(lower(&arm.body)),
+                    terminator: arm.terminator,
+                })
+                .collect(),
+        },
+        CompoundCommandKind::Conditional(ref expr) => {
+            Stmt::Test(push_down_not(expr.clone(), false))
+        }
+        CompoundCommandKind::Arithmetic(ref expr) => Stmt::Arith(expr.clone()),
+    };
+
+    if cmd.io.is_empty() {
+        return stmt;
+    }
+
+    let mut redirects = Vec::new();
+    for redirect in &cmd.io {
+        lower_redirect(redirect, &mut redirects);
+    }
+    Stmt::Redirected(Box::new(stmt), redirects)
+}
+
+fn lower_redirect(redirect: &DefaultRedirect, out: &mut Vec<Redirection>) {
+    let (fd, op, target) = match *redirect {
+        Redirect::Read(fd, ref w) => (fd.unwrap_or(0), RedirectOp::Read, w),
+        Redirect::Write(fd, ref w) => (fd.unwrap_or(1), RedirectOp::Write, w),
+        Redirect::ReadWrite(fd, ref w) => (fd.unwrap_or(0), RedirectOp::ReadWrite, w),
+        Redirect::Append(fd, ref w) => (fd.unwrap_or(1), RedirectOp::Append, w),
+        Redirect::Clobber(fd, ref w) => (fd.unwrap_or(1), RedirectOp::Clobber, w),
+        Redirect::Heredoc(fd, ref w, _) => (fd.unwrap_or(0), RedirectOp::Heredoc, w),
+        Redirect::DupRead(fd, ref w) => (fd.unwrap_or(0), RedirectOp::Dup, w),
+        Redirect::DupWrite(fd, ref w) => (fd.unwrap_or(1), RedirectOp::Dup, w),
+        Redirect::WriteBoth(ref w) => (1, RedirectOp::Write, w),
+        Redirect::AppendBoth(ref w) => (1, RedirectOp::Append, w),
+    };
+
+    out.push(Redirection {
+        fd,
+        op,
+        target: target.clone(),
+    });
+
+    // `&>file` is shorthand for `>file 2>&1`
+    if let Redirect::WriteBoth(_) | Redirect::AppendBoth(_) = *redirect {
+        out.push(dup(2, 1));
+    }
+}
+
+/// Creates a redirection which duplicates `src` onto `fd`.
+fn dup(fd: u16, src: u16) -> Redirection {
+    Redirection {
+        fd,
+        op: RedirectOp::Dup,
+        target: TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(
+            src.to_string(),
+        )))),
+    }
+}
+
+/// Creates the word `"$@"`.
+fn quoted_at() -> IrWord {
+    TopLevelWord(ComplexWord::Single(Word::DoubleQuoted(vec![
+        SimpleWord::Param(Parameter::At),
+    ])))
+}
+
+/// Inverts the exit status of a statement, pushing the negation down where
+/// doing so preserves the resulting status.
+fn negate(stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Not(inner) => *inner,
+        Stmt::Seq(mut stmts) => match stmts.pop() {
+            Some(last) => {
+                stmts.push(negate(last));
+                Stmt::Seq(stmts)
+            }
+            None => Stmt::Not(Box::new(Stmt::Seq(stmts))),
+        },
+        stmt => Stmt::Not(Box::new(stmt)),
+    }
+}
+
+/// Moves the negations of a `[[ ... ]]` expression towards its tests,
+/// negating the expression itself if `negated` is set.
+fn push_down_not(expr: Conditional<IrWord>, negated: bool) -> Conditional<IrWord> {
+    let binary = |l: Box<Conditional<IrWord>>, r: Box<Conditional<IrWord>>| {
+        (
+            Box::new(push_down_not(*l, negated)),
+            Box::new(push_down_not(*r, negated)),
+        )
+    };
+
+    match expr {
+        Conditional::Not(inner) => push_down_not(*inner, !negated),
+        Conditional::And(l, r) => {
+            let (l, r) = binary(l, r);
+            if negated {
+                Conditional::Or(l, r)
+            } else {
+                Conditional::And(l, r)
+            }
+        }
+        Conditional::Or(l, r) => {
+            let (l, r) = binary(l, r);
+            if negated {
+                Conditional::And(l, r)
+            } else {
+                Conditional::Or(l, r)
+            }
+        }
+        expr if !negated => expr,
+        Conditional::Unary('n', w) => Conditional::Unary('z', w),
+        Conditional::Unary('z', w) => Conditional::Unary('n', w),
+        Conditional::Binary(l, op, r) => match inverse_test(op) {
+            Some(op) => Conditional::Binary(l, op, r),
+            None => Conditional::Not(Box::new(Conditional::Binary(l, op, r))),
+        },
+        expr => Conditional::Not(Box::new(expr)),
+    }
+}
+
+/// Returns the test which succeeds exactly when `op` fails, if there is one.
+fn inverse_test(op: BinaryTest) -> Option<BinaryTest> {
+    let inverse = match op {
+        BinaryTest::Match => BinaryTest::NotMatch,
+        BinaryTest::NotMatch => BinaryTest::Match,
+        BinaryTest::NumEq => BinaryTest::NumNotEq,
+        BinaryTest::NumNotEq => BinaryTest::NumEq,
+        BinaryTest::NumLess => BinaryTest::NumGreaterEq,
+        BinaryTest::NumGreaterEq => BinaryTest::NumLess,
+        BinaryTest::NumLessEq => BinaryTest::NumGreater,
+        BinaryTest::NumGreater => BinaryTest::NumLessEq,
+        _ => return None,
+    };
+    Some(inverse)
+}
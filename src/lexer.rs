@@ -3,7 +3,18 @@
 use self::TokenOrLiteral::*;
 use super::token::Token::*;
 use super::token::{Positional, Token};
-use std::iter::{Fuse, Peekable};
+
+#[cfg(feature = "async")]
+mod async_read;
+mod decode;
+mod read;
+mod spanned;
+
+#[cfg(feature = "async")]
+pub use self::async_read::AsyncReadChars;
+pub use self::decode::{Decoded, Encoding, Transcoder};
+pub use self::read::ReadChars;
+pub use self::spanned::SpannedTokens;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 enum TokenOrLiteral {
@@ -12,23 +23,103 @@ enum TokenOrLiteral {
     Lit(char),
 }
 
+/// A fused and peekable character iterator which, unlike `Peekable<Fuse<I>>`,
+/// retains access to the underlying iterator.
+#[derive(Clone, Debug)]
+struct Chars<I> {
+    iter: I,
+    peeked: Option<char>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = char>> Chars<I> {
+    fn peek(&mut self) -> Option<&char> {
+        if self.peeked.is_none() {
+            self.peeked = self.next();
+        }
+
+        self.peeked.as_ref()
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Chars<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.peeked.take() {
+            return Some(c);
+        } else if self.done {
+            return None;
+        }
+
+        let next = self.iter.next();
+        self.done = next.is_none();
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let peeked = if self.peeked.is_some() { 1 } else { 0 };
+        if self.done {
+            return (peeked, Some(peeked));
+        }
+
+        let (lo, hi) = self.iter.size_hint();
+        (
+            lo.saturating_add(peeked),
+            hi.and_then(|hi| hi.checked_add(peeked)),
+        )
+    }
+}
+
 /// Converts raw characters into shell tokens.
+///
+/// Since the lexer consumes characters rather than borrowing a source buffer,
+/// every literal, name, and whitespace token owns its text as a `String` (as do
+/// the words built from them), and there is no mode which borrows from the source.
 #[must_use = "`Lexer` is lazy and does nothing unless consumed"]
 #[derive(Clone, Debug)]
 pub struct Lexer<I: Iterator<Item = char>> {
-    inner: Peekable<Fuse<I>>,
+    inner: Chars<I>,
     peeked: Option<TokenOrLiteral>,
+    /// Whether the next character is the second `$` of a `$$` parameter.
+    dollar_pair: bool,
 }
 
 impl<I: Iterator<Item = char>> Lexer<I> {
     /// Creates a new Lexer from any char iterator.
     pub fn new(iter: I) -> Lexer<I> {
         Lexer {
-            inner: iter.fuse().peekable(),
+            inner: Chars {
+                iter,
+                peeked: None,
+                done: false,
+            },
             peeked: None,
+            dollar_pair: false,
         }
     }
 
+    /// Returns a reference to the underlying char iterator.
+    ///
+    /// Note that the lexer may have already consumed some characters
+    /// beyond the last token it yielded.
+    pub fn get_ref(&self) -> &I {
+        &self.inner.iter
+    }
+
+    /// Returns a mutable reference to the underlying char iterator.
+    ///
+    /// Note that the lexer may have already consumed some characters
+    /// beyond the last token it yielded.
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.inner.iter
+    }
+
+    /// Pairs every token with its location in the source, see `SpannedTokens`.
+    pub fn spanned(self) -> SpannedTokens<Self> {
+        SpannedTokens::new(self)
+    }
+
     #[inline]
     fn next_is(&mut self, c: char) -> bool {
         let is = self.inner.peek() == Some(&c);
@@ -111,6 +202,16 @@ impl<I: Iterator<Item = char>> Lexer<I> {
             '[' => SquareOpen,
             ']' => SquareClose,
 
+            // Make sure the second `$` of `$$` is not treated as the start of a
+            // positional parameter, e.g. `$$1` is `$$` followed by a `1`.
+            '$' if self.dollar_pair => {
+                self.dollar_pair = false;
+                Dollar
+            }
+            '$' if self.inner.peek() == Some(&'$') => {
+                self.dollar_pair = true;
+                Dollar
+            }
             '$' => {
                 // Positional parameters are 0-9, so we only
                 // need to check a single digit ahead.
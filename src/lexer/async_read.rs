@@ -0,0 +1,118 @@
+//! Defines an adapter for decoding characters from an asynchronous byte stream.
+
+use super::read::{decode_char, CHUNK_SIZE};
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A stream which lazily decodes UTF-8 characters from an `AsyncRead` source,
+/// the asynchronous counterpart of `ReadChars` (see `parse::AsyncParser`).
+///
+/// Characters are decoded correctly regardless of how the underlying reader
+/// splits up its data. Any invalid UTF-8 sequences are replaced with U+FFFD.
+///
+/// If the reader returns an error, the stream ends as if the end of input was
+/// reached, and the error is retained so it can be inspected via `error` or
+/// `take_error`.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct AsyncReadChars<R> {
+    /// The underlying reader.
+    reader: R,
+    /// Bytes read from the reader but not yet decoded.
+    buf: Vec<u8>,
+    /// The offset of the first undecoded byte within `buf`.
+    start: usize,
+    /// Whether the reader has reached its end (or failed).
+    done: bool,
+    /// The error returned by the reader, if any.
+    error: Option<io::Error>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReadChars<R> {
+    /// Creates a new adapter over the provided reader.
+    pub fn new(reader: R) -> Self {
+        AsyncReadChars {
+            reader,
+            buf: Vec::new(),
+            start: 0,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// Returns the error the underlying reader failed with, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error the underlying reader failed with, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Unwraps the adapter, returning the underlying reader.
+    ///
+    /// Any bytes which were read but not yet decoded are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads another chunk of data into the buffer, discarding any consumed bytes.
+    fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.buf.drain(..self.start);
+        self.start = 0;
+
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+
+        loop {
+            match Pin::new(&mut self.reader).poll_read(cx, &mut self.buf[len..]) {
+                Poll::Ready(Ok(n)) => {
+                    self.buf.truncate(len + n);
+                    self.done = n == 0;
+                    return Poll::Ready(());
+                }
+                Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => {
+                    self.buf.truncate(len);
+                    self.error = Some(e);
+                    self.done = true;
+                    return Poll::Ready(());
+                }
+                Poll::Pending => {
+                    self.buf.truncate(len);
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncReadChars<R> {
+    type Item = char;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<char>> {
+        let this = self.get_mut();
+        loop {
+            let pending = &this.buf[this.start..];
+            if pending.is_empty() && this.done {
+                return Poll::Ready(None);
+            }
+
+            if !pending.is_empty() {
+                if let Some((c, len)) = decode_char(pending, this.done) {
+                    this.start += len;
+                    return Poll::Ready(Some(c));
+                }
+            }
+
+            // The buffer is empty, or ends in the middle of a character
+            if this.poll_fill_buf(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
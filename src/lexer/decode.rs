@@ -0,0 +1,192 @@
+//! Defines a layer for decoding sources which are not encoded as UTF-8.
+
+use super::read::decode_char;
+use crate::ast::Span;
+use std::ops::Range;
+
+/// A decoder of characters from some encoding of text.
+///
+/// The encodings most commonly found in script archives are supported via
+/// `Encoding`, though other encodings (e.g. Windows-1252 or Shift JIS) can
+/// be decoded by implementing this trait.
+pub trait Transcoder {
+    /// Decodes the first character of a non-empty buffer, returning it along
+    /// with the number of bytes it occupies, which must be at least one.
+    ///
+    /// Invalid (or truncated) sequences should be decoded as U+FFFD.
+    fn decode_char(&mut self, bytes: &[u8]) -> (char, usize);
+}
+
+impl<T: Transcoder + ?Sized> Transcoder for &mut T {
+    fn decode_char(&mut self, bytes: &[u8]) -> (char, usize) {
+        (**self).decode_char(bytes)
+    }
+}
+
+/// The encodings which can be decoded out of the box.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Encoding {
+    /// UTF-8, the encoding the parser works with internally.
+    #[default]
+    Utf8,
+    /// ISO 8859-1, where every byte is the code point of the character.
+    Latin1,
+    /// UTF-16 with the least significant byte of each code unit first.
+    Utf16Le,
+    /// UTF-16 with the most significant byte of each code unit first.
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Detects the encoding of a source from its byte order mark (if any),
+    /// returning it along with the length of the mark in bytes.
+    pub fn from_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        if bytes.starts_with(b"\xEF\xBB\xBF") {
+            Some((Encoding::Utf8, 3))
+        } else if bytes.starts_with(b"\xFF\xFE") {
+            Some((Encoding::Utf16Le, 2))
+        } else if bytes.starts_with(b"\xFE\xFF") {
+            Some((Encoding::Utf16Be, 2))
+        } else {
+            None
+        }
+    }
+}
+
+impl Transcoder for Encoding {
+    fn decode_char(&mut self, bytes: &[u8]) -> (char, usize) {
+        let unit = |b: &[u8]| match *self {
+            Encoding::Utf16Be => u16::from_be_bytes([b[0], b[1]]),
+            _ => u16::from_le_bytes([b[0], b[1]]),
+        };
+
+        match *self {
+            Encoding::Utf8 => decode_char(bytes, true).unwrap(),
+            Encoding::Latin1 => (char::from(bytes[0]), 1),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if bytes.len() < 2 {
+                    return (std::char::REPLACEMENT_CHARACTER, bytes.len());
+                }
+
+                let units = bytes[..bytes.len().min(4)].chunks_exact(2).map(unit);
+                let c = std::char::decode_utf16(units).next().unwrap();
+                match c {
+                    Ok(c) => (c, c.len_utf16() * 2),
+                    // An unpaired surrogate
+                    Err(_) => (std::char::REPLACEMENT_CHARACTER, 2),
+                }
+            }
+        }
+    }
+}
+
+/// A source decoded into UTF-8 (the parser's internal representation), which
+/// maps the positions within the decoded text back to the original bytes.
+///
+/// The positions reported by the parser (e.g. in the `Span`s passed to a
+/// `Builder`, or in a `ParseError`) are byte offsets within the decoded text,
+/// which `original_byte` and `original_range` translate into offsets within
+/// the original source, e.g. to report them against the original file.
+///
+/// ```
+/// use conch_parser::lexer::{Decoded, Lexer};
+/// use conch_parser::parse::DefaultParser;
+///
+/// // "echo café" encoded as UTF-16 (little endian) with a byte order mark
+/// let bytes: Vec<u8> = "\u{feff}echo café"
+///     .encode_utf16()
+///     .flat_map(|unit| unit.to_le_bytes())
+///     .collect();
+///
+/// let decoded = Decoded::detect(&bytes);
+/// assert_eq!(decoded.text(), "echo café");
+///
+/// let mut parser = DefaultParser::new(Lexer::new(decoded.text().chars()));
+/// parser.complete_command().unwrap();
+/// assert_eq!(parser.pos().byte, 10);
+/// assert_eq!(decoded.original_byte(parser.pos().byte), bytes.len());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Decoded {
+    /// The decoded source.
+    text: String,
+    /// Pairs of positions in the decoded text and the original source, from
+    /// which every following character is as long in both, sorted by position.
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl Decoded {
+    /// Decodes an entire source with the provided transcoder.
+    ///
+    /// Any byte order mark is decoded as a regular character (U+FEFF),
+    /// see `Decoded::detect` for skipping it.
+    pub fn new<T: Transcoder>(bytes: &[u8], transcoder: T) -> Self {
+        Self::decode(bytes, 0, transcoder)
+    }
+
+    /// Decodes an entire source with the encoding indicated by its byte order
+    /// mark, which is skipped. Sources without one are decoded as UTF-8.
+    pub fn detect(bytes: &[u8]) -> Self {
+        Self::detect_or(bytes, Encoding::Utf8)
+    }
+
+    /// Decodes an entire source with the encoding indicated by its byte order
+    /// mark, which is skipped. Sources without one are decoded with the fallback
+    /// transcoder, e.g. `Encoding::Latin1` for archives of legacy scripts.
+    pub fn detect_or<T: Transcoder>(bytes: &[u8], fallback: T) -> Self {
+        match Encoding::from_bom(bytes) {
+            Some((encoding, bom)) => Self::decode(bytes, bom, encoding),
+            None => Self::decode(bytes, 0, fallback),
+        }
+    }
+
+    fn decode<T: Transcoder>(bytes: &[u8], start: usize, mut transcoder: T) -> Self {
+        let mut text = String::with_capacity(bytes.len() - start);
+        let mut checkpoints = vec![(0, start)];
+        let mut original = start;
+
+        while original < bytes.len() {
+            let (c, len) = transcoder.decode_char(&bytes[original..]);
+            assert!(len > 0, "transcoders must consume at least one byte");
+
+            text.push(c);
+            original += len;
+
+            if len != c.len_utf8() {
+                checkpoints.push((text.len(), original));
+            }
+        }
+
+        Decoded { text, checkpoints }
+    }
+
+    /// Returns the decoded source.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Unwraps the decoded source.
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// Translates a byte offset within the decoded text (which should fall on a
+    /// character boundary, as every position reported by the parser does) into
+    /// the offset of the same character within the original source.
+    pub fn original_byte(&self, byte: usize) -> usize {
+        let checkpoints = &self.checkpoints;
+        let idx = match checkpoints.binary_search_by_key(&byte, |&(text, _)| text) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        };
+
+        let (text, original) = checkpoints[idx];
+        original + (byte - text)
+    }
+
+    /// Translates a span within the decoded text into the range of bytes it
+    /// covers within the original source.
+    pub fn original_range(&self, span: Span) -> Range<usize> {
+        self.original_byte(span.start.byte)..self.original_byte(span.end.byte)
+    }
+}
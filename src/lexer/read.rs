@@ -0,0 +1,206 @@
+//! Defines an adapter for lexing characters straight from a byte stream.
+
+use std::cmp;
+use std::io::{self, Read};
+use std::str;
+
+/// The number of bytes to request from the underlying reader at a time.
+pub(super) const CHUNK_SIZE: usize = 8 * 1024;
+
+/// An iterator which lazily decodes UTF-8 characters from an `io::Read` source,
+/// allowing the `Lexer` (and thus the `Parser`) to consume a stream (such as
+/// standard input) without buffering it in its entirety.
+///
+/// Characters are decoded correctly regardless of how the underlying reader
+/// splits up its data. Any invalid UTF-8 sequences are replaced with U+FFFD.
+/// Since the replacement is not necessarily as long as the sequence it replaces,
+/// the byte offsets of any positions reported by the parser will no longer
+/// match the original input after such a sequence; use `source_offset` to map
+/// them back.
+///
+/// If the reader returns an error, iteration stops as if the end of input was
+/// reached, and the error is retained so it can be inspected via `error` or
+/// `take_error`. Since the lexer takes ownership of its input, use `by_ref`
+/// to keep access to the adapter while parsing:
+///
+/// ```
+/// use conch_parser::lexer::{Lexer, ReadChars};
+/// use conch_parser::parse::DefaultParser;
+///
+/// let mut chars = ReadChars::new("echo foo\necho bar".as_bytes());
+/// let cmds = DefaultParser::new(Lexer::new(chars.by_ref()))
+///     .parse_all()
+///     .unwrap();
+///
+/// assert_eq!(cmds.len(), 2);
+/// assert!(chars.take_error().is_none());
+/// ```
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Debug)]
+pub struct ReadChars<R> {
+    /// The underlying reader.
+    reader: R,
+    /// Bytes read from the reader but not yet decoded.
+    buf: Vec<u8>,
+    /// The offset of the first undecoded byte within `buf`.
+    start: usize,
+    /// Whether the reader has reached its end (or failed).
+    done: bool,
+    /// The error returned by the reader, if any.
+    error: Option<io::Error>,
+    /// The number of (UTF-8 encoded) bytes of the characters decoded so far.
+    decoded: usize,
+    /// The number of bytes of the original input decoded so far.
+    consumed: usize,
+    /// The `decoded` and `consumed` offsets after each replaced sequence
+    /// whose length differs from that of its replacement.
+    offsets: Vec<(usize, usize)>,
+}
+
+impl<R: Read> ReadChars<R> {
+    /// Creates a new adapter over the provided reader.
+    pub fn new(reader: R) -> Self {
+        ReadChars {
+            reader,
+            buf: Vec::new(),
+            start: 0,
+            done: false,
+            error: None,
+            decoded: 0,
+            consumed: 0,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Returns the error the underlying reader failed with, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error the underlying reader failed with, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Maps a byte offset within the decoded characters (e.g. the `byte` of a
+    /// `SourcePos` reported by the parser) to the corresponding offset within
+    /// the original input.
+    ///
+    /// ```
+    /// use conch_parser::lexer::{Lexer, ReadChars};
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let mut chars = ReadChars::new(&b"echo \xff\xfe; )"[..]);
+    /// let err = DefaultParser::new(Lexer::new(chars.by_ref()))
+    ///     .parse_all()
+    ///     .unwrap_err();
+    ///
+    /// // Each invalid byte was decoded as a 3 byte U+FFFD
+    /// let pos = err.span().unwrap().start;
+    /// assert_eq!(pos.byte, 13);
+    /// assert_eq!(chars.source_offset(pos.byte), 9);
+    /// ```
+    pub fn source_offset(&self, byte: usize) -> usize {
+        let idx = self
+            .offsets
+            .partition_point(|&(decoded, _)| decoded <= byte);
+        match idx.checked_sub(1) {
+            Some(idx) => {
+                let (decoded, consumed) = self.offsets[idx];
+                consumed + (byte - decoded)
+            }
+            None => byte,
+        }
+    }
+
+    /// Unwraps the adapter, returning the underlying reader.
+    ///
+    /// Any bytes which were read but not yet decoded are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads another chunk of data into the buffer, discarding any consumed bytes.
+    fn fill_buf(&mut self) {
+        self.buf.drain(..self.start);
+        self.start = 0;
+
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+
+        loop {
+            match self.reader.read(&mut self.buf[len..]) {
+                Ok(n) => {
+                    self.buf.truncate(len + n);
+                    self.done = n == 0;
+                    return;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buf.truncate(len);
+                    self.error = Some(e);
+                    self.done = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for ReadChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            let pending = &self.buf[self.start..];
+            if pending.is_empty() {
+                if self.done {
+                    return None;
+                }
+
+                self.fill_buf();
+                continue;
+            }
+
+            match decode_char(pending, self.done) {
+                Some((c, len)) => {
+                    self.start += len;
+                    self.decoded += c.len_utf8();
+                    self.consumed += len;
+                    if c.len_utf8() != len {
+                        self.offsets.push((self.decoded, self.consumed));
+                    }
+                    return Some(c);
+                }
+                // The sequence may be completed by the next chunk
+                None => self.fill_buf(),
+            }
+        }
+    }
+}
+
+/// Decodes the first character of a non-empty buffer, returning it along with
+/// its length in bytes. Returns `None` if the buffer ends in the middle of a
+/// character which may still be completed, i.e. if the source is not `done`.
+pub(super) fn decode_char(pending: &[u8], done: bool) -> Option<(char, usize)> {
+    // A single char is at most 4 bytes long, avoid validating the entire buffer
+    let candidate = &pending[..cmp::min(4, pending.len())];
+    let decoded = match str::from_utf8(candidate) {
+        Ok(s) => {
+            let c = s.chars().next().unwrap();
+            (c, c.len_utf8())
+        }
+        Err(e) if e.valid_up_to() > 0 => {
+            let s = str::from_utf8(&candidate[..e.valid_up_to()]).unwrap();
+            let c = s.chars().next().unwrap();
+            (c, c.len_utf8())
+        }
+        Err(e) => match e.error_len() {
+            Some(len) => (std::char::REPLACEMENT_CHARACTER, len),
+            None if !done => return None,
+            None => (std::char::REPLACEMENT_CHARACTER, pending.len()),
+        },
+    };
+
+    Some(decoded)
+}
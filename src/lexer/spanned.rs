@@ -0,0 +1,81 @@
+//! Defines an adapter for locating each token within the source.
+
+use crate::ast::Span;
+use crate::parse::SourcePos;
+use crate::token::Token;
+use std::iter::FusedIterator;
+
+/// An iterator which pairs every token of an underlying token iterator (such
+/// as a `Lexer`) with its location in the source, see `Lexer::spanned`.
+///
+/// Since the lexer is lossless, every character of the source is covered by
+/// exactly one token: whitespace is yielded as `Token::Whitespace`, and a comment
+/// as a `Token::Pound` followed by the tokens of the remainder of its line (up
+/// to, but excluding, the `Token::Newline`). This allows tools such as syntax
+/// highlighters to work with the raw tokens without building an AST.
+///
+/// ```
+/// use conch_parser::ast::Span;
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::SourcePos;
+/// use conch_parser::token::Token;
+///
+/// let mut tokens = Lexer::new("echo\n# hi".chars()).spanned();
+/// assert_eq!(tokens.nth(1), Some((Token::Newline, Span {
+///     start: SourcePos { byte: 4, line: 1, col: 5 },
+///     end: SourcePos { byte: 5, line: 2, col: 1 },
+/// })));
+/// assert_eq!(tokens.next().map(|(tok, span)| (tok, span.start.col)), Some((Token::Pound, 1)));
+/// ```
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+#[derive(Debug, Clone)]
+pub struct SpannedTokens<I> {
+    iter: I,
+    pos: SourcePos,
+}
+
+impl<I: Iterator<Item = Token>> SpannedTokens<I> {
+    /// Creates a new adapter, locating the first token at the start of the source.
+    pub fn new(iter: I) -> Self {
+        Self::with_pos(iter, SourcePos::new())
+    }
+
+    /// Creates a new adapter, locating the first token at `pos`, e.g. when the
+    /// tokens are part of a larger source, or to only track byte offsets
+    /// (see `SourcePos::from_byte`).
+    pub fn with_pos(iter: I, pos: SourcePos) -> Self {
+        SpannedTokens { iter, pos }
+    }
+
+    /// Returns the position immediately after the last token yielded so far.
+    pub fn pos(&self) -> SourcePos {
+        self.pos
+    }
+
+    /// Unwraps the adapter, returning the underlying token iterator.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I: Iterator<Item = Token>> Iterator for SpannedTokens<I> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.iter.next()?;
+        let start = self.pos;
+        self.pos.advance(&tok);
+
+        let span = Span {
+            start,
+            end: self.pos,
+        };
+        Some((tok, span))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = Token>> FusedIterator for SpannedTokens<I> {}
@@ -28,7 +28,7 @@
 //!  * Variable expansion
 //!  * **Not yet implemented**: Other inner abitrary parameter/substitution expansion
 
-#![doc(html_root_url = "https://docs.rs/conch-parser/0.1")]
+#![doc(html_root_url = "https://docs.rs/conch-parser/0.2")]
 #![cfg_attr(not(test), deny(clippy::print_stdout))]
 #![deny(clippy::wrong_self_convention)]
 #![deny(missing_copy_implementations)]
@@ -41,7 +41,19 @@
 #![deny(unused_qualifications)]
 #![forbid(unsafe_code)]
 
+pub mod analysis;
 pub mod ast;
+pub mod completion;
+pub mod folding;
+pub mod highlight;
+pub mod ir;
 pub mod lexer;
+#[cfg(feature = "ndjson")]
+pub mod ndjson;
 pub mod parse;
+pub mod perf;
+pub mod snippet;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod token;
+pub mod validate;
@@ -0,0 +1,216 @@
+//! Streaming of parsed commands as newline-delimited JSON (ndjson).
+//!
+//! An `NdjsonEmitter` writes a single line of JSON for every complete command
+//! as soon as the parser yields it, thus arbitrarily long scripts (e.g. read via
+//! `Parser::from_reader`) can be piped into other tools without ever holding
+//! more than a single command in memory. Every line is an object of the form
+//!
+//! ```text
+//! {"command":{...},"span":{"start":{...},"end":{...}},"diagnostics":[...]}
+//! ```
+//!
+//! where `command` is the command produced by the builder of the parser, serialized
+//! via its `Serialize` implementation (see the `serde` feature). The `span` covers
+//! all source consumed while parsing the command, including any comments or blank
+//! lines preceding it, thus the spans of consecutive lines are contiguous. Each
+//! diagnostic is an object with a `severity`, `code`, `message`, and a `span`
+//! which is `null` if the diagnostic has no location of its own.
+//!
+//! If parsing fails, a final line with a `null` command reports the
+//! error among its diagnostics, and no further commands are emitted.
+
+use crate::analysis::report::error_code;
+use crate::analysis::Diagnostic;
+use crate::ast::builder::Builder;
+use crate::ast::Span;
+use crate::parse::{ParseError, Parser};
+use crate::token::Token;
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+
+/// A single line of output.
+#[derive(Serialize)]
+struct Record<'a, C> {
+    command: Option<&'a C>,
+    span: Span,
+    diagnostics: Vec<DiagnosticRecord>,
+}
+
+/// A diagnostic reported within a `Record`.
+#[derive(Serialize)]
+struct DiagnosticRecord {
+    severity: &'static str,
+    code: &'static str,
+    message: String,
+    span: Option<Span>,
+}
+
+impl DiagnosticRecord {
+    fn from_error<E: fmt::Display>(err: &ParseError<E>) -> Self {
+        DiagnosticRecord {
+            severity: "error",
+            code: error_code(err),
+            message: err.to_string(),
+            span: err.span(),
+        }
+    }
+
+    fn from_diagnostic(diagnostic: Diagnostic) -> Self {
+        DiagnosticRecord {
+            severity: diagnostic.severity.as_str(),
+            code: diagnostic.code,
+            message: diagnostic.message,
+            span: None,
+        }
+    }
+}
+
+/// A summary of the lines written by `NdjsonEmitter::emit_all`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct EmitSummary {
+    /// The number of commands emitted.
+    pub commands: usize,
+    /// The number of diagnostics emitted, including any error which stopped parsing.
+    pub diagnostics: usize,
+    /// Whether parsing stopped due to an error.
+    pub failed: bool,
+}
+
+/// Writes the commands yielded by a parser as newline-delimited JSON,
+/// see the module documentation for the format of each line.
+///
+/// ```
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::ndjson::NdjsonEmitter;
+/// use conch_parser::parse::DefaultParser;
+///
+/// let mut parser = DefaultParser::new(Lexer::new("foo\nbar; baz".chars()));
+/// let mut emitter = NdjsonEmitter::new(Vec::new());
+/// let summary = emitter.emit_all(&mut parser).unwrap();
+/// assert_eq!(summary.commands, 3);
+///
+/// let out = String::from_utf8(emitter.into_inner()).unwrap();
+/// assert_eq!(out.lines().count(), 3);
+/// assert!(out.lines().all(|line| line.starts_with("{\"command\":{")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NdjsonEmitter<W> {
+    out: W,
+}
+
+impl<W: Write> NdjsonEmitter<W> {
+    /// Constructs a new emitter which writes to `out`.
+    ///
+    /// Each line is written as soon as its command has been parsed, thus
+    /// wrapping `out` in a `BufWriter` is recommended if it is unbuffered.
+    pub fn new(out: W) -> Self {
+        NdjsonEmitter { out }
+    }
+
+    /// Emits every command the parser yields until the end of its input,
+    /// or until an error is encountered, then flushes the output.
+    ///
+    /// Any errors recovered from by the parser (see `Parser::set_error_recovery`)
+    /// are reported in the diagnostics of the (unparsed) command they occurred in.
+    /// Errors encountered while writing are returned immediately, while a parse
+    /// error is only reported via the output and the `failed` flag of the summary.
+    /// Note that a parser reading from an `io::Read` source retains any error
+    /// of its reader, see `Parser::take_read_error`.
+    pub fn emit_all<I, B>(&mut self, parser: &mut Parser<I, B>) -> io::Result<EmitSummary>
+    where
+        I: Iterator<Item = Token>,
+        B: Builder,
+        B::Command: Serialize,
+        B::Error: fmt::Display,
+    {
+        self.emit_all_with(parser, |_| Vec::new())
+    }
+
+    /// Emits every command the parser yields like `NdjsonEmitter::emit_all`,
+    /// along with the diagnostics `check` reports for each command.
+    ///
+    /// Any `Lint` can report its diagnostics for each of the commands of the
+    /// default AST, e.g. via `|cmd| linter.check(std::slice::from_ref(cmd))`.
+    pub fn emit_all_with<I, B, F>(
+        &mut self,
+        parser: &mut Parser<I, B>,
+        mut check: F,
+    ) -> io::Result<EmitSummary>
+    where
+        I: Iterator<Item = Token>,
+        B: Builder,
+        B::Command: Serialize,
+        B::Error: fmt::Display,
+        F: FnMut(&B::Command) -> Vec<Diagnostic>,
+    {
+        let mut summary = EmitSummary::default();
+
+        loop {
+            let start = parser.pos();
+            let result = parser.complete_command();
+            let span = Span {
+                start,
+                end: parser.pos(),
+            };
+
+            let mut diagnostics = parser
+                .take_recovered_errors()
+                .iter()
+                .map(DiagnosticRecord::from_error)
+                .collect::<Vec<_>>();
+
+            let cmd = match result {
+                Ok(Some(cmd)) => cmd,
+                Ok(None) => break,
+                Err(err) => {
+                    diagnostics.push(DiagnosticRecord::from_error(&err));
+                    summary.diagnostics += diagnostics.len();
+                    summary.failed = true;
+                    self.write(&Record::<B::Command> {
+                        command: None,
+                        span,
+                        diagnostics,
+                    })?;
+                    break;
+                }
+            };
+
+            diagnostics.extend(
+                check(&cmd)
+                    .into_iter()
+                    .map(DiagnosticRecord::from_diagnostic),
+            );
+            summary.commands += 1;
+            summary.diagnostics += diagnostics.len();
+            self.write(&Record {
+                command: Some(&cmd),
+                span,
+                diagnostics,
+            })?;
+        }
+
+        self.out.flush()?;
+        Ok(summary)
+    }
+
+    fn write<C: Serialize>(&mut self, record: &Record<'_, C>) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        self.out.write_all(b"\n")
+    }
+
+    /// Returns a reference to the underlying output.
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Returns a mutable reference to the underlying output.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Unwraps the emitter, returning the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
@@ -1,26 +1,53 @@
 //! The definition of a parser (and related methods) for the shell language.
-// FIXME: consider parsing [[ exr ]] as keywords? otherwise [[ foo && bar ]] won't get parsed right
 // FIXME: consider parsing out array index syntax? (e.g. ${array[some index]}
 // FIXME: arithmetic substitutions don't currently support param/comand substitutions
 
 use std::convert::From;
 use std::error::Error;
 use std::fmt;
+use std::io::{self, Read};
 use std::iter::empty as empty_iter;
 use std::mem;
 use std::str::FromStr;
+use void::Void;
 
-use self::iter::{PeekableIterator, PositionIterator, TokenIter, TokenIterWrapper, TokenIterator};
+use self::alias::{ActiveAlias, AliasCheckpoint, Aliases};
+use self::iter::{
+    IterCheckpoint, PeekableIterator, PositionIterator, TokenIter, TokenIterWrapper, TokenIterator,
+};
+use self::ReservedWord::*;
 use crate::ast::builder::ComplexWordKind::{self, Concat, Single};
 use crate::ast::builder::WordKind::{self, DoubleQuoted, Simple, SingleQuoted};
-use crate::ast::builder::{self, Builder, SimpleWordKind};
-use crate::ast::{self, DefaultArithmetic, DefaultParameter};
+use crate::ast::builder::{self, Builder, BuilderFactory, SimpleWordKind};
+use crate::ast::{self, Braces, DefaultArithmetic, DefaultParameter, UnparsedSpan};
+use crate::lexer::{Lexer, ReadChars};
 use crate::token::Token;
 use crate::token::Token::*;
 
+mod alias;
+#[cfg(feature = "async")]
+mod async_parser;
+mod dialect;
+mod error_details;
+mod fragment;
 mod iter;
+mod line_index;
+mod parser_builder;
+
+pub use self::alias::AliasProvider;
+#[cfg(feature = "async")]
+pub use self::async_parser::AsyncParser;
+pub use self::dialect::{Dialect, Extension};
+pub use self::error_details::{
+    ErrorDetails, ErrorGroup, ErrorKind, Expected, ExpectedSet, OpenDelimiter,
+};
+pub use self::fragment::{parse_assignment, parse_redirect, parse_word};
+pub use self::line_index::LineIndex;
+pub use self::parser_builder::ParserBuilder;
 
 const CASE: &str = "case";
+const COND_CLOSE: &str = "]]";
+const COND_OPEN: &str = "[[";
 const DO: &str = "do";
 const DONE: &str = "done";
 const ELIF: &str = "elif";
@@ -31,6 +58,7 @@ const FOR: &str = "for";
 const FUNCTION: &str = "function";
 const IF: &str = "if";
 const IN: &str = "in";
+const SELECT: &str = "select";
 const THEN: &str = "then";
 const UNTIL: &str = "until";
 const WHILE: &str = "while";
@@ -42,8 +70,16 @@ pub type DefaultParser<I> = Parser<I, builder::StringBuilder>;
 /// A specialized `Result` type for parsing shell commands.
 pub type ParseResult<T, E> = Result<T, ParseError<E>>;
 
+/// The name of an assigned variable and its value (which is `None` for an
+/// empty assignment like `NAME=`), see `Parser::assignment`.
+pub type Assignment<W> = (String, Option<W>);
+
 /// Indicates a character/token position in the original source.
+///
+/// Positions with a `line` of zero only track their byte offset (see
+/// `PositionTracking::ByteOffsets`), and can be resolved with a `LineIndex`.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourcePos {
     /// The byte offset since the start of parsing.
     pub byte: usize,
@@ -69,8 +105,28 @@ impl SourcePos {
         }
     }
 
+    /// Constructs a position which only tracks the specified byte offset.
+    pub fn from_byte(byte: usize) -> SourcePos {
+        SourcePos {
+            byte,
+            line: 0,
+            col: 0,
+        }
+    }
+
+    /// Checks if this position only tracks its byte offset.
+    pub fn is_byte_only(&self) -> bool {
+        self.line == 0
+    }
+
     /// Increments self using the length of the provided token.
     pub fn advance(&mut self, next: &Token) {
+        let tok_len = next.len();
+        self.byte += tok_len;
+        if self.is_byte_only() {
+            return;
+        }
+
         let newlines = match *next {
             // Most of these should not have any newlines
             // embedded within them, but permitting external
@@ -83,8 +139,6 @@ impl SourcePos {
             _ => 0,
         };
 
-        let tok_len = next.len();
-        self.byte += tok_len;
         self.line += newlines;
         self.col = if newlines == 0 { self.col + tok_len } else { 1 };
     }
@@ -92,7 +146,24 @@ impl SourcePos {
     /// Increments self by `num_tab` tab characters
     fn advance_tabs(&mut self, num_tab: usize) {
         self.byte += num_tab;
-        self.col += num_tab;
+        if !self.is_byte_only() {
+            self.col += num_tab;
+        }
+    }
+
+    /// Increments self past a single character of the source.
+    pub(crate) fn advance_char(&mut self, c: char) {
+        self.byte += c.len_utf8();
+        if self.is_byte_only() {
+            return;
+        }
+
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += c.len_utf8();
+        }
     }
 }
 
@@ -118,10 +189,125 @@ pub enum ParseError<T> {
     Unexpected(Token, SourcePos),
     /// Encountered the end of input while expecting additional tokens.
     UnexpectedEOF,
+    /// Encountered a construct which is an extension of the POSIX grammar
+    /// while parsing in the strict `Dialect::Posix` mode.
+    /// Stores the position at which the construct starts.
+    Extension(Extension, SourcePos),
+    /// Encountered a NUL byte in the source while using `NulHandling::Reject`.
+    /// Stores the position of the NUL byte.
+    NulByte(SourcePos),
+    /// Encountered a numeric literal within an arithmetic expression which is not
+    /// valid for its base (e.g. `08` or `2#12`), or which specifies an invalid base
+    /// (e.g. `65#1`). Stores the literal and the position at which it starts.
+    BadNumber(String, SourcePos),
+    /// Encountered a numeric literal within an arithmetic expression which does not
+    /// fit within 64 bits while parsing in the strict `Dialect::Posix` mode (other
+    /// dialects silently wrap the value, like bash does).
+    /// Stores the literal and the position at which it starts.
+    NumberOverflow(String, SourcePos),
     /// A custom error returned by the AST builder.
     Custom(T),
 }
 
+impl<T> ParseError<T> {
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            ParseError::BadFd(..) => ErrorKind::BadFd,
+            ParseError::BadIdent(..) => ErrorKind::BadIdent,
+            ParseError::BadSubst(..) => ErrorKind::BadSubst,
+            ParseError::Unmatched(..) => ErrorKind::Unmatched,
+            ParseError::IncompleteCmd(..) => ErrorKind::IncompleteCmd,
+            ParseError::Unexpected(..) => ErrorKind::Unexpected,
+            ParseError::UnexpectedEOF => ErrorKind::UnexpectedEOF,
+            ParseError::Extension(..) => ErrorKind::Extension,
+            ParseError::NulByte(..) => ErrorKind::NulByte,
+            ParseError::BadNumber(..) => ErrorKind::BadNumber,
+            ParseError::NumberOverflow(..) => ErrorKind::NumberOverflow,
+            ParseError::Custom(..) => ErrorKind::Custom,
+        }
+    }
+
+    /// Returns the location of the offending source, if the error has one.
+    ///
+    /// Errors which carry a token (or literal) span its entire length, while
+    /// those reported at a single point (e.g. the position at which a missing
+    /// keyword was expected) have an empty span.
+    pub fn span(&self) -> Option<ast::Span> {
+        let after = |start: SourcePos, s: &str| {
+            let mut end = start;
+            s.chars().for_each(|c| end.advance_char(c));
+            ast::Span { start, end }
+        };
+        let after_token = |start: SourcePos, t: &Token| {
+            let mut end = start;
+            end.advance(t);
+            ast::Span { start, end }
+        };
+
+        match *self {
+            ParseError::BadFd(start, end) => Some(ast::Span { start, end }),
+            ParseError::BadIdent(ref s, pos)
+            | ParseError::BadNumber(ref s, pos)
+            | ParseError::NumberOverflow(ref s, pos) => Some(after(pos, s)),
+            ParseError::BadSubst(ref t, pos)
+            | ParseError::Unmatched(ref t, pos)
+            | ParseError::Unexpected(ref t, pos) => Some(after_token(pos, t)),
+            ParseError::NulByte(pos) => Some(after(pos, "\0")),
+            ParseError::IncompleteCmd(_, _, _, pos) | ParseError::Extension(_, pos) => {
+                Some(ast::Span {
+                    start: pos,
+                    end: pos,
+                })
+            }
+            ParseError::UnexpectedEOF | ParseError::Custom(_) => None,
+        }
+    }
+
+    /// Applies a function to every position stored in the error, e.g. to
+    /// resolve byte offsets into lines and columns using a `LineIndex`.
+    pub fn map_pos<F: FnMut(SourcePos) -> SourcePos>(self, mut f: F) -> Self {
+        use self::ParseError::*;
+
+        match self {
+            BadFd(start, end) => BadFd(f(start), f(end)),
+            BadIdent(s, pos) => BadIdent(s, f(pos)),
+            BadSubst(t, pos) => BadSubst(t, f(pos)),
+            Unmatched(t, pos) => Unmatched(t, f(pos)),
+            IncompleteCmd(cmd, pos, kw, kw_pos) => IncompleteCmd(cmd, f(pos), kw, f(kw_pos)),
+            Unexpected(t, pos) => Unexpected(t, f(pos)),
+            UnexpectedEOF => UnexpectedEOF,
+            Extension(ext, pos) => Extension(ext, f(pos)),
+            NulByte(pos) => NulByte(f(pos)),
+            BadNumber(s, pos) => BadNumber(s, f(pos)),
+            NumberOverflow(s, pos) => NumberOverflow(s, f(pos)),
+            Custom(e) => Custom(e),
+        }
+    }
+
+    /// Separates custom errors returned by the AST builder from all other errors.
+    fn into_custom(self) -> Result<T, ParseError<Void>> {
+        use self::ParseError::*;
+
+        let err = match self {
+            BadFd(start, end) => BadFd(start, end),
+            BadIdent(s, pos) => BadIdent(s, pos),
+            BadSubst(t, pos) => BadSubst(t, pos),
+            Unmatched(t, pos) => Unmatched(t, pos),
+            IncompleteCmd(cmd, pos, kw, kw_pos) => IncompleteCmd(cmd, pos, kw, kw_pos),
+            Unexpected(t, pos) => Unexpected(t, pos),
+            UnexpectedEOF => UnexpectedEOF,
+            Extension(ext, pos) => Extension(ext, pos),
+            NulByte(pos) => NulByte(pos),
+            BadNumber(s, pos) => BadNumber(s, pos),
+            NumberOverflow(s, pos) => NumberOverflow(s, pos),
+            Custom(e) => return Ok(e),
+        };
+
+        Err(err)
+    }
+}
+
 impl<T: Error> Error for ParseError<T> {
     // FIXME(breaking): change this to be `source`, breaking because it
     // would require a new 'static bound on T
@@ -133,7 +319,11 @@ impl<T: Error> Error for ParseError<T> {
             | ParseError::Unmatched(..)
             | ParseError::IncompleteCmd(..)
             | ParseError::Unexpected(..)
-            | ParseError::UnexpectedEOF => None,
+            | ParseError::UnexpectedEOF
+            | ParseError::Extension(..)
+            | ParseError::NulByte(..)
+            | ParseError::BadNumber(..)
+            | ParseError::NumberOverflow(..) => None,
             ParseError::Custom(ref e) => Some(e),
         }
     }
@@ -172,6 +362,21 @@ impl<T: fmt::Display> fmt::Display for ParseError<T> {
             }
 
             ParseError::UnexpectedEOF => fmt.write_str("unexpected end of input"),
+            ParseError::Extension(ext, pos) => write!(
+                fmt,
+                "found `{}` on line {}, which is not part of the POSIX standard; \
+                 use the `{}` dialect to enable it",
+                ext,
+                pos,
+                ext.dialect()
+            ),
+            ParseError::NulByte(pos) => write!(fmt, "found NUL byte on line {}", pos),
+            ParseError::BadNumber(ref n, pos) => {
+                write!(fmt, "invalid number on line {}: {}", pos, n)
+            }
+            ParseError::NumberOverflow(ref n, pos) => {
+                write!(fmt, "number too large on line {}: {}", pos, n)
+            }
             ParseError::Custom(ref e) => write!(fmt, "{}", e),
         }
     }
@@ -199,8 +404,84 @@ enum CompoundCmdKeyword {
     Until,
     Brace,
     Subshell,
+    Conditional,
+    Arithmetic,
+}
+
+/// The reserved words which the parser recognizes on its own.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum ReservedWord {
+    Case,
+    Do,
+    Done,
+    Elif,
+    Else,
+    Esac,
+    Fi,
+    For,
+    Function,
+    If,
+    In,
+    Select,
+    Then,
+    Until,
+    While,
+}
+
+impl ReservedWord {
+    /// Identifies the reserved word spelled by a word, if any, with a single
+    /// match on its bytes (which dispatches on the length of the word before
+    /// comparing it against the few reserved words of that length).
+    fn from_word(word: &str) -> Option<Self> {
+        let word = match word.as_bytes() {
+            b"case" => Case,
+            b"do" => Do,
+            b"done" => Done,
+            b"elif" => Elif,
+            b"else" => Else,
+            b"esac" => Esac,
+            b"fi" => Fi,
+            b"for" => For,
+            b"function" => Function,
+            b"if" => If,
+            b"in" => In,
+            b"select" => Select,
+            b"then" => Then,
+            b"until" => Until,
+            b"while" => While,
+            _ => return None,
+        };
+
+        Some(word)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Case => CASE,
+            Do => DO,
+            Done => DONE,
+            Elif => ELIF,
+            Else => ELSE,
+            Esac => ESAC,
+            Fi => FI,
+            For => FOR,
+            Function => FUNCTION,
+            If => IF,
+            In => IN,
+            Select => SELECT,
+            Then => THEN,
+            Until => UNTIL,
+            While => WHILE,
+        }
+    }
 }
 
+/// A list of redirections along with the location of the entire list, if non-empty.
+type SpannedRedirects<R> = (Vec<R>, Option<ast::Span>);
+
+/// The name, syntax, post-name comments, and body of a function declaration.
+type FunctionParts<C> = (String, builder::FunctionSyntax, Vec<builder::Newline>, C);
+
 /// Used to configure when `Parser::command_group` stops parsing commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CommandGroupDelimiters<'a, 'b, 'c> {
@@ -282,6 +563,49 @@ where
     }
 }
 
+/// An `Iterator` adapter around a borrowed `Parser`, see `Parser::commands`.
+///
+/// Like `ParserIterator`, this iterator is `fused` once the underlying parser
+/// yields no command, or an error. However, the parser remains available to
+/// the caller afterwards, e.g. to describe the error via `Parser::error_details`.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+#[derive(Debug)]
+pub struct Commands<'a, I, B> {
+    /// The underlying parser to poll for complete commands.
+    /// A `None` value indicates the stream has been exhausted.
+    parser: Option<&'a mut Parser<I, B>>,
+}
+
+impl<I, B> std::iter::FusedIterator for Commands<'_, I, B>
+where
+    I: Iterator<Item = Token>,
+    B: Builder,
+{
+}
+
+impl<I, B> Iterator for Commands<'_, I, B>
+where
+    I: Iterator<Item = Token>,
+    B: Builder,
+{
+    type Item = ParseResult<B::Command, B::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parser.as_mut().map(|parser| parser.complete_command()) {
+            None => None,
+            Some(Ok(Some(c))) => Some(Ok(c)),
+            Some(Ok(None)) => {
+                let _ = self.parser.take();
+                None
+            }
+            Some(Err(e)) => {
+                let _ = self.parser.take();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
 impl<I, B> IntoIterator for Parser<I, B>
 where
     I: Iterator<Item = Token>,
@@ -301,7 +625,8 @@ where
 /// The parser implements the `IntoIterator` trait so that it can behave like
 /// a stream of parsed shell commands. Converting the parser into an `Iterator`
 /// and calling `next()` on the result will yield a complete shell command, or
-/// an error should one arise.
+/// an error should one arise. `Parser::commands` does the same while only
+/// borrowing the parser.
 ///
 /// # Building
 ///
@@ -362,10 +687,152 @@ where
 pub struct Parser<I, B> {
     iter: TokenIterWrapper<I>,
     builder: B,
+    options: ParserOptions,
+    recovered_errors: Vec<ParseError<Void>>,
+    keywords: Option<Vec<Keyword>>,
+    contexts: Vec<builder::ContextKind>,
+    expected: ExpectedSet,
+    // Whether the innermost command group should add its delimiters to `expected`
+    expected_delims: bool,
+    // The constructs which are currently open, innermost last
+    delims: Vec<OpenDelimiter>,
+    // How many of `delims` were opened outside of the tokens currently being parsed
+    // (e.g. before the body of a heredoc), which cannot be closed by their end
+    delims_floor: usize,
+    // How many of `delims` remain open in the regular token stream, if parsing
+    // failed within buffered tokens (whose constructs are consumed regardless)
+    stream_delims: Option<usize>,
+    // Whether the body of any heredoc was delimited by the end of the input
+    heredoc_reached_eof: bool,
+    aliases: Option<Aliases>,
+}
+
+/// A reserved word (or reserved token, e.g. `{` or `!`) which the parser has
+/// recognized as such, see `Parser::set_track_keywords`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Keyword {
+    /// The text of the reserved word, e.g. `then`.
+    pub word: String,
+    /// The location of the reserved word in the source.
+    pub span: ast::Span,
+}
+
+/// The outcome of `Parser::parse_with_recovery`: every command which could be
+/// parsed, along with the errors encountered while parsing the others.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PartialParse<C> {
+    /// All successfully parsed commands, in source order.
+    pub commands: Vec<C>,
+    /// All errors encountered, in source order.
+    pub errors: Vec<ParseError<Void>>,
+    /// For each error in `errors`, the index of the earlier (primary) error it
+    /// is likely a follow-on of, or `None` if it is a primary error itself.
+    ///
+    /// An error is considered a follow-on if it was encountered after a primary
+    /// error which left a construct open (e.g. a `case` whose header failed to
+    /// parse, such that each of its arms is parsed as a separate command), but
+    /// before the token or reserved word which ends the construct (e.g. `esac`).
+    pub related_to: Vec<Option<usize>>,
+}
+
+impl<C> PartialParse<C> {
+    /// Groups every primary error along with its follow-on errors (see
+    /// `PartialParse::related_to`), in source order.
+    ///
+    /// At most `max_related` follow-on errors are kept in each group, while
+    /// the rest are only counted, so that a single mistake which derails the
+    /// parser for the remainder of a script does not drown out other errors.
+    ///
+    /// ```
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let src = "case $x on\n  a) foo ;;\n  b) bar ;;\n  c) baz ;;\nesac\necho )\n";
+    /// let mut parser = DefaultParser::new(Lexer::new(src.chars()));
+    /// let parsed = parser.parse_with_recovery().unwrap();
+    /// assert_eq!(parsed.errors.len(), 5);
+    ///
+    /// let groups = parsed.error_groups(1);
+    /// assert_eq!(groups.len(), 2);
+    /// assert_eq!(groups[0].related.len(), 1);
+    /// assert_eq!(groups[0].suppressed, 2);
+    /// assert!(groups[1].related.is_empty());
+    /// ```
+    pub fn error_groups(&self, max_related: usize) -> Vec<ErrorGroup<'_>> {
+        let mut groups: Vec<ErrorGroup<'_>> = Vec::new();
+        // The index of the group started by each primary error
+        let mut group_of = vec![None; self.errors.len()];
+
+        for (i, err) in self.errors.iter().enumerate() {
+            let primary = self.related_to.get(i).copied().flatten();
+            match primary.and_then(|primary| group_of.get(primary).copied().flatten()) {
+                Some(group) => {
+                    let group: &mut ErrorGroup<'_> = &mut groups[group];
+                    if group.related.len() < max_related {
+                        group.related.push(err);
+                    } else {
+                        group.suppressed += 1;
+                    }
+                }
+                None => {
+                    group_of[i] = Some(groups.len());
+                    groups.push(ErrorGroup {
+                        primary: err,
+                        related: Vec::new(),
+                        suppressed: 0,
+                    });
+                }
+            }
+        }
+
+        groups
+    }
+}
+
+/// Limits on how much of the input `Parser::parse_preview` parses.
+///
+/// The default limits are unbounded.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct PreviewLimits {
+    /// The maximum number of complete commands to parse.
+    pub max_commands: Option<usize>,
+    /// The number of bytes of input after which no further complete commands are
+    /// started. The command being parsed when the limit is crossed is still parsed
+    /// in its entirety, so more input than this may be consumed.
+    pub max_bytes: Option<usize>,
+}
+
+/// The outcome of `Parser::parse_preview`: the commands parsed before a limit
+/// was reached, and whether any commands were left unparsed because of it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Preview<C> {
+    /// All parsed commands, in source order.
+    pub commands: Vec<C>,
+    /// Whether parsing stopped before the end of the input since a limit was reached.
+    pub truncated: bool,
+}
+
+/// A saved parser state, which the parser can be rewound to via `Parser::rewind`.
+///
+/// Checkpoints are created via `Parser::checkpoint` and must eventually be
+/// given back to the parser via `Parser::rewind` or `Parser::commit`, since
+/// the parser retains all tokens consumed while any checkpoint is live.
+#[must_use = "checkpoints must be rewound or committed"]
+#[derive(Debug)]
+pub struct Checkpoint {
+    iter: IterCheckpoint,
+    recovered_errors: usize,
+    keywords: usize,
+    contexts: usize,
+    delims: usize,
+    aliases: Option<AliasCheckpoint>,
 }
 
 impl<I: Iterator<Item = Token>, B: Builder + Default> Parser<I, B> {
     /// Creates a new Parser from a Token iterator or collection.
+    ///
+    /// The parser accepts the bash dialect; use `Parser::set_dialect` (or
+    /// `ParserOptions`) to parse another one.
     pub fn new<T>(iter: T) -> Parser<I, B>
     where
         T: IntoIterator<Item = Token, IntoIter = I>,
@@ -374,6 +841,228 @@ impl<I: Iterator<Item = Token>, B: Builder + Default> Parser<I, B> {
     }
 }
 
+impl<R: Read, B: Builder + Default> Parser<Lexer<ReadChars<R>>, B> {
+    /// Creates a new Parser which incrementally lexes and parses the contents
+    /// of a reader (e.g. a file, standard input, or a socket) as they are
+    /// requested, without buffering the entire source in memory.
+    ///
+    /// Use `Parser::with_builder(Lexer::new(ReadChars::new(reader)), builder)`
+    /// to parse from a reader with a specific builder. See `ReadChars` for
+    /// details on how the source is decoded.
+    ///
+    /// ```
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let mut parser = DefaultParser::from_reader("echo foo\necho bar".as_bytes());
+    /// let cmds = parser.parse_all().unwrap();
+    ///
+    /// assert_eq!(cmds.len(), 2);
+    /// assert!(parser.read_error().is_none());
+    /// ```
+    pub fn from_reader(reader: R) -> Self {
+        Parser::with_builder(Lexer::new(ReadChars::new(reader)), Default::default())
+    }
+}
+
+impl<R: Read, B: Builder> Parser<Lexer<ReadChars<R>>, B> {
+    /// Returns the error the underlying reader failed with, if any.
+    ///
+    /// A failing reader is treated as if the end of the source was reached, so
+    /// this should be checked after the parser is exhausted to distinguish a
+    /// complete source from a truncated one.
+    pub fn read_error(&self) -> Option<&io::Error> {
+        self.source().get_ref().error()
+    }
+
+    /// Takes the error the underlying reader failed with, if any.
+    pub fn take_read_error(&mut self) -> Option<io::Error> {
+        self.source_mut().get_mut().take_error()
+    }
+}
+
+/// The character which replaces NUL bytes when using `NulHandling::Replace`.
+pub const NUL_REPLACEMENT: char = '\u{FFFD}';
+
+/// Specifies how the parser should treat NUL bytes found in the source.
+///
+/// Shells cannot pass NUL bytes to commands as part of their arguments, and
+/// most of them will silently truncate or drop them, which makes it easy
+/// to hide content from a casual reader of a script.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum NulHandling {
+    /// Fail parsing with a `ParseError::NulByte` error pointing at the NUL byte.
+    #[default]
+    Reject,
+    /// Replace each NUL byte with the `NUL_REPLACEMENT` character so that
+    /// the rest of the source can still be parsed (and inspected).
+    Replace,
+}
+
+/// Specifies how the parser should proceed if a complete command cannot be parsed.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ErrorRecovery {
+    /// Return the error to the caller. The parser should not be used to parse
+    /// any further commands, since its state is unspecified at that point.
+    #[default]
+    Abort,
+    /// Skip to the end of the line on which the error was encountered, and
+    /// pass the raw text of the skipped region (starting at the beginning of
+    /// the failed command) to `Builder::unparsed`. The error itself is retained
+    /// by the parser (see `Parser::take_recovered_errors`), and parsing resumes
+    /// on the next line.
+    ///
+    /// This allows tools such as formatters to pass regions with errors
+    /// through verbatim, instead of refusing to operate on the entire source.
+    /// Errors returned by the builder itself are never recovered from.
+    Passthrough,
+}
+
+/// Specifies how the parser should treat expansions which are still open
+/// when the input ends, e.g. the `${HO` of `echo ${HO`.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum IncompleteWords {
+    /// Fail parsing with a `ParseError::Unmatched` error for the opening delimiter.
+    #[default]
+    Reject,
+    /// Pass the expansion to the builder as if it had been closed at the end
+    /// of the input, with whatever has been typed so far (a parameter whose
+    /// name has not been started yet is passed as an empty `Parameter::Var`).
+    /// The `ParseError::Unmatched` error is retained by the parser instead
+    /// (see `Parser::take_recovered_errors`).
+    ///
+    /// This allows tools such as completion engines to inspect the partial
+    /// expansion at the cursor. Parameter substitutions (`${...}`), command
+    /// substitutions (`$(...)` and backticks) and double quotes are closed this
+    /// way. Compound commands are closed as well once they contain a command
+    /// (e.g. `if true; then ec` or `while tr`), recording a `ParseError::IncompleteCmd`
+    /// (or `ParseError::Unmatched` for `{` and `(`) instead. Single quotes are
+    /// never closed.
+    Partial,
+}
+
+/// Specifies how the parser should treat reserved words which close a compound
+/// command (e.g. `fi`, `done`, or `}`) when found where a command is expected.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ReservedWords {
+    /// Treat the reserved word as the name of a simple command, e.g. a lone
+    /// `fi` is parsed as running a command named `fi`.
+    #[default]
+    Lenient,
+    /// Fail parsing with a `ParseError::Unexpected` error for the reserved word,
+    /// matching how shells such as `bash -n` and `dash -n` reject them.
+    Strict,
+}
+
+/// Specifies how the parser should treat brace expansions within words,
+/// e.g. `{a,b}` or `{1..10}`.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum BraceExpansion {
+    /// Pass braces, and the commas within them, to the builder as literals.
+    #[default]
+    Literal,
+    /// Pass brace expansions to the builder as `WordKind::Braces`, with their
+    /// alternatives (or the bounds of their sequence) already parsed, when using
+    /// `Dialect::Bash`. Other dialects always treat braces as literals.
+    ///
+    /// Like bash, only the words of simple commands, the targets of redirections,
+    /// and the words of `for` loops are considered, but not assignments, `case`
+    /// patterns, or the operands of `[[ ... ]]` and parameter substitutions. Braces
+    /// which are quoted, escaped, or do not enclose a valid expansion (e.g. `{a}`
+    /// or `{1..z}`) remain literals.
+    Structured,
+}
+
+/// Specifies how the parser should treat tildes which begin a tilde prefix,
+/// e.g. the `~` of `~/bin` or the `~user` of `~user/bin`.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum TildeExpansion {
+    /// Pass every unquoted `~` to the builder as `SimpleWordKind::Tilde`, leaving
+    /// it up to the consumer to decide which of them begin a tilde prefix.
+    #[default]
+    Tokens,
+    /// Pass tilde prefixes to the builder as `SimpleWordKind::TildePrefix`, along
+    /// with the login name which follows the tilde (if any).
+    ///
+    /// As specified by POSIX, a tilde prefix is an unquoted `~` at the start of a
+    /// word, along with all characters up to the first unquoted `/` (or the end of
+    /// the word). In the value of an assignment, a tilde following an unquoted `:`
+    /// also begins a tilde prefix, which then ends at the next unquoted `:` as well.
+    /// Tildes whose prefix contains anything other than unquoted literals (e.g.
+    /// `~"user"` or `~$user`) are not expanded, and remain `SimpleWordKind::Tilde`.
+    ///
+    /// Only the words of simple commands, assignments, the targets of redirections,
+    /// the words of `for` loops and `case` commands, and the operands of `[[ ... ]]`
+    /// are considered.
+    Prefixes,
+}
+
+/// Specifies which parts of a `SourcePos` the parser should keep track of.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum PositionTracking {
+    /// Track the byte offset, line, and column of every token.
+    #[default]
+    LinesAndColumns,
+    /// Only track the byte offset of every token, leaving the `line` and `col`
+    /// of every position as zero. This avoids scanning each token for newlines,
+    /// which is useful for tools which rarely (if ever) report positions.
+    ///
+    /// Positions can be resolved lazily using a `LineIndex` of the source.
+    ByteOffsets,
+}
+
+/// All settings which influence how a `Parser` operates.
+///
+/// The options are cheap to copy and can be freely shared across threads,
+/// so a single configuration can be used to create any number of parsers
+/// (e.g. along with a `BuilderFactory`).
+///
+/// ```
+/// use conch_parser::ast::builder::StringBuilder;
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::{Dialect, ParserOptions};
+///
+/// let options = ParserOptions {
+///     dialect: Dialect::Posix,
+///     ..ParserOptions::default()
+/// };
+///
+/// let lex = Lexer::new("echo foo".chars());
+/// let mut parser = options.parser(lex, &StringBuilder::default);
+/// assert_eq!(parser.dialect(), Dialect::Posix);
+/// assert!(parser.complete_command().unwrap().is_some());
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ParserOptions {
+    /// The dialect the parser should accept.
+    pub dialect: Dialect,
+    /// How the parser should treat NUL bytes found in the source.
+    pub nul_handling: NulHandling,
+    /// How the parser should proceed after failing to parse a complete command.
+    pub error_recovery: ErrorRecovery,
+    /// Which parts of a source position the parser should keep track of.
+    pub position_tracking: PositionTracking,
+    /// How the parser should treat expansions left open at the end of the input.
+    pub incomplete_words: IncompleteWords,
+    /// How the parser should treat closing reserved words found where a command is expected.
+    pub reserved_words: ReservedWords,
+    /// How the parser should treat brace expansions within words.
+    pub brace_expansion: BraceExpansion,
+    /// How the parser should treat tildes which begin a tilde prefix.
+    pub tilde_expansion: TildeExpansion,
+}
+
+impl ParserOptions {
+    /// Creates a new parser configured with these options, whose AST builder
+    /// is created by the provided factory.
+    pub fn parser<T, F>(&self, iter: T, factory: &F) -> Parser<T::IntoIter, F::Builder>
+    where
+        T: IntoIterator<Item = Token>,
+        F: BuilderFactory + ?Sized,
+    {
+        Parser::with_options(iter.into_iter(), factory.new_builder(), *self)
+    }
+}
+
 /// A macro that will consume and return a token that matches a specified pattern
 /// from a parser's token iterator. If no matching token is found, None will be yielded.
 macro_rules! eat_maybe {
@@ -439,138 +1128,1185 @@ macro_rules! arith_parse {
 
 impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// Construct an `Unexpected` error using the given token. If `None` specified, the next
-    /// token in the iterator will be used (or `UnexpectedEOF` if none left).
+    /// token in the iterator will be used (or an `eof_err` if none left).
     #[inline]
     fn make_unexpected_err(&mut self) -> ParseError<B::Error> {
+        self.expected = ExpectedSet::default();
+        self.expected_delims = false;
+
         let pos = self.iter.pos();
-        self.iter.next().map_or(ParseError::UnexpectedEOF, |t| {
-            ParseError::Unexpected(t, pos)
-        })
+        match self.iter.next() {
+            Some(t) => ParseError::Unexpected(t, pos),
+            None => self.eof_err(),
+        }
     }
 
-    /// Creates a new Parser from a Token iterator and provided AST builder.
-    pub fn with_builder(iter: I, builder: B) -> Self {
-        Parser {
-            iter: TokenIterWrapper::Regular(TokenIter::new(iter)),
-            builder,
+    /// Constructs the error for reaching the end of input, which points back at
+    /// the innermost construct which is still open, or `UnexpectedEOF` if none.
+    fn eof_err(&self) -> ParseError<B::Error> {
+        let delim = match self.delims[self.delims_floor..].last() {
+            Some(delim) => delim,
+            None => return ParseError::UnexpectedEOF,
+        };
+
+        match delim.open {
+            "(" | "((" => ParseError::Unmatched(ParenOpen, delim.pos),
+            "{" => ParseError::Unmatched(CurlyOpen, delim.pos),
+            kw => ParseError::IncompleteCmd(kw, delim.pos, delim.close, self.iter.pos()),
         }
     }
 
-    /// Returns the parser's current position in the source.
-    pub fn pos(&self) -> SourcePos {
-        self.iter.pos()
+    /// Like `make_unexpected_err`, but records what would have been accepted
+    /// instead, see `Parser::error_details`.
+    fn make_expected_err(&mut self, expected: Vec<Expected>) -> ParseError<B::Error> {
+        let err = self.make_unexpected_err();
+        self.expected = ExpectedSet(expected);
+        err
     }
 
-    /// Parses a single complete command.
-    ///
-    /// For example, `foo && bar; baz` will yield two complete
-    /// commands: `And(foo, bar)`, and `Simple(baz)`.
-    pub fn complete_command(&mut self) -> ParseResult<Option<B::Command>, B::Error> {
-        let pre_cmd_comments = self.linebreak();
+    /// Constructs the error for a point at which a command was expected, to
+    /// which the innermost command group will add its own delimiters.
+    fn make_cmd_err(&mut self) -> ParseError<B::Error> {
+        let err = self.make_expected_err(vec![Expected::Command]);
+        self.expected_delims = true;
+        err
+    }
 
-        if self.iter.peek().is_some() {
-            Ok(Some(self.complete_command_with_leading_comments(
-                pre_cmd_comments,
-            )?))
-        } else {
-            if !pre_cmd_comments.is_empty() {
-                self.builder.comments(pre_cmd_comments)?;
+    /// Returns the underlying token iterator.
+    fn source(&self) -> &I {
+        match self.iter {
+            TokenIterWrapper::Regular(ref iter) => iter.get_ref(),
+            TokenIterWrapper::Buffered(_) => {
+                unreachable!("tokens are only buffered while parsing a word")
             }
-            Ok(None)
         }
     }
 
-    /// Parses a single complete command, but expects caller to parse any leading comments.
-    ///
-    /// It is considered an error there is not a valid complete command to be parsed, thus
-    /// the caller should perform any EOF checks.
-    fn complete_command_with_leading_comments(
-        &mut self,
-        pre_cmd_comments: Vec<builder::Newline>,
-    ) -> ParseResult<B::Command, B::Error> {
-        let cmd = self.and_or_list()?;
-
-        let (sep, cmd_comment) = eat_maybe!(self, {
-            Semi => { (builder::SeparatorKind::Semi, self.newline()) },
-            Amp  => { (builder::SeparatorKind::Amp , self.newline()) };
-            _ => {
-                match self.newline() {
-                    n@Some(_) => (builder::SeparatorKind::Newline, n),
-                    None => (builder::SeparatorKind::Other, None),
-                }
+    /// Returns the underlying token iterator.
+    fn source_mut(&mut self) -> &mut I {
+        match self.iter {
+            TokenIterWrapper::Regular(ref mut iter) => iter.get_mut(),
+            TokenIterWrapper::Buffered(_) => {
+                unreachable!("tokens are only buffered while parsing a word")
             }
-        });
+        }
+    }
 
-        Ok(self
-            .builder
-            .complete_command(pre_cmd_comments, cmd, sep, cmd_comment)?)
+    /// Creates a new Parser from a Token iterator and provided AST builder.
+    pub fn with_builder(iter: I, builder: B) -> Self {
+        Parser::with_options(iter, builder, ParserOptions::default())
     }
 
-    /// Parses compound AND/OR commands.
-    ///
-    /// Commands are left associative. For example `foo || bar && baz`
-    /// parses to `And(Or(foo, bar), baz)`.
-    pub fn and_or_list(&mut self) -> ParseResult<B::CommandList, B::Error> {
-        let first = self.pipeline()?;
-        let mut rest = Vec::new();
+    /// Creates a new Parser from a Token iterator, AST builder, and options.
+    pub fn with_options(iter: I, builder: B, options: ParserOptions) -> Self {
+        let mut parser = Parser {
+            iter: TokenIterWrapper::Regular(TokenIter::new(iter)),
+            builder,
+            options: ParserOptions::default(),
+            recovered_errors: Vec::new(),
+            keywords: None,
+            contexts: Vec::new(),
+            expected: ExpectedSet::default(),
+            expected_delims: false,
+            delims: Vec::new(),
+            delims_floor: 0,
+            stream_delims: None,
+            heredoc_reached_eof: false,
+            aliases: None,
+        };
+        parser.set_options(options);
+        parser
+    }
 
-        loop {
-            self.skip_whitespace();
-            let is_and = eat_maybe!(self, {
-                AndIf => { true },
-                OrIf  => { false };
-                _ => { break },
-            });
+    /// Returns all options the parser is currently configured with.
+    pub fn options(&self) -> ParserOptions {
+        self.options
+    }
 
-            let post_sep_comments = self.linebreak();
-            let next = self.pipeline()?;
+    /// Changes all options of the parser at once, see the individual
+    /// setters (e.g. `set_dialect`) for details.
+    pub fn set_options(&mut self, options: ParserOptions) {
+        self.set_position_tracking(options.position_tracking);
+        self.options = options;
+    }
 
-            let next = if is_and {
-                ast::AndOr::And(next)
-            } else {
-                ast::AndOr::Or(next)
-            };
+    /// Returns the dialect the parser is currently accepting.
+    pub fn dialect(&self) -> Dialect {
+        self.options.dialect
+    }
 
-            rest.push((post_sep_comments, next));
-        }
+    /// Changes the dialect the parser should accept for any subsequent commands.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.options.dialect = dialect;
+    }
 
-        Ok(self.builder.and_or_list(first, rest)?)
+    /// Returns how the parser currently treats NUL bytes in the source.
+    pub fn nul_handling(&self) -> NulHandling {
+        self.options.nul_handling
     }
 
-    /// Parses either a single command or a pipeline of commands.
-    ///
-    /// For example `[!] foo | bar`.
-    pub fn pipeline(&mut self) -> ParseResult<B::ListableCommand, B::Error> {
-        self.skip_whitespace();
-        let bang = eat_maybe!(self, {
-            Bang => { true };
-            _ => { false },
-        });
+    /// Changes how the parser should treat NUL bytes for any subsequent commands.
+    pub fn set_nul_handling(&mut self, nul_handling: NulHandling) {
+        self.options.nul_handling = nul_handling;
+    }
+
+    /// Returns how the parser currently proceeds after failing to parse a complete command.
+    pub fn error_recovery(&self) -> ErrorRecovery {
+        self.options.error_recovery
+    }
+
+    /// Changes how the parser should proceed after failing to parse any subsequent commands.
+    pub fn set_error_recovery(&mut self, error_recovery: ErrorRecovery) {
+        self.options.error_recovery = error_recovery;
+    }
+
+    /// Returns which parts of a source position the parser currently keeps track of.
+    pub fn position_tracking(&self) -> PositionTracking {
+        self.options.position_tracking
+    }
+
+    /// Changes which parts of a source position the parser should keep track of.
+    ///
+    /// This should be set before parsing any commands: switching back to
+    /// `PositionTracking::LinesAndColumns` after some input has been consumed
+    /// restarts line numbering at the current position, though byte offsets
+    /// remain accurate.
+    pub fn set_position_tracking(&mut self, position_tracking: PositionTracking) {
+        if self.options.position_tracking == position_tracking {
+            return;
+        }
+
+        self.options.position_tracking = position_tracking;
+        let byte = self.iter.pos().byte;
+        self.iter.set_pos(match position_tracking {
+            PositionTracking::LinesAndColumns => SourcePos {
+                byte,
+                ..SourcePos::new()
+            },
+            PositionTracking::ByteOffsets => SourcePos::from_byte(byte),
+        });
+    }
+
+    /// Returns how the parser currently treats expansions left open at the end of the input.
+    pub fn incomplete_words(&self) -> IncompleteWords {
+        self.options.incomplete_words
+    }
+
+    /// Changes how the parser should treat expansions left open at the end of the input.
+    pub fn set_incomplete_words(&mut self, incomplete_words: IncompleteWords) {
+        self.options.incomplete_words = incomplete_words;
+    }
+
+    /// Returns how the parser currently treats closing reserved words found where a command is expected.
+    pub fn reserved_words(&self) -> ReservedWords {
+        self.options.reserved_words
+    }
+
+    /// Changes how the parser should treat closing reserved words found where a command is expected.
+    pub fn set_reserved_words(&mut self, reserved_words: ReservedWords) {
+        self.options.reserved_words = reserved_words;
+    }
+
+    /// Returns how the parser currently treats brace expansions within words.
+    pub fn brace_expansion(&self) -> BraceExpansion {
+        self.options.brace_expansion
+    }
+
+    /// Changes how the parser should treat brace expansions within words.
+    pub fn set_brace_expansion(&mut self, brace_expansion: BraceExpansion) {
+        self.options.brace_expansion = brace_expansion;
+    }
+
+    /// Returns how the parser currently treats tildes which begin a tilde prefix.
+    pub fn tilde_expansion(&self) -> TildeExpansion {
+        self.options.tilde_expansion
+    }
+
+    /// Changes how the parser should treat tildes which begin a tilde prefix.
+    pub fn set_tilde_expansion(&mut self, tilde_expansion: TildeExpansion) {
+        self.options.tilde_expansion = tilde_expansion;
+    }
+
+    /// Sets the aliases the parser should expand in any subsequent commands,
+    /// replacing any previously set provider. No aliases are expanded by default.
+    ///
+    /// Like a shell, the parser expands aliases as it goes, since the value of an
+    /// alias may change the grammar of what follows it (e.g. `alias begin='{'`).
+    /// Every unquoted word in command position (i.e. the name of a simple command,
+    /// including one which follows any assignments or redirections) which consists
+    /// only of literal characters, and is not a reserved word, is looked up via
+    /// the provider. If an alias is found, the word is replaced by the tokens of
+    /// its value, which are parsed as if they had appeared in the source instead.
+    ///
+    /// If the value of an alias ends in a blank (e.g. `alias sudo='sudo '`), the
+    /// word following the alias is checked as well, even if it is an argument.
+    /// An alias is never expanded again while its own value (or the value of any
+    /// alias expanded from it) is being parsed, thus `alias ls='ls -F'` only
+    /// expands once.
+    ///
+    /// All tokens of an alias' value are located at the position of the word
+    /// they replaced, thus any nodes built from them have approximate spans.
+    /// The provider must be `Send` and `Sync` so that the parser remains so.
+    ///
+    /// ```
+    /// use conch_parser::ast::printer::ToShellString;
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut aliases = HashMap::new();
+    /// aliases.insert("begin".to_owned(), "{".to_owned());
+    /// aliases.insert("end".to_owned(), "}".to_owned());
+    /// aliases.insert("ll".to_owned(), "ls -l".to_owned());
+    ///
+    /// let lex = Lexer::new("begin ll foo; end".chars());
+    /// let mut parser = DefaultParser::new(lex);
+    /// parser.set_alias_provider(aliases);
+    ///
+    /// let cmd = parser.complete_command().unwrap().unwrap();
+    /// assert_eq!(cmd.to_shell_string(), "{\n    ls -l foo\n}");
+    /// ```
+    pub fn set_alias_provider<A: AliasProvider + Send + Sync + 'static>(&mut self, provider: A) {
+        self.aliases = Some(Aliases::new(Box::new(provider)));
+    }
+
+    /// Stops expanding aliases in any subsequent commands, returning the
+    /// provider which was set via `Parser::set_alias_provider`, if any.
+    pub fn remove_alias_provider(&mut self) -> Option<Box<dyn AliasProvider + Send + Sync>> {
+        self.aliases.take().map(|aliases| aliases.provider)
+    }
+
+    /// Returns the errors which were recovered from (in the order they were
+    /// encountered) since this method was last called. Since errors returned
+    /// by the builder are never recovered from, none of them will be `Custom`.
+    ///
+    /// Each error corresponds to either a region of the source which was passed
+    /// to `Builder::unparsed` while using `ErrorRecovery::Passthrough`, or an
+    /// expansion which was closed at the end of the input while using
+    /// `IncompleteWords::Partial` (innermost expansions first).
+    pub fn take_recovered_errors(&mut self) -> Vec<ParseError<Void>> {
+        mem::take(&mut self.recovered_errors)
+    }
+
+    /// Sets whether the parser should record every reserved word it recognizes,
+    /// which can be retrieved via `Parser::take_keywords`. Disabled by default.
+    ///
+    /// Whether a word such as `in`, `do`, or `esac` is a reserved word depends
+    /// on where it appears (e.g. `echo done` runs `echo` with a `done` argument),
+    /// so tools such as syntax highlighters can use this to determine exactly
+    /// which words the parser has interpreted as reserved words, instead of
+    /// approximating the grammar themselves. Reserved words within a command
+    /// which failed to parse are retained up to the point of the error.
+    pub fn set_track_keywords(&mut self, track: bool) {
+        match (track, self.keywords.is_some()) {
+            (true, false) => self.keywords = Some(Vec::new()),
+            (false, true) => self.keywords = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the reserved words (in source order) which were recognized by
+    /// the parser since this method was last called, if tracking them was
+    /// enabled via `Parser::set_track_keywords`.
+    pub fn take_keywords(&mut self) -> Vec<Keyword> {
+        self.keywords.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Checks if the body of any heredoc parsed so far was delimited by the
+    /// end of the input, rather than by a line matching its delimiter.
+    ///
+    /// Interactive frontends can use this to keep reading lines until the
+    /// delimiter arrives, instead of treating the heredoc as complete.
+    ///
+    /// ```
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let mut parser = DefaultParser::new(Lexer::new("cat <<EOF\nfoo\n".chars()));
+    /// parser.parse_all().unwrap();
+    /// assert!(parser.heredoc_reached_eof());
+    ///
+    /// let mut parser = DefaultParser::new(Lexer::new("cat <<EOF\nfoo\nEOF\n".chars()));
+    /// parser.parse_all().unwrap();
+    /// assert!(!parser.heredoc_reached_eof());
+    /// ```
+    pub fn heredoc_reached_eof(&self) -> bool {
+        self.heredoc_reached_eof
+    }
+
+    /// Returns a structured description of an error returned by this parser,
+    /// including its kind, its span, and everything which would have been
+    /// accepted in place of the offending token.
+    ///
+    /// What the parser expected when encountering an unexpected token, as well
+    /// as the constructs which were still open, are only known for the error it
+    /// most recently returned; they are empty otherwise.
+    ///
+    /// ```
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::{DefaultParser, ErrorKind};
+    ///
+    /// let mut parser = DefaultParser::new(Lexer::new("while true; do echo )".chars()));
+    /// let err = parser.complete_command().unwrap_err();
+    /// let details = parser.error_details(&err);
+    ///
+    /// assert_eq!(details.kind, ErrorKind::Unexpected);
+    /// assert_eq!(details.span.map(|span| span.start.byte), Some(20));
+    /// assert_eq!(details.expected.to_string(), "a command or `done`");
+    /// ```
+    pub fn error_details<E>(&self, err: &ParseError<E>) -> ErrorDetails {
+        let expected = match *err {
+            ParseError::Unexpected(..) | ParseError::UnexpectedEOF => self.expected.clone(),
+            ParseError::IncompleteCmd(_, _, kw, _) => ExpectedSet(vec![Expected::Keyword(kw)]),
+            ParseError::Unmatched(ref open, _) => {
+                let close = match *open {
+                    ParenOpen => Some(ParenClose),
+                    CurlyOpen => Some(CurlyClose),
+                    SquareOpen => Some(SquareClose),
+                    Backtick | SingleQuote | DoubleQuote => Some(open.clone()),
+                    _ => None,
+                };
+                ExpectedSet(close.map(Expected::Token).into_iter().collect())
+            }
+            _ => ExpectedSet::default(),
+        };
+
+        ErrorDetails {
+            kind: err.kind(),
+            span: err.span(),
+            expected,
+            open: self.delims.clone(),
+        }
+    }
+
+    /// Consumes the next token, which the caller has determined to be a
+    /// reserved word, and records it if keywords are being tracked.
+    fn eat_keyword(&mut self) -> Option<Token> {
+        let start = self.iter.pos();
+        let tok = self.iter.next()?;
+
+        if let Some(ref mut keywords) = self.keywords {
+            let mut end = start;
+            end.advance(&tok);
+            keywords.push(Keyword {
+                word: tok.as_str().to_owned(),
+                span: ast::Span { start, end },
+            });
+        }
+
+        Some(tok)
+    }
+
+    /// Checks if the input has ended while the expansion opened by `delim` (at `pos`)
+    /// is still open, and records it as unmatched if it should be closed instead,
+    /// according to the `IncompleteWords` setting of the parser.
+    fn close_at_eof(&mut self, delim: Token, pos: SourcePos) -> bool {
+        if self.options.incomplete_words == IncompleteWords::Reject || self.iter.peek().is_some() {
+            return false;
+        }
+
+        self.recovered_errors.push(ParseError::Unmatched(delim, pos));
+        true
+    }
+
+    /// Like `Parser::close_at_eof`, but for a compound command opened by the
+    /// reserved word `open` (at `pos`) which is still missing its `close` word.
+    fn close_cmd_at_eof(
+        &mut self,
+        open: &'static str,
+        pos: SourcePos,
+        close: &'static str,
+    ) -> bool {
+        if self.options.incomplete_words == IncompleteWords::Reject || self.iter.peek().is_some() {
+            return false;
+        }
+
+        let err = ParseError::IncompleteCmd(open, pos, close, self.iter.pos());
+        self.recovered_errors.push(err);
+        true
+    }
+
+    /// Applies the current `NulHandling` to some literal text found at the specified position.
+    fn check_nul(&self, s: String, pos: SourcePos) -> ParseResult<String, B::Error> {
+        let mut pos = pos;
+        for c in s.chars() {
+            if c == '\0' {
+                return match self.options.nul_handling {
+                    NulHandling::Reject => Err(ParseError::NulByte(pos)),
+                    NulHandling::Replace => Ok(s.replace('\0', &NUL_REPLACEMENT.to_string())),
+                };
+            }
+
+            pos.advance_char(c);
+        }
+
+        Ok(s)
+    }
+
+    /// Returns the parser's current position in the source.
+    pub fn pos(&self) -> SourcePos {
+        self.iter.pos()
+    }
+
+    /// Returns the span from `start` up to the end of the last consumed
+    /// token, disregarding any trailing whitespace.
+    fn span_from(&self, start: SourcePos) -> ast::Span {
+        let end = self.iter.last_end();
+        ast::Span {
+            start,
+            end: if end.byte < start.byte { start } else { end },
+        }
+    }
+
+    /// Resets the parser's position back to the start of a source, so that
+    /// the positions of all subsequent tokens are relative to the next token.
+    ///
+    /// This is useful for restarting line numbering whenever `parse_all` stops at
+    /// the start of a new logical document. It should only be called in between
+    /// complete commands.
+    pub fn reset_pos(&mut self) {
+        self.iter.set_pos(match self.options.position_tracking {
+            PositionTracking::LinesAndColumns => SourcePos::new(),
+            PositionTracking::ByteOffsets => SourcePos::from_byte(0),
+        });
+
+        // Aliases being expanded are tracked by position
+        if let Some(ref mut aliases) = self.aliases {
+            aliases.active.clear();
+            aliases.check_next = None;
+        }
+    }
+
+    /// Returns `true` if the parser has consumed all of its input.
+    pub fn is_exhausted(&mut self) -> bool {
+        self.iter.peek().is_none()
+    }
+
+    /// Saves the current state of the parser so that it can speculatively
+    /// attempt to parse some construct and back off if it turns out the
+    /// input is something else, e.g. when parsing custom keywords.
+    ///
+    /// The checkpoint captures the position in the token stream (including
+    /// any tokens buffered for look ahead or while collecting heredoc bodies),
+    /// the current source position, and any recovered errors (or tracked
+    /// keywords, see `Parser::set_track_keywords`). Checkpoints can
+    /// be nested, but must be rewound or committed in the reverse order they
+    /// were created; doing either to a checkpoint also releases all checkpoints
+    /// created after it.
+    ///
+    /// Note that any nodes created while parsing speculatively have already
+    /// been passed to the builder, and a rewind will not undo this. Builders
+    /// with side effects (e.g. `EventLog`) will observe them all, including any
+    /// nested contexts which were entered (see `Builder::enter_context`) but
+    /// which will not be exited due to the rewind.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        Checkpoint {
+            iter: self.iter.checkpoint(),
+            recovered_errors: self.recovered_errors.len(),
+            keywords: self.keywords.as_ref().map_or(0, Vec::len),
+            contexts: self.contexts.len(),
+            delims: self.delims.len(),
+            aliases: self.aliases.as_ref().map(Aliases::save),
+        }
+    }
+
+    /// Restores the parser to the state it was in when the checkpoint was
+    /// created, such that all tokens consumed since will be parsed again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint was already released, i.e. if it was created
+    /// after another checkpoint which was rewound or committed.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.iter.rewind_to(checkpoint.iter);
+        self.recovered_errors.truncate(checkpoint.recovered_errors);
+        if let Some(ref mut keywords) = self.keywords {
+            keywords.truncate(checkpoint.keywords);
+        }
+        self.contexts.truncate(checkpoint.contexts);
+        self.delims.truncate(checkpoint.delims);
+        self.stream_delims = None;
+        if let (Some(aliases), Some(saved)) = (self.aliases.as_mut(), checkpoint.aliases) {
+            aliases.restore(saved);
+        }
+    }
+
+    /// Accepts everything parsed since the checkpoint was created,
+    /// releasing it without changing the state of the parser.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint was already released, i.e. if it was created
+    /// after another checkpoint which was rewound or committed.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        self.iter.release(checkpoint.iter);
+    }
+
+    /// Runs `f` speculatively, rewinding the parser to where it started if
+    /// `f` fails, or committing whatever it parsed if it succeeds.
+    ///
+    /// See `Parser::checkpoint` for more details.
+    pub fn speculate<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        let checkpoint = self.checkpoint();
+        let ret = f(self);
+        match ret {
+            Ok(_) => self.commit(checkpoint),
+            Err(_) => self.rewind(checkpoint),
+        }
+        ret
+    }
+
+    /// Parses all complete commands of the current logical document.
+    ///
+    /// Parsing stops at the end of input, or when a shebang line (i.e. a line
+    /// starting with `#!`) is found after at least one complete command has been
+    /// parsed. Such a line is assumed to start a new script, as is the case when
+    /// several scripts are concatenated into a single stream (e.g. `cat a.sh b.sh | sh`).
+    /// The shebang is left unconsumed, allowing `parse_all` to be called again
+    /// to parse the next document (optionally after calling `reset_pos`).
+    ///
+    /// Once the input has been exhausted, an empty list will be returned.
+    pub fn parse_all(&mut self) -> ParseResult<Vec<B::Command>, B::Error> {
+        let mut cmds = Vec::new();
+
+        loop {
+            let mut comments = Vec::new();
+            loop {
+                self.skip_whitespace();
+                if !cmds.is_empty() && self.peek_shebang() {
+                    if !comments.is_empty() {
+                        self.builder.comments(comments)?;
+                    }
+                    return Ok(cmds);
+                }
+
+                match self.newline() {
+                    Some(n) => comments.push(n),
+                    None => break,
+                }
+            }
+
+            if self.iter.peek().is_none() {
+                if !comments.is_empty() {
+                    self.builder.comments(comments)?;
+                }
+                return Ok(cmds);
+            }
+
+            cmds.push(self.top_level_command(comments)?);
+        }
+    }
+
+    /// Checks if the next tokens are a `<(` or `>(` which start a process substitution.
+    fn peek_process_substitution(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        match peeked.peek_next() {
+            Some(&Less) | Some(&Great) => {}
+            _ => return false,
+        }
+
+        peeked.peek_next() == Some(&ParenOpen)
+    }
+
+    /// Checks if the next tokens are a `;&` terminator of a `case` arm.
+    fn peek_case_fall_through(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        peeked.peek_next() == Some(&Semi) && peeked.peek_next() == Some(&Amp)
+    }
+
+    /// Checks if the next tokens are a `(< file)` command substitution which
+    /// only reads the contents of a file (i.e. `$(< file)`), a bash extension.
+    ///
+    /// The file name must be a single word followed by nothing but whitespace
+    /// until the closing paren, otherwise the substitution should be treated as
+    /// a regular command (e.g. `$(< file cat)` or `$(< file; foo)`).
+    fn peek_read_file(&mut self) -> bool {
+        if self.options.dialect == Dialect::Posix {
+            return false;
+        }
+
+        let mut peeked = self.iter.multipeek();
+        if peeked.peek_next() != Some(&ParenOpen) {
+            return false;
+        }
+
+        let mut next = peeked.peek_next().cloned();
+        while let Some(Whitespace(_)) = next {
+            next = peeked.peek_next().cloned();
+        }
+        if next != Some(Less) {
+            return false;
+        }
+
+        next = peeked.peek_next().cloned();
+        while let Some(Whitespace(_)) = next {
+            next = peeked.peek_next().cloned();
+        }
+
+        // Scan the file name word, keeping track of quotes and nested substitutions
+        let mut empty = true;
+        let mut depth = 0usize;
+        let mut after_dollar = false;
+        loop {
+            let tok = match next {
+                Some(tok) => tok,
+                None => return false,
+            };
+
+            match tok {
+                ParenClose | Whitespace(_) | Newline if depth == 0 => {
+                    next = Some(tok);
+                    break;
+                }
+
+                ParenOpen | CurlyOpen if after_dollar || depth > 0 => depth += 1,
+                ParenClose | CurlyClose if depth > 0 => depth -= 1,
+
+                Semi | Amp | Pipe | AndIf | OrIf | DSemi | Less | Great | DLess | DGreat
+                | GreatAnd | LessAnd | DLessDash | Clobber | LessGreat | ParenOpen
+                    if depth == 0 =>
+                {
+                    return false
+                }
+
+                SingleQuote | DoubleQuote | Backtick if depth == 0 => loop {
+                    match peeked.peek_next() {
+                        Some(t) if *t == tok => break,
+                        Some(&Backslash) if tok != SingleQuote => {
+                            let _ = peeked.peek_next();
+                        }
+                        Some(_) => {}
+                        None => return false,
+                    }
+                },
+
+                // Skip over whatever token is being escaped
+                Backslash => match peeked.peek_next() {
+                    Some(_) => {}
+                    None => return false,
+                },
+
+                _ => {}
+            }
+
+            empty = false;
+            after_dollar = tok == Dollar;
+            next = peeked.peek_next().cloned();
+        }
+
+        while let Some(Whitespace(_)) | Some(Newline) = next {
+            next = peeked.peek_next().cloned();
+        }
+
+        !empty && next == Some(ParenClose)
+    }
+
+    /// Checks if the next tokens are the `((` of an arithmetic substitution
+    /// (i.e. `$((...))`) rather than a command substitution whose body starts
+    /// with a subshell (e.g. `$((cmd) | other)`).
+    ///
+    /// Like bash, the substitution is only considered arithmetic if the paren
+    /// matching the second `(` is immediately followed by another `)`. If the
+    /// parens are unbalanced the substitution is assumed to be arithmetic.
+    fn peek_arith_subst(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        if peeked.peek_next() != Some(&ParenOpen) || peeked.peek_next() != Some(&ParenOpen) {
+            return false;
+        }
+
+        let mut depth = 0usize;
+        loop {
+            let tok = match peeked.peek_next() {
+                Some(tok) => tok.clone(),
+                None => return true,
+            };
+
+            match tok {
+                ParenOpen => depth += 1,
+                ParenClose if depth > 0 => depth -= 1,
+                ParenClose => return peeked.peek_next() == Some(&ParenClose),
+
+                SingleQuote | DoubleQuote | Backtick => loop {
+                    match peeked.peek_next() {
+                        Some(t) if *t == tok => break,
+                        Some(&Backslash) if tok != SingleQuote => {
+                            let _ = peeked.peek_next();
+                        }
+                        Some(_) => {}
+                        None => return true,
+                    }
+                },
+
+                // Skip over whatever token is being escaped
+                Backslash if peeked.peek_next().is_none() => return true,
+
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks if the next tokens are a `#!` at the very start of a line.
+    fn peek_shebang(&mut self) -> bool {
+        if !self.iter.at_line_start() {
+            return false;
+        }
+
+        let mut peeked = self.iter.multipeek();
+        peeked.peek_next() == Some(&Pound) && peeked.peek_next() == Some(&Bang)
+    }
+
+    /// Parses all remaining commands, skipping any command which fails to parse
+    /// (up to the next `;`, `&`, or newline) instead of stopping at the first error.
+    ///
+    /// This allows tools such as editors and linters to inspect scripts which are
+    /// temporarily invalid (e.g. while they are being edited). Unlike `parse_all`,
+    /// the input is always parsed until it is exhausted, and the `ErrorRecovery`
+    /// setting of the parser is ignored: failed commands are never passed to the
+    /// builder. Any errors otherwise recovered from (see `Parser::take_recovered_errors`)
+    /// are returned along with the errors of the skipped commands.
+    ///
+    /// Errors returned by the builder itself are never recovered from.
+    pub fn parse_with_recovery(&mut self) -> Result<PartialParse<B::Command>, B::Error> {
+        let error_recovery = self.options.error_recovery;
+        self.options.error_recovery = ErrorRecovery::Abort;
+        let result = self.parse_with_recovery_internal();
+        self.options.error_recovery = error_recovery;
+        result
+    }
+
+    fn parse_with_recovery_internal(&mut self) -> Result<PartialParse<B::Command>, B::Error> {
+        let mut commands = Vec::new();
+        let mut errors = Vec::new();
+        let mut related_to = Vec::new();
+        // The primary error whose follow-on errors are currently being encountered,
+        // until all constructs it (or its follow-on errors) left open are closed
+        let mut cascade = None;
+        let mut unclosed: Vec<OpenDelimiter> = Vec::new();
+
+        loop {
+            let start = self.iter.pos();
+            let closing = match unclosed.last() {
+                Some(delim) => self.peek_construct_end(delim),
+                None => false,
+            };
+            let depth = unclosed.len();
+
+            let result = self.complete_command().map_err(ParseError::into_custom);
+            for err in self.recovered_errors.drain(..) {
+                errors.push(err);
+                related_to.push(cascade);
+            }
+
+            match result {
+                Ok(Some(cmd)) => commands.push(cmd),
+                Ok(None) => {
+                    return Ok(PartialParse {
+                        commands,
+                        errors,
+                        related_to,
+                    })
+                }
+                Err(Ok(e)) => return Err(e),
+                Err(Err(e)) => {
+                    let open = self.stream_delims.unwrap_or(self.delims.len());
+                    if cascade.is_none() && open > 0 {
+                        cascade = Some(errors.len());
+                        related_to.push(None);
+                    } else {
+                        related_to.push(cascade);
+                    }
+
+                    if cascade.is_some() {
+                        unclosed.extend_from_slice(&self.delims[..open]);
+                    }
+                    self.skip_failed_command(&e, start, &mut unclosed);
+                    errors.push(e);
+                }
+            }
+
+            if closing && depth <= unclosed.len() {
+                unclosed.remove(depth - 1);
+            }
+            if unclosed.is_empty() {
+                cascade = None;
+            }
+        }
+    }
+
+    /// Parses complete commands until the end of input, or until one of the limits
+    /// is reached, in which case the remainder of the input is left unconsumed.
+    ///
+    /// This allows tools which show previews or outlines of potentially large
+    /// scripts to only pay for parsing (and lexing) the start of the script.
+    /// Once a limit is reached, the parser can be used to continue parsing the
+    /// rest of the input as usual, e.g. via `parse_all`.
+    ///
+    /// ```
+    /// use conch_parser::ast::builder::StringBuilder;
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::{Parser, PreviewLimits};
+    ///
+    /// let lex = Lexer::new("echo a\necho b\necho c\n".chars());
+    /// let mut parser = Parser::with_builder(lex, StringBuilder::new());
+    /// let limits = PreviewLimits {
+    ///     max_commands: Some(2),
+    ///     ..PreviewLimits::default()
+    /// };
+    ///
+    /// let preview = parser.parse_preview(limits).unwrap();
+    /// assert_eq!(preview.commands.len(), 2);
+    /// assert!(preview.truncated);
+    /// ```
+    pub fn parse_preview(
+        &mut self,
+        limits: PreviewLimits,
+    ) -> ParseResult<Preview<B::Command>, B::Error> {
+        let mut commands = Vec::new();
+
+        loop {
+            let reached_commands = limits.max_commands.is_some_and(|max| commands.len() >= max);
+            let reached_bytes = limits
+                .max_bytes
+                .is_some_and(|max| self.iter.pos().byte >= max);
+
+            if reached_commands || reached_bytes {
+                let truncated = self.peek_command_start();
+                return Ok(Preview {
+                    commands,
+                    truncated,
+                });
+            }
+
+            match self.complete_command()? {
+                Some(cmd) => commands.push(cmd),
+                None => {
+                    return Ok(Preview {
+                        commands,
+                        truncated: false,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Checks if anything other than whitespace, newlines, or comments remains.
+    fn peek_command_start(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        let mut in_comment = false;
+
+        loop {
+            match peeked.peek_next() {
+                None => return false,
+                Some(&Newline) => in_comment = false,
+                Some(&Pound) => in_comment = true,
+                Some(&Whitespace(_)) => {}
+                Some(_) if in_comment => {}
+                Some(_) => return true,
+            }
+        }
+    }
+
+    /// Skips the remainder of a command which failed to parse with the specified
+    /// error, up to (and including) the next `;`, `&`, or newline.
+    fn skip_failed_command(
+        &mut self,
+        err: &ParseError<Void>,
+        start: SourcePos,
+        unclosed: &mut Vec<OpenDelimiter>,
+    ) {
+        // The offending token has already been consumed if it was a separator,
+        // but some token must be consumed regardless to guarantee progress
+        match *err {
+            ParseError::Unexpected(Semi, _)
+            | ParseError::Unexpected(Amp, _)
+            | ParseError::Unexpected(Newline, _)
+                if self.iter.pos() != start => {}
+
+            _ => {
+                for tok in &mut self.iter {
+                    if let Semi | Amp | Newline = tok {
+                        break;
+                    }
+
+                    // Skipping past the end of a construct left open by an earlier error
+                    if unclosed.last().is_some_and(|delim| ends_construct(delim, &tok)) {
+                        unclosed.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks if the next command (after any blank lines or comments) starts
+    /// with the token or reserved word which ends the specified construct.
+    fn peek_construct_end(&mut self, delim: &OpenDelimiter) -> bool {
+        let mut peeked = self.iter.multipeek();
+        loop {
+            match peeked.peek_next() {
+                Some(&Whitespace(_)) | Some(&Newline) => {}
+                Some(&Pound) => loop {
+                    match peeked.peek_next() {
+                        Some(&Newline) | None => break,
+                        Some(_) => {}
+                    }
+                },
+                Some(tok) => return ends_construct(delim, tok),
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns an iterator which parses and yields one complete command at a
+    /// time, without consuming the parser (unlike `Parser::into_iter`).
+    ///
+    /// Each command is parsed only once it is requested, so that consumers
+    /// (e.g. a REPL reading from standard input) can act on every command as
+    /// soon as it has been parsed, rather than waiting for the entire script.
+    ///
+    /// ```
+    /// use conch_parser::lexer::Lexer;
+    /// use conch_parser::parse::DefaultParser;
+    ///
+    /// let mut parser = DefaultParser::new(Lexer::new("echo foo; echo bar\n) echo baz".chars()));
+    /// let mut commands = parser.commands();
+    /// assert!(commands.next().unwrap().is_ok());
+    /// assert!(commands.next().unwrap().is_ok());
+    ///
+    /// let err = commands.next().unwrap().unwrap_err();
+    /// assert!(commands.next().is_none());
+    /// assert_eq!(parser.error_details(&err).expected.to_string(), "a command");
+    /// ```
+    pub fn commands(&mut self) -> Commands<'_, I, B> {
+        Commands { parser: Some(self) }
+    }
+
+    /// Parses a single complete command.
+    ///
+    /// For example, `foo && bar; baz` will yield two complete
+    /// commands: `And(foo, bar)`, and `Simple(baz)`.
+    pub fn complete_command(&mut self) -> ParseResult<Option<B::Command>, B::Error> {
+        self.delims.clear();
+        self.stream_delims = None;
+        let pre_cmd_comments = self.linebreak();
+
+        if self.iter.peek().is_some() {
+            Ok(Some(self.top_level_command(pre_cmd_comments)?))
+        } else {
+            if !pre_cmd_comments.is_empty() {
+                self.builder.comments(pre_cmd_comments)?;
+            }
+            Ok(None)
+        }
+    }
+
+    /// Parses a single top-level complete command (expecting the caller to parse any
+    /// leading comments), recovering from any errors according to the `ErrorRecovery`
+    /// setting of the parser.
+    fn top_level_command(
+        &mut self,
+        pre_cmd_comments: Vec<builder::Newline>,
+    ) -> ParseResult<B::Command, B::Error> {
+        if self.options.error_recovery == ErrorRecovery::Abort {
+            return self.complete_command_with_leading_comments(pre_cmd_comments);
+        }
+
+        let start = self.iter.pos();
+        self.iter.start_capture();
+        let result = self.complete_command_with_leading_comments(pre_cmd_comments.clone());
+        let err = match result.map_err(ParseError::into_custom) {
+            Ok(cmd) => {
+                let _ = self.iter.take_capture();
+                return Ok(cmd);
+            }
+            Err(Ok(e)) => {
+                let _ = self.iter.take_capture();
+                return Err(ParseError::Custom(e));
+            }
+            Err(Err(e)) => e,
+        };
+
+        // Skip the remainder of the line on which the error occured, unless
+        // the offending token was the newline which terminates it
+        if let ParseError::Unexpected(Newline, _) = err {
+        } else {
+            for tok in &mut self.iter {
+                if tok == Newline {
+                    break;
+                }
+            }
+        }
+
+        let span = UnparsedSpan {
+            text: self.iter.take_capture(),
+            start,
+            end: self.iter.pos(),
+        };
+
+        // Exit any contexts the error occured in, innermost first
+        while let Some(kind) = self.contexts.pop() {
+            self.builder.exit_context(kind)?;
+        }
+
+        self.recovered_errors.push(err);
+        Ok(self.builder.unparsed(pre_cmd_comments, span)?)
+    }
+
+    /// Parses a single complete command, but expects caller to parse any leading comments.
+    ///
+    /// It is considered an error there is not a valid complete command to be parsed, thus
+    /// the caller should perform any EOF checks.
+    fn complete_command_with_leading_comments(
+        &mut self,
+        pre_cmd_comments: Vec<builder::Newline>,
+    ) -> ParseResult<B::Command, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        let cmd = self.and_or_list()?;
+
+        // A `;&` terminates the arm of a `case` command, rather than the command itself
+        let sep = if self.peek_case_fall_through() {
+            None
+        } else {
+            eat_maybe!(self, {
+                Semi => { Some(builder::SeparatorKind::Semi) },
+                Amp  => { Some(builder::SeparatorKind::Amp) };
+                _ => { None }
+            })
+        };
+
+        // The span should not include any comment which follows the command
+        let span = self.span_from(start);
+        let cmd_comment = self.newline();
+        let sep = match (sep, &cmd_comment) {
+            (Some(sep), _) => sep,
+            (None, Some(_)) => builder::SeparatorKind::Newline,
+            (None, None) => builder::SeparatorKind::Other,
+        };
+
+        Ok(self
+            .builder
+            .complete_command(pre_cmd_comments, cmd, sep, cmd_comment, span)?)
+    }
+
+    /// Parses compound AND/OR commands.
+    ///
+    /// `&&` and `||` have equal precedence and are left associative, so the
+    /// commands are collected into a single flat list instead of nested nodes.
+    /// For example, `foo || bar && baz` yields `foo` as the first command,
+    /// followed by `Or(bar)` and `And(baz)`, and is evaluated as `(foo || bar) && baz`.
+    pub fn and_or_list(&mut self) -> ParseResult<B::CommandList, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        let first = self.pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            let is_and = eat_maybe!(self, {
+                AndIf => { true },
+                OrIf  => { false };
+                _ => { break },
+            });
+
+            let post_sep_comments = self.linebreak();
+            let next = self.pipeline()?;
+
+            let next = if is_and {
+                ast::AndOr::And(next)
+            } else {
+                ast::AndOr::Or(next)
+            };
+
+            rest.push((post_sep_comments, next));
+        }
+
+        let span = self.span_from(start);
+        Ok(self.builder.and_or_list(first, rest, span)?)
+    }
+
+    /// Parses either a single command or a pipeline of commands.
+    ///
+    /// For example `[!] foo | bar`.
+    pub fn pipeline(&mut self) -> ParseResult<B::ListableCommand, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        let bang = Some(&Bang) == self.iter.peek();
+        if bang {
+            self.eat_keyword();
+        }
 
         let mut cmds = Vec::new();
+        let mut pipe = None;
+        let mut pipe_stderr = false;
         loop {
             // We've already passed an apropriate spot for !, so it
             // is an error if it appears before the start of a command.
             if let Some(&Bang) = self.iter.peek() {
-                return Err(self.make_unexpected_err());
+                return Err(self.make_cmd_err());
             }
 
-            let cmd = self.command()?;
+            let segment = ast::PipeSegment {
+                pipe: pipe.take(),
+                pipe_stderr,
+                cmd: self.command()?,
+            };
+            let pipe_pos = self.iter.pos();
 
             eat_maybe!(self, {
-                Pipe => { cmds.push((self.linebreak(), cmd)) };
+                Pipe => {
+                    pipe_stderr = Some(&Amp) == self.iter.peek();
+                    if pipe_stderr {
+                        if !self.options.dialect.supports(Extension::PipeAmp) {
+                            return Err(ParseError::Extension(Extension::PipeAmp, pipe_pos));
+                        }
+                        self.iter.next();
+                    }
+
+                    pipe = Some(ast::Span {
+                        start: pipe_pos,
+                        end: self.iter.pos(),
+                    });
+                    cmds.push((self.linebreak(), segment))
+                };
                 _ => {
-                    cmds.push((Vec::new(), cmd));
+                    cmds.push((Vec::new(), segment));
                     break;
                 },
             });
         }
 
-        Ok(self.builder.pipeline(bang, cmds)?)
+        let span = self.span_from(start);
+        Ok(self.builder.pipeline(bang, cmds, span)?)
     }
 
     /// Parses any compound or individual command.
     pub fn command(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
+        if let Some(ref mut aliases) = self.aliases {
+            aliases.check_next = None;
+        }
+        while self.expand_alias() {}
+
+        // Bash supports every extension, so there is no need to look for them
+        if self.options.dialect != Dialect::Bash {
+            if let Some((ext, pos)) = self.peek_extension() {
+                if !self.options.dialect.supports(ext) {
+                    return Err(ParseError::Extension(ext, pos));
+                }
+            }
+        }
+
+        if self.options.reserved_words == ReservedWords::Strict
+            && (self
+                .peek_keyword(&[Then, Else, Elif, Fi, Do, Done, Esac, In])
+                .is_some()
+                || self.peek_reserved_token(&[CurlyClose]).is_some())
+        {
+            return Err(self.make_cmd_err());
+        }
+
         if let Some(kw) = self.next_compound_command_type() {
             let compound = self.compound_command_internal(Some(kw))?;
             Ok(self.builder.compound_command_into_pipeable(compound)?)
@@ -588,37 +2324,24 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     pub fn simple_command(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
         use crate::ast::{RedirectOrCmdWord, RedirectOrEnvVar};
 
+        self.skip_whitespace();
+        let start = self.iter.pos();
         let mut vars = Vec::new();
         let mut cmd_args = Vec::new();
 
         loop {
             self.skip_whitespace();
-            let is_name = {
-                let mut peeked = self.iter.multipeek();
-                if let Some(&Name(_)) = peeked.peek_next() {
-                    Some(&Equals) == peeked.peek_next()
-                } else {
-                    false
-                }
-            };
-
-            if is_name {
-                if let Some(Name(var)) = self.iter.next() {
-                    self.iter.next(); // Consume the =
+            if let Some((var, value)) = self.assignment()? {
+                vars.push(RedirectOrEnvVar::EnvVar(var, value));
 
-                    let value = if let Some(&Whitespace(_)) = self.iter.peek() {
-                        None
-                    } else {
-                        self.word()?
-                    };
-                    vars.push(RedirectOrEnvVar::EnvVar(var, value));
+                // Make sure we continue checking for assignments,
+                // otherwise it they can be interpreted as literal words.
+                continue;
+            }
 
-                    // Make sure we continue checking for assignments,
-                    // otherwise it they can be interpreted as literal words.
-                    continue;
-                } else {
-                    unreachable!();
-                }
+            // The command name may follow any assignments or redirects
+            if !vars.is_empty() {
+                while self.expand_alias() {}
             }
 
             // If we find a redirect we should keep checking for
@@ -644,6 +2367,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // Now that all assignments are taken care of, any other occurances of `=` will be
         // treated as literals when we attempt to parse a word out.
         loop {
+            self.expand_alias_after_blank();
             match self.redirect()? {
                 Some(Ok(redirect)) => cmd_args.push(RedirectOrCmdWord::Redirect(redirect)),
                 Some(Err(w)) => cmd_args.push(RedirectOrCmdWord::CmdWord(w)),
@@ -654,32 +2378,90 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // "Blank" commands are only allowed if redirection occurs
         // or if there is some variable assignment
         if vars.is_empty() && cmd_args.is_empty() {
-            Err(self.make_unexpected_err())
+            Err(self.make_cmd_err())
         } else {
-            Ok(self.builder.simple_command(vars, cmd_args)?)
+            let span = self.span_from(start);
+            Ok(self.builder.simple_command(vars, cmd_args, span)?)
+        }
+    }
+
+    /// Parses a variable assignment, e.g. `NAME=value`, returning the name of the
+    /// variable and its value (which is `None` for an empty assignment like `NAME=`).
+    ///
+    /// Nothing is consumed (and `None` is returned) unless the next tokens are
+    /// a valid name immediately followed by an `=`, thus the caller may try to
+    /// parse a word or redirect instead if no assignment is found.
+    pub fn assignment(&mut self) -> ParseResult<Option<Assignment<B::Word>>, B::Error> {
+        let is_name = {
+            let mut peeked = self.iter.multipeek();
+            if let Some(&Name(_)) = peeked.peek_next() {
+                Some(&Equals) == peeked.peek_next()
+            } else {
+                false
+            }
+        };
+
+        if !is_name {
+            return Ok(None);
         }
+
+        let var = match self.iter.next() {
+            Some(Name(var)) => var,
+            _ => unreachable!(),
+        };
+        self.iter.next(); // Consume the =
+
+        let value = if let Some(&Whitespace(_)) = self.iter.peek() {
+            None
+        } else {
+            let start = self.iter.pos();
+            let value = match self.word_preserve_trailing_whitespace_raw()? {
+                Some(w) => {
+                    let w = self.tilde_prefixes(w, true);
+                    Some(self.builder.word(w, self.span_from(start))?)
+                }
+                None => None,
+            };
+            self.skip_whitespace();
+            value
+        };
+
+        Ok(Some((var, value)))
     }
 
     /// Parses a continuous list of redirections and will error if any words
     /// that are not valid file descriptors are found. Essentially used for
     /// parsing redirection lists after a compound command like `while` or `if`.
     pub fn redirect_list(&mut self) -> ParseResult<Vec<B::Redirect>, B::Error> {
+        self.redirect_list_with_span().map(|(list, _)| list)
+    }
+
+    /// Parses a continuous list of redirections like `Parser::redirect_list`,
+    /// additionally returning the location of the entire list (if non-empty).
+    fn redirect_list_with_span(&mut self) -> ParseResult<SpannedRedirects<B::Redirect>, B::Error> {
         let mut list = Vec::new();
+        let mut span: Option<ast::Span> = None;
         loop {
             self.skip_whitespace();
             let start_pos = self.iter.pos();
             match self.redirect()? {
-                Some(Ok(io)) => list.push(io),
+                Some(Ok(io)) => {
+                    let end = self.iter.pos();
+                    let start = span.map_or(start_pos, |span| span.start);
+                    span = Some(ast::Span { start, end });
+                    list.push(io)
+                }
                 Some(Err(_)) => return Err(ParseError::BadFd(start_pos, self.iter.pos())),
                 None => break,
             }
         }
 
-        Ok(list)
+        Ok((list, span))
     }
 
     /// Parses a redirection token an any source file descriptor and
-    /// path/destination descriptor as appropriate, e.g. `>out`, `1>& 2`, or `2>&-`.
+    /// path/destination descriptor as appropriate, e.g. `>out`, `1>& 2`, or `2>&-`
+    /// (or `&>out` in dialects which support redirecting both stdout and stderr).
     ///
     /// Since the source descriptor can be any arbitrarily complicated word,
     /// it makes it difficult to reliably peek forward whether a valid redirection
@@ -698,6 +2480,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 | SimpleWordKind::SquareOpen
                 | SimpleWordKind::SquareClose
                 | SimpleWordKind::Tilde
+                | SimpleWordKind::TildePrefix(_)
                 | SimpleWordKind::Colon => false,
 
                 // Literals and can be statically checked if they have non-numeric characters
@@ -716,6 +2499,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 Simple(ref s) => simple_could_be_numeric(s),
                 SingleQuoted(ref s) => s.chars().all(|c| c.is_digit(10)),
                 DoubleQuoted(ref fragments) => fragments.iter().all(simple_could_be_numeric),
+                WordKind::Braces(_) => false,
             }
         }
 
@@ -738,22 +2522,45 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         }
 
-        let (src_fd, src_fd_as_word) = match self.word_preserve_trailing_whitespace_raw()? {
-            None => (None, None),
-            Some(w) => match as_num(&w) {
-                Some(num) => (Some(num), Some(w)),
-                None => return Ok(Some(Err(self.builder.word(w)?))),
-            },
+        self.skip_whitespace();
+        let start = self.iter.pos();
+
+        // Redirecting both stdout and stderr via `&>` or `&>>`, which never have a source fd
+        let both = self.options.dialect.supports(Extension::RedirectBoth) && {
+            let mut peeked = self.iter.multipeek();
+            peeked.peek_next() == Some(&Amp)
+                && matches!(peeked.peek_next(), Some(&Great) | Some(&DGreat))
+        };
+
+        let (src_fd, src_fd_as_word) = if both {
+            self.iter.next();
+            (None, None)
+        } else {
+            match self.word_preserve_trailing_whitespace_raw()? {
+                None => (None, None),
+                Some(w) => match as_num(&w) {
+                    Some(num) => (Some(num), Some(w)),
+                    None => {
+                        let w = self.tilde_prefixes(self.brace_expansions(w), false);
+                        return Ok(Some(Err(self.builder.word(w, self.span_from(start))?)));
+                    }
+                },
+            }
         };
 
         let redir_tok = match self.iter.peek() {
             Some(&Less) | Some(&Great) | Some(&DGreat) | Some(&Clobber) | Some(&LessAnd)
             | Some(&GreatAnd) | Some(&LessGreat) => self.iter.next().unwrap(),
 
-            Some(&DLess) | Some(&DLessDash) => return Ok(Some(Ok(self.redirect_heredoc(src_fd)?))),
+            Some(&DLess) | Some(&DLessDash) => {
+                return Ok(Some(Ok(self.redirect_heredoc_from(src_fd, start)?)))
+            }
 
             _ => match src_fd_as_word {
-                Some(w) => return Ok(Some(Err(self.builder.word(w)?))),
+                Some(w) => {
+                    let w = self.tilde_prefixes(w, false);
+                    return Ok(Some(Err(self.builder.word(w, self.span_from(start))?)));
+                }
                 None => return Ok(None),
             },
         };
@@ -761,25 +2568,30 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         self.skip_whitespace();
 
         macro_rules! get_path {
-            ($parser:expr) => {
+            ($parser:expr) => {{
+                let path_start_pos = $parser.iter.pos();
                 match $parser.word_preserve_trailing_whitespace_raw()? {
-                    Some(p) => $parser.builder.word(p)?,
-                    None => return Err(self.make_unexpected_err()),
+                    Some(p) => {
+                        let p = $parser.tilde_prefixes($parser.brace_expansions(p), false);
+                        $parser.builder.word(p, $parser.span_from(path_start_pos))?
+                    }
+                    None => return Err(self.make_expected_err(vec![Expected::Word])),
                 }
-            };
+            }};
         }
 
         macro_rules! get_dup_path {
             ($parser:expr) => {{
+                let path_start_pos = $parser.iter.pos();
                 let path = if $parser.peek_reserved_token(&[Dash]).is_some() {
-                    let dash = $parser.reserved_token(&[Dash])?;
+                    // A lone `-` closes the descriptor, but is not a reserved word
+                    let dash = $parser.iter.next().unwrap();
                     Single(Simple(SimpleWordKind::Literal(dash.to_string())))
                 } else {
-                    let path_start_pos = $parser.iter.pos();
                     let path = if let Some(p) = $parser.word_preserve_trailing_whitespace_raw()? {
                         p
                     } else {
-                        return Err($parser.make_unexpected_err());
+                        return Err($parser.make_expected_err(vec![Expected::Word]));
                     };
                     let is_numeric = match path {
                         Single(ref p) => could_be_numeric(&p),
@@ -791,11 +2603,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         return Err(ParseError::BadFd(path_start_pos, self.iter.pos()));
                     }
                 };
-                $parser.builder.word(path)?
+                $parser.builder.word(path, $parser.span_from(path_start_pos))?
             }};
         }
 
         let redirect = match redir_tok {
+            Great if both => builder::RedirectKind::WriteBoth(get_path!(self)),
+            DGreat if both => builder::RedirectKind::AppendBoth(get_path!(self)),
+
             Less => builder::RedirectKind::Read(src_fd, get_path!(self)),
             Great => builder::RedirectKind::Write(src_fd, get_path!(self)),
             DGreat => builder::RedirectKind::Append(src_fd, get_path!(self)),
@@ -808,7 +2623,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             _ => unreachable!(),
         };
 
-        Ok(Some(Ok(self.builder.redirect(redirect)?)))
+        let span = self.span_from(start);
+        Ok(Some(Ok(self.builder.redirect(redirect, span)?)))
     }
 
     /// Parses a heredoc redirection and the heredoc's body.
@@ -837,6 +2653,18 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// Note: this method expects that the caller provide a potential file
     /// descriptor for redirection.
     pub fn redirect_heredoc(&mut self, src_fd: Option<u16>) -> ParseResult<B::Redirect, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        self.redirect_heredoc_from(src_fd, start)
+    }
+
+    /// Parses a heredoc redirection like `Parser::redirect_heredoc`, whose
+    /// source file descriptor (if any) was found at `start`.
+    fn redirect_heredoc_from(
+        &mut self,
+        src_fd: Option<u16>,
+        start: SourcePos,
+    ) -> ParseResult<B::Redirect, B::Error> {
         use std::iter::FromIterator;
 
         macro_rules! try_map {
@@ -964,7 +2792,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
 
         if delim.is_empty() {
-            return Err(self.make_unexpected_err());
+            return Err(self.make_expected_err(vec![Expected::Word]));
         }
 
         delim.shrink_to_fit();
@@ -996,16 +2824,22 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
 
         let heredoc_start_pos = self.iter.pos();
+        let heredoc_end_pos;
         let mut heredoc = Vec::new();
+        let mut raw_lines = Vec::new();
         'heredoc: loop {
-            let mut line_start_pos = self.iter.pos();
+            let line_pos = self.iter.pos();
+            let mut line_start_pos = line_pos;
             let mut line = Vec::new();
+            let mut raw_line = String::new();
             'line: loop {
-                if strip_tabs {
+                // Only the tabs at the very start of the line are stripped
+                if strip_tabs && line.is_empty() && raw_line.is_empty() {
                     let skip_next = if let Some(&Whitespace(ref w)) = self.iter.peek() {
                         let stripped = w.trim_start_matches('\t');
                         let num_tabs = w.len() - stripped.len();
                         line_start_pos.advance_tabs(num_tabs);
+                        raw_line.push_str(w);
 
                         if !stripped.is_empty() {
                             line.push(Whitespace(stripped.to_owned()));
@@ -1025,7 +2859,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 match next {
                     // If we haven't grabbed any input we must have hit EOF
                     // which should delimit the heredoc body
-                    None if line.is_empty() => break 'heredoc,
+                    None if line.is_empty() => {
+                        heredoc_end_pos = self.iter.pos();
+                        self.heredoc_reached_eof = true;
+                        break 'heredoc;
+                    }
 
                     // Otherwise, if we have a partial line captured, check
                     // whether it happens to be the delimeter, and append it
@@ -1052,6 +2890,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         if line_len == delim_len || line_len == delim_r_len {
                             let line_str = concat_tokens(&line);
                             if line_str == delim || line_str == delim_r {
+                                heredoc_end_pos = line_pos;
                                 break 'heredoc;
                             }
                         }
@@ -1062,19 +2901,30 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         break 'line;
                     }
 
-                    Some(t) => line.push(t),
+                    Some(t) => {
+                        raw_line.push_str(t.as_str());
+                        line.push(t)
+                    }
                 }
             }
 
             heredoc.push((line, line_start_pos));
+            raw_lines.push(raw_line);
         }
 
         self.iter
             .buffer_tokens_to_yield_first(saved_tokens, saved_pos);
+        // The redirect (and its command) ends at the delimiter, not the body
+        self.iter.set_last_end(saved_pos);
 
         let body = if quoted {
-            let body = heredoc.into_iter().flat_map(|(t, _)| t).collect::<Vec<_>>();
-            Single(Simple(SimpleWordKind::Literal(concat_tokens(&body))))
+            // Each line is checked from its own position, since any leading
+            // tabs stripped from it are no longer part of the body
+            let mut body = String::new();
+            for (line, line_pos) in heredoc {
+                body.push_str(&self.check_nul(concat_tokens(&line), line_pos)?);
+            }
+            Single(Simple(SimpleWordKind::Literal(body)))
         } else {
             let mut tok_iter = TokenIter::with_position(empty_iter(), heredoc_start_pos);
             while let Some((line, pos)) = heredoc.pop() {
@@ -1082,9 +2932,16 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
 
             let mut tok_backup = TokenIterWrapper::Buffered(tok_iter);
+            let depth = self.delims.len();
+            let delims_floor = mem::replace(&mut self.delims_floor, depth);
             mem::swap(&mut self.iter, &mut tok_backup);
-            let mut body = self.word_interpolated_raw(None, heredoc_start_pos)?;
+            let body = self.word_interpolated_raw(None, heredoc_start_pos);
             let _ = mem::replace(&mut self.iter, tok_backup);
+            self.delims_floor = delims_floor;
+            if body.is_err() {
+                self.stream_delims = Some(depth);
+            }
+            let mut body = body?;
 
             if body.len() > 1 {
                 Concat(body.into_iter().map(Simple).collect())
@@ -1096,10 +2953,22 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         };
 
-        let word = self.builder.word(body)?;
+        let body_span = ast::Span {
+            start: heredoc_start_pos,
+            end: heredoc_end_pos,
+        };
+        let info = ast::HeredocInfo {
+            delimiter: delim,
+            quoted,
+            strip_tabs,
+            lines: raw_lines,
+        };
+
+        let word = self.builder.word(body, body_span)?;
+        let span = self.span_from(start);
         Ok(self
             .builder
-            .redirect(builder::RedirectKind::Heredoc(src_fd, word))?)
+            .redirect(builder::RedirectKind::Heredoc(src_fd, word, info), span)?)
     }
 
     /// Parses a whitespace delimited chunk of text, honoring space quoting rules,
@@ -1120,13 +2989,76 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
     /// Identical to `Parser::word()` but preserves trailing whitespace after the word.
     pub fn word_preserve_trailing_whitespace(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        let w = match self.word_preserve_trailing_whitespace_raw()? {
+            Some(w) => {
+                let w = self.tilde_prefixes(w, false);
+                Some(self.builder.word(w, self.span_from(start))?)
+            }
+            None => None,
+        };
+        Ok(w)
+    }
+
+    /// Parses a word like `Parser::word`, additionally splitting out any brace
+    /// expansions, which bash only performs on some of the words of a command.
+    fn word_with_braces(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
         let w = match self.word_preserve_trailing_whitespace_raw()? {
-            Some(w) => Some(self.builder.word(w)?),
+            Some(w) => {
+                let w = self.tilde_prefixes(self.brace_expansions(w), false);
+                Some(self.builder.word(w, self.span_from(start))?)
+            }
             None => None,
         };
+        self.skip_whitespace();
         Ok(w)
     }
 
+    /// Splits any brace expansions out of the parts of an unquoted word,
+    /// if enabled (see `BraceExpansion`).
+    fn brace_expansions(&self, word: ComplexWordKind<B::Command>) -> ComplexWordKind<B::Command> {
+        if self.options.brace_expansion != BraceExpansion::Structured
+            || self.options.dialect != Dialect::Bash
+        {
+            return word;
+        }
+
+        let parts = match word {
+            Single(w) => vec![w],
+            Concat(ws) => ws,
+        };
+
+        let mut parts = brace_expansions(parts);
+        if parts.len() == 1 {
+            Single(parts.pop().unwrap())
+        } else {
+            Concat(parts)
+        }
+    }
+
+    /// Marks any tilde prefixes of an unquoted word (or the value of an
+    /// assignment), if enabled (see `TildeExpansion`).
+    fn tilde_prefixes(
+        &self,
+        word: ComplexWordKind<B::Command>,
+        assignment: bool,
+    ) -> ComplexWordKind<B::Command> {
+        if self.options.tilde_expansion != TildeExpansion::Prefixes {
+            return word;
+        }
+
+        match word {
+            Single(w) => {
+                let mut parts = tilde_prefixes(vec![w], assignment);
+                Single(parts.pop().unwrap())
+            }
+            Concat(ws) => Concat(tilde_prefixes(ws, assignment)),
+        }
+    }
+
     /// Identical to `Parser::word_preserve_trailing_whitespace()` but does
     /// not pass the result to the AST builder.
     fn word_preserve_trailing_whitespace_raw(
@@ -1155,6 +3087,12 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 break;
             }
 
+            let maybe_process = matches!(self.iter.peek(), Some(&Less) | Some(&Great));
+            if maybe_process && self.peek_process_substitution() {
+                words.push(Simple(self.process_substitution_raw()?));
+                continue;
+            }
+
             match self.iter.peek() {
                 Some(&CurlyOpen) | Some(&CurlyClose) | Some(&SquareOpen) | Some(&SquareClose)
                 | Some(&SingleQuote) | Some(&DoubleQuote) | Some(&Pound) | Some(&Star)
@@ -1164,7 +3102,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 | Some(&Literal(_)) => {}
 
                 Some(&Backtick) => {
-                    words.push(Simple(self.backticked_raw()?));
+                    words.push(Simple(self.backticked_raw(false)?));
                     continue;
                 }
 
@@ -1200,7 +3138,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 | tok @ CurlyOpen
                 | tok @ CurlyClose => Simple(SimpleWordKind::Literal(tok.to_string())),
 
-                Name(s) | Literal(s) => Simple(SimpleWordKind::Literal(s)),
+                Name(s) => Simple(SimpleWordKind::Literal(s)),
+                Literal(s) => Simple(SimpleWordKind::Literal(self.check_nul(s, start_pos)?)),
 
                 Star => Simple(SimpleWordKind::Star),
                 Question => Simple(SimpleWordKind::Question),
@@ -1209,20 +3148,26 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 SquareClose => Simple(SimpleWordKind::SquareClose),
                 Colon => Simple(SimpleWordKind::Colon),
 
-                Backslash => match self.iter.next() {
-                    // Escaped newlines become whitespace and a delimiter.
-                    // Alternatively, can't escape EOF, just ignore the slash
-                    Some(Newline) | None => break,
-                    Some(t) => Simple(SimpleWordKind::Escaped(t.to_string())),
-                },
+                Backslash => {
+                    let escaped_pos = self.iter.pos();
+                    match self.iter.next() {
+                        // Escaped newlines become whitespace and a delimiter.
+                        // Alternatively, can't escape EOF, just ignore the slash
+                        Some(Newline) | None => break,
+                        Some(t) => Simple(SimpleWordKind::Escaped(
+                            self.check_nul(t.to_string(), escaped_pos)?,
+                        )),
+                    }
+                }
 
                 SingleQuote => {
+                    let body_pos = self.iter.pos();
                     let mut buf = String::new();
                     for t in self.iter.single_quoted(start_pos) {
                         buf.push_str(t.map_err(|e| ParseError::Unmatched(e.0, e.1))?.as_str())
                     }
 
-                    SingleQuoted(buf)
+                    SingleQuoted(self.check_nul(buf, body_pos)?)
                 }
 
                 DoubleQuote => DoubleQuoted(
@@ -1305,13 +3250,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 }
 
                 Some(&Backtick) => {
-                    store!(self.backticked_raw()?);
+                    store!(self.backticked_raw(delim_close == Some(DoubleQuote))?);
                     continue;
                 }
 
                 _ => {}
             }
 
+            let tok_pos = self.iter.pos();
             match self.iter.next() {
                 // Backslashes only escape a few tokens when double-quoted-type words
                 Some(Backslash) => {
@@ -1333,8 +3279,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 Some(Dollar) => unreachable!(),   // Sanity
                 Some(Backtick) => unreachable!(), // Sanity
 
+                Some(Literal(s)) => buf.push_str(&self.check_nul(s, tok_pos)?),
                 Some(t) => buf.push_str(t.as_str()),
                 None => match delim_open {
+                    Some(delim) if self.close_at_eof(delim.clone(), start_pos) => break,
                     Some(delim) => return Err(ParseError::Unmatched(delim, start_pos)),
                     None => break,
                 },
@@ -1354,13 +3302,19 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// before the contents inside the original backticks are recursively parsed
     /// as a command.
     pub fn backticked_command_substitution(&mut self) -> ParseResult<B::Word, B::Error> {
-        let word = self.backticked_raw()?;
-        Ok(self.builder.word(Single(Simple(word)))?)
+        let start = self.iter.pos();
+        let word = self.backticked_raw(false)?;
+        let span = self.span_from(start);
+        Ok(self.builder.word(Single(Simple(word)), span)?)
     }
 
     /// Identical to `Parser::backticked_command_substitution`, except but does not pass the
-    /// result to the AST builder.
-    fn backticked_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    /// result to the AST builder. If the substitution is `double_quoted`, any backslashes
+    /// that are immediately followed by " are removed as well.
+    fn backticked_raw(
+        &mut self,
+        double_quoted: bool,
+    ) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
         let backtick_pos = self.iter.pos();
         eat!(self, { Backtick => {} });
 
@@ -1369,18 +3323,39 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // returns a Result<Token, UnmatchedError>, so we can't temporarily substitute it
         // for our regular iterator (without forcing us to check the the value of each
         // `peek` or `next` operation we make).
-        let tok_iter = self
+        let until_eof = self.options.incomplete_words == IncompleteWords::Partial;
+        let (tok_iter, unterminated) = self
             .iter
-            .token_iter_from_backticked_with_removed_backslashes(backtick_pos)
+            .token_iter_from_backticked_with_removed_backslashes(
+                backtick_pos,
+                double_quoted,
+                until_eof,
+            )
             .map_err(|e| ParseError::Unmatched(e.0, e.1))?;
 
         let mut tok_backup = TokenIterWrapper::Buffered(tok_iter);
 
+        // The end of the buffered tokens is the closing backtick
+        let depth = self.delims.len();
+        self.open_delim("`", backtick_pos, "`");
+        let delims_floor = mem::replace(&mut self.delims_floor, self.delims.len());
         mem::swap(&mut self.iter, &mut tok_backup);
-        let cmd_subst = self.command_group_internal(CommandGroupDelimiters::default());
+        let cmd_subst = self.in_context(builder::ContextKind::CommandSubstitution, |slf| {
+            slf.command_group_internal(CommandGroupDelimiters::default())
+        });
         let _ = mem::replace(&mut self.iter, tok_backup);
+        self.delims_floor = delims_floor;
+        if cmd_subst.is_err() {
+            self.stream_delims = Some(depth);
+        }
+        let cmd_subst = cmd_subst?;
+        self.delims.pop();
+
+        if unterminated && !self.close_at_eof(Backtick, backtick_pos) {
+            return Err(ParseError::Unmatched(Backtick, backtick_pos));
+        }
 
-        Ok(SimpleWordKind::CommandSubst(cmd_subst?))
+        Ok(SimpleWordKind::CommandSubst(cmd_subst))
     }
 
     /// Parses a parameters such as `$$`, `$1`, `$foo`, etc, or
@@ -1391,8 +3366,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// returns an `Word`, which will capture both cases where a literal or
     /// parameter is parsed.
     pub fn parameter(&mut self) -> ParseResult<B::Word, B::Error> {
+        let start = self.iter.pos();
         let param = self.parameter_raw()?;
-        Ok(self.builder.word(Single(Simple(param)))?)
+        let span = self.span_from(start);
+        Ok(self.builder.word(Single(Simple(param)), span)?)
     }
 
     /// Identical to `Parser::parameter()` but does not pass the result to the AST builder.
@@ -1409,7 +3386,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     Ok(SimpleWordKind::Param(self.parameter_inner()?))
                 }
 
-                Some(&ParenOpen) | Some(&CurlyOpen) => self.parameter_substitution_raw(),
+                Some(&ParenOpen) | Some(&CurlyOpen) => self.parameter_substitution_raw(start_pos),
 
                 _ => Ok(SimpleWordKind::Literal(Dollar.to_string())),
             },
@@ -1519,10 +3496,12 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         }
 
-        eat_maybe!(self, {
-            CurlyClose => {};
-            _ => { return Err(ParseError::Unmatched(CurlyOpen, curly_open_pos)); }
-        });
+        if !self.close_at_eof(CurlyOpen, curly_open_pos) {
+            eat_maybe!(self, {
+                CurlyClose => {};
+                _ => { return Err(ParseError::Unmatched(CurlyOpen, curly_open_pos)); }
+            });
+        }
 
         if words.is_empty() {
             Ok(None)
@@ -1567,6 +3546,9 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             Some(CurlyClose) => return Ok(SimpleWordKind::Param(param)),
 
             Some(t) => return Err(ParseError::BadSubst(t, op_pos)),
+            None if self.close_at_eof(CurlyOpen, curly_open_pos) => {
+                return Ok(SimpleWordKind::Param(param))
+            }
             None => return Err(ParseError::Unmatched(CurlyOpen, curly_open_pos)),
         };
 
@@ -1591,150 +3573,177 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         Ok(SimpleWordKind::Subst(Box::new(ret)))
     }
 
-    /// Parses a parameter substitution in the form of `${...}`, `$(...)`, or `$((...))`.
-    /// Nothing is passed to the builder.
-    fn parameter_substitution_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    /// Parses a parameter substitution in the form of `${...}`, `$(...)`, or `$((...))`,
+    /// where `dollar_pos` is the position of the leading `$`.
+    /// Only the expression of an arithmetic substitution, and substitutions within
+    /// curly braces, are passed to the builder (before the word containing them).
+    fn parameter_substitution_raw(
+        &mut self,
+        dollar_pos: SourcePos,
+    ) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
         use crate::ast::builder::ParameterSubstitutionKind::*;
-        use crate::ast::Parameter;
 
         let start_pos = self.iter.pos();
         match self.iter.peek() {
             Some(&ParenOpen) => {
-                let is_arith = {
-                    let mut peeked = self.iter.multipeek();
-                    peeked.peek_next(); // Skip first ParenOpen
-                    Some(&ParenOpen) == peeked.peek_next()
-                };
+                let is_arith = self.peek_arith_subst();
 
-                let subst = if is_arith {
+                let subst = if !is_arith && self.peek_read_file() {
                     eat!(self, { ParenOpen => {} });
-                    eat!(self, { ParenOpen => {} });
-
-                    // If we hit a paren right off the bat either the body is empty
-                    // or there is a stray paren which will result in an error either
-                    // when we look for the closing parens or sometime after.
                     self.skip_whitespace();
-                    let subst = if let Some(&ParenClose) = self.iter.peek() {
-                        None
-                    } else {
-                        Some(self.arithmetic_substitution()?)
+                    eat!(self, { Less => {} });
+
+                    let path = match self.word_preserve_trailing_whitespace_raw()? {
+                        Some(path) => path,
+                        None => unreachable!("peek_read_file ensures a word follows"),
                     };
 
-                    // Some shells allow the closing parens to have whitespace in between
-                    self.skip_whitespace();
-                    eat!(self, { ParenClose => {} });
-                    self.skip_whitespace();
+                    self.linebreak();
                     eat!(self, { ParenClose => {} });
 
-                    Arith(subst)
+                    ReadFile(path)
+                } else if is_arith {
+                    let subst = self.arithmetic_parens(start_pos)?;
+                    let span = self.span_from(dollar_pos);
+                    Arith(self.builder.arithmetic(subst, span)?)
                 } else {
-                    Command(self.subshell_internal(true)?)
+                    let kind = builder::ContextKind::CommandSubstitution;
+                    Command(self.in_context(kind, Parser::command_substitution_body)?)
                 };
 
                 Ok(SimpleWordKind::Subst(Box::new(subst)))
             }
 
-            Some(&CurlyOpen) => {
-                let curly_open_pos = start_pos;
-                self.iter.next();
+            Some(&CurlyOpen) => match self.braced_parameter_raw(start_pos)? {
+                SimpleWordKind::Subst(subst) => {
+                    let span = self.span_from(dollar_pos);
+                    let subst = self.builder.parameter_substitution(*subst, span)?;
+                    Ok(SimpleWordKind::Subst(Box::new(subst)))
+                }
+                word => Ok(word),
+            },
 
-                let param = self.parameter_inner()?;
-                let subst = match self.iter.peek() {
-                    Some(&Percent) => {
-                        self.iter.next();
-                        eat_maybe!(self, {
-                            Percent => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                RemoveLargestSuffix(param, word?)
-                            };
-                            _ => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                RemoveSmallestSuffix(param, word?)
-                            }
-                        })
-                    }
+            _ => Err(self.make_unexpected_err()),
+        }
+    }
 
-                    Some(&Slash) => {
-                        self.iter.next();
-                        eat_maybe!(self, {
-                            Slash => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                ReplaceStringAll(param, word?)
-                            };
-                            _ => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                ReplaceString(param, word?)
-                            }
-                        })
-                    }
+    /// Parses a parameter (or a substitution of it) within curly braces, e.g. `${x}`
+    /// or `${x:-word}`, where `curly_open_pos` is the position of the `{`.
+    fn braced_parameter_raw(
+        &mut self,
+        curly_open_pos: SourcePos,
+    ) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+        use crate::ast::builder::ParameterSubstitutionKind::*;
+        use crate::ast::Parameter;
 
-                    Some(&Pound) => {
-                        self.iter.next();
-                        eat_maybe!(self, {
-                            Pound => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                RemoveLargestPrefix(param, word?)
-                            };
-                            _ => {
-                                match self.parameter_substitution_word_raw(curly_open_pos)? {
-                                    // Handle ${##} case
-                                    None if Parameter::Pound == param => Len(Parameter::Pound),
-                                    w => RemoveSmallestPrefix(param, w),
-                                }
-                            }
-                        })
+        self.iter.next();
+
+        if self.close_at_eof(CurlyOpen, curly_open_pos) {
+            return Ok(SimpleWordKind::Param(Parameter::Var(String::new())));
+        }
+
+        let param = self.delimited("{", curly_open_pos, "}", Parser::parameter_inner)?;
+        if self.close_at_eof(CurlyOpen, curly_open_pos) {
+            return Ok(SimpleWordKind::Param(param));
+        }
+
+        let subst = match self.iter.peek() {
+            Some(&Percent) => {
+                self.iter.next();
+                eat_maybe!(self, {
+                    Percent => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        RemoveLargestSuffix(param, word?)
+                    };
+                    _ => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        RemoveSmallestSuffix(param, word?)
                     }
+                })
+            }
 
-                    Some(&Caret) => {
-                        self.iter.next();
-                        eat_maybe!(self, {
-                            Caret => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                Uppercase(true, param, word?)
-                            };
-                            _ => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                Uppercase(false, param, word?)
-                            }
-                        })
+            Some(&Slash) => {
+                self.iter.next();
+                eat_maybe!(self, {
+                    Slash => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        ReplaceStringAll(param, word?)
+                    };
+                    _ => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        ReplaceString(param, word?)
                     }
+                })
+            }
 
-                    Some(&Comma) => {
-                        self.iter.next();
-                        eat_maybe!(self, {
-                            Comma => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                Lowercase(true, param, word?)
-                            };
-                            _ => {
-                                let word = self.parameter_substitution_word_raw(curly_open_pos);
-                                Lowercase(false, param, word?)
-                            }
-                        })
+            Some(&Pound) => {
+                self.iter.next();
+                eat_maybe!(self, {
+                    Pound => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        RemoveLargestPrefix(param, word?)
+                    };
+                    _ => {
+                        match self.parameter_substitution_word_raw(curly_open_pos)? {
+                            // Handle ${##} case
+                            None if Parameter::Pound == param => Len(Parameter::Pound),
+                            w => RemoveSmallestPrefix(param, w),
+                        }
                     }
+                })
+            }
 
-                    // In this case the found # is the parameter itself
-                    Some(&Colon) | Some(&Dash) | Some(&Equals) | Some(&Question) | Some(&Plus)
-                    | Some(&CurlyClose)
-                        if Parameter::Pound == param =>
-                    {
-                        return self.parameter_substitution_body_raw(param, curly_open_pos)
+            Some(&Caret) => {
+                self.iter.next();
+                eat_maybe!(self, {
+                    Caret => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        Uppercase(true, param, word?)
+                    };
+                    _ => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        Uppercase(false, param, word?)
                     }
+                })
+            }
 
-                    // Otherwise we must have ${#param}
-                    _ if Parameter::Pound == param => {
-                        let param = self.parameter_inner()?;
-                        eat!(self, { CurlyClose => { Len(param) } })
+            Some(&Comma) => {
+                self.iter.next();
+                eat_maybe!(self, {
+                    Comma => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        Lowercase(true, param, word?)
+                    };
+                    _ => {
+                        let word = self.parameter_substitution_word_raw(curly_open_pos);
+                        Lowercase(false, param, word?)
                     }
+                })
+            }
 
-                    _ => return self.parameter_substitution_body_raw(param, curly_open_pos),
-                };
+            // In this case the found # is the parameter itself
+            Some(&Colon) | Some(&Dash) | Some(&Equals) | Some(&Question) | Some(&Plus)
+            | Some(&CurlyClose)
+                if Parameter::Pound == param =>
+            {
+                return self.parameter_substitution_body_raw(param, curly_open_pos)
+            }
 
-                Ok(SimpleWordKind::Subst(Box::new(subst)))
+            // Otherwise we must have ${#param}
+            _ if Parameter::Pound == param => {
+                let param =
+                    self.delimited("{", curly_open_pos, "}", Parser::parameter_inner)?;
+                if self.close_at_eof(CurlyOpen, curly_open_pos) {
+                    Len(param)
+                } else {
+                    eat!(self, { CurlyClose => { Len(param) } })
+                }
             }
 
-            _ => Err(self.make_unexpected_err()),
-        }
+            _ => return self.parameter_substitution_body_raw(param, curly_open_pos),
+        };
+
+        Ok(SimpleWordKind::Subst(Box::new(subst)))
     }
 
     /// Parses a valid parameter that can appear inside a set of curly braces.
@@ -1758,7 +3767,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             },
 
             Some(t) => return Err(ParseError::BadSubst(t, start_pos)),
-            None => return Err(ParseError::UnexpectedEOF),
+            None => return Err(self.eof_err()),
         };
 
         Ok(param)
@@ -1769,15 +3778,23 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// quoted or concatenated.
     pub fn do_group(&mut self) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[DO])
-            .map_err(|_| self.make_unexpected_err())?;
-        let result = self.command_group(CommandGroupDelimiters {
-            reserved_words: &[DONE],
-            ..Default::default()
-        })?;
-        self.reserved_word(&[DONE])
-            .map_err(|()| ParseError::IncompleteCmd(DO, start_pos, DONE, self.iter.pos()))?;
-        Ok(result)
+        self.keyword(&[Do])
+            .map_err(|_| self.make_expected_err(vec![Expected::Keyword(DO)]))?;
+        self.delimited(DO, start_pos, DONE, |slf| {
+            let result = slf.command_group(CommandGroupDelimiters {
+                reserved_words: &[DONE],
+                ..Default::default()
+            })?;
+            if slf.keyword(&[Done]).is_err() && !slf.close_cmd_at_eof(DO, start_pos, DONE) {
+                return Err(ParseError::IncompleteCmd(
+                    DO,
+                    start_pos,
+                    DONE,
+                    slf.iter.pos(),
+                ));
+            }
+            Ok(result)
+        })
     }
 
     /// Parses any number of sequential commands between balanced `{` and `}`
@@ -1787,45 +3804,133 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // even though it is represented as its own token
         let start_pos = self.iter.pos();
         self.reserved_token(&[CurlyOpen])?;
-        let cmds = self.command_group(CommandGroupDelimiters {
-            reserved_tokens: &[CurlyClose],
-            ..Default::default()
-        })?;
-        self.reserved_token(&[CurlyClose])
-            .map_err(|_| ParseError::Unmatched(CurlyOpen, start_pos))?;
-        Ok(cmds)
+        self.delimited("{", start_pos, "}", |slf| {
+            let cmds = slf.in_context(builder::ContextKind::BraceGroup, |slf| {
+                slf.command_group(CommandGroupDelimiters {
+                    reserved_tokens: &[CurlyClose],
+                    ..Default::default()
+                })
+            })?;
+            if slf.reserved_token(&[CurlyClose]).is_err() && !slf.close_at_eof(CurlyOpen, start_pos)
+            {
+                return Err(ParseError::Unmatched(CurlyOpen, start_pos));
+            }
+            Ok(cmds)
+        })
+    }
+
+    /// Parses any number of sequential commands between balanced `(` and `)`.
+    ///
+    /// It is considered an error if no commands are present inside the subshell.
+    pub fn subshell(&mut self) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
+        self.subshell_internal(false)
     }
 
-    /// Parses any number of sequential commands between balanced `(` and `)`.
-    ///
-    /// It is considered an error if no commands are present inside the subshell.
-    pub fn subshell(&mut self) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
-        self.subshell_internal(false)
+    /// Like `Parser::subshell` but allows the caller to specify
+    /// if an empty body constitutes an error or not.
+    fn subshell_internal(
+        &mut self,
+        empty_body_ok: bool,
+    ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
+        let start_pos = self.iter.pos();
+        eat!(self, { ParenOpen => {} });
+
+        // An empty body (e.g. the `()` of a function declaration) has no context to enter
+        if empty_body_ok {
+            self.skip_whitespace();
+            if Some(&ParenClose) == self.iter.peek() {
+                self.iter.next();
+                return Ok(builder::CommandGroup {
+                    commands: Vec::new(),
+                    trailing_comments: Vec::new(),
+                });
+            }
+        }
+
+        // Parens are always special tokens
+        self.delimited("(", start_pos, ")", |slf| {
+            let body = slf.in_context(builder::ContextKind::Subshell, |slf| {
+                slf.command_group_internal(CommandGroupDelimiters {
+                    exact_tokens: &[ParenClose],
+                    ..Default::default()
+                })
+            })?;
+
+            if !body.commands.is_empty() && slf.close_at_eof(ParenOpen, start_pos) {
+                return Ok(body);
+            }
+
+            match slf.iter.peek() {
+                Some(&ParenClose) if empty_body_ok || !body.commands.is_empty() => {
+                    slf.iter.next();
+                    Ok(body)
+                }
+                Some(_) => Err(slf.make_expected_err(vec![Expected::Token(ParenClose)])),
+                None => Err(ParseError::Unmatched(ParenOpen, start_pos)),
+            }
+        })
     }
 
-    /// Like `Parser::subshell` but allows the caller to specify
-    /// if an empty body constitutes an error or not.
-    fn subshell_internal(
+    /// Parses the body of a command substitution between balanced `(` and `)`,
+    /// which may be empty.
+    fn command_substitution_body(
         &mut self,
-        empty_body_ok: bool,
     ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         let start_pos = self.iter.pos();
         eat!(self, { ParenOpen => {} });
 
-        // Parens are always special tokens
-        let body = self.command_group_internal(CommandGroupDelimiters {
-            exact_tokens: &[ParenClose],
-            ..Default::default()
-        })?;
+        self.delimited("(", start_pos, ")", |slf| {
+            let body = slf.command_group_internal(CommandGroupDelimiters {
+                exact_tokens: &[ParenClose],
+                ..Default::default()
+            })?;
 
-        match self.iter.peek() {
-            Some(&ParenClose) if empty_body_ok || !body.commands.is_empty() => {
-                self.iter.next();
-                Ok(body)
+            if slf.close_at_eof(ParenOpen, start_pos) {
+                return Ok(body);
+            }
+
+            match slf.iter.peek() {
+                Some(&ParenClose) => {
+                    slf.iter.next();
+                    Ok(body)
+                }
+                Some(_) => Err(slf.make_expected_err(vec![Expected::Token(ParenClose)])),
+                None => Err(ParseError::Unmatched(ParenOpen, start_pos)),
             }
-            Some(_) => Err(self.make_unexpected_err()),
-            None => Err(ParseError::Unmatched(ParenOpen, start_pos)),
+        })
+    }
+
+    /// Parses a process substitution in the form of `<(cmd)` or `>(cmd)`, a bash extension.
+    /// Nothing is passed to the builder.
+    fn process_substitution_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+        use crate::ast::builder::ParameterSubstitutionKind::*;
+
+        let start_pos = self.iter.pos();
+        let is_read = match self.iter.next() {
+            Some(Less) => true,
+            Some(Great) => false,
+            Some(t) => return Err(ParseError::Unexpected(t, start_pos)),
+            None => return Err(ParseError::UnexpectedEOF),
+        };
+
+        let ext = if is_read {
+            Extension::ProcessRead
+        } else {
+            Extension::ProcessWrite
+        };
+        if !self.options.dialect.supports(ext) {
+            return Err(ParseError::Extension(ext, start_pos));
         }
+
+        let kind = builder::ContextKind::ProcessSubstitution;
+        let body = self.in_context(kind, Parser::command_substitution_body)?;
+        let subst = if is_read {
+            ProcessRead(body)
+        } else {
+            ProcessWrite(body)
+        };
+
+        Ok(SimpleWordKind::Subst(Box::new(subst)))
     }
 
     /// Peeks at the next token (after skipping whitespace) to determine
@@ -1833,21 +3938,156 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     fn next_compound_command_type(&mut self) -> Option<CompoundCmdKeyword> {
         self.skip_whitespace();
         if Some(&ParenOpen) == self.iter.peek() {
-            Some(CompoundCmdKeyword::Subshell)
+            if self.options.dialect.supports(Extension::DoubleParen) && self.peek_arith_subst() {
+                Some(CompoundCmdKeyword::Arithmetic)
+            } else {
+                Some(CompoundCmdKeyword::Subshell)
+            }
         } else if self.peek_reserved_token(&[CurlyOpen]).is_some() {
             Some(CompoundCmdKeyword::Brace)
+        } else if self.options.dialect.supports(Extension::DoubleBracket) && self.peek_cond_open()
+        {
+            Some(CompoundCmdKeyword::Conditional)
         } else {
-            match self.peek_reserved_word(&[FOR, CASE, IF, WHILE, UNTIL]) {
-                Some(FOR) => Some(CompoundCmdKeyword::For),
-                Some(CASE) => Some(CompoundCmdKeyword::Case),
-                Some(IF) => Some(CompoundCmdKeyword::If),
-                Some(WHILE) => Some(CompoundCmdKeyword::While),
-                Some(UNTIL) => Some(CompoundCmdKeyword::Until),
+            match self.peek_keyword(&[For, Case, If, While, Until]) {
+                Some(For) => Some(CompoundCmdKeyword::For),
+                Some(Case) => Some(CompoundCmdKeyword::Case),
+                Some(If) => Some(CompoundCmdKeyword::If),
+                Some(While) => Some(CompoundCmdKeyword::While),
+                Some(Until) => Some(CompoundCmdKeyword::Until),
                 _ => None,
             }
         }
     }
 
+    /// Replaces the upcoming word with the value of the alias it names, if any,
+    /// and returns whether it did so, see `Parser::set_alias_provider`.
+    fn expand_alias(&mut self) -> bool {
+        if self.aliases.is_none() {
+            return false;
+        }
+
+        self.skip_whitespace();
+        let start = self.iter.pos();
+
+        // Only words made up entirely of literal characters can name an alias
+        let (name, len) = {
+            let mut name = String::new();
+            let mut len = 0;
+            let mut peeked = self.iter.multipeek();
+            loop {
+                match peeked.peek_next() {
+                    Some(&Name(ref s)) | Some(&Literal(ref s)) => name.push_str(s),
+                    Some(t) if matches!(*t, Bang | Percent | Comma | Dash | At) => {
+                        name.push_str(t.as_str())
+                    }
+                    Some(t) if t.is_word_delimiter() => break,
+                    Some(_) => return false,
+                    None => break,
+                }
+                len += 1;
+            }
+            (name, len)
+        };
+
+        let reserved = [
+            CASE, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, SELECT, THEN, UNTIL, WHILE,
+        ];
+        if name.is_empty() || reserved.contains(&&*name) {
+            return false;
+        }
+
+        let aliases = self.aliases.as_mut().unwrap();
+        if aliases.is_active(&name, start) {
+            return false;
+        }
+        let value = match aliases.provider.alias(&name) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        for _ in 0..len {
+            self.iter.next();
+        }
+
+        // Parsing resumes after the outermost alias once its value is consumed
+        let end = self.iter.pos();
+        let aliases = self.aliases.as_mut().unwrap();
+        let resume = aliases
+            .active
+            .iter()
+            .map(|alias| alias.resume)
+            .fold(end, |a, b| if b.byte > a.byte { b } else { a });
+
+        if value.ends_with([' ', '\t']) {
+            aliases.check_next = Some(resume);
+        }
+        aliases.active.push(ActiveAlias { name, resume });
+
+        let tokens = Lexer::new(value.chars()).collect();
+        self.iter.buffer_tokens_at(tokens, start);
+        true
+    }
+
+    /// Checks the upcoming word for an alias if it follows the value of an
+    /// alias which ended in a blank, see `Parser::set_alias_provider`.
+    fn expand_alias_after_blank(&mut self) {
+        let resume = match self.aliases {
+            Some(ref aliases) => match aliases.check_next {
+                Some(resume) => resume,
+                None => return,
+            },
+            None => return,
+        };
+
+        // Any words before the resume position are part of the alias' value
+        self.skip_whitespace();
+        if self.iter.pos().byte < resume.byte {
+            return;
+        }
+
+        if let Some(ref mut aliases) = self.aliases {
+            aliases.check_next = None;
+        }
+        self.expand_alias();
+    }
+
+    /// Checks if the upcoming command begins with a construct which is an extension
+    /// of the POSIX grammar (e.g. `[[`, `((`, `function`, or `select`), and if so,
+    /// returns it along with the position where it starts.
+    fn peek_extension(&mut self) -> Option<(Extension, SourcePos)> {
+        self.skip_whitespace();
+        let pos = self.iter.pos();
+
+        let ext = {
+            let mut peeked = self.iter.multipeek();
+            let first = peeked.peek_next().cloned();
+            match first {
+                Some(SquareOpen) if Some(&SquareOpen) == peeked.peek_next() => {
+                    Some(Extension::DoubleBracket)
+                }
+                _ => None,
+            }
+        };
+
+        // Like bash, `((cmd) )` is a subshell nested within another
+        let ext = ext.or_else(|| {
+            if self.peek_arith_subst() {
+                Some(Extension::DoubleParen)
+            } else {
+                None
+            }
+        });
+
+        let ext = ext.or_else(|| match self.peek_keyword(&[Function, Select]) {
+            Some(Function) => Some(Extension::FunctionKeyword),
+            Some(Select) => Some(Extension::Select),
+            _ => None,
+        });
+
+        ext.map(|ext| (ext, pos))
+    }
+
     /// Parses compound commands like `for`, `case`, `if`, `while`, `until`,
     /// brace groups, or subshells, including any redirection lists to be applied to them.
     pub fn compound_command(&mut self) -> ParseResult<B::CompoundCommand, B::Error> {
@@ -1860,49 +4100,431 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
         kw: Option<CompoundCmdKeyword>,
     ) -> ParseResult<B::CompoundCommand, B::Error> {
+        self.skip_whitespace();
+        let start = self.iter.pos();
         let cmd = match kw.or_else(|| self.next_compound_command_type()) {
             Some(CompoundCmdKeyword::If) => {
                 let fragments = self.if_command()?;
-                let io = self.redirect_list()?;
-                self.builder.if_command(fragments, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.if_command(fragments, io, io_span, span)?
             }
 
             Some(CompoundCmdKeyword::While) | Some(CompoundCmdKeyword::Until) => {
                 let (until, guard_body_pair) = self.loop_command()?;
-                let io = self.redirect_list()?;
-                self.builder.loop_command(until, guard_body_pair, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder
+                    .loop_command(until, guard_body_pair, io, io_span, span)?
             }
 
             Some(CompoundCmdKeyword::For) => {
                 let for_fragments = self.for_command()?;
-                let io = self.redirect_list()?;
-                self.builder.for_command(for_fragments, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.for_command(for_fragments, io, io_span, span)?
             }
 
             Some(CompoundCmdKeyword::Case) => {
                 let fragments = self.case_command()?;
-                let io = self.redirect_list()?;
-                self.builder.case_command(fragments, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.case_command(fragments, io, io_span, span)?
             }
 
             Some(CompoundCmdKeyword::Brace) => {
                 let cmds = self.brace_group()?;
-                let io = self.redirect_list()?;
-                self.builder.brace_group(cmds, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.brace_group(cmds, io, io_span, span)?
             }
 
             Some(CompoundCmdKeyword::Subshell) => {
                 let cmds = self.subshell()?;
-                let io = self.redirect_list()?;
-                self.builder.subshell(cmds, io)?
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.subshell(cmds, io, io_span, span)?
+            }
+
+            Some(CompoundCmdKeyword::Conditional) => {
+                let expr = self.conditional_command()?;
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.conditional_command(expr, io, io_span, span)?
+            }
+
+            Some(CompoundCmdKeyword::Arithmetic) => {
+                let expr = self.arithmetic_command()?;
+                let (io, io_span) = self.redirect_list_with_span()?;
+                let span = self.span_from(start);
+                self.builder.arithmetic_command(expr, io, io_span, span)?
             }
 
-            None => return Err(self.make_unexpected_err()),
+            None => return Err(self.make_expected_err(vec![Expected::CompoundCommand])),
         };
 
         Ok(cmd)
     }
 
+    /// Parses a single `[[ ... ]]` conditional command (a bash extension) but
+    /// does not parse any redirections that may follow.
+    ///
+    /// Since it is a compound command (and can have redirections applied to it)
+    /// this method returns the expression within the brackets, without
+    /// constructing an AST node, so that the caller can do so with redirections.
+    ///
+    /// Within the brackets, words are separated by whitespace (or newlines), and
+    /// `&&`, `||`, `!`, and parentheses combine the tests. Note that the right
+    /// hand side of `=~` may contain unquoted parentheses and `|`, as they are
+    /// part of the regular expression.
+    pub fn conditional_command(&mut self) -> ParseResult<ast::Conditional<B::Word>, B::Error> {
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
+        if !self.peek_cond_open() {
+            return Err(self.make_expected_err(vec![Expected::Keyword(COND_OPEN)]));
+        }
+        self.eat_cond_bracket();
+
+        self.open_delim(COND_OPEN, start_pos, COND_CLOSE);
+        let expr = self.cond_or(start_pos)?;
+        match self.peek_cond_words()[0].as_deref() {
+            Some(COND_CLOSE) => {
+                self.eat_cond_bracket();
+                self.delims.pop();
+                Ok(expr)
+            }
+            Some(_) => Err(self.make_expected_err(vec![Expected::Keyword(COND_CLOSE)])),
+            None => Err(ParseError::IncompleteCmd(
+                COND_OPEN,
+                start_pos,
+                COND_CLOSE,
+                self.iter.pos(),
+            )),
+        }
+    }
+
+    /// Parses a single `(( ... ))` arithmetic command (a bash extension) but
+    /// does not parse any redirections that may follow.
+    ///
+    /// Since it is a compound command (and can have redirections applied to it)
+    /// this method returns the expression within the parens (or `None` if it is
+    /// empty), without constructing an AST node, so that the caller can do so
+    /// with redirections.
+    pub fn arithmetic_command(&mut self) -> ParseResult<Option<DefaultArithmetic>, B::Error> {
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
+        self.arithmetic_parens(start_pos)
+    }
+
+    /// Parses an arithmetic expression enclosed by `((` and `))`, e.g. the
+    /// body of an arithmetic substitution or command.
+    fn arithmetic_parens(
+        &mut self,
+        start_pos: SourcePos,
+    ) -> ParseResult<Option<DefaultArithmetic>, B::Error> {
+        eat!(self, { ParenOpen => {} });
+        eat!(self, { ParenOpen => {} });
+
+        self.delimited("((", start_pos, "))", |slf| {
+            // If we hit a paren right off the bat either the body is empty
+            // or there is a stray paren which will result in an error either
+            // when we look for the closing parens or sometime after.
+            slf.skip_whitespace();
+            let expr = if let Some(&ParenClose) = slf.iter.peek() {
+                None
+            } else {
+                Some(slf.arithmetic_substitution()?)
+            };
+
+            // Some shells allow the closing parens to have whitespace in between
+            slf.skip_whitespace();
+            eat!(slf, { ParenClose => {} });
+            slf.skip_whitespace();
+            eat!(slf, { ParenClose => {} });
+            Ok(expr)
+        })
+    }
+
+    /// Checks if the next two tokens form a `[[` which is delimited from
+    /// any word which follows it.
+    fn peek_cond_open(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        if peeked.peek_next() != Some(&SquareOpen) || peeked.peek_next() != Some(&SquareOpen) {
+            return false;
+        }
+
+        match peeked.peek_next() {
+            Some(tok) => tok.is_word_delimiter(),
+            None => true,
+        }
+    }
+
+    /// Consumes a `[[` or `]]` and records it as a keyword.
+    fn eat_cond_bracket(&mut self) {
+        let start = self.iter.pos();
+        let mut word = String::new();
+        for tok in self.iter.by_ref().take(2) {
+            word.push_str(tok.as_str());
+        }
+
+        let span = self.span_from(start);
+        if let Some(ref mut keywords) = self.keywords {
+            keywords.push(Keyword { word, span });
+        }
+    }
+
+    /// Skips any whitespace or newlines within a conditional expression.
+    fn skip_cond_whitespace(&mut self) {
+        while let Some(&Whitespace(_)) | Some(&Newline) = self.iter.peek() {
+            self.iter.next();
+        }
+    }
+
+    /// Peeks at the source text of the next two words (or operators) within a
+    /// conditional expression, without consuming anything. The text of a word
+    /// spans up to the next word delimiter, while a delimiter (e.g. `&&` or `(`)
+    /// is a word on its own.
+    fn peek_cond_words(&mut self) -> [Option<String>; 2] {
+        self.skip_cond_whitespace();
+
+        let mut words = [None, None];
+        let mut peeked = self.iter.multipeek();
+        let mut tok = peeked.peek_next().cloned();
+        for word in &mut words {
+            while let Some(Whitespace(_)) | Some(Newline) = tok {
+                tok = peeked.peek_next().cloned();
+            }
+
+            let mut text = String::new();
+            while let Some(t) = tok.take() {
+                if t.is_word_delimiter() && !text.is_empty() {
+                    tok = Some(t);
+                    break;
+                }
+
+                text.push_str(t.as_str());
+                tok = peeked.peek_next().cloned();
+                if t.is_word_delimiter() {
+                    break;
+                }
+            }
+
+            if text.is_empty() {
+                break;
+            }
+            *word = Some(text);
+        }
+
+        words
+    }
+
+    /// Consumes the tokens of the next word (or operator) peeked by `peek_cond_words`.
+    fn eat_cond_word(&mut self) {
+        self.skip_cond_whitespace();
+        if let Some(tok) = self.iter.next() {
+            if !tok.is_word_delimiter() {
+                while let Some(false) = self.iter.peek().map(Token::is_word_delimiter) {
+                    self.iter.next();
+                }
+            }
+        }
+    }
+
+    /// Returns the error for a conditional expression which ended unexpectedly.
+    fn cond_err(&mut self, start_pos: SourcePos) -> ParseError<B::Error> {
+        if self.iter.peek().is_none() {
+            ParseError::IncompleteCmd(COND_OPEN, start_pos, COND_CLOSE, self.iter.pos())
+        } else {
+            self.make_unexpected_err()
+        }
+    }
+
+    /// Parses a conditional expression whose tests are combined with `||`.
+    fn cond_or(
+        &mut self,
+        start_pos: SourcePos,
+    ) -> ParseResult<ast::Conditional<B::Word>, B::Error> {
+        let mut expr = self.cond_and(start_pos)?;
+        loop {
+            self.skip_cond_whitespace();
+            if Some(&OrIf) != self.iter.peek() {
+                return Ok(expr);
+            }
+
+            self.iter.next();
+            let rhs = self.cond_and(start_pos)?;
+            expr = ast::Conditional::Or(Box::new(expr), Box::new(rhs));
+        }
+    }
+
+    /// Parses a conditional expression whose tests are combined with `&&`.
+    fn cond_and(
+        &mut self,
+        start_pos: SourcePos,
+    ) -> ParseResult<ast::Conditional<B::Word>, B::Error> {
+        let mut expr = self.cond_not(start_pos)?;
+        loop {
+            self.skip_cond_whitespace();
+            if Some(&AndIf) != self.iter.peek() {
+                return Ok(expr);
+            }
+
+            self.iter.next();
+            let rhs = self.cond_not(start_pos)?;
+            expr = ast::Conditional::And(Box::new(expr), Box::new(rhs));
+        }
+    }
+
+    /// Parses a single (possibly negated) test, or a parenthesized expression.
+    fn cond_not(
+        &mut self,
+        start_pos: SourcePos,
+    ) -> ParseResult<ast::Conditional<B::Word>, B::Error> {
+        let [first, second] = self.peek_cond_words();
+        let first = match first {
+            Some(first) => first,
+            None => return Err(self.cond_err(start_pos)),
+        };
+
+        // A unary operator is always followed by its operand, while a lone `!`
+        // is tested as a plain word
+        let has_operand = match second.as_deref() {
+            Some(COND_CLOSE) | Some("&&") | Some("||") | Some(")") | None => false,
+            Some(_) => true,
+        };
+
+        match first.as_str() {
+            "!" if has_operand => {
+                self.eat_cond_word();
+                let expr = self.cond_not(start_pos)?;
+                return Ok(ast::Conditional::Not(Box::new(expr)));
+            }
+
+            "(" => {
+                self.eat_cond_word();
+                let expr = self.cond_or(start_pos)?;
+                self.skip_cond_whitespace();
+                return match self.iter.peek() {
+                    Some(&ParenClose) => {
+                        self.iter.next();
+                        Ok(expr)
+                    }
+                    _ => Err(self.cond_err(start_pos)),
+                };
+            }
+
+            COND_CLOSE => return Err(self.make_unexpected_err()),
+            _ => {}
+        }
+
+        let unary = {
+            let mut chars = first.chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some('-'), Some(op), None) if ast::UNARY_TESTS.contains(op) => Some(op),
+                _ => None,
+            }
+        };
+
+        if let Some(op) = unary {
+            self.eat_cond_word();
+            let word = self.cond_word(start_pos)?;
+            return Ok(ast::Conditional::Unary(op, word));
+        }
+
+        let lhs = self.cond_word(start_pos)?;
+        let op = match self.peek_cond_words()[0] {
+            Some(ref op) => ast::BinaryTest::from_op(op),
+            None => None,
+        };
+
+        let op = match op {
+            Some(op) => op,
+            None => return Ok(ast::Conditional::Word(lhs)),
+        };
+
+        self.eat_cond_word();
+        let rhs = if op == ast::BinaryTest::RegexMatch {
+            self.cond_regex(start_pos)?
+        } else {
+            self.cond_word(start_pos)?
+        };
+
+        Ok(ast::Conditional::Binary(lhs, op, rhs))
+    }
+
+    /// Parses a single word within a conditional expression.
+    fn cond_word(&mut self, start_pos: SourcePos) -> ParseResult<B::Word, B::Error> {
+        if self.peek_cond_words()[0].as_deref() == Some(COND_CLOSE) {
+            return Err(self.make_expected_err(vec![Expected::Word]));
+        }
+
+        let start = self.iter.pos();
+        match self.word_preserve_trailing_whitespace_raw()? {
+            Some(w) => {
+                let w = self.tilde_prefixes(w, false);
+                Ok(self.builder.word(w, self.span_from(start))?)
+            }
+            None => Err(self.cond_err(start_pos)),
+        }
+    }
+
+    /// Parses the regular expression on the right hand side of `=~`, which may
+    /// contain unquoted `|` and (balanced) parentheses, along with any
+    /// whitespace which appears within them.
+    fn cond_regex(&mut self, start_pos: SourcePos) -> ParseResult<B::Word, B::Error> {
+        if self.peek_cond_words()[0].as_deref() == Some(COND_CLOSE) {
+            return Err(self.make_expected_err(vec![Expected::Word]));
+        }
+
+        let start = self.iter.pos();
+
+        let mut words = Vec::new();
+        let mut depth = 0usize;
+        loop {
+            let literal = match self.iter.peek() {
+                Some(&ParenOpen) => {
+                    depth += 1;
+                    true
+                }
+                Some(&ParenClose) if depth > 0 => {
+                    depth -= 1;
+                    true
+                }
+                Some(&Pipe) => true,
+                Some(&Whitespace(_)) => depth > 0,
+                _ => false,
+            };
+
+            if literal {
+                let tok = self.iter.next().unwrap();
+                words.push(Simple(SimpleWordKind::Literal(tok.to_string())));
+                continue;
+            }
+
+            if let Some(&Whitespace(_)) = self.iter.peek() {
+                break;
+            }
+
+            match self.word_preserve_trailing_whitespace_raw()? {
+                Some(Single(w)) => words.push(w),
+                Some(Concat(ws)) => words.extend(ws),
+                None => break,
+            }
+        }
+
+        if depth > 0 {
+            return Err(self.cond_err(start_pos));
+        }
+
+        let word = match words.len() {
+            0 => return Err(self.cond_err(start_pos)),
+            1 => Single(words.pop().unwrap()),
+            _ => Concat(words),
+        };
+
+        Ok(self.builder.word(word, self.span_from(start))?)
+    }
+
     /// Parses loop commands like `while` and `until` but does not parse any
     /// redirections that may follow.
     ///
@@ -1913,33 +4535,42 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
     ) -> ParseResult<(builder::LoopKind, builder::GuardBodyPairGroup<B::Command>), B::Error> {
         let start_pos = self.iter.pos();
-        let kind = match self
-            .reserved_word(&[WHILE, UNTIL])
-            .map_err(|_| self.make_unexpected_err())?
-        {
+        let loop_kw = self.keyword(&[While, Until]).map_err(|_| {
+            self.make_expected_err(vec![Expected::Keyword(WHILE), Expected::Keyword(UNTIL)])
+        })?;
+        let kind = match loop_kw {
             WHILE => builder::LoopKind::While,
             UNTIL => builder::LoopKind::Until,
             _ => unreachable!(),
         };
-        let guard = self.command_group(CommandGroupDelimiters {
-            reserved_words: &[DO],
-            ..Default::default()
+        let (guard, closed) = self.delimited(loop_kw, start_pos, DO, |slf| {
+            let guard = slf.command_group(CommandGroupDelimiters {
+                reserved_words: &[DO],
+                ..Default::default()
+            })?;
+            match slf.peek_keyword(&[Do]) {
+                Some(_) => Ok((guard, false)),
+                None if slf.close_cmd_at_eof(loop_kw, start_pos, DO) => Ok((guard, true)),
+                None => Err(ParseError::IncompleteCmd(
+                    loop_kw,
+                    start_pos,
+                    DO,
+                    slf.iter.pos(),
+                )),
+            }
         })?;
-        match self.peek_reserved_word(&[DO]) {
-            Some(_) => Ok((
-                kind,
-                builder::GuardBodyPairGroup {
-                    guard,
-                    body: self.do_group()?,
-                },
-            )),
-            None => Err(ParseError::IncompleteCmd(
-                WHILE,
-                start_pos,
-                DO,
-                self.iter.pos(),
-            )),
-        }
+
+        // The input may end before the body, see `IncompleteWords::Partial`
+        let body = if closed {
+            builder::CommandGroup {
+                commands: Vec::new(),
+                trailing_comments: Vec::new(),
+            }
+        } else {
+            self.do_group()?
+        };
+
+        Ok((kind, builder::GuardBodyPairGroup { guard, body }))
     }
 
     /// Parses a single `if` command but does not parse any redirections that may follow.
@@ -1949,8 +4580,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// AST node, it so that the caller can do so with redirections.
     pub fn if_command(&mut self) -> ParseResult<builder::IfFragments<B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[IF])
-            .map_err(|_| self.make_unexpected_err())?;
+        self.keyword(&[If])
+            .map_err(|_| self.make_expected_err(vec![Expected::Keyword(IF)]))?;
 
         macro_rules! missing_fi {
             () => {
@@ -1966,34 +4597,59 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
         let mut conditionals = Vec::new();
         loop {
+            self.open_delim(IF, start_pos, THEN);
             let guard = self.command_group(CommandGroupDelimiters {
                 reserved_words: &[THEN],
                 ..Default::default()
             })?;
-            self.reserved_word(&[THEN]).map_err(missing_then!())?;
 
+            // The input may end before the body, see `IncompleteWords::Partial`
+            if self.keyword(&[Then]).is_err() {
+                if !self.close_cmd_at_eof(IF, start_pos, THEN) {
+                    return Err(()).map_err(missing_then!());
+                }
+
+                self.delims.pop();
+                let body = builder::CommandGroup {
+                    commands: Vec::new(),
+                    trailing_comments: Vec::new(),
+                };
+                conditionals.push(builder::GuardBodyPairGroup { guard, body });
+                return Ok(builder::IfFragments {
+                    conditionals,
+                    else_branch: None,
+                });
+            }
+            self.delims.pop();
+
+            self.open_delim(IF, start_pos, FI);
             let body = self.command_group(CommandGroupDelimiters {
                 reserved_words: &[ELIF, ELSE, FI],
                 ..Default::default()
             })?;
             conditionals.push(builder::GuardBodyPairGroup { guard, body });
 
-            let els = match self
-                .reserved_word(&[ELIF, ELSE, FI])
-                .map_err(missing_fi!())?
-            {
-                ELIF => continue,
-                ELSE => {
+            let els = match self.reserved_word(&[ELIF, ELSE, FI]) {
+                Ok(ELIF) => {
+                    self.delims.pop();
+                    continue;
+                }
+                Ok(ELSE) => {
                     let els = self.command_group(CommandGroupDelimiters {
                         reserved_words: &[FI],
                         ..Default::default()
                     })?;
-                    self.reserved_word(&[FI]).map_err(missing_fi!())?;
+                    if self.keyword(&[Fi]).is_err() && !self.close_cmd_at_eof(IF, start_pos, FI) {
+                        return Err(()).map_err(missing_fi!());
+                    }
                     Some(els)
                 }
-                FI => None,
-                _ => unreachable!(),
+                Ok(FI) => None,
+                Ok(_) => unreachable!(),
+                Err(()) if self.close_cmd_at_eof(IF, start_pos, FI) => None,
+                Err(()) => return Err(()).map_err(missing_fi!()),
             };
+            self.delims.pop();
 
             return Ok(builder::IfFragments {
                 conditionals,
@@ -2011,14 +4667,15 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
     ) -> ParseResult<builder::ForFragments<B::Word, B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[FOR])
-            .map_err(|_| self.make_unexpected_err())?;
+        self.keyword(&[For])
+            .map_err(|_| self.make_expected_err(vec![Expected::Keyword(FOR)]))?;
 
+        self.open_delim(FOR, start_pos, DO);
         self.skip_whitespace();
 
         match self.iter.peek() {
             Some(&Name(_)) | Some(&Literal(_)) => {}
-            _ => return Err(self.make_unexpected_err()),
+            _ => return Err(self.make_expected_err(vec![Expected::Name])),
         }
 
         let var_pos = self.iter.pos();
@@ -2034,13 +4691,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // A for command can take one of several different shapes (in pseudo regex syntax):
         // `for name [\n*] [in [word*]] [;\n* | \n+] do_group`
         // Below we'll disambiguate what situation we have as we move along.
-        let (words, pre_body_comments) = if self.peek_reserved_word(&[IN]).is_some() {
+        let mut closed = false;
+        let (words, pre_body_comments) = if self.peek_keyword(&[In]).is_some() {
             // Found `in` keyword, therefore we're looking at something like
             // `for name \n* in [words*] [;\n* | \n+] do_group`
-            self.reserved_word(&[IN]).unwrap();
+            self.keyword(&[In]).unwrap();
 
             let mut words = Vec::new();
-            while let Some(w) = self.word()? {
+            while let Some(w) = self.word_with_braces()? {
                 words.push(w);
             }
 
@@ -2053,7 +4711,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             // Thus if neither is found it is considered an error
             let words_comment = self.newline();
             if !found_semi && words_comment.is_none() {
-                return Err(self.make_unexpected_err());
+                closed = self.close_cmd_at_eof(FOR, start_pos, DO);
+                if !closed {
+                    return Err(self
+                        .make_expected_err(vec![Expected::Token(Semi), Expected::Token(Newline)]));
+                }
             }
 
             (
@@ -2064,7 +4726,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             // `for name \n*;\n* do_group`
             eat!(self, { Semi => {} });
             (None, self.linebreak())
-        } else if self.peek_reserved_word(&[DO]).is_none() {
+        } else if self.peek_keyword(&[Do]).is_none() {
             // If we didn't find an `in` keyword, and we havent hit the body
             // (a `do` keyword), then we can reasonably say the script has
             // words without an `in` keyword.
@@ -2079,7 +4741,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             (None, post_var_comments)
         };
 
-        if self.peek_reserved_word(&[DO]).is_none() {
+        // The input may end before the body, see `IncompleteWords::Partial`
+        let closed = closed
+            || self.peek_keyword(&[Do]).is_none() && self.close_cmd_at_eof(FOR, start_pos, DO);
+        if !closed && self.peek_keyword(&[Do]).is_none() {
             return Err(ParseError::IncompleteCmd(
                 FOR,
                 start_pos,
@@ -2088,7 +4753,15 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             ));
         }
 
-        let body = self.do_group()?;
+        self.delims.pop();
+        let body = if closed {
+            builder::CommandGroup {
+                commands: Vec::new(),
+                trailing_comments: Vec::new(),
+            }
+        } else {
+            self.do_group()?
+        };
         Ok(builder::ForFragments {
             var,
             var_comment,
@@ -2110,33 +4783,36 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
         macro_rules! missing_in {
             () => {
-                |_| ParseError::IncompleteCmd(CASE, start_pos, IN, self.iter.pos());
+                |_| ParseError::IncompleteCmd(CASE, start_pos, IN, self.iter.pos())
             };
         }
 
         macro_rules! missing_esac {
             () => {
-                |_| ParseError::IncompleteCmd(CASE, start_pos, ESAC, self.iter.pos());
+                |_| ParseError::IncompleteCmd(CASE, start_pos, ESAC, self.iter.pos())
             };
         }
 
-        self.reserved_word(&[CASE])
-            .map_err(|_| self.make_unexpected_err())?;
+        self.keyword(&[Case])
+            .map_err(|_| self.make_expected_err(vec![Expected::Keyword(CASE)]))?;
 
+        self.open_delim(CASE, start_pos, IN);
         let word = match self.word()? {
             Some(w) => w,
-            None => return Err(self.make_unexpected_err()),
+            None => return Err(self.make_expected_err(vec![Expected::Word])),
         };
 
         let post_word_comments = self.linebreak();
-        self.reserved_word(&[IN]).map_err(missing_in!())?;
+        self.keyword(&[In]).map_err(missing_in!())?;
         let in_comment = self.newline();
+        self.delims.pop();
+        self.open_delim(CASE, start_pos, ESAC);
 
         let mut pre_esac_comments = None;
         let mut arms = Vec::new();
         loop {
             let pre_pattern_comments = self.linebreak();
-            if self.peek_reserved_word(&[ESAC]).is_some() {
+            if self.peek_keyword(&[Esac]).is_some() {
                 // Make sure we don't lose the captured comments if there are no body
                 debug_assert_eq!(pre_esac_comments, None);
                 pre_esac_comments = Some(pre_pattern_comments);
@@ -2157,7 +4833,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             loop {
                 match self.word()? {
                     Some(p) => patterns.push(p),
-                    None => return Err(self.make_unexpected_err()),
+                    None => return Err(self.make_expected_err(vec![Expected::Word])),
                 }
 
                 match self.iter.peek() {
@@ -2174,7 +4850,12 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     // Make sure we check for missing `esac` here, otherwise if we have EOF
                     // trying to parse a word will result in an `UnexpectedEOF` error
                     None => return Err(()).map_err(missing_esac!()),
-                    _ => return Err(self.make_unexpected_err()),
+                    _ => {
+                        return Err(self.make_expected_err(vec![
+                            Expected::Token(Pipe),
+                            Expected::Token(ParenClose),
+                        ]))
+                    }
                 }
             }
 
@@ -2182,14 +4863,48 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             let body = self.command_group_internal(CommandGroupDelimiters {
                 reserved_words: &[ESAC],
                 reserved_tokens: &[],
-                exact_tokens: &[DSemi],
+                exact_tokens: &[DSemi, Semi],
             })?;
 
-            let (no_more_arms, arm_comment) = if Some(&DSemi) == self.iter.peek() {
-                self.iter.next();
-                (false, self.newline())
-            } else {
-                (true, None)
+            let terminator_pos = self.iter.pos();
+            let fall_through = self.peek_case_fall_through();
+            let terminator = match self.iter.peek() {
+                Some(&DSemi) => {
+                    self.iter.next();
+                    if Some(&Amp) == self.iter.peek() {
+                        self.iter.next();
+                        Some(ast::CaseTerminator::Continue)
+                    } else {
+                        Some(ast::CaseTerminator::Break)
+                    }
+                }
+
+                Some(&Semi) if fall_through => {
+                    self.iter.next();
+                    self.iter.next();
+                    Some(ast::CaseTerminator::FallThrough)
+                }
+
+                // A `;` cannot otherwise appear before a command
+                Some(&Semi) => return Err(self.make_unexpected_err()),
+                _ => None,
+            };
+
+            let extension = match terminator {
+                Some(ast::CaseTerminator::FallThrough) => Some(Extension::CaseFallThrough),
+                Some(ast::CaseTerminator::Continue) => Some(Extension::CaseContinue),
+                _ => None,
+            };
+
+            if let Some(extension) = extension {
+                if !self.options.dialect.supports(extension) {
+                    return Err(ParseError::Extension(extension, terminator_pos));
+                }
+            }
+
+            let (no_more_arms, arm_comment) = match terminator {
+                Some(_) => (false, self.newline()),
+                None => (true, None),
             };
 
             arms.push(builder::CaseArm {
@@ -2199,6 +4914,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     pattern_comment,
                 },
                 body,
+                terminator: terminator.unwrap_or_default(),
                 arm_comment,
             });
 
@@ -2216,7 +4932,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             None => remaining_comments,
         };
 
-        self.reserved_word(&[ESAC]).map_err(missing_esac!())?;
+        if self.keyword(&[Esac]).is_err() && !self.close_cmd_at_eof(CASE, start_pos, ESAC) {
+            return Err(()).map_err(missing_esac!());
+        }
+        self.delims.pop();
 
         Ok(builder::CaseFragments {
             word,
@@ -2232,7 +4951,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     pub fn maybe_function_declaration(
         &mut self,
     ) -> ParseResult<Option<B::PipeableCommand>, B::Error> {
-        if self.peek_reserved_word(&[FUNCTION]).is_some() {
+        if self.peek_keyword(&[Function]).is_some() {
             return self.function_declaration().map(Some);
         }
 
@@ -2262,19 +4981,22 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// the name of the function must be followed by `()`. Whitespace is allowed between
     /// the name and `(`, and whitespace is allowed between `()`.
     pub fn function_declaration(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
-        let (name, post_name_comments, body) = self.function_declaration_internal()?;
+        self.skip_whitespace();
+        let start = self.iter.pos();
+        let (name, syntax, post_name_comments, body) = self.function_declaration_internal()?;
+        let span = self.span_from(start);
         Ok(self
             .builder
-            .function_declaration(name, post_name_comments, body)?)
+            .function_declaration(name, syntax, post_name_comments, body, span)?)
     }
 
     /// Like `Parser::function_declaration`, but does not pass the result to the builder
     fn function_declaration_internal(
         &mut self,
-    ) -> ParseResult<(String, Vec<builder::Newline>, B::CompoundCommand), B::Error> {
-        let found_fn = match self.peek_reserved_word(&[FUNCTION]) {
+    ) -> ParseResult<FunctionParts<B::CompoundCommand>, B::Error> {
+        let found_fn = match self.peek_keyword(&[Function]) {
             Some(_) => {
-                self.iter.next();
+                self.eat_keyword();
                 true
             }
             None => false,
@@ -2284,7 +5006,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
         match self.iter.peek() {
             Some(&Name(_)) | Some(&Literal(_)) => {}
-            _ => return Err(self.make_unexpected_err()),
+            _ => return Err(self.make_expected_err(vec![Expected::Name])),
         }
 
         let ident_pos = self.iter.pos();
@@ -2294,16 +5016,36 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             _ => unreachable!(),
         };
 
+        let kind = builder::ContextKind::Function(name.clone());
+        let (syntax, post_name_comments, body) =
+            self.in_context(kind, |slf| slf.function_body(found_fn))?;
+        Ok((name, syntax, post_name_comments, body))
+    }
+
+    /// Parses the body of a function declaration (including the `()` following
+    /// its name, if any), returning the syntax used to declare the function
+    /// and any comments found before the body.
+    fn function_body(
+        &mut self,
+        found_fn: bool,
+    ) -> ParseResult<
+        (
+            builder::FunctionSyntax,
+            Vec<builder::Newline>,
+            B::CompoundCommand,
+        ),
+        B::Error,
+    > {
         // If there is no whitespace after the function name, the only valid
         // possibility is for `()` to appear.
-        let body = if Some(&ParenOpen) == self.iter.peek() {
+        let (parens, body) = if Some(&ParenOpen) == self.iter.peek() {
             eat!(self, { ParenOpen => {} });
             self.skip_whitespace();
             eat!(self, { ParenClose => {} });
-            None
+            (true, None)
         } else if found_fn && Some(&Newline) == self.iter.peek() {
             // Do nothing, function declaration satisfied
-            None
+            (false, None)
         } else {
             // Enforce at least one whitespace between function declaration and body
             eat!(self, { Whitespace(_) => {} });
@@ -2314,28 +5056,77 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 eat!(self, { ParenOpen => {} });
                 self.skip_whitespace();
                 eat!(self, { ParenClose => {} });
-                None
+                (true, None)
             } else if Some(&ParenOpen) == self.iter.peek() {
                 // Otherwise it is possible for there to be a subshell as the body
+                let start = self.iter.pos();
                 let subshell = self.subshell_internal(true)?;
                 if subshell.commands.is_empty() && subshell.trailing_comments.is_empty() {
                     // Case like `function foo () ...`
-                    None
+                    (true, None)
                 } else {
-                    // Case like `function foo (subshell)`
-                    Some(self.builder.subshell(subshell, Vec::new())?)
+                    // Case like `function foo (subshell) [redirects]`
+                    let (io, io_span) = self.redirect_list_with_span()?;
+                    let span = self.span_from(start);
+                    (false, Some(self.builder.subshell(subshell, io, io_span, span)?))
                 }
             } else {
-                None
+                (false, None)
             }
         };
 
-        let (post_name_comments, body) = match body {
-            Some(subshell) => (Vec::new(), subshell),
-            None => (self.linebreak(), self.compound_command()?),
+        let syntax = match (found_fn, parens) {
+            (false, _) => builder::FunctionSyntax::Posix,
+            (true, false) => builder::FunctionSyntax::Keyword,
+            (true, true) => builder::FunctionSyntax::KeywordParens,
         };
 
-        Ok((name, post_name_comments, body))
+        match body {
+            Some(subshell) => Ok((syntax, Vec::new(), subshell)),
+            None => Ok((syntax, self.linebreak(), self.compound_command()?)),
+        }
+    }
+
+    /// Runs `f` within a nested context, notifying the builder before
+    /// entering and after exiting it (see `Builder::enter_context`).
+    fn in_context<T, F>(&mut self, kind: builder::ContextKind, f: F) -> ParseResult<T, B::Error>
+    where
+        F: FnOnce(&mut Self) -> ParseResult<T, B::Error>,
+    {
+        self.builder.enter_context(kind.clone())?;
+        self.contexts.push(kind);
+        let ret = f(self)?;
+        if let Some(kind) = self.contexts.pop() {
+            self.builder.exit_context(kind)?;
+        }
+        Ok(ret)
+    }
+
+    /// Records that the construct opened by `open` (at `pos`) remains open until
+    /// `close`, such that reaching the end of input within it is reported as such.
+    ///
+    /// The caller should pop it off `delims` once the construct is closed, but not
+    /// if parsing fails, so that it can be reported by `Parser::error_details`.
+    fn open_delim(&mut self, open: &'static str, pos: SourcePos, close: &'static str) {
+        self.delims.push(OpenDelimiter { open, pos, close });
+    }
+
+    /// Runs `f` within a construct which remains open until `close` (see
+    /// `Parser::open_delim`), closing it if `f` succeeds.
+    fn delimited<T, F>(
+        &mut self,
+        open: &'static str,
+        pos: SourcePos,
+        close: &'static str,
+        f: F,
+    ) -> ParseResult<T, B::Error>
+    where
+        F: FnOnce(&mut Self) -> ParseResult<T, B::Error>,
+    {
+        self.open_delim(open, pos, close);
+        let ret = f(self)?;
+        self.delims.pop();
+        Ok(ret)
     }
 
     /// Skips over any encountered whitespace but preserves newlines.
@@ -2346,14 +5137,18 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 self.iter.next();
             }
 
-            let found_backslash_newline = {
+            let found_backslash_newline = Some(&Backslash) == self.iter.peek() && {
                 let mut peeked = self.iter.multipeek();
-                Some(&Backslash) == peeked.peek_next() && Some(&Newline) == peeked.peek_next()
+                let _ = peeked.peek_next();
+                Some(&Newline) == peeked.peek_next()
             };
 
             if found_backslash_newline {
+                // A line continuation is not part of the preceding token
+                let last_end = self.iter.last_end();
                 self.iter.next();
                 self.iter.next();
+                self.iter.set_last_end(last_end);
             } else {
                 break;
             }
@@ -2426,23 +5221,23 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         };
 
         for _ in 0..num_tries {
-            {
+            let found_tok = match self.iter.peek() {
+                Some(tok) => tokens.iter().find(|&t| t == tok),
+                None => return None,
+            };
+
+            // Only look further ahead if the next token matches
+            if found_tok.is_some() {
                 let mut peeked = self.iter.multipeek();
+                let _ = peeked.peek_next();
 
-                let found_tok = match peeked.peek_next() {
-                    Some(tok) => tokens.iter().find(|&t| t == tok),
-                    None => return None,
+                let found_delim = match peeked.peek_next() {
+                    Some(delim) => delim.is_word_delimiter(),
+                    None => true, // EOF is also a valid delimeter
                 };
 
-                if let ret @ Some(_) = found_tok {
-                    let found_delim = match peeked.peek_next() {
-                        Some(delim) => delim.is_word_delimiter(),
-                        None => true, // EOF is also a valid delimeter
-                    };
-
-                    if found_delim {
-                        return ret;
-                    }
+                if found_delim {
+                    return found_tok;
                 }
             }
 
@@ -2467,18 +5262,47 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
 
         self.skip_whitespace();
-        let mut peeked = self.iter.multipeek();
-        let found_tok = match peeked.peek_next() {
+        let found_tok = match self.iter.peek() {
             Some(&Name(ref kw)) | Some(&Literal(ref kw)) => {
                 words.iter().find(|&w| w == kw).copied()
             }
             _ => None,
         };
 
+        // Only look further ahead if the next token matches
+        found_tok?;
+        if self.peek_delimited_word() {
+            found_tok
+        } else {
+            None
+        }
+    }
+
+    /// Checks that one of the specified reserved words appears next, like
+    /// `peek_reserved_word`, but identifies the upcoming word by its bytes
+    /// rather than by comparing it against each of the specified words.
+    fn peek_keyword(&mut self, words: &[ReservedWord]) -> Option<ReservedWord> {
+        self.skip_whitespace();
+        let found = match self.iter.peek() {
+            Some(&Name(ref w)) | Some(&Literal(ref w)) => ReservedWord::from_word(w)?,
+            _ => return None,
+        };
+
+        if words.contains(&found) && self.peek_delimited_word() {
+            Some(found)
+        } else {
+            None
+        }
+    }
+
+    /// Checks that the token following the upcoming one delimits a word.
+    fn peek_delimited_word(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        let _ = peeked.peek_next();
+
         match peeked.peek_next() {
-            Some(delim) if delim.is_word_delimiter() => found_tok,
-            None => found_tok, // EOF is a valid delimeter
-            _ => None,
+            Some(delim) => delim.is_word_delimiter(),
+            None => true, // EOF is a valid delimeter
         }
     }
 
@@ -2487,7 +5311,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// cares which specific reserved word was found.
     pub fn reserved_token(&mut self, tokens: &[Token]) -> ParseResult<Token, B::Error> {
         match self.peek_reserved_token(tokens) {
-            Some(_) => Ok(self.iter.next().unwrap()),
+            Some(_) => Ok(self.eat_keyword().unwrap()),
             None => {
                 // If the desired token is next, but we failed to find a reserved
                 // token (because the token after it isn't a valid delimeter)
@@ -2500,8 +5324,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
                 if skip_one {
                     self.iter.next();
+                    Err(self.make_unexpected_err())
+                } else {
+                    let expected = tokens.iter().cloned().map(Expected::Token).collect();
+                    Err(self.make_expected_err(expected))
                 }
-                Err(self.make_unexpected_err())
             }
         }
     }
@@ -2512,13 +5339,25 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     pub fn reserved_word<'a>(&mut self, words: &'a [&str]) -> Result<&'a str, ()> {
         match self.peek_reserved_word(words) {
             Some(s) => {
-                self.iter.next();
+                self.eat_keyword();
                 Ok(s)
             }
             None => Err(()),
         }
     }
 
+    /// Checks that one of the specified reserved words appears next (see
+    /// `peek_keyword`) and consumes it, returning the string it matched.
+    fn keyword(&mut self, words: &[ReservedWord]) -> Result<&'static str, ()> {
+        match self.peek_keyword(words) {
+            Some(word) => {
+                self.eat_keyword();
+                Ok(word.as_str())
+            }
+            None => Err(()),
+        }
+    }
+
     /// Parses commands until a configured delimeter (or EOF)
     /// is reached, without consuming the token or reserved word.
     ///
@@ -2533,7 +5372,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         let group = self.command_group_internal(cfg)?;
         if group.commands.is_empty() {
-            Err(self.make_unexpected_err())
+            Err(self.make_cmd_err())
         } else {
             Ok(group)
         }
@@ -2545,6 +5384,9 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         cfg: CommandGroupDelimiters<'_, '_, '_>,
     ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         let found_delim = |slf: &mut Parser<_, _>| {
+            // An alias may expand into a delimiter, e.g. `alias end='}'`
+            while slf.expand_alias() {}
+
             let found_exact = !cfg.exact_tokens.is_empty()
                 && slf
                     .iter
@@ -2572,7 +5414,22 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 break;
             }
 
-            cmds.push(self.complete_command_with_leading_comments(leading_comments)?);
+            match self.complete_command_with_leading_comments(leading_comments) {
+                Ok(cmd) => cmds.push(cmd),
+                Err(err) => {
+                    if mem::replace(&mut self.expected_delims, false) {
+                        let delims = cfg
+                            .exact_tokens
+                            .iter()
+                            .chain(cfg.reserved_tokens)
+                            .cloned()
+                            .map(Expected::Token)
+                            .chain(cfg.reserved_words.iter().map(|&w| Expected::Keyword(w)));
+                        delims.for_each(|delim| self.expected.push(delim));
+                    }
+                    return Err(err);
+                }
+            }
         }
 
         Ok(builder::CommandGroup {
@@ -2604,8 +5461,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
     /// Parses expressions such as `var = expr` or `var op= expr`, where `op` is
     /// any of the following operators: *, /, %, +, -, <<, >>, &, |, ^.
+    /// The target may also be an array element, e.g. `arr[i] += expr`.
     fn arith_assig(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         use crate::ast::Arithmetic::*;
+        use crate::ast::AssignOperator;
 
         self.skip_whitespace();
 
@@ -2619,6 +5478,19 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     Some(&Name(_)) => loop {
                         match peeked.peek_next() {
                             Some(&Whitespace(_)) => continue, // Skip whitespace and peek next
+                            // Skip over the index of an array element, e.g. `arr[i + 1]`
+                            Some(&SquareOpen) => {
+                                let mut depth = 1;
+                                while depth > 0 {
+                                    match peeked.peek_next() {
+                                        Some(&SquareOpen) => depth += 1,
+                                        Some(&SquareClose) => depth -= 1,
+                                        Some(_) => {}
+                                        None => break 'assig_check,
+                                    }
+                                }
+                                continue;
+                            }
                             Some(&Star) | Some(&Slash) | Some(&Percent) | Some(&Plus)
                             | Some(&Dash) | Some(&DLess) | Some(&DGreat) | Some(&Amp)
                             | Some(&Pipe) | Some(&Caret) => {
@@ -2643,7 +5515,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             return self.arith_ternary();
         }
 
-        let var = self.arith_var()?;
+        let target = self.arith_target()?;
         self.skip_whitespace();
         let op = match self.iter.next() {
             Some(op @ Star) | Some(op @ Slash) | Some(op @ Percent) | Some(op @ Plus)
@@ -2657,30 +5529,34 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         };
 
         let value = Box::new(self.arith_assig()?);
-        let expr = match op {
-            Star => Box::new(Mult(Box::new(Var(var.clone())), value)),
-            Slash => Box::new(Div(Box::new(Var(var.clone())), value)),
-            Percent => Box::new(Modulo(Box::new(Var(var.clone())), value)),
-            Plus => Box::new(Add(Box::new(Var(var.clone())), value)),
-            Dash => Box::new(Sub(Box::new(Var(var.clone())), value)),
-            DLess => Box::new(ShiftLeft(Box::new(Var(var.clone())), value)),
-            DGreat => Box::new(ShiftRight(Box::new(Var(var.clone())), value)),
-            Amp => Box::new(BitwiseAnd(Box::new(Var(var.clone())), value)),
-            Pipe => Box::new(BitwiseOr(Box::new(Var(var.clone())), value)),
-            Caret => Box::new(BitwiseXor(Box::new(Var(var.clone())), value)),
-            Equals => value,
+        let op = match op {
+            Star => AssignOperator::Mult,
+            Slash => AssignOperator::Div,
+            Percent => AssignOperator::Modulo,
+            Plus => AssignOperator::Add,
+            Dash => AssignOperator::Sub,
+            DLess => AssignOperator::ShiftLeft,
+            DGreat => AssignOperator::ShiftRight,
+            Amp => AssignOperator::BitwiseAnd,
+            Pipe => AssignOperator::BitwiseOr,
+            Caret => AssignOperator::BitwiseXor,
+            Equals => return Ok(Assign(target, value)),
             _ => unreachable!(),
         };
-        Ok(Assign(var, expr))
+        Ok(AssignOp(op, target, value))
     }
 
     /// Parses expressions such as `expr ? expr : expr`.
+    ///
+    /// Like in C, the middle operand may be any expression (including assignments
+    /// and comma separated sequences), while the last operand binds tighter than
+    /// any assignment.
     fn arith_ternary(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         let guard = self.arith_logical_or()?;
         self.skip_whitespace();
         eat_maybe!(self, {
             Question => {
-                let body = self.arith_ternary()?;
+                let body = self.arithmetic_substitution()?;
                 self.skip_whitespace();
                 eat!(self, { Colon => {} });
                 let els = self.arith_ternary()?;
@@ -2833,7 +5709,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
     }
 
-    /// Parses expressions such as `!expr`, `~expr`, `+expr`, `-expr`, `++var` and `--var`.
+    /// Parses expressions such as `!expr`, `~expr`, `+expr`, `-expr`, `++var` and `--var`,
+    /// where `var` may also be an array element.
     fn arith_unary_misc(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         self.skip_whitespace();
         let expr = eat_maybe!(self, {
@@ -2847,7 +5724,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         let next = self.arith_unary_misc()?;
                         ast::Arithmetic::UnaryPlus(Box::new(ast::Arithmetic::UnaryMinus(Box::new(next))))
                     },
-                    Plus => { ast::Arithmetic::PreIncr(self.arith_var()?) };
+                    Plus => { ast::Arithmetic::PreIncr(self.arith_target()?) };
                     _ => { ast::Arithmetic::UnaryPlus(Box::new(self.arith_unary_misc()?)) }
                 })
             },
@@ -2860,7 +5737,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         let next = self.arith_unary_misc()?;
                         ast::Arithmetic::UnaryMinus(Box::new(ast::Arithmetic::UnaryPlus(Box::new(next))))
                     },
-                    Dash => { ast::Arithmetic::PreDecr(self.arith_var()?) };
+                    Dash => { ast::Arithmetic::PreDecr(self.arith_target()?) };
                     _ => { ast::Arithmetic::UnaryMinus(Box::new(self.arith_unary_misc()?)) }
                 })
             };
@@ -2870,9 +5747,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         Ok(expr)
     }
 
-    /// Parses expressions such as `(expr)`, numeric literals, `var`, `var++`, or `var--`.
-    /// Numeric literals must appear as a single `Literal` token. `Name` tokens will be
-    /// treated as variables.
+    /// Parses expressions such as `(expr)`, numeric literals, `var`, `arr[i]`, `var++`,
+    /// or `var--`. `Name` tokens will be treated as variables.
     #[inline]
     fn arith_post_incr(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         self.skip_whitespace();
@@ -2885,36 +5761,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         });
 
-        let num = if let Some(&Literal(ref s)) = self.iter.peek() {
-            if s.starts_with("0x") || s.starts_with("0X") {
-                // from_str_radix does not like it when 0x is present
-                // in the string to parse, thus we should strip it off.
-                // Also, if the string is empty from_str_radix will return
-                // an error; shells like bash and zsh treat `0x` as `0x0`
-                // so we will do the same.
-                let num = &s[2..];
-                if num.is_empty() {
-                    Some(0)
-                } else {
-                    isize::from_str_radix(&s[2..], 16).ok()
-                }
-            } else if s.starts_with('0') {
-                isize::from_str_radix(s, 8).ok()
-            } else {
-                isize::from_str_radix(s, 10).ok()
-            }
-        } else {
-            None
-        };
-
-        let expr = match num {
-            Some(num) => {
-                // Make sure we consume the Token::Literal which holds the number
-                self.iter.next();
-                ast::Arithmetic::Literal(num)
-            }
+        let expr = match self.arith_number()? {
+            Some(num) => num,
             None => {
-                let var = self.arith_var()?;
+                let target = self.arith_target()?;
 
                 // We must be extra careful here because post-increment has a higher precedence
                 // than addition/subtraction meaning post-increment operations will be parsed
@@ -2933,17 +5783,89 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
                 if post_incr {
                     eat!(self, {
-                        Plus => { eat!(self, { Plus => { ast::Arithmetic::PostIncr(var) } }) },
-                        Dash => { eat!(self, { Dash => { ast::Arithmetic::PostDecr(var) } }) },
+                        Plus => { eat!(self, { Plus => { ast::Arithmetic::PostIncr(target) } }) },
+                        Dash => { eat!(self, { Dash => { ast::Arithmetic::PostDecr(target) } }) },
                     })
                 } else {
-                    ast::Arithmetic::Var(var)
+                    match target {
+                        ast::ArithmeticTarget::Var(var) => ast::Arithmetic::Var(var),
+                        ast::ArithmeticTarget::Index(var, index) => {
+                            ast::Arithmetic::Index(var, index)
+                        }
+                    }
                 }
             }
         };
         Ok(expr)
     }
 
+    /// Parses a numeric literal such as `42`, `0x1f`, `017`, or `2#1010`, if present.
+    ///
+    /// Literals which do not fit within 64 bits wrap around (like they do in bash),
+    /// unless parsing in the strict `Dialect::Posix` mode, where they are an error.
+    fn arith_number(&mut self) -> ParseResult<Option<DefaultArithmetic>, B::Error> {
+        let start_pos = self.iter.pos();
+        let mut text = match self.iter.peek() {
+            Some(&Literal(ref s)) if s.starts_with(|c: char| c.is_ascii_digit()) => s.clone(),
+            _ => return Ok(None),
+        };
+        self.iter.next();
+
+        if Some(&Pound) == self.iter.peek() {
+            if !self.options.dialect.supports(Extension::ArithmeticBase) {
+                return Err(ParseError::Extension(Extension::ArithmeticBase, start_pos));
+            }
+
+            self.iter.next();
+            text.push('#');
+
+            // The digits of large bases may consist of several adjacent tokens, e.g. `64#a@_`
+            loop {
+                match self.iter.peek() {
+                    Some(&Name(ref s)) | Some(&Literal(ref s)) => text.push_str(s),
+                    Some(&At) => text.push('@'),
+                    _ => break,
+                }
+                self.iter.next();
+            }
+        }
+
+        let (value, radix, overflow) = match arith_literal(&text) {
+            Some(literal) => literal,
+            None => return Err(ParseError::BadNumber(text, start_pos)),
+        };
+
+        if overflow && self.options.dialect == Dialect::Posix {
+            return Err(ParseError::NumberOverflow(text, start_pos));
+        }
+
+        match radix {
+            ast::Radix::Decimal => Ok(Some(ast::Arithmetic::Literal(value))),
+            radix => Ok(Some(ast::Arithmetic::RadixLiteral(value, radix, text))),
+        }
+    }
+
+    /// Parses a variable in the form `name` or `$name`, or an array element
+    /// such as `arr[i + 1]`, which can be read, assigned, or incremented.
+    fn arith_target(&mut self) -> ParseResult<ast::ArithmeticTarget<String>, B::Error> {
+        let var = self.arith_var()?;
+
+        if let Some(&SquareOpen) = self.iter.peek() {
+            if !self.options.dialect.supports(Extension::ArithmeticIndex) {
+                let pos = self.iter.pos();
+                return Err(ParseError::Extension(Extension::ArithmeticIndex, pos));
+            }
+
+            self.iter.next();
+            let index = self.arithmetic_substitution()?;
+            self.skip_whitespace();
+            eat!(self, { SquareClose => {} });
+            return Ok(ast::ArithmeticTarget::Index(var, Box::new(index)));
+        }
+
+        Ok(ast::ArithmeticTarget::Var(var))
+    }
+
     /// Parses a variable name in the form `name` or `$name`.
     #[inline]
     fn arith_var(&mut self) -> ParseResult<String, B::Error> {
@@ -2957,9 +5879,289 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 unreachable!()
             }
         } else {
-            Err(self.make_unexpected_err())
+            Err(self.make_expected_err(vec![Expected::Name]))
+        }
+    }
+}
+
+/// Checks if a token is the one, or the reserved word, which ends an open construct.
+fn ends_construct(delim: &OpenDelimiter, token: &Token) -> bool {
+    match (delim.open, token) {
+        ("{", &CurlyClose) | ("(", &ParenClose) | ("((", &ParenClose) => true,
+        (IF, Name(word)) => word == FI,
+        (CASE, Name(word)) => word == ESAC,
+        (FOR, Name(word)) | (WHILE, Name(word)) | (UNTIL, Name(word)) | (DO, Name(word)) => {
+            word == DONE
+        }
+        _ => false,
+    }
+}
+
+/// Parses the source text of a numeric literal (e.g. `42`, `0x1f`, `017`, or `2#1010`)
+/// into its value and base, along with whether the value overflowed (and wrapped around),
+/// or returns `None` if the literal is invalid.
+pub(crate) fn arith_literal(text: &str) -> Option<(isize, ast::Radix, bool)> {
+    let (radix, digits) = if let Some(pound) = text.find('#') {
+        match text[..pound].parse::<u8>() {
+            Ok(base) if (2..=64).contains(&base) => (ast::Radix::Explicit(base), pound + 1),
+            _ => return None,
+        }
+    } else if text.starts_with("0x") || text.starts_with("0X") {
+        (ast::Radix::Hex, 2)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (ast::Radix::Octal, 1)
+    } else {
+        (ast::Radix::Decimal, 0)
+    };
+
+    let digits = &text[digits..];
+
+    // Shells like bash and zsh treat `0x` as `0x0` so we will do the same.
+    if digits.is_empty() && radix != ast::Radix::Hex {
+        return None;
+    }
+
+    let base = i64::from(radix.base());
+    let mut overflow = false;
+    let mut value = 0i64;
+    for c in digits.chars() {
+        let digit = i64::from(arith_digit(c, radix.base())?);
+
+        value = match value.checked_mul(base).and_then(|v| v.checked_add(digit)) {
+            Some(v) => v,
+            None => {
+                overflow = true;
+                value.wrapping_mul(base).wrapping_add(digit)
+            }
+        };
+    }
+
+    Some((value as isize, radix, overflow))
+}
+
+/// Returns the value of a digit within a numeric literal of the specified base (if valid).
+///
+/// Bases up to 36 treat letters case insensitively, larger bases use lowercase letters,
+/// uppercase letters, `@`, and `_` for digits 10 through 63, in that order.
+fn arith_digit(c: char, base: u8) -> Option<u8> {
+    let digit = match c {
+        '0'..='9' => c as u8 - b'0',
+        'a'..='z' => c as u8 - b'a' + 10,
+        'A'..='Z' if base <= 36 => c as u8 - b'A' + 10,
+        'A'..='Z' => c as u8 - b'A' + 36,
+        '@' => 62,
+        '_' => 63,
+        _ => return None,
+    };
+
+    if digit < base {
+        Some(digit)
+    } else {
+        None
+    }
+}
+
+/// Splits any brace expansions out of the parts of an unquoted word, i.e. braces
+/// enclosing either a sequence or at least two comma separated alternatives
+/// (whose own brace expansions are split out as well).
+///
+/// Since the parts of the word have not been coalesced yet, every unquoted `{`,
+/// `,` and `}` of the word is still a separate literal part.
+fn brace_expansions<C>(mut parts: Vec<WordKind<C>>) -> Vec<WordKind<C>> {
+    fn is_literal<C>(part: &WordKind<C>, lit: &str) -> bool {
+        match *part {
+            Simple(SimpleWordKind::Literal(ref s)) => s == lit,
+            _ => false,
+        }
+    }
+
+    // Finds the `}` which closes the `{` at `open`, along with the
+    // braces expansion they enclose (which has no alternatives yet)
+    fn find_expansion<C>(parts: &[WordKind<C>], open: usize) -> Option<(usize, Braces<WordKind<C>>)> {
+        let mut depth = 0usize;
+        let mut commas = Vec::new();
+        let mut close = None;
+        for (i, part) in parts.iter().enumerate().skip(open + 1) {
+            if is_literal(part, "{") {
+                depth += 1;
+            } else if is_literal(part, "}") {
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+                depth -= 1;
+            } else if depth == 0 && is_literal(part, ",") {
+                commas.push(i);
+            }
+        }
+
+        let close = close?;
+        if !commas.is_empty() {
+            return Some((close, Braces::Alternatives(Vec::new())));
+        }
+
+        let mut seq = String::new();
+        for part in &parts[open + 1..close] {
+            match *part {
+                Simple(SimpleWordKind::Literal(ref s)) => seq.push_str(s),
+                _ => return None,
+            }
+        }
+
+        brace_sequence(&seq).map(|braces| (close, braces))
+    }
+
+    let mut ret = Vec::with_capacity(parts.len());
+    let mut open = 0;
+    while open < parts.len() {
+        if !is_literal(&parts[open], "{") {
+            open += 1;
+            continue;
+        }
+
+        let (close, braces) = match find_expansion(&parts, open) {
+            Some(found) => found,
+            None => {
+                open += 1;
+                continue;
+            }
+        };
+
+        let rest = parts.split_off(close + 1);
+        let mut inner = parts.split_off(open);
+        ret.append(&mut parts);
+        parts = rest;
+        open = 0;
+
+        let braces = match braces {
+            Braces::Alternatives(_) => {
+                // Drop the braces, and split the rest at the top level commas
+                inner.pop();
+                let mut alternatives = vec![Vec::new()];
+                let mut depth = 0usize;
+                for part in inner.into_iter().skip(1) {
+                    if is_literal(&part, "{") {
+                        depth += 1;
+                    } else if is_literal(&part, "}") {
+                        depth -= 1;
+                    } else if depth == 0 && is_literal(&part, ",") {
+                        alternatives.push(Vec::new());
+                        continue;
+                    }
+
+                    alternatives.last_mut().unwrap().push(part);
+                }
+
+                Braces::Alternatives(alternatives.into_iter().map(brace_expansions).collect())
+            }
+            seq => seq,
+        };
+
+        ret.push(WordKind::Braces(braces));
+    }
+
+    ret.append(&mut parts);
+    ret
+}
+
+/// Replaces the tilde prefixes of an unquoted word with `SimpleWordKind::TildePrefix`,
+/// i.e. a tilde at the start of the word (or following an unquoted `:` within an
+/// assignment) along with the unquoted literals up to the next `/` (or `:`).
+///
+/// Since the parts of the word have not been coalesced yet, every unquoted `/`
+/// and `:` of the word is still a separate part.
+fn tilde_prefixes<C>(parts: Vec<WordKind<C>>, assignment: bool) -> Vec<WordKind<C>> {
+    let mut ret = Vec::with_capacity(parts.len());
+    let mut parts = parts.into_iter().peekable();
+    let mut at_prefix = true;
+
+    while let Some(part) = parts.next() {
+        if !at_prefix || !matches!(part, Simple(SimpleWordKind::Tilde)) {
+            at_prefix = assignment && matches!(part, Simple(SimpleWordKind::Colon));
+            ret.push(part);
+            continue;
+        }
+
+        let mut login = Vec::new();
+        let is_prefix = loop {
+            match parts.peek() {
+                None => break true,
+                Some(Simple(SimpleWordKind::Colon)) if assignment => break true,
+                Some(Simple(SimpleWordKind::Literal(s))) if s == "/" => break true,
+                Some(Simple(SimpleWordKind::Literal(_))) => login.push(parts.next().unwrap()),
+                Some(_) => break false,
+            }
+        };
+
+        at_prefix = false;
+        if !is_prefix {
+            ret.push(part);
+            ret.append(&mut login);
+            continue;
         }
+
+        let login = if login.is_empty() {
+            None
+        } else {
+            let login = login.into_iter().map(|part| match part {
+                Simple(SimpleWordKind::Literal(s)) => s,
+                _ => unreachable!(),
+            });
+            Some(login.collect())
+        };
+
+        ret.push(Simple(SimpleWordKind::TildePrefix(login)));
+    }
+
+    ret
+}
+
+/// Parses the sequence enclosed by the braces of a brace expansion, e.g. `1..10..2` or `a..z`.
+fn brace_sequence<W>(seq: &str) -> Option<Braces<W>> {
+    let mut bounds = seq.split("..");
+    let (start, end) = (bounds.next()?, bounds.next()?);
+    let step = match bounds.next() {
+        Some(step) => Some(step.parse::<i64>().ok()?),
+        None => None,
+    };
+
+    if bounds.next().is_some() {
+        return None;
+    }
+
+    if let (Ok(start_num), Ok(end_num)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        let padded = |n: &str| {
+            let digits = n.trim_start_matches(['-', '+']);
+            digits.len() > 1 && digits.starts_with('0')
+        };
+
+        let width = if padded(start) || padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+
+        return Some(Braces::Numbers {
+            start: start_num,
+            end: end_num,
+            step,
+            width,
+        });
     }
+
+    let letter = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(c),
+            _ => None,
+        }
+    };
+
+    Some(Braces::Chars {
+        start: letter(start)?,
+        end: letter(end)?,
+        step,
+    })
 }
 
 fn concat_tokens(tokens: &[Token]) -> String {
@@ -3044,10 +6246,12 @@ mod tests {
         let body_brace = CompoundCommand {
             kind: Brace(body.clone()),
             io: vec![],
+            io_span: None,
         };
         let body_subshell = CompoundCommand {
             kind: Subshell(body),
             io: vec![],
+            io_span: None,
         };
 
         let iter = cases_brace
@@ -3057,10 +6261,9 @@ mod tests {
             .map(|(src, body)| (src, (name.clone(), comments.clone(), body)));
 
         for (src, correct) in iter {
-            assert_eq!(
-                correct,
-                make_parser(src).function_declaration_internal().unwrap()
-            );
+            let (name, _, comments, body) =
+                make_parser(src).function_declaration_internal().unwrap();
+            assert_eq!(correct, (name, comments, body));
         }
     }
 
@@ -3073,7 +6276,7 @@ mod tests {
 
     #[test]
     fn test_parameter_substitution_command_can_contain_comments() {
-        let param_subst = builder::SimpleWordKind::Subst(Box::new(
+        let param_subst = SimpleWordKind::Subst(Box::new(
             builder::ParameterSubstitutionKind::Command(builder::CommandGroup {
                 commands: vec![cmd("foo")],
                 trailing_comments: vec![Newline(Some("#comment".into()))],
@@ -3087,13 +6290,13 @@ mod tests {
 
     #[test]
     fn test_backticked_command_can_contain_comments() {
-        let cmd_subst = builder::SimpleWordKind::CommandSubst(builder::CommandGroup {
+        let cmd_subst = SimpleWordKind::CommandSubst(builder::CommandGroup {
             commands: vec![cmd("foo")],
             trailing_comments: vec![Newline(Some("#comment".into()))],
         });
         assert_eq!(
             Ok(cmd_subst),
-            make_parser("`foo\n#comment\n`").backticked_raw()
+            make_parser("`foo\n#comment\n`").backticked_raw(false)
         );
     }
 }
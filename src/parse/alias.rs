@@ -0,0 +1,101 @@
+//! Expansion of aliases while parsing, see `Parser::set_alias_provider`.
+
+use crate::parse::SourcePos;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::BuildHasher;
+
+/// A source of alias definitions, which the parser consults for every word
+/// in command position, see `Parser::set_alias_provider`.
+///
+/// Implemented for maps of alias names to their values, as well as for any
+/// closure which looks up the value of an alias by its name.
+pub trait AliasProvider {
+    /// Returns the value of the alias named `name`, or `None` if no such alias is defined.
+    fn alias(&self, name: &str) -> Option<String>;
+}
+
+impl<S: BuildHasher> AliasProvider for HashMap<String, String, S> {
+    fn alias(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+impl AliasProvider for BTreeMap<String, String> {
+    fn alias(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> AliasProvider for F {
+    fn alias(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// An alias whose value is (possibly) still being parsed.
+#[derive(Debug, Clone)]
+pub(super) struct ActiveAlias {
+    /// The name of the alias.
+    pub(super) name: String,
+    /// The position in the source following the word which was replaced
+    /// (along with any enclosing aliases), where parsing resumes once the
+    /// value of the alias has been consumed.
+    pub(super) resume: SourcePos,
+}
+
+/// The state of alias expansion within a parser.
+pub(super) struct Aliases {
+    pub(super) provider: Box<dyn AliasProvider + Send + Sync>,
+    /// The aliases which are currently being expanded, outermost first.
+    pub(super) active: Vec<ActiveAlias>,
+    /// Set if the value of the most recently expanded alias ended in a blank,
+    /// in which case the next word at or after this position is also checked.
+    pub(super) check_next: Option<SourcePos>,
+}
+
+impl Aliases {
+    pub(super) fn new(provider: Box<dyn AliasProvider + Send + Sync>) -> Self {
+        Aliases {
+            provider,
+            active: Vec::new(),
+            check_next: None,
+        }
+    }
+
+    /// Forgets every alias whose value has been consumed by the time the
+    /// parser reaches `pos`, and checks whether `name` is still being expanded.
+    pub(super) fn is_active(&mut self, name: &str, pos: SourcePos) -> bool {
+        self.active.retain(|alias| alias.resume.byte > pos.byte);
+        self.active.iter().any(|alias| alias.name == name)
+    }
+
+    /// Captures the expansion state, so that it can be restored by `Aliases::restore`.
+    pub(super) fn save(&self) -> AliasCheckpoint {
+        AliasCheckpoint {
+            active: self.active.clone(),
+            check_next: self.check_next,
+        }
+    }
+
+    pub(super) fn restore(&mut self, checkpoint: AliasCheckpoint) {
+        self.active = checkpoint.active;
+        self.check_next = checkpoint.check_next;
+    }
+}
+
+impl fmt::Debug for Aliases {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Aliases")
+            .field("active", &self.active)
+            .field("check_next", &self.check_next)
+            .finish()
+    }
+}
+
+/// The expansion state of a parser captured by a `Checkpoint`.
+#[derive(Debug)]
+pub(super) struct AliasCheckpoint {
+    active: Vec<ActiveAlias>,
+    check_next: Option<SourcePos>,
+}
@@ -0,0 +1,277 @@
+//! Defines a parser which yields complete commands from an asynchronous source.
+
+use super::{ParseError, ParseResult, Parser, ParserOptions, PositionTracking, SourcePos};
+use crate::ast::builder::{Builder, BuilderFactory, StringBuilder};
+use crate::lexer::{AsyncReadChars, Lexer};
+use crate::token::Token;
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The result of parsing a single command with a builder created by `F`.
+type FactoryResult<F> = ParseResult<
+    <<F as BuilderFactory>::Builder as Builder>::Command,
+    <<F as BuilderFactory>::Builder as Builder>::Error,
+>;
+
+/// A parser which pulls characters from an asynchronous `Stream`, and yields
+/// complete commands as a `Stream` of its own, without ever blocking on input.
+///
+/// Characters are buffered until the end of a line, at which point the buffered
+/// input is parsed. If it ends in the middle of a command (e.g. an open quote,
+/// a trailing `&&` or `\`, an `if` without its `fi`, or a heredoc without its
+/// delimiter) the parser waits for further lines, much like an interactive
+/// shell prompting for a continuation. Otherwise all commands in the buffer
+/// are yielded, and the buffer is cleared. Whatever remains buffered once the
+/// source ends is parsed as is.
+///
+/// Every line is parsed by a fresh `Parser` (created via the configured
+/// `ParserOptions` and `BuilderFactory`), whose positions continue from the end
+/// of the previously parsed input. Consequently, an error only affects the
+/// commands of the lines it was encountered in: it is yielded in place of
+/// those commands, and parsing resumes on the following line. Errors recovered
+/// from via `ErrorRecovery::Passthrough` are not retained, and alias expansion
+/// is not supported.
+///
+/// ```
+/// use conch_parser::parse::AsyncParser;
+/// # use futures_core::Stream;
+/// # use std::pin::Pin;
+/// # use std::sync::Arc;
+/// # use std::task::{Context, Poll, Wake};
+/// # struct Noop;
+/// # impl Wake for Noop { fn wake(self: Arc<Self>) {} }
+/// # let waker = Arc::new(Noop).into();
+/// # let mut cx = Context::from_waker(&waker);
+///
+/// let source = "if true\nthen echo foo\nfi\necho bar\n".as_bytes();
+/// let mut parser = AsyncParser::from_reader(source);
+///
+/// let mut cmds = Vec::new();
+/// while let Poll::Ready(Some(cmd)) = Pin::new(&mut parser).poll_next(&mut cx) {
+///     cmds.push(cmd.unwrap());
+/// }
+/// assert_eq!(cmds.len(), 2);
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct AsyncParser<S, F = fn() -> StringBuilder>
+where
+    F: BuilderFactory,
+{
+    /// The source of characters to parse.
+    source: S,
+    /// Creates the builder of every parser.
+    factory: F,
+    /// The configuration of every parser.
+    options: ParserOptions,
+    /// The input which has not yet been parsed into complete commands.
+    buf: String,
+    /// The position of the start of `buf` within the source.
+    offset: SourcePos,
+    /// Results which were parsed but not yet yielded.
+    pending: VecDeque<FactoryResult<F>>,
+    /// Whether the source has been exhausted.
+    done: bool,
+}
+
+impl<S, F> fmt::Debug for AsyncParser<S, F>
+where
+    S: fmt::Debug,
+    F: BuilderFactory,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("AsyncParser")
+            .field("source", &self.source)
+            .field("options", &self.options)
+            .field("buf", &self.buf)
+            .field("offset", &self.offset)
+            .field("pending", &self.pending.len())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<S: Unpin, F: BuilderFactory> Unpin for AsyncParser<S, F> {}
+
+impl<S> AsyncParser<S>
+where
+    S: Stream<Item = char> + Unpin,
+{
+    /// Creates a new parser over a stream of characters, using the default
+    /// options and AST builder.
+    pub fn new(source: S) -> Self {
+        let factory: fn() -> StringBuilder = StringBuilder::default;
+        Self::with_options(source, ParserOptions::default(), factory)
+    }
+}
+
+impl<R> AsyncParser<AsyncReadChars<R>>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Creates a new parser over the UTF-8 contents of an asynchronous reader,
+    /// using the default options and AST builder.
+    ///
+    /// Should the reader fail, the parser treats the failure as the end of the
+    /// input. The error can be retrieved from the underlying `AsyncReadChars`.
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(AsyncReadChars::new(reader))
+    }
+}
+
+impl<S, F> AsyncParser<S, F>
+where
+    S: Stream<Item = char> + Unpin,
+    F: BuilderFactory,
+{
+    /// Creates a new parser over a stream of characters, configuring each
+    /// underlying `Parser` with the provided options and builder factory.
+    pub fn with_options(source: S, options: ParserOptions, factory: F) -> Self {
+        let offset = match options.position_tracking {
+            PositionTracking::LinesAndColumns => SourcePos::new(),
+            PositionTracking::ByteOffsets => SourcePos::from_byte(0),
+        };
+
+        AsyncParser {
+            source,
+            factory,
+            options,
+            buf: String::new(),
+            offset,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Returns a reference to the underlying source.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns a mutable reference to the underlying source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Unwraps the parser, returning the underlying source.
+    ///
+    /// Any input which was buffered but not yet parsed is lost.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Parses the buffered input into complete commands, unless it ends in
+    /// the middle of a command and the source may still provide the rest.
+    fn parse_buf(&mut self) {
+        if !self.done && ends_in_line_continuation(&self.buf) {
+            return;
+        }
+
+        let end = self.offset.byte + self.buf.len();
+        let mut parser = self
+            .options
+            .parser(Lexer::new(self.buf.chars()), &self.factory);
+        parser.iter.set_pos(self.offset);
+
+        let mut results = Vec::new();
+        loop {
+            match parser.complete_command() {
+                Ok(Some(cmd)) => results.push(Ok(cmd)),
+                Ok(None) => break,
+                Err(err) => {
+                    let incomplete = is_incomplete(&err, end);
+                    results.push(Err(err));
+                    if incomplete && !self.done {
+                        return;
+                    }
+                    break;
+                }
+            }
+        }
+
+        if !self.done && is_waiting_for_input(&mut parser, end) {
+            return;
+        }
+
+        for c in self.buf.chars() {
+            self.offset.advance_char(c);
+        }
+
+        self.buf.clear();
+        self.pending.extend(results);
+    }
+}
+
+impl<S, F> Stream for AsyncParser<S, F>
+where
+    S: Stream<Item = char> + Unpin,
+    F: BuilderFactory,
+{
+    type Item = FactoryResult<F>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(result) = this.pending.pop_front() {
+                return Poll::Ready(Some(result));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut this.source).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(c)) => {
+                    this.buf.push(c);
+                    if c == '\n' {
+                        this.parse_buf();
+                    }
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    if !this.buf.is_empty() {
+                        this.parse_buf();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks if the last line of the input ends with an unescaped backslash,
+/// which joins the next line onto it.
+fn ends_in_line_continuation(buf: &str) -> bool {
+    let line = buf.trim_end_matches('\n');
+    let backslashes = line.len() - line.trim_end_matches('\\').len();
+    backslashes % 2 == 1
+}
+
+/// Checks if an error was caused by the input ending prematurely, where `end`
+/// is the byte offset of the end of the input.
+fn is_incomplete<E>(err: &ParseError<E>, end: usize) -> bool {
+    match *err {
+        // Unmatched delimiters are only reported once all input is consumed
+        ParseError::UnexpectedEOF | ParseError::Unmatched(..) => true,
+        ParseError::IncompleteCmd(_, _, _, kw_pos) => kw_pos.byte >= end,
+        _ => false,
+    }
+}
+
+/// Checks if a parser which consumed all of its input only did so by treating
+/// the end of the input as the end of some construct, be it a heredoc body or
+/// a region which was recovered from.
+fn is_waiting_for_input<I, B>(parser: &mut Parser<I, B>, end: usize) -> bool
+where
+    I: Iterator<Item = Token>,
+    B: Builder,
+{
+    parser.heredoc_reached_eof()
+        || parser
+            .take_recovered_errors()
+            .iter()
+            .any(|err| is_incomplete(err, end))
+}
@@ -0,0 +1,136 @@
+//! Definitions of the shell dialects understood by the parser.
+
+use std::fmt;
+
+/// The flavor of the shell language which the parser should accept.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Dialect {
+    /// Only accept the grammar defined by the POSIX standard.
+    ///
+    /// Any constructs which are known extensions of other shells (and are
+    /// merely reserved by the standard) will be rejected with a
+    /// `ParseError::Extension` error describing the offending construct.
+    Posix,
+    /// Accept the POSIX grammar along with any bash extensions supported by the parser.
+    ///
+    /// This is the default dialect, since the parser has always accepted some
+    /// extensions (e.g. `function` declarations) unless told otherwise.
+    #[default]
+    Bash,
+    /// Accept the POSIX grammar along with the extensions shared by bash and mksh.
+    ///
+    /// Extensions which mksh does not support (e.g. process substitutions), or
+    /// which mean something else in mksh (e.g. `|&`, which starts a co-process),
+    /// are rejected with a `ParseError::Extension` error.
+    Mksh,
+}
+
+impl Dialect {
+    /// Checks if the dialect accepts an extension.
+    ///
+    /// ```
+    /// use conch_parser::parse::{Dialect, Extension};
+    ///
+    /// assert!(Dialect::Bash.supports(Extension::ProcessRead));
+    /// assert!(Dialect::Mksh.supports(Extension::DoubleBracket));
+    /// assert!(!Dialect::Mksh.supports(Extension::ProcessRead));
+    /// assert!(!Dialect::Posix.supports(Extension::DoubleBracket));
+    /// ```
+    pub fn supports(&self, ext: Extension) -> bool {
+        match *self {
+            Dialect::Posix => false,
+            Dialect::Bash => true,
+            Dialect::Mksh => match ext {
+                Extension::DoubleBracket
+                | Extension::DoubleParen
+                | Extension::FunctionKeyword
+                | Extension::Select
+                | Extension::ArithmeticBase
+                | Extension::ArithmeticIndex
+                | Extension::CaseFallThrough
+                | Extension::RedirectBoth => true,
+
+                Extension::PipeAmp
+                | Extension::ProcessRead
+                | Extension::ProcessWrite
+                | Extension::CaseContinue => false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Dialect::Posix => fmt.write_str("posix"),
+            Dialect::Bash => fmt.write_str("bash"),
+            Dialect::Mksh => fmt.write_str("mksh"),
+        }
+    }
+}
+
+/// A construct which is not part of the POSIX standard, but is
+/// recognized by (at least) one of the supported dialects.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Extension {
+    /// The `[[ ... ]]` conditional command.
+    DoubleBracket,
+    /// The `(( ... ))` arithmetic command.
+    DoubleParen,
+    /// Function declarations using the `function` reserved word.
+    FunctionKeyword,
+    /// The `select` loop.
+    Select,
+    /// The `|&` pipe which also redirects standard error.
+    PipeAmp,
+    /// Arithmetic numeric literals with an explicit base, e.g. `16#ff`.
+    ArithmeticBase,
+    /// Indexed array elements within arithmetic, e.g. `arr[i]`.
+    ArithmeticIndex,
+    /// The `<(cmd)` process substitution.
+    ProcessRead,
+    /// The `>(cmd)` process substitution.
+    ProcessWrite,
+    /// The `;&` terminator of a `case` arm, which falls through to the next arm.
+    CaseFallThrough,
+    /// The `;;&` terminator of a `case` arm, which continues matching the following arms.
+    CaseContinue,
+    /// The `&>` and `&>>` redirections of both standard output and standard error.
+    ///
+    /// Since `cmd &>file` is also valid POSIX syntax (running `cmd` in the background,
+    /// followed by an empty command redirected to `file`), it is parsed as such by
+    /// dialects which do not support the extension, instead of being rejected.
+    RedirectBoth,
+}
+
+impl Extension {
+    /// Returns the source text which introduces the construct.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Extension::DoubleBracket => "[[",
+            Extension::DoubleParen => "((",
+            Extension::FunctionKeyword => "function",
+            Extension::Select => "select",
+            Extension::PipeAmp => "|&",
+            Extension::ArithmeticBase => "base#",
+            Extension::ArithmeticIndex => "[",
+            Extension::ProcessRead => "<(",
+            Extension::ProcessWrite => ">(",
+            Extension::CaseFallThrough => ";&",
+            Extension::CaseContinue => ";;&",
+            Extension::RedirectBoth => "&>",
+        }
+    }
+
+    /// Returns the dialect which should be used to enable the construct,
+    /// i.e. the most permissive dialect, which supports all extensions.
+    pub fn dialect(&self) -> Dialect {
+        Dialect::Bash
+    }
+}
+
+impl fmt::Display for Extension {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
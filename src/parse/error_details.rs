@@ -0,0 +1,169 @@
+//! Structured descriptions of parse errors, for tools which render their own diagnostics.
+
+use crate::ast::Span;
+use crate::parse::{ParseError, SourcePos};
+use crate::token::Token;
+use std::fmt;
+use void::Void;
+
+/// The kind of a `ParseError`, without any of its data.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See `ParseError::BadFd`.
+    BadFd,
+    /// See `ParseError::BadIdent`.
+    BadIdent,
+    /// See `ParseError::BadSubst`.
+    BadSubst,
+    /// See `ParseError::Unmatched`.
+    Unmatched,
+    /// See `ParseError::IncompleteCmd`.
+    IncompleteCmd,
+    /// See `ParseError::Unexpected`.
+    Unexpected,
+    /// See `ParseError::UnexpectedEOF`.
+    UnexpectedEOF,
+    /// See `ParseError::Extension`.
+    Extension,
+    /// See `ParseError::NulByte`.
+    NulByte,
+    /// See `ParseError::BadNumber`.
+    BadNumber,
+    /// See `ParseError::NumberOverflow`.
+    NumberOverflow,
+    /// See `ParseError::Custom`.
+    Custom,
+}
+
+/// Something which the parser would have accepted at the point where it failed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum Expected {
+    /// A specific token, e.g. `)`, `;`, or a newline.
+    Token(Token),
+    /// A reserved word, e.g. `done`.
+    Keyword(&'static str),
+    /// Any word, e.g. the target of a redirect.
+    Word,
+    /// A valid name, e.g. the variable of a `for` loop.
+    Name,
+    /// The start of a command.
+    Command,
+    /// The start of a compound command, e.g. the body of a function.
+    CompoundCommand,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Expected::Token(Token::Newline) => fmt.write_str("newline"),
+            Expected::Token(Token::Backtick) => fmt.write_str("a backtick"),
+            Expected::Token(ref t) => write!(fmt, "`{}`", t),
+            Expected::Keyword(kw) => write!(fmt, "`{}`", kw),
+            Expected::Word => fmt.write_str("a word"),
+            Expected::Name => fmt.write_str("a name"),
+            Expected::Command => fmt.write_str("a command"),
+            Expected::CompoundCommand => fmt.write_str("a compound command"),
+        }
+    }
+}
+
+/// The set of everything which the parser would have accepted at the point
+/// where it failed, in the order the parser considered them.
+///
+/// Displays as a list suitable for diagnostics, e.g. "`done`, `;`, or newline".
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ExpectedSet(pub Vec<Expected>);
+
+impl ExpectedSet {
+    /// Checks if nothing is known about what the parser expected.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Checks if the set contains an item.
+    pub fn contains(&self, expected: &Expected) -> bool {
+        self.0.contains(expected)
+    }
+
+    /// Adds an item to the end of the set, unless it is already present.
+    pub(crate) fn push(&mut self, expected: Expected) {
+        if !self.contains(&expected) {
+            self.0.push(expected);
+        }
+    }
+}
+
+impl fmt::Display for ExpectedSet {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.len();
+        for (i, expected) in self.0.iter().enumerate() {
+            if i > 0 {
+                fmt.write_str(match (len, i == len - 1) {
+                    (2, _) => " or ",
+                    (_, true) => ", or ",
+                    (_, false) => ", ",
+                })?;
+            }
+            write!(fmt, "{}", expected)?;
+        }
+        Ok(())
+    }
+}
+
+/// A construct which was still open (i.e. its closing token or reserved word
+/// had not been found yet) at the point where the parser failed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OpenDelimiter {
+    /// The token or reserved word which opened the construct, e.g. `(` or `if`.
+    pub open: &'static str,
+    /// The location of the opening token or reserved word.
+    pub pos: SourcePos,
+    /// The token or reserved word which would have closed the construct at
+    /// that point, e.g. `)`, or `then` while parsing the guard of an `if`.
+    pub close: &'static str,
+}
+
+/// A structured description of a `ParseError`, see `Parser::error_details`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ErrorDetails {
+    /// The kind of the error.
+    pub kind: ErrorKind,
+    /// The location of the offending source, if any. Errors which are reported
+    /// at a point rather than a token (e.g. a missing keyword) have an empty span.
+    pub span: Option<Span>,
+    /// Everything which would have been accepted instead, which is empty if the
+    /// parser cannot tell.
+    pub expected: ExpectedSet,
+    /// The constructs which were still open where the parser failed, outermost first.
+    pub open: Vec<OpenDelimiter>,
+}
+
+/// A primary parse error along with the follow-on errors it likely caused,
+/// see `PartialParse::error_groups`.
+///
+/// Displays the primary error on the first line, followed by an indented line
+/// for each follow-on error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ErrorGroup<'a> {
+    /// The error which likely caused the others.
+    pub primary: &'a ParseError<Void>,
+    /// The follow-on errors which were kept, in source order.
+    pub related: Vec<&'a ParseError<Void>>,
+    /// The number of follow-on errors which were omitted.
+    pub suppressed: usize,
+}
+
+impl fmt::Display for ErrorGroup<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.primary)?;
+        for err in &self.related {
+            write!(fmt, "\n  related: {}", err)?;
+        }
+        if self.suppressed > 0 {
+            write!(fmt, "\n  ... and {} more related errors", self.suppressed)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! Parsing of individual pieces of the grammar from standalone strings.
+//!
+//! Formats which borrow parts of the shell syntax, such as env files made up of
+//! `NAME=value` assignments, or configuration files which list redirections,
+//! can reuse exactly the grammar of those parts without parsing whole commands.
+//! Each function expects the entire source to make up the requested piece (save
+//! for any surrounding blanks, and any trailing comments or newlines), and returns
+//! an error at the first token which is not part of it.
+
+use super::{Assignment, DefaultParser, Dialect, ParseError, Parser};
+use crate::ast::builder::Builder;
+use crate::ast::{DefaultRedirect, TopLevelWord};
+use crate::lexer::Lexer;
+use crate::token::Token;
+use std::str::Chars;
+use void::Void;
+
+type FragmentParser<'a> = DefaultParser<Lexer<Chars<'a>>>;
+
+fn parser(source: &str, dialect: Dialect) -> FragmentParser<'_> {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_dialect(dialect);
+    parser.skip_whitespace();
+    parser
+}
+
+/// Returns the parsed value if the parser has consumed all of its input (save for
+/// any trailing blanks, comments, or newlines), or an error at the first remaining token.
+fn finish<I, B, T>(parser: &mut Parser<I, B>, value: T) -> Result<T, ParseError<B::Error>>
+where
+    I: Iterator<Item = Token>,
+    B: Builder,
+{
+    parser.linebreak();
+    if parser.is_exhausted() {
+        Ok(value)
+    } else {
+        Err(parser.make_unexpected_err())
+    }
+}
+
+/// Parses the source as a single word, e.g. `"$HOME"/bin`, see `Parser::word`.
+///
+/// ```
+/// use conch_parser::ast::{ComplexWord, SimpleWord, TopLevelWord, Word};
+/// use conch_parser::parse::{parse_word, Dialect};
+///
+/// assert_eq!(
+///     parse_word("foo", Dialect::Posix),
+///     Ok(TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Literal(
+///         "foo".to_owned()
+///     )))))
+/// );
+/// assert!(parse_word("foo bar", Dialect::Posix).is_err());
+/// ```
+pub fn parse_word(
+    source: &str,
+    dialect: Dialect,
+) -> Result<TopLevelWord<String>, ParseError<Void>> {
+    let mut parser = parser(source, dialect);
+    match parser.word()? {
+        Some(word) => finish(&mut parser, word),
+        None => Err(parser.make_unexpected_err()),
+    }
+}
+
+/// Parses the source as a single redirection, e.g. `2>>log` or `<&-`, see `Parser::redirect`.
+///
+/// A heredoc (e.g. `<<EOF`) is only accepted if its body, and the line
+/// delimiting it, follow on the subsequent lines of the source.
+///
+/// ```
+/// use conch_parser::ast::Redirect;
+/// use conch_parser::parse::{parse_redirect, Dialect};
+///
+/// match parse_redirect("2>>log", Dialect::Posix) {
+///     Ok(Redirect::Append(Some(2), _)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+///
+/// assert!(parse_redirect("&>log", Dialect::Bash).is_ok());
+/// assert!(parse_redirect("&>log", Dialect::Posix).is_err());
+/// assert!(parse_redirect("log", Dialect::Posix).is_err());
+/// ```
+pub fn parse_redirect(source: &str, dialect: Dialect) -> Result<DefaultRedirect, ParseError<Void>> {
+    let mut parser = parser(source, dialect);
+    let checkpoint = parser.checkpoint();
+    match parser.redirect()? {
+        Some(Ok(redirect)) => {
+            parser.commit(checkpoint);
+            finish(&mut parser, redirect)
+        }
+        Some(Err(_)) | None => {
+            // Report the start of the word which turned out not to be a redirection
+            parser.rewind(checkpoint);
+            Err(parser.make_unexpected_err())
+        }
+    }
+}
+
+/// Parses the source as a single variable assignment, e.g. `NAME=value`, returning
+/// the name of the variable and its value, see `Parser::assignment`.
+///
+/// The value of an empty assignment (e.g. `NAME=`) is `None`.
+///
+/// ```
+/// use conch_parser::parse::{parse_assignment, parse_word, Dialect};
+///
+/// let (name, value) = parse_assignment("PATH=\"$HOME\"/bin", Dialect::Posix).unwrap();
+/// assert_eq!(name, "PATH");
+/// assert_eq!(value, Some(parse_word("\"$HOME\"/bin", Dialect::Posix).unwrap()));
+///
+/// assert_eq!(parse_assignment("EMPTY=", Dialect::Posix), Ok(("EMPTY".to_owned(), None)));
+/// assert!(parse_assignment("1NAME=value", Dialect::Posix).is_err());
+/// ```
+pub fn parse_assignment(
+    source: &str,
+    dialect: Dialect,
+) -> Result<Assignment<TopLevelWord<String>>, ParseError<Void>> {
+    let mut parser = parser(source, dialect);
+    match parser.assignment()? {
+        Some(assignment) => finish(&mut parser, assignment),
+        None => Err(parser.make_unexpected_err()),
+    }
+}
@@ -14,7 +14,7 @@ pub struct UnmatchedError(pub Token, pub SourcePos);
 
 /// An internal variant that indicates if a token should be yielded
 /// or the current position updated to some value.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum TokenOrPos {
     /// A consumed token which should be yielded.
     Tok(Token),
@@ -127,9 +127,12 @@ trait RewindableTokenIterator {
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
 #[derive(Debug)]
 pub struct TokenIter<I> {
-    /// The underlying token iterator being wrapped. Iterator is fused to avoid
-    /// inconsistent behavior when doing multiple peek ahead operations.
-    iter: std_iter::Fuse<I>,
+    /// The underlying token iterator being wrapped.
+    iter: I,
+    /// Indicates if the underlying iterator has been exhausted, in which case
+    /// it is never polled again (i.e. it is fused) to avoid inconsistent
+    /// behavior when doing multiple peek ahead operations.
+    exhausted: bool,
     /// Any tokens that were previously yielded but to be consumed later, stored
     /// as a stack. Intersperced between the tokens are any changes to the current
     /// position that should be applied. This is useful for situations where the
@@ -138,6 +141,38 @@ pub struct TokenIter<I> {
     prev_buffered: Vec<TokenOrPos>,
     /// The current position in the source that we have consumed up to
     pos: SourcePos,
+    /// The number of bytes pulled from the underlying iterator so far.
+    pulled: usize,
+    /// The raw text of all tokens pulled from the underlying iterator since
+    /// the capture was started, along with the offset at which it starts.
+    capture: Option<(usize, String)>,
+    /// Indicates if the last yielded token ended a line (or if nothing has been yielded yet).
+    line_start: bool,
+    /// The position immediately after the last yielded token which was
+    /// not whitespace or a newline.
+    last_end: SourcePos,
+    /// All tokens pulled from the underlying iterator while any checkpoints
+    /// are live, so that they can be yielded again if a checkpoint is rewound.
+    journal: Vec<Token>,
+    /// The number of checkpoints which have not yet been rewound or released.
+    checkpoints: usize,
+}
+
+/// A snapshot of the state of a `TokenIter` which it can later be rewound to.
+#[derive(Debug)]
+pub struct IterCheckpoint {
+    /// The buffered tokens (and positions) at the time of the snapshot.
+    buffered: Vec<TokenOrPos>,
+    /// The length of the journal at the time of the snapshot.
+    journal_len: usize,
+    /// The number of live checkpoints before this one was created.
+    depth: usize,
+    /// The saved value of `TokenIter::pos`.
+    pos: SourcePos,
+    /// The saved value of `TokenIter::line_start`.
+    line_start: bool,
+    /// The saved value of `TokenIter::last_end`.
+    last_end: SourcePos,
 }
 
 impl<I: Iterator<Item = Token>> PositionIterator for TokenIter<I> {
@@ -148,10 +183,15 @@ impl<I: Iterator<Item = Token>> PositionIterator for TokenIter<I> {
 
 impl<I: Iterator<Item = Token>> PeekableIterator for TokenIter<I> {
     fn peek(&mut self) -> Option<&Self::Item> {
-        // Peek the next token, then drop the wrapper to get the token pushed
-        // back on our buffer. Not the clearest solution, but gets around
-        // the borrow checker.
-        let _ = self.multipeek().peek_next()?;
+        // Once any buffered positions at the top of the stack are applied, the
+        // next token is either on top, or must be pulled from the underlying
+        // iterator. Peeking is done for nearly every token, so this avoids the
+        // allocations of a `Multipeek`.
+        self.updated_buffered_pos();
+        if self.prev_buffered.is_empty() {
+            let next = self.next_token_or_pos()?;
+            self.prev_buffered.push(next);
+        }
 
         if let Some(&TokenOrPos::Tok(ref t)) = self.prev_buffered.last() {
             Some(t)
@@ -171,6 +211,15 @@ impl<I: Iterator<Item = Token>> Iterator for TokenIter<I> {
             match self.next_token_or_pos() {
                 Some(TokenOrPos::Tok(next)) => {
                     self.pos.advance(&next);
+                    match next {
+                        Whitespace(_) | Newline => {}
+                        _ => self.last_end = self.pos,
+                    }
+                    self.line_start = match next {
+                        Newline => true,
+                        Name(ref s) | Literal(ref s) | Whitespace(ref s) => s.ends_with('\n'),
+                        _ => false,
+                    };
                     ret = Some(next);
                     break;
                 }
@@ -190,7 +239,11 @@ impl<I: Iterator<Item = Token>> Iterator for TokenIter<I> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let (low_hint, hi) = self.iter.size_hint();
+        let (low_hint, hi) = if self.exhausted {
+            (0, Some(0))
+        } else {
+            self.iter.size_hint()
+        };
         let low = if self.prev_buffered.is_empty() {
             low_hint
         } else {
@@ -206,9 +259,27 @@ impl<I: Iterator<Item = Token>> RewindableTokenIterator for TokenIter<I> {
     }
 
     fn next_token_or_pos(&mut self) -> Option<TokenOrPos> {
-        self.prev_buffered
-            .pop()
-            .or_else(|| self.iter.next().map(TokenOrPos::Tok))
+        self.prev_buffered.pop().or_else(|| {
+            if self.exhausted {
+                return None;
+            }
+
+            let next = match self.iter.next() {
+                Some(next) => next,
+                None => {
+                    self.exhausted = true;
+                    return None;
+                }
+            };
+            self.pulled += next.len();
+            if let Some((_, ref mut text)) = self.capture {
+                text.push_str(next.as_str());
+            }
+            if self.checkpoints > 0 {
+                self.journal.push(next.clone());
+            }
+            Some(TokenOrPos::Tok(next))
+        })
     }
 }
 
@@ -218,16 +289,34 @@ impl<I: Iterator<Item = Token>> TokenIter<I> {
     /// Creates a new TokenIter from another Token iterator.
     pub fn new(iter: I) -> TokenIter<I> {
         TokenIter {
-            iter: iter.fuse(),
+            iter,
+            exhausted: false,
             prev_buffered: Vec::new(),
             pos: SourcePos::new(),
+            pulled: 0,
+            capture: None,
+            line_start: true,
+            last_end: SourcePos::new(),
+            journal: Vec::new(),
+            checkpoints: 0,
         }
     }
 
+    /// Returns a reference to the underlying token iterator.
+    pub fn get_ref(&self) -> &I {
+        &self.iter
+    }
+
+    /// Returns a mutable reference to the underlying token iterator.
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.iter
+    }
+
     /// Creates a new TokenIter from another Token iterator and an initial position.
     pub fn with_position(iter: I, pos: SourcePos) -> TokenIter<I> {
         let mut iter = TokenIter::new(iter);
         iter.pos = pos;
+        iter.last_end = pos;
         iter
     }
 
@@ -237,6 +326,140 @@ impl<I: Iterator<Item = Token>> TokenIter<I> {
         Multipeek::new(self)
     }
 
+    /// Overrides the position of the next token to be yielded.
+    pub fn set_pos(&mut self, pos: SourcePos) {
+        self.pos = pos;
+        self.last_end = pos;
+    }
+
+    /// Returns the position immediately after the last yielded token which
+    /// was not whitespace or a newline, i.e. where the last consumed construct ends.
+    pub fn last_end(&self) -> SourcePos {
+        self.last_end
+    }
+
+    /// Overrides the position returned by `TokenIter::last_end`, e.g. to
+    /// disregard tokens which were consumed as whitespace.
+    pub fn set_last_end(&mut self, pos: SourcePos) {
+        self.last_end = pos;
+    }
+
+    /// Checks if the next token to be yielded starts a new line.
+    ///
+    /// Unlike inspecting the column of the current position, this works
+    /// even if only byte offsets are being tracked.
+    pub fn at_line_start(&self) -> bool {
+        self.line_start
+    }
+
+    /// Starts capturing the raw text of the source, beginning with the next
+    /// token to be yielded. Any previous capture is discarded.
+    ///
+    /// Unlike the tokens yielded by the iterator (which may be buffered and
+    /// yielded out of order, e.g. when parsing heredocs), the captured text
+    /// always matches the original source.
+    pub fn start_capture(&mut self) {
+        let buffered = self
+            .prev_buffered
+            .iter()
+            .rev()
+            .filter_map(|t| match *t {
+                TokenOrPos::Tok(ref t) => Some(t.as_str()),
+                TokenOrPos::Pos(_) => None,
+            })
+            .collect::<String>();
+
+        self.capture = Some((self.pulled.saturating_sub(buffered.len()), buffered));
+    }
+
+    /// Stops capturing the source, and returns the raw text of the source
+    /// between the start of the capture and the next token to be yielded.
+    pub fn take_capture(&mut self) -> String {
+        let (start, mut text) = match self.capture.take() {
+            Some(capture) => capture,
+            None => return String::new(),
+        };
+
+        let buffered: usize = self
+            .prev_buffered
+            .iter()
+            .map(|t| match *t {
+                TokenOrPos::Tok(ref t) => t.len(),
+                TokenOrPos::Pos(_) => 0,
+            })
+            .sum();
+
+        let len = (self.pulled - start).saturating_sub(buffered);
+        if text.is_char_boundary(len) {
+            text.truncate(len);
+        }
+        text
+    }
+
+    /// Takes a snapshot of the current state of the iterator, which can be
+    /// restored via `TokenIter::rewind_to`. Until the checkpoint is rewound
+    /// or released, all tokens pulled from the underlying iterator are
+    /// retained so that they can be yielded again.
+    pub fn checkpoint(&mut self) -> IterCheckpoint {
+        let checkpoint = IterCheckpoint {
+            buffered: self.prev_buffered.clone(),
+            journal_len: self.journal.len(),
+            depth: self.checkpoints,
+            pos: self.pos,
+            line_start: self.line_start,
+            last_end: self.last_end,
+        };
+
+        self.checkpoints += 1;
+        checkpoint
+    }
+
+    /// Restores the iterator to the state it was in when the checkpoint was
+    /// taken, such that all tokens yielded since will be yielded again.
+    /// Any checkpoints taken after the provided one are released as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint (or one taken before it) was already released.
+    pub fn rewind_to(&mut self, checkpoint: IterCheckpoint) {
+        // Since the underlying iterator is only polled once all buffered
+        // tokens have been yielded, everything yielded since the checkpoint
+        // is whatever was buffered at the time followed by all tokens pulled
+        // from the underlying iterator since. Any heredoc or look ahead
+        // rebuffering in between only ever reorders these same tokens.
+        let pulled = self.journal[checkpoint.journal_len..]
+            .iter()
+            .rev()
+            .cloned()
+            .map(TokenOrPos::Tok);
+
+        self.prev_buffered.clear();
+        self.prev_buffered.extend(pulled);
+        self.prev_buffered.extend(checkpoint.buffered.iter().cloned());
+        self.pos = checkpoint.pos;
+        self.line_start = checkpoint.line_start;
+        self.last_end = checkpoint.last_end;
+        self.release(checkpoint);
+        self.updated_buffered_pos();
+    }
+
+    /// Releases a checkpoint (and any taken after it) without rewinding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint (or one taken before it) was already released.
+    pub fn release(&mut self, checkpoint: IterCheckpoint) {
+        assert!(
+            checkpoint.depth < self.checkpoints,
+            "checkpoint was already released"
+        );
+
+        self.checkpoints = checkpoint.depth;
+        if self.checkpoints == 0 {
+            self.journal.clear();
+        }
+    }
+
     /// Update the current position based on any buffered state.
     ///
     /// This allows us to always correctly report the position of the next token
@@ -286,15 +509,33 @@ impl<I: Iterator<Item = Token>> TokenIter<I> {
         self.buffer_tokens_and_positions_to_yield_first(tokens, Some(buf_start));
     }
 
+    /// Accepts a vector of tokens to be yielded completely before the inner
+    /// iterator is advanced further, all of which are positioned at `pos`
+    /// since they replace the source at that point (e.g. the value of an alias).
+    pub fn buffer_tokens_at(&mut self, buf: Vec<Token>, pos: SourcePos) {
+        let tokens = buf
+            .into_iter()
+            .flat_map(|t| vec![TokenOrPos::Pos(pos), TokenOrPos::Tok(t)])
+            .collect();
+        self.buffer_tokens_and_positions_to_yield_first(tokens, Some(pos));
+    }
+
     /// Collects all tokens yielded by `TokenIter::backticked_remove_backslashes`
     /// and creates a `TokenIter` which will yield the collected tokens, and maintain
     /// the correct position of where each token appears in the original source,
     /// regardless of how many backslashes may have been removed since then.
+    ///
+    /// If the backticks are `double_quoted`, backslashes followed by " are removed as well.
+    ///
+    /// If `until_eof` is set, reaching the end of the input before the closing backtick
+    /// is not an error, and the tokens collected so far are returned along with `true`.
     pub fn token_iter_from_backticked_with_removed_backslashes(
         &mut self,
         pos: SourcePos,
-    ) -> Result<TokenIter<std_iter::Empty<Token>>, UnmatchedError> {
-        BacktickBackslashRemover::create_token_iter(self.backticked(pos))
+        double_quoted: bool,
+        until_eof: bool,
+    ) -> Result<(TokenIter<std_iter::Empty<Token>>, bool), UnmatchedError> {
+        BacktickBackslashRemover::create_token_iter(self.backticked(pos), double_quoted, until_eof)
     }
 }
 
@@ -406,6 +647,78 @@ impl<I: Iterator<Item = Token>> TokenIterWrapper<I> {
         }
     }
 
+    /// Delegates to `TokenIter::set_pos`.
+    pub fn set_pos(&mut self, pos: SourcePos) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.set_pos(pos),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.set_pos(pos),
+        }
+    }
+
+    /// Delegates to `TokenIter::last_end`.
+    pub fn last_end(&self) -> SourcePos {
+        match *self {
+            TokenIterWrapper::Regular(ref inner) => inner.last_end(),
+            TokenIterWrapper::Buffered(ref inner) => inner.last_end(),
+        }
+    }
+
+    /// Delegates to `TokenIter::set_last_end`.
+    pub fn set_last_end(&mut self, pos: SourcePos) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.set_last_end(pos),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.set_last_end(pos),
+        }
+    }
+
+    /// Delegates to `TokenIter::at_line_start`.
+    pub fn at_line_start(&self) -> bool {
+        match *self {
+            TokenIterWrapper::Regular(ref inner) => inner.at_line_start(),
+            TokenIterWrapper::Buffered(ref inner) => inner.at_line_start(),
+        }
+    }
+
+    /// Delegates to `TokenIter::start_capture`.
+    pub fn start_capture(&mut self) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.start_capture(),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.start_capture(),
+        }
+    }
+
+    /// Delegates to `TokenIter::take_capture`.
+    pub fn take_capture(&mut self) -> String {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.take_capture(),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.take_capture(),
+        }
+    }
+
+    /// Delegates to `TokenIter::checkpoint`.
+    pub fn checkpoint(&mut self) -> IterCheckpoint {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.checkpoint(),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.checkpoint(),
+        }
+    }
+
+    /// Delegates to `TokenIter::rewind_to`.
+    pub fn rewind_to(&mut self, checkpoint: IterCheckpoint) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.rewind_to(checkpoint),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.rewind_to(checkpoint),
+        }
+    }
+
+    /// Delegates to `TokenIter::release`.
+    pub fn release(&mut self, checkpoint: IterCheckpoint) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.release(checkpoint),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.release(checkpoint),
+        }
+    }
+
     /// Delegates to `TokenIter::buffer_tokens_to_yield_first`.
     pub fn buffer_tokens_to_yield_first(&mut self, buf: Vec<Token>, buf_start: SourcePos) {
         match *self {
@@ -418,18 +731,26 @@ impl<I: Iterator<Item = Token>> TokenIterWrapper<I> {
         }
     }
 
+    /// Delegates to `TokenIter::buffer_tokens_at`.
+    pub fn buffer_tokens_at(&mut self, buf: Vec<Token>, pos: SourcePos) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.buffer_tokens_at(buf, pos),
+            TokenIterWrapper::Buffered(ref mut inner) => inner.buffer_tokens_at(buf, pos),
+        }
+    }
+
     /// Delegates to `TokenIter::token_iter_from_backticked_with_removed_backslashes`.
     pub fn token_iter_from_backticked_with_removed_backslashes(
         &mut self,
         pos: SourcePos,
-    ) -> Result<TokenIter<std_iter::Empty<Token>>, UnmatchedError> {
+        double_quoted: bool,
+        until_eof: bool,
+    ) -> Result<(TokenIter<std_iter::Empty<Token>>, bool), UnmatchedError> {
         match *self {
-            TokenIterWrapper::Regular(ref mut inner) => {
-                inner.token_iter_from_backticked_with_removed_backslashes(pos)
-            }
-            TokenIterWrapper::Buffered(ref mut inner) => {
-                inner.token_iter_from_backticked_with_removed_backslashes(pos)
-            }
+            TokenIterWrapper::Regular(ref mut inner) => inner
+                .token_iter_from_backticked_with_removed_backslashes(pos, double_quoted, until_eof),
+            TokenIterWrapper::Buffered(ref mut inner) => inner
+                .token_iter_from_backticked_with_removed_backslashes(pos, double_quoted, until_eof),
         }
     }
 }
@@ -632,40 +953,67 @@ impl<I: PeekablePositionIterator<Item = Token>> BacktickBackslashRemover<I> {
     /// and creates a `TokenIter` which will yield the collected tokens, and maintain
     /// the correct position of where each token appears in the original source,
     /// regardless of how many backslashes may have been removed since then.
+    ///
+    /// If the backticks are `double_quoted`, backslashes followed by " are removed as well.
+    /// If `until_eof` is set, any unmatched delimiters are left for the caller to handle,
+    /// which is indicated by returning `true` alongside the collected tokens.
     fn create_token_iter(
         mut iter: Balanced<I>,
-    ) -> Result<TokenIter<std_iter::Empty<Token>>, UnmatchedError> {
+        double_quoted: bool,
+        until_eof: bool,
+    ) -> Result<(TokenIter<std_iter::Empty<Token>>, bool), UnmatchedError> {
+        let removes_backslash = |tok: &Token| match *tok {
+            Dollar | Backtick | Backslash => true,
+            DoubleQuote => double_quoted,
+            _ => false,
+        };
+
         let mut all_chunks = Vec::new();
         let mut chunk_start = iter.pos();
         let mut chunk = Vec::new();
 
+        // Unmatched delimiters are only reported at the end of the input
+        let mut unmatched = None;
         loop {
             match iter.next() {
                 Some(Ok(Backslash)) => {
                     let next_pos = iter.pos();
                     match iter.next() {
-                        Some(Ok(tok @ Dollar))
-                        | Some(Ok(tok @ Backtick))
-                        | Some(Ok(tok @ Backslash)) => {
+                        Some(Ok(tok)) if removes_backslash(&tok) => {
                             all_chunks.push((chunk, chunk_start));
                             chunk_start = next_pos;
                             chunk = vec![tok];
                         }
 
-                        Some(tok) => {
+                        Some(Ok(tok)) => {
                             chunk.push(Backslash);
-                            chunk.push(tok?);
+                            chunk.push(tok);
+                        }
+
+                        Some(Err(err)) => {
+                            chunk.push(Backslash);
+                            unmatched = Some(err);
+                            break;
                         }
 
                         None => chunk.push(Backslash),
                     }
                 }
 
-                Some(tok) => chunk.push(tok?),
+                Some(Ok(tok)) => chunk.push(tok),
+                Some(Err(err)) => {
+                    unmatched = Some(err);
+                    break;
+                }
                 None => break,
             }
         }
 
+        let unterminated = match unmatched {
+            Some(err) if !until_eof => return Err(err),
+            unmatched => unmatched.is_some(),
+        };
+
         if !chunk.is_empty() {
             all_chunks.push((chunk, chunk_start));
         }
@@ -674,7 +1022,7 @@ impl<I: PeekablePositionIterator<Item = Token>> BacktickBackslashRemover<I> {
         while let Some((chunk, chunk_end)) = all_chunks.pop() {
             tok_iter.buffer_tokens_to_yield_first(chunk, chunk_end);
         }
-        Ok(tok_iter)
+        Ok((tok_iter, unterminated))
     }
 }
 
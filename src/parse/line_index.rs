@@ -0,0 +1,83 @@
+//! Lazy resolution of byte offsets into lines and columns.
+
+use crate::parse::SourcePos;
+
+/// An index of where each line of a source starts, which can resolve byte
+/// offsets into complete `SourcePos` values after parsing is done.
+///
+/// This allows parsing with `PositionTracking::ByteOffsets` and only paying
+/// for line and column information when a position actually needs to be
+/// reported. The resolved positions are identical to those the parser would
+/// have produced with `PositionTracking::LinesAndColumns`, i.e. columns are
+/// counted in bytes, starting at 1.
+///
+/// ```
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::{DefaultParser, LineIndex, PositionTracking};
+///
+/// let source = "echo foo\necho $(bar";
+/// let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+/// parser.set_position_tracking(PositionTracking::ByteOffsets);
+///
+/// parser.complete_command().unwrap();
+/// let err = parser.complete_command().unwrap_err();
+///
+/// let index = LineIndex::new(source);
+/// let err = err.map_pos(|pos| index.resolve(pos));
+/// assert_eq!(err.to_string(), "unmatched `(` starting on line 2:7");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The byte offset at which each line starts, in ascending order.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Indexes the lines of a source. The source should be the same text
+    /// which was (or will be) parsed, starting at the byte offset 0.
+    pub fn new(source: &str) -> Self {
+        let newlines = source
+            .bytes()
+            .enumerate()
+            .filter(|&(_, b)| b == b'\n')
+            .map(|(i, _)| i + 1);
+
+        LineIndex {
+            line_starts: Some(0).into_iter().chain(newlines).collect(),
+        }
+    }
+
+    /// Returns the number of lines in the indexed source.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the byte offset at which the specified (1-based) line starts, if it exists.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        line.checked_sub(1)
+            .and_then(|i| self.line_starts.get(i))
+            .cloned()
+    }
+
+    /// Returns the full position of the specified byte offset.
+    ///
+    /// Offsets beyond the end of the source are treated as if the last line
+    /// continued indefinitely.
+    pub fn pos(&self, byte: usize) -> SourcePos {
+        let line = match self.line_starts.binary_search(&byte) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        SourcePos {
+            byte,
+            line: line + 1,
+            col: byte - self.line_starts[line] + 1,
+        }
+    }
+
+    /// Resolves the line and column of a position using its byte offset.
+    pub fn resolve(&self, pos: SourcePos) -> SourcePos {
+        self.pos(pos.byte)
+    }
+}
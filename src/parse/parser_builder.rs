@@ -0,0 +1,175 @@
+//! Defines a fluent API for configuring and constructing a `Parser`.
+
+use super::alias::Aliases;
+use super::{
+    AliasProvider, BraceExpansion, Dialect, ErrorRecovery, IncompleteWords, NulHandling, Parser,
+    ParserOptions, PositionTracking, ReservedWords, TildeExpansion,
+};
+use crate::ast::builder::{Builder, StringBuilder};
+use crate::lexer::{Lexer, ReadChars};
+use crate::token::Token;
+use std::fmt;
+use std::io::Read;
+
+/// Assembles the configuration of a `Parser` (its AST builder, options, and
+/// alias provider) before creating it from a source of tokens, characters, or bytes.
+///
+/// A builder starts out with the defaults of a `DefaultParser`, thus only the
+/// settings which differ need to be specified. `Parser::new` (or `DefaultParser::new`)
+/// remains the simplest way to create a parser with the default configuration.
+///
+/// ```
+/// use conch_parser::ast::builder::ArcBuilder;
+/// use conch_parser::parse::{Dialect, ErrorRecovery, ParserBuilder};
+///
+/// let mut parser = ParserBuilder::new()
+///     .builder(ArcBuilder::new())
+///     .dialect(Dialect::Posix)
+///     .error_recovery(ErrorRecovery::Passthrough)
+///     .alias_provider(|name: &str| match name {
+///         "ll" => Some("ls -l".to_owned()),
+///         _ => None,
+///     })
+///     .chars("ll foo".chars());
+///
+/// assert_eq!(parser.dialect(), Dialect::Posix);
+/// assert_eq!(parser.parse_all().unwrap().len(), 1);
+/// ```
+pub struct ParserBuilder<B = StringBuilder> {
+    builder: B,
+    options: ParserOptions,
+    aliases: Option<Box<dyn AliasProvider + Send + Sync>>,
+    track_keywords: bool,
+}
+
+impl ParserBuilder {
+    /// Creates a new builder with the configuration of a `DefaultParser`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for ParserBuilder {
+    fn default() -> Self {
+        ParserBuilder {
+            builder: StringBuilder::default(),
+            options: ParserOptions::default(),
+            aliases: None,
+            track_keywords: false,
+        }
+    }
+}
+
+impl<B: Builder> ParserBuilder<B> {
+    /// Sets the AST builder the parser passes its results to.
+    pub fn builder<C: Builder>(self, builder: C) -> ParserBuilder<C> {
+        ParserBuilder {
+            builder,
+            options: self.options,
+            aliases: self.aliases,
+            track_keywords: self.track_keywords,
+        }
+    }
+
+    /// Replaces all options at once, see `Parser::set_options`.
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the dialect the parser should accept, see `Parser::set_dialect`.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.options.dialect = dialect;
+        self
+    }
+
+    /// Sets how the parser should treat NUL bytes, see `Parser::set_nul_handling`.
+    pub fn nul_handling(mut self, nul_handling: NulHandling) -> Self {
+        self.options.nul_handling = nul_handling;
+        self
+    }
+
+    /// Sets how the parser should proceed after an error, see `Parser::set_error_recovery`.
+    pub fn error_recovery(mut self, error_recovery: ErrorRecovery) -> Self {
+        self.options.error_recovery = error_recovery;
+        self
+    }
+
+    /// Sets which parts of a source position the parser should keep track of,
+    /// see `Parser::set_position_tracking`.
+    pub fn position_tracking(mut self, position_tracking: PositionTracking) -> Self {
+        self.options.position_tracking = position_tracking;
+        self
+    }
+
+    /// Sets how the parser should treat expansions left open at the end of the input,
+    /// see `Parser::set_incomplete_words`.
+    pub fn incomplete_words(mut self, incomplete_words: IncompleteWords) -> Self {
+        self.options.incomplete_words = incomplete_words;
+        self
+    }
+
+    /// Sets how the parser should treat closing reserved words found where a command
+    /// is expected, see `Parser::set_reserved_words`.
+    pub fn reserved_words(mut self, reserved_words: ReservedWords) -> Self {
+        self.options.reserved_words = reserved_words;
+        self
+    }
+
+    /// Sets how the parser should treat brace expansions within words,
+    /// see `Parser::set_brace_expansion`.
+    pub fn brace_expansion(mut self, brace_expansion: BraceExpansion) -> Self {
+        self.options.brace_expansion = brace_expansion;
+        self
+    }
+
+    /// Sets how the parser should treat tildes which begin a tilde prefix,
+    /// see `Parser::set_tilde_expansion`.
+    pub fn tilde_expansion(mut self, tilde_expansion: TildeExpansion) -> Self {
+        self.options.tilde_expansion = tilde_expansion;
+        self
+    }
+
+    /// Sets the aliases the parser should expand, see `Parser::set_alias_provider`.
+    pub fn alias_provider<A: AliasProvider + Send + Sync + 'static>(mut self, provider: A) -> Self {
+        self.aliases = Some(Box::new(provider));
+        self
+    }
+
+    /// Sets whether the parser should record every reserved word it recognizes,
+    /// see `Parser::set_track_keywords`.
+    pub fn track_keywords(mut self, track: bool) -> Self {
+        self.track_keywords = track;
+        self
+    }
+
+    /// Creates a parser which consumes the provided tokens.
+    pub fn tokens<T: IntoIterator<Item = Token>>(self, tokens: T) -> Parser<T::IntoIter, B> {
+        let mut parser = Parser::with_options(tokens.into_iter(), self.builder, self.options);
+        parser.aliases = self.aliases.map(Aliases::new);
+        parser.set_track_keywords(self.track_keywords);
+        parser
+    }
+
+    /// Creates a parser which lexes and consumes the provided characters.
+    pub fn chars<C: IntoIterator<Item = char>>(self, chars: C) -> Parser<Lexer<C::IntoIter>, B> {
+        self.tokens(Lexer::new(chars.into_iter()))
+    }
+
+    /// Creates a parser which incrementally lexes and consumes the contents
+    /// of a reader, see `Parser::from_reader`.
+    pub fn reader<R: Read>(self, reader: R) -> Parser<Lexer<ReadChars<R>>, B> {
+        self.chars(ReadChars::new(reader))
+    }
+}
+
+impl<B: fmt::Debug> fmt::Debug for ParserBuilder<B> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ParserBuilder")
+            .field("builder", &self.builder)
+            .field("options", &self.options)
+            .field("aliases", &self.aliases.is_some())
+            .field("track_keywords", &self.track_keywords)
+            .finish()
+    }
+}
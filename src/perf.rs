@@ -0,0 +1,298 @@
+//! A benchmark runner for gating on the performance of the parser.
+//!
+//! Services which embed the parser on a latency sensitive path can run a
+//! corpus of their own scripts through `run`, and compare the resulting
+//! `PerfReport` against one recorded earlier (see `PerfReport::regressions`)
+//! to fail their CI if parsing becomes slower.
+//!
+//! Every script of the corpus is lexed and parsed with the `DefaultBuilder`,
+//! first `PerfConfig::warmup` times (which are not measured), then another
+//! `PerfConfig::iterations` times. The time spent parsing each complete command
+//! is attributed to the category of the command, and the median time of all
+//! measured iterations is reported for each category (along with the median of
+//! the total time), which keeps the report stable on noisy machines.
+//!
+//! Categories are named after the kind of each top level command, using the
+//! names of `analysis::corpus` where possible: `"simple"`, `"pipeline"`,
+//! `"and-or"` (for `&&` and `||` lists), `"function"`, or the kind of compound
+//! command, e.g. `"if"` or `"while"`. Any comments or blank lines preceding a
+//! command are attributed to it.
+//!
+//! ```
+//! use conch_parser::perf::{run, PerfConfig};
+//!
+//! let corpus = ["echo foo | wc -l", "for x in a b; do echo $x; done\nfoo && bar"];
+//! let report = run(&corpus, &PerfConfig::default()).unwrap();
+//!
+//! assert_eq!(report.total.commands, 3);
+//! assert_eq!(report.categories["pipeline"].commands, 1);
+//! assert_eq!(report.categories["for"].commands, 1);
+//! assert_eq!(report.categories["and-or"].commands, 1);
+//!
+//! // A report never regresses against itself
+//! assert!(report.regressions(&report, 0.1).is_empty());
+//! ```
+
+use crate::analysis::dialect_diff::DefaultParseError;
+use crate::ast::{Command, CompoundCommandKind, ListableCommand, PipeableCommand, TopLevelCommand};
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, ParserOptions};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Settings which control how a corpus is measured.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PerfConfig {
+    /// The options every script is parsed with.
+    pub options: ParserOptions,
+    /// How many times the corpus is parsed before measuring, e.g. to warm up caches.
+    pub warmup: u32,
+    /// How many times the corpus is parsed while measuring. At least one
+    /// iteration is always measured.
+    pub iterations: u32,
+}
+
+impl Default for PerfConfig {
+    fn default() -> Self {
+        PerfConfig {
+            options: ParserOptions::default(),
+            warmup: 1,
+            iterations: 5,
+        }
+    }
+}
+
+/// The amount of source parsed within some time.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Throughput {
+    /// The number of bytes of source which were parsed.
+    pub bytes: u64,
+    /// The number of complete commands which were parsed.
+    pub commands: u64,
+    /// The (median) time spent parsing the source.
+    pub time: Duration,
+}
+
+impl Throughput {
+    /// Returns the number of bytes parsed per second.
+    pub fn bytes_per_sec(&self) -> f64 {
+        per_sec(self.bytes, self.time)
+    }
+
+    /// Returns the number of complete commands parsed per second.
+    pub fn commands_per_sec(&self) -> f64 {
+        per_sec(self.commands, self.time)
+    }
+}
+
+fn per_sec(amount: u64, time: Duration) -> f64 {
+    let secs = time.as_secs_f64();
+    if secs > 0.0 {
+        amount as f64 / secs
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// The results of measuring a corpus with `run`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PerfReport {
+    /// How many iterations were measured.
+    pub iterations: u32,
+    /// The throughput of parsing the entire corpus.
+    pub total: Throughput,
+    /// The throughput of parsing the commands of each category, sorted by name.
+    pub categories: BTreeMap<String, Throughput>,
+}
+
+/// A category whose throughput dropped below the tolerance of a baseline,
+/// see `PerfReport::regressions`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Regression {
+    /// The name of the category, or `"total"` for the entire corpus.
+    pub category: String,
+    /// The bytes parsed per second by the baseline.
+    pub baseline: f64,
+    /// The bytes parsed per second by the current report.
+    pub current: f64,
+}
+
+impl Regression {
+    /// Returns how much slower the current throughput is, relative to the
+    /// baseline, e.g. `0.25` if it is 25% slower.
+    pub fn slowdown(&self) -> f64 {
+        1.0 - self.current / self.baseline
+    }
+}
+
+impl PerfReport {
+    /// Compares this report against a baseline (e.g. one recorded on the main
+    /// branch), returning every category (including the `"total"`) whose
+    /// throughput is slower than the baseline by more than the tolerance,
+    /// e.g. `0.1` allows up to 10% slower throughput.
+    ///
+    /// Categories which only appear in one of the reports are not compared.
+    pub fn regressions(&self, baseline: &PerfReport, tolerance: f64) -> Vec<Regression> {
+        let total = ("total", &self.total, &baseline.total);
+        let categories = self.categories.iter().filter_map(|(name, current)| {
+            let base = baseline.categories.get(name)?;
+            Some((&**name, current, base))
+        });
+
+        Some(total)
+            .into_iter()
+            .chain(categories)
+            .filter_map(|(name, current, base)| {
+                let regression = Regression {
+                    category: String::from(name),
+                    baseline: base.bytes_per_sec(),
+                    current: current.bytes_per_sec(),
+                };
+
+                if regression.slowdown() > tolerance {
+                    Some(regression)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Measures how quickly the corpus is parsed, see the module documentation
+/// for the methodology. Returns the first error if any script fails to parse.
+pub fn run<S: AsRef<str>>(
+    corpus: &[S],
+    config: &PerfConfig,
+) -> Result<PerfReport, DefaultParseError> {
+    for _ in 0..config.warmup {
+        for script in corpus {
+            parse(script.as_ref(), config.options, &mut Timings::default())?;
+        }
+    }
+
+    let iterations = config.iterations.max(1);
+    let mut measured = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let mut timings = Timings::default();
+        for script in corpus {
+            parse(script.as_ref(), config.options, &mut timings)?;
+        }
+        measured.push(timings);
+    }
+
+    let median = |mut times: Vec<Duration>| {
+        times.sort_unstable();
+        times[times.len() / 2]
+    };
+
+    // Every iteration parses the same commands, only their times differ
+    let first = &measured[0];
+    let total = Throughput {
+        time: median(measured.iter().map(|t| t.total.time).collect()),
+        ..first.total
+    };
+
+    let categories = first
+        .categories
+        .iter()
+        .map(|(&name, throughput)| {
+            let times = measured.iter().map(|t| t.categories[name].time).collect();
+            let throughput = Throughput {
+                time: median(times),
+                ..*throughput
+            };
+            (String::from(name), throughput)
+        })
+        .collect();
+
+    Ok(PerfReport {
+        iterations,
+        total,
+        categories,
+    })
+}
+
+/// The throughput of a single iteration over the corpus.
+#[derive(Debug, Default)]
+struct Timings {
+    total: Throughput,
+    categories: BTreeMap<&'static str, Throughput>,
+}
+
+impl Timings {
+    fn add(&mut self, category: &'static str, bytes: u64, time: Duration) {
+        let throughput = self.categories.entry(category).or_default();
+        throughput.bytes += bytes;
+        throughput.commands += 1;
+        throughput.time += time;
+
+        self.total.bytes += bytes;
+        self.total.commands += 1;
+        self.total.time += time;
+    }
+}
+
+fn parse(
+    source: &str,
+    options: ParserOptions,
+    timings: &mut Timings,
+) -> Result<(), DefaultParseError> {
+    let lex = Lexer::new(source.chars());
+    let mut parser = DefaultParser::with_options(lex, Default::default(), options);
+
+    loop {
+        let start_byte = parser.pos().byte;
+        let start = Instant::now();
+        let cmd = parser.complete_command()?;
+        let time = start.elapsed();
+
+        match cmd {
+            Some(cmd) => {
+                let bytes = (parser.pos().byte - start_byte) as u64;
+                timings.add(category(&cmd), bytes, time);
+            }
+            None => {
+                // Any trailing comments and whitespace count towards the total
+                timings.total.bytes += (parser.pos().byte - start_byte) as u64;
+                timings.total.time += time;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Returns the category of a top level command.
+fn category(cmd: &TopLevelCommand<String>) -> &'static str {
+    let list = match cmd.0 {
+        Command::List(ref list) | Command::Job(ref list) => list,
+        Command::Unparsed(_) => return "unparsed",
+    };
+
+    if !list.rest.is_empty() {
+        return "and-or";
+    }
+
+    let cmd = match list.first {
+        ListableCommand::Pipe(_) => return "pipeline",
+        ListableCommand::Single(ref cmd) => cmd,
+    };
+
+    let compound = match *cmd {
+        PipeableCommand::Simple(_) => return "simple",
+        PipeableCommand::FunctionDef(..) => return "function",
+        PipeableCommand::Compound(ref compound) => compound,
+    };
+
+    match compound.kind {
+        CompoundCommandKind::Brace(_) => "brace",
+        CompoundCommandKind::Subshell(_) => "subshell",
+        CompoundCommandKind::While(_) => "while",
+        CompoundCommandKind::Until(_) => "until",
+        CompoundCommandKind::If { .. } => "if",
+        CompoundCommandKind::For { .. } => "for",
+        CompoundCommandKind::Case { .. } => "case",
+        CompoundCommandKind::Conditional(_) => "conditional",
+        CompoundCommandKind::Arithmetic(_) => "arithmetic",
+    }
+}
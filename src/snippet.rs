@@ -0,0 +1,267 @@
+//! Rendering of annotated source excerpts for errors and diagnostics.
+//!
+//! Excerpts follow the familiar layout of compiler errors: the location of the
+//! first annotation, followed by each annotated line (with its line number) and
+//! a caret underline beneath the annotated region of the line, e.g.
+//!
+//! ```text
+//!  --> 2:7
+//!   |
+//! 2 | echo $(bar
+//!   |       ^ unmatched `(`
+//! ```
+//!
+//! Annotations are located by the byte offsets of their spans alone, so spans
+//! recorded with `PositionTracking::ByteOffsets` can be rendered as well.
+
+use crate::analysis::Diagnostic;
+use crate::ast::Span;
+use crate::parse::{LineIndex, ParseError, SourcePos};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write;
+
+/// The number of columns a tab character is rendered as.
+const TAB_WIDTH: usize = 4;
+
+/// A labelled region of the source.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Annotation {
+    /// The annotated region of the source.
+    pub span: Span,
+    /// A short description shown next to the underline of the region (may be empty).
+    pub label: String,
+}
+
+impl Annotation {
+    /// Constructs a new annotation.
+    pub fn new<L: Into<String>>(span: Span, label: L) -> Self {
+        Annotation {
+            span,
+            label: label.into(),
+        }
+    }
+}
+
+/// Renders an excerpt of the source with a single annotated span.
+///
+/// ```
+/// use conch_parser::ast::Span;
+/// use conch_parser::parse::SourcePos;
+/// use conch_parser::snippet::render_snippet;
+///
+/// let span = Span {
+///     start: SourcePos::from_byte(5),
+///     end: SourcePos::from_byte(8),
+/// };
+///
+/// assert_eq!(
+///     render_snippet("echo foo", span, "an argument"),
+///     " --> 1:6\n  |\n1 | echo foo\n  |      ^^^ an argument\n"
+/// );
+/// ```
+pub fn render_snippet(source: &str, span: Span, label: &str) -> String {
+    render_annotations(source, &[Annotation::new(span, label)])
+}
+
+/// Renders an excerpt of the source with any number of annotated spans.
+///
+/// Every line touched by an annotation is shown in source order, and each
+/// annotation is underlined on a row of its own. Spans which cover multiple
+/// lines are underlined on each of them, with the label shown on the last one.
+/// Empty spans (or spans at the end of the source) are marked with a single
+/// caret. Runs of more than one unannotated line between annotated lines are
+/// elided as `...`.
+pub fn render_annotations(source: &str, annotations: &[Annotation]) -> String {
+    let index = LineIndex::new(source);
+
+    // The underlines of each line, as (start column, width, label)
+    let mut lines = BTreeMap::<usize, Vec<(usize, usize, &str)>>::new();
+    for annotation in annotations {
+        let start = clamp(source, annotation.span.start.byte);
+        let end = clamp(source, annotation.span.end.byte).max(start);
+
+        // A span which ends with a newline should not spill onto the next line
+        let last = if end > start && source[..end].ends_with('\n') {
+            end - 1
+        } else {
+            end
+        };
+
+        let first_line = index.pos(start).line;
+        let last_line = index.pos(last).line;
+        for line in first_line..=last_line {
+            let (line_start, text) = line_text(source, &index, line);
+            let from = if line == first_line {
+                start - line_start
+            } else {
+                0
+            };
+            let to = if line == last_line {
+                (end - line_start).min(text.len())
+            } else {
+                text.len()
+            };
+
+            let col = display_width(&text[..from]);
+            let width = display_width(&text[from..to.max(from)]).max(1);
+            let label = if line == last_line {
+                &*annotation.label
+            } else {
+                ""
+            };
+            lines.entry(line).or_default().push((col, width, label));
+        }
+    }
+
+    let mut out = String::new();
+    let gutter = lines.keys().last().map_or(1, |line| line.to_string().len());
+    let pad = " ".repeat(gutter);
+
+    if let Some(first) = annotations.first() {
+        let pos = index.pos(clamp(source, first.span.start.byte));
+        let _ = writeln!(out, "{} --> {}:{}", &pad[1..], pos.line, pos.col);
+    }
+    let _ = writeln!(out, "{} |", pad);
+
+    let mut prev_line = None;
+    for (&line, underlines) in &lines {
+        match prev_line {
+            Some(prev) if line == prev + 2 => {
+                write_line(&mut out, source, &index, line - 1, gutter)
+            }
+            Some(prev) if line > prev + 2 => out.push_str("...\n"),
+            _ => {}
+        }
+        prev_line = Some(line);
+
+        write_line(&mut out, source, &index, line, gutter);
+        for &(col, width, label) in underlines {
+            let _ = write!(out, "{} | {}{}", pad, " ".repeat(col), "^".repeat(width));
+            if !label.is_empty() {
+                let _ = write!(out, " {}", label);
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Renders a parse error along with an excerpt of the source annotating
+/// every position it refers to.
+///
+/// ```
+/// use conch_parser::lexer::Lexer;
+/// use conch_parser::parse::DefaultParser;
+/// use conch_parser::snippet::render_parse_error;
+///
+/// let source = "if true; then\n  echo foo\n";
+/// let err = DefaultParser::new(Lexer::new(source.chars()))
+///     .complete_command()
+///     .unwrap_err();
+///
+/// assert_eq!(
+///     render_parse_error(source, &err),
+///     "error: did not find `fi` keyword on line 3:1, in `if` command which starts on line 1:1
+///  --> 1:1
+///   |
+/// 1 | if true; then
+///   | ^^ `if` command starts here
+/// 2 |   echo foo
+/// 3 |
+///   | ^ expected `fi` here
+/// "
+/// );
+/// ```
+pub fn render_parse_error<E: fmt::Display>(source: &str, err: &ParseError<E>) -> String {
+    let mut out = format!("error: {}\n", err);
+    let annotations = parse_error_annotations(source, err);
+    if !annotations.is_empty() {
+        out.push_str(&render_annotations(source, &annotations));
+    }
+    out
+}
+
+/// Renders a diagnostic along with an excerpt of the source annotating the
+/// regions of the source it applies to.
+pub fn render_diagnostic(
+    source: &str,
+    diagnostic: &Diagnostic,
+    annotations: &[Annotation],
+) -> String {
+    let mut out = format!("{}\n", diagnostic);
+    if !annotations.is_empty() {
+        out.push_str(&render_annotations(source, annotations));
+    }
+    out
+}
+
+/// Returns annotations for all positions a parse error refers to.
+fn parse_error_annotations<E>(source: &str, err: &ParseError<E>) -> Vec<Annotation> {
+    let at = |pos: SourcePos, len: usize, label: String| {
+        let end = SourcePos {
+            byte: pos.byte + len,
+            col: pos.col + len,
+            ..pos
+        };
+        Annotation::new(Span { start: pos, end }, label)
+    };
+
+    match *err {
+        ParseError::BadFd(start, end) => vec![Annotation::new(
+            Span { start, end },
+            "not a valid file descriptor",
+        )],
+        ParseError::BadIdent(ref id, pos) => {
+            vec![at(pos, id.len(), "not a valid identifier".into())]
+        }
+        ParseError::BadSubst(ref t, pos) => vec![at(pos, t.len(), "invalid token".into())],
+        ParseError::Unmatched(ref t, pos) => vec![at(pos, t.len(), format!("unmatched `{}`", t))],
+        ParseError::IncompleteCmd(cmd, pos, kw, kw_pos) => vec![
+            at(pos, cmd.len(), format!("`{}` command starts here", cmd)),
+            at(kw_pos, 0, format!("expected `{}` here", kw)),
+        ],
+        ParseError::Unexpected(ref t, pos) => vec![at(pos, t.len(), "unexpected token".into())],
+        ParseError::UnexpectedEOF => {
+            let end = SourcePos::from_byte(source.len());
+            vec![at(end, 0, "unexpected end of input".into())]
+        }
+        ParseError::Extension(_, pos) => vec![at(pos, 0, "not part of the POSIX standard".into())],
+        ParseError::NulByte(pos) => vec![at(pos, 1, "NUL byte".into())],
+        ParseError::BadNumber(ref n, pos) => vec![at(pos, n.len(), "invalid number".into())],
+        ParseError::NumberOverflow(ref n, pos) => vec![at(pos, n.len(), "number too large".into())],
+        ParseError::Custom(_) => Vec::new(),
+    }
+}
+
+/// Clamps a byte offset to the source, and to the start of the character it is in.
+fn clamp(source: &str, byte: usize) -> usize {
+    let mut byte = byte.min(source.len());
+    while !source.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    byte
+}
+
+/// Returns the byte offset at which a line starts, and its text (without a newline).
+fn line_text<'a>(source: &'a str, index: &LineIndex, line: usize) -> (usize, &'a str) {
+    let start = index.line_start(line).unwrap_or(source.len());
+    let text = &source[start..];
+    (start, text.split('\n').next().unwrap_or(""))
+}
+
+fn write_line(out: &mut String, source: &str, index: &LineIndex, line: usize, gutter: usize) {
+    let (_, text) = line_text(source, index, line);
+    let text = text.replace('\t', &" ".repeat(TAB_WIDTH));
+    let line = format!("{:>width$} | {}", line, text, width = gutter);
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+/// Returns the number of columns the text occupies when rendered.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
@@ -0,0 +1,474 @@
+//! Utilities for writing tests against the parser.
+//!
+//! This module is only available when the `testing` feature is enabled, and
+//! is meant to be used by the test suites of crates which build on this one.
+//!
+//! Since the default AST does not retain any whitespace, comments, or how
+//! commands were separated (e.g. `;` vs. a newline), two snippets which only
+//! differ in such trivia will produce identical ASTs. The `assert_parses_same!`
+//! macro takes advantage of this to compare two snippets structurally.
+//! The one exception is the location of pipe operators (see `ast::PipeSegment`),
+//! so snippets containing pipelines only parse the same if each `|` appears at
+//! the same position in both:
+//!
+//! ```
+//! # #[macro_use] extern crate conch_parser;
+//! # fn main() {
+//! assert_parses_same!("foo  bar;baz", "foo bar # comment\nbaz");
+//! # }
+//! ```
+//!
+//! Inputs found by fuzzing can be kept as a regression corpus (one input per
+//! file), and replayed against the invariants the parser is expected to uphold
+//! with `load_corpus` and `replay`.
+
+use crate::analysis::format_diff::erase_locations;
+use crate::ast::builder::{Builder, BuilderFactory};
+use crate::ast::printer::ToShellString;
+use crate::ast::TopLevelCommand;
+use crate::lexer::Lexer;
+use crate::parse::{DefaultParser, ParseError, Parser};
+use std::any::Any;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use void::Void;
+
+/// Parses the provided source with the `DefaultParser` into a list of commands.
+pub fn parse(source: &str) -> Result<Vec<TopLevelCommand<String>>, ParseError<Void>> {
+    DefaultParser::new(Lexer::new(source.chars()))
+        .into_iter()
+        .collect()
+}
+
+/// Renders a line based diff between the pretty printed debug representations
+/// of two values. Lines only present in the `left` value are prefixed with `-`,
+/// lines only present in the `right` value are prefixed with `+`.
+///
+/// Returns `None` if both values render identically.
+pub fn debug_diff<L: fmt::Debug, R: fmt::Debug>(left: &L, right: &R) -> Option<String> {
+    let left = format!("{:#?}", left);
+    let right = format!("{:#?}", right);
+    if left == right {
+        return None;
+    }
+
+    let left: Vec<_> = left.lines().collect();
+    let right: Vec<_> = right.lines().collect();
+
+    // Longest common subsequence table, lcs[i][j] holds the length
+    // of the LCS between `left[i..]` and `right[j..]`.
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            writeln!(diff, "  {}", left[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if i < left.len() && (j == right.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            writeln!(diff, "- {}", left[i]).unwrap();
+            i += 1;
+        } else {
+            writeln!(diff, "+ {}", right[j]).unwrap();
+            j += 1;
+        }
+    }
+
+    Some(diff)
+}
+
+/// Parses both snippets and panics with a structural diff of the resulting ASTs
+/// if they differ, or if either snippet fails to parse.
+///
+/// Prefer using the `assert_parses_same!` macro over calling this directly.
+#[track_caller]
+pub fn assert_parses_same(left: &str, right: &str, msg: Option<fmt::Arguments<'_>>) {
+    let context = msg.map(|m| format!(": {}", m)).unwrap_or_default();
+
+    let left_cmds = parse_or_panic("left", left, &context);
+    let right_cmds = parse_or_panic("right", right, &context);
+
+    if let Some(diff) = debug_diff(&left_cmds, &right_cmds) {
+        panic!(
+            "snippets do not parse the same{}\n left: {:?}\nright: {:?}\n\n{}",
+            context, left, right, diff
+        );
+    }
+}
+
+#[track_caller]
+fn parse_or_panic(which: &str, source: &str, context: &str) -> Vec<TopLevelCommand<String>> {
+    match parse(source) {
+        Ok(cmds) => cmds,
+        Err(e) => panic!(
+            "failed to parse {} snippet {:?}: {}{}",
+            which, source, e, context
+        ),
+    }
+}
+
+/// Asserts that two shell snippets parse to the same AST (using the
+/// `DefaultParser`), ignoring any differences in whitespace, comments, and
+/// command separators (except where they shift the position of a pipe
+/// operator, since those locations are retained in the AST).
+///
+/// On failure, the panic message includes a structural diff of both ASTs.
+/// Like `assert_eq!`, an optional custom message can be provided.
+///
+/// Only available with the `testing` feature.
+#[macro_export]
+macro_rules! assert_parses_same {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::testing::assert_parses_same($left, $right, None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::testing::assert_parses_same($left, $right, Some(format_args!($($arg)+)))
+    };
+}
+
+/// A snippet of shell source exercising a construct which is notoriously
+/// tricky to parse, along with the expected shape of its parse.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CorpusCase {
+    /// A short, unique name describing the case.
+    pub name: &'static str,
+    /// The shell source of the case.
+    pub source: &'static str,
+    /// The number of top level commands the source parses into.
+    pub commands: usize,
+}
+
+/// A corpus of deeply nested constructs which shell parsers frequently get
+/// wrong: heredocs within command substitutions (and vice versa), compound
+/// commands within substitutions, and quotes which span substitution boundaries.
+///
+/// Every case parses successfully with the `DefaultParser` into the specified
+/// number of commands, which makes the corpus suitable for regression testing
+/// parsers (or builders) which build on this crate.
+pub const NESTED_CORPUS: &[CorpusCase] = &[
+    CorpusCase {
+        name: "heredoc-in-command-subst",
+        source: "echo $(cat <<EOF\nhi )\nEOF\n)\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "heredoc-in-command-subst-in-heredoc",
+        source: "cat <<OUTER\nx $(cat <<INNER\ninner $y\nINNER\n) z\nOUTER\necho after\n",
+        commands: 2,
+    },
+    CorpusCase {
+        name: "quoted-heredoc-in-command-subst",
+        source: "x=$(cat <<'EOF'\n$(not a subst) `nor this`\nEOF\n)\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "command-subst-closing-paren-in-heredoc-body",
+        source: "cat <<EOF\n$(echo \")\")\nEOF\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "heredocs-nested-three-deep",
+        source: "f() {\n  cat <<A\n$(cat <<B\n$(cat <<C\nc\nC\n)\nB\n)\nA\n}\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "multiple-heredocs-on-one-line",
+        source: "cat <<A; cat <<B | $(cat <<C\nc\nC\n)\na\nA\nb\nB\n",
+        commands: 2,
+    },
+    CorpusCase {
+        name: "case-in-command-subst",
+        source: "echo $(case $x in a) echo a;; b|c) echo b;; esac)\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "case-with-parens-in-command-subst",
+        source: "echo \"$(case $x in (a) echo ')';; (*) echo \"(\";; esac)\"\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "double-quotes-across-command-subst",
+        source: "echo \"a $(echo \"b $(echo \"c\") d\") e\"\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "double-quotes-across-parameter-subst",
+        source: "echo \"${x:-\"$(echo \"}\")\"}\"\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "single-quoted-paren-in-command-subst",
+        source: "echo $(echo ')' \"(\")\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "comment-with-paren-in-command-subst",
+        source: "echo $(# a comment )\necho hi)\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "nested-backticks",
+        source: "echo `echo \\`echo hi\\``\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "subshell-at-start-of-command-subst",
+        source: "echo $((echo a) | (echo b))\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "arithmetic-with-parens",
+        source: "echo $(( (1 + 2) * (3) ))\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "loops-in-command-subst",
+        source: "x=$(for i in 1 2; do while false; do :; done; echo $i; done)\n",
+        commands: 1,
+    },
+    CorpusCase {
+        name: "function-with-heredoc-in-pipeline",
+        source: "f() { cat <<EOF | sed 's/)/(/'\n$(g)\nEOF\n}\nf\n",
+        commands: 2,
+    },
+];
+
+/// A regression input loaded from a corpus directory, see `load_corpus`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RegressionCase {
+    /// The file name of the input within the corpus.
+    pub name: String,
+    /// The contents of the input, with any invalid UTF-8 replaced by U+FFFD.
+    pub source: String,
+}
+
+/// Loads every file of a directory (e.g. the inputs found by a fuzzer) as a
+/// regression input, in order of their names.
+///
+/// Subdirectories and hidden files (e.g. `.gitignore`) are skipped.
+pub fn load_corpus<P: AsRef<Path>>(dir: P) -> io::Result<Vec<RegressionCase>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let source = String::from_utf8_lossy(&fs::read(entry.path())?).into_owned();
+        cases.push(RegressionCase { name, source });
+    }
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// An invariant which a regression input failed to uphold, see `check_invariants`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Violation {
+    /// Parsing (or printing) panicked with the specified message.
+    Panic(String),
+    /// The source parsed successfully, but the printed commands failed to parse.
+    ReprintFailed {
+        /// The printed commands.
+        printed: String,
+        /// The error encountered while parsing them.
+        error: String,
+    },
+    /// The source parsed successfully, but the printed commands parsed differently.
+    ReprintChanged {
+        /// The printed commands.
+        printed: String,
+        /// A structural diff between the original and reparsed commands (see `debug_diff`).
+        diff: String,
+    },
+    /// The provided builder did not parse the source the same way the default builder did.
+    BuilderMismatch(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Violation::Panic(ref msg) => write!(fmt, "panicked: {}", msg),
+            Violation::ReprintFailed {
+                ref printed,
+                ref error,
+            } => write!(fmt, "failed to reparse {:?}: {}", printed, error),
+            Violation::ReprintChanged {
+                ref printed,
+                ref diff,
+            } => write!(fmt, "{:?} parses differently:\n{}", printed, diff),
+            Violation::BuilderMismatch(ref msg) => fmt.write_str(msg),
+        }
+    }
+}
+
+/// A regression input which failed to uphold an invariant, see `replay`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReplayFailure {
+    /// The name of the regression input.
+    pub name: String,
+    /// The invariant which was violated.
+    pub violation: Violation,
+}
+
+impl fmt::Display for ReplayFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}: {}", self.name, self.violation)
+    }
+}
+
+/// Runs a closure, converting any panic into a `Violation`.
+fn catch_panic<T, F: FnOnce() -> T>(f: F) -> Result<T, Violation> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload: Box<dyn Any + Send>| {
+        let msg = match payload.downcast::<String>() {
+            Ok(msg) => *msg,
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map_or_else(|| String::from("<unknown>"), |msg| String::from(*msg)),
+        };
+        Violation::Panic(msg)
+    })
+}
+
+/// Checks that a source upholds the invariants which the `DefaultParser` is
+/// expected to maintain for any input, along with a builder created by the
+/// provided factory:
+///
+/// * Neither parser panics, regardless of whether parsing succeeds.
+/// * If the source parses successfully, printing the commands (see
+///   `ast::printer`) yields a source which parses back into the same commands,
+///   disregarding any locations recorded within them.
+/// * Parsing with the provided builder succeeds (yielding the same number of
+///   commands) exactly when the `DefaultParser` does, or fails with an error
+///   of the same kind at the same location.
+///
+/// Note that any panics are still reported by the panic hook (e.g. printed to stderr).
+pub fn check_invariants<F>(source: &str, factory: &F) -> Result<(), Violation>
+where
+    F: BuilderFactory + ?Sized,
+    <F::Builder as Builder>::Error: fmt::Debug,
+{
+    let default = catch_panic(|| parse(source))?;
+    let custom = catch_panic(|| {
+        Parser::with_builder(Lexer::new(source.chars()), factory.new_builder())
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let mismatch = match (&default, custom) {
+        (Ok(expected), Ok(cmds)) if cmds.len() != expected.len() => Some(format!(
+            "the builder built {} commands instead of {}",
+            cmds.len(),
+            expected.len()
+        )),
+        (Ok(_), Err(e)) => Some(format!("the builder failed to parse the source: {:?}", e)),
+        (Err(expected), Ok(_)) => Some(format!(
+            "the builder parsed the source, which should fail with: {}",
+            expected
+        )),
+        (Err(expected), Err(e)) if (e.kind(), e.span()) != (expected.kind(), expected.span()) => {
+            Some(format!(
+                "the builder failed with {:?} instead of: {}",
+                e, expected
+            ))
+        }
+        _ => None,
+    };
+    if let Some(msg) = mismatch {
+        return Err(Violation::BuilderMismatch(msg));
+    }
+
+    match default {
+        Ok(cmds) => check_round_trip(&cmds),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Checks that printing the provided commands (see `ast::printer`) yields a
+/// source which the `DefaultParser` parses back into the same commands,
+/// disregarding any locations recorded within them.
+///
+/// Combined with the `Arbitrary` implementations of the AST (or the strategies
+/// of `ast::strategy`) provided by the `proptest` feature, this allows property
+/// testing the printer and parser against each other with arbitrary ASTs,
+/// e.g. via `prop_assert!(check_round_trip(&[cmd]).is_ok())`.
+///
+/// ```
+/// use conch_parser::testing::{check_round_trip, parse};
+///
+/// let cmds = parse("foo | bar && baz\nif a; then b; fi").unwrap();
+/// assert_eq!(check_round_trip(&cmds), Ok(()));
+/// ```
+pub fn check_round_trip(cmds: &[TopLevelCommand<String>]) -> Result<(), Violation> {
+    let printed = catch_panic(|| cmds.to_shell_string())?;
+    let mut reparsed = match catch_panic(|| parse(&printed))? {
+        Ok(reparsed) => reparsed,
+        Err(e) => {
+            return Err(Violation::ReprintFailed {
+                printed,
+                error: e.to_string(),
+            })
+        }
+    };
+
+    let mut cmds = cmds.to_vec();
+    erase_locations(&mut cmds);
+    erase_locations(&mut reparsed);
+    match debug_diff(&cmds, &reparsed) {
+        Some(diff) => Err(Violation::ReprintChanged { printed, diff }),
+        None => Ok(()),
+    }
+}
+
+/// Panics with the violation reported by `check_round_trip` if the provided
+/// commands do not parse back into the same commands after printing them.
+#[track_caller]
+pub fn assert_round_trip(cmds: &[TopLevelCommand<String>]) {
+    if let Err(violation) = check_round_trip(cmds) {
+        panic!("commands do not round trip: {}", violation);
+    }
+}
+
+/// Checks every regression input with `check_invariants`, returning those
+/// which failed in the order they were provided.
+///
+/// ```no_run
+/// use conch_parser::ast::builder::RcBuilder;
+/// use conch_parser::testing::{load_corpus, replay};
+///
+/// let corpus = load_corpus("fuzz/regressions").unwrap();
+/// for failure in replay(&corpus, &RcBuilder::new) {
+///     println!("{}", failure);
+/// }
+/// ```
+pub fn replay<F>(cases: &[RegressionCase], factory: &F) -> Vec<ReplayFailure>
+where
+    F: BuilderFactory + ?Sized,
+    <F::Builder as Builder>::Error: fmt::Debug,
+{
+    cases
+        .iter()
+        .filter_map(|case| {
+            check_invariants(&case.source, factory)
+                .err()
+                .map(|violation| ReplayFailure {
+                    name: case.name.clone(),
+                    violation,
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,456 @@
+//! Checking the syntax of a script without building an AST, similar to `sh -n`.
+//!
+//! Validation accepts and rejects sources the same way the reference shell of
+//! each dialect does when invoked with `-n`: `dash` for `Dialect::Posix`, and
+//! `bash` for `Dialect::Bash`. The exceptions are recorded in `DIVERGENCES`,
+//! which is kept up to date by running the `CONFORMANCE_CORPUS` through both
+//! this module and the reference shells (see `conformance`, and the
+//! `conformance` example which invokes the shells directly).
+
+use crate::ast::builder::EmptyBuilder;
+use crate::lexer::Lexer;
+use crate::parse::{Dialect, ParseError, Parser, ParserOptions, ReservedWords};
+use void::Void;
+
+/// Checks that the source is a syntactically valid script in the given dialect.
+///
+/// ```
+/// use conch_parser::parse::Dialect;
+/// use conch_parser::validate::validate;
+///
+/// assert!(validate("for x in a b; do echo $x; done", Dialect::Posix).is_ok());
+/// assert!(validate("[[ -n $x ]]", Dialect::Bash).is_ok());
+///
+/// // Closing reserved words are not treated as command names
+/// assert!(validate("echo foo; fi", Dialect::Bash).is_err());
+/// ```
+///
+/// Parsing stops at the first error, which is returned.
+pub fn validate(source: &str, dialect: Dialect) -> Result<(), ParseError<Void>> {
+    let options = ParserOptions {
+        dialect,
+        reserved_words: ReservedWords::Strict,
+        ..ParserOptions::default()
+    };
+    let mut parser = Parser::with_options(Lexer::new(source.chars()), EmptyBuilder::new(), options);
+
+    while parser.complete_command()?.is_some() {}
+    Ok(())
+}
+
+/// A source which `validate` and the reference shell of a dialect disagree on.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Divergence {
+    /// The source in question.
+    pub source: &'static str,
+    /// The dialect the source was validated in.
+    pub dialect: Dialect,
+    /// Whether the reference shell accepts the source (and thus `validate` rejects it).
+    pub shell_accepts: bool,
+    /// Why the verdicts differ.
+    pub reason: &'static str,
+}
+
+/// The reason given by `conformance` for divergences which are not listed in `DIVERGENCES`.
+pub const UNEXPLAINED: &str = "unexplained";
+
+const EXTENSION: &str =
+    "bash extensions are rejected by the POSIX dialect, dash treats them as plain words";
+const ARITHMETIC: &str = "arithmetic is parsed eagerly, shells only parse it when expanded";
+const SUBSTITUTION: &str =
+    "parameter substitutions are parsed eagerly, shells only parse them when expanded";
+const UNSUPPORTED: &str = "not supported by the parser";
+const FUNCTION_BODY: &str = "function bodies must be compound commands in the POSIX grammar";
+const ARRAY: &str = "array assignments are not distinguished from simple words";
+const BANG: &str = "repeated `!` are accepted in the POSIX dialect as they are by bash";
+
+/// Sources exercising the syntax of both dialects, which `validate` is
+/// continuously tested against the reference shells with.
+pub const CONFORMANCE_CORPUS: &[&str] = &[
+    // Valid scripts
+    "echo foo",
+    "foo && bar || baz",
+    "! foo | bar",
+    "{ foo; }",
+    "(foo)",
+    "if a; then b; elif c; then d; else e; fi",
+    "for x in a b; do echo $x; done",
+    "for x; do :; done",
+    "for x do :; done",
+    "for x in; do :; done",
+    "for x in a b\ndo\n:\ndone",
+    "while a; do b; done",
+    "until a; do b; done",
+    "while :; do break; done",
+    "case $x in a|b) c;; *) d;; esac",
+    "case x in esac",
+    "case x in (a) :;; esac",
+    "case x in a) :; esac",
+    "case x in a) ;; b) ;; esac",
+    "case a in a) echo ;; b) esac",
+    "f() { :; }",
+    "f() ( foo )",
+    "f() if a; then b; fi",
+    "foo() {\n:\n}",
+    "echo ${x:-y} $(foo) `bar` $((1+2))",
+    "echo ${x-${y-z}} ${x:?} ${#x} ${10} $10 ${#}",
+    "x=$(case a in a) b;; esac)",
+    "echo $( (foo) )",
+    "cat <<EOF\nfoo\nEOF\n",
+    "cat <<-EOF\n\tfoo\n\tEOF\n",
+    "cat << EOF\nEOF",
+    "cat <<EOF",
+    "cat <<EOF\nfoo",
+    "echo 'a' \"b\" \\c",
+    "echo a\\\nb",
+    "echo foo # comment",
+    "x=1 y=2",
+    "a=",
+    "a=1 b",
+    "exec 3>&1 2>&-",
+    "foo 1>&2 3<>file 4>|x",
+    "{ foo; } > out",
+    "(foo) 2>/dev/null",
+    "if a; then b; fi > out",
+    "foo &",
+    "foo & bar",
+    "echo {a,b} } { ]]",
+    "echo do done",
+    "foo do",
+    "echo ;",
+    "echo $((1?2:3)) $((x=1,y=2)) $((010 + 0x1f)) $(( (1) ))",
+    // Invalid scripts
+    "if true; then",
+    "if; then :; fi",
+    "if then fi",
+    "while do :; done",
+    "echo (",
+    "foo |",
+    "foo &&",
+    "echo && && b",
+    "foo ;;",
+    "foo;;bar",
+    "; foo",
+    "& foo",
+    "echo &;",
+    "echo ; ;",
+    ")",
+    "{",
+    "}",
+    "fi",
+    "done",
+    "esac",
+    "then",
+    "in",
+    "do",
+    "echo foo; fi",
+    "{ foo }",
+    "{ }",
+    "( )",
+    "case x in a) b",
+    "echo $(foo",
+    "echo ${x",
+    "echo $((",
+    "echo $(( 1 +",
+    "echo 'a",
+    "echo \"a",
+    "echo `foo",
+    "echo \\",
+    "'a",
+    "\"",
+    "echo >",
+    "echo 2>&",
+    "foo | ! bar",
+    "f()",
+    "(( a",
+    // Bash extensions
+    "[[ -n $x ]]",
+    "[[ a",
+    "[[ -f $x && ( $y == *.sh || ! -d $y ) ]]",
+    "[[ $x =~ ^(a|b)+$ ]] >out",
+    "[[ a < b\n]]",
+    "[[ ! ]]",
+    "(( x++ ))",
+    "echo a |& b",
+    "echo $(( 16#ff )) $((2#1010))",
+    "echo $(( a[1] ))",
+    "echo $((9999999999999999999999))",
+    "echo ${x/a/b} ${x:0:1} ${x,,} ${x^^}",
+    "function f { :; }",
+    "function f() { :; }",
+    "function",
+    "function f",
+    "select x in a b; do :; done",
+    "a=(1 2 3)",
+    "! ! foo",
+    "!",
+    "f() foo",
+    "for 1 in a; do :; done",
+    "for x in a; { :; }",
+    "for ((i=0;i<3;i++)); do :; done",
+    "cat <(foo)",
+    "coproc foo",
+    "time foo",
+    "foo &> bar",
+    "echo $'ab' $\"ab\"",
+    // Expansions which shells only parse when expanded
+    "echo $((1+))",
+    "$(( 1 + ))",
+    "echo $(())",
+    "echo $(( ))",
+    "echo $((08))",
+    "echo $((1)) $[1]",
+    "echo ${}",
+    "echo ${x!}",
+    "echo ${!x}",
+    "echo ${x[1]}",
+];
+
+/// All sources in `CONFORMANCE_CORPUS` which `validate` and the reference
+/// shells (`dash` 0.5 and `bash` 5.2) are known to disagree on.
+pub const DIVERGENCES: &[Divergence] = &[
+    Divergence {
+        source: "[[ -n $x ]]",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "[[ a",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "[[ a < b\n]]",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "[[ ! ]]",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "(( x++ ))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "echo $(( 16#ff )) $((2#1010))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "echo $(( a[1] ))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "echo $((9999999999999999999999))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "function",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "function f",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: EXTENSION,
+    },
+    Divergence {
+        source: "select x in a b; do :; done",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: UNSUPPORTED,
+    },
+    Divergence {
+        source: "a=(1 2 3)",
+        dialect: Dialect::Posix,
+        shell_accepts: false,
+        reason: ARRAY,
+    },
+    Divergence {
+        source: "! ! foo",
+        dialect: Dialect::Posix,
+        shell_accepts: false,
+        reason: BANG,
+    },
+    Divergence {
+        source: "!",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: UNSUPPORTED,
+    },
+    Divergence {
+        source: "f() foo",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: FUNCTION_BODY,
+    },
+    Divergence {
+        source: "for 1 in a; do :; done",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: UNSUPPORTED,
+    },
+    Divergence {
+        source: "for x in a; { :; }",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: UNSUPPORTED,
+    },
+    Divergence {
+        source: "for ((i=0;i<3;i++)); do :; done",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: UNSUPPORTED,
+    },
+    Divergence {
+        source: "echo $((1+))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "echo $((1+))",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "$(( 1 + ))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "$(( 1 + ))",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "echo $((08))",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "echo $((08))",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: ARITHMETIC,
+    },
+    Divergence {
+        source: "echo ${}",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${}",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${x!}",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${x!}",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${!x}",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${!x}",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${x[1]}",
+        dialect: Dialect::Posix,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+    Divergence {
+        source: "echo ${x[1]}",
+        dialect: Dialect::Bash,
+        shell_accepts: true,
+        reason: SUBSTITUTION,
+    },
+];
+
+/// Validates every source of the `CONFORMANCE_CORPUS` in both dialects, and
+/// returns those for which `validate` disagrees with the reference shell,
+/// in the same order as `DIVERGENCES`.
+///
+/// The reference shell is consulted through `shell_accepts`, which should
+/// report whether the shell of the given dialect accepts the source (e.g. by
+/// running `dash -n` or `bash -n` on it). Divergences which have not been
+/// published in `DIVERGENCES` are returned with an `UNEXPLAINED` reason.
+///
+/// ```
+/// use conch_parser::validate::{conformance, validate, DIVERGENCES};
+///
+/// // A "shell" which agrees with validation everywhere except on the published divergences
+/// let divergences = conformance(|source, dialect| {
+///     let known = DIVERGENCES.iter().find(|d| d.source == source && d.dialect == dialect);
+///     match known {
+///         Some(divergence) => divergence.shell_accepts,
+///         None => validate(source, dialect).is_ok(),
+///     }
+/// });
+///
+/// assert_eq!(divergences, DIVERGENCES);
+/// ```
+pub fn conformance<F>(mut shell_accepts: F) -> Vec<Divergence>
+where
+    F: FnMut(&str, Dialect) -> bool,
+{
+    let mut divergences = Vec::new();
+    for &source in CONFORMANCE_CORPUS {
+        for &dialect in &[Dialect::Posix, Dialect::Bash] {
+            let accepts = shell_accepts(source, dialect);
+            if accepts == validate(source, dialect).is_ok() {
+                continue;
+            }
+
+            let reason = DIVERGENCES
+                .iter()
+                .find(|d| d.source == source && d.dialect == dialect && d.shell_accepts == accepts)
+                .map_or(UNEXPLAINED, |d| d.reason);
+
+            divergences.push(Divergence {
+                source,
+                dialect,
+                shell_accepts: accepts,
+                reason,
+            });
+        }
+    }
+
+    divergences
+}
@@ -0,0 +1,125 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::TopLevelCommand;
+use conch_parser::parse::AliasProvider;
+use std::collections::HashMap;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn aliases() -> HashMap<String, String> {
+    let mut aliases = HashMap::new();
+    for &(name, value) in &[
+        ("ll", "ls -l"),
+        ("ls", "ls -F"),
+        ("rec", "rec x; rec"),
+        ("s", "sudo "),
+        ("n", "nice "),
+        ("begin", "{"),
+        ("end", "}"),
+        ("two", "echo 1; echo 2"),
+        ("empty", ""),
+    ] {
+        aliases.insert(name.to_owned(), value.to_owned());
+    }
+    aliases
+}
+
+fn parse_with<A: AliasProvider + Send + Sync + 'static>(
+    aliases: A,
+    src: &str,
+) -> Vec<TopLevelCommand<String>> {
+    let mut p = make_parser(src);
+    p.set_alias_provider(aliases);
+    p.parse_all().unwrap()
+}
+
+fn assert_expands(src: &str, expanded: &str) {
+    let expected = make_parser(expanded).parse_all().unwrap();
+    assert_eq!(
+        parse_with(aliases(), src).to_shell_string(),
+        expected.to_shell_string(),
+        "{}",
+        src
+    );
+}
+
+#[test]
+fn test_alias_expansion_in_command_position() {
+    let cases = [
+        ("ll foo", "ls -F -l foo"),
+        ("ll | ll", "ls -F -l | ls -F -l"),
+        ("true && ll", "true && ls -F -l"),
+        (
+            "if ll; then two; fi",
+            "if ls -F -l; then echo 1; echo 2; fi",
+        ),
+        ("two; two", "echo 1; echo 2; echo 1; echo 2"),
+        ("empty echo hi", "echo hi"),
+        ("x=1 ll", "x=1 ls -F -l"),
+        (">out ll", ">out ls -F -l"),
+        ("echo $(ll)", "echo $(ls -F -l)"),
+    ];
+    for &(src, expanded) in &cases {
+        assert_expands(src, expanded);
+    }
+}
+
+#[test]
+fn test_alias_not_expanded_outside_command_position() {
+    let unchanged = [
+        "echo ll",
+        "'ll'",
+        "\"ll\"",
+        "\\ll",
+        "ll=1",
+        "lll",
+        "for ll in a; do :; done",
+        "case ll in ll) :;; esac",
+    ];
+    for src in &unchanged {
+        assert_expands(src, src);
+    }
+}
+
+#[test]
+fn test_alias_recursion_is_prevented() {
+    assert_expands("ls", "ls -F");
+    assert_expands("ll", "ls -F -l");
+    assert_expands("rec; rec", "rec x; rec; rec x; rec");
+}
+
+#[test]
+fn test_alias_ending_in_blank_checks_next_word() {
+    assert_expands("s ll", "sudo ls -l");
+    assert_expands("s n ll foo", "sudo nice ls -l foo");
+    assert_expands("s s ll", "sudo sudo ls -l");
+    assert_expands("s foo ll", "sudo foo ll");
+    assert_expands("s; ll", "sudo; ls -F -l");
+}
+
+#[test]
+fn test_alias_may_change_grammar() {
+    assert_expands("begin ll; end", "{ ls -F -l; }");
+    assert_expands("begin two; end | cat", "{ echo 1; echo 2; } | cat");
+}
+
+#[test]
+fn test_alias_provider_closure_and_removal() {
+    let provider = |name: &str| match name {
+        "hi" => Some("echo hello".to_owned()),
+        _ => None,
+    };
+    let expected = make_parser("echo hello; bye").parse_all().unwrap();
+    assert_eq!(parse_with(provider, "hi; bye"), expected);
+
+    let mut p = make_parser("ll; ll");
+    p.set_alias_provider(aliases());
+    assert_eq!(
+        p.complete_command().unwrap(),
+        Some(cmd_args("ls", &["-F", "-l"]))
+    );
+    assert!(p.remove_alias_provider().is_some());
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("ll")));
+    assert!(p.remove_alias_provider().is_none());
+}
@@ -0,0 +1,78 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::arith::{check, DYNAMIC_EVAL, RECURSIVE_EVAL};
+use conch_parser::analysis::{Diagnostic, Severity};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn lint(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+#[test]
+fn test_arith_dynamic_eval() {
+    let diags = lint("x='y + 1'\necho $((x * 2)) \"$(( $x ))\"");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, DYNAMIC_EVAL);
+    assert_eq!(diags[0].severity, Severity::Info);
+    assert_eq!(
+        diags[0].message,
+        "`x` is assigned `y + 1`, which will be evaluated as an \
+         arithmetic expression when referenced within arithmetic"
+    );
+
+    assert_eq!(codes(lint("n=5; echo $(( n + 1 ))")), Vec::<&str>::new());
+    assert_eq!(codes(lint("n=; echo $(( n + 1 ))")), Vec::<&str>::new());
+    assert_eq!(codes(lint("n=a; n=-3; echo $(( n ))")), Vec::<&str>::new());
+    assert_eq!(
+        codes(lint("n=a; n=$(cmd); echo $(( n ))")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(codes(lint("n=a cmd; echo $(( n ))")), Vec::<&str>::new());
+    assert_eq!(codes(lint("n=a; echo $n")), Vec::<&str>::new());
+}
+
+#[test]
+fn test_arith_dynamic_eval_nested_words() {
+    assert_eq!(
+        codes(lint("i=j; echo ${foo:-$(( arr[i] ))}")),
+        vec![DYNAMIC_EVAL]
+    );
+    assert_eq!(
+        codes(lint("i=j; for x in $((i)); do :; done")),
+        vec![DYNAMIC_EVAL]
+    );
+    assert_eq!(codes(lint("i=j; cat >$((i++))")), vec![DYNAMIC_EVAL]);
+    assert_eq!(
+        codes(lint("i=j\nif true; then : $((i)); fi")),
+        vec![DYNAMIC_EVAL]
+    );
+}
+
+#[test]
+fn test_arith_dynamic_eval_arithmetic_commands() {
+    assert_eq!(codes(lint("x='y+1'; (( x * 2 ))")), vec![DYNAMIC_EVAL]);
+
+    assert_eq!(
+        codes(lint("i=j; while (( i < 3 )) >out; do :; done")),
+        vec![DYNAMIC_EVAL]
+    );
+    assert_eq!(codes(lint("x='x+1'; ((x++))")), vec![RECURSIVE_EVAL]);
+    assert_eq!(codes(lint("n=5; (( n * 2 ))")), Vec::<&str>::new());
+}
+
+#[test]
+fn test_arith_recursive_eval() {
+    let diags = lint("a='b + 1'; b='c * a'; c=2; echo $(( a ))");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, RECURSIVE_EVAL);
+    assert_eq!(diags[0].severity, Severity::Error);
+    assert!(diags[0].message.contains("(a -> b -> a)"));
+
+    assert_eq!(codes(lint("x='x + 1'; echo $((x))")), vec![RECURSIVE_EVAL]);
+    assert_eq!(
+        codes(lint("x=y; y=z; z=y; echo $((x))")),
+        vec![DYNAMIC_EVAL],
+        "only cycles through the referenced variable itself are reported"
+    );
+}
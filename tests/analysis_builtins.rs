@@ -0,0 +1,106 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::builtins::{
+    special_commands, BuiltinKind, Operand, SpecialCommand, BUILTINS,
+};
+use conch_parser::ast::{Command, ListableCommand, Parameter, PipeableCommand};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn classify(src: &str) -> Option<(BuiltinKind, String, Vec<String>)> {
+    let cmds = parse(src);
+    let simple = match cmds[0].0 {
+        Command::List(ref list) => match list.first {
+            ListableCommand::Single(PipeableCommand::Simple(ref cmd)) => cmd,
+            _ => panic!("not a simple command: {}", src),
+        },
+        _ => panic!("not a simple command: {}", src),
+    };
+
+    SpecialCommand::classify(simple).map(|special| {
+        let args = special.args.iter().map(|w| format!("{:?}", w)).collect();
+        (special.kind, format!("{:?}", special.operand), args)
+    })
+}
+
+#[test]
+fn test_builtins_schema_lookup() {
+    for &(name, kind) in BUILTINS {
+        assert_eq!(BuiltinKind::from_name(name), Some(kind));
+    }
+    assert_eq!(BuiltinKind::from_name("source"), Some(BuiltinKind::Source));
+    assert_eq!(BuiltinKind::from_name("."), Some(BuiltinKind::Source));
+    assert_eq!(BuiltinKind::from_name("echo"), None);
+    assert!(!BuiltinKind::Null.takes_number());
+    assert!(BuiltinKind::Shift.takes_number());
+}
+
+#[test]
+fn test_builtins_numeric_operands() {
+    let cases = [
+        ("break", BuiltinKind::Break, Operand::Omitted),
+        ("continue 2", BuiltinKind::Continue, Operand::Number(2)),
+        ("return -1", BuiltinKind::Return, Operand::Number(-1)),
+        ("exit '3'", BuiltinKind::Exit, Operand::Number(3)),
+        ("shift 10", BuiltinKind::Shift, Operand::Number(10)),
+    ];
+
+    for &(src, kind, ref operand) in &cases {
+        let expected = (kind, format!("{:?}", operand), vec![]);
+        assert_eq!(classify(src), Some(expected), "{}", src);
+    }
+}
+
+#[test]
+fn test_builtins_dynamic_and_invalid_operands() {
+    let cmds = parse("shift $n; exit foo bar");
+    let special = special_commands(&cmds);
+    assert_eq!(special.len(), 2);
+
+    assert_eq!(special[0].kind, BuiltinKind::Shift);
+    assert_eq!(
+        special[0].operand,
+        Operand::Dynamic(&word_param(Parameter::Var(String::from("n"))))
+    );
+
+    assert_eq!(special[1].kind, BuiltinKind::Exit);
+    assert_eq!(special[1].operand, Operand::Invalid(&word("foo")));
+    assert_eq!(special[1].args, vec![&word("bar")]);
+}
+
+#[test]
+fn test_builtins_arguments_of_non_numeric_builtins() {
+    let cmds = parse(". ./lib.sh a; source b; : 1 2");
+    let special = special_commands(&cmds);
+    let kinds = special.iter().map(|s| s.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![BuiltinKind::Source, BuiltinKind::Source, BuiltinKind::Null]
+    );
+
+    assert!(special.iter().all(|s| s.operand == Operand::Omitted));
+    assert_eq!(special[0].args, vec![&word("./lib.sh"), &word("a")]);
+    assert_eq!(special[1].args, vec![&word("b")]);
+    assert_eq!(special[2].args, vec![&word("1"), &word("2")]);
+}
+
+#[test]
+fn test_builtins_nested_commands_are_classified() {
+    let cmds = parse("f() { while true; do break 2; done; return; }\nif x; then exit 1; fi | cat");
+    let kinds = special_commands(&cmds)
+        .iter()
+        .map(|s| s.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![BuiltinKind::Break, BuiltinKind::Return, BuiltinKind::Exit]
+    );
+}
+
+#[test]
+fn test_builtins_other_commands_are_not_classified() {
+    assert_eq!(classify("echo break"), None);
+    assert_eq!(classify("$cmd 1"), None);
+    assert_eq!(classify("x=1"), None);
+    assert_eq!(classify("builtin exit 1"), None);
+}
@@ -0,0 +1,91 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::control_chars::{check, BIDI_CONTROL, CONTROL_CHAR, ZERO_WIDTH};
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::parse::NulHandling;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+#[test]
+fn test_control_chars_bidi_override() {
+    assert_eq!(
+        diagnostics("echo 'access\u{202E}level'"),
+        vec![Diagnostic::new(
+            Severity::Error,
+            BIDI_CONTROL,
+            "literal \"access\\u{202e}level\" contains bidirectional control characters (U+202E)"
+        )]
+    );
+}
+
+#[test]
+fn test_control_chars_kinds_reported_separately() {
+    let diags = diagnostics("echo a\x1bb\u{200B}\u{2066}\u{2069}\x1b");
+    assert_eq!(diags.len(), 3);
+    assert_eq!(diags[0].code, CONTROL_CHAR);
+    assert_eq!(diags[0].severity, Severity::Warning);
+    assert!(diags[0].message.ends_with("(U+001B)"));
+    assert_eq!(diags[1].code, BIDI_CONTROL);
+    assert!(diags[1].message.ends_with("(U+2066, U+2069)"));
+    assert_eq!(diags[2].code, ZERO_WIDTH);
+    assert!(diags[2].message.ends_with("(U+200B)"));
+}
+
+#[test]
+fn test_control_chars_ignores_tabs_and_newlines() {
+    assert!(diagnostics("echo 'a\tb\nc' \"d\te\"").is_empty());
+}
+
+#[test]
+fn test_control_chars_checks_all_word_positions() {
+    assert_eq!(codes(diagnostics("x=\u{202E} foo")), vec![BIDI_CONTROL]);
+    assert_eq!(codes(diagnostics("foo >\u{202E}")), vec![BIDI_CONTROL]);
+    assert_eq!(
+        codes(diagnostics("cat <<EOF\n\u{202E}\nEOF\n")),
+        vec![BIDI_CONTROL]
+    );
+    assert_eq!(
+        codes(diagnostics("for x in \u{202E}; do :; done")),
+        vec![BIDI_CONTROL]
+    );
+    assert_eq!(
+        codes(diagnostics("case \u{202E} in \u{2066}) :;; esac")),
+        vec![BIDI_CONTROL, BIDI_CONTROL]
+    );
+    assert_eq!(codes(diagnostics("{ :; } >\u{202E}")), vec![BIDI_CONTROL]);
+    assert_eq!(
+        codes(diagnostics("f() { echo \u{202E}; }")),
+        vec![BIDI_CONTROL]
+    );
+}
+
+#[test]
+fn test_control_chars_checks_substitutions() {
+    assert_eq!(
+        codes(diagnostics("echo $(echo \u{202E})")),
+        vec![BIDI_CONTROL]
+    );
+    assert_eq!(
+        codes(diagnostics("echo \"${x:-\u{202E}}\"")),
+        vec![BIDI_CONTROL]
+    );
+}
+
+#[test]
+fn test_control_chars_reports_replaced_nul() {
+    let mut p = make_parser("echo a\0b");
+    p.set_nul_handling(NulHandling::Replace);
+    let cmds = vec![p.complete_command().unwrap().unwrap()];
+
+    let diags = check(&cmds);
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, CONTROL_CHAR);
+    assert_eq!(
+        diags[0].message,
+        "literal \"a\\u{fffd}b\" contains a replaced NUL byte (U+FFFD)"
+    );
+}
@@ -0,0 +1,170 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::corpus::{Category, CorpusStats, Count};
+use conch_parser::parse::Dialect;
+
+fn count(occurrences: u64, files: u64) -> Count {
+    Count { occurrences, files }
+}
+
+fn get(stats: &CorpusStats, category: Category, key: &str) -> Option<Count> {
+    stats.histogram(category).get(key).cloned()
+}
+
+fn stats() -> CorpusStats {
+    let mut stats = CorpusStats::new();
+    stats
+        .add_script(
+            "x=1 echo \"$x\" 'q' >>log\nfor f in *.sh; do . \"$f\" || exit $?; done &",
+            Dialect::Posix,
+        )
+        .unwrap();
+    stats
+        .add_script(
+            "function f { a |& b; }\n\
+             case $1 in a) echo ${1:-d} ;& *) cat <(ls) ;; esac\n\
+             echo $(( 16#ff + arr[i] * 2 )) $(printf x) ~\n\
+             [[ -n $x ]] && ! true | [ -z \"$x\" ]",
+            Dialect::Bash,
+        )
+        .unwrap();
+    stats
+}
+
+#[test]
+fn test_command_counts() {
+    let stats = stats();
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.failed, 0);
+
+    let c = Category::Commands;
+    assert_eq!(get(&stats, c, "simple"), Some(count(12, 2)));
+    assert_eq!(get(&stats, c, "assignment"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "for"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "or"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "and"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "background"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "function"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "brace"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "pipeline"), Some(count(2, 1)));
+    assert_eq!(get(&stats, c, "negation"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "case"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "case-arm"), Some(count(2, 1)));
+    assert_eq!(get(&stats, c, "conditional"), Some(count(1, 1)));
+    assert_eq!(get(&stats, c, "while"), None);
+
+    assert_eq!(get(&stats, Category::Redirects, ">>"), Some(count(1, 1)));
+}
+
+#[test]
+fn test_word_and_expansion_counts() {
+    let stats = stats();
+
+    let w = Category::Words;
+    assert_eq!(get(&stats, w, "double-quoted"), Some(count(3, 2)));
+    assert_eq!(get(&stats, w, "single-quoted"), Some(count(1, 1)));
+    assert_eq!(get(&stats, w, "glob"), Some(count(3, 2)));
+    assert_eq!(get(&stats, w, "tilde"), Some(count(1, 1)));
+
+    let e = Category::Expansions;
+    assert_eq!(get(&stats, e, "$var"), Some(count(4, 2)));
+    assert_eq!(get(&stats, e, "$?"), Some(count(1, 1)));
+    assert_eq!(get(&stats, e, "$N"), Some(count(2, 1)));
+    assert_eq!(get(&stats, e, "${...:-...}"), Some(count(1, 1)));
+    assert_eq!(get(&stats, e, "<(...)"), Some(count(1, 1)));
+    assert_eq!(get(&stats, e, "$((...))"), Some(count(1, 1)));
+    assert_eq!(get(&stats, e, "$(...)"), Some(count(1, 1)));
+
+    let a = Category::Arithmetic;
+    assert_eq!(get(&stats, a, "radix"), Some(count(1, 1)));
+    assert_eq!(get(&stats, a, "index"), Some(count(1, 1)));
+    assert_eq!(get(&stats, a, "var"), Some(count(1, 1)));
+    assert_eq!(get(&stats, a, "literal"), Some(count(1, 1)));
+    assert_eq!(get(&stats, a, "+"), Some(count(1, 1)));
+    assert_eq!(get(&stats, a, "*"), Some(count(1, 1)));
+}
+
+#[test]
+fn test_builtin_and_extension_counts() {
+    let stats = stats();
+
+    let b = Category::Builtins;
+    assert_eq!(get(&stats, b, "echo"), Some(count(3, 2)));
+    assert_eq!(get(&stats, b, "."), Some(count(1, 1)));
+    assert_eq!(get(&stats, b, "exit"), Some(count(1, 1)));
+    assert_eq!(get(&stats, b, "printf"), Some(count(1, 1)));
+    assert_eq!(get(&stats, b, "true"), Some(count(1, 1)));
+    assert_eq!(get(&stats, b, "["), Some(count(1, 1)));
+    assert_eq!(get(&stats, b, "cat"), None);
+    assert_eq!(get(&stats, b, "ls"), None);
+
+    let x = Category::Extensions;
+    let extensions: Vec<_> = stats.histogram(x).keys().map(String::as_str).collect();
+    assert_eq!(
+        extensions,
+        vec![";&", "<(", "[", "[[", "base#", "function", "|&"]
+    );
+}
+
+#[test]
+fn test_failed_scripts_are_not_counted() {
+    let mut stats = CorpusStats::new();
+    assert!(stats.add_script("echo a; if", Dialect::Posix).is_err());
+    assert!(stats.add_script("a |& b", Dialect::Posix).is_err());
+
+    assert_eq!(stats.files, 0);
+    assert_eq!(stats.failed, 2);
+    for &category in &Category::ALL {
+        assert!(stats.histogram(category).is_empty(), "{}", category);
+    }
+}
+
+#[test]
+fn test_merge_is_independent_of_order() {
+    let sources = ["echo a | cat", "f() { echo $1; }", "echo $(( x++ ))"];
+
+    let mut all = CorpusStats::new();
+    for src in &sources {
+        all.add_script(src, Dialect::Posix).unwrap();
+    }
+
+    let mut reversed = CorpusStats::new();
+    for src in sources.iter().rev() {
+        let mut single = CorpusStats::new();
+        single.add_script(src, Dialect::Posix).unwrap();
+        reversed.merge(&single);
+    }
+
+    assert_eq!(all, reversed);
+    assert_eq!(all.to_json(), reversed.to_json());
+    assert_eq!(get(&all, Category::Builtins, "echo"), Some(count(3, 3)));
+}
+
+#[test]
+fn test_to_json() {
+    let mut stats = CorpusStats::new();
+    stats.add_script("echo \"a\" >out", Dialect::Posix).unwrap();
+    assert!(stats.add_script("(", Dialect::Posix).is_err());
+
+    let expected = r#"{
+  "files": 1,
+  "failed": 1,
+  "commands": {
+    "simple": {"occurrences": 1, "files": 1}
+  },
+  "redirects": {
+    ">": {"occurrences": 1, "files": 1}
+  },
+  "words": {
+    "double-quoted": {"occurrences": 1, "files": 1},
+    "literal": {"occurrences": 3, "files": 1}
+  },
+  "expansions": {},
+  "arithmetic": {},
+  "builtins": {
+    "echo": {"occurrences": 1, "files": 1}
+  },
+  "extensions": {}
+}
+"#;
+    assert_eq!(stats.to_json(), expected);
+}
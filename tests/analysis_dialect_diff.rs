@@ -0,0 +1,92 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::dialect_diff::{compare, DialectDifference};
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{Dialect, Extension};
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_dialect_diff_identical() {
+    let cmp = compare(
+        "foo; bar | baz\nfor x in a; do :; done",
+        Dialect::Bash,
+        Dialect::Posix,
+    );
+
+    assert_eq!(cmp.left, Dialect::Bash);
+    assert_eq!(cmp.right, Dialect::Posix);
+    assert!(cmp.is_identical());
+}
+
+#[test]
+fn test_dialect_diff_extension_only_rejected_by_posix() {
+    let cmp = compare(
+        "foo\nbar\nfunction f { :; }\nbaz",
+        Dialect::Bash,
+        Dialect::Posix,
+    );
+
+    assert_eq!(
+        cmp.differences,
+        vec![DialectDifference::Error {
+            index: 2,
+            left: None,
+            right: Some(Extension(Extension::FunctionKeyword, src(8, 3, 1))),
+        }]
+    );
+    assert_eq!(
+        cmp.differences[0].to_string(),
+        "command #3: found `function` on line 3:1, which is not part of the POSIX \
+         standard; use the `bash` dialect to enable it (second dialect only)"
+    );
+}
+
+#[test]
+fn test_dialect_diff_same_error_is_not_a_difference() {
+    let cmp = compare("foo\n)", Dialect::Posix, Dialect::Bash);
+    assert!(cmp.is_identical());
+}
+
+#[test]
+fn test_dialect_diff_stops_after_first_error() {
+    let cmp = compare(
+        "[[ foo ]]\nfunction f { :; }",
+        Dialect::Posix,
+        Dialect::Bash,
+    );
+
+    assert_eq!(cmp.differences.len(), 1);
+    match cmp.differences[0] {
+        DialectDifference::Error {
+            index: 0,
+            left: Some(_),
+            right: None,
+        } => {}
+        ref d => panic!("unexpected difference: {:?}", d),
+    }
+}
+
+#[test]
+fn test_dialect_diff_different_number_of_commands() {
+    // POSIX parses `foo &` and `>bar` as two separate commands
+    let cmp = compare("foo &>bar", Dialect::Bash, Dialect::Posix);
+
+    assert_eq!(cmp.differences.len(), 2);
+    match cmp.differences[0] {
+        DialectDifference::Command { index: 0, .. } => {}
+        ref d => panic!("unexpected difference: {:?}", d),
+    }
+    match cmp.differences[1] {
+        DialectDifference::CommandCount {
+            index: 1,
+            left: None,
+            right: Some(_),
+        } => {}
+        ref d => panic!("unexpected difference: {:?}", d),
+    }
+    assert_eq!(
+        cmp.differences[1].to_string(),
+        "command #2 is only parsed by the second dialect"
+    );
+}
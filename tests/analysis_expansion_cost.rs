@@ -0,0 +1,150 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::expansion_cost::{
+    check, check_with_limits, word_cost, ExpansionCost, Limits, BRACE_BOMB, REPEATED_EXPANSION,
+};
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::ast::{AndOrList, Command, ListableCommand, PipeableCommand, RedirectOrCmdWord};
+use conch_parser::parse::BraceExpansion;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn cost(src: &str) -> ExpansionCost {
+    let mut p = make_parser(src);
+    word_cost(&p.word().unwrap().unwrap())
+}
+
+fn lint(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+#[test]
+fn test_expansion_cost_of_brace_expansions() {
+    let cases = [
+        ("foo", 1),
+        ("{a,b}", 2),
+        ("{a,b}{c,d,e}", 6),
+        ("x{a,{b,c}}y", 3),
+        ("{a,b{1,2}}", 3),
+        ("{1..10}", 10),
+        ("{10..1}", 10),
+        ("{-5..5}", 11),
+        ("{a..z}", 26),
+        ("{0..100..10}", 11),
+        ("{a}", 1),
+        ("{a,b", 1),
+        ("{1..x}", 1),
+        ("'{a,b}'", 1),
+        ("\"{a,b}\"", 1),
+        ("\\{a,b}", 1),
+        ("{a,$x}", 2),
+    ];
+
+    for &(src, fields) in &cases {
+        assert_eq!(cost(src).fields, fields, "{}", src);
+    }
+}
+
+#[test]
+fn test_expansion_cost_of_structured_brace_expansions() {
+    let srcs = [
+        "{a,b}{c,d,e}",
+        "x{a,{b,c}}y",
+        "{-5..5}",
+        "{0..100..10}",
+        "{a..z}",
+        "{a,$x}",
+    ];
+    for src in &srcs {
+        let cmd = format!("echo {}", src);
+        let mut p = make_parser(&cmd);
+        p.set_brace_expansion(BraceExpansion::Structured);
+        let word = match p.complete_command().unwrap().unwrap().0 {
+            Command::List(AndOrList {
+                first: ListableCommand::Single(PipeableCommand::Simple(ref cmd)),
+                ..
+            }) => match cmd.redirects_or_cmd_words[1] {
+                RedirectOrCmdWord::CmdWord(ref word) => word.clone(),
+                RedirectOrCmdWord::Redirect(_) => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        assert_ne!(word, make_parser(src).word().unwrap().unwrap(), "{}", src);
+        assert_eq!(word_cost(&word), cost(src), "{}", src);
+    }
+}
+
+#[test]
+fn test_expansion_cost_saturates() {
+    let src = "{1..4294967296}".repeat(3);
+    assert_eq!(cost(&src).fields, u64::MAX);
+}
+
+#[test]
+fn test_expansion_cost_of_parameters() {
+    assert_eq!(cost("foo").expansions, 0);
+    assert_eq!(cost("$x$x\"$y${z}\"$(foo)").expansions, 5);
+    assert_eq!(cost("'$x'\\$x").expansions, 0);
+}
+
+#[test]
+fn test_expansion_cost_reports_brace_bombs() {
+    let src = "echo {a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}{a,b}";
+    assert_eq!(
+        check(&parse(src)),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            BRACE_BOMB,
+            format!("brace expansion of `{}` produces 16384 words", &src[5..])
+        )]
+    );
+
+    assert_eq!(codes(lint("echo {1..100}")), Vec::<&str>::new());
+    assert_eq!(codes(lint("x={1..100000}")), vec![BRACE_BOMB]);
+    assert_eq!(
+        codes(lint("for x in {1..100000}; do :; done")),
+        vec![BRACE_BOMB]
+    );
+    assert_eq!(codes(lint("f() { cat > {1..100000}; }")), vec![BRACE_BOMB]);
+}
+
+#[test]
+fn test_expansion_cost_reports_repeated_expansions() {
+    let word = "${x}".repeat(33);
+    assert_eq!(
+        check(&parse(&format!("echo {}", word))),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            REPEATED_EXPANSION,
+            format!(
+                "`{}` repeats 33 expansions, and grows with the length of each value",
+                word
+            )
+        )]
+    );
+
+    assert_eq!(
+        codes(lint(&format!("echo {}", "$x".repeat(32)))),
+        Vec::<&str>::new()
+    );
+    assert_eq!(
+        codes(lint(&format!("case {} in *) ;; esac", "$x".repeat(33)))),
+        vec![REPEATED_EXPANSION]
+    );
+
+    // Heredoc bodies are not checked
+    let heredoc = format!("cat <<EOF\n{}\nEOF\n", "$x ".repeat(100));
+    assert_eq!(codes(lint(&heredoc)), Vec::<&str>::new());
+}
+
+#[test]
+fn test_expansion_cost_custom_limits() {
+    let limits = Limits {
+        max_fields: 1,
+        max_expansions: 1,
+    };
+    let diagnostics = check_with_limits(&parse("echo {a,b} $x$y"), limits);
+    let codes = diagnostics.into_iter().map(|d| d.code).collect::<Vec<_>>();
+    assert_eq!(codes, vec![BRACE_BOMB, REPEATED_EXPANSION]);
+}
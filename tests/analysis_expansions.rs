@@ -0,0 +1,295 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::expansions::{
+    expansions, split_fields, Expansion, ExpansionKind, PositionalExpansion, WordContext,
+};
+use conch_parser::ast::ParameterSubstitution;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn describe(e: &Expansion<'_>) -> String {
+    let kind = match e.kind {
+        ExpansionKind::Tilde => String::from("~"),
+        ExpansionKind::Parameter(p) => p.to_string(),
+        ExpansionKind::ParameterSubstitution(subst) => match subst.parameter() {
+            Some(p) => format!("subst {}", p),
+            None => String::from("subst"),
+        },
+        ExpansionKind::CommandSubstitution(cmds) => format!("$({})", cmds.len()),
+        ExpansionKind::ProcessSubstitution(subst) => match *subst {
+            ParameterSubstitution::ProcessRead(ref cmds) => format!("<({})", cmds.len()),
+            ParameterSubstitution::ProcessWrite(ref cmds) => format!(">({})", cmds.len()),
+            _ => unreachable!(),
+        },
+        ExpansionKind::Arithmetic(_) => String::from("$(())"),
+        ExpansionKind::Pathname => String::from("glob"),
+    };
+
+    if e.quoted {
+        format!("\"{}\"", kind)
+    } else {
+        kind
+    }
+}
+
+fn order(src: &str) -> Vec<String> {
+    expansions(&parse(src)).map(|e| describe(&e)).collect()
+}
+
+#[test]
+fn test_simple_command_evaluation_order() {
+    assert_eq!(
+        order("a=$a b=~ $cmd $arg >$out 2>\"$err\" $last"),
+        vec![
+            "${cmd}",
+            "${arg}",
+            "${last}",
+            "${out}",
+            "\"${err}\"",
+            "${a}",
+            "~"
+        ]
+    );
+}
+
+#[test]
+fn test_word_evaluation_order() {
+    // Tildes are expanded first, and pathname expansion last
+    assert_eq!(order("echo *$a~/$b?"), vec!["${a}", "${b}", "glob"]);
+    assert_eq!(order("echo ~/*$a$(b)"), vec!["~", "${a}", "$(1)", "glob"]);
+}
+
+#[test]
+fn test_contexts_and_quoting() {
+    let cmds = parse("x=$x cmd \"$a\" '$b' $c <<EOF\n$d\nEOF\n");
+    let found = expansions(&cmds).collect::<Vec<_>>();
+
+    let contexts = found
+        .iter()
+        .map(|e| (describe(e), e.context))
+        .map(|(e, ctx)| match ctx {
+            WordContext::Argument(i) => format!("{} arg {}", e, i),
+            WordContext::Assignment(name) => format!("{} assign {}", e, name),
+            WordContext::Redirect(_) => format!("{} redirect", e),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        contexts,
+        vec![
+            "\"${a}\" arg 1",
+            "${c} arg 3",
+            "${d} redirect",
+            "${x} assign x"
+        ]
+    );
+}
+
+#[test]
+fn test_tildes_only_expanded_at_word_start_or_after_assignment_colon() {
+    assert_eq!(order("echo a~ \"~\" '~' ~ ~user"), vec!["~", "~"]);
+    assert_eq!(order("x=~:~/a:b~ echo ~:~"), vec!["~", "~", "~"]);
+}
+
+#[test]
+fn test_pathname_expansion_only_for_unquoted_split_words() {
+    assert_eq!(order("echo \"*\" '?' \\* *"), vec!["glob"]);
+    assert_eq!(order("x=* echo >*.txt"), Vec::<String>::new());
+    assert_eq!(order("for i in *; do :; done"), vec!["glob"]);
+    assert_eq!(order("case * in *) :;; esac"), Vec::<String>::new());
+}
+
+#[test]
+fn test_nested_expansions_follow_their_enclosing_expansion() {
+    let cmds = parse("echo ${a:-\"$b\"$(c $d)} $e");
+    let found = expansions(&cmds)
+        .map(|e| (describe(&e), e.depth))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        found,
+        vec![
+            (String::from("subst ${a}"), 0),
+            (String::from("\"${b}\""), 1),
+            (String::from("$(1)"), 1),
+            (String::from("${d}"), 2),
+            (String::from("${e}"), 0),
+        ]
+    );
+}
+
+#[test]
+fn test_quoting_is_reset_within_command_substitutions() {
+    assert_eq!(
+        order("echo \"$(echo $a \"$b\")\" \"${c:-$d}\""),
+        vec!["\"$(1)\"", "${a}", "\"${b}\"", "\"subst ${c}\"", "\"${d}\""]
+    );
+}
+
+#[test]
+fn test_nested_expansions_keep_outermost_word() {
+    let cmds = parse("echo ${a:-$b} $(echo $c)");
+    let found = expansions(&cmds).collect::<Vec<_>>();
+
+    // `$b` belongs to the word containing `${a:-$b}`, while `$c` is part of
+    // the word of its own command
+    assert!(std::ptr::eq(found[0].word, found[1].word));
+    assert_eq!(found[3].context, WordContext::Argument(1));
+    assert!(!std::ptr::eq(found[2].word, found[3].word));
+}
+
+#[test]
+fn test_arithmetic_and_parameter_substitution_kinds() {
+    let cmds = parse("echo $((1 + x)) $(( )) ${#a} $(<file)");
+    let found = expansions(&cmds).map(|e| e.kind).collect::<Vec<_>>();
+
+    assert_eq!(found.len(), 4);
+    assert!(matches!(found[0], ExpansionKind::Arithmetic(Some(_))));
+    assert!(matches!(found[1], ExpansionKind::Arithmetic(None)));
+    assert!(matches!(
+        found[2],
+        ExpansionKind::ParameterSubstitution(ParameterSubstitution::Len(_))
+    ));
+    assert!(matches!(
+        found[3],
+        ExpansionKind::ParameterSubstitution(ParameterSubstitution::ReadFile(_))
+    ));
+}
+
+#[test]
+fn test_compound_command_evaluation_order() {
+    let src = "\
+{ echo $a; } >$b
+for i in $c; do echo $i; done
+case $d in $e) echo $f;; $g) ;; esac
+if $h; then $i; elif $j; then $k; else $l; fi
+while $m; do $n; done
+f() { echo $o; } <$p
+";
+    assert_eq!(
+        order(src),
+        vec![
+            "${b}", "${a}", "${c}", "${i}", "${d}", "${e}", "${f}", "${g}", "${h}", "${i}", "${j}",
+            "${k}", "${l}", "${m}", "${n}", "${p}", "${o}",
+        ]
+    );
+}
+
+#[test]
+fn test_for_and_case_contexts() {
+    let cmds = parse("for i in $a; do :; done; case $b in $c) ;; esac");
+    let found = expansions(&cmds).map(|e| e.context).collect::<Vec<_>>();
+
+    assert_eq!(
+        found,
+        vec![
+            WordContext::ForWord("i"),
+            WordContext::CaseWord,
+            WordContext::CasePattern,
+        ]
+    );
+}
+
+#[test]
+fn test_process_substitutions() {
+    assert_eq!(
+        order("diff <(a $x) >(b; c) $y"),
+        vec!["<(1)", "${x}", ">(2)", "${y}"]
+    );
+}
+
+fn positionals(src: &str, args: &[&str], ifs: Option<&str>) -> Vec<PositionalExpansion> {
+    expansions(&parse(src))
+        .filter_map(|e| e.positional_parameters(args, ifs))
+        .collect()
+}
+
+fn fields(fields: &[&str]) -> PositionalExpansion {
+    PositionalExpansion::Fields(fields.iter().map(|&f| f.to_owned()).collect())
+}
+
+fn joined(s: &str) -> PositionalExpansion {
+    PositionalExpansion::Joined(s.to_owned())
+}
+
+#[test]
+fn test_positional_parameters_in_split_words() {
+    let src = "echo \"$@\" \"$*\" $@ $* ${@}";
+    let args = ["a b", "", "c:d"];
+
+    assert_eq!(
+        positionals(src, &args, None),
+        vec![
+            fields(&["a b", "", "c:d"]),
+            joined("a b  c:d"),
+            fields(&["a", "b", "c:d"]),
+            fields(&["a", "b", "c:d"]),
+            fields(&["a", "b", "c:d"]),
+        ]
+    );
+    assert_eq!(
+        positionals(src, &args, Some(":")),
+        vec![
+            fields(&["a b", "", "c:d"]),
+            joined("a b::c:d"),
+            fields(&["a b", "c", "d"]),
+            fields(&["a b", "c", "d"]),
+            fields(&["a b", "c", "d"]),
+        ]
+    );
+    assert_eq!(
+        positionals(src, &args, Some("")),
+        vec![
+            fields(&["a b", "", "c:d"]),
+            joined("a bc:d"),
+            fields(&["a b", "c:d"]),
+            fields(&["a b", "c:d"]),
+            fields(&["a b", "c:d"]),
+        ]
+    );
+
+    // No parameters at all
+    assert_eq!(
+        positionals("for x in \"$@\" \"$*\" $*; do :; done", &[], None),
+        vec![fields(&[]), joined(""), fields(&[])]
+    );
+    assert_eq!(joined("").into_fields(), vec![String::new()]);
+}
+
+#[test]
+fn test_positional_parameters_in_unsplit_words() {
+    let src = "x=$@ y=\"$*\" z=$*; case $@ in *) ;; esac";
+    let args = ["a", "b c"];
+
+    assert_eq!(
+        positionals(src, &args, Some(":-")),
+        vec![
+            joined("a b c"),
+            joined("a:b c"),
+            joined("a:b c"),
+            joined("a b c")
+        ]
+    );
+    assert_eq!(positionals(src, &args, Some(""))[1], joined("ab c"));
+    assert!(positionals("echo $# $1 \"${@:-x}\" $x", &args, None).is_empty());
+}
+
+#[test]
+fn test_split_fields() {
+    let cases: &[(&str, Option<&str>, &[&str])] = &[
+        ("", None, &[]),
+        (" \t\n", None, &[]),
+        ("  a \t b\n", None, &["a", "b"]),
+        ("a\tb", Some(" "), &["a\tb"]),
+        (":a::b:", Some(":"), &["", "a", "", "b"]),
+        ("a : b", Some(" :"), &["a", "b"]),
+        ("a: :b", Some(": "), &["a", "", "b"]),
+        ("a  b", Some(""), &["a  b"]),
+        ("", Some(""), &[]),
+    ];
+
+    for &(value, ifs, expected) in cases {
+        assert_eq!(split_fields(value, ifs), expected, "{:?} {:?}", value, ifs);
+    }
+}
@@ -0,0 +1,178 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::explain::{construct, explain, Explanation, CONSTRUCTS};
+use conch_parser::ast::Span;
+use conch_parser::parse::{Dialect, Extension};
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Explains the construct at the first occurrence of `needle` within the source.
+fn explain_at(source: &str, needle: &str) -> Explanation {
+    let byte = source.find(needle).unwrap();
+    let pos = src(byte, 1, byte + 1);
+    explain(
+        source,
+        Span {
+            start: pos,
+            end: pos,
+        },
+    )
+    .unwrap()
+}
+
+/// Returns the source of the construct explained at `needle`, along with its id.
+fn id_at<'a>(source: &'a str, needle: &str) -> (&'static str, &'a str) {
+    let explanation = explain_at(source, needle);
+    let span = explanation.span;
+    (
+        explanation.construct.id,
+        &source[span.start.byte..span.end.byte],
+    )
+}
+
+#[test]
+fn test_explain_innermost_construct() {
+    let source = "for x in a b; do { echo \"$x\" >>log; } & done";
+
+    assert_eq!(id_at(source, "for"), ("for-loop", source));
+    assert_eq!(id_at(source, "a b"), ("word", "a"));
+    assert_eq!(
+        id_at(source, "{"),
+        ("brace-group", "{ echo \"$x\" >>log; }")
+    );
+    assert_eq!(id_at(source, "echo"), ("word", "echo"));
+    assert_eq!(id_at(source, "\"$x"), ("double-quotes", "\"$x\""));
+    assert_eq!(
+        id_at(source, " \"$x"),
+        ("simple-command", "echo \"$x\" >>log")
+    );
+    assert_eq!(id_at(source, ">>"), ("redirect-append", ">>log"));
+    assert_eq!(
+        id_at(source, "&"),
+        ("async-list", "{ echo \"$x\" >>log; } &")
+    );
+}
+
+#[test]
+fn test_explain_substitutions() {
+    let source = "echo ~ ${x:-$(y)} $(w) $((1 + 2)) ${x/a/b} <(z) $x~";
+
+    assert_eq!(
+        id_at(source, "${x:"),
+        ("parameter-substitution", "${x:-$(y)}")
+    );
+    // Words nested within `${...}` are explained along with it, except for any commands
+    assert_eq!(
+        id_at(source, "$(y"),
+        ("parameter-substitution", "${x:-$(y)}")
+    );
+    assert_eq!(id_at(source, "y)"), ("word", "y"));
+    assert_eq!(id_at(source, "$(w"), ("command-substitution", "$(w)"));
+    assert_eq!(id_at(source, "1 +"), ("arithmetic-expansion", "$((1 + 2))"));
+    assert_eq!(
+        id_at(source, "${x/"),
+        ("parameter-substitution-extension", "${x/a/b}")
+    );
+    assert_eq!(id_at(source, "<("), ("process-read", "<(z)"));
+    assert_eq!(id_at(source, "$x~"), ("word", "$x~"));
+    assert_eq!(id_at(source, "~ "), ("tilde-expansion", "~"));
+}
+
+#[test]
+fn test_explain_reports_extensions_per_node() {
+    let cases = vec![
+        ("a | b", "|", vec![]),
+        ("a |& b", "|", vec![Extension::PipeAmp]),
+        ("case x in a) ;; esac", "case", vec![]),
+        (
+            "case x in a) ;& b) ;;& esac",
+            "case",
+            vec![Extension::CaseFallThrough, Extension::CaseContinue],
+        ),
+        ("f() { :; }", "f", vec![]),
+        ("function f { :; }", "f", vec![Extension::FunctionKeyword]),
+        ("[[ -n x ]]", "[[", vec![Extension::DoubleBracket]),
+        ("echo &>out", "&>", vec![Extension::RedirectBoth]),
+        (
+            "echo $((16#f + a[1]))",
+            "16",
+            vec![Extension::ArithmeticBase, Extension::ArithmeticIndex],
+        ),
+        ("echo $((a[1]++))", "a", vec![Extension::ArithmeticIndex]),
+    ];
+
+    for (source, needle, extensions) in cases {
+        assert_eq!(
+            explain_at(source, needle).extensions,
+            extensions,
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_explain_dialects() {
+    let dialects = |source, needle| explain_at(source, needle).dialects;
+
+    assert_eq!(
+        dialects("a | b", "|"),
+        vec![Dialect::Posix, Dialect::Bash, Dialect::Mksh]
+    );
+    assert_eq!(
+        dialects("[[ x ]]", "[["),
+        vec![Dialect::Bash, Dialect::Mksh]
+    );
+    assert_eq!(dialects("cat <(x)", "<("), vec![Dialect::Bash]);
+}
+
+#[test]
+fn test_explain_display() {
+    assert_eq!(
+        explain_at("while :; do :; done", "while").to_string(),
+        "while loop (POSIX 2.9.4.5)"
+    );
+    assert_eq!(
+        explain_at("[[ x ]]", "[[").to_string(),
+        "conditional command (extension: `[[`)"
+    );
+    assert_eq!(
+        explain_at("echo $(<file)", "$(").to_string(),
+        "file contents substitution (not part of POSIX)"
+    );
+}
+
+#[test]
+fn test_explain_outside_of_any_construct() {
+    let source = "echo a   # comment\necho b; )";
+    let span = |start, end| Span {
+        start: src(start, 1, start + 1),
+        end: src(end, 1, end + 1),
+    };
+
+    assert_eq!(explain(source, span(7, 7)), None);
+    assert_eq!(explain(source, span(12, 12)), None);
+    // A span which is only partially within a construct
+    assert_eq!(explain(source, span(5, 8)), None);
+    // Constructs before a parse error can still be explained
+    assert_eq!(explain_at(source, "b").construct.id, "word");
+    assert_eq!(explain(source, span(28, 28)), None);
+}
+
+#[test]
+fn test_construct_metadata() {
+    for c in CONSTRUCTS {
+        assert_eq!(construct(c.id), Some(c));
+        assert!(
+            !(c.posix_section.is_some() && c.extension.is_some()),
+            "{}",
+            c.id
+        );
+    }
+
+    assert_eq!(construct("nonexistent"), None);
+    assert_eq!(
+        construct("heredoc").unwrap().posix_url().unwrap(),
+        "https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_07_04"
+    );
+}
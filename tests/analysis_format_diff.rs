@@ -0,0 +1,118 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::format_diff::{compare, Change, ChangeLevel};
+use conch_parser::ast::Span;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Option<Span> {
+    Some(Span {
+        start: src(start.0, start.1, start.2),
+        end: src(end.0, end.1, end.2),
+    })
+}
+
+#[test]
+fn test_identical_sources() {
+    let change = compare("echo hi\n", "echo hi\n");
+    assert_eq!(
+        change,
+        Change {
+            level: ChangeLevel::Identical,
+            old: None,
+            new: None,
+        }
+    );
+    assert!(change.is_formatting_only());
+}
+
+#[test]
+fn test_trivia_changes() {
+    let old = "foo a b\nbar | baz\n";
+    let cases = [
+        "foo  a b\nbar | baz\n",
+        "foo a b\n\n\nbar|baz",
+        "# leading\nfoo a b # trailing\nbar | baz\n",
+        "foo a \\\n  b\nbar | baz\n",
+        "\n\nfoo\ta b\n  bar |  baz  \n\n",
+    ];
+
+    for &new in &cases {
+        let change = compare(old, new);
+        assert_eq!(change.level, ChangeLevel::Trivia, "{:?}", new);
+        assert!(change.is_formatting_only());
+    }
+
+    // Locations recorded within commands are disregarded
+    assert_eq!(
+        compare("{ foo; } >out | bar", "  {  foo; }   >out|bar").level,
+        ChangeLevel::Trivia
+    );
+
+    // The first differing token of any kind is located
+    assert_eq!(
+        compare(old, "foo a b # trailing\nbar | baz\n"),
+        Change {
+            level: ChangeLevel::Trivia,
+            old: span((7, 1, 8), (8, 2, 1)),
+            new: span((7, 1, 8), (8, 1, 9)),
+        }
+    );
+}
+
+#[test]
+fn test_token_changes_with_the_same_structure() {
+    let cases = [
+        ("foo; bar", "foo\nbar"),
+        ("echo `pwd`", "echo $(pwd)"),
+        ("function f { :; }", "f() { :; }"),
+        ("if true; then :; fi", "if true\nthen\n  :\nfi"),
+    ];
+
+    for &(old, new) in &cases {
+        let change = compare(old, new);
+        assert_eq!(change.level, ChangeLevel::Tokens, "{:?} vs {:?}", old, new);
+        assert!(!change.is_formatting_only());
+    }
+
+    assert_eq!(
+        compare("foo; bar", "foo\n\n  bar"),
+        Change {
+            level: ChangeLevel::Tokens,
+            old: span((3, 1, 4), (4, 1, 5)),
+            new: span((3, 1, 4), (4, 2, 1)),
+        }
+    );
+}
+
+#[test]
+fn test_structural_changes() {
+    let cases = [
+        ("echo hi", "echo bye"),
+        ("echo hi # comment", "echo hi comment"),
+        ("echo \"a\" \"b\"", "echo \"a\"\"b\""),
+        ("foo && bar", "foo || bar"),
+        ("cat <<EOF\n  body\nEOF\n", "cat <<EOF\nbody\nEOF\n"),
+        ("echo hi", "echo hi; fi"),
+    ];
+
+    for &(old, new) in &cases {
+        let change = compare(old, new);
+        assert_eq!(
+            change.level,
+            ChangeLevel::Structure,
+            "{:?} vs {:?}",
+            old,
+            new
+        );
+    }
+
+    assert_eq!(
+        compare("echo hi\nfoo", "echo hi"),
+        Change {
+            level: ChangeLevel::Structure,
+            old: span((7, 1, 8), (8, 2, 1)),
+            new: span((7, 1, 8), (7, 1, 8)),
+        }
+    );
+}
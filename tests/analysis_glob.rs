@@ -0,0 +1,65 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::glob::{check, DESTRUCTIVE_GLOB, UNMATCHED_GLOB};
+use conch_parser::analysis::{Diagnostic, Severity};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn lint(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+#[test]
+fn test_glob_destructive_commands() {
+    assert_eq!(codes(lint("rm -rf *")), vec![DESTRUCTIVE_GLOB]);
+    assert_eq!(codes(lint("/bin/rm foo/*.o")), vec![DESTRUCTIVE_GLOB]);
+    assert_eq!(codes(lint("chown root: ./file?")), vec![DESTRUCTIVE_GLOB]);
+    assert_eq!(codes(lint("chmod -R 755 [ab]*")), vec![DESTRUCTIVE_GLOB]);
+    assert_eq!(
+        codes(lint("chmod --recursive 755 *")),
+        vec![DESTRUCTIVE_GLOB]
+    );
+
+    let diag = &lint("rm -rf *")[0];
+    assert_eq!(diag.severity, Severity::Warning);
+    assert_eq!(
+        diag.message,
+        "unquoted glob `*` passed to destructive command `rm`"
+    );
+}
+
+#[test]
+fn test_glob_destructive_after_expansion_mentions_root() {
+    let diag = &lint("rm -rf $dir/*")[0];
+    assert_eq!(diag.code, DESTRUCTIVE_GLOB);
+    assert!(diag.message.contains("${dir}/*"));
+    assert!(diag.message.contains("root directory"));
+}
+
+#[test]
+fn test_glob_non_recursive_chmod_is_not_destructive() {
+    assert_eq!(codes(lint("chmod 644 *.txt")), vec![UNMATCHED_GLOB]);
+}
+
+#[test]
+fn test_glob_unmatched() {
+    assert_eq!(codes(lint("cat *.txt")), vec![UNMATCHED_GLOB]);
+    assert_eq!(
+        codes(lint("for f in *.txt; do echo $f; done")),
+        vec![UNMATCHED_GLOB]
+    );
+    assert_eq!(lint("ls *")[0].severity, Severity::Info);
+}
+
+#[test]
+fn test_glob_nested_commands_are_checked() {
+    let src = "if true; then\n  foo() { rm *; }\nfi\nwhile true; do cat ?; done";
+    assert_eq!(codes(lint(src)), vec![DESTRUCTIVE_GLOB, UNMATCHED_GLOB]);
+}
+
+#[test]
+fn test_glob_quoted_or_escaped_globs_are_ignored() {
+    assert!(lint("rm '*' \"*.txt\" \\*").is_empty());
+    assert!(lint("echo foo bar; rm -rf build").is_empty());
+    assert!(lint("for f in a b c; do :; done").is_empty());
+}
@@ -0,0 +1,161 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::graph::{Call, GraphKind, ScriptGraph};
+use conch_parser::parse::{Dialect, ParseError};
+
+fn graph() -> ScriptGraph {
+    let mut graph = ScriptGraph::new();
+    graph
+        .add_script(
+            "bin/main.sh",
+            ". ../lib/log.sh\nsource \"$dir/dynamic.sh\"\nrun() {\n  log start\n  ls\n}\nrun; run",
+            Dialect::Bash,
+        )
+        .unwrap();
+    graph
+        .add_script(
+            "lib/log.sh",
+            ". lib/fmt.sh\nlog() { fmt \"$@\"; }",
+            Dialect::Posix,
+        )
+        .unwrap();
+    graph
+        .add_script(
+            "lib/fmt.sh",
+            "fmt() { printf '%s\\n' \"$*\"; }\n. ./missing.sh",
+            Dialect::Posix,
+        )
+        .unwrap();
+    graph
+}
+
+#[test]
+fn test_graph_collects_declarations_and_references() {
+    let graph = graph();
+    let main = &graph.files()[0];
+
+    assert_eq!(main.path, "bin/main.sh");
+    assert_eq!(main.dialect, Dialect::Bash);
+    assert_eq!(main.functions.len(), 1);
+    assert_eq!(main.functions[0].name, "run");
+    assert_eq!(main.functions[0].span.start.line, 3);
+    assert_eq!(main.functions[0].span.end.line, 6);
+
+    // The dynamically sourced file cannot be determined
+    assert_eq!(main.includes.len(), 1);
+    assert_eq!(main.includes[0].path, "../lib/log.sh");
+
+    let calls = main
+        .calls
+        .iter()
+        .map(|c| (&*c.name, c.caller, c.span.start.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            ("log", Some(0), 4),
+            ("ls", Some(0), 5),
+            ("run", None, 7),
+            ("run", None, 7),
+        ]
+    );
+}
+
+#[test]
+fn test_graph_resolves_includes_and_calls() {
+    let graph = graph();
+    let files = graph.files();
+
+    assert_eq!(graph.resolve_include(0, &files[0].includes[0]), Some(1));
+    assert_eq!(graph.resolve_include(1, &files[1].includes[0]), Some(2));
+    assert_eq!(graph.resolve_include(2, &files[2].includes[0]), None);
+
+    let resolve = |file: usize, name: &str| {
+        let call = files[file].calls.iter().find(|c| c.name == name).unwrap();
+        graph.resolve_call(file, call)
+    };
+    assert_eq!(resolve(0, "run"), Some((0, 0)));
+    assert_eq!(resolve(0, "log"), Some((1, 0)));
+    assert_eq!(resolve(1, "fmt"), Some((2, 0)));
+    assert_eq!(resolve(0, "ls"), None);
+
+    // Functions of files which are not sourced are not visible
+    let call = Call {
+        name: String::from("run"),
+        caller: None,
+        span: files[1].calls[0].span,
+    };
+    assert_eq!(graph.resolve_call(1, &call), None);
+}
+
+#[test]
+fn test_graph_includes_dot() {
+    let expected = r#"digraph includes {
+    n0 [label="bin/main.sh", dialect="bash"];
+    n1 [label="lib/log.sh", dialect="posix"];
+    n2 [label="lib/fmt.sh", dialect="posix"];
+    n3 [label="./missing.sh", style=dashed];
+    n0 -> n1 [label="1:1"];
+    n1 -> n2 [label="1:1"];
+    n2 -> n3 [label="2:1"];
+}
+"#;
+    assert_eq!(graph().to_dot(GraphKind::Includes), expected);
+}
+
+#[test]
+fn test_graph_calls_dot() {
+    let expected = r#"digraph calls {
+    n0 [label="bin/main.sh", dialect="bash"];
+    n1 [label="lib/log.sh", dialect="posix"];
+    n2 [label="lib/fmt.sh", dialect="posix"];
+    n3 [label="run", shape=box, file="bin/main.sh", dialect="bash", span="3:1-6:2"];
+    n4 [label="log", shape=box, file="lib/log.sh", dialect="posix", span="2:1-2:20"];
+    n5 [label="fmt", shape=box, file="lib/fmt.sh", dialect="posix", span="1:1-1:30"];
+    n3 -> n4 [label="4:3"];
+    n0 -> n3 [label="7:1, 7:6"];
+    n4 -> n5 [label="2:9"];
+}
+"#;
+    assert_eq!(graph().to_dot(GraphKind::Calls), expected);
+}
+
+#[test]
+fn test_graph_json() {
+    let mut graph = ScriptGraph::new();
+    graph
+        .add_script("a \"b\".sh", "f() { :; }\nf", Dialect::Posix)
+        .unwrap();
+
+    let expected = r#"{
+  "graph": "calls",
+  "nodes": [
+    {"id": "n0", "kind": "file", "name": "a \"b\".sh", "file": "a \"b\".sh", "dialect": "posix", "span": null},
+    {"id": "n1", "kind": "function", "name": "f", "file": "a \"b\".sh", "dialect": "posix", "span": {"start": {"byte": 0, "line": 1, "col": 1}, "end": {"byte": 10, "line": 1, "col": 11}}}
+  ],
+  "edges": [
+    {"from": "n0", "to": "n1", "spans": [{"start": {"byte": 11, "line": 2, "col": 1}, "end": {"byte": 12, "line": 2, "col": 2}}]}
+  ]
+}
+"#;
+    assert_eq!(graph.to_json(GraphKind::Calls), expected);
+
+    let expected = r#"{
+  "graph": "includes",
+  "nodes": [
+    {"id": "n0", "kind": "file", "name": "a \"b\".sh", "file": "a \"b\".sh", "dialect": "posix", "span": null}
+  ],
+  "edges": [
+  ]
+}
+"#;
+    assert_eq!(graph.to_json(GraphKind::Includes), expected);
+}
+
+#[test]
+fn test_graph_skips_scripts_which_fail_to_parse() {
+    let mut graph = ScriptGraph::new();
+    let err = graph.add_script("bad.sh", "function f { :; }", Dialect::Posix);
+
+    assert!(matches!(err, Err(ParseError::Extension(..))));
+    assert!(graph.files().is_empty());
+}
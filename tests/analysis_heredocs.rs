@@ -0,0 +1,77 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::heredocs::HeredocLibrary;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_heredocs_are_collected_in_source_order() {
+    let source = "cat <<A 3<<-'B'; echo \"$(cat <<C\nc\nC\n)\"\na\nA\n\tb\nB\n\
+               f() {\n  tee out <<\\D\n$d\nD\n}\n";
+    let library = HeredocLibrary::from_source(source);
+
+    let summary = library
+        .iter()
+        .map(|h| (h.delimiter.as_str(), h.fd, h.body.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("A", None, "a\n"),
+            ("B", Some(3), "b\n"),
+            ("C", None, "c\n"),
+            ("D", None, "$d\n"),
+        ]
+    );
+
+    let b = library.get("B").next().unwrap();
+    assert!(b.quoted && b.strip_tabs && !b.expands());
+    assert_eq!(b.span.start, src(8, 1, 9));
+
+    let d = library.get("D").next().unwrap();
+    assert!(d.quoted && !d.has_expansions);
+    assert_eq!(d.span.start.line, 10);
+}
+
+#[test]
+fn test_heredoc_expansion_flags() {
+    let library = HeredocLibrary::from_source(
+        "cat <<A\nplain \\$text\nA\ncat <<B\n${x:-y} $(z)\nB\ncat <<C\n$((1 + 2))\nC\n",
+    );
+    let flags = library
+        .iter()
+        .map(|h| (h.expands(), h.has_expansions))
+        .collect::<Vec<_>>();
+    assert_eq!(flags, vec![(true, false), (true, true), (true, true)]);
+}
+
+#[test]
+fn test_heredoc_lookup() {
+    let src = "cat <<EOF\n1\nEOF\ncat <<END\n2\nEND\ncat <<EOF\n3\nEOF\n";
+    let library = HeredocLibrary::from_source(src);
+    assert_eq!(library.len(), 3);
+    assert_eq!(
+        library.delimiters().into_iter().collect::<Vec<_>>(),
+        vec!["END", "EOF"]
+    );
+    assert_eq!(
+        library
+            .get("EOF")
+            .map(|h| h.body.as_str())
+            .collect::<Vec<_>>(),
+        vec!["1\n", "3\n"]
+    );
+    assert_eq!(library.get("MISSING").count(), 0);
+
+    let second = src.find("<<END").unwrap();
+    assert_eq!(library.at(second + 2).unwrap().delimiter, "END");
+    assert_eq!(library.at(0), None);
+    assert_eq!((&library).into_iter().count(), 3);
+}
+
+#[test]
+fn test_heredocs_before_an_error_are_collected() {
+    let library = HeredocLibrary::from_source("cat <<EOF\nfoo\nEOF\necho )\n");
+    assert_eq!(library.len(), 1);
+    assert!(HeredocLibrary::from_source("echo foo").is_empty());
+}
@@ -0,0 +1,116 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::jobs::{check, UNWAITED_JOB, WAIT_WITHOUT_JOB};
+use conch_parser::analysis::{Diagnostic, Severity};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+#[test]
+fn test_jobs_unwaited_background_job() {
+    assert_eq!(
+        diagnostics("server --port 80 &\necho started"),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            UNWAITED_JOB,
+            "background job `server` is never waited for; \
+             the script may exit before it completes"
+        )]
+    );
+}
+
+#[test]
+fn test_jobs_waited_for() {
+    let sources = [
+        "a & b & wait",
+        "a & wait $!",
+        "a & wait \"$!\"",
+        "a & pid=$!; b; wait $pid",
+        "a & wait %1",
+        "a & fg",
+        "a & disown",
+        "for i in 1 2; do a & done; wait",
+        "a & b | c & wait -n; wait",
+        "a & pids=\"$pids $!\"; wait $pids",
+    ];
+
+    for src in &sources {
+        assert_eq!(
+            codes(diagnostics(src)),
+            Vec::<&str>::new(),
+            "source: {}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_jobs_partially_waited_for() {
+    assert_eq!(codes(diagnostics("a & b & wait $!")), vec![UNWAITED_JOB]);
+    assert_eq!(
+        codes(diagnostics("a & p=$!; b & wait $p")),
+        vec![UNWAITED_JOB]
+    );
+    assert_eq!(codes(diagnostics("a & b & wait %2")), vec![UNWAITED_JOB]);
+    assert_eq!(codes(diagnostics("a & b & wait %-")), vec![UNWAITED_JOB]);
+    assert_eq!(codes(diagnostics("a & b & fg")), vec![UNWAITED_JOB]);
+
+    let diags = diagnostics("a & b & wait $!");
+    assert!(diags[0].message.contains("`a`"));
+}
+
+#[test]
+fn test_jobs_pid_variable_reassigned() {
+    assert_eq!(
+        codes(diagnostics("a & p=$!; p=foo; wait $p")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(
+        codes(diagnostics("a & p=$!; b & q=$!; p=foo; wait $q")),
+        vec![UNWAITED_JOB]
+    );
+}
+
+#[test]
+fn test_jobs_wait_without_background_job() {
+    assert_eq!(
+        diagnostics("foo; wait"),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            WAIT_WITHOUT_JOB,
+            "`wait` is called before any commands are run in the background"
+        )]
+    );
+    assert_eq!(codes(diagnostics("fg %1")), vec![WAIT_WITHOUT_JOB]);
+    assert_eq!(
+        codes(diagnostics("if true; then wait; fi")),
+        vec![WAIT_WITHOUT_JOB]
+    );
+}
+
+#[test]
+fn test_jobs_deferred_wait_without_background_job_is_ignored() {
+    assert_eq!(
+        codes(diagnostics("f() { wait; }; a & f")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(
+        codes(diagnostics("while true; do wait; a & done")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(codes(diagnostics("disown")), Vec::<&str>::new());
+}
+
+#[test]
+fn test_jobs_nested_background_jobs() {
+    assert_eq!(
+        codes(diagnostics("{ a & wait; } & wait")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(codes(diagnostics("{ a & } && wait")), Vec::<&str>::new());
+    assert_eq!(codes(diagnostics("( a; b ) &")), vec![UNWAITED_JOB]);
+    assert!(diagnostics("( a; b ) &")[0].message.contains("`( ... )`"));
+}
@@ -0,0 +1,180 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::lint::{
+    ExitStatusComparison, Lint, Linter, UncheckedCd, UnquotedExpansion, UselessCat,
+    EXIT_STATUS_COMPARISON, UNCHECKED_CD, UNQUOTED_EXPANSION, USELESS_CAT,
+};
+use conch_parser::analysis::{glob, Diagnostic, Severity};
+use conch_parser::ast::TopLevelCommand;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn lint<L: Lint>(lint: L, src: &str) -> Vec<Diagnostic> {
+    lint.check(&parse(src))
+}
+
+fn count<L: Lint>(lint: L, src: &str) -> usize {
+    lint.check(&parse(src)).len()
+}
+
+#[test]
+fn test_unquoted_expansion() {
+    let diags = lint(
+        UnquotedExpansion,
+        "rm $file \"$dir\"/x; echo $(ls)/*; echo $1",
+    );
+    assert_eq!(diags.len(), 3);
+    assert_eq!(diags[0].code, UNQUOTED_EXPANSION);
+    assert_eq!(diags[0].severity, Severity::Warning);
+    assert_eq!(
+        diags[0].message,
+        "unquoted expansion in `${file}` is subject to field splitting \
+         and pathname expansion; double quote it"
+    );
+
+    let ignored = [
+        "echo \"$x\" '$y' \"$(z)\"",
+        "echo $? $# $$ $! ${#x} $((x + 1))",
+        "echo $@",
+        "x=$y cmd",
+        "local x=$y; export PATH=$PATH:$dir",
+        "for x in $list; do :; done",
+        "[[ -n $x ]]",
+    ];
+    for src in &ignored {
+        assert_eq!(count(UnquotedExpansion, src), 0, "{}", src);
+    }
+
+    assert_eq!(count(UnquotedExpansion, "local $x"), 1);
+    assert_eq!(count(UnquotedExpansion, "f() { cmd $*; }"), 1);
+}
+
+#[test]
+fn test_unchecked_cd() {
+    let diags = lint(UncheckedCd, "cd build\nmake");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, UNCHECKED_CD);
+    assert_eq!(diags[0].severity, Severity::Warning);
+
+    let reported = [
+        "cd dir; make",
+        "true || cd dir",
+        "{ cd dir; make; }",
+        "if true; then cd dir; fi",
+        "f() { cd \"$1\"; }",
+        "(cd dir; make)",
+    ];
+    for src in &reported {
+        assert_eq!(count(UncheckedCd, src), 1, "{}", src);
+    }
+
+    let ignored = [
+        "cd dir || exit",
+        "cd dir && make",
+        "if cd dir; then make; fi",
+        "while ! cd dir; do sleep 1; done",
+        "{ cd dir; make; } || exit 1",
+        "cd dir | cat",
+        "echo cd",
+    ];
+    for src in &ignored {
+        assert_eq!(count(UncheckedCd, src), 0, "{}", src);
+    }
+}
+
+#[test]
+fn test_useless_cat() {
+    let diags = lint(UselessCat, "cat \"$file\" | grep foo | wc -l");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, USELESS_CAT);
+    assert_eq!(diags[0].severity, Severity::Info);
+    assert_eq!(
+        diags[0].message,
+        "`cat \"${file}\"` only feeds a single file into the pipeline; \
+         redirect the input of the next command instead, e.g. `cmd <\"${file}\"`"
+    );
+
+    let ignored = [
+        "cat file",
+        "cat a b | grep foo",
+        "cat -n file | grep foo",
+        "cat - | grep foo",
+        "cat file 2>/dev/null | grep foo",
+        "grep foo | cat file",
+        "cat | grep foo",
+    ];
+    for src in &ignored {
+        assert_eq!(count(UselessCat, src), 0, "{}", src);
+    }
+
+    assert_eq!(count(UselessCat, "if true; then cat f | sort; fi"), 1);
+}
+
+#[test]
+fn test_exit_status_comparison() {
+    let diags = lint(ExitStatusComparison, "make; if [ $? -ne 0 ]; then exit; fi");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, EXIT_STATUS_COMPARISON);
+    assert_eq!(diags[0].severity, Severity::Info);
+
+    let reported = [
+        "test \"$?\" = 0",
+        "[[ $? != 0 ]]",
+        "[[ -f x && ! $? -eq 1 ]]",
+    ];
+    for src in &reported {
+        assert_eq!(count(ExitStatusComparison, src), 1, "{}", src);
+    }
+
+    let ignored = [
+        "echo $? -eq 0",
+        "[ -n \"$?\" ]",
+        "[ \"$?x\" = 0x ]",
+        "[[ $x == 0 ]]",
+        "exit $?",
+    ];
+    for src in &ignored {
+        assert_eq!(count(ExitStatusComparison, src), 0, "{}", src);
+    }
+}
+
+#[test]
+fn test_linter_runs_lints_in_order() {
+    let src = "cd $dir; cat f | rm -rf *; [ $? = 0 ]";
+
+    let linter = Linter::with_builtin_lints();
+    assert_eq!(linter.len(), 4);
+    let codes = linter
+        .check(&parse(src))
+        .into_iter()
+        .map(|d| d.code)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        vec![
+            UNQUOTED_EXPANSION,
+            UNCHECKED_CD,
+            USELESS_CAT,
+            EXIT_STATUS_COMPARISON
+        ]
+    );
+
+    let mut linter = Linter::new();
+    assert!(linter.is_empty());
+    linter
+        .add(glob::check)
+        .add(|cmds: &[TopLevelCommand<String>]| {
+            vec![Diagnostic::new(
+                Severity::Info,
+                "custom",
+                format!("{} commands", cmds.len()),
+            )]
+        })
+        .add(Linter::with_builtin_lints());
+
+    let diags = linter.check(&parse(src));
+    assert_eq!(diags.len(), 6);
+    assert_eq!(diags[0].code, glob::DESTRUCTIVE_GLOB);
+    assert_eq!(diags[1].to_string(), "info[custom]: 3 commands");
+    assert_eq!(diags[2].code, UNQUOTED_EXPANSION);
+}
@@ -0,0 +1,123 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::loop_control::{
+    check, COUNT_OUT_OF_RANGE, OUTSIDE_LOOP, RETURN_OUTSIDE_FUNCTION,
+};
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::parse::Dialect;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn diagnostics(src: &str, dialect: Dialect) -> Vec<Diagnostic> {
+    check(&parse(src), dialect)
+}
+
+#[test]
+fn test_loop_control_outside_loop() {
+    assert_eq!(
+        diagnostics("if true; then break; fi", Dialect::Bash),
+        vec![Diagnostic::new(
+            Severity::Error,
+            OUTSIDE_LOOP,
+            "`break` is not within a loop"
+        )]
+    );
+    assert_eq!(
+        codes(diagnostics("continue 2", Dialect::Bash)),
+        vec![OUTSIDE_LOOP]
+    );
+    assert_eq!(
+        codes(diagnostics("{ break; } | (continue)", Dialect::Bash)),
+        vec![OUTSIDE_LOOP, OUTSIDE_LOOP]
+    );
+}
+
+#[test]
+fn test_loop_control_within_loops() {
+    let sources = [
+        "while true; do break; done",
+        "until false; do continue; done",
+        "for x in a b; do if [ $x ]; then break; fi; done",
+        "while break; do :; done",
+        "for x; do case $x in a) continue;; esac; done",
+        "while :; do for x in a; do break 2; done; done",
+        "for x; do (break); done",
+        "for x; do break $n; done",
+    ];
+
+    for src in &sources {
+        assert_eq!(
+            codes(diagnostics(src, Dialect::Bash)),
+            Vec::<&str>::new(),
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_loop_control_count_exceeds_depth() {
+    assert_eq!(
+        diagnostics(
+            "while :; do for x in a; do break 3; done; done",
+            Dialect::Bash
+        ),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            COUNT_OUT_OF_RANGE,
+            "`break 3` exceeds the 2 enclosing loop(s): `while` > `for`"
+        )]
+    );
+}
+
+#[test]
+fn test_loop_control_invalid_count() {
+    assert_eq!(
+        diagnostics("for x; do continue 0; done", Dialect::Posix),
+        vec![Diagnostic::new(
+            Severity::Error,
+            COUNT_OUT_OF_RANGE,
+            "`continue 0` is invalid, the loop count must be at least 1"
+        )]
+    );
+}
+
+#[test]
+fn test_loop_control_function_bodies_are_not_within_enclosing_loops() {
+    assert_eq!(
+        diagnostics("for x; do f() { break; }; done", Dialect::Bash),
+        vec![Diagnostic::new(
+            Severity::Error,
+            OUTSIDE_LOOP,
+            "`break` is not within a loop in the body of function `f`"
+        )]
+    );
+    assert_eq!(
+        codes(diagnostics(
+            "f() { while :; do break; done; }; for x; do f; done",
+            Dialect::Bash
+        )),
+        Vec::<&str>::new()
+    );
+}
+
+#[test]
+fn test_loop_control_return_outside_function() {
+    assert_eq!(
+        diagnostics("return 1", Dialect::Bash),
+        vec![Diagnostic::new(
+            Severity::Warning,
+            RETURN_OUTSIDE_FUNCTION,
+            "`return` is not within a function, and will fail unless the script is sourced"
+        )]
+    );
+    assert_eq!(diagnostics("return 1", Dialect::Posix), vec![]);
+    assert_eq!(
+        codes(diagnostics("f() { if :; then return; fi; }", Dialect::Bash)),
+        Vec::<&str>::new()
+    );
+    assert_eq!(
+        codes(diagnostics("for x; do return; done", Dialect::Bash)),
+        vec![RETURN_OUTSIDE_FUNCTION]
+    );
+}
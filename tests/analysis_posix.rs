@@ -0,0 +1,144 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::posix::{convert, Conversion, Conversions, UNCONVERTED};
+use conch_parser::ast::printer::ToShellString;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn converted_with(src: &str, conversions: Conversions) -> (String, Conversion) {
+    let mut cmds = parse(src);
+    let conversion = convert(&mut cmds, conversions);
+    (cmds.to_shell_string(), conversion)
+}
+
+fn converted(src: &str) -> String {
+    let (out, conversion) = converted_with(src, Conversions::default());
+    assert_eq!(conversion.unconverted, vec![], "{:?}", src);
+    out
+}
+
+fn unconverted(src: &str) -> Vec<String> {
+    let (_, conversion) = converted_with(src, Conversions::default());
+    assert!(conversion.unconverted.iter().all(|d| d.code == UNCONVERTED));
+    conversion
+        .unconverted
+        .into_iter()
+        .map(|d| d.message)
+        .collect()
+}
+
+#[test]
+fn test_posix_converts_simple_conditionals() {
+    assert_eq!(converted("[[ $x == y ]]"), "[ \"$x\" = \"y\" ]\n");
+    assert_eq!(
+        converted("[[ $x != 'a b'\"$y\" ]]"),
+        "[ \"$x\" != 'a b'\"$y\" ]\n"
+    );
+    assert_eq!(converted("[[ $x ]]"), "[ -n \"$x\" ]\n");
+    assert_eq!(converted("[[ -f *.txt ]] >out"), "[ -f \"*.txt\" ] >out\n");
+    assert_eq!(converted("! [[ ! -d $d ]]"), "! [ ! -d \"$d\" ]\n");
+    assert_eq!(
+        converted("[[ $# -gt 1 ]] && a"),
+        "[ \"$#\" -gt \"1\" ] && a\n"
+    );
+    assert_eq!(converted("[[ \\a == \\$ ]]"), "[ \"a\" = \"\\$\" ]\n");
+}
+
+#[test]
+fn test_posix_reports_complex_conditionals() {
+    let sources = [
+        "[[ $x == y* ]]",
+        "[[ $x == $pattern ]]",
+        "[[ $x =~ ^a ]]",
+        "[[ a < b ]]",
+        "[[ x+1 -eq 2 ]]",
+        "[[ -v x ]]",
+        "[[ ~ == x ]]",
+        "[[ a && b ]]",
+        "[[ ! ! a ]]",
+    ];
+
+    for src in &sources {
+        let messages = unconverted(src);
+        assert_eq!(messages.len(), 1, "{:?}", src);
+        assert!(messages[0].starts_with("`[[ "), "{:?}", messages);
+
+        // The command is left as is
+        assert_eq!(
+            converted_with(src, Conversions::default()).0,
+            parse(src).to_shell_string()
+        );
+    }
+}
+
+#[test]
+fn test_posix_converts_redirects_and_pipes() {
+    assert_eq!(converted("cmd &>out arg"), "cmd >out 2>&1 arg\n");
+    assert_eq!(converted("&>>log cmd"), ">>log 2>&1 cmd\n");
+    assert_eq!(converted("{ a; } &>out"), "{\n    a\n} >out 2>&1\n");
+    assert_eq!(converted("a >x |& b |& c"), "a >x 2>&1 | b 2>&1 | c\n");
+    assert_eq!(converted("(a) |& b"), "(\n    a\n) 2>&1 | b\n");
+    assert_eq!(converted("echo $(< file)"), "echo $(cat file)\n");
+}
+
+#[test]
+fn test_posix_reports_unconvertible_constructs() {
+    assert_eq!(
+        unconverted("diff <(a) >(b); echo ${x:1} ${x/a/b} ${x,,} ${x^}"),
+        vec![
+            "`<(...)` process substitutions cannot be converted",
+            "`>(...)` process substitutions cannot be converted",
+            "`${x:offset}` substrings cannot be converted",
+            "`${x/pattern/string}` replacements cannot be converted",
+            "`${x,}` lowercase conversions cannot be converted",
+            "`${x^}` uppercase conversions cannot be converted",
+        ]
+    );
+
+    assert_eq!(
+        unconverted("case x in a) ;& b) ;;& esac"),
+        vec![
+            "`;&` case terminators cannot be converted",
+            "`;;&` case terminators cannot be converted",
+        ]
+    );
+
+    assert_eq!(
+        unconverted("echo $(( 2 ** x++ + a[1] + 2#10 + 0x10 ))"),
+        vec![
+            "`**` within arithmetic cannot be converted",
+            "`++` within arithmetic cannot be converted",
+            "array indexing within arithmetic cannot be converted",
+            "`base#digits` literals within arithmetic cannot be converted",
+        ]
+    );
+
+    assert_eq!(
+        unconverted("echo $'a\\tb'"),
+        vec![
+            "`$'a\\tb'` quoting cannot be converted, consider \"$(printf 'a\\tb')\" \
+             instead (which strips any trailing newlines)"
+        ]
+    );
+}
+
+#[test]
+fn test_posix_reports_disabled_conversions() {
+    let src = "[[ -n $x ]] &>out |& b; echo $(< f)";
+    let none = Conversions {
+        conditionals: false,
+        redirect_both: false,
+        pipe_stderr: false,
+        read_file: false,
+    };
+
+    let (out, conversion) = converted_with(src, none);
+    assert_eq!(out, parse(src).to_shell_string());
+    assert_eq!(conversion.converted, 0);
+    assert_eq!(conversion.unconverted.len(), 4);
+
+    let (out, conversion) = converted_with(src, Conversions::default());
+    assert_eq!(out, "[ -n \"$x\" ] >out 2>&1 2>&1 | b\necho $(cat f)\n");
+    assert_eq!(conversion.converted, 4);
+    assert!(conversion.unconverted.is_empty());
+}
@@ -0,0 +1,157 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::quoting::{
+    check, fix, suggest, PREFER_SINGLE_QUOTES, SINGLE_QUOTED_EXPANSION, UNQUOTED_AT,
+};
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::DefaultWord;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn lint(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src))
+}
+
+fn fixed(src: &str) -> String {
+    let mut cmds = parse(src);
+    fix(&mut cmds);
+    cmds.to_shell_string()
+}
+
+#[test]
+fn test_quoting_double_quotes_without_expansions() {
+    assert_eq!(
+        codes(lint("echo \"hello world\"")),
+        vec![PREFER_SINGLE_QUOTES]
+    );
+    assert_eq!(
+        codes(lint("foo=\"bar\" cmd >\"out.txt\"")),
+        vec![PREFER_SINGLE_QUOTES; 2]
+    );
+
+    let diag = &lint("echo \"hello world\"")[0];
+    assert_eq!(diag.severity, Severity::Info);
+    assert_eq!(
+        diag.message,
+        "\"hello world\" contains no expansions or escapes, use 'hello world' instead"
+    );
+}
+
+#[test]
+fn test_quoting_double_quotes_which_are_needed() {
+    assert!(lint("echo \"$foo\" \"a${b}c\" \"$(date)\"").is_empty());
+    assert!(lint("echo \"a\\$b\" \"c\\\\d\"").is_empty());
+    assert!(lint("echo \"it's\"").is_empty());
+    assert!(lint("echo \"\"").is_empty());
+}
+
+#[test]
+fn test_quoting_single_quoted_expansion() {
+    assert_eq!(
+        codes(lint("echo '$HOME/bin'")),
+        vec![SINGLE_QUOTED_EXPANSION]
+    );
+    assert_eq!(codes(lint("echo 'a${b}c'")), vec![SINGLE_QUOTED_EXPANSION]);
+
+    let diag = &lint("echo 'hello $name'")[0];
+    assert_eq!(diag.severity, Severity::Warning);
+    assert_eq!(
+        diag.message,
+        "$name is not expanded within single quotes in 'hello $name', use \"hello $name\" if it should be"
+    );
+}
+
+#[test]
+fn test_quoting_single_quotes_without_expansions() {
+    assert!(lint("echo 'plain' 'costs $5' 'a $ b' '${1}'").is_empty());
+}
+
+#[test]
+fn test_quoting_code_commands_are_ignored() {
+    assert!(lint("awk '{ print $NF }' file").is_empty());
+    assert!(lint("/bin/sh -c 'echo $HOME'").is_empty());
+    assert!(lint("trap 'rm -f $tmp' EXIT").is_empty());
+    assert!(lint("sed -e 's/$foo/bar/'").is_empty());
+
+    // Arguments of other commands nested within code commands are still checked
+    assert_eq!(
+        codes(lint("sh -c \"$(echo '$HOME')\"")),
+        vec![SINGLE_QUOTED_EXPANSION]
+    );
+}
+
+#[test]
+fn test_quoting_nested_words_are_checked() {
+    assert_eq!(
+        codes(lint("echo $(echo \"foo\")")),
+        vec![PREFER_SINGLE_QUOTES]
+    );
+    assert_eq!(
+        codes(lint("echo ${x:-\"foo\"}")),
+        vec![PREFER_SINGLE_QUOTES]
+    );
+    assert_eq!(
+        codes(lint("for x in \"a\" 'b$c'; do :; done")),
+        vec![PREFER_SINGLE_QUOTES, SINGLE_QUOTED_EXPANSION]
+    );
+}
+
+#[test]
+fn test_quoting_unquoted_at() {
+    assert_eq!(codes(lint("cp $@ ${@} dest")), vec![UNQUOTED_AT; 2]);
+    assert_eq!(codes(lint("for f in x$@; do :; done")), vec![UNQUOTED_AT]);
+    assert!(lint("cp \"$@\" $* \"${@:-x}\" dest").is_empty());
+
+    let diag = &lint("rm $@")[0];
+    assert_eq!(diag.severity, Severity::Warning);
+    assert_eq!(
+        diag.message,
+        "$@ splits every positional parameter into fields, use \"$@\" to keep each one intact"
+    );
+    assert_eq!(fixed("rm -f $@ x$@"), "rm -f \"$@\" x\"$@\"\n");
+}
+
+#[test]
+fn test_quoting_heredoc_bodies_are_not_checked() {
+    assert!(lint("cat <<EOF\n\"foo\" '$bar'\nEOF\n").is_empty());
+}
+
+#[test]
+fn test_quoting_suggestions() {
+    let suggestions = suggest(&parse("echo \"foo\" '$bar'"));
+    assert_eq!(suggestions.len(), 2);
+
+    assert_eq!(
+        suggestions[0].original,
+        DefaultWord::DoubleQuoted(vec![conch_parser::ast::SimpleWord::Literal("foo".into())])
+    );
+    assert_eq!(
+        suggestions[0].replacement,
+        DefaultWord::SingleQuoted("foo".into())
+    );
+    assert_eq!(suggestions[1].diagnostic.code, SINGLE_QUOTED_EXPANSION);
+}
+
+#[test]
+fn test_quoting_fix() {
+    assert_eq!(fixed("echo \"hello world\""), "echo 'hello world'\n");
+    assert_eq!(fixed("echo '$HOME/bin'"), "echo \"$HOME/bin\"\n");
+    assert_eq!(fixed("echo '${a}b'"), "echo \"${a}b\"\n");
+    assert_eq!(fixed("echo 'x=$x \"`\\'"), "echo \"x=$x \\\"\\`\\\\\"\n");
+    assert_eq!(
+        fixed("awk '{ print $1 }' \"f\""),
+        "awk '{ print $1 }' 'f'\n"
+    );
+
+    let mut cmds = parse("echo \"a\" \"$b\" 'c$d'");
+    assert_eq!(fix(&mut cmds), 2);
+    assert!(check(&cmds).is_empty());
+}
+
+#[test]
+fn test_quoting_fixed_source_parses_into_fixed_ast() {
+    let mut cmds = parse("echo '$a \\ ` \" $' \"b\"; x='${y}z'");
+    fix(&mut cmds);
+    assert_eq!(parse(&cmds.to_shell_string()), cmds);
+}
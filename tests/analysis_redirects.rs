@@ -0,0 +1,111 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::redirects::{redirect_scopes, RedirectScope, ScopedCommand};
+use conch_parser::ast::{DefaultRedirect, Redirect};
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Renders a redirect along the lines of how it was written, e.g. `2>log`.
+fn render(redirect: &DefaultRedirect) -> String {
+    let op = match *redirect {
+        Redirect::Read(..) => "<",
+        Redirect::Write(..) => ">",
+        Redirect::ReadWrite(..) => "<>",
+        Redirect::Append(..) => ">>",
+        Redirect::Clobber(..) => ">|",
+        Redirect::Heredoc(..) => "<<",
+        Redirect::DupRead(..) => "<&",
+        Redirect::DupWrite(..) => ">&",
+        Redirect::WriteBoth(_) => "&>",
+        Redirect::AppendBoth(_) => "&>>",
+        _ => unimplemented!(),
+    };
+    let fd = redirect.fd().map_or(String::new(), |fd| fd.to_string());
+    format!("{}{}{}", fd, op, redirect.word())
+}
+
+/// Returns the inherited and own redirects of each scope, rendered.
+fn scopes(src: &str) -> Vec<(Vec<String>, Vec<String>)> {
+    let cmds = parse(src);
+    redirect_scopes(&cmds)
+        .iter()
+        .map(|scope| {
+            let inherited = scope.inherited.iter().map(|r| render(r)).collect();
+            let own = scope.own.iter().map(|r| render(r)).collect();
+            (inherited, own)
+        })
+        .collect()
+}
+
+fn strings(strs: &[&str]) -> Vec<String> {
+    strs.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_redirect_scopes_of_nested_commands() {
+    let src = "{ a 2>err; while b; do c >out; done <in; } >log; d >other";
+
+    assert_eq!(
+        scopes(src),
+        vec![
+            (strings(&[">log"]), strings(&["2>err"])),
+            (strings(&[">log"]), strings(&["<in"])),
+            (strings(&[">log", "<in"]), strings(&[])),
+            (strings(&[">log", "<in"]), strings(&[">out"])),
+        ]
+    );
+
+    let cmds = parse(src);
+    let scopes = redirect_scopes(&cmds);
+    assert!(matches!(scopes[0].command, ScopedCommand::Simple(_)));
+    assert!(matches!(scopes[1].command, ScopedCommand::Compound(_)));
+    assert_eq!(
+        scopes[3].redirects().map(render).collect::<Vec<_>>(),
+        strings(&[">log", "<in", ">out"])
+    );
+}
+
+#[test]
+fn test_redirect_scopes_skip_commands_without_inherited_redirects() {
+    let sources = [
+        "a >out; { b; c 2>err; }",
+        "if a; then b; fi >/dev/null; c",
+        "f() { a; } 2>err; g() { b; }",
+    ];
+
+    let counts = sources
+        .iter()
+        .map(|src| scopes(src).len())
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![0, 2, 1]);
+}
+
+#[test]
+fn test_redirect_scopes_of_function_bodies() {
+    // The body of a function does not inherit the redirects where it is declared,
+    // thus neither it nor its declaration are reported
+    assert_eq!(
+        scopes("{ f() { a; } >log; b; } 2>err"),
+        vec![
+            (strings(&[">log"]), strings(&[])),
+            (strings(&["2>err"]), strings(&[])),
+        ]
+    );
+}
+
+#[test]
+fn test_redirect_scope_target() {
+    let cmds = parse("{ ( a 2>&1; b 3<in | c ) &>all; } >out <input");
+    let scopes = redirect_scopes(&cmds);
+    let target = |scope: &RedirectScope<'_>, fd| scope.target(fd).map(render);
+
+    // The subshell itself, followed by `a`, `b`, and `c`
+    assert_eq!(scopes.len(), 4);
+    assert_eq!(target(&scopes[0], 0), Some("<input".to_owned()));
+    assert_eq!(target(&scopes[0], 1), Some("&>all".to_owned()));
+    assert_eq!(target(&scopes[1], 1), Some("&>all".to_owned()));
+    assert_eq!(target(&scopes[1], 2), Some("2>&1".to_owned()));
+    assert_eq!(target(&scopes[2], 3), Some("3<in".to_owned()));
+    assert_eq!(target(&scopes[3], 3), None);
+    assert_eq!(target(&scopes[3], 0), Some("<input".to_owned()));
+}
@@ -0,0 +1,145 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::report::ParseReport;
+use conch_parser::analysis::{glob, Severity};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn record(report: &mut ParseReport, src: &str) {
+    let result = make_parser(src).into_iter().collect::<Result<Vec<_>, _>>();
+    if let Ok(ref cmds) = result {
+        report.record_diagnostics(glob::check(cmds));
+    }
+    report.record_script(&result);
+}
+
+fn record_with_recovery(report: &mut ParseReport, src: &str) {
+    let parsed = make_parser(src).parse_with_recovery().unwrap();
+    report.record_diagnostics(glob::check(&parsed.commands));
+    report.record_partial(&parsed);
+}
+
+#[test]
+fn test_report_counts_scripts_and_severities() {
+    let mut report = ParseReport::new();
+    record(&mut report, "rm -rf *; ls *.txt");
+    record(&mut report, "if true; then echo; fi");
+    record(&mut report, "case foo");
+
+    assert_eq!(report.scripts(), 3);
+    assert_eq!(report.failed(), 1);
+    assert_eq!(report.count(Severity::Error), 1);
+    assert_eq!(report.count(Severity::Warning), 1);
+    assert_eq!(report.count(Severity::Info), 1);
+    assert_eq!(report.count_code("parse-incomplete-cmd"), 1);
+    assert_eq!(report.count_code(glob::DESTRUCTIVE_GLOB), 1);
+}
+
+#[test]
+fn test_report_construct_statistics() {
+    let mut report = ParseReport::new();
+    record(&mut report, "foo() { for x in a; do bar; done; }\nfoo");
+    record(&mut report, "for y in b; do (baz); done");
+
+    assert_eq!(report.construct_count("function"), 1);
+    assert_eq!(report.construct_count("for"), 2);
+    assert_eq!(report.construct_count("brace group"), 1);
+    assert_eq!(report.construct_count("subshell"), 1);
+    assert_eq!(report.construct_count("simple command"), 3);
+    assert_eq!(report.construct_count("case"), 0);
+}
+
+#[test]
+fn test_report_merge() {
+    let mut first = ParseReport::new();
+    record(&mut first, "for x in a; do :; done");
+
+    let mut second = ParseReport::new();
+    record(&mut second, "for x in *; do :; done");
+    record(&mut second, "foo && )");
+
+    first.merge(second);
+    assert_eq!(first.scripts(), 3);
+    assert_eq!(first.failed(), 1);
+    assert_eq!(first.construct_count("for"), 2);
+    assert_eq!(first.diagnostics().len(), 2);
+}
+
+#[test]
+fn test_report_sorted_diagnostics_do_not_depend_on_merge_order() {
+    let sources = ["ls *.txt", "rm -rf *", "foo && )", "for x in *; do :; done"];
+    let report = |order: &[usize]| {
+        let mut merged = ParseReport::new();
+        for &i in order {
+            let mut report = ParseReport::new();
+            record(&mut report, sources[i]);
+            merged.merge(report);
+        }
+        merged
+    };
+
+    let mut forward = report(&[0, 1, 2, 3]);
+    let mut backward = report(&[3, 2, 1, 0]);
+    assert_ne!(forward, backward);
+
+    forward.sort_diagnostics();
+    backward.sort_diagnostics();
+    assert_eq!(forward, backward);
+    assert_eq!(forward.to_string(), backward.to_string());
+
+    let severities = forward
+        .diagnostics()
+        .iter()
+        .map(|d| d.severity)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        severities,
+        vec![
+            Severity::Error,
+            Severity::Warning,
+            Severity::Info,
+            Severity::Info
+        ]
+    );
+}
+
+#[test]
+fn test_report_summary_table() {
+    let mut report = ParseReport::new();
+    record(&mut report, "rm *; echo");
+    record(&mut report, ")");
+
+    let expected = "\
+scripts: 2 (1 failed)
+
+severity  count
+error         1
+warning       1
+info          0
+
+code              count
+glob-destructive      1
+parse-unexpected      1
+
+construct       count
+simple command      2
+";
+    assert_eq!(report.to_string(), expected);
+}
+
+#[test]
+fn test_report_records_every_error_of_a_partial_parse() {
+    let mut report = ParseReport::new();
+    record_with_recovery(
+        &mut report,
+        "echo )\nrm -rf *\nfoo && )\nfor x in a; do :; done",
+    );
+    record_with_recovery(&mut report, "echo ok");
+
+    assert_eq!(report.scripts(), 2);
+    assert_eq!(report.failed(), 1);
+    assert_eq!(report.count_code("parse-unexpected"), 2);
+    assert_eq!(report.count_code(glob::DESTRUCTIVE_GLOB), 1);
+    assert_eq!(report.construct_count("for"), 1);
+    assert_eq!(report.construct_count("simple command"), 4);
+}
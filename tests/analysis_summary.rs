@@ -0,0 +1,158 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::report::ParseReport;
+use conch_parser::analysis::summary::{DiagnosticsSummary, ExitPolicy, PolicyViolation};
+use conch_parser::analysis::{glob, Diagnostic, Severity};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn summary(diagnostics: &[(Severity, &'static str)]) -> DiagnosticsSummary {
+    diagnostics
+        .iter()
+        .map(|&(severity, code)| Diagnostic::new(severity, code, "message"))
+        .collect::<Vec<_>>()
+        .iter()
+        .collect()
+}
+
+#[test]
+fn test_summary_counts_severities_and_codes() {
+    let s = summary(&[
+        (Severity::Warning, "b"),
+        (Severity::Error, "a"),
+        (Severity::Warning, "b"),
+        (Severity::Info, "c"),
+    ]);
+
+    assert_eq!(s.total(), 4);
+    assert!(!s.is_empty());
+    assert_eq!(s.count(Severity::Error), 1);
+    assert_eq!(s.count(Severity::Warning), 2);
+    assert_eq!(s.count(Severity::Info), 1);
+    assert_eq!(s.count_at_least(Severity::Warning), 3);
+    assert_eq!(s.count_code("b"), 2);
+    assert_eq!(s.count_code("missing"), 0);
+    assert_eq!(s.max_severity(), Some(Severity::Error));
+    assert_eq!(
+        s.codes().collect::<Vec<_>>(),
+        vec![("a", 1), ("b", 2), ("c", 1)]
+    );
+    assert_eq!(s.to_string(), "1 error, 2 warnings, 1 info");
+
+    let empty = DiagnosticsSummary::new();
+    assert!(empty.is_empty());
+    assert_eq!(empty.max_severity(), None);
+    assert_eq!(empty.to_string(), "no diagnostics");
+}
+
+#[test]
+fn test_summary_merge() {
+    let mut s = summary(&[(Severity::Warning, "a")]);
+    s.merge(summary(&[(Severity::Warning, "a"), (Severity::Info, "b")]));
+    assert_eq!(
+        s,
+        summary(&[
+            (Severity::Info, "b"),
+            (Severity::Warning, "a"),
+            (Severity::Warning, "a"),
+        ])
+    );
+}
+
+#[test]
+fn test_default_policy_fails_on_errors_only() {
+    let policy = ExitPolicy::default();
+    assert!(policy.passes(&DiagnosticsSummary::new()));
+    assert!(policy.passes(&summary(&[(Severity::Warning, "a"), (Severity::Info, "b")])));
+
+    let errors = summary(&[(Severity::Error, "a"), (Severity::Error, "b")]);
+    assert_eq!(
+        policy.violations(&errors),
+        vec![PolicyViolation::Severity {
+            severity: Severity::Error,
+            count: 2,
+        }]
+    );
+    assert_eq!(policy.exit_code(&errors), 1);
+}
+
+#[test]
+fn test_policy_thresholds() {
+    let policy = ExitPolicy {
+        fail_on: None,
+        max_warnings: Some(1),
+        fail_codes: vec!["z".to_owned(), "c".to_owned()].into_iter().collect(),
+        failure_exit_code: 3,
+    };
+
+    let s = summary(&[
+        (Severity::Error, "a"),
+        (Severity::Warning, "b"),
+        (Severity::Info, "c"),
+    ]);
+    assert!(policy.passes(&summary(&[
+        (Severity::Error, "a"),
+        (Severity::Warning, "b")
+    ])));
+    assert_eq!(
+        policy.violations(&s),
+        vec![PolicyViolation::Code {
+            code: "c".to_owned(),
+            count: 1,
+        }]
+    );
+    assert_eq!(policy.exit_code(&s), 3);
+
+    let s = summary(&[
+        (Severity::Warning, "z"),
+        (Severity::Warning, "b"),
+        (Severity::Info, "c"),
+    ]);
+    let violations = policy.violations(&s);
+    assert_eq!(
+        violations,
+        vec![
+            PolicyViolation::TooManyWarnings { count: 2, max: 1 },
+            PolicyViolation::Code {
+                code: "c".to_owned(),
+                count: 1,
+            },
+            PolicyViolation::Code {
+                code: "z".to_owned(),
+                count: 1,
+            },
+        ]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "2 warning(s) reported, at most 1 allowed"
+    );
+
+    let policy = ExitPolicy {
+        fail_on: Some(Severity::Warning),
+        ..ExitPolicy::default()
+    };
+    assert!(!policy.passes(&summary(&[(Severity::Warning, "b")])));
+    assert!(policy.passes(&summary(&[(Severity::Info, "c")])));
+}
+
+#[test]
+fn test_report_summary() {
+    let mut report = ParseReport::new();
+    let result = make_parser("rm -rf *")
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>();
+    report.record_diagnostics(glob::check(result.as_ref().unwrap()));
+    report.record_script(&result);
+    report.record_script(
+        &make_parser("case foo")
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>(),
+    );
+
+    let s = report.summary();
+    assert_eq!(s.count(Severity::Error), report.count(Severity::Error));
+    assert_eq!(s.count(Severity::Warning), report.count(Severity::Warning));
+    assert_eq!(s.count_code(glob::DESTRUCTIVE_GLOB), 1);
+    assert_eq!(ExitPolicy::default().exit_code(&s), 1);
+}
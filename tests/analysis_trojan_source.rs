@@ -0,0 +1,120 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::control_chars::{BIDI_CONTROL, CONTROL_CHAR, ZERO_WIDTH};
+use conch_parser::analysis::trojan_source::{scan, TextContext, CONFUSABLE};
+use conch_parser::analysis::Severity;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn summary(source: &str) -> Vec<(&'static str, TextContext)> {
+    scan(source)
+        .into_iter()
+        .map(|f| (f.diagnostic.code, f.context))
+        .collect()
+}
+
+#[test]
+fn test_trojan_source_bidi_in_comment_with_span() {
+    let findings = scan("echo ok\n# \u{202E} } \u{2066}\n");
+    assert_eq!(findings.len(), 2);
+
+    assert_eq!(findings[0].diagnostic.code, BIDI_CONTROL);
+    assert_eq!(findings[0].diagnostic.severity, Severity::Error);
+    assert_eq!(findings[0].context, TextContext::Comment);
+    assert_eq!(findings[0].start, src(10, 2, 3));
+    assert_eq!(findings[0].end, src(13, 2, 6));
+    assert_eq!(
+        findings[0].to_string(),
+        "2:3: error[bidi-control]: bidirectional control character U+202E in comment"
+    );
+
+    assert_eq!(findings[1].start, src(16, 2, 9));
+}
+
+#[test]
+fn test_trojan_source_contexts() {
+    assert_eq!(
+        summary("echo '\u{202E}' \"\u{200B}\" a\u{202E}"),
+        vec![
+            (BIDI_CONTROL, TextContext::Quoted),
+            (ZERO_WIDTH, TextContext::Quoted),
+            (BIDI_CONTROL, TextContext::Code),
+        ]
+    );
+}
+
+#[test]
+fn test_trojan_source_pound_within_word_is_not_a_comment() {
+    assert_eq!(
+        summary("echo a#\u{200B}"),
+        vec![(ZERO_WIDTH, TextContext::Code)]
+    );
+    assert_eq!(
+        summary("echo \\# \u{200B}"),
+        vec![(ZERO_WIDTH, TextContext::Code)]
+    );
+}
+
+#[test]
+fn test_trojan_source_quotes_are_tracked() {
+    assert_eq!(
+        summary("echo \"a\\\"#\" \u{0441}url"),
+        vec![(CONFUSABLE, TextContext::Code)]
+    );
+    assert_eq!(
+        summary("echo \\' \u{0441}url"),
+        vec![(CONFUSABLE, TextContext::Code)]
+    );
+    assert_eq!(
+        summary("echo '\\' \u{0441}url"),
+        vec![(CONFUSABLE, TextContext::Code)]
+    );
+}
+
+#[test]
+fn test_trojan_source_confusables_only_reported_in_code() {
+    let findings = scan("\u{0435}cho \u{2013}rf # \u{0435}\necho '\u{0435}'");
+    assert_eq!(findings.len(), 2);
+    assert_eq!(
+        findings[0].diagnostic.message,
+        "`\u{0435}` (U+0435) in code looks like `e`"
+    );
+    assert_eq!(findings[0].start, src(0, 1, 1));
+    assert_eq!(findings[1].diagnostic.code, CONFUSABLE);
+    assert_eq!(
+        findings[1].diagnostic.message,
+        "`\u{2013}` (U+2013) in code looks like `-`"
+    );
+}
+
+#[test]
+fn test_trojan_source_fullwidth_confusables() {
+    let findings = scan("\u{FF52}m x");
+    assert_eq!(findings.len(), 1);
+    assert!(findings[0].diagnostic.message.ends_with("looks like `r`"));
+}
+
+#[test]
+fn test_trojan_source_control_chars() {
+    assert_eq!(
+        summary("echo a\x1bb\t\n"),
+        vec![(CONTROL_CHAR, TextContext::Code)]
+    );
+    assert!(scan("echo \u{FFFD} caf\u{e9} # \u{4e2d}\u{6587}").is_empty());
+}
+
+#[test]
+fn test_trojan_source_contexts_follow_the_parser() {
+    assert_eq!(
+        summary("echo \"$(\u{0435}cho x) \u{0435}\""),
+        vec![(CONFUSABLE, TextContext::Code)]
+    );
+    assert_eq!(
+        summary("cat <<EOF\n\u{0435}cho \u{202E}\nEOF\n\u{0435}cho"),
+        vec![
+            (BIDI_CONTROL, TextContext::Quoted),
+            (CONFUSABLE, TextContext::Code),
+        ]
+    );
+    assert_eq!(summary("case x in a) # \u{0435}\n;; esac"), vec![]);
+}
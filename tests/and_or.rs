@@ -20,6 +20,24 @@ fn test_and_or_correct_associativity() {
     assert_eq!(correct, p.and_or_list().unwrap());
 }
 
+#[test]
+fn test_and_or_long_chain_is_flat() {
+    let n = 10_000;
+    let source = vec!["foo"; n + 1].join(" && ");
+    let mut p = make_parser(&source);
+    let list = p.and_or_list().unwrap();
+
+    assert_eq!(
+        list.first,
+        ListableCommand::Single(Simple(cmd_simple("foo")))
+    );
+    assert_eq!(list.rest.len(), n);
+    assert!(list
+        .rest
+        .iter()
+        .all(|cmd| *cmd == AndOr::And(ListableCommand::Single(Simple(cmd_simple("foo"))))));
+}
+
 #[test]
 fn test_and_or_valid_with_newlines_after_operator() {
     let mut p = make_parser("foo ||\n\n\n\nbar && baz");
@@ -2,6 +2,9 @@
 use conch_parser::ast::Arithmetic::*;
 use conch_parser::ast::DefaultArithmetic as Arithmetic;
 use conch_parser::ast::ParameterSubstitution::Arith;
+use conch_parser::ast::Radix;
+use conch_parser::ast::{ArithmeticTarget, AssignOperator};
+use conch_parser::parse::Dialect;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -24,15 +27,42 @@ fn test_arithmetic_substitution_valid() {
         ("$(( x ))", *x()),
         ("$(( 5 ))", Literal(5)),
         ("$(( 0 ))", Literal(0)),
-        ("$(( 010 ))", Literal(8)),
-        ("$(( 0xa ))", Literal(10)),
-        ("$(( 0Xa ))", Literal(10)),
-        ("$(( 0xA ))", Literal(10)),
-        ("$(( 0XA ))", Literal(10)),
-        ("$(( x++ ))", PostIncr(String::from("x"))),
-        ("$(( x-- ))", PostDecr(String::from("x"))),
-        ("$(( ++x ))", PreIncr(String::from("x"))),
-        ("$(( --x ))", PreDecr(String::from("x"))),
+        (
+            "$(( 010 ))",
+            RadixLiteral(8, Radix::Octal, String::from("010")),
+        ),
+        (
+            "$(( 0xa ))",
+            RadixLiteral(10, Radix::Hex, String::from("0xa")),
+        ),
+        (
+            "$(( 0Xa ))",
+            RadixLiteral(10, Radix::Hex, String::from("0Xa")),
+        ),
+        (
+            "$(( 0xA ))",
+            RadixLiteral(10, Radix::Hex, String::from("0xA")),
+        ),
+        (
+            "$(( 0XA ))",
+            RadixLiteral(10, Radix::Hex, String::from("0XA")),
+        ),
+        (
+            "$(( x++ ))",
+            PostIncr(ArithmeticTarget::Var(String::from("x"))),
+        ),
+        (
+            "$(( x-- ))",
+            PostDecr(ArithmeticTarget::Var(String::from("x"))),
+        ),
+        (
+            "$(( ++x ))",
+            PreIncr(ArithmeticTarget::Var(String::from("x"))),
+        ),
+        (
+            "$(( --x ))",
+            PreDecr(ArithmeticTarget::Var(String::from("x"))),
+        ),
         ("$(( +x ))", UnaryPlus(x())),
         ("$(( -x ))", UnaryMinus(x())),
         ("$(( !x ))", LogicalNot(x())),
@@ -57,51 +87,97 @@ fn test_arithmetic_substitution_valid() {
         ("$(( x && y ))", LogicalAnd(x(), y())),
         ("$(( x || y ))", LogicalOr(x(), y())),
         ("$(( x ? y : z ))", Ternary(x(), y(), z())),
-        ("$(( x = y ))", Assign(String::from("x"), y())),
+        (
+            "$(( x = y ))",
+            Assign(ArithmeticTarget::Var(String::from("x")), y()),
+        ),
         (
             "$(( x *= y ))",
-            Assign(String::from("x"), Box::new(Mult(x(), y()))),
+            AssignOp(
+                AssignOperator::Mult,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x /= y ))",
-            Assign(String::from("x"), Box::new(Div(x(), y()))),
+            AssignOp(
+                AssignOperator::Div,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x %= y ))",
-            Assign(String::from("x"), Box::new(Modulo(x(), y()))),
+            AssignOp(
+                AssignOperator::Modulo,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x += y ))",
-            Assign(String::from("x"), Box::new(Add(x(), y()))),
+            AssignOp(
+                AssignOperator::Add,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x -= y ))",
-            Assign(String::from("x"), Box::new(Sub(x(), y()))),
+            AssignOp(
+                AssignOperator::Sub,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x <<= y ))",
-            Assign(String::from("x"), Box::new(ShiftLeft(x(), y()))),
+            AssignOp(
+                AssignOperator::ShiftLeft,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x >>= y ))",
-            Assign(String::from("x"), Box::new(ShiftRight(x(), y()))),
+            AssignOp(
+                AssignOperator::ShiftRight,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x &= y ))",
-            Assign(String::from("x"), Box::new(BitwiseAnd(x(), y()))),
+            AssignOp(
+                AssignOperator::BitwiseAnd,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x ^= y ))",
-            Assign(String::from("x"), Box::new(BitwiseXor(x(), y()))),
+            AssignOp(
+                AssignOperator::BitwiseXor,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x |= y ))",
-            Assign(String::from("x"), Box::new(BitwiseOr(x(), y()))),
+            AssignOp(
+                AssignOperator::BitwiseOr,
+                ArithmeticTarget::Var(String::from("x")),
+                y(),
+            ),
         ),
         (
             "$(( x = 5, x + y ))",
             Sequence(vec![
-                Assign(String::from("x"), Box::new(Literal(5))),
+                Assign(
+                    ArithmeticTarget::Var(String::from("x")),
+                    Box::new(Literal(5)),
+                ),
                 Add(x(), y()),
             ]),
         ),
@@ -160,12 +236,14 @@ fn test_arithmetic_substitution_left_to_right_associativity() {
             }
         }};
 
-        (assig: $constructor:path, $op:tt) => {{
-            let correct = word_subst(Arith(Some(Assign(
-                String::from("x"),
-                Box::new($constructor(
-                    x(),
-                    Box::new(Assign(String::from("y"), Box::new($constructor(y(), z())))),
+        (assig: $constructor:ident, $op:tt) => {{
+            let correct = word_subst(Arith(Some(AssignOp(
+                AssignOperator::$constructor,
+                ArithmeticTarget::Var(String::from("x")),
+                Box::new(AssignOp(
+                    AssignOperator::$constructor,
+                    ArithmeticTarget::Var(String::from("y")),
+                    z(),
                 )),
             ))));
 
@@ -215,8 +293,8 @@ fn test_arithmetic_substitution_left_to_right_associativity() {
     check!(assig: BitwiseOr,  | );
 
     let correct = word_subst(Arith(Some(Assign(
-        String::from("x"),
-        Box::new(Assign(String::from("y"), z())),
+        ArithmeticTarget::Var(String::from("x")),
+        Box::new(Assign(ArithmeticTarget::Var(String::from("y")), z())),
     ))));
     assert_eq!(
         correct,
@@ -399,6 +477,114 @@ fn test_arithmetic_substitution_invalid() {
     }
 }
 
+#[test]
+fn test_arithmetic_substitution_numbers_with_bases() {
+    let cases = vec![
+        ("0x", RadixLiteral(0, Radix::Hex, String::from("0x"))),
+        ("0x1F", RadixLiteral(31, Radix::Hex, String::from("0x1F"))),
+        (
+            "0777",
+            RadixLiteral(511, Radix::Octal, String::from("0777")),
+        ),
+        (
+            "2#1010",
+            RadixLiteral(10, Radix::Explicit(2), String::from("2#1010")),
+        ),
+        (
+            "16#ff",
+            RadixLiteral(255, Radix::Explicit(16), String::from("16#ff")),
+        ),
+        (
+            "16#FF",
+            RadixLiteral(255, Radix::Explicit(16), String::from("16#FF")),
+        ),
+        (
+            "36#Zz",
+            RadixLiteral(35 * 36 + 35, Radix::Explicit(36), String::from("36#Zz")),
+        ),
+        (
+            "64#aA@_",
+            RadixLiteral(
+                ((10 * 64 + 36) * 64 + 62) * 64 + 63,
+                Radix::Explicit(64),
+                String::from("64#aA@_"),
+            ),
+        ),
+        (
+            "10#0042",
+            RadixLiteral(42, Radix::Explicit(10), String::from("10#0042")),
+        ),
+        ("9223372036854775807", Literal(9223372036854775807)),
+        // Like bash, literals which overflow wrap around
+        ("9223372036854775808", Literal(-9223372036854775808)),
+        (
+            "0xffffffffffffffff",
+            RadixLiteral(-1, Radix::Hex, String::from("0xffffffffffffffff")),
+        ),
+    ];
+
+    for (s, a) in cases.into_iter() {
+        let src = format!("$(( {} ))", s);
+        let correct = word_subst(Arith(Some(a)));
+        assert_eq!(Ok(correct), make_parser(&src).parameter(), "{}", s);
+    }
+
+    let correct = word_subst(Arith(Some(Add(
+        Box::new(RadixLiteral(
+            255,
+            Radix::Explicit(16),
+            String::from("16#ff"),
+        )),
+        Box::new(Literal(1)),
+    ))));
+    assert_eq!(Ok(correct), make_parser("$((16#ff+1))").parameter());
+}
+
+#[test]
+fn test_arithmetic_substitution_invalid_numbers() {
+    let cases = vec![
+        ("$(( 08 ))", BadNumber(String::from("08"), src(4, 1, 5))),
+        ("$(( 12ab ))", BadNumber(String::from("12ab"), src(4, 1, 5))),
+        ("$(( 0xg ))", BadNumber(String::from("0xg"), src(4, 1, 5))),
+        ("$(( 2#12 ))", BadNumber(String::from("2#12"), src(4, 1, 5))),
+        ("$(( 1#0 ))", BadNumber(String::from("1#0"), src(4, 1, 5))),
+        ("$(( 65#1 ))", BadNumber(String::from("65#1"), src(4, 1, 5))),
+        ("$(( 16# ))", BadNumber(String::from("16#"), src(4, 1, 5))),
+        ("$(( 16#g ))", BadNumber(String::from("16#g"), src(4, 1, 5))),
+    ];
+
+    for (s, correct) in cases.into_iter() {
+        assert_eq!(Err(correct), make_parser(s).parameter(), "{}", s);
+    }
+}
+
+#[test]
+fn test_arithmetic_substitution_posix_rejects_overflow_and_bases() {
+    let mut p = make_parser("$(( 9223372036854775808 ))");
+    p.set_dialect(Dialect::Posix);
+    assert_eq!(
+        Err(NumberOverflow(
+            String::from("9223372036854775808"),
+            src(4, 1, 5)
+        )),
+        p.parameter()
+    );
+
+    let mut p = make_parser("$(( 0x1f + 017 + 9223372036854775807 ))");
+    p.set_dialect(Dialect::Posix);
+    assert!(p.parameter().is_ok());
+
+    let mut p = make_parser("$(( 16#ff ))");
+    p.set_dialect(Dialect::Posix);
+    assert_eq!(
+        Err(Extension(
+            conch_parser::parse::Extension::ArithmeticBase,
+            src(4, 1, 5)
+        )),
+        p.parameter()
+    );
+}
+
 #[test]
 fn test_arithmetic_substitution_precedence() {
     fn var(x: &str) -> Box<Arithmetic> {
@@ -406,18 +592,24 @@ fn test_arithmetic_substitution_precedence() {
     }
 
     let cases = vec![
-        ("~o++", BitwiseNot(Box::new(PostIncr(String::from("o"))))),
+        (
+            "~o++",
+            BitwiseNot(Box::new(PostIncr(ArithmeticTarget::Var(String::from("o"))))),
+        ),
         ("~(o+p)", BitwiseNot(Box::new(Add(var("o"), var("p"))))),
-        ("-o++", UnaryMinus(Box::new(PostIncr(String::from("o"))))),
+        (
+            "-o++",
+            UnaryMinus(Box::new(PostIncr(ArithmeticTarget::Var(String::from("o"))))),
+        ),
         ("-(o+p)", UnaryMinus(Box::new(Add(var("o"), var("p"))))),
-        ("++o", PreIncr(String::from("o"))),
+        ("++o", PreIncr(ArithmeticTarget::Var(String::from("o")))),
     ];
 
     for (s, end) in cases.into_iter() {
         let correct = word_subst(Arith(Some(Sequence(vec![
             *var("x"),
             Assign(
-                String::from("a"),
+                ArithmeticTarget::Var(String::from("a")),
                 Box::new(Ternary(
                     var("b"),
                     var("c"),
@@ -580,19 +772,27 @@ fn test_arithmetic_substitution_operators_of_equal_precedence() {
         ),
         (
             "$(( !~++x ))",
-            LogicalNot(Box::new(BitwiseNot(Box::new(PreIncr(String::from("x")))))),
+            LogicalNot(Box::new(BitwiseNot(Box::new(PreIncr(
+                ArithmeticTarget::Var(String::from("x")),
+            ))))),
         ),
         (
             "$(( ~!++x ))",
-            BitwiseNot(Box::new(LogicalNot(Box::new(PreIncr(String::from("x")))))),
+            BitwiseNot(Box::new(LogicalNot(Box::new(PreIncr(
+                ArithmeticTarget::Var(String::from("x")),
+            ))))),
         ),
         (
             "$(( !~--x ))",
-            LogicalNot(Box::new(BitwiseNot(Box::new(PreDecr(String::from("x")))))),
+            LogicalNot(Box::new(BitwiseNot(Box::new(PreDecr(
+                ArithmeticTarget::Var(String::from("x")),
+            ))))),
         ),
         (
             "$(( ~!--x ))",
-            BitwiseNot(Box::new(LogicalNot(Box::new(PreDecr(String::from("x")))))),
+            BitwiseNot(Box::new(LogicalNot(Box::new(PreDecr(
+                ArithmeticTarget::Var(String::from("x")),
+            ))))),
         ),
         ("$(( -+x ))", UnaryMinus(Box::new(UnaryPlus(x())))),
         ("$(( +-x ))", UnaryPlus(Box::new(UnaryMinus(x())))),
@@ -613,3 +813,234 @@ fn test_arithmetic_substitution_operators_of_equal_precedence() {
         }
     }
 }
+
+#[test]
+fn test_arithmetic_substitution_side_effects() {
+    fn var(x: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(x)))
+    }
+
+    let cases = vec![
+        (
+            "$(( i++, j-- ))",
+            Sequence(vec![
+                PostIncr(ArithmeticTarget::Var(String::from("i"))),
+                PostDecr(ArithmeticTarget::Var(String::from("j"))),
+            ]),
+        ),
+        (
+            "$(( a ? x = 1 : 2 ))",
+            Ternary(
+                var("a"),
+                Box::new(Assign(
+                    ArithmeticTarget::Var(String::from("x")),
+                    Box::new(Literal(1)),
+                )),
+                Box::new(Literal(2)),
+            ),
+        ),
+        (
+            "$(( a ? x++, y : z ))",
+            Ternary(
+                var("a"),
+                Box::new(Sequence(vec![
+                    PostIncr(ArithmeticTarget::Var(String::from("x"))),
+                    *var("y"),
+                ])),
+                var("z"),
+            ),
+        ),
+        (
+            "$(( a ? b : c, d ))",
+            Sequence(vec![Ternary(var("a"), var("b"), var("c")), *var("d")]),
+        ),
+        (
+            "$(( x = y += 2 ))",
+            Assign(
+                ArithmeticTarget::Var(String::from("x")),
+                Box::new(AssignOp(
+                    AssignOperator::Add,
+                    ArithmeticTarget::Var(String::from("y")),
+                    Box::new(Literal(2)),
+                )),
+            ),
+        ),
+    ];
+
+    for (s, a) in cases.into_iter() {
+        let correct = word_subst(Arith(Some(a)));
+        assert_eq!(make_parser(s).parameter(), Ok(correct), "{}", s);
+    }
+
+    // `:` binds tighter than assignment, just like in C
+    assert!(make_parser("$(( a ? b : c = 1 ))").parameter().is_err());
+}
+
+#[test]
+fn test_arithmetic_assigned_vars() {
+    fn var(x: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(x)))
+    }
+
+    // (( a ? x = i++ : --y, z += w ))
+    let expr: Arithmetic = Sequence(vec![
+        Ternary(
+            var("a"),
+            Box::new(Assign(
+                ArithmeticTarget::Var(String::from("x")),
+                Box::new(PostIncr(ArithmeticTarget::Var(String::from("i")))),
+            )),
+            Box::new(PreDecr(ArithmeticTarget::Var(String::from("y")))),
+        ),
+        AssignOp(
+            AssignOperator::Add,
+            ArithmeticTarget::Var(String::from("z")),
+            var("w"),
+        ),
+    ]);
+
+    assert_eq!(expr.assigned_vars(), vec!["i", "x", "y", "z"]);
+    assert!(Add(var("a"), var("b")).assigned_vars().is_empty());
+}
+
+#[test]
+fn test_arithmetic_substitution_array_index() {
+    fn var(x: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(x)))
+    }
+
+    let cases = vec![
+        ("$(( arr[i] ))", Index(String::from("arr"), var("i"))),
+        (
+            "$(( arr[ i + 1 ] * 2 ))",
+            Mult(
+                Box::new(Index(
+                    String::from("arr"),
+                    Box::new(Add(var("i"), Box::new(Literal(1)))),
+                )),
+                Box::new(Literal(2)),
+            ),
+        ),
+        (
+            "$(( a[b[0]] ))",
+            Index(
+                String::from("a"),
+                Box::new(Index(String::from("b"), Box::new(Literal(0)))),
+            ),
+        ),
+    ];
+
+    for (s, a) in cases.into_iter() {
+        let correct = word_subst(Arith(Some(a)));
+        assert_eq!(make_parser(s).parameter(), Ok(correct), "{}", s);
+    }
+
+    assert_eq!(
+        make_parser("$(( arr[i ))").parameter(),
+        Err(Unexpected(Token::ParenClose, src(10, 1, 11)))
+    );
+
+    let mut p = make_parser("$(( arr[i] ))");
+    p.set_dialect(Dialect::Posix);
+    assert_eq!(
+        p.parameter(),
+        Err(Extension(
+            conch_parser::parse::Extension::ArithmeticIndex,
+            src(7, 1, 8)
+        ))
+    );
+}
+
+#[test]
+fn test_arithmetic_substitution_array_element_targets() {
+    fn var(x: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(x)))
+    }
+    fn elem(index: Box<Arithmetic>) -> ArithmeticTarget<String> {
+        ArithmeticTarget::Index(String::from("a"), index)
+    }
+
+    let cases = vec![
+        (
+            "$(( a[i] = 1 ))",
+            Assign(elem(var("i")), Box::new(Literal(1))),
+        ),
+        (
+            "$(( a[i] += 1 ))",
+            AssignOp(AssignOperator::Add, elem(var("i")), Box::new(Literal(1))),
+        ),
+        (
+            "$(( a[ i + 1 ]=x ))",
+            Assign(
+                elem(Box::new(Add(var("i"), Box::new(Literal(1))))),
+                var("x"),
+            ),
+        ),
+        ("$(( a[i]++ ))", PostIncr(elem(var("i")))),
+        ("$(( a[i]-- ))", PostDecr(elem(var("i")))),
+        ("$(( ++a[i] ))", PreIncr(elem(var("i")))),
+        ("$(( --a[i] ))", PreDecr(elem(var("i")))),
+        (
+            "$(( a[b[0]] = 1 ))",
+            Assign(
+                elem(Box::new(Index(String::from("b"), Box::new(Literal(0))))),
+                Box::new(Literal(1)),
+            ),
+        ),
+        (
+            "$(( a[i] == 1 ))",
+            Eq(
+                Box::new(Index(String::from("a"), var("i"))),
+                Box::new(Literal(1)),
+            ),
+        ),
+    ];
+
+    for (s, a) in cases.into_iter() {
+        let correct = word_subst(Arith(Some(a)));
+        assert_eq!(make_parser(s).parameter(), Ok(correct), "{}", s);
+    }
+
+    let incr = PostIncr(elem(var("i")));
+    assert_eq!(incr.referenced_vars(), vec!["i", "a"]);
+    assert_eq!(incr.assigned_vars(), vec!["a"]);
+
+    let mut p = make_parser("$(( a[i] = 1 ))");
+    p.set_dialect(Dialect::Posix);
+    assert_eq!(
+        p.parameter(),
+        Err(Extension(
+            conch_parser::parse::Extension::ArithmeticIndex,
+            src(5, 1, 6)
+        ))
+    );
+}
+
+#[test]
+fn test_arithmetic_referenced_vars() {
+    fn var(x: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(x)))
+    }
+
+    // (( x = arr[i++] + y, z *= 2 ))
+    let expr: Arithmetic = Sequence(vec![
+        Assign(
+            ArithmeticTarget::Var(String::from("x")),
+            Box::new(Add(
+                Box::new(Index(
+                    String::from("arr"),
+                    Box::new(PostIncr(ArithmeticTarget::Var(String::from("i")))),
+                )),
+                var("y"),
+            )),
+        ),
+        AssignOp(
+            AssignOperator::Mult,
+            ArithmeticTarget::Var(String::from("z")),
+            Box::new(Literal(2)),
+        ),
+    ]);
+
+    assert_eq!(expr.referenced_vars(), vec!["i", "arr", "y", "z"]);
+    assert_eq!(expr.assigned_vars(), vec!["i", "x", "z"]);
+}
@@ -0,0 +1,173 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{DefaultParser, Dialect, Extension, ParserOptions};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn var(name: &str) -> Box<DefaultArithmetic> {
+    Box::new(Arithmetic::Var(String::from(name)))
+}
+
+fn parse(src: &str) -> Option<DefaultArithmetic> {
+    make_parser(src).arithmetic_command().unwrap()
+}
+
+fn parse_dialect(src: &str, dialect: Dialect) -> Vec<TopLevelCommand<String>> {
+    let options = ParserOptions {
+        dialect,
+        ..ParserOptions::default()
+    };
+    DefaultParser::with_options(Lexer::new(src.chars()), Default::default(), options)
+        .parse_all()
+        .unwrap()
+}
+
+/// Returns the kind of the compound command which makes up the entire command.
+fn compound_kind(cmd: &TopLevelCommand<String>) -> &DefaultCompoundCommandKind {
+    match cmd.0 {
+        Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Compound(ref compound)),
+            ..
+        }) => &compound.kind,
+        ref other => panic!("expected a compound command: {:?}", other),
+    }
+}
+
+#[test]
+fn test_arithmetic_command_valid() {
+    assert_eq!(
+        parse("((i++))"),
+        Some(Arithmetic::PostIncr(ArithmeticTarget::Var(String::from(
+            "i"
+        ))))
+    );
+    assert_eq!(
+        parse("((a, b))"),
+        Some(Arithmetic::Sequence(vec![*var("a"), *var("b")]))
+    );
+    assert_eq!(
+        parse("((i++, j--))"),
+        Some(Arithmetic::Sequence(vec![
+            Arithmetic::PostIncr(ArithmeticTarget::Var(String::from("i"))),
+            Arithmetic::PostDecr(ArithmeticTarget::Var(String::from("j"))),
+        ]))
+    );
+    assert_eq!(
+        parse("(( x += 1 ) )"),
+        Some(Arithmetic::AssignOp(
+            AssignOperator::Add,
+            ArithmeticTarget::Var(String::from("x")),
+            Box::new(Arithmetic::Literal(1)),
+        ))
+    );
+    assert_eq!(parse("(( ))"), None);
+}
+
+#[test]
+fn test_arithmetic_command_distinguished_from_nested_subshells() {
+    let cmds = make_parser("(( (x) ))").parse_all().unwrap();
+    assert_eq!(
+        *compound_kind(&cmds[0]),
+        CompoundCommandKind::Arithmetic(Some(*var("x")))
+    );
+
+    // Like bash, the command is only arithmetic if the paren matching the
+    // second `(` is immediately followed by another `)`
+    for src in &["( (x) )", "((x) )", "((echo a); echo b)"] {
+        let cmds = make_parser(src).parse_all().unwrap();
+        match *compound_kind(&cmds[0]) {
+            CompoundCommandKind::Subshell(ref cmds) => match *compound_kind(&cmds[0]) {
+                CompoundCommandKind::Subshell(_) => {}
+                ref other => panic!("expected a nested subshell in {:?}: {:?}", src, other),
+            },
+            ref other => panic!("expected a subshell in {:?}: {:?}", src, other),
+        }
+    }
+}
+
+#[test]
+fn test_arithmetic_command_with_redirects_and_lists() {
+    let cmds = make_parser("((x > 1)) 2>/dev/null && echo big")
+        .parse_all()
+        .unwrap();
+    let compound = match cmds[0].0 {
+        Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Compound(ref compound)),
+            ref rest,
+        }) => {
+            assert_eq!(rest.len(), 1);
+            compound
+        }
+        ref other => panic!("expected a compound command: {:?}", other),
+    };
+
+    assert_eq!(
+        compound.kind,
+        CompoundCommandKind::Arithmetic(Some(Arithmetic::Great(
+            var("x"),
+            Box::new(Arithmetic::Literal(1)),
+        )))
+    );
+    assert_eq!(
+        compound.io,
+        vec![Redirect::Write(Some(2), word("/dev/null"))]
+    );
+}
+
+#[test]
+fn test_arithmetic_command_round_trips_through_printer() {
+    let sources = [
+        "(( i++, j-- ))\n",
+        "(( x = (y + 1) * 2 )) >out\n",
+        "(( ))\n",
+    ];
+
+    for src in &sources {
+        let cmds = make_parser(src).parse_all().unwrap();
+        assert_eq!(cmds.to_shell_string(), *src);
+    }
+}
+
+#[test]
+fn test_arithmetic_command_dialects() {
+    let cmds = parse_dialect("((x))", Dialect::Mksh);
+    assert_eq!(
+        *compound_kind(&cmds[0]),
+        CompoundCommandKind::Arithmetic(Some(*var("x")))
+    );
+
+    let options = ParserOptions {
+        dialect: Dialect::Posix,
+        ..ParserOptions::default()
+    };
+    let mut p =
+        DefaultParser::with_options(Lexer::new("((x))".chars()), Default::default(), options);
+    assert_eq!(
+        p.complete_command(),
+        Err(Extension(Extension::DoubleParen, src(0, 1, 1)))
+    );
+
+    // Nested subshells are not an extension
+    let cmds = parse_dialect("((echo a) )", Dialect::Posix);
+    match *compound_kind(&cmds[0]) {
+        CompoundCommandKind::Subshell(_) => {}
+        ref other => panic!("expected a subshell: {:?}", other),
+    }
+}
+
+#[test]
+fn test_arithmetic_command_invalid() {
+    let cases = [
+        ("(( x", Unmatched(Token::ParenOpen, src(0, 1, 1))),
+        ("(( x + ))", Unexpected(Token::ParenClose, src(7, 1, 8))),
+    ];
+
+    for (s, err) in cases.iter() {
+        assert_eq!(make_parser(s).complete_command(), Err(err.clone()), "{}", s);
+    }
+}
@@ -0,0 +1,128 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::*;
+
+type Subst = ParameterSubstitution<Parameter<String>, &'static str, &'static str, ()>;
+
+#[test]
+fn test_parameter_substitution_accessors() {
+    let p = Parameter::Var(String::from("foo"));
+
+    let default: Subst = ParameterSubstitution::Default(true, p.clone(), Some("word"));
+    assert_eq!(default.parameter(), Some(&p));
+    assert_eq!(default.word(), Some(&"word"));
+    assert_eq!(default.commands(), None);
+
+    let unset: Subst = ParameterSubstitution::RemoveSmallestSuffix(p.clone(), None);
+    assert_eq!(unset.parameter(), Some(&p));
+    assert_eq!(unset.word(), None);
+
+    let read: Subst = ParameterSubstitution::ReadFile("file");
+    assert_eq!(read.parameter(), None);
+    assert_eq!(read.word(), Some(&"file"));
+
+    let cmd: Subst = ParameterSubstitution::Command(vec!["a", "b"]);
+    assert_eq!(cmd.parameter(), None);
+    assert_eq!(cmd.word(), None);
+    assert_eq!(cmd.commands(), Some(&["a", "b"][..]));
+}
+
+#[test]
+fn test_simple_word_accessors() {
+    let lit: SimpleWord<&str, (), ()> = SimpleWord::Literal("foo");
+    let esc: SimpleWord<&str, (), ()> = SimpleWord::Escaped("*");
+    assert_eq!(lit.literal(), Some(&"foo"));
+    assert_eq!(esc.literal(), Some(&"*"));
+    assert!(!esc.is_glob());
+
+    for glob in [
+        SimpleWord::<&str, (), ()>::Star,
+        SimpleWord::Question,
+        SimpleWord::SquareOpen,
+        SimpleWord::SquareClose,
+    ] {
+        assert!(glob.is_glob());
+        assert_eq!(glob.literal(), None);
+    }
+
+    assert!(!SimpleWord::<&str, (), ()>::Tilde.is_glob());
+}
+
+#[test]
+fn test_redirect_accessors() {
+    assert_eq!(Redirect::Write(Some(2), "file").fd(), Some(2));
+    assert_eq!(Redirect::Append(None, "file").fd(), None);
+    assert_eq!(Redirect::DupRead(Some(0), "3").word(), &"3");
+
+    let info = HeredocInfo {
+        delimiter: String::from("EOF"),
+        quoted: true,
+        strip_tabs: false,
+        lines: vec![String::from("body")],
+    };
+    let heredoc = Redirect::Heredoc(None, "body", info.clone());
+    assert_eq!(heredoc.word(), &"body");
+    assert_eq!(heredoc.heredoc_info(), Some(&info));
+    assert_eq!(Redirect::Read(None, "file").heredoc_info(), None);
+}
+
+#[test]
+fn test_redirect_kind_accessors() {
+    use conch_parser::ast::builder::RedirectKind;
+
+    assert_eq!(RedirectKind::Write(Some(2), "file").fd(), Some(2));
+    assert_eq!(RedirectKind::AppendBoth("file").fd(), None);
+    assert_eq!(RedirectKind::DupRead(Some(0), "3").word(), &"3");
+    assert_eq!(RedirectKind::Clobber(Some(1), "f").into_parts(), (Some(1), "f"));
+    assert_eq!(RedirectKind::WriteBoth("f").into_parts(), (None, "f"));
+
+    let info = HeredocInfo {
+        delimiter: String::from("EOF"),
+        quoted: false,
+        strip_tabs: true,
+        lines: Vec::new(),
+    };
+    let heredoc = RedirectKind::Heredoc(Some(3), "body", info);
+    assert_eq!(heredoc.word(), &"body");
+    assert_eq!(heredoc.into_parts(), (Some(3), "body"));
+}
+
+#[test]
+fn test_compound_command_kind_accessors() {
+    let if_cmd: CompoundCommandKind<(), (), &str> = CompoundCommandKind::If {
+        conditionals: vec![
+            GuardBodyPair {
+                guard: vec!["g1"],
+                body: vec!["b1"],
+            },
+            GuardBodyPair {
+                guard: vec!["g2"],
+                body: vec!["b2"],
+            },
+        ],
+        else_branch: Some(vec!["e"]),
+    };
+    assert_eq!(
+        if_cmd.bodies(),
+        vec![&["g1"][..], &["b1"], &["g2"], &["b2"], &["e"]]
+    );
+    assert!(if_cmd.words().is_empty());
+
+    let case: CompoundCommandKind<(), &str, &str> = CompoundCommandKind::Case {
+        word: "w",
+        arms: vec![PatternBodyPair {
+            patterns: vec!["a", "b"],
+            body: vec!["c"],
+            terminator: CaseTerminator::Break,
+        }],
+    };
+    assert_eq!(case.bodies(), vec![&["c"][..]]);
+    assert_eq!(case.words(), vec![&"w", &"a", &"b"]);
+
+    let for_cmd: CompoundCommandKind<&str, &str, &str> = CompoundCommandKind::For {
+        var: "i",
+        words: Some(vec!["1", "2"]),
+        body: vec!["x"],
+    };
+    assert_eq!(for_cmd.bodies(), vec![&["x"][..]]);
+    assert_eq!(for_cmd.words(), vec![&"1", &"2"]);
+}
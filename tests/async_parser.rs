@@ -0,0 +1,315 @@
+#![cfg(feature = "async")]
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::StringBuilder;
+use conch_parser::ast::TopLevelCommand;
+use conch_parser::lexer::AsyncReadChars;
+use conch_parser::parse::{AsyncParser, ParseError, ParserOptions, PositionTracking, SourcePos};
+use conch_parser::token::Token;
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+mod parse_support;
+use crate::parse_support::*;
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn waker() -> Waker {
+    Arc::new(Noop).into()
+}
+
+/// A stream of characters which are made available one chunk at a time,
+/// returning `Pending` in between chunks.
+struct Chunks {
+    chunks: VecDeque<VecDeque<char>>,
+    ready: bool,
+}
+
+impl Chunks {
+    fn new(chunks: &[&str]) -> Self {
+        Chunks {
+            chunks: chunks.iter().map(|c| c.chars().collect()).collect(),
+            ready: false,
+        }
+    }
+}
+
+impl Stream for Chunks {
+    type Item = char;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<char>> {
+        loop {
+            if self.chunks.is_empty() {
+                return Poll::Ready(None);
+            }
+
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            match self.chunks[0].pop_front() {
+                Some(c) => return Poll::Ready(Some(c)),
+                None => {
+                    self.chunks.pop_front();
+                    self.ready = false;
+                }
+            }
+        }
+    }
+}
+
+type Results = Vec<Result<TopLevelCommand<String>, ParseError<void::Void>>>;
+
+/// Polls the parser until it ends, recording how many results
+/// were available after each chunk of input.
+fn poll_all<S>(parser: &mut AsyncParser<S>) -> (Results, Vec<usize>)
+where
+    S: Stream<Item = char> + Unpin,
+{
+    let waker = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut results = Vec::new();
+    let mut ready = Vec::new();
+
+    loop {
+        match Pin::new(&mut *parser).poll_next(&mut cx) {
+            Poll::Ready(Some(result)) => results.push(result),
+            Poll::Ready(None) => return (results, ready),
+            Poll::Pending => ready.push(results.len()),
+        }
+    }
+}
+
+fn parse_chunks(chunks: &[&str]) -> (Results, Vec<usize>) {
+    poll_all(&mut AsyncParser::new(Chunks::new(chunks)))
+}
+
+#[test]
+fn test_commands_are_yielded_once_their_line_is_complete() {
+    let (results, ready) = parse_chunks(&["echo f", "oo\necho ", "bar\n", "baz"]);
+    assert_eq!(
+        results,
+        vec![
+            Ok(cmd_args("echo", &["foo"])),
+            Ok(cmd_args("echo", &["bar"])),
+            Ok(cmd("baz")),
+        ]
+    );
+    assert_eq!(ready, vec![0, 0, 1, 2]);
+}
+
+#[test]
+fn test_incomplete_commands_wait_for_more_input() {
+    let (results, ready) = parse_chunks(&[
+        "if true\n",
+        "then echo foo\n",
+        "fi\n",
+        "echo 'a\n",
+        "b' &&\n",
+        "echo c \\\n",
+        "d\n",
+    ]);
+
+    let expected = parse("if true\nthen echo foo\nfi\necho 'a\nb' &&\necho c \\\nd\n");
+    assert_eq!(expected.len(), 2);
+    assert_eq!(results, expected.into_iter().map(Ok).collect::<Vec<_>>());
+    assert_eq!(ready, vec![0, 0, 0, 1, 1, 1, 1]);
+}
+
+#[test]
+fn test_heredoc_bodies_wait_for_their_delimiter() {
+    let (results, ready) = parse_chunks(&["cat <<EOF\n", "foo\n", "EOF\n", "echo\n"]);
+    let expected = make_parser("cat <<EOF\nfoo\nEOF\n")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(results, vec![Ok(expected), Ok(cmd("echo"))]);
+    assert_eq!(ready, vec![0, 0, 0, 1]);
+
+    // An unterminated heredoc is delimited by the end of the input
+    let (results, _) = parse_chunks(&["cat <<EOF\n", "foo\n"]);
+    let expected = make_parser("cat <<EOF\nfoo\n")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(results, vec![Ok(expected)]);
+}
+
+#[test]
+fn test_errors_are_positioned_within_the_entire_source() {
+    let (results, _) = parse_chunks(&["echo foo\n", "echo bar\n)\n", "echo baz\n", "&&"]);
+    assert_eq!(
+        results,
+        vec![
+            Ok(cmd_args("echo", &["foo"])),
+            Ok(cmd_args("echo", &["bar"])),
+            Err(ParseError::Unexpected(Token::ParenClose, src(18, 3, 1))),
+            Ok(cmd_args("echo", &["baz"])),
+            Err(ParseError::Unexpected(Token::AndIf, src(29, 5, 1))),
+        ]
+    );
+
+    // Incomplete commands fail once the input ends
+    let (results, _) = parse_chunks(&["echo\n", "if true\n"]);
+    assert_eq!(results.len(), 2);
+    match results[1] {
+        Err(ParseError::IncompleteCmd("if", pos, "then", _)) => assert_eq!(pos, src(5, 2, 1)),
+        ref r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_options_are_applied_to_every_command() {
+    let options = ParserOptions {
+        position_tracking: PositionTracking::ByteOffsets,
+        ..ParserOptions::default()
+    };
+    let mut parser = AsyncParser::with_options(
+        Chunks::new(&["echo\n", "echo\n)\n"]),
+        options,
+        StringBuilder::default,
+    );
+
+    let waker = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut results = Vec::new();
+    loop {
+        match Pin::new(&mut parser).poll_next(&mut cx) {
+            Poll::Ready(Some(result)) => results.push(result),
+            Poll::Ready(None) => break,
+            Poll::Pending => {}
+        }
+    }
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(cmd("echo")),
+            Ok(cmd("echo")),
+            Err(ParseError::Unexpected(
+                Token::ParenClose,
+                SourcePos::from_byte(10)
+            )),
+        ]
+    );
+}
+
+/// A reader which returns the provided chunks of bytes one read at a time,
+/// returning `Pending` (or an interruption) in between reads.
+struct Reads {
+    reads: VecDeque<Vec<u8>>,
+    ready: bool,
+}
+
+impl AsyncRead for Reads {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        match self.reads.pop_front() {
+            None => Poll::Ready(Ok(0)),
+            Some(ref read) if read.is_empty() => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "interrupted",
+            ))),
+            Some(read) => {
+                buf[..read.len()].copy_from_slice(&read);
+                Poll::Ready(Ok(read.len()))
+            }
+        }
+    }
+}
+
+fn reads(reads: &[&[u8]]) -> Reads {
+    Reads {
+        reads: reads.iter().map(|r| r.to_vec()).collect(),
+        ready: false,
+    }
+}
+
+fn read_chars(reader: Reads) -> (String, AsyncReadChars<Reads>) {
+    let waker = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut chars = AsyncReadChars::new(reader);
+    let mut s = String::new();
+
+    loop {
+        match Pin::new(&mut chars).poll_next(&mut cx) {
+            Poll::Ready(Some(c)) => s.push(c),
+            Poll::Ready(None) => return (s, chars),
+            Poll::Pending => {}
+        }
+    }
+}
+
+#[test]
+fn test_async_read_chars_decodes_characters_split_across_reads() {
+    let euro = "€".as_bytes();
+    let (s, chars) = read_chars(reads(&[
+        b"a",
+        &euro[..1],
+        &[],
+        &euro[1..2],
+        &euro[2..],
+        b"b\xff",
+    ]));
+    assert_eq!(s, "a€b\u{FFFD}");
+    assert!(chars.error().is_none());
+
+    // A truncated character at the end of the input is replaced
+    let (s, _) = read_chars(reads(&[b"a", &euro[..2]]));
+    assert_eq!(s, "a\u{FFFD}");
+}
+
+#[test]
+fn test_async_read_chars_ends_on_error() {
+    struct Failing;
+
+    impl AsyncRead for Failing {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Err(io::Error::other("failed")))
+        }
+    }
+
+    let waker = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut chars = AsyncReadChars::new(Failing);
+    assert_eq!(Pin::new(&mut chars).poll_next(&mut cx), Poll::Ready(None));
+    assert_eq!(chars.take_error().unwrap().to_string(), "failed");
+    assert!(chars.error().is_none());
+}
+
+#[test]
+fn test_parser_from_reader() {
+    let mut parser = AsyncParser::from_reader(reads(&[b"echo f", b"oo\nif true\nth", b"en :; fi"]));
+    let (results, _) = poll_all(&mut parser);
+    assert_eq!(
+        results.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>(),
+        make_parser("echo foo\nif true\nthen :; fi")
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>()
+    );
+    assert!(parser.source().error().is_none());
+}
@@ -162,6 +162,25 @@ fn test_backticked_nested_backticks_x3() {
     );
 }
 
+#[test]
+fn test_backticked_backslashes_removed_before_double_quote_only_if_double_quoted() {
+    let cases = [
+        (r#""`echo \"a b\"`""#, r#""$(echo "a b")""#),
+        (r#"`echo \"a b\"`"#, r#"$(echo \"a b\")"#),
+        (r#""`echo \`echo \\"a\\"\``""#, r#""$(echo $(echo \"a\"))""#),
+        (r#""x`echo '\"'`""#, r#""x$(echo '"')""#),
+    ];
+
+    for &(backticked, expected) in &cases {
+        assert_eq!(
+            make_parser(backticked).word().unwrap(),
+            make_parser(expected).word().unwrap(),
+            "{}",
+            backticked
+        );
+    }
+}
+
 #[test]
 fn test_backticked_invalid_missing_closing_backtick() {
     let src = [
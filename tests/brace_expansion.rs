@@ -0,0 +1,219 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{BraceExpansion, DefaultParser, Dialect};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn parse(src: &str, dialect: Dialect, brace_expansion: BraceExpansion) -> TopLevelCommand<String> {
+    let mut p = DefaultParser::new(Lexer::new(src.chars()));
+    p.set_dialect(dialect);
+    p.set_brace_expansion(brace_expansion);
+    p.complete_command().unwrap().unwrap()
+}
+
+fn structured(src: &str) -> TopLevelCommand<String> {
+    parse(src, Dialect::Bash, BraceExpansion::Structured)
+}
+
+fn simple(cmd: &TopLevelCommand<String>) -> &DefaultSimpleCommand {
+    match cmd.0 {
+        Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Simple(ref cmd)),
+            ..
+        }) => cmd,
+        ref c => panic!("not a simple command: {:#?}", c),
+    }
+}
+
+/// Returns the arguments of a simple command (excluding its name).
+fn args(src: &str) -> Vec<TopLevelWord<String>> {
+    simple(&structured(src))
+        .redirects_or_cmd_words
+        .iter()
+        .skip(1)
+        .map(|w| match *w {
+            RedirectOrCmdWord::CmdWord(ref w) => w.clone(),
+            RedirectOrCmdWord::Redirect(ref r) => panic!("unexpected redirect: {:?}", r),
+        })
+        .collect()
+}
+
+fn braces(braces: Braces<DefaultWord>) -> DefaultWord {
+    Word::Braces(braces)
+}
+
+fn alternatives(alternatives: &[&[DefaultWord]]) -> DefaultWord {
+    braces(Braces::Alternatives(
+        alternatives.iter().map(|words| words.to_vec()).collect(),
+    ))
+}
+
+fn single(part: DefaultWord) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Single(part))
+}
+
+fn concat(parts: Vec<DefaultWord>) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Concat(parts))
+}
+
+#[test]
+fn test_brace_alternatives() {
+    assert_eq!(
+        args("echo a{b,c}d {a,{b,c}} {,x} {\"a\",$b}"),
+        vec![
+            concat(vec![
+                lit("a"),
+                alternatives(&[&[lit("b")], &[lit("c")]]),
+                lit("d"),
+            ]),
+            single(alternatives(&[
+                &[lit("a")],
+                &[alternatives(&[&[lit("b")], &[lit("c")]])],
+            ])),
+            single(alternatives(&[&[], &[lit("x")]])),
+            single(alternatives(&[
+                &[Word::DoubleQuoted(vec![SimpleWord::Literal("a".into())])],
+                &[Word::Simple(SimpleWord::Param(Parameter::Var("b".into())))],
+            ])),
+        ]
+    );
+}
+
+#[test]
+fn test_brace_sequences() {
+    assert_eq!(
+        args("echo {1..10} {10..1..3} {01..10} {-05..5} {a..e} {z..a..-2}"),
+        vec![
+            single(braces(Braces::Numbers {
+                start: 1,
+                end: 10,
+                step: None,
+                width: 0,
+            })),
+            single(braces(Braces::Numbers {
+                start: 10,
+                end: 1,
+                step: Some(3),
+                width: 0,
+            })),
+            single(braces(Braces::Numbers {
+                start: 1,
+                end: 10,
+                step: None,
+                width: 2,
+            })),
+            single(braces(Braces::Numbers {
+                start: -5,
+                end: 5,
+                step: None,
+                width: 3,
+            })),
+            single(braces(Braces::Chars {
+                start: 'a',
+                end: 'e',
+                step: None,
+            })),
+            single(braces(Braces::Chars {
+                start: 'z',
+                end: 'a',
+                step: Some(-2),
+            })),
+        ]
+    );
+}
+
+#[test]
+fn test_words_which_are_not_brace_expansions_remain_literal() {
+    assert_eq!(
+        args("echo {a} {a,b {1..a} {a..bc} {} '{a,b}' \"{a,b}\""),
+        vec![
+            word("{a}"),
+            word("{a,b"),
+            word("{1..a}"),
+            word("{a..bc}"),
+            word("{}"),
+            single_quoted("{a,b}"),
+            double_quoted("{a,b}"),
+        ]
+    );
+
+    assert_eq!(
+        args("echo \\{a,b\\}"),
+        vec![concat(vec![escaped("{"), lit("a,b"), escaped("}")])]
+    );
+}
+
+#[test]
+fn test_brace_expansions_are_literal_by_default_and_outside_of_bash() {
+    let src = "echo {a,b} {1..3}";
+    let literal = cmd_args("echo", &["{a,b}", "{1..3}"]);
+    assert_eq!(
+        parse(src, Dialect::Bash, BraceExpansion::default()),
+        literal
+    );
+    assert_eq!(
+        parse(src, Dialect::Posix, BraceExpansion::Structured),
+        literal
+    );
+}
+
+#[test]
+fn test_brace_expansions_of_redirects_and_for_words() {
+    let cmd = structured("cat >{a,b}");
+    match simple(&cmd).redirects_or_cmd_words[1] {
+        RedirectOrCmdWord::Redirect(Redirect::Write(None, ref w)) => {
+            assert_eq!(*w, single(alternatives(&[&[lit("a")], &[lit("b")]])))
+        }
+        ref w => panic!("unexpected word: {:?}", w),
+    }
+
+    let mut p = make_parser("for i in {1..3} x; do :; done");
+    p.set_brace_expansion(BraceExpansion::Structured);
+    match p.compound_command().unwrap().kind {
+        CompoundCommandKind::For { words, .. } => {
+            let sequence = braces(Braces::Numbers {
+                start: 1,
+                end: 3,
+                step: None,
+                width: 0,
+            });
+            assert_eq!(words, Some(vec![single(sequence), word("x")]));
+        }
+        kind => panic!("unexpected command: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_words_which_bash_does_not_brace_expand_remain_literal() {
+    let cmd = structured("x={a,b} cmd");
+    match simple(&cmd).redirects_or_env_vars[0] {
+        RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+            assert_eq!(name, "x");
+            assert_eq!(*value, Some(word("{a,b}")));
+        }
+        ref v => panic!("unexpected assignment: {:?}", v),
+    }
+
+    for src in &["case {a,b} in {a,b}) ;; esac", "[[ {a,b} == {c,d} ]]"] {
+        let cmd = structured(src);
+        assert_eq!(cmd, parse(src, Dialect::Bash, BraceExpansion::Literal));
+    }
+}
+
+#[test]
+fn test_brace_expansions_round_trip_through_printer() {
+    let srcs = [
+        "echo a{b,c}d {a,{b,c}} {,x} {\"a\",$b}",
+        "echo {1..10} {10..1..3} {01..10} {-05..5} {a..e} {z..a..-2}",
+        "echo {a} {a,b {1..a} '{a,b}' \\{a,b\\}",
+        "cat >{a,b}",
+    ];
+
+    for src in &srcs {
+        let cmd = structured(src);
+        assert_eq!(structured(&cmd.to_shell_string()), cmd, "{}", src);
+    }
+}
@@ -0,0 +1,219 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::*;
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{ErrorRecovery, Parser};
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// A builder which only implements the required methods (by delegating to the
+/// `DefaultBuilder`), and relies on the defaults of every other method.
+#[derive(Default)]
+struct Minimal(DefaultBuilder<String>);
+
+type B = DefaultBuilder<String>;
+
+impl Builder for Minimal {
+    type Command = <B as Builder>::Command;
+    type CommandList = <B as Builder>::CommandList;
+    type ListableCommand = <B as Builder>::ListableCommand;
+    type PipeableCommand = <B as Builder>::PipeableCommand;
+    type CompoundCommand = <B as Builder>::CompoundCommand;
+    type Word = <B as Builder>::Word;
+    type Redirect = <B as Builder>::Redirect;
+    type Error = <B as Builder>::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+        span: Span,
+    ) -> Result<Self::Command, Self::Error> {
+        self.0
+            .complete_command(pre_cmd_comments, list, separator, cmd_comment, span)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+        span: Span,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.0.and_or_list(first, rest, span)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, PipeSegment<Self::PipeableCommand>)>,
+        span: Span,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.0.pipeline(bang, cmds, span)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.0
+            .simple_command(redirects_or_env_vars, redirects_or_cmd_words, span)
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0.brace_group(cmds, redirects, redirects_span, span)
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0.subshell(cmds, redirects, redirects_span, span)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0
+            .loop_command(kind, guard_body_pair, redirects, redirects_span, span)
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0
+            .if_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0
+            .for_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+        redirects_span: Option<Span>,
+        span: Span,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.0
+            .case_command(fragments, redirects, redirects_span, span)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.0.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        syntax: FunctionSyntax,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+        span: Span,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.0
+            .function_declaration(name, syntax, post_name_comments, body, span)
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.0.comments(comments)
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command>,
+        span: Span,
+    ) -> Result<Self::Word, Self::Error> {
+        self.0.word(kind, span)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: RedirectKind<Self::Word>,
+        span: Span,
+    ) -> Result<Self::Redirect, Self::Error> {
+        self.0.redirect(kind, span)
+    }
+}
+
+fn parse_minimal(src: &str) -> Vec<TopLevelCommand<String>> {
+    let mut parser = Parser::with_builder(Lexer::new(src.chars()), Minimal::default());
+    parser.set_error_recovery(ErrorRecovery::Passthrough);
+    parser.into_iter().collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn test_arithmetic_defaults_to_the_parsed_expression() {
+    let src = "echo $((1 + x))";
+    assert_eq!(parse_minimal(src), parse(src));
+}
+
+#[test]
+fn test_unparsed_defaults_to_an_empty_command() {
+    let empty = TopLevelCommand(Command::List(CommandList {
+        first: ListableCommand::Single(PipeableCommand::Simple(Box::new(SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: vec![],
+        }))),
+        rest: vec![],
+    }));
+
+    assert_eq!(
+        parse_minimal("foo )\nbar"),
+        vec![cmd("foo"), empty, cmd("bar")]
+    );
+}
+
+#[test]
+fn test_conditional_command_defaults_to_a_brace_group() {
+    let cmds = parse_minimal("[[ -f $x && ( $y == z || ! -d $y ) ]] > out");
+    let printed: Vec<_> = cmds.iter().map(|cmd| cmd.to_shell_string()).collect();
+    assert_eq!(
+        printed,
+        vec!["{\n    [[ -f $x && ( $y == z || ! -d $y ) ]]\n} >out"]
+    );
+}
+
+#[test]
+fn test_arithmetic_command_defaults_to_a_let_command() {
+    let cmds = parse_minimal("((i++, j--)) 2>&1; (( ))");
+    let printed: Vec<_> = cmds.iter().map(|cmd| cmd.to_shell_string()).collect();
+    assert_eq!(
+        printed,
+        vec!["{\n    let 'i++, j--'\n} 2>&1", "{\n    let 0\n}"]
+    );
+}
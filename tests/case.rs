@@ -1,5 +1,6 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
+use conch_parser::ast::*;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -23,6 +24,7 @@ fn test_case_command_valid() {
                     commands: vec![cmd_args("echo", &["greeting"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseTerminator::Break,
                 arm_comment: None,
             },
             CaseArm {
@@ -35,6 +37,7 @@ fn test_case_command_valid() {
                     commands: vec![cmd_args("echo", &["noun"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseTerminator::Break,
                 arm_comment: None,
             },
         ],
@@ -84,6 +87,7 @@ fn test_case_command_valid_with_comments() {
                         Newline(Some(String::from("#post_body_a"))),
                     ],
                 },
+                terminator: CaseTerminator::Break,
                 arm_comment: Some(Newline(Some(String::from("#arm_a")))),
             },
             CaseArm {
@@ -99,6 +103,7 @@ fn test_case_command_valid_with_comments() {
                     commands: vec![cmd_args("echo", &["noun"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseTerminator::Break,
                 arm_comment: Some(Newline(Some(String::from("#arm_b")))),
             },
         ],
@@ -168,6 +173,36 @@ fn test_case_command_word_need_not_be_simple_literal() {
     p.case_command().unwrap();
 }
 
+#[test]
+fn test_case_command_word_and_patterns_support_full_word_grammar() {
+    let mut p = make_parser("case \"$x\"y${z:-w}$(a) in $p|\"q\"*) ;; esac");
+    let fragments = p.case_command().unwrap();
+
+    assert_eq!(
+        fragments.word,
+        TopLevelWord(ComplexWord::Concat(vec![
+            Word::DoubleQuoted(vec![SimpleWord::Param(Parameter::Var("x".into()))]),
+            lit("y"),
+            subst(ParameterSubstitution::Default(
+                true,
+                Parameter::Var("z".into()),
+                Some(word("w")),
+            )),
+            subst(ParameterSubstitution::Command(vec![cmd("a")])),
+        ]))
+    );
+    assert_eq!(
+        fragments.arms[0].patterns.pattern_alternatives,
+        vec![
+            word_param(Parameter::Var("p".into())),
+            TopLevelWord(ComplexWord::Concat(vec![
+                Word::DoubleQuoted(vec![SimpleWord::Literal("q".into())]),
+                Word::Simple(SimpleWord::Star),
+            ])),
+        ]
+    );
+}
+
 #[test]
 fn test_case_command_valid_with_no_arms() {
     let mut p = make_parser("case foo in esac");
@@ -347,6 +382,52 @@ fn test_case_command_invalid_concat() {
     );
 }
 
+#[test]
+fn test_case_command_arm_terminators() {
+    let src = "case foo in a) echo a;& b) echo b;;& c) ;& d) echo d ;; e) echo e\nesac";
+    let terminators = make_parser(src)
+        .case_command()
+        .unwrap()
+        .arms
+        .into_iter()
+        .map(|arm| (arm.terminator, arm.body.commands.len()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        terminators,
+        vec![
+            (CaseTerminator::FallThrough, 1),
+            (CaseTerminator::Continue, 1),
+            (CaseTerminator::FallThrough, 0),
+            (CaseTerminator::Break, 1),
+            (CaseTerminator::Break, 1),
+        ]
+    );
+
+    let cmd = make_parser("case foo in a) echo a;&\n*) echo b;; esac")
+        .compound_command()
+        .unwrap();
+    match cmd.kind {
+        CompoundCommandKind::Case { arms, .. } => {
+            assert_eq!(arms[0].terminator, CaseTerminator::FallThrough);
+            assert_eq!(arms[1].terminator, CaseTerminator::Break);
+        }
+        kind => panic!("expected a case command, found {:?}", kind),
+    }
+}
+
+#[test]
+fn test_case_command_invalid_terminators() {
+    assert_eq!(
+        Err(Unexpected(Token::Semi, src(15, 1, 16))),
+        make_parser("case foo in a) ; esac").case_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Amp, src(22, 1, 23))),
+        make_parser("case foo in a) echo ; & esac").case_command()
+    );
+}
+
 #[test]
 fn test_case_command_should_recognize_literals_and_names() {
     let case_str = String::from("case");
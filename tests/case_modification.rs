@@ -0,0 +1,80 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::case_modification::CaseMapping;
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::parse::{parse_word, Dialect};
+
+fn subst(src: &str) -> DefaultParameterSubstitution {
+    match parse_word(src, Dialect::Bash).unwrap() {
+        TopLevelWord(ComplexWord::Single(Word::Simple(SimpleWord::Subst(s)))) => *s,
+        w => panic!("unexpected word: {:?}", w),
+    }
+}
+
+fn modify(src: &str, value: &str, mapping: CaseMapping) -> Option<String> {
+    subst(src).modify_case(value, mapping)
+}
+
+#[test]
+fn test_case_modification_is_ascii_by_default() {
+    assert_eq!(CaseMapping::default(), CaseMapping::Ascii);
+
+    let value = "éclair über straße";
+    let ascii = |src| modify(src, value, CaseMapping::default()).unwrap();
+    assert_eq!(ascii("${x^^}"), "éCLAIR üBER STRAßE");
+    assert_eq!(ascii("${x^}"), "éclair über straße");
+    assert_eq!(ascii("${x,,}"), value);
+
+    let convert = |src, value| modify(src, value, CaseMapping::default()).unwrap();
+    assert_eq!(convert("${x^}", "istanbul"), "Istanbul");
+    assert_eq!(convert("${x,}", "ISTANBUL"), "iSTANBUL");
+    assert_eq!(convert("${x,,}", "İSTANBUL"), "İstanbul");
+}
+
+#[test]
+fn test_case_modification_with_unicode_mapping() {
+    let unicode = |src, value| modify(src, value, CaseMapping::Unicode).unwrap();
+    assert_eq!(unicode("${x^^}", "éclair straße"), "ÉCLAIR STRASSE");
+    assert_eq!(unicode("${x^}", "éclair"), "Éclair");
+    assert_eq!(unicode("${x,,}", "ÜBER ΣΑΣ"), "über σασ");
+    assert_eq!(unicode("${x,,[Ü]}", "ÜBER"), "üBER");
+}
+
+#[test]
+fn test_case_modification_patterns() {
+    let ascii = |src| modify(src, "abcabc", CaseMapping::Ascii);
+    assert_eq!(ascii("${x^^a}").unwrap(), "AbcAbc");
+    assert_eq!(ascii("${x^^'b'}").unwrap(), "aBcaBc");
+    assert_eq!(ascii("${x^^\"c\"}").unwrap(), "abCabC");
+    assert_eq!(ascii("${x^^?}").unwrap(), "ABCABC");
+    assert_eq!(ascii("${x^^*}").unwrap(), "ABCABC");
+    assert_eq!(ascii("${x^^[a-b]}").unwrap(), "ABcABc");
+    assert_eq!(ascii("${x^^[!a]}").unwrap(), "aBCaBC");
+    assert_eq!(ascii("${x^^[^ac]}").unwrap(), "aBcaBc");
+    assert_eq!(ascii("${x^b}").unwrap(), "abcabc");
+    assert_eq!(ascii("${x^a}").unwrap(), "Abcabc");
+
+    // Patterns which cannot be evaluated statically
+    assert_eq!(ascii("${x^^$y}"), None);
+    assert_eq!(ascii("${x^^ab}"), None);
+    assert_eq!(ascii("${x^^[a]b}"), None);
+    assert_eq!(ascii("${x^^[]}"), None);
+
+    // Other substitutions are not case modifications
+    assert_eq!(ascii("${x:-y}"), None);
+}
+
+#[test]
+fn test_case_modifications_are_printed_verbatim() {
+    for src in &[
+        "${x^^}",
+        "${x^}",
+        "${x,,}",
+        "${x,}",
+        "${x^^[a-z]}",
+        "${x,,'É'}",
+    ] {
+        let word = parse_word(src, Dialect::Bash).unwrap();
+        assert_eq!(word.to_shell_string(), *src);
+    }
+}
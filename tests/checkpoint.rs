@@ -0,0 +1,146 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::TopLevelCommand;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, SourcePos};
+use std::str::Chars;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Parses all remaining commands, along with the position after each one.
+fn parse_rest(
+    p: &mut DefaultParser<Lexer<Chars<'_>>>,
+) -> Vec<(TopLevelCommand<String>, SourcePos)> {
+    let mut cmds = Vec::new();
+    while let Some(cmd) = p.complete_command().unwrap() {
+        cmds.push((cmd, p.pos()));
+    }
+    cmds
+}
+
+#[test]
+fn test_rewind_reparses_identically() {
+    let sources = [
+        "foo bar; baz\nqux",
+        "cat <<EOF; echo hi\nbody $x\nEOF\nnext",
+        "cat <<-A <<B\n\tone\nA\ntwo\nB\nif true; then :; fi",
+        "echo `echo nested` \"$(sub)\" && f() { :; }\nlast",
+    ];
+
+    for src in &sources {
+        let expected = parse_rest(&mut make_parser(src));
+
+        for skip in 0..expected.len() {
+            let mut p = make_parser(src);
+            for _ in 0..skip {
+                p.complete_command().unwrap();
+            }
+
+            let start = p.pos();
+            let checkpoint = p.checkpoint();
+            assert_eq!(parse_rest(&mut p), &expected[skip..]);
+            assert!(p.is_exhausted());
+
+            p.rewind(checkpoint);
+            assert_eq!(p.pos(), start);
+            assert_eq!(
+                parse_rest(&mut p),
+                &expected[skip..],
+                "{:?} @ {}",
+                src,
+                skip
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rewind_within_heredoc_line() {
+    // Rewinding after the heredoc body has been consumed, but before the
+    // rest of its line has been, must not yield the body twice
+    let src = "cat <<EOF word\nbody\nEOF\nnext";
+    let expected = parse_rest(&mut make_parser(src));
+
+    let mut p = make_parser(src);
+    let checkpoint = p.checkpoint();
+    assert_eq!(p.word().unwrap(), Some(word("cat")));
+    p.redirect().unwrap().unwrap().unwrap();
+    assert_eq!(p.word().unwrap(), Some(word("word")));
+    p.rewind(checkpoint);
+
+    assert_eq!(parse_rest(&mut p), expected);
+}
+
+#[test]
+fn test_nested_checkpoints() {
+    let mut p = make_parser("a b c d");
+
+    let outer = p.checkpoint();
+    assert_eq!(p.word().unwrap(), Some(word("a")));
+
+    let inner = p.checkpoint();
+    assert_eq!(p.word().unwrap(), Some(word("b")));
+    assert_eq!(p.word().unwrap(), Some(word("c")));
+    p.rewind(inner);
+    assert_eq!(p.pos(), src(2, 1, 3));
+
+    let inner = p.checkpoint();
+    assert_eq!(p.word().unwrap(), Some(word("b")));
+    p.commit(inner);
+    assert_eq!(p.word().unwrap(), Some(word("c")));
+
+    p.rewind(outer);
+    assert_eq!(p.pos(), src(0, 1, 1));
+    for w in &["a", "b", "c", "d"] {
+        assert_eq!(p.word().unwrap(), Some(word(w)));
+    }
+    assert_eq!(p.word().unwrap(), None);
+}
+
+#[test]
+#[should_panic(expected = "checkpoint was already released")]
+fn test_rewinding_released_checkpoint_panics() {
+    let mut p = make_parser("a b");
+    let outer = p.checkpoint();
+    let inner = p.checkpoint();
+    p.commit(outer);
+    p.rewind(inner);
+}
+
+#[test]
+fn test_speculate_backs_off_on_failure() {
+    let mut p = make_parser("unless true; echo no");
+
+    let ret = p.speculate(|p| {
+        p.reserved_word(&["unless"])?;
+        p.reserved_word(&["begin"])
+    });
+    assert_eq!(ret, Err(()));
+    assert_eq!(p.pos(), src(0, 1, 1));
+
+    let ret = p.speculate(|p| p.reserved_word(&["unless"]));
+    assert_eq!(ret, Ok("unless"));
+    assert_eq!(
+        parse_rest(&mut p),
+        vec![
+            (cmd("true"), src(13, 1, 14)),
+            (cmd_args("echo", &["no"]), src(20, 1, 21)),
+        ]
+    );
+}
+
+#[test]
+fn test_rewind_discards_tracked_keywords() {
+    let mut p = make_parser("{ a; }; b");
+    p.set_track_keywords(true);
+
+    let checkpoint = p.checkpoint();
+    p.complete_command().unwrap();
+    assert_eq!(p.take_keywords().len(), 2);
+    p.rewind(checkpoint);
+
+    let checkpoint = p.checkpoint();
+    p.complete_command().unwrap();
+    p.rewind(checkpoint);
+    assert_eq!(p.take_keywords(), vec![]);
+}
@@ -1,6 +1,7 @@
 #![deny(rust_2018_idioms)]
 use std::rc::Rc;
 
+use conch_parser::ast::builder::FunctionSyntax;
 use conch_parser::ast::Command::*;
 use conch_parser::ast::CompoundCommandKind::*;
 use conch_parser::ast::PipeableCommand::*;
@@ -75,6 +76,7 @@ fn test_command_delegates_valid_commands_brace() {
     let correct = Compound(Box::new(CompoundCommand {
         kind: Brace(vec![cmd("foo")]),
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(correct, make_parser("{ foo; }").command().unwrap());
 }
@@ -86,6 +88,7 @@ fn test_command_delegates_valid_commands_subshell() {
     let correct = Compound(Box::new(CompoundCommand {
         kind: Subshell(vec![cmd("foo")]),
         io: vec![],
+        io_span: None,
     }));
 
     for cmd in &commands {
@@ -108,6 +111,7 @@ fn test_command_delegates_valid_commands_while() {
             body: vec![cmd("foo")],
         }),
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(
         correct,
@@ -123,6 +127,7 @@ fn test_command_delegates_valid_commands_until() {
             body: vec![cmd("foo")],
         }),
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(
         correct,
@@ -139,6 +144,7 @@ fn test_command_delegates_valid_commands_for() {
             body: vec![cmd("foo")],
         },
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(
         correct,
@@ -157,6 +163,7 @@ fn test_command_delegates_valid_commands_if() {
             else_branch: None,
         },
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(
         correct,
@@ -172,6 +179,7 @@ fn test_command_delegates_valid_commands_case() {
             arms: vec![],
         },
         io: vec![],
+        io_span: None,
     }));
     assert_eq!(correct, make_parser("case foo in esac").command().unwrap());
 }
@@ -185,26 +193,43 @@ fn test_command_delegates_valid_simple_commands() {
 #[test]
 fn test_command_delegates_valid_commands_function() {
     let commands = [
-        "function foo()      { echo body; }",
-        "function foo ()     { echo body; }",
-        "function foo (    ) { echo body; }",
-        "function foo(    )  { echo body; }",
-        "function foo        { echo body; }",
-        "foo()               { echo body; }",
-        "foo ()              { echo body; }",
-        "foo (    )          { echo body; }",
-        "foo(    )           { echo body; }",
+        (
+            "function foo()      { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo ()     { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo (    ) { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo(    )  { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo        { echo body; }",
+            FunctionSyntax::Keyword,
+        ),
+        ("foo()               { echo body; }", FunctionSyntax::Posix),
+        ("foo ()              { echo body; }", FunctionSyntax::Posix),
+        ("foo (    )          { echo body; }", FunctionSyntax::Posix),
+        ("foo(    )           { echo body; }", FunctionSyntax::Posix),
     ];
 
-    let correct = FunctionDef(
-        String::from("foo"),
-        Rc::new(CompoundCommand {
-            kind: Brace(vec![cmd_args("echo", &["body"])]),
-            io: vec![],
-        }),
-    );
+    for &(cmd, syntax) in &commands {
+        let correct = FunctionDef(
+            String::from("foo"),
+            syntax,
+            Rc::new(CompoundCommand {
+                kind: Brace(vec![cmd_args("echo", &["body"])]),
+                io: vec![],
+                io_span: None,
+            }),
+        );
 
-    for cmd in &commands {
         match make_parser(cmd).command() {
             Ok(ref result) if result == &correct => {}
             Ok(result) => panic!(
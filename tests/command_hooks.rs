@@ -0,0 +1,88 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{CommandHooks, StringBuilder};
+use conch_parser::ast::{RedirectOrCmdWord, Span};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::Parser;
+use std::cell::RefCell;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_hooks_see_commands_by_literal_name() {
+    let src = "git push; 'git' status >out; g\\it log; $git x; echo git\n\
+               if true; then x=1 git -C dir; fi; echo \"$(git rev-parse)\"";
+    let calls = RefCell::new(Vec::new());
+
+    let mut hooks = CommandHooks::new(StringBuilder::new());
+    hooks.on("git", |cmd, span: Span| {
+        let args = cmd
+            .redirects_or_cmd_words
+            .iter()
+            .filter_map(|w| match *w {
+                RedirectOrCmdWord::CmdWord(ref w) => Some(w.to_string()),
+                RedirectOrCmdWord::Redirect(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        calls.borrow_mut().push((
+            args.join(" "),
+            cmd.redirects_or_env_vars.len(),
+            span.start.byte,
+        ));
+        Ok(())
+    });
+
+    let cmds = Parser::with_builder(Lexer::new(src.chars()), &mut hooks)
+        .parse_all()
+        .unwrap();
+    assert_eq!(cmds, make_parser(src).parse_all().unwrap());
+    drop(hooks);
+
+    assert_eq!(
+        calls.into_inner(),
+        vec![
+            ("git push".to_owned(), 0, 0),
+            ("'git' status".to_owned(), 0, 10),
+            ("g\\it log".to_owned(), 0, 29),
+            ("git -C dir".to_owned(), 1, 70),
+            ("git rev-parse".to_owned(), 0, 98),
+        ]
+    );
+}
+
+#[test]
+fn test_hooks_run_in_registration_order() {
+    let order = RefCell::new(Vec::new());
+
+    let mut hooks = CommandHooks::new(StringBuilder::new());
+    hooks
+        .on("a", |_, _| {
+            order.borrow_mut().push("a1");
+            Ok(())
+        })
+        .on("b", |_, _| {
+            order.borrow_mut().push("b");
+            Ok(())
+        })
+        .on("a", |_, _| {
+            order.borrow_mut().push("a2");
+            Ok(())
+        });
+
+    Parser::with_builder(Lexer::new("a; b | a; c".chars()), &mut hooks)
+        .parse_all()
+        .unwrap();
+    drop(hooks);
+
+    assert_eq!(order.into_inner(), vec!["a1", "a2", "b", "a1", "a2"]);
+}
+
+#[test]
+fn test_hooks_debug_lists_names() {
+    let mut hooks = CommandHooks::new(StringBuilder::new());
+    hooks.on("kubectl", |_, _| Ok(())).on("git", |_, _| Ok(()));
+
+    let debug = format!("{:?}", hooks);
+    assert!(debug.contains("[\"git\", \"kubectl\"]"), "{}", debug);
+}
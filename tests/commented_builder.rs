@@ -0,0 +1,124 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{CommentedDefaultBuilder, Newline};
+use conch_parser::ast::{
+    Command, CommentedTopLevelCommand, CompoundCommandKind, ListableCommand, PipeableCommand,
+};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{ErrorRecovery, Parser};
+
+fn parse(src: &str) -> (Vec<CommentedTopLevelCommand<String>>, Vec<Newline>) {
+    let mut builder = CommentedDefaultBuilder::new();
+    let mut parser = Parser::with_builder(Lexer::new(src.chars()), &mut builder);
+    parser.set_error_recovery(ErrorRecovery::Passthrough);
+    let cmds = parser.parse_all().unwrap();
+    (cmds, builder.take_trailing_comments())
+}
+
+fn comment(s: &str) -> Newline {
+    Newline(Some(String::from(s)))
+}
+
+fn blank() -> Newline {
+    Newline(None)
+}
+
+/// Returns the commands within the body of a compound command.
+fn body(cmd: &CommentedTopLevelCommand<String>) -> &[CommentedTopLevelCommand<String>] {
+    let list = match cmd.command {
+        Command::List(ref list) => list,
+        ref c => panic!("unexpected command: {:?}", c),
+    };
+
+    match list.first {
+        ListableCommand::Single(PipeableCommand::Compound(ref compound)) => match compound.kind {
+            CompoundCommandKind::Brace(ref body) => body,
+            CompoundCommandKind::While(ref pair) => &pair.body,
+            ref kind => panic!("unexpected compound command: {:?}", kind),
+        },
+        ref c => panic!("unexpected command: {:?}", c),
+    }
+}
+
+#[test]
+fn test_comments_are_attached_to_commands() {
+    let src = "\
+#!/bin/sh
+# leading
+
+foo # same line
+bar &&
+  # after and
+  baz ||
+  qux
+";
+    let (cmds, trailing) = parse(src);
+    assert_eq!(cmds.len(), 2);
+    assert!(trailing.is_empty());
+
+    assert_eq!(
+        cmds[0].pre_cmd_comments,
+        vec![comment("#!/bin/sh"), comment("# leading"), blank()]
+    );
+    assert_eq!(cmds[0].cmd_comment, Some(comment("# same line")));
+    assert!(cmds[0].and_or_comments.is_empty());
+
+    assert!(cmds[1].pre_cmd_comments.is_empty());
+    assert_eq!(cmds[1].cmd_comment, None);
+    assert_eq!(
+        cmds[1].and_or_comments,
+        vec![vec![blank(), comment("# after and")], vec![blank()]]
+    );
+}
+
+#[test]
+fn test_comments_trailing_a_body_are_attached_to_its_last_command() {
+    let src = "\
+{
+  # first
+  a
+  b # b
+  # before brace
+}
+while x; do
+  y
+  # before done
+done
+";
+    let (cmds, _) = parse(src);
+
+    let brace = body(&cmds[0]);
+    assert_eq!(brace.len(), 2);
+    // The newline after `{` is reported before the first command
+    assert_eq!(brace[0].pre_cmd_comments, vec![blank(), comment("# first")]);
+    assert!(brace[0].post_cmd_comments.is_empty());
+    assert_eq!(brace[1].cmd_comment, Some(comment("# b")));
+    assert_eq!(brace[1].post_cmd_comments, vec![comment("# before brace")]);
+
+    let loop_body = body(&cmds[1]);
+    assert_eq!(loop_body.len(), 1);
+    assert_eq!(
+        loop_body[0].post_cmd_comments,
+        vec![comment("# before done")]
+    );
+}
+
+#[test]
+fn test_comments_at_end_of_input_are_retained_by_builder() {
+    let (cmds, trailing) = parse("foo\n# one\n\n# two\n");
+    assert_eq!(cmds.len(), 1);
+    assert_eq!(cmds[0].cmd_comment, None);
+    assert_eq!(trailing, vec![comment("# one"), blank(), comment("# two")]);
+}
+
+#[test]
+fn test_comments_before_unparsed_regions_are_retained() {
+    let (cmds, _) = parse("# broken\na && ;;\nb");
+    assert_eq!(cmds.len(), 2);
+    match cmds[0].command {
+        Command::Unparsed(_) => {}
+        ref c => panic!("unexpected command: {:?}", c),
+    }
+    assert_eq!(cmds[0].pre_cmd_comments, vec![comment("# broken")]);
+    assert!(cmds[0].and_or_comments.is_empty());
+    assert!(cmds[1].and_or_comments.is_empty());
+}
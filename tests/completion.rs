@@ -0,0 +1,191 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::{Parameter, ParameterSubstitution, SimpleWord};
+use conch_parser::completion::CompletionKind::*;
+use conch_parser::completion::{completion_context, CompletionKind};
+use conch_parser::token::Token::{self, Backtick, CurlyOpen, DoubleQuote, ParenOpen};
+
+fn complete(src: &str) -> Option<(CompletionKind, String, Vec<Token>)> {
+    completion_context(src).map(|c| (c.kind, c.prefix, c.open))
+}
+
+fn ctx(
+    kind: CompletionKind,
+    prefix: &str,
+    open: &[Token],
+) -> Option<(CompletionKind, String, Vec<Token>)> {
+    Some((kind, String::from(prefix), open.to_vec()))
+}
+
+#[test]
+fn test_completion_commands_and_arguments() {
+    assert_eq!(complete(""), ctx(Command, "", &[]));
+    assert_eq!(complete("ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("foo=1 ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("echo"), ctx(Command, "echo", &[]));
+    assert_eq!(complete("echo "), ctx(Argument, "", &[]));
+    assert_eq!(complete("echo a fo"), ctx(Argument, "fo", &[]));
+    assert_eq!(complete("echo a 'b c'd\\ e"), ctx(Argument, "b cd e", &[]));
+    assert_eq!(complete("echo a >fo"), ctx(Argument, "fo", &[]));
+    assert_eq!(complete("foo=ba"), ctx(Argument, "ba", &[]));
+}
+
+#[test]
+fn test_completion_after_separators() {
+    for &src in &[
+        "foo; ", "foo;", "foo &", "foo\n", "foo && ", "foo | ", "if ", "foo | b",
+    ] {
+        let expected = if src.ends_with('b') { "b" } else { "" };
+        assert_eq!(complete(src), ctx(Command, expected, &[]), "src: {:?}", src);
+    }
+}
+
+#[test]
+fn test_completion_unbraced_variables() {
+    assert_eq!(complete("echo $HO"), ctx(Variable, "HO", &[]));
+    assert_eq!(complete("echo a$"), ctx(Variable, "", &[]));
+    assert_eq!(complete("echo ${HO}"), None);
+    assert_eq!(complete("echo \\$HO"), ctx(Argument, "$HO", &[]));
+}
+
+#[test]
+fn test_completion_dangling_parameter_substitution() {
+    let context = completion_context("echo ${HO").unwrap();
+    assert_eq!(context.kind, Variable);
+    assert_eq!(context.prefix, "HO");
+    assert_eq!(context.open, vec![CurlyOpen]);
+    assert_eq!(
+        context.expansion,
+        Some(SimpleWord::Param(Parameter::Var(String::from("HO"))))
+    );
+
+    assert_eq!(complete("echo ${"), ctx(Variable, "", &[CurlyOpen]));
+    assert_eq!(complete("echo ${#HO"), ctx(Variable, "HO", &[CurlyOpen]));
+    assert_eq!(complete("echo ${x:-fo"), ctx(Argument, "fo", &[CurlyOpen]));
+    assert_eq!(complete("echo ${x:-$HO"), ctx(Variable, "HO", &[CurlyOpen]));
+}
+
+#[test]
+fn test_completion_dangling_command_substitution() {
+    assert_eq!(complete("echo $("), ctx(Command, "", &[ParenOpen]));
+    assert_eq!(complete("echo $(gi"), ctx(Command, "gi", &[ParenOpen]));
+    assert_eq!(complete("echo $(git st"), ctx(Argument, "st", &[ParenOpen]));
+    assert_eq!(complete("echo $(git "), ctx(Argument, "", &[ParenOpen]));
+
+    let context = completion_context("x=$(cat ${HO").unwrap();
+    assert_eq!(context.kind, Variable);
+    assert_eq!(context.prefix, "HO");
+    assert_eq!(context.open, vec![ParenOpen, CurlyOpen]);
+    assert_eq!(
+        context.expansion,
+        Some(SimpleWord::Param(Parameter::Var(String::from("HO"))))
+    );
+
+    match completion_context("echo $(gi").unwrap().expansion {
+        Some(SimpleWord::Subst(ref subst)) => match **subst {
+            ParameterSubstitution::Command(ref cmds) => assert_eq!(cmds.len(), 1),
+            ref subst => panic!("unexpected substitution: {:?}", subst),
+        },
+        expansion => panic!("unexpected expansion: {:?}", expansion),
+    }
+}
+
+#[test]
+fn test_completion_within_double_quotes() {
+    assert_eq!(complete("echo \"fo"), ctx(Argument, "fo", &[DoubleQuote]));
+    assert_eq!(
+        complete("echo a'b'\"c d"),
+        ctx(Argument, "abc d", &[DoubleQuote])
+    );
+    assert_eq!(complete("echo \""), ctx(Argument, "", &[DoubleQuote]));
+    assert_eq!(complete("echo \"$HO"), ctx(Variable, "HO", &[DoubleQuote]));
+    assert_eq!(
+        complete("echo \"${HO"),
+        ctx(Variable, "HO", &[DoubleQuote, CurlyOpen])
+    );
+    assert_eq!(
+        complete("echo \"$(cat ${HO"),
+        ctx(Variable, "HO", &[DoubleQuote, ParenOpen, CurlyOpen])
+    );
+    assert_eq!(
+        complete("echo \"$(git st"),
+        ctx(Argument, "st", &[DoubleQuote, ParenOpen])
+    );
+    assert_eq!(
+        complete("echo $(echo \"fo"),
+        ctx(Argument, "fo", &[ParenOpen, DoubleQuote])
+    );
+
+    let context = completion_context("echo \"${HO").unwrap();
+    assert_eq!(
+        context.expansion,
+        Some(SimpleWord::Param(Parameter::Var(String::from("HO"))))
+    );
+}
+
+#[test]
+fn test_completion_within_backticks() {
+    assert_eq!(complete("echo `"), ctx(Command, "", &[Backtick]));
+    assert_eq!(complete("echo `ec"), ctx(Command, "ec", &[Backtick]));
+    assert_eq!(complete("echo `git st"), ctx(Argument, "st", &[Backtick]));
+    assert_eq!(
+        complete("echo `cat ${HO"),
+        ctx(Variable, "HO", &[Backtick, CurlyOpen])
+    );
+    assert_eq!(
+        complete("echo \"`ec"),
+        ctx(Command, "ec", &[DoubleQuote, Backtick])
+    );
+    assert_eq!(complete("echo `ec`"), None);
+}
+
+#[test]
+fn test_completion_within_compound_commands() {
+    assert_eq!(complete("if true; then ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("if true; then echo "), ctx(Argument, "", &[]));
+    assert_eq!(complete("if true; then echo;"), ctx(Command, "", &[]));
+    assert_eq!(complete("if tr"), ctx(Command, "tr", &[]));
+    assert_eq!(
+        complete("if a; then b; elif c; then d; else ec"),
+        ctx(Command, "ec", &[])
+    );
+    assert_eq!(complete("while tr"), ctx(Command, "tr", &[]));
+    assert_eq!(complete("until a; do ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("for x in a b; do echo $"), ctx(Variable, "", &[]));
+    assert_eq!(complete("for f in *.tx"), ctx(Argument, "*.tx", &[]));
+    assert_eq!(complete("for f in "), ctx(Argument, "", &[]));
+    assert_eq!(complete("case $x in a) ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("{ ec"), ctx(Command, "ec", &[]));
+    assert_eq!(complete("(cd foo; ec"), ctx(Command, "ec", &[]));
+    assert_eq!(
+        complete("foo() { echo \"$HO"),
+        ctx(Variable, "HO", &[DoubleQuote])
+    );
+    assert_eq!(
+        complete("echo $(if true; then echo ${HO"),
+        ctx(Variable, "HO", &[ParenOpen, CurlyOpen])
+    );
+    assert_eq!(
+        complete("if true; then echo $(ec"),
+        ctx(Command, "ec", &[ParenOpen])
+    );
+}
+
+#[test]
+fn test_completion_after_trailing_backslash() {
+    assert_eq!(complete("echo \\"), ctx(Argument, "", &[]));
+    assert_eq!(complete("\\"), ctx(Command, "", &[]));
+    assert_eq!(complete("echo a\\"), ctx(Argument, "a", &[]));
+    assert_eq!(complete("echo \\\\"), ctx(Argument, "\\", &[]));
+}
+
+#[test]
+fn test_completion_unavailable() {
+    assert_eq!(complete("echo 'a"), None);
+    assert_eq!(complete("echo \"a\""), None);
+    assert_eq!(complete("echo $(foo)"), None);
+    assert_eq!(complete("if true; then foo; fi"), None);
+    assert_eq!(complete("while true; do foo; done"), None);
+    assert_eq!(complete("{ foo; }"), None);
+    assert_eq!(complete("(foo) >ou"), None);
+    assert_eq!(complete("echo )"), None);
+}
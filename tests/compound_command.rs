@@ -151,6 +151,7 @@ fn test_compound_command_delegates_valid_commands_brace() {
     let correct = CompoundCommand {
         kind: Brace(vec![cmd("foo")]),
         io: vec![],
+        io_span: None,
     };
     assert_eq!(correct, make_parser("{ foo; }").compound_command().unwrap());
 }
@@ -162,6 +163,7 @@ fn test_compound_command_delegates_valid_commands_subshell() {
     let correct = CompoundCommand {
         kind: Subshell(vec![cmd("foo")]),
         io: vec![],
+        io_span: None,
     };
 
     for cmd in &commands {
@@ -184,6 +186,7 @@ fn test_compound_command_delegates_valid_commands_while() {
             body: vec![cmd("foo")],
         }),
         io: vec![],
+        io_span: None,
     };
     assert_eq!(
         correct,
@@ -201,6 +204,7 @@ fn test_compound_command_delegates_valid_commands_until() {
             body: vec![cmd("foo")],
         }),
         io: vec![],
+        io_span: None,
     };
     assert_eq!(
         correct,
@@ -219,6 +223,7 @@ fn test_compound_command_delegates_valid_commands_for() {
             body: vec![cmd("foo")],
         },
         io: vec![],
+        io_span: None,
     };
     assert_eq!(
         correct,
@@ -239,6 +244,7 @@ fn test_compound_command_delegates_valid_commands_if() {
             else_branch: None,
         },
         io: vec![],
+        io_span: None,
     };
     assert_eq!(
         correct,
@@ -256,6 +262,7 @@ fn test_compound_command_delegates_valid_commands_case() {
             arms: vec![],
         },
         io: vec![],
+        io_span: None,
     };
     assert_eq!(
         correct,
@@ -364,6 +371,75 @@ fn test_compound_command_captures_redirections_after_command() {
     }
 }
 
+#[test]
+fn test_compound_command_records_span_of_redirections_after_command() {
+    let cases = [
+        "{ foo; } 1>>out <& 2 2>&-",
+        "( foo; ) 1>>out <& 2 2>&-",
+        "while guard; do foo; done 1>>out <& 2 2>&-",
+        "if guard; then body; fi 1>>out <& 2 2>&-",
+        "for var in; do foo; done 1>>out <& 2 2>&-",
+        "case foo in esac 1>>out <& 2 2>&-  ",
+    ];
+
+    for cmd in &cases {
+        let start = cmd.find("1>>").unwrap();
+        let end = cmd.find("&-").unwrap() + 2;
+        let correct = Some(Span {
+            start: src(start, 1, start + 1),
+            end: src(end, 1, end + 1),
+        });
+
+        match make_parser(cmd).compound_command() {
+            Ok(CompoundCommand { io_span, .. }) => assert_eq!(io_span, correct),
+            Err(err) => panic!("Failed to parse \"{}\": {}", cmd, err),
+        }
+    }
+
+    let cmd = make_parser("{ foo; }  ").compound_command().unwrap();
+    assert_eq!(cmd.io_span, None);
+}
+
+#[test]
+fn test_compound_command_captures_heredocs_after_command() {
+    let mut p = make_parser("while read l; do foo; done <<EOF 2>err <<-B\nx\nEOF\n\ty\n\tB\nbar");
+    let correct = CompoundCommand {
+        kind: While(GuardBodyPair {
+            guard: vec![cmd_args("read", &["l"])],
+            body: vec![cmd("foo")],
+        }),
+        io: vec![
+            Redirect::Heredoc(
+                None,
+                word("x\n"),
+                HeredocInfo {
+                    delimiter: String::from("EOF"),
+                    quoted: false,
+                    strip_tabs: false,
+                    lines: vec![String::from("x")],
+                },
+            ),
+            Redirect::Write(Some(2), word("err")),
+            Redirect::Heredoc(
+                None,
+                word("y\n"),
+                HeredocInfo {
+                    delimiter: String::from("B"),
+                    quoted: false,
+                    strip_tabs: true,
+                    lines: vec![String::from("\ty")],
+                },
+            ),
+        ],
+        io_span: Some(Span {
+            start: src(27, 1, 28),
+            end: src(43, 1, 44),
+        }),
+    };
+    assert_eq!(correct, p.compound_command().unwrap());
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("bar")));
+}
+
 #[test]
 fn test_compound_command_should_delegate_literals_and_names_loop() {
     for kw in vec![
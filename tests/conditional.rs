@@ -0,0 +1,215 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::parse::ParseError::*;
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn var(name: &str) -> TopLevelWord<String> {
+    word_param(Parameter::Var(String::from(name)))
+}
+
+fn cond_word(s: &str) -> Box<DefaultConditional> {
+    Box::new(Conditional::Word(word(s)))
+}
+
+fn parse(src: &str) -> DefaultConditional {
+    make_parser(src).conditional_command().unwrap()
+}
+
+#[test]
+fn test_conditional_command_valid() {
+    let glob = TopLevelWord(ComplexWord::Concat(vec![
+        Word::Simple(SimpleWord::Star),
+        lit(".sh"),
+    ]));
+
+    let correct = Conditional::And(
+        Box::new(Conditional::Unary('f', var("x"))),
+        Box::new(Conditional::Or(
+            Box::new(Conditional::Binary(var("y"), BinaryTest::Match, glob)),
+            Box::new(Conditional::Not(Box::new(Conditional::Unary(
+                'd',
+                var("y"),
+            )))),
+        )),
+    );
+
+    assert_eq!(parse("[[ -f $x && ( $y == *.sh || ! -d $y ) ]]"), correct);
+}
+
+#[test]
+fn test_conditional_command_precedence() {
+    let a_or_b_and_c = Conditional::Or(
+        cond_word("a"),
+        Box::new(Conditional::And(cond_word("b"), cond_word("c"))),
+    );
+    assert_eq!(parse("[[ a || b && c ]]"), a_or_b_and_c);
+
+    let a_and_b_or_c = Conditional::Or(
+        Box::new(Conditional::And(cond_word("a"), cond_word("b"))),
+        cond_word("c"),
+    );
+    assert_eq!(parse("[[ a && b || c ]]"), a_and_b_or_c);
+    assert_eq!(parse("[[ (a && b) || c ]]"), a_and_b_or_c);
+
+    let not_a_and_b = Conditional::And(Box::new(Conditional::Not(cond_word("a"))), cond_word("b"));
+    assert_eq!(parse("[[ ! a && b ]]"), not_a_and_b);
+}
+
+#[test]
+fn test_conditional_command_operators() {
+    for &op in BinaryTest::ALL.iter() {
+        let src = format!("[[ a {} b ]]", op);
+        let correct = Conditional::Binary(word("a"), op, word("b"));
+        assert_eq!(parse(&src), correct, "{}", src);
+    }
+
+    let correct = Conditional::Binary(word("a"), BinaryTest::Match, word("b"));
+    assert_eq!(parse("[[ a = b ]]"), correct);
+
+    for op in UNARY_TESTS.chars() {
+        let src = format!("[[ -{} a ]]", op);
+        assert_eq!(parse(&src), Conditional::Unary(op, word("a")), "{}", src);
+    }
+
+    // Lone operators are tested as plain words
+    assert_eq!(parse("[[ ! ]]"), Conditional::Word(word("!")));
+    assert_eq!(parse("[[ == ]]"), Conditional::Word(word("==")));
+}
+
+#[test]
+fn test_conditional_command_regex_allows_parens_and_pipes() {
+    let cond = parse("[[ $x =~ ^(a|b c)+$ ]]");
+    let rhs = match cond {
+        Conditional::Binary(_, BinaryTest::RegexMatch, ref rhs) => rhs.clone(),
+        _ => panic!("expected a regex match: {:?}", cond),
+    };
+
+    let words = match rhs.0 {
+        ComplexWord::Concat(words) => words,
+        ComplexWord::Single(w) => vec![w],
+    };
+    let text: String = words
+        .iter()
+        .map(|w| match *w {
+            Word::Simple(SimpleWord::Literal(ref s)) => s.clone(),
+            Word::Simple(SimpleWord::Param(Parameter::Dollar)) => String::from("$"),
+            ref w => panic!("unexpected word: {:?}", w),
+        })
+        .collect();
+    assert_eq!(text, "^(a|b c)+$");
+}
+
+#[test]
+fn test_conditional_command_with_newlines_and_redirects() {
+    let mut p = make_parser("[[ a\n&&\nb\n]] >out && echo");
+    let cmd = p.complete_command().unwrap().unwrap();
+
+    let correct = CompoundCommand {
+        kind: CompoundCommandKind::Conditional(Conditional::And(cond_word("a"), cond_word("b"))),
+        io: vec![Redirect::Write(None, word("out"))],
+        io_span: None,
+    };
+
+    match cmd.0 {
+        Command::List(AndOrList { first, ref rest }) => {
+            assert_eq!(rest.len(), 1);
+            match first {
+                ListableCommand::Single(PipeableCommand::Compound(ref compound)) => {
+                    assert_eq!(compound.kind, correct.kind);
+                    assert_eq!(compound.io, correct.io);
+                }
+                ref other => panic!("expected a compound command: {:?}", other),
+            }
+        }
+        ref other => panic!("expected a command list: {:?}", other),
+    }
+}
+
+#[test]
+fn test_conditional_command_round_trips_through_printer() {
+    let sources = [
+        "[[ -f $x && ( $y == *.sh || ! -d $y ) ]]\n",
+        "[[ a || b && c ]] >out\n",
+        "[[ ! ( a || b ) ]]\n",
+        "[[ \"$a\" < b ]]\n",
+    ];
+
+    for src in &sources {
+        let cmds = make_parser(src).parse_all().unwrap();
+        assert_eq!(cmds.to_shell_string(), *src);
+    }
+}
+
+#[test]
+fn test_conditional_command_invalid() {
+    let cases = [
+        (
+            "[[ a",
+            IncompleteCmd("[[", src(0, 1, 1), "]]", src(4, 1, 5)),
+        ),
+        (
+            "[[ -n $x &&",
+            IncompleteCmd("[[", src(0, 1, 1), "]]", src(11, 1, 12)),
+        ),
+        ("[[ ]]", Unexpected(Token::SquareClose, src(3, 1, 4))),
+        ("[[ -n ]]", Unexpected(Token::SquareClose, src(6, 1, 7))),
+        ("[[ a == ]]", Unexpected(Token::SquareClose, src(8, 1, 9))),
+        (
+            "[[ a b ]]",
+            Unexpected(Token::Name(String::from("b")), src(5, 1, 6)),
+        ),
+        ("[[ a && ]]", Unexpected(Token::SquareClose, src(8, 1, 9))),
+        ("[[ ( a ]]", Unexpected(Token::SquareClose, src(7, 1, 8))),
+        ("[[ a; ]]", Unexpected(Token::Semi, src(4, 1, 5))),
+    ];
+
+    for (s, err) in cases.iter() {
+        assert_eq!(make_parser(s).complete_command(), Err(err.clone()), "{}", s);
+    }
+}
+
+#[test]
+fn test_conditional_command_requires_delimited_brackets() {
+    // `[[` must be a word on its own to start a conditional command
+    let cmds = make_parser("[[a ]]").parse_all().unwrap();
+    assert_eq!(cmds.len(), 1);
+    match cmds[0].0 {
+        Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Simple(_)),
+            ..
+        }) => {}
+        ref other => panic!("expected a simple command: {:?}", other),
+    }
+
+    // `]]` only closes the command if it is a word on its own
+    let cond = parse("[[ a]] ]]");
+    let words = TopLevelWord(ComplexWord::Concat(vec![
+        lit("a"),
+        Word::Simple(SimpleWord::SquareClose),
+        Word::Simple(SimpleWord::SquareClose),
+    ]));
+    assert_eq!(cond, Conditional::Word(words));
+}
+
+#[test]
+fn test_conditional_command_keywords() {
+    let mut p = make_parser("[[ a ]] && [[ -n b ]]");
+    p.set_track_keywords(true);
+    p.complete_command().unwrap();
+
+    let keywords = p
+        .take_keywords()
+        .into_iter()
+        .map(|kw| (kw.word, kw.span.start.byte, kw.span.end.byte))
+        .collect::<Vec<_>>();
+    let expected = [("[[", 0, 2), ("]]", 5, 7), ("[[", 11, 13), ("]]", 19, 21)];
+    let expected = expected
+        .iter()
+        .map(|&(w, start, end)| (String::from(w), start, end))
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, expected);
+}
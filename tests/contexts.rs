@@ -0,0 +1,141 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{ContextKind, Event, EventLog};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{ErrorRecovery, Parser};
+
+fn context_name(kind: &ContextKind) -> String {
+    match *kind {
+        ContextKind::Function(ref name) => format!("function {}", name),
+        ContextKind::Subshell => String::from("subshell"),
+        ContextKind::BraceGroup => String::from("brace"),
+        ContextKind::CommandSubstitution => String::from("cmd subst"),
+        ContextKind::ProcessSubstitution => String::from("proc subst"),
+        _ => unimplemented!(),
+    }
+}
+
+/// Records the contexts entered (`+`) and exited (`-`) while parsing, along
+/// with any commands built in between.
+fn trace_events(events: &[Event]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::EnterContext { ref kind } => Some(format!("+{}", context_name(kind))),
+            Event::ExitContext { ref kind } => Some(format!("-{}", context_name(kind))),
+            Event::SimpleCommand { .. } => Some(String::from("simple")),
+            Event::BraceGroup { .. } => Some(String::from("brace")),
+            Event::Subshell { .. } => Some(String::from("subshell")),
+            Event::FunctionDeclaration { .. } => Some(String::from("function")),
+            Event::Unparsed { .. } => Some(String::from("unparsed")),
+            _ => None,
+        })
+        .collect()
+}
+
+fn trace(src: &str, error_recovery: ErrorRecovery) -> Vec<String> {
+    let mut log = EventLog::new();
+    let mut parser = Parser::with_builder(Lexer::new(src.chars()), &mut log);
+    parser.set_error_recovery(error_recovery);
+    parser.parse_all().unwrap();
+    trace_events(log.events())
+}
+
+#[test]
+fn test_contexts_are_entered_and_exited_around_their_bodies() {
+    assert_eq!(
+        trace("f() { a; }", ErrorRecovery::Abort),
+        vec![
+            "+function f",
+            "+brace",
+            "simple",
+            "-brace",
+            "brace",
+            "-function f",
+            "function",
+        ]
+    );
+
+    assert_eq!(
+        trace("( a $(b `c`) <(d) >(e) ); f", ErrorRecovery::Abort),
+        vec![
+            "+subshell",
+            "+cmd subst",
+            "+cmd subst",
+            "simple",
+            "-cmd subst",
+            "simple",
+            "-cmd subst",
+            "+proc subst",
+            "simple",
+            "-proc subst",
+            "+proc subst",
+            "simple",
+            "-proc subst",
+            "simple",
+            "-subshell",
+            "subshell",
+            "simple",
+        ]
+    );
+}
+
+#[test]
+fn test_function_parens_do_not_enter_a_subshell() {
+    let expected = vec![
+        "+function f",
+        "+brace",
+        "simple",
+        "-brace",
+        "brace",
+        "-function f",
+        "function",
+    ];
+    assert_eq!(
+        trace("function f () { a; }", ErrorRecovery::Abort),
+        expected
+    );
+    assert_eq!(trace("f ( ) { a; }", ErrorRecovery::Abort), expected);
+
+    assert_eq!(
+        trace("function f (a) >out", ErrorRecovery::Abort),
+        vec![
+            "+function f",
+            "+subshell",
+            "simple",
+            "-subshell",
+            "subshell",
+            "-function f",
+            "function",
+        ]
+    );
+}
+
+#[test]
+fn test_contexts_are_exited_when_recovering_from_errors() {
+    assert_eq!(
+        trace("f() { ( a; ;; ) }\nb", ErrorRecovery::Passthrough),
+        vec![
+            "+function f",
+            "+brace",
+            "+subshell",
+            "simple",
+            "-subshell",
+            "-brace",
+            "-function f",
+            "unparsed",
+            "simple",
+        ]
+    );
+}
+
+#[test]
+fn test_contexts_are_replayed() {
+    let mut log = EventLog::new();
+    Parser::with_builder(Lexer::new("f() { echo $(a); }".chars()), &mut log)
+        .parse_all()
+        .unwrap();
+
+    let mut replayed = EventLog::new();
+    log.replay(&mut replayed).unwrap();
+    assert_eq!(trace_events(replayed.events()), trace_events(log.events()));
+}
@@ -0,0 +1,144 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{Event, EventLog};
+use conch_parser::lexer::{Decoded, Encoding, Transcoder};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+}
+
+fn utf16be(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|u| u.to_be_bytes()).collect()
+}
+
+/// Parses the decoded source, returning the original bytes covered by every word.
+fn original_words(decoded: &Decoded, bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut log = EventLog::new();
+    parse_with(decoded.text(), &mut log);
+
+    log.into_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Word { span, .. } => Some(bytes[decoded.original_range(span)].to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_encodings_are_detected_from_byte_order_marks() {
+    assert_eq!(
+        Encoding::from_bom(b"\xEF\xBB\xBFecho"),
+        Some((Encoding::Utf8, 3))
+    );
+    assert_eq!(
+        Encoding::from_bom(b"\xFF\xFEe\0"),
+        Some((Encoding::Utf16Le, 2))
+    );
+    assert_eq!(
+        Encoding::from_bom(b"\xFE\xFF\0e"),
+        Some((Encoding::Utf16Be, 2))
+    );
+    assert_eq!(Encoding::from_bom(b"echo"), None);
+
+    let src = "echo \u{e9}t\u{e9} \u{1f600}";
+    let mut utf8 = b"\xEF\xBB\xBF".to_vec();
+    utf8.extend(src.as_bytes());
+    let mut le = vec![0xFF, 0xFE];
+    le.extend(utf16le(src));
+    let mut be = vec![0xFE, 0xFF];
+    be.extend(utf16be(src));
+
+    for bytes in &[src.as_bytes().to_vec(), utf8, le, be] {
+        assert_eq!(Decoded::detect(bytes).text(), src);
+    }
+}
+
+#[test]
+fn test_byte_order_marks_are_only_skipped_when_detecting() {
+    let bytes = b"\xEF\xBB\xBFecho";
+    assert_eq!(Decoded::new(bytes, Encoding::Utf8).text(), "\u{feff}echo");
+    assert_eq!(Decoded::detect(bytes).original_byte(0), 3);
+}
+
+#[test]
+fn test_fallback_encoding_without_byte_order_mark() {
+    let bytes = b"echo caf\xE9";
+    assert_eq!(Decoded::detect(bytes).text(), "echo caf\u{fffd}");
+    assert_eq!(
+        Decoded::detect_or(bytes, Encoding::Latin1).text(),
+        "echo caf\u{e9}"
+    );
+
+    // A byte order mark takes precedence over the fallback
+    assert_eq!(
+        Decoded::detect_or(b"\xEF\xBB\xBFcaf\xC3\xA9", Encoding::Latin1).text(),
+        "caf\u{e9}"
+    );
+}
+
+#[test]
+fn test_spans_map_back_to_original_bytes() {
+    let latin1 = b"echo caf\xE9 \xFCber\nfoo=\xE9 bar";
+    let decoded = Decoded::new(latin1, Encoding::Latin1);
+    assert_eq!(
+        original_words(&decoded, latin1),
+        vec![
+            b"echo".to_vec(),
+            b"caf\xE9".to_vec(),
+            b"\xFCber".to_vec(),
+            b"\xE9".to_vec(),
+            b"bar".to_vec(),
+        ]
+    );
+
+    let mut utf16 = vec![0xFE, 0xFF];
+    utf16.extend(utf16be("echo \u{1f600} \u{e9}"));
+    let decoded = Decoded::detect(&utf16);
+    assert_eq!(
+        original_words(&decoded, &utf16),
+        vec![utf16be("echo"), utf16be("\u{1f600}"), utf16be("\u{e9}")]
+    );
+
+    // Positions at the end of the source map to its end
+    assert_eq!(decoded.original_byte(decoded.text().len()), utf16.len());
+}
+
+#[test]
+fn test_invalid_utf16_is_replaced() {
+    // An unpaired surrogate, followed by a truncated code unit
+    let bytes = [b'a', 0, 0x00, 0xD8, b'b', 0, b'c'];
+    let decoded = Decoded::new(&bytes[..], Encoding::Utf16Le);
+    assert_eq!(decoded.text(), "a\u{fffd}b\u{fffd}");
+    assert_eq!(decoded.original_byte("a\u{fffd}".len()), 4);
+    assert_eq!(decoded.original_byte(decoded.text().len()), bytes.len());
+}
+
+/// Decodes a toy encoding where `+` escapes the next byte as its uppercase form.
+struct Shouting;
+
+impl Transcoder for Shouting {
+    fn decode_char(&mut self, bytes: &[u8]) -> (char, usize) {
+        match bytes {
+            [b'+', b, ..] => (char::from(b.to_ascii_uppercase()), 2),
+            [b, ..] => (char::from(*b), 1),
+            [] => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_custom_transcoders() {
+    let bytes = b"+echo +foo";
+    let decoded = Decoded::new(bytes, Shouting);
+    assert_eq!(decoded.text(), "Echo Foo");
+    assert_eq!(
+        original_words(&decoded, bytes),
+        vec![b"+echo".to_vec(), b"+foo".to_vec()]
+    );
+
+    let mut shouting = Shouting;
+    assert_eq!(Decoded::detect_or(bytes, &mut shouting).text(), "Echo Foo");
+}
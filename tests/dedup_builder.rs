@@ -0,0 +1,180 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{ArcBuilder, DedupBuilder, DedupStats, RcBuilder, StringPool};
+use conch_parser::ast::{Command, ComplexWord, ListableCommand, PipeableCommand};
+use conch_parser::ast::{
+    Parameter, RedirectOrCmdWord, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use std::rc::Rc;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Returns the literal contents of every word of every simple command.
+fn literals(cmds: &[TopLevelCommand<Rc<String>>]) -> Vec<Rc<String>> {
+    let mut literals = Vec::new();
+    let mut push = |w: &Word<Rc<String>, _>| match *w {
+        Word::Simple(SimpleWord::Literal(ref s)) | Word::SingleQuoted(ref s) => {
+            literals.push(s.clone())
+        }
+        Word::DoubleQuoted(ref ws) => literals.extend(ws.iter().filter_map(|w| match *w {
+            SimpleWord::Literal(ref s) => Some(s.clone()),
+            _ => None,
+        })),
+        Word::Simple(_) | Word::Braces(_) => {}
+    };
+
+    for cmd in cmds {
+        let simple = match cmd.0 {
+            Command::List(ref list) => match list.first {
+                ListableCommand::Single(PipeableCommand::Simple(ref simple)) => simple,
+                _ => panic!("expected a simple command"),
+            },
+            _ => panic!("expected a simple command"),
+        };
+
+        for item in &simple.redirects_or_cmd_words {
+            if let RedirectOrCmdWord::CmdWord(TopLevelWord(ref word)) = *item {
+                match *word {
+                    ComplexWord::Single(ref w) => push(w),
+                    ComplexWord::Concat(ref ws) => ws.iter().for_each(&mut push),
+                }
+            }
+        }
+    }
+
+    literals
+}
+
+#[test]
+fn test_dedup_builder_matches_wrapped_builder() {
+    let src = "echo foo 'foo' \"foo$bar\" ${x:-foo} $(echo foo)\nfor i in a a; do echo a; done";
+
+    let mut builder = DedupBuilder::new(RcBuilder::new());
+    let deduped = parse_with(src, &mut builder);
+
+    assert_eq!(deduped, parse_with(src, RcBuilder::new()));
+    assert_eq!(
+        builder.stats(),
+        DedupStats {
+            // `echo` and `foo` in the command substitution are built first,
+            // the `foo` nested in `${x:-foo}` is not considered, but `bar` is
+            literals: 11,
+            shared: 7,
+            shared_bytes: 19,
+        }
+    );
+}
+
+#[test]
+fn test_dedup_builder_shares_identical_literals() {
+    let mut builder = DedupBuilder::new(RcBuilder::new());
+    let cmds = parse_with("cp file 'file' \"file\" other\ncp file dest", &mut builder);
+    let literals = literals(&cmds);
+
+    let file = &literals[1];
+    assert_eq!(**file, "file");
+    for shared in &[&literals[2], &literals[3], &literals[6]] {
+        assert!(Rc::ptr_eq(file, shared));
+    }
+    assert!(Rc::ptr_eq(&literals[0], &literals[5]));
+
+    // Only the copy retained by the builder, and those in the words
+    // (along with their clones collected above) remain
+    assert_eq!(Rc::strong_count(file), 1 + 4 + 4);
+    assert_eq!(Rc::strong_count(&literals[4]), 1 + 1 + 1);
+}
+
+#[test]
+fn test_dedup_builder_skips_short_literals() {
+    let mut builder = DedupBuilder::with_min_len(RcBuilder::new(), 4);
+    let cmds = parse_with("ls -l long; ls -l long", &mut builder);
+    let literals = literals(&cmds);
+
+    assert!(!Rc::ptr_eq(&literals[0], &literals[3]));
+    assert!(!Rc::ptr_eq(&literals[1], &literals[4]));
+    assert!(Rc::ptr_eq(&literals[2], &literals[5]));
+    assert_eq!(
+        builder.stats(),
+        DedupStats {
+            literals: 2,
+            shared: 1,
+            shared_bytes: 4,
+        }
+    );
+}
+
+#[test]
+fn test_dedup_builder_with_atomic_builder() {
+    let mut builder = DedupBuilder::new(ArcBuilder::new());
+    let cmds = parse_with("echo foo; echo foo", &mut builder);
+
+    assert_eq!(cmds, parse_with("echo foo; echo foo", ArcBuilder::new()));
+    assert_eq!(builder.stats().shared, 2);
+}
+
+#[test]
+fn test_dedup_builder_shares_variable_names() {
+    let mut builder = DedupBuilder::new(RcBuilder::new());
+    let cmds = parse_with("echo $name \"$name\" name $1", &mut builder);
+
+    let simple = match cmds[0].0 {
+        Command::List(ref list) => match list.first {
+            ListableCommand::Single(PipeableCommand::Simple(ref simple)) => simple,
+            _ => panic!("expected a simple command"),
+        },
+        _ => panic!("expected a simple command"),
+    };
+
+    let names: Vec<_> = simple.redirects_or_cmd_words[1..]
+        .iter()
+        .filter_map(|item| match *item {
+            RedirectOrCmdWord::CmdWord(TopLevelWord(ComplexWord::Single(ref w))) => match *w {
+                Word::Simple(SimpleWord::Param(Parameter::Var(ref v))) => Some(v.clone()),
+                Word::DoubleQuoted(ref ws) => match ws[..] {
+                    [SimpleWord::Param(Parameter::Var(ref v))] => Some(v.clone()),
+                    _ => None,
+                },
+                Word::Simple(SimpleWord::Literal(ref s)) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(names.len(), 3);
+    assert!(Rc::ptr_eq(&names[0], &names[1]));
+    assert!(Rc::ptr_eq(&names[0], &names[2]));
+    assert_eq!(builder.stats().literals, 4);
+    assert_eq!(builder.stats().shared, 2);
+}
+
+#[test]
+fn test_string_pool_is_shared_between_builders() {
+    let mut pool = StringPool::with_min_len(2);
+    let mut all = Vec::new();
+
+    for src in &["cp file dest", "cp file other", "mv dest other"] {
+        let mut builder = DedupBuilder::with_pool(RcBuilder::new(), pool);
+        all.extend(literals(&parse_with(src, &mut builder)));
+        pool = builder.into_pool();
+    }
+
+    assert_eq!(pool.len(), 5);
+    assert_eq!(
+        pool.stats(),
+        DedupStats {
+            literals: 9,
+            shared: 4,
+            shared_bytes: 2 + 4 + 4 + 5,
+        }
+    );
+
+    assert!(Rc::ptr_eq(&all[0], &all[3]));
+    assert!(Rc::ptr_eq(&all[1], &all[4]));
+    assert!(Rc::ptr_eq(&all[2], &all[7]));
+    assert!(Rc::ptr_eq(&all[5], &all[8]));
+
+    pool.clear();
+    assert!(pool.is_empty());
+    assert_eq!(pool.stats().shared, 4);
+}
@@ -0,0 +1,160 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{DefaultParser, Dialect, Extension};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn make_posix_parser(src: &str) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    let mut p = make_parser(src);
+    p.set_dialect(Dialect::Posix);
+    p
+}
+
+fn make_mksh_parser(src: &str) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    let mut p = make_parser(src);
+    p.set_dialect(Dialect::Mksh);
+    p
+}
+
+#[test]
+fn test_dialect_defaults_to_bash() {
+    let p = make_parser("foo");
+    assert_eq!(p.dialect(), Dialect::Bash);
+}
+
+#[test]
+fn test_dialect_posix_rejects_extensions() {
+    let cases = [
+        ("[[ -n foo ]]", Extension::DoubleBracket, src(0, 1, 1)),
+        ("((x = 1))", Extension::DoubleParen, src(0, 1, 1)),
+        (
+            "function foo { bar; }",
+            Extension::FunctionKeyword,
+            src(0, 1, 1),
+        ),
+        (
+            "select x in a b; do :; done",
+            Extension::Select,
+            src(0, 1, 1),
+        ),
+        ("foo |& bar", Extension::PipeAmp, src(4, 1, 5)),
+        ("cat <(foo)", Extension::ProcessRead, src(4, 1, 5)),
+        ("foo 2>&1 >(cat)", Extension::ProcessWrite, src(9, 1, 10)),
+        ("case x in a) b;& esac", Extension::CaseFallThrough, src(14, 1, 15)),
+        ("case x in a) b;;& esac", Extension::CaseContinue, src(14, 1, 15)),
+        ("foo && [[ a ]]", Extension::DoubleBracket, src(7, 1, 8)),
+        (
+            "if true; then function foo { :; }; fi",
+            Extension::FunctionKeyword,
+            src(14, 1, 15),
+        ),
+    ];
+
+    for &(s, ext, pos) in &cases {
+        assert_eq!(
+            Err(Extension(ext, pos)),
+            make_posix_parser(s).complete_command(),
+            "{}",
+            s
+        );
+    }
+}
+
+#[test]
+fn test_dialect_posix_error_names_dialect() {
+    let msg = make_posix_parser("  [[ foo ]]")
+        .complete_command()
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        msg,
+        "found `[[` on line 1:3, which is not part of the POSIX standard; \
+         use the `bash` dialect to enable it"
+    );
+}
+
+#[test]
+fn test_dialect_posix_accepts_portable_lookalikes() {
+    let cases = [
+        "[ -n foo ]",
+        "( (foo) )",
+        "foo() { bar; }",
+        "echo function select [[",
+        "foo | bar",
+        "function=1 select=2",
+    ];
+
+    for s in &cases {
+        assert!(make_posix_parser(s).complete_command().is_ok(), "{}", s);
+    }
+}
+
+#[test]
+fn test_dialect_bash_accepts_function_keyword() {
+    let mut p = make_parser("function foo { bar; }");
+    p.set_dialect(Dialect::Bash);
+    assert!(p.complete_command().unwrap().is_some());
+}
+
+#[test]
+fn test_dialect_mksh_accepts_shared_extensions() {
+    let cases = [
+        "[[ -n foo ]]",
+        "function foo { bar; }",
+        "select x in a b; do :; done",
+        "case x in a) b;& esac",
+        "echo $(( 16#ff + a[1] ))",
+        "foo &>out &>>log",
+    ];
+
+    for s in &cases {
+        assert!(make_mksh_parser(s).complete_command().is_ok(), "{}", s);
+    }
+}
+
+#[test]
+fn test_dialect_mksh_rejects_unsupported_extensions() {
+    let cases = [
+        ("foo |& bar", Extension::PipeAmp, src(4, 1, 5)),
+        ("cat <(foo)", Extension::ProcessRead, src(4, 1, 5)),
+        ("foo 2>&1 >(cat)", Extension::ProcessWrite, src(9, 1, 10)),
+        ("case x in a) b;;& esac", Extension::CaseContinue, src(14, 1, 15)),
+    ];
+
+    for &(s, ext, pos) in &cases {
+        assert_eq!(
+            Err(Extension(ext, pos)),
+            make_mksh_parser(s).complete_command(),
+            "{}",
+            s
+        );
+    }
+}
+
+#[test]
+fn test_dialect_posix_parses_redirect_both_as_background_job() {
+    let mut p = make_posix_parser("foo &>out");
+    let first = p.complete_command().unwrap().unwrap();
+    let second = p.complete_command().unwrap().unwrap();
+    assert_eq!(first.to_string(), "foo &");
+    assert_eq!(second.to_string(), ">out");
+    assert_eq!(p.complete_command(), Ok(None));
+
+    let mut p = make_parser("foo &>out");
+    assert_eq!(
+        p.complete_command().unwrap().unwrap().to_string(),
+        "foo &>out"
+    );
+    assert_eq!(p.complete_command(), Ok(None));
+}
+
+#[test]
+fn test_dialect_supports_extensions() {
+    assert!(Dialect::Bash.supports(Extension::RedirectBoth));
+    assert!(Dialect::Mksh.supports(Extension::RedirectBoth));
+    assert!(!Dialect::Posix.supports(Extension::RedirectBoth));
+    assert!(!Dialect::Mksh.supports(Extension::PipeAmp));
+    assert_eq!(Dialect::Mksh.to_string(), "mksh");
+}
@@ -0,0 +1,102 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    AnyNode, Builder, ComplexWordKind, DynBuilder, EmptyBuilder, ErasedBuilder, NodeMismatch,
+    SimpleWordKind, StringBuilder, WordKind,
+};
+use conch_parser::ast::{RedirectOrCmdWord, Span, TopLevelCommand, TopLevelWord};
+use conch_parser::parse::SourcePos;
+
+mod parse_support;
+use crate::parse_support::*;
+
+const SOURCE: &str = r#"
+# leading comment
+foo=bar baz qux >out 2>&1 <<EOF
+heredoc $body
+EOF
+! a | b && c || d &
+f() { echo "${x:-$(y)}" `z` ${#x} $(( 1 + 2 )); } 3<in
+for i in 1 2 3; do echo $i; done
+case $x in a|b) echo ab;; *) :;; esac
+if true; then :; elif false; then :; else :; fi
+while true; do break; done
+until (false); do :; done
+# trailing comment
+"#;
+
+fn literal(s: &str) -> ComplexWordKind<AnyNode> {
+    ComplexWordKind::Single(WordKind::Simple(SimpleWordKind::Literal(String::from(s))))
+}
+
+fn span() -> Span {
+    Span {
+        start: SourcePos::new(),
+        end: SourcePos::new(),
+    }
+}
+
+#[test]
+fn test_erased_builder_matches_direct_parse() {
+    let expected = parse(SOURCE);
+
+    let cmds = parse_with(SOURCE, ErasedBuilder::new(StringBuilder::new()).boxed())
+        .into_iter()
+        .map(|cmd| *cmd.downcast::<TopLevelCommand<String>>().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(cmds, expected);
+}
+
+/// Creates a fresh builder for parsing a script.
+type MakeBuilder = fn() -> Box<DynBuilder>;
+
+#[test]
+fn test_dyn_builders_chosen_at_runtime() {
+    // A registry of builders, e.g. as populated by plugins
+    let registry: Vec<(&str, MakeBuilder)> = vec![
+        ("strings", || {
+            ErasedBuilder::new(StringBuilder::new()).boxed()
+        }),
+        ("empty", || ErasedBuilder::new(EmptyBuilder::new()).boxed()),
+    ];
+
+    for (name, make_builder) in registry {
+        let cmds = parse_with(SOURCE, make_builder());
+        assert_eq!(cmds.len(), 8, "builder: {}", name);
+
+        let is_string_cmd = cmds[0].is::<TopLevelCommand<String>>();
+        let is_empty_cmd = cmds[0].is::<()>();
+        assert_eq!(is_string_cmd, name == "strings");
+        assert_eq!(is_empty_cmd, name == "empty");
+    }
+}
+
+#[test]
+fn test_erased_builder_rejects_foreign_nodes() {
+    let mut empty = ErasedBuilder::new(EmptyBuilder::new());
+    let mut strings = ErasedBuilder::new(StringBuilder::new());
+
+    let word = empty.word(literal("foo"), span()).unwrap();
+    let err = strings
+        .simple_command(vec![], vec![RedirectOrCmdWord::CmdWord(word)], span())
+        .unwrap_err();
+
+    let err = err.downcast::<NodeMismatch>().unwrap();
+    assert!(err.expected.contains("TopLevelWord"), "{}", err);
+    assert!(err.to_string().starts_with("expected a node of type `"));
+
+    // The wrapped builder remains usable afterwards
+    let word = strings.word(literal("foo"), span()).unwrap();
+    assert_eq!(
+        *word.downcast::<TopLevelWord<String>>().unwrap(),
+        crate::parse_support::word("foo")
+    );
+}
+
+#[test]
+fn test_erased_builder_accessors() {
+    let mut builder = ErasedBuilder::new(EmptyBuilder::new());
+    let _: &EmptyBuilder = builder.get_ref();
+    let _: &mut EmptyBuilder = builder.get_mut();
+    let _: EmptyBuilder = builder.into_inner();
+}
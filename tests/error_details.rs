@@ -0,0 +1,193 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::Span;
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{ErrorDetails, ErrorKind, Expected, ExpectedSet, OpenDelimiter};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn details_of(src: &str) -> ErrorDetails {
+    let mut p = make_parser(src);
+    let err = p.parse_all().unwrap_err();
+    p.error_details(&err)
+}
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Option<Span> {
+    Some(Span {
+        start: src(start.0, start.1, start.2),
+        end: src(end.0, end.1, end.2),
+    })
+}
+
+#[test]
+fn test_error_details_unexpected_token_in_command_group() {
+    let details = details_of("while true; do echo )");
+    assert_eq!(
+        details,
+        ErrorDetails {
+            kind: ErrorKind::Unexpected,
+            span: span((20, 1, 21), (21, 1, 22)),
+            expected: ExpectedSet(vec![Expected::Command, Expected::Keyword("done")]),
+            open: vec![OpenDelimiter {
+                open: "do",
+                pos: src(12, 1, 13),
+                close: "done",
+            }],
+        }
+    );
+    assert_eq!(details.expected.to_string(), "a command or `done`");
+
+    let details = details_of("if true; then echo; )");
+    assert_eq!(
+        details.expected.to_string(),
+        "a command, `elif`, `else`, or `fi`"
+    );
+
+    // Only the innermost group contributes its delimiters
+    let details = details_of("{ while true; do echo; ) }");
+    assert_eq!(details.expected.to_string(), "a command or `done`");
+}
+
+#[test]
+fn test_error_details_expected_tokens() {
+    let cases = [
+        ("for x in a b )", "`;` or newline"),
+        ("case x in a b) ;; esac", "`|` or `)`"),
+        ("f() echo", "a compound command"),
+        ("echo >", "a word"),
+        ("echo a |", "a command"),
+        ("{ }", "a command"),
+    ];
+
+    for &(src, expected) in &cases {
+        assert_eq!(details_of(src).expected.to_string(), expected, "{:?}", src);
+    }
+}
+
+#[test]
+fn test_error_details_derived_from_error() {
+    assert_eq!(
+        details_of("while true; do echo a"),
+        ErrorDetails {
+            kind: ErrorKind::IncompleteCmd,
+            span: span((21, 1, 22), (21, 1, 22)),
+            expected: ExpectedSet(vec![Expected::Keyword("done")]),
+            open: vec![OpenDelimiter {
+                open: "do",
+                pos: src(12, 1, 13),
+                close: "done",
+            }],
+        }
+    );
+    assert_eq!(
+        details_of("echo $(echo"),
+        ErrorDetails {
+            kind: ErrorKind::Unmatched,
+            span: span((6, 1, 7), (7, 1, 8)),
+            expected: ExpectedSet(vec![Expected::Token(Token::ParenClose)]),
+            open: vec![OpenDelimiter {
+                open: "(",
+                pos: src(6, 1, 7),
+                close: ")",
+            }],
+        }
+    );
+    assert_eq!(
+        details_of("for 1 in a; do :; done"),
+        ErrorDetails {
+            kind: ErrorKind::BadIdent,
+            span: span((4, 1, 5), (5, 1, 6)),
+            expected: ExpectedSet::default(),
+            open: vec![OpenDelimiter {
+                open: "for",
+                pos: src(0, 1, 1),
+                close: "do",
+            }],
+        }
+    );
+}
+
+#[test]
+fn test_unclosed_constructs_report_their_opening_location() {
+    let cases = vec![
+        (
+            "until true; do",
+            IncompleteCmd("do", src(12, 1, 13), "done", src(14, 1, 15)),
+        ),
+        (
+            "if true; then :; elif true; then",
+            IncompleteCmd("if", src(0, 1, 1), "fi", src(32, 1, 33)),
+        ),
+        (
+            "for",
+            IncompleteCmd("for", src(0, 1, 1), "do", src(3, 1, 4)),
+        ),
+        (
+            "case x in a|",
+            IncompleteCmd("case", src(0, 1, 1), "esac", src(12, 1, 13)),
+        ),
+        (
+            "until true",
+            IncompleteCmd("until", src(0, 1, 1), "do", src(10, 1, 11)),
+        ),
+        ("echo ${#", Unmatched(Token::CurlyOpen, src(6, 1, 7))),
+        ("echo $((1 +", Unmatched(Token::ParenOpen, src(6, 1, 7))),
+        (
+            "echo `if true`",
+            IncompleteCmd("if", src(6, 1, 7), "then", src(14, 1, 15)),
+        ),
+        // The closing backtick is found, the end of input is not inside the brace group
+        ("{ echo `echo |` }", UnexpectedEOF),
+    ];
+
+    for (s, err) in cases {
+        assert_eq!(make_parser(s).parse_all(), Err(err), "{:?}", s);
+    }
+}
+
+#[test]
+fn test_error_details_open_constructs() {
+    let open = |open, pos, close| OpenDelimiter { open, pos, close };
+
+    assert_eq!(
+        details_of("while true; do ( if true; then echo |").open,
+        vec![
+            open("do", src(12, 1, 13), "done"),
+            open("(", src(15, 1, 16), ")"),
+            open("if", src(17, 1, 18), "fi"),
+        ]
+    );
+    assert_eq!(
+        details_of("{ { echo; } ; echo `echo )`").open,
+        vec![open("{", src(0, 1, 1), "}"), open("`", src(19, 1, 20), "`"),]
+    );
+    assert_eq!(details_of("echo; )").open, vec![]);
+}
+
+#[test]
+fn test_error_span_and_kind() {
+    let err: conch_parser::parse::ParseError<()> = UnexpectedEOF;
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEOF);
+    assert_eq!(err.span(), None);
+
+    let err: conch_parser::parse::ParseError<()> =
+        Unexpected(Token::Name("foo".into()), src(4, 2, 1));
+    assert_eq!(err.kind(), ErrorKind::Unexpected);
+    assert_eq!(err.span(), span((4, 2, 1), (7, 2, 4)));
+}
+
+#[test]
+fn test_expected_set_display() {
+    assert_eq!(ExpectedSet::default().to_string(), "");
+    assert_eq!(ExpectedSet(vec![Expected::Name]).to_string(), "a name");
+    assert_eq!(
+        ExpectedSet(vec![
+            Expected::Keyword("done"),
+            Expected::Token(Token::Semi),
+            Expected::Token(Token::Newline),
+        ])
+        .to_string(),
+        "`done`, `;`, or newline"
+    );
+}
@@ -0,0 +1,232 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    EmptyBuilder, Event, EventLog, NodeId, ParameterSubstitutionKind, RedirectKind, ReplayError,
+    StringBuilder,
+};
+use conch_parser::ast::{Arithmetic, Parameter};
+
+mod parse_support;
+use crate::parse_support::*;
+
+const SOURCE: &str = r#"
+# leading comment
+foo=bar baz qux >out 2>&1 <<EOF
+heredoc $body
+EOF
+! a | b && c || d &
+f() { echo "${x:-$(y)}" `z`; } 3<in
+for i in 1 2 3; do echo $i; done
+case $x in a|b) echo ab;; *) :;; esac
+if true; then :; elif false; then :; else :; fi
+while true; do break; done
+until (false); do :; done
+# trailing comment
+"#;
+
+fn record(src: &str) -> (EventLog, Vec<NodeId>) {
+    let mut log = EventLog::new();
+    let ids = parse_with(src, &mut log);
+
+    (log, ids)
+}
+
+#[test]
+fn test_event_log_replay_matches_direct_parse() {
+    let (log, ids) = record(SOURCE);
+
+    let expected = parse(SOURCE);
+    let replayed = log.replay(&mut StringBuilder::new()).unwrap();
+
+    assert_eq!(ids.len(), expected.len());
+    assert_eq!(replayed, expected);
+}
+
+#[test]
+fn test_event_log_can_be_replayed_multiple_times() {
+    let (log, ids) = record(SOURCE);
+
+    let first = log.replay(&mut StringBuilder::new()).unwrap();
+    let second = log.replay(&mut StringBuilder::new()).unwrap();
+    assert_eq!(first, second);
+
+    let empty = log.replay(&mut EmptyBuilder::new()).unwrap();
+    assert_eq!(empty.len(), ids.len());
+}
+
+#[test]
+fn test_event_log_node_ids_refer_to_events() {
+    let (log, ids) = record("foo");
+
+    assert_eq!(ids, vec![NodeId(log.events().len() - 1)]);
+    match log.events()[0] {
+        Event::Word { .. } => {}
+        ref e => panic!("expected a word event, found {:?}", e),
+    }
+    match log.events()[ids[0].0] {
+        Event::CompleteCommand { .. } => {}
+        ref e => panic!("expected a complete command event, found {:?}", e),
+    }
+}
+
+#[test]
+fn test_event_log_from_events() {
+    let (log, _) = record("foo; bar");
+    let copy = EventLog::from(log.clone().into_events());
+
+    assert_eq!(copy, log);
+    assert_eq!(
+        copy.replay(&mut StringBuilder::new()).unwrap(),
+        vec![cmd("foo"), cmd("bar")]
+    );
+}
+
+#[test]
+fn test_event_log_replay_rejects_invalid_log() {
+    let log = EventLog::from(vec![Event::CompoundCommandIntoPipeable { cmd: NodeId(5) }]);
+    assert_eq!(
+        log.replay(&mut StringBuilder::new()),
+        Err(ReplayError::InvalidNode(NodeId(5)))
+    );
+
+    // Nodes can only be consumed once, and only by events expecting their type
+    let (log, _) = record("foo");
+    let mut events = log.into_events();
+    let span = match events[0] {
+        Event::Word { span, .. } => span,
+        ref e => panic!("expected a word event, found {:?}", e),
+    };
+
+    let word = events[0].clone();
+    events.push(Event::Redirect {
+        kind: RedirectKind::Write(None, NodeId(0)),
+        span,
+    });
+    assert_eq!(
+        EventLog::from(events).replay(&mut StringBuilder::new()),
+        Err(ReplayError::InvalidNode(NodeId(0)))
+    );
+
+    let log = EventLog::from(vec![
+        word,
+        Event::CompoundCommandIntoPipeable { cmd: NodeId(0) },
+    ]);
+    assert_eq!(
+        log.replay(&mut StringBuilder::new()),
+        Err(ReplayError::InvalidNode(NodeId(0)))
+    );
+}
+
+#[test]
+fn test_event_log_replay_rejects_missing_substitutions() {
+    let (log, _) = record("echo ${x:-y} $((1))");
+    let events = log.into_events();
+
+    // Drop the events which built the substitutions of each word
+    for &(dropped, word) in &[(1, NodeId(1)), (3, NodeId(3))] {
+        let mut events = events.clone();
+        match events.remove(dropped) {
+            Event::ParameterSubstitution { .. } | Event::Arithmetic { .. } => {}
+            ref e => panic!("expected a substitution event, found {:?}", e),
+        }
+
+        assert_eq!(
+            EventLog::from(events).replay(&mut StringBuilder::new()),
+            Err(ReplayError::MissingSubstitution(word))
+        );
+    }
+}
+
+#[test]
+fn test_event_log_replays_arithmetic_returned_by_builder() {
+    let (log, _) = record("echo $((1))\"$(echo $((2)) ${x:-$((3))})\"$((4))");
+
+    // Rewrite the recorded expressions, which should end up in the replayed
+    // words in place of the ones recorded as part of the words themselves
+    let events = log
+        .into_events()
+        .into_iter()
+        .map(|event| match event {
+            Event::Arithmetic {
+                expr: Some(Arithmetic::Literal(n)),
+                span,
+            } => Event::Arithmetic {
+                expr: Some(Arithmetic::Literal(n * 10)),
+                span,
+            },
+            event => event,
+        })
+        .collect::<Vec<_>>();
+
+    let expected = parse("echo $((10))\"$(echo $((20)) ${x:-$((30))})\"$((40))");
+    let replayed = EventLog::from(events)
+        .replay(&mut StringBuilder::new())
+        .unwrap();
+
+    assert_eq!(replayed, expected);
+}
+
+#[test]
+fn test_event_log_records_parameter_substitutions() {
+    let (log, _) = record("echo ${x:-${y:+b}} \"${#z}\" $y ${y} $((1))");
+
+    let substs = log
+        .events()
+        .iter()
+        .filter_map(|event| match *event {
+            Event::ParameterSubstitution { ref kind, span } => {
+                Some((kind.clone(), span.start.byte, span.end.byte))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Nested substitutions are recorded first, plain `${y}` and `$((1))` are not recorded
+    assert_eq!(substs.len(), 3);
+    assert!(matches!(
+        substs[0].0,
+        ParameterSubstitutionKind::Alternative(..)
+    ));
+    assert_eq!((substs[0].1, substs[0].2), (10, 17));
+    assert!(matches!(
+        substs[1].0,
+        ParameterSubstitutionKind::Default(..)
+    ));
+    assert_eq!((substs[1].1, substs[1].2), (5, 18));
+    assert!(matches!(substs[2].0, ParameterSubstitutionKind::Len(_)));
+    assert_eq!((substs[2].1, substs[2].2), (20, 25));
+}
+
+#[test]
+fn test_event_log_replays_substitutions_returned_by_builder() {
+    let (log, _) =
+        record("echo ${x:-$((1))${y:-a}}\"$(echo ${z:-b})${#w}\"${v:-c}$((2)) ${u:-${t:-d}}");
+
+    // Rewrite the recorded parameters, which should end up in the replayed
+    // words in place of the ones recorded as part of the words themselves
+    let events = log
+        .into_events()
+        .into_iter()
+        .map(|event| match event {
+            Event::ParameterSubstitution {
+                kind: ParameterSubstitutionKind::Default(colon, Parameter::Var(name), word),
+                span,
+            } => Event::ParameterSubstitution {
+                kind: ParameterSubstitutionKind::Default(
+                    colon,
+                    Parameter::Var(name.to_uppercase()),
+                    word,
+                ),
+                span,
+            },
+            event => event,
+        })
+        .collect::<Vec<_>>();
+
+    let expected =
+        parse("echo ${X:-$((1))${Y:-a}}\"$(echo ${Z:-b})${#w}\"${V:-c}$((2)) ${U:-${T:-d}}");
+    let replayed = EventLog::from(events)
+        .replay(&mut StringBuilder::new())
+        .unwrap();
+
+    assert_eq!(replayed, expected);
+}
@@ -0,0 +1,122 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::Span;
+use conch_parser::folding::{keyword_groups, keyword_groups_of, KeywordGroup};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, Keyword, SourcePos};
+
+fn words(group: &KeywordGroup) -> Vec<&str> {
+    group.keywords.iter().map(|kw| &*kw.word).collect()
+}
+
+fn all_words(src: &str) -> Vec<Vec<String>> {
+    keyword_groups_of(src)
+        .iter()
+        .map(|g| words(g).into_iter().map(String::from).collect())
+        .collect()
+}
+
+fn sections<'a>(src: &'a str, group: &KeywordGroup) -> Vec<&'a str> {
+    group
+        .sections()
+        .into_iter()
+        .map(|span| &src[span.start.byte..span.end.byte])
+        .collect()
+}
+
+#[test]
+fn test_if_branches_are_grouped() {
+    let src = "if a; then b; elif c; then d; else e; fi";
+    let groups = keyword_groups_of(src);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(
+        words(&groups[0]),
+        ["if", "then", "elif", "then", "else", "fi"]
+    );
+    assert_eq!(groups[0].opener(), "if");
+    assert!(groups[0].is_closed());
+    assert_eq!(groups[0].span().start.byte, 0);
+    assert_eq!(groups[0].span().end.byte, src.len());
+    assert_eq!(
+        sections(src, &groups[0]),
+        ["if a; ", "then b; ", "elif c; ", "then d; ", "else e; "]
+    );
+}
+
+#[test]
+fn test_loops_case_and_brace_groups_are_grouped() {
+    assert_eq!(
+        all_words("while a; do b; done; until a; do b; done"),
+        vec![vec!["while", "do", "done"], vec!["until", "do", "done"]]
+    );
+    assert_eq!(
+        all_words("for x in a b; do c; done; for y; do d; done"),
+        vec![vec!["for", "in", "do", "done"], vec!["for", "do", "done"]]
+    );
+    assert_eq!(
+        all_words("case x in a) b;; esac"),
+        vec![vec!["case", "in", "esac"]]
+    );
+    assert_eq!(all_words("foo() { bar; }"), vec![vec!["{", "}"]]);
+}
+
+#[test]
+fn test_nested_commands_have_their_own_groups() {
+    let src = "if a; then\n  for x in y; do\n    case $x in *) echo done fi;; esac\n  done\nelse\n  b\nfi";
+    assert_eq!(
+        all_words(src),
+        vec![
+            vec!["if", "then", "else", "fi"],
+            vec!["for", "in", "do", "done"],
+            vec!["case", "in", "esac"],
+        ]
+    );
+}
+
+#[test]
+fn test_other_reserved_words_are_ignored() {
+    assert_eq!(
+        all_words("! [[ a ]] && function f { g; }"),
+        vec![vec!["{", "}"]]
+    );
+}
+
+#[test]
+fn test_incomplete_commands_remain_open() {
+    let mut p = DefaultParser::new(Lexer::new("if a; then while b; do c".chars()));
+    p.set_track_keywords(true);
+    assert!(p.complete_command().is_err());
+
+    let groups = keyword_groups(&p.take_keywords());
+    assert_eq!(groups.len(), 2);
+    assert_eq!(words(&groups[0]), ["if", "then"]);
+    assert_eq!(words(&groups[1]), ["while", "do"]);
+    assert!(!groups[0].is_closed());
+    assert!(!groups[1].is_closed());
+}
+
+#[test]
+fn test_unmatched_closers_are_ignored() {
+    let kw = |word: &str, byte: usize| Keyword {
+        word: String::from(word),
+        span: Span {
+            start: SourcePos::from_byte(byte),
+            end: SourcePos::from_byte(byte + word.len()),
+        },
+    };
+
+    let keywords = vec![
+        kw("done", 0),
+        kw("if", 5),
+        kw("then", 10),
+        kw("while", 15),
+        kw("do", 21),
+        kw("fi", 30),
+    ];
+
+    let groups = keyword_groups(&keywords);
+    assert_eq!(groups.len(), 2);
+    assert_eq!(words(&groups[0]), ["if", "then", "fi"]);
+    assert_eq!(words(&groups[1]), ["while", "do"]);
+    assert!(groups[0].is_closed());
+    assert!(!groups[1].is_closed());
+}
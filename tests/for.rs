@@ -1,5 +1,6 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
+use conch_parser::ast::*;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -46,6 +47,31 @@ fn test_for_command_valid_with_words() {
     );
 }
 
+#[test]
+fn test_for_command_words_support_full_word_grammar() {
+    let mut p = make_parser("for i in \"$@\" a`b`c 'q r' ${x#y}; do :; done");
+    let (_, words, _) = p.for_command().unwrap().words.unwrap();
+
+    assert_eq!(
+        words,
+        vec![
+            TopLevelWord(ComplexWord::Single(Word::DoubleQuoted(vec![
+                SimpleWord::Param(Parameter::At)
+            ]))),
+            TopLevelWord(ComplexWord::Concat(vec![
+                lit("a"),
+                subst(ParameterSubstitution::Command(vec![cmd("b")])),
+                lit("c"),
+            ])),
+            single_quoted("q r"),
+            word_subst(ParameterSubstitution::RemoveSmallestPrefix(
+                Parameter::Var("x".into()),
+                Some(word("y")),
+            )),
+        ]
+    );
+}
+
 #[test]
 fn test_for_command_valid_without_words() {
     let mut p = make_parser(
@@ -73,6 +99,56 @@ fn test_for_command_valid_without_words() {
     );
 }
 
+#[test]
+fn test_for_command_distinguishes_omitted_and_empty_word_lists() {
+    let omitted = [
+        "for var do body; done",
+        "for var; do body; done",
+        "for var\n\ndo body; done",
+    ];
+    for src in &omitted {
+        let fragments = make_parser(src).for_command().unwrap();
+        assert_eq!(fragments.words, None, "{}", src);
+    }
+
+    let empty = [
+        "for var in; do body; done",
+        "for var in\ndo body; done",
+        "for var\nin ;\ndo body; done",
+    ];
+    for src in &empty {
+        let fragments = make_parser(src).for_command().unwrap();
+        let (_, words, _) = fragments.words.expect(src);
+        assert!(words.is_empty(), "{}", src);
+    }
+
+    let body = vec![cmd("body")];
+    assert_eq!(
+        make_parser("for var do body; done").compound_command(),
+        Ok(CompoundCommand {
+            kind: CompoundCommandKind::For {
+                var: "var".into(),
+                words: None,
+                body: body.clone(),
+            },
+            io: vec![],
+            io_span: None,
+        })
+    );
+    assert_eq!(
+        make_parser("for var in; do body; done").compound_command(),
+        Ok(CompoundCommand {
+            kind: CompoundCommandKind::For {
+                var: "var".into(),
+                words: Some(vec![]),
+                body,
+            },
+            io: vec![],
+            io_span: None,
+        })
+    );
+}
+
 #[test]
 fn test_for_command_valid_separators() {
     let cases = vec![
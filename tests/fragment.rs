@@ -0,0 +1,116 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::*;
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{parse_assignment, parse_redirect, parse_word, Dialect};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_parse_word() {
+    assert_eq!(parse_word("foo", Dialect::Posix), Ok(word("foo")));
+    assert_eq!(
+        parse_word("  'foo'\t", Dialect::Posix),
+        Ok(single_quoted("foo"))
+    );
+    assert_eq!(
+        parse_word("foo bar", Dialect::Posix),
+        Err(Unexpected(Token::Name("bar".into()), src(4, 1, 5)))
+    );
+    assert_eq!(
+        parse_word("foo;", Dialect::Posix),
+        Err(Unexpected(Token::Semi, src(3, 1, 4)))
+    );
+    assert_eq!(
+        parse_word("foo # comment\n", Dialect::Posix),
+        Ok(word("foo"))
+    );
+    assert_eq!(parse_word("", Dialect::Posix), Err(UnexpectedEOF));
+    assert_eq!(
+        parse_word("$(foo", Dialect::Posix),
+        Err(Unmatched(Token::ParenOpen, src(1, 1, 2)))
+    );
+}
+
+#[test]
+fn test_parse_redirect() {
+    assert_eq!(
+        parse_redirect("2>> log", Dialect::Posix),
+        Ok(Redirect::Append(Some(2), word("log")))
+    );
+    assert_eq!(
+        parse_redirect("<&-", Dialect::Posix),
+        Ok(Redirect::DupRead(None, word("-")))
+    );
+    assert_eq!(
+        parse_redirect("&>log", Dialect::Bash),
+        Ok(Redirect::WriteBoth(word("log")))
+    );
+    assert!(parse_redirect("&>log", Dialect::Posix).is_err());
+
+    // Words which are not redirections are reported from their start
+    assert_eq!(
+        parse_redirect("2 >log", Dialect::Posix),
+        Err(Unexpected(Token::Literal("2".into()), src(0, 1, 1)))
+    );
+    assert_eq!(
+        parse_redirect(">log extra", Dialect::Posix),
+        Err(Unexpected(Token::Name("extra".into()), src(5, 1, 6)))
+    );
+}
+
+#[test]
+fn test_parse_redirect_heredoc_followed_by_body() {
+    assert_eq!(
+        parse_redirect("<<EOF\nbody\nEOF", Dialect::Posix),
+        Ok(Redirect::Heredoc(
+            None,
+            word("body\n"),
+            HeredocInfo {
+                delimiter: "EOF".into(),
+                quoted: false,
+                strip_tabs: false,
+                lines: vec!["body".into()],
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_parse_assignment() {
+    assert_eq!(
+        parse_assignment("FOO=bar", Dialect::Posix),
+        Ok(("FOO".to_owned(), Some(word("bar"))))
+    );
+    assert_eq!(
+        parse_assignment("FOO=\"bar\"", Dialect::Posix),
+        Ok(("FOO".to_owned(), Some(double_quoted("bar"))))
+    );
+    assert_eq!(
+        parse_assignment("FOO=", Dialect::Posix),
+        Ok(("FOO".to_owned(), None))
+    );
+    assert_eq!(
+        parse_assignment("FOO= bar", Dialect::Posix),
+        Err(Unexpected(Token::Name("bar".into()), src(5, 1, 6)))
+    );
+    assert_eq!(
+        parse_assignment("foo", Dialect::Posix),
+        Err(Unexpected(Token::Name("foo".into()), src(0, 1, 1)))
+    );
+}
+
+#[test]
+fn test_parser_assignment_consumes_nothing_if_not_found() {
+    let mut p = make_parser("FOO =bar");
+    assert_eq!(p.assignment(), Ok(None));
+    assert_eq!(p.word(), Ok(Some(word("FOO"))));
+
+    let mut p = make_parser("FOO=bar baz");
+    assert_eq!(
+        p.assignment(),
+        Ok(Some(("FOO".to_owned(), Some(word("bar")))))
+    );
+    assert_eq!(p.word(), Ok(Some(word("baz"))));
+}
@@ -1,8 +1,11 @@
 #![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{Event, EventLog, FunctionSyntax};
 use conch_parser::ast::CompoundCommandKind::*;
 use conch_parser::ast::PipeableCommand::*;
 use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
 use conch_parser::parse::ParseError::*;
+use conch_parser::parse::Parser;
 use conch_parser::token::Token;
 
 use std::rc::Rc;
@@ -12,123 +15,75 @@ use crate::parse_support::*;
 
 #[test]
 fn test_function_declaration_valid() {
-    let correct = FunctionDef(
-        String::from("foo"),
-        Rc::new(CompoundCommand {
-            kind: Brace(vec![cmd_args("echo", &["body"])]),
-            io: vec![],
-        }),
-    );
+    let cases = [
+        (
+            "function foo()      { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo ()     { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo (    ) { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo(    )  { echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo        { echo body; }",
+            FunctionSyntax::Keyword,
+        ),
+        ("foo()               { echo body; }", FunctionSyntax::Posix),
+        ("foo ()              { echo body; }", FunctionSyntax::Posix),
+        ("foo (    )          { echo body; }", FunctionSyntax::Posix),
+        ("foo(    )           { echo body; }", FunctionSyntax::Posix),
+        (
+            "function foo()     \n{ echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo ()    \n{ echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo (    )\n{ echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo(    ) \n{ echo body; }",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo       \n{ echo body; }",
+            FunctionSyntax::Keyword,
+        ),
+        ("foo()              \n{ echo body; }", FunctionSyntax::Posix),
+        ("foo ()             \n{ echo body; }", FunctionSyntax::Posix),
+        ("foo (    )         \n{ echo body; }", FunctionSyntax::Posix),
+        ("foo(    )          \n{ echo body; }", FunctionSyntax::Posix),
+    ];
 
-    assert_eq!(
-        correct,
-        make_parser("function foo()      { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo ()     { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo (    ) { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo(    )  { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo        { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo()               { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo ()              { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo (    )          { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo(    )           { echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
+    for &(src, syntax) in &cases {
+        let correct = FunctionDef(
+            String::from("foo"),
+            syntax,
+            Rc::new(CompoundCommand {
+                kind: Brace(vec![cmd_args("echo", &["body"])]),
+                io: vec![],
+                io_span: None,
+            }),
+        );
 
-    assert_eq!(
-        correct,
-        make_parser("function foo()     \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo ()    \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo (    )\n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo(    ) \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo       \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo()              \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo ()             \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo (    )         \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("foo(    )          \n{ echo body; }")
-            .function_declaration()
-            .unwrap()
-    );
+        assert_eq!(
+            correct,
+            make_parser(src).function_declaration().unwrap(),
+            "src: {:?}",
+            src
+        );
+    }
 }
 
 #[test]
@@ -172,35 +127,91 @@ fn test_function_declaration_valid_body_need_not_be_a_compound_command() {
 
 #[test]
 fn test_function_declaration_parens_can_be_subshell_if_function_keyword_present() {
+    let cases = [
+        ("function foo (echo subshell)", FunctionSyntax::Keyword),
+        (
+            "function foo() (echo subshell)",
+            FunctionSyntax::KeywordParens,
+        ),
+        (
+            "function foo () (echo subshell)",
+            FunctionSyntax::KeywordParens,
+        ),
+        ("function foo\n(echo subshell)", FunctionSyntax::Keyword),
+    ];
+
+    for &(src, syntax) in &cases {
+        let correct = FunctionDef(
+            String::from("foo"),
+            syntax,
+            Rc::new(CompoundCommand {
+                kind: Subshell(vec![cmd_args("echo", &["subshell"])]),
+                io: vec![],
+                io_span: None,
+            }),
+        );
+
+        assert_eq!(
+            correct,
+            make_parser(src).function_declaration().unwrap(),
+            "src: {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_function_declaration_captures_redirections_after_body() {
     let correct = FunctionDef(
         String::from("foo"),
+        FunctionSyntax::Keyword,
         Rc::new(CompoundCommand {
             kind: Subshell(vec![cmd_args("echo", &["subshell"])]),
-            io: vec![],
+            io: vec![
+                Redirect::Write(None, word("out")),
+                Redirect::DupWrite(Some(2), word("1")),
+            ],
+            io_span: Some(Span {
+                start: src(29, 1, 30),
+                end: src(38, 1, 39),
+            }),
         }),
     );
 
     assert_eq!(
         correct,
-        make_parser("function foo (echo subshell)")
+        make_parser("function foo (echo subshell) >out 2>&1")
             .function_declaration()
             .unwrap()
     );
-    assert_eq!(
-        correct,
-        make_parser("function foo() (echo subshell)")
-            .function_declaration()
-            .unwrap()
-    );
-    assert_eq!(
-        correct,
-        make_parser("function foo () (echo subshell)")
-            .function_declaration()
-            .unwrap()
+
+    let correct = FunctionDef(
+        String::from("foo"),
+        FunctionSyntax::Posix,
+        Rc::new(CompoundCommand {
+            kind: Brace(vec![cmd("body")]),
+            io: vec![
+                Redirect::Read(None, word("in")),
+                Redirect::Heredoc(
+                    None,
+                    word("doc\n"),
+                    HeredocInfo {
+                        delimiter: String::from("EOF"),
+                        quoted: false,
+                        strip_tabs: false,
+                        lines: vec![String::from("doc")],
+                    },
+                ),
+            ],
+            io_span: Some(Span {
+                start: src(16, 1, 17),
+                end: src(25, 1, 26),
+            }),
+        }),
     );
     assert_eq!(
         correct,
-        make_parser("function foo\n(echo subshell)")
+        make_parser("foo() { body; } <in <<EOF\ndoc\nEOF\n")
             .function_declaration()
             .unwrap()
     );
@@ -440,3 +451,31 @@ fn test_function_declaration_should_recognize_literals_and_names_for_fn_keyword(
         p.function_declaration().unwrap();
     }
 }
+
+#[test]
+fn test_function_declaration_reports_syntax_used() {
+    let cases = vec![
+        ("foo() { echo; }", FunctionSyntax::Posix),
+        ("foo ( ) { echo; }", FunctionSyntax::Posix),
+        ("foo() (echo)", FunctionSyntax::Posix),
+        ("function foo { echo; }", FunctionSyntax::Keyword),
+        ("function foo\n{ echo; }", FunctionSyntax::Keyword),
+        ("function foo (echo)", FunctionSyntax::Keyword),
+        ("function foo() { echo; }", FunctionSyntax::KeywordParens),
+        ("function foo ( ) { echo; }", FunctionSyntax::KeywordParens),
+        ("function foo() (echo)", FunctionSyntax::KeywordParens),
+    ];
+
+    for (src, expected) in cases {
+        let mut log = EventLog::new();
+        Parser::with_builder(Lexer::new(src.chars()), &mut log)
+            .function_declaration()
+            .unwrap();
+
+        let syntax = log.events().iter().find_map(|event| match event {
+            Event::FunctionDeclaration { syntax, .. } => Some(*syntax),
+            _ => None,
+        });
+        assert_eq!(syntax, Some(expected), "{}", src);
+    }
+}
@@ -9,19 +9,37 @@ use conch_parser::token::Token;
 mod parse_support;
 use crate::parse_support::*;
 
-fn cat_heredoc(fd: Option<u16>, body: &'static str) -> TopLevelCommand<String> {
+fn heredoc_info(delimiter: &str, quoted: bool, strip_tabs: bool, lines: &[&str]) -> HeredocInfo {
+    HeredocInfo {
+        delimiter: String::from(delimiter),
+        quoted,
+        strip_tabs,
+        lines: lines.iter().map(|&line| String::from(line)).collect(),
+    }
+}
+
+/// The info of an unquoted `<<EOF` heredoc whose body is `here\n`.
+fn here_info() -> HeredocInfo {
+    heredoc_info("EOF", false, false, &["here"])
+}
+
+fn cat_heredoc(fd: Option<u16>, body: &'static str, info: HeredocInfo) -> TopLevelCommand<String> {
     cmd_from_simple(SimpleCommand {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(fd, word(body))),
+            RedirectOrCmdWord::Redirect(Heredoc(fd, word(body), info)),
         ],
     })
 }
 
 #[test]
 fn test_heredoc_valid() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let correct = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof", false, false, &["hello"]),
+    ));
     assert_eq!(
         correct,
         make_parser("cat <<eof\nhello\neof\n")
@@ -32,7 +50,11 @@ fn test_heredoc_valid() {
 
 #[test]
 fn test_heredoc_valid_eof_after_delimiter_allowed() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let correct = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof", false, false, &["hello"]),
+    ));
     assert_eq!(
         correct,
         make_parser("cat <<eof\nhello\neof")
@@ -43,7 +65,11 @@ fn test_heredoc_valid_eof_after_delimiter_allowed() {
 
 #[test]
 fn test_heredoc_valid_with_empty_body() {
-    let correct = Some(cat_heredoc(None, ""));
+    let correct = Some(cat_heredoc(
+        None,
+        "",
+        heredoc_info("eof", false, false, &[]),
+    ));
     assert_eq!(
         correct,
         make_parser("cat <<eof\neof").complete_command().unwrap()
@@ -60,7 +86,11 @@ fn test_heredoc_valid_with_empty_body() {
 
 #[test]
 fn test_heredoc_valid_eof_acceptable_as_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let correct = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof", false, false, &["hello"]),
+    ));
     assert_eq!(
         correct,
         make_parser("cat <<eof\nhello\neof")
@@ -72,8 +102,16 @@ fn test_heredoc_valid_eof_acceptable_as_delimeter() {
 #[test]
 fn test_heredoc_valid_does_not_lose_tokens_up_to_next_newline() {
     let mut p = make_parser("cat <<eof1; cat 3<<eof2\nhello\neof1\nworld\neof2");
-    let first = Some(cat_heredoc(None, "hello\n"));
-    let second = Some(cat_heredoc(Some(3), "world\n"));
+    let first = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof1", false, false, &["hello"]),
+    ));
+    let second = Some(cat_heredoc(
+        Some(3),
+        "world\n",
+        heredoc_info("eof2", false, false, &["world"]),
+    ));
 
     assert_eq!(first, p.complete_command().unwrap());
     assert_eq!(second, p.complete_command().unwrap());
@@ -82,8 +120,16 @@ fn test_heredoc_valid_does_not_lose_tokens_up_to_next_newline() {
 #[test]
 fn test_heredoc_valid_space_before_delimeter_allowed() {
     let mut p = make_parser("cat <<   eof1; cat 3<<- eof2\nhello\neof1\nworld\neof2");
-    let first = Some(cat_heredoc(None, "hello\n"));
-    let second = Some(cat_heredoc(Some(3), "world\n"));
+    let first = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof1", false, false, &["hello"]),
+    ));
+    let second = Some(cat_heredoc(
+        Some(3),
+        "world\n",
+        heredoc_info("eof2", false, true, &["world"]),
+    ));
 
     assert_eq!(first, p.complete_command().unwrap());
     assert_eq!(second, p.complete_command().unwrap());
@@ -105,11 +151,15 @@ fn test_heredoc_valid_unquoted_delimeter_should_expand_body() {
                     subst(ParameterSubstitution::Command(vec![cmd("foo")])),
                     lit("\n"),
                 ])),
+                heredoc_info("eof", false, false, &["$$ ${#!} `foo`"]),
             )),
         ],
     }));
 
-    let literal = Some(cat_heredoc(None, "$$ ${#!} `foo`\n"));
+    let literal = |delimiter| {
+        let info = heredoc_info(delimiter, true, false, &["$$ ${#!} `foo`"]);
+        Some(cat_heredoc(None, "$$ ${#!} `foo`\n", info))
+    };
 
     assert_eq!(
         expanded,
@@ -118,25 +168,25 @@ fn test_heredoc_valid_unquoted_delimeter_should_expand_body() {
             .unwrap()
     );
     assert_eq!(
-        literal,
+        literal("eof"),
         make_parser("cat <<'eof'\n$$ ${#!} `foo`\neof")
             .complete_command()
             .unwrap()
     );
     assert_eq!(
-        literal,
+        literal("`eof`"),
         make_parser("cat <<`eof`\n$$ ${#!} `foo`\n`eof`")
             .complete_command()
             .unwrap()
     );
     assert_eq!(
-        literal,
+        literal("eof"),
         make_parser("cat <<\"eof\"\n$$ ${#!} `foo`\neof")
             .complete_command()
             .unwrap()
     );
     assert_eq!(
-        literal,
+        literal("eof"),
         make_parser("cat <<e\\of\n$$ ${#!} `foo`\neof")
             .complete_command()
             .unwrap()
@@ -147,8 +197,16 @@ fn test_heredoc_valid_unquoted_delimeter_should_expand_body() {
 fn test_heredoc_valid_leading_tab_removal_works() {
     let mut p =
         make_parser("cat <<-eof1; cat 3<<-eof2\n\t\thello\n\teof1\n\t\t \t\nworld\n\t\teof2");
-    let first = Some(cat_heredoc(None, "hello\n"));
-    let second = Some(cat_heredoc(Some(3), " \t\nworld\n"));
+    let first = Some(cat_heredoc(
+        None,
+        "hello\n",
+        heredoc_info("eof1", false, true, &["\t\thello"]),
+    ));
+    let second = Some(cat_heredoc(
+        Some(3),
+        " \t\nworld\n",
+        heredoc_info("eof2", false, true, &["\t\t \t", "world"]),
+    ));
 
     assert_eq!(first, p.complete_command().unwrap());
     assert_eq!(second, p.complete_command().unwrap());
@@ -157,13 +215,19 @@ fn test_heredoc_valid_leading_tab_removal_works() {
 #[test]
 fn test_heredoc_valid_leading_tab_removal_works_if_dash_immediately_after_dless() {
     let mut p = make_parser("cat 3<< -eof\n\t\t \t\nworld\n\t\teof\n\t\t-eof\n-eof");
-    let correct = Some(cat_heredoc(Some(3), "\t\t \t\nworld\n\t\teof\n\t\t-eof\n"));
+    let lines = ["\t\t \t", "world", "\t\teof", "\t\t-eof"];
+    let correct = Some(cat_heredoc(
+        Some(3),
+        "\t\t \t\nworld\n\t\teof\n\t\t-eof\n",
+        heredoc_info("-eof", false, false, &lines),
+    ));
     assert_eq!(correct, p.complete_command().unwrap());
 }
 
 #[test]
 fn test_heredoc_valid_unquoted_backslashes_in_delimeter_disappear() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("e ff", true, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<e\\ f\\f\nhello\ne ff")
@@ -174,7 +238,8 @@ fn test_heredoc_valid_unquoted_backslashes_in_delimeter_disappear() {
 
 #[test]
 fn test_heredoc_valid_balanced_single_quotes_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("eof", true, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<e'o'f\nhello\neof")
@@ -185,7 +250,8 @@ fn test_heredoc_valid_balanced_single_quotes_in_delimeter() {
 
 #[test]
 fn test_heredoc_valid_balanced_double_quotes_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("e\\o${foo}f", true, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<e\"\\o${foo}\"f\nhello\ne\\o${foo}f")
@@ -196,7 +262,8 @@ fn test_heredoc_valid_balanced_double_quotes_in_delimeter() {
 
 #[test]
 fn test_heredoc_valid_balanced_backticks_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("e`\\o$`\\${f}`", true, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<e`\\o\\$\\`\\\\${f}`\nhello\ne`\\o$`\\${f}`")
@@ -207,7 +274,8 @@ fn test_heredoc_valid_balanced_backticks_in_delimeter() {
 
 #[test]
 fn test_heredoc_valid_balanced_parens_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("eof(  )", false, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<eof(  )\nhello\neof(  )")
@@ -218,7 +286,8 @@ fn test_heredoc_valid_balanced_parens_in_delimeter() {
 
 #[test]
 fn test_heredoc_valid_cmd_subst_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("eof$(  )", false, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<eof$(  )\nhello\neof$(  )")
@@ -229,7 +298,8 @@ fn test_heredoc_valid_cmd_subst_in_delimeter() {
 
 #[test]
 fn test_heredoc_valid_param_subst_in_delimeter() {
-    let correct = Some(cat_heredoc(None, "hello\n"));
+    let info = heredoc_info("eof${  }", false, false, &["hello"]);
+    let correct = Some(cat_heredoc(None, "hello\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<eof${  }\nhello\neof${  }")
@@ -244,7 +314,7 @@ fn test_heredoc_valid_skip_past_newlines_in_single_quotes() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(single_quoted("\n")),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
@@ -263,7 +333,7 @@ fn test_heredoc_valid_skip_past_newlines_in_double_quotes() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(double_quoted("\n")),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
@@ -282,7 +352,7 @@ fn test_heredoc_valid_skip_past_newlines_in_backticks() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(word_subst(ParameterSubstitution::Command(vec![cmd(
                 "echo",
             )]))),
@@ -299,7 +369,7 @@ fn test_heredoc_valid_skip_past_newlines_in_backticks() {
 
 #[test]
 fn test_heredoc_valid_skip_past_newlines_in_parens() {
-    let correct = Some(cat_heredoc(None, "here\n"));
+    let correct = Some(cat_heredoc(None, "here\n", here_info()));
     assert_eq!(
         correct,
         make_parser("cat <<EOF; (foo\n); arg\nhere\nEOF")
@@ -314,7 +384,7 @@ fn test_heredoc_valid_skip_past_newlines_in_cmd_subst() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(word_subst(ParameterSubstitution::Command(vec![cmd(
                 "foo",
             )]))),
@@ -335,7 +405,7 @@ fn test_heredoc_valid_skip_past_newlines_in_param_subst() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(word_subst(ParameterSubstitution::Assign(
                 false,
                 Parameter::Var(String::from("foo")),
@@ -358,7 +428,7 @@ fn test_heredoc_valid_skip_past_escaped_newlines() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
     }));
@@ -372,7 +442,8 @@ fn test_heredoc_valid_skip_past_escaped_newlines() {
 
 #[test]
 fn test_heredoc_valid_double_quoted_delim_keeps_backslashe_except_after_specials() {
-    let correct = Some(cat_heredoc(None, "here\n"));
+    let info = heredoc_info("\\EOF$`\"\\", true, false, &["here"]);
+    let correct = Some(cat_heredoc(None, "here\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<\"\\EOF\\$\\`\\\"\\\\\"\nhere\n\\EOF$`\"\\\n")
@@ -383,7 +454,8 @@ fn test_heredoc_valid_double_quoted_delim_keeps_backslashe_except_after_specials
 
 #[test]
 fn test_heredoc_valid_unquoting_only_removes_outer_quotes_and_backslashes() {
-    let correct = Some(cat_heredoc(None, "here\n"));
+    let info = heredoc_info("EOF${ asdf}(hello'){o}", true, false, &["here"]);
+    let correct = Some(cat_heredoc(None, "here\n", info));
     assert_eq!(
         correct,
         make_parser("cat <<EOF${ 'asdf'}(\"hello'\"){\\o}\nhere\nEOF${ asdf}(hello'){o}")
@@ -398,7 +470,7 @@ fn test_heredoc_valid_delimeter_can_be_followed_by_carriage_return_newline() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"), here_info())),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
     }));
@@ -413,7 +485,11 @@ fn test_heredoc_valid_delimeter_can_be_followed_by_carriage_return_newline() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\r\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(
+                None,
+                word("here\r\n"),
+                heredoc_info("EOF", false, false, &["here\r"]),
+            )),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
     }));
@@ -427,7 +503,12 @@ fn test_heredoc_valid_delimeter_can_be_followed_by_carriage_return_newline() {
 
 #[test]
 fn test_heredoc_valid_delimiter_can_start_with() {
-    let correct = Some(cat_heredoc(None, "\thello\n\t\tworld\n"));
+    let lines = ["\thello", "\t\tworld"];
+    let correct = Some(cat_heredoc(
+        None,
+        "\thello\n\t\tworld\n",
+        heredoc_info("-EOF", false, false, &lines),
+    ));
     assert_eq!(
         correct,
         make_parser("cat << -EOF\n\thello\n\t\tworld\n-EOF")
@@ -435,7 +516,11 @@ fn test_heredoc_valid_delimiter_can_start_with() {
             .unwrap()
     );
 
-    let correct = Some(cat_heredoc(None, "hello\nworld\n"));
+    let correct = Some(cat_heredoc(
+        None,
+        "hello\nworld\n",
+        heredoc_info("-EOF", false, true, &lines),
+    ));
     assert_eq!(
         correct,
         make_parser("cat <<--EOF\n\thello\n\t\tworld\n-EOF")
@@ -444,6 +529,33 @@ fn test_heredoc_valid_delimiter_can_start_with() {
     );
 }
 
+#[test]
+fn test_heredoc_valid_only_leading_tabs_are_stripped() {
+    let correct = Some(cat_heredoc(
+        None,
+        "a\tb \t\n",
+        heredoc_info("EOF", false, true, &["\t\ta\tb \t"]),
+    ));
+    assert_eq!(
+        correct,
+        make_parser("cat <<-EOF\n\t\ta\tb \t\n\tEOF")
+            .complete_command()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_heredoc_valid_info_records_unterminated_last_line() {
+    let info = heredoc_info("EOF", true, false, &["a", "", "b"]);
+    let correct = Some(cat_heredoc(None, "a\n\nb", info));
+    assert_eq!(
+        correct,
+        make_parser("cat <<'EOF'\na\n\nb")
+            .complete_command()
+            .unwrap()
+    );
+}
+
 #[test]
 fn test_heredoc_invalid_missing_delimeter() {
     assert_eq!(
@@ -515,3 +627,43 @@ fn test_heredoc_keeps_track_of_correct_position_after_redirect() {
         p.complete_command()
     );
 }
+
+#[test]
+fn test_heredoc_in_command_subst_within_heredoc_body() {
+    let mut p = make_parser("cat <<A\nx $(cat <<B\n) $y\nB\n) z\nA\necho after");
+
+    let inner = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cat")),
+            RedirectOrCmdWord::Redirect(Heredoc(
+                None,
+                TopLevelWord(Concat(vec![
+                    lit(") "),
+                    Word::Simple(Param(Parameter::Var(String::from("y")))),
+                    lit("\n"),
+                ])),
+                heredoc_info("B", false, false, &[") $y"]),
+            )),
+        ],
+    });
+
+    let outer = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cat")),
+            RedirectOrCmdWord::Redirect(Heredoc(
+                None,
+                TopLevelWord(Concat(vec![
+                    lit("x "),
+                    subst(ParameterSubstitution::Command(vec![inner])),
+                    lit(" z\n"),
+                ])),
+                heredoc_info("A", false, false, &["x $(cat <<B", ") $y", "B", ") z"]),
+            )),
+        ],
+    });
+
+    assert_eq!(Ok(Some(outer)), p.complete_command());
+    assert_eq!(Ok(Some(cmd_args("echo", &["after"]))), p.complete_command());
+}
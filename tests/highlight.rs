@@ -0,0 +1,322 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::highlight::SemanticTokenType::*;
+use conch_parser::highlight::{encode_lsp, semantic_tokens, SemanticTokenType, TokenModifiers};
+
+const NONE: TokenModifiers = TokenModifiers::NONE;
+const QUOTED: TokenModifiers = TokenModifiers::QUOTED;
+const HEREDOC: TokenModifiers = TokenModifiers::HEREDOC;
+const ARITH: TokenModifiers = TokenModifiers::ARITHMETIC;
+
+fn highlight(src: &str) -> Vec<(&str, SemanticTokenType, TokenModifiers)> {
+    semantic_tokens(src)
+        .into_iter()
+        .map(|t| (&src[t.start.byte..t.end.byte], t.token_type, t.modifiers))
+        .collect()
+}
+
+#[test]
+fn test_highlight_code() {
+    assert_eq!(
+        highlight("if true; then foo=1 echo a >out # note\nfi"),
+        vec![
+            ("if", Keyword, NONE),
+            (";", Operator, NONE),
+            ("then", Keyword, NONE),
+            (">", Operator, NONE),
+            ("# note", Comment, NONE),
+            ("fi", Keyword, NONE),
+        ]
+    );
+
+    assert_eq!(
+        highlight("for in in in; do echo done; done"),
+        vec![
+            ("for", Keyword, NONE),
+            ("in", Keyword, NONE),
+            (";", Operator, NONE),
+            ("do", Keyword, NONE),
+            (";", Operator, NONE),
+            ("done", Keyword, NONE),
+        ]
+    );
+    assert_eq!(
+        highlight("for x do :; done"),
+        vec![
+            ("for", Keyword, NONE),
+            ("do", Keyword, NONE),
+            (";", Operator, NONE),
+            ("done", Keyword, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_redirect_both() {
+    assert_eq!(
+        highlight("foo &>out & bar &>>log"),
+        vec![
+            ("&>", Operator, NONE),
+            ("&", Operator, NONE),
+            ("&>>", Operator, NONE)
+        ]
+    );
+
+    // The target of the redirect does not start a command
+    assert_eq!(
+        highlight("echo\nif ) foo &>done"),
+        vec![
+            ("if", Keyword, NONE),
+            (")", Operator, NONE),
+            ("&>", Operator, NONE)
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_keywords_as_parsed() {
+    // Reserved words are only keywords where the parser treats them as such
+    assert_eq!(
+        highlight("x=1 if; fi; done\nfunction f { ! echo in; }"),
+        vec![
+            (";", Operator, NONE),
+            (";", Operator, NONE),
+            ("function", Keyword, NONE),
+            ("{", Keyword, NONE),
+            ("!", Keyword, NONE),
+            (";", Operator, NONE),
+            ("}", Keyword, NONE),
+        ]
+    );
+
+    // Keywords after an error are approximated
+    assert_eq!(
+        highlight("echo\nif ); then fi"),
+        vec![
+            ("if", Keyword, NONE),
+            (")", Operator, NONE),
+            (";", Operator, NONE),
+            ("then", Keyword, NONE),
+            ("fi", Keyword, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_expansions_in_double_quotes() {
+    assert_eq!(
+        highlight("echo \"hi $USER, ${name:-a $1}!\" $@"),
+        vec![
+            ("\"hi ", String, QUOTED),
+            ("$USER", Variable, QUOTED),
+            (", ", String, QUOTED),
+            ("${", Operator, QUOTED),
+            ("name", Variable, QUOTED),
+            (":-", Operator, QUOTED),
+            ("a ", String, QUOTED),
+            ("$1", Parameter, QUOTED),
+            ("}", Operator, QUOTED),
+            ("!\"", String, QUOTED),
+            ("$@", Parameter, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_parameter_substitution_operators() {
+    assert_eq!(
+        highlight("\"${#x}${x%%.*}${!ref}\" ${y:=z}"),
+        vec![
+            ("\"", String, QUOTED),
+            ("${", Operator, QUOTED),
+            ("#", Operator, QUOTED),
+            ("x", Variable, QUOTED),
+            ("}", Operator, QUOTED),
+            ("${", Operator, QUOTED),
+            ("x", Variable, QUOTED),
+            ("%%", Operator, QUOTED),
+            (".*", String, QUOTED),
+            ("}", Operator, QUOTED),
+            ("${", Operator, QUOTED),
+            ("!", Operator, QUOTED),
+            ("ref", Variable, QUOTED),
+            ("}", Operator, QUOTED),
+            ("\"", String, QUOTED),
+            ("${", Operator, NONE),
+            ("y", Variable, NONE),
+            (":=", Operator, NONE),
+            ("}", Operator, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_command_substitution_resets_modifiers() {
+    assert_eq!(
+        highlight("\"a $(ls 'b') `c`\""),
+        vec![
+            ("\"a ", String, QUOTED),
+            ("$(", Operator, QUOTED),
+            ("'b'", String, QUOTED),
+            (")", Operator, QUOTED),
+            (" ", String, QUOTED),
+            ("`", Operator, QUOTED),
+            ("`", Operator, QUOTED),
+            ("\"", String, QUOTED),
+        ]
+    );
+    assert_eq!(
+        highlight("\"$(if x; then :; fi)\""),
+        vec![
+            ("\"", String, QUOTED),
+            ("$(", Operator, QUOTED),
+            ("if", Keyword, NONE),
+            (";", Operator, NONE),
+            ("then", Keyword, NONE),
+            (";", Operator, NONE),
+            ("fi", Keyword, NONE),
+            (")", Operator, QUOTED),
+            ("\"", String, QUOTED),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_arithmetic() {
+    assert_eq!(
+        highlight("\"$((x <= 10 ? $y : -1))\"; ((i += 2))"),
+        vec![
+            ("\"", String, QUOTED),
+            ("$((", Operator, QUOTED),
+            ("x", Variable, QUOTED | ARITH),
+            ("<=", Operator, QUOTED | ARITH),
+            ("10", Number, QUOTED | ARITH),
+            ("?", Operator, QUOTED | ARITH),
+            ("$y", Variable, QUOTED | ARITH),
+            (":", Operator, QUOTED | ARITH),
+            ("-", Operator, QUOTED | ARITH),
+            ("1", Number, QUOTED | ARITH),
+            ("))", Operator, QUOTED),
+            ("\"", String, QUOTED),
+            (";", Operator, NONE),
+            ("((", Operator, NONE),
+            ("i", Variable, ARITH),
+            ("+=", Operator, ARITH),
+            ("2", Number, ARITH),
+            ("))", Operator, NONE),
+        ]
+    );
+    assert_eq!(
+        highlight("$((16#ff+64#a@_))"),
+        vec![
+            ("$((", Operator, NONE),
+            ("16#ff", Number, ARITH),
+            ("+", Operator, ARITH),
+            ("64#a@_", Number, ARITH),
+            ("))", Operator, NONE),
+        ]
+    );
+    assert_eq!(
+        highlight("$((arr[i]+1))"),
+        vec![
+            ("$((", Operator, NONE),
+            ("arr", Variable, ARITH),
+            ("[", Operator, ARITH),
+            ("i", Variable, ARITH),
+            ("]", Operator, ARITH),
+            ("+", Operator, ARITH),
+            ("1", Number, ARITH),
+            ("))", Operator, NONE),
+        ]
+    );
+    assert_eq!(
+        highlight("$((a) ); ((b) | c)"),
+        vec![
+            ("$(", Operator, NONE),
+            ("(", Operator, NONE),
+            (")", Operator, NONE),
+            (")", Operator, NONE),
+            (";", Operator, NONE),
+            ("(", Operator, NONE),
+            ("(", Operator, NONE),
+            (")", Operator, NONE),
+            ("|", Operator, NONE),
+            (")", Operator, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_heredocs() {
+    let src = "cat <<EOF; cat <<-'RAW'\nhi $USER\n${x}\nEOF\n\t$raw\n\tRAW\necho";
+    assert_eq!(
+        highlight(src),
+        vec![
+            ("<<", Operator, NONE),
+            ("EOF", String, HEREDOC),
+            (";", Operator, NONE),
+            ("<<-", Operator, NONE),
+            ("'RAW'", String, HEREDOC),
+            ("hi ", String, HEREDOC),
+            ("$USER", Variable, HEREDOC),
+            ("${", Operator, HEREDOC),
+            ("x", Variable, HEREDOC),
+            ("}", Operator, HEREDOC),
+            ("EOF", String, HEREDOC),
+            ("\t$raw", String, HEREDOC),
+            ("\tRAW", String, HEREDOC),
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_tokens_are_single_line_and_ordered() {
+    let src = "echo \"a\nb $x\n\" 'c\nd' # e\n";
+    let tokens = semantic_tokens(src);
+
+    for pair in tokens.windows(2) {
+        assert!(pair[0].end.byte <= pair[1].start.byte);
+    }
+    for tok in &tokens {
+        assert_eq!(tok.start.line, tok.end.line);
+        assert!(!src[tok.start.byte..tok.end.byte].contains('\n'));
+    }
+
+    assert_eq!(
+        highlight(src),
+        vec![
+            ("\"a", String, QUOTED),
+            ("b ", String, QUOTED),
+            ("$x", Variable, QUOTED),
+            ("\"", String, QUOTED),
+            ("'c", String, QUOTED),
+            ("d'", String, QUOTED),
+            ("# e", Comment, NONE),
+        ]
+    );
+}
+
+#[test]
+fn test_encode_lsp() {
+    let src = "x=\"\u{e9}$y\"\n  echo $z";
+    let tokens = semantic_tokens(src);
+
+    let (string, var) = (String.index(), Variable.index());
+    let quoted = QUOTED.bits();
+
+    #[rustfmt::skip]
+    let expected = vec![
+        0, 2, 2, string, quoted,
+        0, 2, 2, var, quoted,
+        0, 2, 1, string, quoted,
+        1, 7, 2, var, 0,
+    ];
+    assert_eq!(encode_lsp(src, &tokens), expected);
+
+    assert_eq!(SemanticTokenType::ALL[Operator.index() as usize], Operator);
+    assert_eq!(Operator.as_str(), "operator");
+    assert_eq!(TokenModifiers::NAMES[2], "arithmetic");
+    assert_eq!(ARITH.bits(), 1 << 2);
+    assert!((QUOTED | ARITH).contains(ARITH));
+    assert!(!QUOTED.contains(QUOTED | HEREDOC));
+    assert!(NONE.is_empty());
+}
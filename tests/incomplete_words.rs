@@ -0,0 +1,135 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::ParameterSubstitution::*;
+use conch_parser::ast::*;
+use conch_parser::parse::{IncompleteWords, ParseError};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn parse_partial(source: &str) -> (Vec<TopLevelCommand<String>>, Vec<ParseError<void::Void>>) {
+    let mut p = make_parser(source);
+    p.set_incomplete_words(IncompleteWords::Partial);
+
+    let mut cmds = Vec::new();
+    while let Some(cmd) = p.complete_command().unwrap() {
+        cmds.push(cmd);
+    }
+
+    (cmds, p.take_recovered_errors())
+}
+
+fn echo(arg: TopLevelWord<String>) -> TopLevelCommand<String> {
+    cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(arg),
+        ],
+    })
+}
+
+fn var(name: &str) -> DefaultParameter {
+    Parameter::Var(String::from(name))
+}
+
+#[test]
+fn test_incomplete_words_rejected_by_default() {
+    let mut p = make_parser("echo ${HO");
+    assert_eq!(p.incomplete_words(), IncompleteWords::Reject);
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unmatched(Token::CurlyOpen, src(6, 1, 7)))
+    );
+}
+
+#[test]
+fn test_incomplete_words_partial_parameter() {
+    let (cmds, errors) = parse_partial("echo ${HO");
+    assert_eq!(cmds, vec![echo(word_param(var("HO")))]);
+    assert_eq!(
+        errors,
+        vec![ParseError::Unmatched(Token::CurlyOpen, src(6, 1, 7))]
+    );
+
+    let (cmds, errors) = parse_partial("echo ${");
+    assert_eq!(cmds, vec![echo(word_param(var("")))]);
+    assert_eq!(
+        errors,
+        vec![ParseError::Unmatched(Token::CurlyOpen, src(6, 1, 7))]
+    );
+}
+
+#[test]
+fn test_incomplete_words_partial_parameter_substitution() {
+    let cases = vec![
+        ("echo ${#HO", Len(var("HO"))),
+        ("echo ${x:-fo", Default(true, var("x"), Some(word("fo")))),
+        ("echo ${x#", RemoveSmallestPrefix(var("x"), None)),
+        ("echo ${x//a", ReplaceStringAll(var("x"), Some(word("a")))),
+    ];
+
+    for (source, subst) in cases {
+        let (cmds, errors) = parse_partial(source);
+        assert_eq!(cmds, vec![echo(word_subst(subst))], "source: {:?}", source);
+        assert_eq!(
+            errors,
+            vec![ParseError::Unmatched(Token::CurlyOpen, src(6, 1, 7))]
+        );
+    }
+}
+
+#[test]
+fn test_incomplete_words_partial_command_substitution() {
+    let (cmds, errors) = parse_partial("echo $(gi");
+    assert_eq!(cmds, vec![echo(word_subst(Command(vec![cmd("gi")])))]);
+    assert_eq!(
+        errors,
+        vec![ParseError::Unmatched(Token::ParenOpen, src(6, 1, 7))]
+    );
+
+    let (cmds, _) = parse_partial("echo $(");
+    assert_eq!(cmds, vec![echo(word_subst(Command(vec![])))]);
+}
+
+#[test]
+fn test_incomplete_words_partial_nested_expansions_innermost_first() {
+    let (cmds, errors) = parse_partial("echo $(cat ${HO");
+    let inner = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cat")),
+            RedirectOrCmdWord::CmdWord(word_param(var("HO"))),
+        ],
+    });
+    assert_eq!(cmds, vec![echo(word_subst(Command(vec![inner])))]);
+    assert_eq!(
+        errors,
+        vec![
+            ParseError::Unmatched(Token::CurlyOpen, src(12, 1, 13)),
+            ParseError::Unmatched(Token::ParenOpen, src(6, 1, 7)),
+        ]
+    );
+}
+
+#[test]
+fn test_incomplete_words_partial_only_closes_at_end_of_input() {
+    let (cmds, errors) = parse_partial("echo ${HO}\necho $(foo)");
+    assert_eq!(
+        cmds,
+        vec![
+            echo(word_param(var("HO"))),
+            echo(word_subst(Command(vec![cmd("foo")]))),
+        ]
+    );
+    assert_eq!(errors, vec![]);
+
+    // Errors which are not caused by the end of the input are still reported
+    let mut reject = make_parser("echo ${HO bar");
+    let mut partial = make_parser("echo ${HO bar");
+    partial.set_incomplete_words(IncompleteWords::Partial);
+
+    let expected = reject.complete_command();
+    assert!(expected.is_err());
+    assert_eq!(partial.complete_command(), expected);
+}
@@ -0,0 +1,193 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::{BinaryTest, CaseTerminator, Conditional};
+use conch_parser::ir::{lower, CaseArm, Exec, RedirectOp, Redirection, Stmt};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn lowered(src: &str) -> Stmt {
+    lower(&make_parser(src).parse_all().unwrap())
+}
+
+fn exec(words: &[&str]) -> Stmt {
+    Stmt::Exec(Exec {
+        assignments: vec![],
+        words: words.iter().map(|w| word(w)).collect(),
+        redirects: vec![],
+    })
+}
+
+fn redirect(fd: u16, op: RedirectOp, target: &str) -> Redirection {
+    Redirection {
+        fd,
+        op,
+        target: word(target),
+    }
+}
+
+fn boxed(stmt: Stmt) -> Box<Stmt> {
+    Box::new(stmt)
+}
+
+#[test]
+fn test_lists_are_flattened() {
+    assert_eq!(lowered("a"), exec(&["a"]));
+    assert_eq!(
+        lowered("a; { b; { c; }; }\nd &"),
+        Stmt::Seq(vec![
+            exec(&["a"]),
+            exec(&["b"]),
+            exec(&["c"]),
+            Stmt::Background(boxed(exec(&["d"]))),
+        ])
+    );
+    assert_eq!(
+        lowered("a && b || c"),
+        Stmt::Or(
+            boxed(Stmt::And(boxed(exec(&["a"])), boxed(exec(&["b"])))),
+            boxed(exec(&["c"]))
+        )
+    );
+    assert_eq!(
+        lowered("(a; b) | c"),
+        Stmt::Pipeline(vec![
+            Stmt::Subshell(boxed(Stmt::Seq(vec![exec(&["a"]), exec(&["b"])]))),
+            exec(&["c"]),
+        ])
+    );
+}
+
+#[test]
+fn test_redirects_are_normalized() {
+    let stmt = lowered("x=1 <in a 2>&- &>out b >>log");
+    assert_eq!(
+        stmt,
+        Stmt::Exec(Exec {
+            assignments: vec![(String::from("x"), Some(word("1")))],
+            words: vec![word("a"), word("b")],
+            redirects: vec![
+                redirect(0, RedirectOp::Read, "in"),
+                redirect(2, RedirectOp::Dup, "-"),
+                redirect(1, RedirectOp::Write, "out"),
+                redirect(2, RedirectOp::Dup, "1"),
+                redirect(1, RedirectOp::Append, "log"),
+            ],
+        })
+    );
+
+    assert_eq!(
+        lowered("a >x |& { b; c; } |& d"),
+        Stmt::Pipeline(vec![
+            Stmt::Exec(Exec {
+                assignments: vec![],
+                words: vec![word("a")],
+                redirects: vec![
+                    redirect(1, RedirectOp::Write, "x"),
+                    redirect(2, RedirectOp::Dup, "1"),
+                ],
+            }),
+            Stmt::Redirected(
+                boxed(Stmt::Seq(vec![exec(&["b"]), exec(&["c"])])),
+                vec![redirect(2, RedirectOp::Dup, "1")]
+            ),
+            exec(&["d"]),
+        ])
+    );
+
+    assert_eq!(
+        lowered("{ a; b; } <&3 >|f"),
+        Stmt::Redirected(
+            boxed(Stmt::Seq(vec![exec(&["a"]), exec(&["b"])])),
+            vec![
+                redirect(0, RedirectOp::Dup, "3"),
+                redirect(1, RedirectOp::Clobber, "f"),
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_compound_commands_are_desugared() {
+    assert_eq!(
+        lowered("if a; then b; elif c; then d; else e; fi"),
+        Stmt::If {
+            guard: boxed(exec(&["a"])),
+            then: boxed(exec(&["b"])),
+            otherwise: Some(boxed(Stmt::If {
+                guard: boxed(exec(&["c"])),
+                then: boxed(exec(&["d"])),
+                otherwise: Some(boxed(exec(&["e"]))),
+            })),
+        }
+    );
+
+    assert_eq!(
+        lowered("until a; do b; done"),
+        Stmt::While {
+            guard: boxed(Stmt::Not(boxed(exec(&["a"])))),
+            body: boxed(exec(&["b"])),
+        }
+    );
+
+    assert_eq!(
+        lowered("for x; do b; done"),
+        Stmt::For {
+            var: String::from("x"),
+            words: vec![make_parser("\"$@\"").word().unwrap().unwrap()],
+            body: boxed(exec(&["b"])),
+        }
+    );
+
+    assert_eq!(
+        lowered("case $x in a|b) c;; esac"),
+        Stmt::Case {
+            word: make_parser("$x").word().unwrap().unwrap(),
+            arms: vec![CaseArm {
+                patterns: vec![word("a"), word("b")],
+                body: boxed(exec(&["c"])),
+                terminator: CaseTerminator::Break,
+            }],
+        }
+    );
+
+    assert_eq!(
+        lowered("f() { a; }"),
+        Stmt::Function {
+            name: String::from("f"),
+            body: boxed(exec(&["a"])),
+        }
+    );
+}
+
+#[test]
+fn test_negations_are_pushed_down() {
+    assert_eq!(lowered("! { ! a; }"), exec(&["a"]));
+    assert_eq!(
+        lowered("! { a; b; }"),
+        Stmt::Seq(vec![exec(&["a"]), Stmt::Not(boxed(exec(&["b"])))])
+    );
+    assert_eq!(
+        lowered("! (a)"),
+        Stmt::Not(boxed(Stmt::Subshell(boxed(exec(&["a"])))))
+    );
+    assert_eq!(
+        lowered("! a | b"),
+        Stmt::Not(boxed(Stmt::Pipeline(vec![exec(&["a"]), exec(&["b"])])))
+    );
+
+    let x = || make_parser("$x").word().unwrap().unwrap();
+    assert_eq!(
+        lowered("[[ ! ( -n $x && $x == c ) ]]"),
+        Stmt::Test(Conditional::Or(
+            Box::new(Conditional::Unary('z', x())),
+            Box::new(Conditional::Binary(x(), BinaryTest::NotMatch, word("c"))),
+        ))
+    );
+    assert_eq!(
+        lowered("[[ ! ! $x -lt 1 && ! -f $x ]]"),
+        Stmt::Test(Conditional::And(
+            Box::new(Conditional::Binary(x(), BinaryTest::NumLess, word("1"))),
+            Box::new(Conditional::Not(Box::new(Conditional::Unary('f', x())))),
+        ))
+    );
+}
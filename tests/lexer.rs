@@ -1,5 +1,7 @@
 #![deny(rust_2018_idioms)]
-use conch_parser::lexer::Lexer;
+use conch_parser::ast::Span;
+use conch_parser::lexer::{Lexer, SpannedTokens};
+use conch_parser::parse::SourcePos;
 use conch_parser::token::Token::*;
 use conch_parser::token::{Positional, Token};
 
@@ -137,6 +139,17 @@ lex_str!(
     Literal(String::from("3"))
 );
 
+lex_str!(
+    check_dollar_dollar_is_not_positional,
+    "$$1$$$2",
+    Dollar,
+    Dollar,
+    Literal(String::from("1")),
+    Dollar,
+    Dollar,
+    ParamPositional(Positional::Two)
+);
+
 lex_str!(
     check_no_tokens_lost,
     "word\\'",
@@ -144,3 +157,64 @@ lex_str!(
     Backslash,
     SingleQuote
 );
+
+#[test]
+fn test_spanned_tokens_cover_source() {
+    let source = "if true; then # caf\u{e9}\n\techo 'a\nb'\nfi";
+    let tokens = Lexer::new(source.chars()).spanned().collect::<Vec<_>>();
+
+    let mut end = 0;
+    for (tok, span) in &tokens {
+        assert_eq!(span.start.byte, end);
+        assert_eq!(&source[span.start.byte..span.end.byte], tok.as_str());
+        end = span.end.byte;
+    }
+    assert_eq!(end, source.len());
+
+    let find = |needle: &Token| tokens.iter().find(|(tok, _)| tok == needle).unwrap().1;
+    assert_eq!(
+        find(&Pound),
+        Span {
+            start: SourcePos {
+                byte: 14,
+                line: 1,
+                col: 15
+            },
+            end: SourcePos {
+                byte: 15,
+                line: 1,
+                col: 16
+            },
+        }
+    );
+    assert_eq!(
+        find(&Name(String::from("echo"))).start,
+        SourcePos {
+            byte: 23,
+            line: 2,
+            col: 2
+        }
+    );
+    assert_eq!(
+        find(&Name(String::from("fi"))).start,
+        SourcePos {
+            byte: 34,
+            line: 4,
+            col: 1
+        }
+    );
+}
+
+#[test]
+fn test_spanned_tokens_with_pos() {
+    let mut tokens = SpannedTokens::with_pos(Lexer::new("a  b".chars()), SourcePos::from_byte(10));
+    assert_eq!(
+        tokens.nth(1).unwrap().1,
+        Span {
+            start: SourcePos::from_byte(11),
+            end: SourcePos::from_byte(13)
+        }
+    );
+    assert_eq!(tokens.pos(), SourcePos::from_byte(13));
+    assert_eq!(tokens.count(), 1);
+}
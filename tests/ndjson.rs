@@ -0,0 +1,109 @@
+#![cfg(feature = "ndjson")]
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::lint::{Lint, UncheckedCd, UNCHECKED_CD};
+use conch_parser::ast::TopLevelCommand;
+use conch_parser::lexer::Lexer;
+use conch_parser::ndjson::{EmitSummary, NdjsonEmitter};
+use conch_parser::parse::{DefaultParser, ErrorRecovery};
+use serde_json::Value;
+use std::slice;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn emit(p: &mut DefaultParser<Lexer<std::str::Chars<'_>>>) -> (EmitSummary, Vec<Value>) {
+    let mut emitter = NdjsonEmitter::new(Vec::new());
+    let summary = emitter.emit_all(p).unwrap();
+    let out = String::from_utf8(emitter.into_inner()).unwrap();
+    let lines = out
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    (summary, lines)
+}
+
+#[test]
+fn test_emits_one_line_per_command() {
+    let src = "# leading\nfoo a\n\nbar | baz; qux &\n# trailing\n";
+    let (summary, lines) = emit(&mut make_parser(src));
+    assert_eq!(
+        summary,
+        EmitSummary {
+            commands: 3,
+            diagnostics: 0,
+            failed: false,
+        }
+    );
+
+    let cmds = make_parser(src).parse_all().unwrap();
+    assert_eq!(lines.len(), cmds.len());
+    for (line, cmd) in lines.iter().zip(&cmds) {
+        let parsed: TopLevelCommand<String> =
+            serde_json::from_value(line["command"].clone()).unwrap();
+        assert_eq!(parsed, *cmd);
+        assert_eq!(line["diagnostics"], Value::Array(vec![]));
+    }
+
+    let bytes = lines
+        .iter()
+        .map(|line| {
+            (
+                line["span"]["start"]["byte"].as_u64().unwrap(),
+                line["span"]["end"]["byte"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(bytes, vec![(0, 16), (16, 28), (28, 34)]);
+    assert_eq!(lines[1]["span"]["start"]["line"], 3);
+}
+
+#[test]
+fn test_reports_recovered_and_fatal_errors_inline() {
+    let mut p = make_parser("foo\n) bar\nbaz");
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+
+    let (summary, lines) = emit(&mut p);
+    assert_eq!(summary.commands, 3);
+    assert_eq!(summary.diagnostics, 1);
+    assert!(!summary.failed);
+
+    let diag = &lines[1]["diagnostics"][0];
+    assert_eq!(diag["severity"], "error");
+    assert_eq!(diag["code"], "parse-unexpected");
+    assert_eq!(diag["span"]["start"]["byte"], 4);
+    assert_eq!(lines[2]["diagnostics"], Value::Array(vec![]));
+
+    let (summary, lines) = emit(&mut make_parser("foo; ) bar\nbaz"));
+    assert_eq!(
+        summary,
+        EmitSummary {
+            commands: 1,
+            diagnostics: 1,
+            failed: true,
+        }
+    );
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["command"], Value::Null);
+    assert_eq!(lines[1]["diagnostics"][0]["code"], "parse-unexpected");
+}
+
+#[test]
+fn test_emit_all_with_reports_lint_diagnostics() {
+    let mut emitter = NdjsonEmitter::new(Vec::new());
+    let summary = emitter
+        .emit_all_with(&mut make_parser("cd dir\nmake\ncd dir || exit"), |cmd| {
+            UncheckedCd.check(slice::from_ref(cmd))
+        })
+        .unwrap();
+    assert_eq!(summary.diagnostics, 1);
+
+    let out = String::from_utf8(emitter.into_inner()).unwrap();
+    let lines = out
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let diag = &lines[0]["diagnostics"][0];
+    assert_eq!(diag["severity"], "warning");
+    assert_eq!(diag["code"], UNCHECKED_CD);
+    assert_eq!(diag["span"], Value::Null);
+}
@@ -0,0 +1,67 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{NulHandling, NUL_REPLACEMENT};
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_nul_handling_defaults_to_reject() {
+    assert_eq!(make_parser("").nul_handling(), NulHandling::Reject);
+}
+
+#[test]
+fn test_nul_byte_rejected_with_position() {
+    let cases = [
+        ("foo a\0b", src(5, 1, 6)),
+        ("foo \\\nbar \0", src(10, 2, 5)),
+        ("foo 'a\nb\0'", src(8, 2, 2)),
+        ("foo \"ab\0\"", src(7, 1, 8)),
+        ("foo \\\0", src(5, 1, 6)),
+        ("cat <<'EOF'\nab\n\0\nEOF\n", src(15, 3, 1)),
+        ("cat <<-'EOF'\n\tx\n\t\tab\0\nEOF\n", src(20, 3, 5)),
+        ("cat <<EOF\nab\0\nEOF\n", src(12, 2, 3)),
+    ];
+
+    for &(source, pos) in &cases {
+        assert_eq!(
+            Err(NulByte(pos)),
+            make_parser(source).complete_command(),
+            "source: {:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_nul_byte_error_display() {
+    let err = make_parser("foo a\0b").complete_command().unwrap_err();
+    assert_eq!(err.to_string(), "found NUL byte on line 1:6");
+}
+
+#[test]
+fn test_nul_byte_replaced() {
+    let mut p = make_parser("foo a\0b 'c\0' \"\0\"");
+    p.set_nul_handling(NulHandling::Replace);
+
+    let replaced = format!("a{}b", NUL_REPLACEMENT);
+    let cmd = p.complete_command().unwrap().unwrap();
+    let expected = make_parser(&format!(
+        "foo {} 'c{}' \"{}\"",
+        replaced, NUL_REPLACEMENT, NUL_REPLACEMENT
+    ))
+    .complete_command()
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(cmd, expected);
+}
+
+#[test]
+fn test_other_control_chars_are_preserved() {
+    let mut p = make_parser("foo a\x07b\u{202E}c");
+    assert_eq!(
+        p.complete_command(),
+        Ok(Some(cmd_args("foo", &["a\x07b\u{202E}c"])))
+    );
+}
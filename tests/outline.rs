@@ -0,0 +1,147 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{DefaultBuilder, OutlineBuilder, OutlineItem, OutlineKind};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{ErrorRecovery, Parser, SourcePos};
+
+fn outline_with(src: &str, error_recovery: ErrorRecovery) -> Vec<OutlineItem> {
+    let mut builder = OutlineBuilder::new(DefaultBuilder::<String>::new());
+    let mut parser = Parser::with_builder(Lexer::new(src.chars()), &mut builder);
+    parser.set_error_recovery(error_recovery);
+    parser.parse_all().unwrap();
+    builder.take_outline()
+}
+
+fn outline(src: &str) -> Vec<OutlineItem> {
+    outline_with(src, ErrorRecovery::Abort)
+}
+
+/// Renders an outline as `kind name` lines, indented by their depth.
+fn render(items: &[OutlineItem]) -> Vec<String> {
+    fn render_into(items: &[OutlineItem], depth: usize, out: &mut Vec<String>) {
+        for item in items {
+            out.push(format!(
+                "{}{:?} {}",
+                "  ".repeat(depth),
+                item.kind,
+                item.name
+            ));
+            render_into(&item.children, depth + 1, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    render_into(items, 0, &mut out);
+    out
+}
+
+fn src(byte: usize, line: usize, col: usize) -> SourcePos {
+    SourcePos { byte, line, col }
+}
+
+#[test]
+fn test_outline_lists_functions_and_top_level_compound_commands() {
+    let src = "\
+setup() { mkdir -p out; }
+if true; then
+  while false; do helper() { :; }; done
+fi
+echo done
+for f in *; do case $f in *) ;; esac; done
+{ a; } && ( b )
+until x; do y; done | [[ -n z ]]
+";
+    assert_eq!(
+        render(&outline(src)),
+        vec![
+            "Function setup",
+            "If if",
+            "  Function helper",
+            "For f",
+            "BraceGroup {",
+            "Subshell (",
+            "Until until",
+        ]
+    );
+}
+
+#[test]
+fn test_outline_nests_functions_and_heredocs() {
+    let src = "\
+outer() {
+  inner() {
+    cat <<-'END'
+\tbody
+\tEND
+  }
+  if true; then cat <<A <<B; fi
+a
+A
+b
+B
+}
+";
+    assert_eq!(
+        render(&outline(src)),
+        vec![
+            "Function outer",
+            "  Function inner",
+            "    Heredoc END",
+            "  Heredoc A",
+            "  Heredoc B",
+        ]
+    );
+}
+
+#[test]
+fn test_outline_omits_compound_commands_within_substitutions() {
+    assert_eq!(
+        render(&outline(
+            "x=$(if a; then f() { b; }; fi) echo `while c; do d; done`"
+        )),
+        vec!["Function f"]
+    );
+}
+
+#[test]
+fn test_outline_records_spans() {
+    let items = outline("echo\nf() {\n  cat <<EOF\nbody\nEOF\n}\n");
+    assert_eq!(items.len(), 1);
+
+    let f = &items[0];
+    assert_eq!(f.span.start, src(5, 2, 1));
+    assert_eq!(f.span.end, src(33, 6, 2));
+
+    let heredoc = &f.children[0];
+    assert_eq!(heredoc.kind, OutlineKind::Heredoc);
+    assert_eq!(heredoc.span.start, src(17, 3, 7));
+    assert_eq!(heredoc.span.end, src(22, 3, 12));
+}
+
+#[test]
+fn test_outline_path_to_position() {
+    let items = outline("f() {\n  g() {\n    cat <<EOF\nEOF\n  }\n}\nwhile x; do y; done\n");
+
+    let names = |pos| {
+        OutlineItem::path_to(&items, pos)
+            .into_iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(src(0, 1, 1)), vec!["f"]);
+    assert_eq!(names(src(10, 2, 5)), vec!["f", "g"]);
+    assert_eq!(names(src(23, 3, 10)), vec!["f", "g", "EOF"]);
+    assert_eq!(names(src(40, 7, 1)), vec!["while"]);
+    assert!(names(src(1000, 10, 1)).is_empty());
+}
+
+#[test]
+fn test_outline_drops_entries_from_unparsed_regions() {
+    assert_eq!(
+        render(&outline_with(
+            "f() { a; }\ng() { if b; then ;; fi }\nh() { c; }",
+            ErrorRecovery::Passthrough
+        )),
+        vec!["Function f", "Function h"]
+    );
+}
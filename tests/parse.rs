@@ -167,3 +167,62 @@ fn ensure_parser_could_be_send_and_sync() {
     fn send_and_sync<T: Send + Sync>() {}
     send_and_sync::<Parser<std::vec::IntoIter<Token>, ArcBuilder>>();
 }
+
+#[test]
+fn test_track_keywords() {
+    let src = "if a; then echo done; fi\nfunction f { ! x | y; } 2<&-\nfi";
+    let mut p = make_parser(src);
+    p.complete_command().unwrap();
+    assert_eq!(p.take_keywords(), vec![]);
+
+    p.set_track_keywords(true);
+    p.complete_command().unwrap();
+
+    let keywords = p
+        .take_keywords()
+        .into_iter()
+        .map(|kw| (kw.word, kw.span.start.byte, kw.span.end.byte))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("function", 25, 33),
+        ("{", 36, 37),
+        ("!", 38, 39),
+        ("}", 47, 48),
+    ];
+    let expected = expected
+        .iter()
+        .map(|&(w, start, end)| (String::from(w), start, end))
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, expected);
+
+    // Closing reserved words are parsed as commands where they do not close anything
+    assert!(p.complete_command().unwrap().is_some());
+    assert_eq!(p.take_keywords(), vec![]);
+}
+
+#[test]
+fn test_track_keywords_within_compound_commands() {
+    let mut p = make_parser("for in in in; do case in in in) :;; esac; done");
+    p.set_track_keywords(true);
+    p.complete_command().unwrap();
+
+    let keywords = p
+        .take_keywords()
+        .into_iter()
+        .map(|kw| (kw.word, kw.span.start.byte))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("for", 0),
+        ("in", 7),
+        ("do", 14),
+        ("case", 17),
+        ("in", 25),
+        ("esac", 36),
+        ("done", 42),
+    ];
+    let expected = expected
+        .iter()
+        .map(|&(w, start)| (String::from(w), start))
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, expected);
+}
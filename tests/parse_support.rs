@@ -3,6 +3,8 @@
 // see our intent
 #![allow(dead_code)]
 
+use conch_parser::analysis::Diagnostic;
+use conch_parser::ast::builder::{Builder, DefaultBuilder};
 use conch_parser::ast::Command::*;
 use conch_parser::ast::ComplexWord::*;
 use conch_parser::ast::PipeableCommand::*;
@@ -11,6 +13,7 @@ use conch_parser::ast::*;
 use conch_parser::lexer::Lexer;
 use conch_parser::parse::*;
 use conch_parser::token::Token;
+use std::fmt;
 
 pub fn lit(s: &str) -> DefaultWord {
     Word::Simple(Literal(String::from(s)))
@@ -56,6 +59,27 @@ pub fn make_parser_from_tokens(src: Vec<Token>) -> DefaultParser<std::vec::IntoI
     DefaultParser::new(src.into_iter())
 }
 
+/// Parses every command of the source, panicking on any error.
+pub fn parse(src: &str) -> Vec<TopLevelCommand<String>> {
+    parse_with(src, DefaultBuilder::default())
+}
+
+/// Parses every command of the source with a specific builder, panicking on any error.
+pub fn parse_with<B: Builder>(src: &str, builder: B) -> Vec<B::Command>
+where
+    B::Error: fmt::Debug,
+{
+    Parser::with_builder(Lexer::new(src.chars()), builder)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+}
+
+/// Returns the codes of the diagnostics, in the order they were reported.
+pub fn codes<I: IntoIterator<Item = Diagnostic>>(diagnostics: I) -> Vec<&'static str> {
+    diagnostics.into_iter().map(|d| d.code).collect()
+}
+
 pub fn cmd_args_simple(cmd: &str, args: &[&str]) -> Box<DefaultSimpleCommand> {
     let mut cmd_args = Vec::with_capacity(args.len() + 1);
     cmd_args.push(RedirectOrCmdWord::CmdWord(word(cmd)));
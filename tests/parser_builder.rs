@@ -0,0 +1,76 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{ArcBuilder, StringBuilder};
+use conch_parser::parse::{
+    BraceExpansion, Dialect, ErrorRecovery, IncompleteWords, NulHandling, ParserBuilder,
+    ParserOptions, PositionTracking, ReservedWords, TildeExpansion,
+};
+use conch_parser::token::Token;
+use std::collections::HashMap;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_default_builder_matches_default_parser() {
+    let mut p = ParserBuilder::new().chars("foo; bar baz".chars());
+    assert_eq!(p.options(), ParserOptions::default());
+    assert_eq!(
+        p.parse_all(),
+        Ok(vec![cmd("foo"), cmd_args("bar", &["baz"])])
+    );
+
+    let mut p = ParserBuilder::default().tokens(vec![Token::Name(String::from("foo"))]);
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo")]));
+
+    let mut p = ParserBuilder::new().reader("foo\nbar".as_bytes());
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo"), cmd("bar")]));
+    assert!(p.read_error().is_none());
+}
+
+#[test]
+fn test_builder_applies_every_setting() {
+    let options = ParserOptions {
+        dialect: Dialect::Posix,
+        nul_handling: NulHandling::Reject,
+        error_recovery: ErrorRecovery::Passthrough,
+        position_tracking: PositionTracking::ByteOffsets,
+        incomplete_words: IncompleteWords::Partial,
+        reserved_words: ReservedWords::Strict,
+        brace_expansion: BraceExpansion::Structured,
+        tilde_expansion: TildeExpansion::Prefixes,
+    };
+
+    let p = ParserBuilder::new()
+        .dialect(options.dialect)
+        .nul_handling(options.nul_handling)
+        .error_recovery(options.error_recovery)
+        .position_tracking(options.position_tracking)
+        .incomplete_words(options.incomplete_words)
+        .reserved_words(options.reserved_words)
+        .brace_expansion(options.brace_expansion)
+        .tilde_expansion(options.tilde_expansion)
+        .chars("".chars());
+    assert_eq!(p.options(), options);
+
+    let p = ParserBuilder::new()
+        .options(options)
+        .builder(ArcBuilder::new())
+        .chars("".chars());
+    assert_eq!(p.options(), options);
+}
+
+#[test]
+fn test_builder_sets_aliases_and_keyword_tracking() {
+    let mut aliases = HashMap::new();
+    aliases.insert(String::from("ll"), String::from("ls -l"));
+
+    let mut p = ParserBuilder::new()
+        .alias_provider(aliases)
+        .track_keywords(true)
+        .builder(StringBuilder::new())
+        .chars("if ll; then :; fi".chars());
+    let expected = make_parser("if ls -l; then :; fi").parse_all().unwrap();
+    assert_eq!(p.parse_all().unwrap(), expected);
+    assert_eq!(p.take_keywords().len(), 3);
+    assert!(p.remove_alias_provider().is_some());
+}
@@ -0,0 +1,140 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{ArcBuilder, BuilderFactory, StringBuilder};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{
+    BraceExpansion, DefaultParser, Dialect, ErrorRecovery, Extension, IncompleteWords, LineIndex,
+    NulHandling, ParseError, ParserOptions, PositionTracking, ReservedWords, SourcePos,
+    TildeExpansion,
+};
+use conch_parser::token::Token;
+use std::sync::Arc;
+use std::thread;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn assert_shareable<T: Clone + Send + Sync>() {}
+
+#[test]
+fn test_options_and_factories_are_shareable_across_threads() {
+    assert_shareable::<ParserOptions>();
+    assert_shareable::<Dialect>();
+    assert_shareable::<NulHandling>();
+    assert_shareable::<ErrorRecovery>();
+    assert_shareable::<PositionTracking>();
+    assert_shareable::<ReservedWords>();
+    assert_shareable::<LineIndex>();
+    assert_shareable::<fn() -> StringBuilder>();
+}
+
+#[test]
+fn test_options_default_to_parser_defaults() {
+    let p = make_parser("");
+    assert_eq!(p.options(), ParserOptions::default());
+    assert_eq!(p.dialect(), Dialect::default());
+    assert_eq!(p.nul_handling(), NulHandling::default());
+    assert_eq!(p.error_recovery(), ErrorRecovery::default());
+    assert_eq!(p.position_tracking(), PositionTracking::default());
+    assert_eq!(p.incomplete_words(), IncompleteWords::default());
+    assert_eq!(p.reserved_words(), ReservedWords::default());
+    assert_eq!(p.brace_expansion(), BraceExpansion::default());
+    assert_eq!(p.tilde_expansion(), TildeExpansion::default());
+}
+
+#[test]
+fn test_with_options_applies_all_options() {
+    let options = ParserOptions {
+        dialect: Dialect::Posix,
+        nul_handling: NulHandling::Replace,
+        error_recovery: ErrorRecovery::Passthrough,
+        position_tracking: PositionTracking::ByteOffsets,
+        incomplete_words: IncompleteWords::Partial,
+        reserved_words: ReservedWords::Strict,
+        brace_expansion: BraceExpansion::Structured,
+        tilde_expansion: TildeExpansion::Prefixes,
+    };
+
+    let lex = Lexer::new("foo".chars());
+    let p = DefaultParser::with_options(lex, Default::default(), options);
+    assert_eq!(p.options(), options);
+    assert_eq!(p.dialect(), Dialect::Posix);
+    assert_eq!(p.nul_handling(), NulHandling::Replace);
+    assert_eq!(p.error_recovery(), ErrorRecovery::Passthrough);
+    assert_eq!(p.position_tracking(), PositionTracking::ByteOffsets);
+    assert_eq!(p.incomplete_words(), IncompleteWords::Partial);
+    assert_eq!(p.reserved_words(), ReservedWords::Strict);
+    assert_eq!(p.brace_expansion(), BraceExpansion::Structured);
+    assert_eq!(p.tilde_expansion(), TildeExpansion::Prefixes);
+    assert_eq!(p.pos(), SourcePos::from_byte(0));
+}
+
+#[test]
+fn test_set_options_keeps_line_numbering_if_tracking_is_unchanged() {
+    let mut p = make_parser("foo\nbar\n)");
+    p.complete_command().unwrap();
+    p.set_options(ParserOptions {
+        dialect: Dialect::Posix,
+        ..p.options()
+    });
+
+    assert_eq!(p.dialect(), Dialect::Posix);
+    assert_eq!(p.pos(), src(4, 2, 1));
+    p.complete_command().unwrap();
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unexpected(Token::ParenClose, src(8, 3, 1)))
+    );
+}
+
+#[test]
+fn test_options_create_parsers_with_builders_from_factory() {
+    let options = ParserOptions {
+        dialect: Dialect::Posix,
+        ..ParserOptions::default()
+    };
+
+    let factory = || StringBuilder::default();
+    let mut p = options.parser(Lexer::new("foo".chars()), &factory);
+    assert_eq!(p.options(), options);
+    assert_eq!(p.complete_command(), Ok(Some(cmd("foo"))));
+
+    let factory: &dyn BuilderFactory<Builder = StringBuilder> = &StringBuilder::default;
+    let mut p = options.parser(Lexer::new("[[ foo ]]".chars()), factory);
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Extension(
+            Extension::DoubleBracket,
+            src(0, 1, 1)
+        ))
+    );
+}
+
+#[test]
+fn test_shared_options_and_factory_parse_concurrently() {
+    let shared = Arc::new((
+        ParserOptions {
+            position_tracking: PositionTracking::ByteOffsets,
+            ..ParserOptions::default()
+        },
+        ArcBuilder::default,
+    ));
+
+    let workers = (0..4)
+        .map(|i| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let (ref options, ref factory) = *shared;
+                let source = "foo\n".repeat(i + 1);
+                options
+                    .parser(Lexer::new(source.chars()), factory)
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (i, worker) in workers.into_iter().enumerate() {
+        assert_eq!(worker.join().unwrap().len(), i + 1);
+    }
+}
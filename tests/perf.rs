@@ -0,0 +1,138 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::parse::{Dialect, ParserOptions};
+use conch_parser::perf::{run, PerfConfig, PerfReport, Throughput};
+use std::time::Duration;
+
+fn throughput(bytes: u64, millis: u64) -> Throughput {
+    Throughput {
+        bytes,
+        commands: 1,
+        time: Duration::from_millis(millis),
+    }
+}
+
+fn report(total: Throughput, categories: &[(&str, Throughput)]) -> PerfReport {
+    PerfReport {
+        iterations: 1,
+        total,
+        categories: categories
+            .iter()
+            .map(|&(name, t)| (String::from(name), t))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_commands_are_counted_by_category() {
+    let corpus = [
+        "# comment\necho foo\nfoo | bar\n",
+        "a && b || c\nf() { g; }\nif a; then b; fi\nwhile a; do b; done\n",
+        "case x in *) ;; esac\n{ a; }\n( b )\n[[ -n x ]]\n",
+    ];
+
+    let config = PerfConfig {
+        warmup: 0,
+        iterations: 3,
+        ..PerfConfig::default()
+    };
+    let report = run(&corpus, &config).unwrap();
+
+    assert_eq!(report.iterations, 3);
+    assert_eq!(report.total.commands, 10);
+    assert_eq!(
+        report.total.bytes,
+        corpus.iter().map(|s| s.len() as u64).sum::<u64>()
+    );
+
+    let names: Vec<_> = report.categories.keys().map(|s| &**s).collect();
+    assert_eq!(
+        names,
+        [
+            "and-or",
+            "brace",
+            "case",
+            "conditional",
+            "function",
+            "if",
+            "pipeline",
+            "simple",
+            "subshell",
+            "while"
+        ]
+    );
+
+    // Leading comments are attributed to the following command
+    assert_eq!(report.categories["simple"].bytes, 19);
+    assert_eq!(report.categories["pipeline"].bytes, 10);
+    assert!(report.categories.values().all(|t| t.commands == 1));
+}
+
+#[test]
+fn test_parse_errors_are_returned() {
+    let config = PerfConfig {
+        options: ParserOptions {
+            dialect: Dialect::Posix,
+            ..ParserOptions::default()
+        },
+        ..PerfConfig::default()
+    };
+
+    assert!(run(&["echo ok"], &config).is_ok());
+    assert!(run(&["echo ok", "[[ -n x ]]"], &config).is_err());
+    assert!(run(&["if"], &PerfConfig::default()).is_err());
+}
+
+#[test]
+fn test_at_least_one_iteration_is_measured() {
+    let config = PerfConfig {
+        warmup: 0,
+        iterations: 0,
+        ..PerfConfig::default()
+    };
+
+    let report = run(&["echo foo"], &config).unwrap();
+    assert_eq!(report.iterations, 1);
+    assert_eq!(report.total.commands, 1);
+}
+
+#[test]
+fn test_throughput_rates() {
+    let t = Throughput {
+        bytes: 1000,
+        commands: 10,
+        time: Duration::from_millis(500),
+    };
+    assert_eq!(t.bytes_per_sec(), 2000.0);
+    assert_eq!(t.commands_per_sec(), 20.0);
+}
+
+#[test]
+fn test_regressions_beyond_tolerance() {
+    let baseline = report(
+        throughput(1000, 100),
+        &[
+            ("simple", throughput(500, 50)),
+            ("if", throughput(500, 50)),
+            ("case", throughput(500, 50)),
+        ],
+    );
+
+    let current = report(
+        throughput(1000, 105),
+        &[
+            ("simple", throughput(500, 40)),
+            ("if", throughput(500, 100)),
+            ("for", throughput(500, 500)),
+        ],
+    );
+
+    assert_eq!(current.regressions(&current, 0.0), vec![]);
+    assert_eq!(current.regressions(&baseline, 0.1).len(), 1);
+
+    let regressions = current.regressions(&baseline, 0.01);
+    let names: Vec<_> = regressions.iter().map(|r| &*r.category).collect();
+    assert_eq!(names, ["total", "if"]);
+    assert_eq!(regressions[1].baseline, 10_000.0);
+    assert_eq!(regressions[1].current, 5_000.0);
+    assert_eq!(regressions[1].slowdown(), 0.5);
+}
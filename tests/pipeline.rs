@@ -7,16 +7,38 @@ use conch_parser::token::Token;
 mod parse_support;
 use crate::parse_support::*;
 
+fn segment(
+    pipe: Option<(usize, usize)>,
+    pipe_stderr: bool,
+    name: &str,
+) -> PipeSegment<DefaultPipeableCommand> {
+    PipeSegment {
+        pipe: pipe.map(|(start, end)| Span {
+            start: src(start, 1, start + 1),
+            end: src(end, 1, end + 1),
+        }),
+        pipe_stderr,
+        cmd: Simple(cmd_simple(name)),
+    }
+}
+
+fn pipeline(
+    bang: bool,
+    segments: Vec<PipeSegment<DefaultPipeableCommand>>,
+) -> DefaultListableCommand {
+    ListableCommand::Pipe(Pipeline { bang, segments })
+}
+
 #[test]
 fn test_pipeline_valid_bang() {
     let mut p = make_parser("! foo | bar | baz");
     let correct = CommandList {
-        first: ListableCommand::Pipe(
+        first: pipeline(
             true,
             vec![
-                Simple(cmd_simple("foo")),
-                Simple(cmd_simple("bar")),
-                Simple(cmd_simple("baz")),
+                segment(None, false, "foo"),
+                segment(Some((6, 7)), false, "bar"),
+                segment(Some((12, 13)), false, "baz"),
             ],
         ),
         rest: vec![],
@@ -28,21 +50,68 @@ fn test_pipeline_valid_bang() {
 fn test_pipeline_valid_bangs_in_and_or() {
     let mut p = make_parser("! foo | bar || ! baz && ! foobar");
     let correct = CommandList {
-        first: ListableCommand::Pipe(
+        first: pipeline(
             true,
-            vec![Simple(cmd_simple("foo")), Simple(cmd_simple("bar"))],
+            vec![
+                segment(None, false, "foo"),
+                segment(Some((6, 7)), false, "bar"),
+            ],
         ),
         rest: vec![
-            AndOr::Or(ListableCommand::Pipe(true, vec![Simple(cmd_simple("baz"))])),
-            AndOr::And(ListableCommand::Pipe(
-                true,
-                vec![Simple(cmd_simple("foobar"))],
-            )),
+            AndOr::Or(pipeline(true, vec![segment(None, false, "baz")])),
+            AndOr::And(pipeline(true, vec![segment(None, false, "foobar")])),
         ],
     };
     assert_eq!(correct, p.and_or_list().unwrap());
 }
 
+#[test]
+fn test_pipeline_segments_record_pipe_spans_and_stderr() {
+    let mut p = make_parser("foo |& bar|baz |&\n\n qux");
+    let correct = pipeline(
+        false,
+        vec![
+            segment(None, false, "foo"),
+            segment(Some((4, 6)), true, "bar"),
+            segment(Some((10, 11)), false, "baz"),
+            PipeSegment {
+                pipe: Some(Span {
+                    start: src(15, 1, 16),
+                    end: src(17, 1, 18),
+                }),
+                pipe_stderr: true,
+                cmd: Simple(cmd_simple("qux")),
+            },
+        ],
+    );
+    assert_eq!(correct, p.pipeline().unwrap());
+}
+
+#[test]
+fn test_pipeline_commands_iterates_in_order() {
+    let mut p = make_parser("foo | bar |& baz");
+    match p.pipeline().unwrap() {
+        ListableCommand::Pipe(pipeline) => {
+            assert!(!pipeline.bang);
+            assert_eq!(
+                pipeline.commands().collect::<Vec<_>>(),
+                vec![
+                    &Simple(cmd_simple("foo")),
+                    &Simple(cmd_simple("bar")),
+                    &Simple(cmd_simple("baz")),
+                ]
+            );
+        }
+        cmd => panic!("expected a pipeline, found {:?}", cmd),
+    }
+}
+
+#[test]
+fn test_pipeline_invalid_pipe_amp_separated_by_whitespace() {
+    let mut p = make_parser("foo | & bar");
+    assert_eq!(Err(Unexpected(Token::Amp, src(6, 1, 7))), p.pipeline());
+}
+
 #[test]
 fn test_pipeline_no_bang_single_cmd_optimize_wrapper_out() {
     let mut p = make_parser("foo");
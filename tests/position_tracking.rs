@@ -0,0 +1,141 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{Builder, DefaultBuilder};
+use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{ErrorRecovery, LineIndex, ParseError, PositionTracking, SourcePos};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Parses all commands of a source, returning the first error (if any).
+fn first_error(
+    source: &str,
+    tracking: PositionTracking,
+) -> ParseError<<DefaultBuilder<String> as Builder>::Error> {
+    let mut p = make_parser(source);
+    p.set_position_tracking(tracking);
+    loop {
+        match p.complete_command() {
+            Ok(Some(_)) => {}
+            Ok(None) => panic!("no error found in {:?}", source),
+            Err(e) => return e,
+        }
+    }
+}
+
+#[test]
+fn test_position_tracking_defaults_to_lines_and_columns() {
+    let p = make_parser("");
+    assert_eq!(p.position_tracking(), PositionTracking::LinesAndColumns);
+    assert_eq!(p.pos(), src(0, 1, 1));
+}
+
+#[test]
+fn test_byte_offsets_only_tracks_bytes() {
+    let mut p = make_parser("foo\nbar\nbaz");
+    p.set_position_tracking(PositionTracking::ByteOffsets);
+    assert_eq!(p.pos(), SourcePos::from_byte(0));
+
+    p.complete_command().unwrap();
+    p.complete_command().unwrap();
+    assert_eq!(p.pos(), SourcePos::from_byte(8));
+    assert!(p.pos().is_byte_only());
+}
+
+#[test]
+fn test_byte_offsets_resolve_to_same_positions_as_lines_and_columns() {
+    let sources = [
+        "foo\nbar )",
+        "foo 'a\nb' \"c\nd\" \0",
+        "if true\nthen\n  foo\n",
+        "foo `bar\nbaz\n)`",
+        "foo $(bar\n  baz \\\n qux ;;)",
+        "cat <<-EOF\n\t\tbody\n\tEOF\nfoo )",
+        "cat <<EOF\n$(foo\n  )\n`bar\n;;`\nEOF\n",
+        "echo ${foo\nbar}",
+        "case x in\n  y) foo ;;\n  z) bar\nesac )",
+        "foo \"$(( 1 +\n 99999999999999999999 ))\"",
+        "{ foo\n",
+    ];
+
+    for &source in &sources {
+        let full = first_error(source, PositionTracking::LinesAndColumns);
+        let bytes = first_error(source, PositionTracking::ByteOffsets);
+        assert_ne!(full, UnexpectedEOF, "source: {:?}", source);
+
+        let index = LineIndex::new(source);
+        let mut found_byte_only = true;
+        let bytes = bytes.map_pos(|pos| {
+            found_byte_only &= pos.is_byte_only();
+            index.resolve(pos)
+        });
+
+        assert!(found_byte_only, "source: {:?}", source);
+        assert_eq!(full, bytes, "source: {:?}", source);
+    }
+}
+
+#[test]
+fn test_byte_offsets_with_recovered_spans() {
+    let source = "foo\nbar )\nbaz";
+    let mut p = make_parser(source);
+    p.set_position_tracking(PositionTracking::ByteOffsets);
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+    p.parse_all().unwrap();
+
+    let index = LineIndex::new(source);
+    let errors = p
+        .take_recovered_errors()
+        .into_iter()
+        .map(|e| e.map_pos(|pos| index.resolve(pos)))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, vec![Unexpected(Token::ParenClose, src(8, 2, 5))]);
+}
+
+#[test]
+fn test_byte_offsets_parse_all_stops_at_shebang() {
+    let source = "#!/bin/sh\nfoo\n  #!not a shebang\n#!/bin/bash\nbar";
+    let mut p = make_parser(source);
+    p.set_position_tracking(PositionTracking::ByteOffsets);
+
+    assert_eq!(p.parse_all().unwrap().len(), 1);
+    assert_eq!(p.pos(), SourcePos::from_byte(32));
+
+    p.reset_pos();
+    assert_eq!(p.pos(), SourcePos::from_byte(0));
+    assert_eq!(p.parse_all().unwrap().len(), 1);
+    assert!(p.is_exhausted());
+}
+
+#[test]
+fn test_switching_back_to_lines_and_columns_restarts_line_numbering() {
+    let mut p = make_parser("foo\nbar\n)");
+    p.set_position_tracking(PositionTracking::ByteOffsets);
+    p.complete_command().unwrap();
+
+    p.set_position_tracking(PositionTracking::LinesAndColumns);
+    assert_eq!(p.pos(), src(4, 1, 1));
+    p.complete_command().unwrap();
+    assert_eq!(
+        p.complete_command(),
+        Err(Unexpected(Token::ParenClose, src(8, 2, 1)))
+    );
+}
+
+#[test]
+fn test_line_index_positions() {
+    let index = LineIndex::new("ab\n\nçd\n");
+    assert_eq!(index.line_count(), 4);
+
+    assert_eq!(index.pos(0), src(0, 1, 1));
+    assert_eq!(index.pos(2), src(2, 1, 3));
+    assert_eq!(index.pos(3), src(3, 2, 1));
+    assert_eq!(index.pos(4), src(4, 3, 1));
+    // Columns are counted in bytes, like the parser does
+    assert_eq!(index.pos(7), src(7, 3, 4));
+    assert_eq!(index.pos(8), src(8, 4, 1));
+    assert_eq!(index.pos(10), src(10, 4, 3));
+
+    assert_eq!(index.resolve(SourcePos::from_byte(3)), src(3, 2, 1));
+    assert_eq!(LineIndex::new("").pos(0), src(0, 1, 1));
+}
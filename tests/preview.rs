@@ -0,0 +1,71 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::parse::{Preview, PreviewLimits};
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn limits(max_commands: Option<usize>, max_bytes: Option<usize>) -> PreviewLimits {
+    PreviewLimits {
+        max_commands,
+        max_bytes,
+    }
+}
+
+#[test]
+fn test_preview_stops_after_max_commands() {
+    let mut p = make_parser("foo\nbar; baz\nqux");
+    assert_eq!(
+        p.parse_preview(limits(Some(2), None)),
+        Ok(Preview {
+            commands: vec![cmd("foo"), cmd("bar")],
+            truncated: true,
+        })
+    );
+
+    // The rest of the input is left for the parser to continue with
+    assert_eq!(p.parse_all(), Ok(vec![cmd("baz"), cmd("qux")]));
+}
+
+#[test]
+fn test_preview_stops_once_max_bytes_is_crossed() {
+    let src = "foo\nif true; then\n  bar\nfi\nbaz\n";
+
+    // The command crossing the limit is parsed in its entirety
+    let preview = make_parser(src)
+        .parse_preview(limits(None, Some(6)))
+        .unwrap();
+    assert_eq!(preview.commands.len(), 2);
+    assert_eq!(
+        preview.commands[1].to_string(),
+        "if true; then\n    bar\nfi"
+    );
+    assert!(preview.truncated);
+
+    let preview = make_parser(src)
+        .parse_preview(limits(None, Some(0)))
+        .unwrap();
+    assert!(preview.commands.is_empty());
+    assert!(preview.truncated);
+}
+
+#[test]
+fn test_preview_is_not_truncated_if_only_comments_remain() {
+    let src = "foo\nbar\n  # trailing comment\n\n# another\n";
+    let mut p = make_parser(src);
+    assert_eq!(
+        p.parse_preview(limits(Some(2), None)),
+        Ok(Preview {
+            commands: vec![cmd("foo"), cmd("bar")],
+            truncated: false,
+        })
+    );
+
+    let mut p = make_parser(src);
+    assert_eq!(
+        p.parse_preview(PreviewLimits::default()),
+        Ok(Preview {
+            commands: vec![cmd("foo"), cmd("bar")],
+            truncated: false,
+        })
+    );
+}
@@ -0,0 +1,363 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::{arithmetic_command, conditional_command, ToShellString};
+use conch_parser::ast::visit::VisitorMut;
+use conch_parser::ast::{
+    Command, CompoundCommandKind, Conditional, DefaultArithmetic, DefaultConditional,
+    DefaultRedirect, ListableCommand, PipeableCommand, Radix, Redirect, TopLevelCommand,
+};
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Returns the debug representation of some commands, without any of the
+/// spans or raw heredoc lines they contain, since those will differ after
+/// reformatting.
+fn without_spans(cmds: &[TopLevelCommand<String>]) -> String {
+    let debug = format!("{:?}", cmds);
+    let mut out = String::new();
+    let mut rest = &*debug;
+    while let Some(start) = rest.find("Span {") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let mut depth = 0;
+        for (i, c) in rest.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 1 => {
+                    rest = &rest[i + 1..];
+                    break;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    out.push_str(rest);
+
+    let debug = out;
+    let mut out = String::new();
+    let mut rest = &*debug;
+    while let Some(start) = rest.find("lines: [") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let mut quoted = false;
+        let mut escaped = false;
+        for (i, c) in rest.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = !quoted,
+                ']' if !quoted => {
+                    rest = &rest[i + 1..];
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn assert_round_trip(src: &str) {
+    let cmds = make_parser(src).parse_all().unwrap();
+    let printed = cmds.to_shell_string();
+
+    let reparsed = match make_parser(&printed).parse_all() {
+        Ok(cmds) => cmds,
+        Err(e) => panic!(
+            "failed to reparse {:?} printed from {:?}: {}",
+            printed, src, e
+        ),
+    };
+    assert_eq!(
+        without_spans(&reparsed),
+        without_spans(&cmds),
+        "{:?} was printed as {:?}",
+        src,
+        printed
+    );
+
+    // Printing should be idempotent
+    assert_eq!(reparsed.to_shell_string(), printed);
+}
+
+#[test]
+fn test_round_trip_commands() {
+    let sources = [
+        "foo bar baz",
+        "a && b || c; d & e",
+        "! a | b |& c",
+        "x=1 y= >out 2>&1 cmd <in 3<>rw 4>>app 5>|clob 6<&- arg",
+        "cmd &>out arg &>>log",
+        "f() { a; b & }",
+        "function f { a; }; function g() (b) >out; function h (c)",
+        "{ a; }; ( b ) >out",
+        "if a; b & then c; elif d; then e; else f; fi",
+        "while a; do b; done; until c & do d; done",
+        "for i in a b; do c; done; for i; do :; done; for i in; do :; done",
+        "case $x in a|b) c ;; (*) ;& 'q') d ;;& esac",
+        "case x in esac",
+    ];
+
+    for src in &sources {
+        assert_round_trip(src);
+    }
+}
+
+#[test]
+fn test_round_trip_words() {
+    let sources = [
+        "echo 'single' \"double $x ${y}z\" \\$esc ~/x a:b *.[ch] ?",
+        "echo $a.b ${a}b \"${a}b\" $1 ${10} $10 $@ $* $# $? $- $$ $! $0 $$1",
+        "echo ${x:-a} ${x-} ${x:=b} ${x?c} ${x:+d} ${#x} ${#@} ${$:-e} ${!%f}",
+        "echo ${x%a} ${x%%b} ${x#c} ${x##d} ${x/e} ${x//f} ${x:1:2} ${x,} ${x,,y} ${x^} ${x^^}",
+        "echo $(a; b &) `c` \"$(d)\" $( (e) ) $(< file) <(f) >(g)",
+        "echo &> >(a) 2>> >(b) < <(c)",
+        "echo $(( 1 + 2 * 3 )) $(( (1 + 2) * 3 )) $(( a - (b - c) )) $(())",
+        "echo $(( x = y ? z : -(-w), ++a, b--, !c, ~d, 2 ** 3 ** 4, e[1], + --f, - ++g ))",
+        "echo $(( 0x1f + 017 + 2#1010 + 64#a@_ + 36#Z ))",
+        "echo $(( 0XFF + 0017 + 10#0042 + 16#Ff + 0x ))",
+        "echo $(( a[i] = 1, a[i + 1]++, --a[0], -a[j]-- ))",
+        "echo $(( x <<= 2, a[i] *= y += 3, z = z % 4, (w |= 1) ? 1 : 0 ))",
+    ];
+
+    for src in &sources {
+        assert_round_trip(src);
+    }
+}
+
+#[test]
+fn test_round_trip_heredocs() {
+    let sources = [
+        "cat <<EOF\nliteral $ text\nEOF\n",
+        "cat <<'EOF'\n$not expanded\nEOF\n",
+        "cat <<EOF; echo after\n$x \\$y ${z}\nEOF\n",
+        "cat <<A 2<<B | grep x\na\nA\nb\nB\n",
+        "cat <<EOF\nEOF\nEOF_\nEOF\n",
+        "x=$(cat <<EOF\nin subst\nEOF\n)",
+        "if cat <<EOF; then :; fi\nbody\nEOF\n",
+        "cat <<EOF | (a; b) && (c)\nbody\nEOF\n",
+        "cat <<EOF $(case a in (b) c;; esac) | (case d in (e) ;; esac)\nbody\nEOF\n",
+        "f() {\n\tcat <<-EOF\n\tstripped\n\tEOF\n}",
+    ];
+
+    for src in &sources {
+        assert_round_trip(src);
+    }
+}
+
+#[test]
+fn test_heredocs_are_printed_as_written() {
+    let sources = [
+        "cat <<'END'\n$x\nEND\n",
+        "cat <<-EOF\n\t\t$x\n\ty\nEOF\n",
+        "cat <<EOF\n$x \\$y\nEOF\n",
+    ];
+
+    for src in &sources {
+        let cmds = make_parser(src).parse_all().unwrap();
+        assert_eq!(cmds.to_shell_string(), *src);
+    }
+
+    let cmds = make_parser("cat <<\\EOF\n$x\nEOF\n").parse_all().unwrap();
+    assert_eq!(cmds.to_shell_string(), "cat <<'EOF'\n$x\nEOF\n");
+}
+
+#[test]
+fn test_modified_heredocs_are_printed_from_their_body() {
+    struct ReplaceBodies(&'static str);
+
+    impl VisitorMut for ReplaceBodies {
+        fn visit_redirect(&mut self, redirect: &mut DefaultRedirect) {
+            if let Redirect::Heredoc(_, ref mut body, _) = *redirect {
+                *body = word(self.0);
+            }
+        }
+    }
+
+    let replaced = |body| {
+        let mut cmds = make_parser("cat <<-END\n\thello\n\tEND\n")
+            .parse_all()
+            .unwrap();
+        ReplaceBodies(body).visit_commands(&mut cmds);
+        cmds.to_shell_string()
+    };
+
+    assert_eq!(replaced("bye\n"), "cat <<-'END'\nbye\nEND\n");
+    assert_eq!(replaced("\tEND\nEND\n"), "cat <<'END_'\n\tEND\nEND\nEND_\n");
+}
+
+#[test]
+fn test_redirects_are_printed_in_their_original_positions() {
+    let sources = [
+        ">out cmd arg 2>&1\n",
+        "cmd arg >out 2>&1\n",
+        "cmd arg 2>&1 >out\n",
+        "x=1 2>>log y=2 <in cmd 1>out arg >|clob 3<>rw <&- >&2\n",
+        ">out\n",
+        "{\n    a\n} 2>&1 >>out\n",
+    ];
+
+    for src in &sources {
+        let cmds = make_parser(src).parse_all().unwrap();
+        assert_eq!(cmds.to_shell_string(), *src);
+    }
+}
+
+#[test]
+fn test_printed_layout() {
+    let cmds = make_parser(
+        "if true;then echo  a&&b;elif ! false;then x=1 y|z;else c&fi\n\
+         f(){ case $1 in a|b) y;; esac; }\n\
+         cat <<A >out\nbody\nA\n",
+    )
+    .parse_all()
+    .unwrap();
+
+    let expected = "if true; then\n    echo a && b\nelif ! false; then\n    x=1 y | z\nelse\n    c &\nfi\n\
+                    f() {\n    case $1 in\n        a | b)\n            y\n            ;;\n    esac\n}\n\
+                    cat <<A >out\nbody\nA\n";
+    assert_eq!(cmds.to_shell_string(), expected);
+}
+
+#[test]
+fn test_display() {
+    let cmd = make_parser("echo  $a.b;")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(cmd.to_string(), "echo $a.b");
+
+    let cmd = make_parser("cat<<X\nbody\nX\n")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(cmd.to_string(), "cat <<X\nbody\nX\n");
+
+    assert_eq!(word("foo").to_string(), "foo");
+    assert_eq!(cmd_args("echo", &["a", "b"]).to_string(), "echo a b");
+}
+
+#[test]
+fn test_function_definitions_are_printed_as_written() {
+    let sources = [
+        "foo() {\n    echo\n}\n",
+        "function foo {\n    echo\n}\n",
+        "function foo() {\n    echo\n}\n",
+        "function foo (\n    echo\n)\n",
+    ];
+
+    for src in &sources {
+        let cmds = make_parser(src).parse_all().unwrap();
+        assert_eq!(cmds.to_shell_string(), *src);
+    }
+}
+
+#[test]
+fn test_compound_assignments_are_printed_as_written() {
+    let src = "echo $(( x <<= 2 )) $(( x = x << 2 )) $(( a[i] ^= 1 ))";
+    let cmds = make_parser(src).parse_all().unwrap();
+    assert_eq!(cmds[0].to_shell_string(), src);
+}
+
+#[test]
+fn test_radix_literals_are_printed_as_written() {
+    use conch_parser::ast::Arithmetic::RadixLiteral;
+
+    let src = "echo $(( 10#0042 + 0XFF + 0017 + 0x0a + 16#Ff + 0x ))";
+    let cmds = make_parser(src).parse_all().unwrap();
+    assert_eq!(cmds[0].to_shell_string(), src);
+
+    // Literals whose text no longer matches are printed from their value
+    let changed: DefaultArithmetic = RadixLiteral(255, Radix::Hex, String::from("0X1"));
+    assert_eq!(changed.to_string(), "0xff");
+    let changed: DefaultArithmetic = RadixLiteral(8, Radix::Explicit(8), String::from("010"));
+    assert_eq!(changed.to_string(), "8#10");
+}
+
+#[test]
+fn test_standalone_arithmetic() {
+    use conch_parser::ast::Arithmetic::*;
+
+    fn var(name: &str) -> Box<DefaultArithmetic> {
+        Box::new(Var(String::from(name)))
+    }
+
+    let expr: DefaultArithmetic = Mult(
+        Box::new(Add(Box::new(Literal(1)), var("x"))),
+        Box::new(RadixLiteral(31, Radix::Hex, String::from("0x1f"))),
+    );
+    assert_eq!(expr.to_shell_string(), "(1 + x) * 0x1f");
+    assert_eq!(expr.to_string(), "(1 + x) * 0x1f");
+    assert_eq!(arithmetic_command(&expr), "(( (1 + x) * 0x1f ))");
+
+    let expr: DefaultArithmetic = Sub(var("a"), Box::new(Sub(var("b"), var("c"))));
+    assert_eq!(expr.to_string(), "a - (b - c)");
+
+    let expr: DefaultArithmetic = Pow(
+        Box::new(Pow(
+            Box::new(RadixLiteral(10, Radix::Explicit(2), String::from("2#1010"))),
+            Box::new(RadixLiteral(8, Radix::Octal, String::from("010"))),
+        )),
+        Box::new(Literal(4)),
+    );
+    assert_eq!(expr.to_string(), "(2#1010 ** 010) ** 4");
+}
+
+#[test]
+fn test_standalone_arithmetic_round_trip() {
+    let sources = [
+        "0XFF",
+        "010",
+        "8#017",
+        "10#0042",
+        "0XFF + 010 * (8#017 - 10#0042)",
+    ];
+
+    for src in &sources {
+        let expr = make_parser(src).arithmetic_substitution().unwrap();
+        assert_eq!(expr.to_shell_string(), *src);
+        assert_eq!(arithmetic_command(&expr), format!("(( {} ))", src));
+
+        let reparsed = make_parser(&expr.to_string()).arithmetic_substitution();
+        assert_eq!(reparsed, Ok(expr));
+    }
+}
+
+#[test]
+fn test_standalone_conditional() {
+    let cmd = make_parser("[[ ! ( -f a || $x == b* ) && ( -n $y ) ]]")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+
+    let cond = match cmd.0 {
+        Command::List(ref list) => match list.first {
+            ListableCommand::Single(PipeableCommand::Compound(ref cmd)) => match cmd.kind {
+                CompoundCommandKind::Conditional(ref cond) => cond.clone(),
+                ref kind => panic!("unexpected command: {:?}", kind),
+            },
+            ref cmd => panic!("unexpected command: {:?}", cmd),
+        },
+        ref cmd => panic!("unexpected command: {:?}", cmd),
+    };
+
+    assert_eq!(cond.to_shell_string(), "! ( -f a || $x == b* ) && -n $y");
+    assert_eq!(cond.to_string(), "! ( -f a || $x == b* ) && -n $y");
+    assert_eq!(
+        conditional_command(&cond),
+        "[[ ! ( -f a || $x == b* ) && -n $y ]]"
+    );
+
+    let cond: DefaultConditional = Conditional::And(
+        Box::new(Conditional::Or(
+            Box::new(Conditional::Word(word("a"))),
+            Box::new(Conditional::Word(word("b"))),
+        )),
+        Box::new(Conditional::Unary('z', word("c"))),
+    );
+    assert_eq!(conditional_command(&cond), "[[ ( a || b ) && -z c ]]");
+}
@@ -0,0 +1,128 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    Callback, CommandHooks, Event, EventLog, ProfilingBuilder, StringBuilder,
+};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::Parser;
+use std::thread;
+use std::time::Duration;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_profile_attributes_time_to_slow_callbacks() {
+    let src = "slow; fast; slow";
+    let delay = Duration::from_millis(5);
+
+    let mut hooks = CommandHooks::new(StringBuilder::new());
+    hooks.on("slow", |_, _| {
+        thread::sleep(delay);
+        Ok(())
+    });
+
+    let mut builder = ProfilingBuilder::new(hooks);
+    let cmds = Parser::with_builder(Lexer::new(src.chars()), &mut builder)
+        .parse_all()
+        .unwrap();
+    assert_eq!(cmds, make_parser(src).parse_all().unwrap());
+
+    let profile = builder.profile();
+    let simple = *profile.callback(Callback::SimpleCommand).unwrap();
+    assert_eq!(simple.calls, 3);
+    assert!(simple.total >= delay * 2);
+    assert!(simple.max >= delay);
+    assert_eq!(profile.callbacks[0].0, Callback::SimpleCommand);
+    assert_eq!(profile.callback(Callback::Word).unwrap().calls, 3);
+    assert_eq!(profile.callback(Callback::IfCommand), None);
+
+    assert_eq!(
+        profile.builder,
+        profile.callbacks.iter().map(|&(_, s)| s.total).sum()
+    );
+    assert!(profile.builder <= profile.elapsed);
+    assert_eq!(profile.parser(), profile.elapsed - profile.builder);
+    assert!(builder.timeline().is_empty());
+
+    let report = profile.to_string();
+    assert!(report.starts_with("total: "), "{}", report);
+    assert!(report.contains("\n  simple_command: "), "{}", report);
+    assert!(report.contains(" over 3 calls, at most "), "{}", report);
+}
+
+#[test]
+fn test_timeline_corresponds_to_event_log() {
+    let src = "for x in a b; do echo \"$x\" >out; done\nf() { g | h; }";
+
+    let mut builder = ProfilingBuilder::with_timeline(EventLog::new());
+    Parser::with_builder(Lexer::new(src.chars()), &mut builder)
+        .parse_all()
+        .unwrap();
+
+    let timeline = builder.timeline().to_vec();
+    let profile = builder.profile();
+    let log = builder.into_inner();
+
+    let callbacks = timeline.iter().map(|t| t.callback).collect::<Vec<_>>();
+    let expected = log
+        .events()
+        .iter()
+        .map(|event| match *event {
+            Event::CompleteCommand { .. } => Callback::CompleteCommand,
+            Event::AndOrList { .. } => Callback::AndOrList,
+            Event::Pipeline { .. } => Callback::Pipeline,
+            Event::SimpleCommand { .. } => Callback::SimpleCommand,
+            Event::ForCommand { .. } => Callback::ForCommand,
+            Event::BraceGroup { .. } => Callback::BraceGroup,
+            Event::CompoundCommandIntoPipeable { .. } => Callback::CompoundCommandIntoPipeable,
+            Event::FunctionDeclaration { .. } => Callback::FunctionDeclaration,
+            Event::Word { .. } => Callback::Word,
+            Event::Redirect { .. } => Callback::Redirect,
+            Event::EnterContext { .. } => Callback::EnterContext,
+            Event::ExitContext { .. } => Callback::ExitContext,
+            ref event => panic!("unexpected event: {:?}", event),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(callbacks, expected);
+
+    for pair in timeline.windows(2) {
+        assert!(pair[0].start + pair[0].elapsed <= pair[1].start);
+    }
+    let last = timeline.last().unwrap();
+    assert_eq!(profile.elapsed, last.start + last.elapsed);
+
+    let calls = profile
+        .callbacks
+        .iter()
+        .map(|&(_, s)| s.calls)
+        .sum::<usize>();
+    assert_eq!(calls, timeline.len());
+}
+
+#[test]
+fn test_reset_discards_measurements() {
+    let mut builder = ProfilingBuilder::with_timeline(StringBuilder::new());
+    Parser::with_builder(Lexer::new("echo foo".chars()), &mut builder)
+        .parse_all()
+        .unwrap();
+    assert!(!builder.timeline().is_empty());
+
+    builder.reset();
+    assert!(builder.timeline().is_empty());
+
+    let profile = builder.profile();
+    assert_eq!(profile.elapsed, Duration::default());
+    assert_eq!(profile.builder, Duration::default());
+    assert!(profile.callbacks.is_empty());
+}
+
+#[test]
+fn test_callback_names() {
+    let names = Callback::ALL
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names[0], "complete_command");
+    assert_eq!(names[12], "compound_command_into_pipeable");
+    assert_eq!(names[21], "exit_context");
+}
@@ -0,0 +1,284 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{DefaultBuilder, EventLog};
+use conch_parser::ast::{Command, TopLevelCommand, UnparsedSpan};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{ErrorGroup, ErrorRecovery, ParseError, Parser};
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn unparsed(
+    text: &str,
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+) -> TopLevelCommand<String> {
+    TopLevelCommand(Command::Unparsed(UnparsedSpan {
+        text: String::from(text),
+        start: src(start.0, start.1, start.2),
+        end: src(end.0, end.1, end.2),
+    }))
+}
+
+fn parse_recovering(source: &str) -> (Vec<TopLevelCommand<String>>, Vec<ParseError<void::Void>>) {
+    let mut p = make_parser(source);
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+
+    let mut cmds = Vec::new();
+    while let Some(cmd) = p.complete_command().unwrap() {
+        cmds.push(cmd);
+    }
+
+    (cmds, p.take_recovered_errors())
+}
+
+#[test]
+fn test_recovery_aborts_by_default() {
+    let mut p = make_parser("foo )\nbar");
+    assert_eq!(p.error_recovery(), ErrorRecovery::Abort);
+    assert_eq!(p.complete_command(), Ok(Some(cmd("foo"))));
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unexpected(Token::ParenClose, src(4, 1, 5)))
+    );
+}
+
+#[test]
+fn test_recovery_passes_through_failed_line() {
+    let (cmds, errors) = parse_recovering("foo\nbar  ) baz # comment\nqux\n");
+    assert_eq!(
+        cmds,
+        vec![
+            cmd("foo"),
+            cmd("bar"),
+            unparsed(") baz # comment\n", (9, 2, 6), (25, 3, 1)),
+            cmd("qux"),
+        ]
+    );
+    assert_eq!(
+        errors,
+        vec![ParseError::Unexpected(Token::ParenClose, src(9, 2, 6))]
+    );
+}
+
+#[test]
+fn test_recovery_includes_entire_failed_command() {
+    let source = "if true; then\n  echo 'a  b'\n  ) ;;\nfi\necho done";
+    let (cmds, errors) = parse_recovering(source);
+
+    assert_eq!(
+        cmds,
+        vec![
+            unparsed(
+                "if true; then\n  echo 'a  b'\n  ) ;;\n",
+                (0, 1, 1),
+                (35, 4, 1)
+            ),
+            cmd("fi"),
+            cmd_args("echo", &["done"]),
+        ]
+    );
+    assert_eq!(
+        errors,
+        vec![ParseError::Unexpected(Token::ParenClose, src(30, 3, 3))]
+    );
+}
+
+#[test]
+fn test_recovery_does_not_skip_line_after_unexpected_newline() {
+    let (cmds, errors) = parse_recovering("foo >\nbar\n");
+    assert_eq!(
+        cmds,
+        vec![unparsed("foo >\n", (0, 1, 1), (6, 2, 1)), cmd("bar")]
+    );
+    assert_eq!(
+        errors,
+        vec![ParseError::Unexpected(Token::Newline, src(5, 1, 6))]
+    );
+}
+
+#[test]
+fn test_recovery_at_eof() {
+    let (cmds, errors) = parse_recovering("foo; (bar\nbaz");
+    assert_eq!(
+        cmds,
+        vec![cmd("foo"), unparsed("(bar\nbaz", (5, 1, 6), (13, 2, 4))]
+    );
+    assert_eq!(
+        errors,
+        vec![ParseError::Unmatched(Token::ParenOpen, src(5, 1, 6))]
+    );
+}
+
+#[test]
+fn test_recovery_preserves_text_around_heredocs() {
+    let source = "cat <<EOF | )\nbody $x\nEOF\necho ok\n";
+    let (cmds, _) = parse_recovering(source);
+    assert_eq!(
+        cmds,
+        vec![
+            unparsed("cat <<EOF | )\nbody $x\nEOF\n", (0, 1, 1), (26, 4, 1)),
+            cmd_args("echo", &["ok"]),
+        ]
+    );
+}
+
+#[test]
+fn test_recovery_spans_match_source() {
+    let source = "foo 'a\tb' )\n  if ; then\nbar\n\tbaz \"q  \" | &\n{ x; } }\n";
+    let (cmds, errors) = parse_recovering(source);
+
+    let spans = cmds
+        .iter()
+        .filter_map(|cmd| match cmd.0 {
+            Command::Unparsed(ref span) => Some(span),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(spans.len(), 4);
+    assert_eq!(errors.len(), 4);
+    for span in spans {
+        assert_eq!(span.text, source[span.start.byte..span.end.byte]);
+    }
+}
+
+#[test]
+fn test_recovery_with_parse_all_and_event_log() {
+    let source = "foo\n) bar\nbaz";
+
+    let mut log = EventLog::new();
+    let mut p = Parser::with_builder(Lexer::new(source.chars()), &mut log);
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+    assert_eq!(p.parse_all().unwrap().len(), 3);
+    assert_eq!(p.take_recovered_errors().len(), 1);
+    assert!(p.take_recovered_errors().is_empty());
+
+    let mut p = make_parser(source);
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+    let expected = p.parse_all().unwrap();
+    assert_eq!(
+        expected,
+        vec![
+            cmd("foo"),
+            unparsed(") bar\n", (4, 2, 1), (10, 3, 1)),
+            cmd("baz")
+        ]
+    );
+
+    assert_eq!(log.replay(&mut DefaultBuilder::new()), Ok(expected));
+}
+
+#[test]
+fn test_parse_with_recovery_skips_to_next_separator() {
+    let mut p = make_parser("foo; bar ) baz & qux; a && ) b\nc");
+    let parsed = p.parse_with_recovery().unwrap();
+    assert_eq!(
+        parsed.commands,
+        vec![cmd("foo"), cmd("bar"), cmd("qux"), cmd("c")]
+    );
+    assert_eq!(
+        parsed.errors,
+        vec![
+            ParseError::Unexpected(Token::ParenClose, src(9, 1, 10)),
+            ParseError::Unexpected(Token::ParenClose, src(27, 1, 28)),
+        ]
+    );
+    assert!(p.is_exhausted());
+}
+
+#[test]
+fn test_parse_with_recovery_within_compound_command() {
+    let mut p = make_parser("if a; then b ); c; fi\nd");
+    let parsed = p.parse_with_recovery().unwrap();
+    assert_eq!(parsed.commands, vec![cmd("c"), cmd("fi"), cmd("d")]);
+    assert_eq!(
+        parsed.errors,
+        vec![ParseError::Unexpected(Token::ParenClose, src(13, 1, 14))]
+    );
+}
+
+#[test]
+fn test_parse_with_recovery_makes_progress_on_separators() {
+    let mut p = make_parser(";\n& ; foo;;\nbar");
+    let parsed = p.parse_with_recovery().unwrap();
+    assert_eq!(parsed.commands, vec![cmd("foo"), cmd("bar")]);
+    assert_eq!(
+        parsed.errors,
+        vec![
+            ParseError::Unexpected(Token::Semi, src(0, 1, 1)),
+            ParseError::Unexpected(Token::Amp, src(2, 2, 1)),
+            ParseError::Unexpected(Token::Semi, src(4, 2, 3)),
+            ParseError::Unexpected(Token::DSemi, src(9, 2, 8)),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_with_recovery_ignores_error_recovery_setting() {
+    let mut p = make_parser("foo )\necho ${x\n");
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+    let parsed = p.parse_with_recovery().unwrap();
+    assert_eq!(parsed.commands, vec![cmd("foo")]);
+    assert_eq!(parsed.errors.len(), 2);
+    assert_eq!(p.error_recovery(), ErrorRecovery::Passthrough);
+    assert!(p.take_recovered_errors().is_empty());
+}
+
+#[test]
+fn test_parse_with_recovery_relates_follow_on_errors() {
+    let cases = [
+        // The arms of a broken case header are parsed as separate commands
+        (
+            "case $x on\n  a) foo ;;\n  b) bar ;;\nesac\necho )",
+            vec![None, Some(0), Some(0), None],
+        ),
+        // Errors nested in a construct which was left open
+        (
+            "while true; do\n  echo )\n  echo )\ndone\necho )",
+            vec![None, Some(0), None],
+        ),
+        // The construct is closed on the same line as the error
+        ("{ echo; ) }\necho )", vec![None, None]),
+        // No construct is left open
+        ("echo ) ; echo ) ; echo `echo )`", vec![None, None, None]),
+    ];
+
+    for (s, related_to) in cases {
+        let parsed = make_parser(s).parse_with_recovery().unwrap();
+        assert_eq!(parsed.related_to, related_to, "{:?}", s);
+        assert_eq!(parsed.errors.len(), related_to.len(), "{:?}", s);
+    }
+}
+
+#[test]
+fn test_error_groups() {
+    let parsed = make_parser("for\n  echo )\n  echo )\n  echo )\ndone\necho )")
+        .parse_with_recovery()
+        .unwrap();
+    let errors = &parsed.errors;
+
+    assert_eq!(
+        parsed.error_groups(1),
+        vec![
+            ErrorGroup {
+                primary: &errors[0],
+                related: vec![&errors[1]],
+                suppressed: 2,
+            },
+            ErrorGroup {
+                primary: &errors[4],
+                related: vec![],
+                suppressed: 0,
+            },
+        ]
+    );
+    assert_eq!(parsed.error_groups(5)[0].related.len(), 3);
+    assert_eq!(
+        parsed.error_groups(1)[0].to_string(),
+        format!(
+            "{}\n  related: {}\n  ... and 2 more related errors",
+            errors[0], errors[1]
+        )
+    );
+}
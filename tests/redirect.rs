@@ -37,6 +37,24 @@ fn test_redirect_valid_close_with_whitespace() {
     );
 }
 
+#[test]
+fn test_redirect_valid_stdout_and_stderr() {
+    let mut p = make_parser("&>out");
+    assert_eq!(
+        Some(Ok(Redirect::WriteBoth(word("out")))),
+        p.redirect().unwrap()
+    );
+
+    let mut p = make_parser("&>>  log");
+    assert_eq!(
+        Some(Ok(Redirect::AppendBoth(word("log")))),
+        p.redirect().unwrap()
+    );
+
+    let correct = simple_command_with_redirect("foo", Redirect::WriteBoth(word("/dev/null")));
+    assert_eq!(correct, make_parser("foo &>/dev/null").simple_command().unwrap());
+}
+
 #[test]
 fn test_redirect_valid_start_with_dash_if_not_dup() {
     let path = word("-test");
@@ -0,0 +1,99 @@
+#![cfg(feature = "serde")]
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    ArcBuilder, ComplexWordKind, EventLog, Newline, SimpleWordKind, StringBuilder, WordKind,
+};
+use conch_parser::ast::{AtomicTopLevelCommand, TopLevelCommand, TopLevelWord};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::Parser;
+use std::sync::Arc;
+
+mod parse_support;
+use crate::parse_support::*;
+
+const SOURCE: &str = r#"
+foo() { echo "${1:-default}" $((x + 1)) >&2; }
+for x in a 'b' "c$d"; do foo "$x" | cat <(bar) && baz; done
+case $1 in a|b) :;& *) break ;; esac
+cat <<EOF
+heredoc $body
+EOF
+"#;
+
+#[test]
+fn test_ast_round_trips_through_json() {
+    let cmds = make_parser(SOURCE).parse_all().unwrap();
+
+    let json = serde_json::to_string(&cmds).unwrap();
+    let deserialized: Vec<TopLevelCommand<String>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, cmds);
+}
+
+#[test]
+fn test_atomic_ast_round_trips_through_json() {
+    let cmds = Parser::with_builder(Lexer::new(SOURCE.chars()), ArcBuilder::new())
+        .parse_all()
+        .unwrap();
+
+    let json = serde_json::to_string(&cmds).unwrap();
+    let deserialized: Vec<AtomicTopLevelCommand<Arc<String>>> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, cmds);
+}
+
+#[test]
+fn test_top_level_wrappers_are_transparent() {
+    assert_eq!(
+        serde_json::to_value(cmd("foo")).unwrap(),
+        serde_json::to_value(&cmd("foo").0).unwrap()
+    );
+
+    let json = r#"{"Single":{"Simple":{"Literal":"foo"}}}"#;
+    assert_eq!(serde_json::to_string(&word("foo")).unwrap(), json);
+    assert_eq!(
+        serde_json::from_str::<TopLevelWord<String>>(json).unwrap(),
+        word("foo")
+    );
+}
+
+#[test]
+fn test_builder_types_round_trip_through_json() {
+    let kind: ComplexWordKind<()> = ComplexWordKind::Concat(vec![
+        WordKind::Simple(SimpleWordKind::Literal(String::from("foo"))),
+        WordKind::SingleQuoted(String::from("bar")),
+        WordKind::DoubleQuoted(vec![SimpleWordKind::Star]),
+    ]);
+    let json = serde_json::to_string(&kind).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ComplexWordKind<()>>(&json).unwrap(),
+        kind
+    );
+
+    let newline = Newline(Some(String::from("#comment")));
+    assert_eq!(serde_json::to_string(&newline).unwrap(), r##""#comment""##);
+
+    // A newline without a comment is distinct from a missing newline
+    let newlines = vec![None, Some(Newline(None))];
+    let json = serde_json::to_string(&newlines).unwrap();
+    assert_eq!(json, r#"[null,""]"#);
+    assert_eq!(
+        serde_json::from_str::<Vec<Option<Newline>>>(&json).unwrap(),
+        newlines
+    );
+}
+
+#[test]
+fn test_event_log_round_trips_through_json() {
+    let mut log = EventLog::new();
+    Parser::with_builder(Lexer::new(SOURCE.chars()), &mut log)
+        .parse_all()
+        .unwrap();
+
+    let json = serde_json::to_string(&log).unwrap();
+    let deserialized: EventLog = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, log);
+    assert_eq!(
+        deserialized.replay(&mut StringBuilder::new()).unwrap(),
+        make_parser(SOURCE).parse_all().unwrap()
+    );
+}
@@ -108,3 +108,46 @@ fn test_simple_command_redirections_throughout_the_command() {
 
     assert_eq!(correct, p.simple_command().unwrap());
 }
+
+#[test]
+fn test_simple_command_redirection_order_relative_to_words_is_preserved() {
+    let before = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::Redirect(Write(None, word("out")))],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::CmdWord(word("arg")),
+            RedirectOrCmdWord::Redirect(DupWrite(Some(2), word("1"))),
+        ],
+    }));
+    let after = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::CmdWord(word("arg")),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+            RedirectOrCmdWord::Redirect(DupWrite(Some(2), word("1"))),
+        ],
+    }));
+    let swapped = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::CmdWord(word("arg")),
+            RedirectOrCmdWord::Redirect(DupWrite(Some(2), word("1"))),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+        ],
+    }));
+
+    assert_eq!(
+        before,
+        make_parser(">out cmd arg 2>&1").simple_command().unwrap()
+    );
+    assert_eq!(
+        after,
+        make_parser("cmd arg >out 2>&1").simple_command().unwrap()
+    );
+    assert_eq!(
+        swapped,
+        make_parser("cmd arg 2>&1 >out").simple_command().unwrap()
+    );
+}
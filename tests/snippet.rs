@@ -0,0 +1,189 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::analysis::{Diagnostic, Severity};
+use conch_parser::ast::Span;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{DefaultParser, ParseError, PositionTracking, SourcePos};
+use conch_parser::snippet::*;
+
+fn span(start: usize, end: usize) -> Span {
+    Span {
+        start: SourcePos::from_byte(start),
+        end: SourcePos::from_byte(end),
+    }
+}
+
+fn parse_err(source: &str) -> ParseError<void::Void> {
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    loop {
+        match parser.complete_command() {
+            Ok(Some(_)) => {}
+            Ok(None) => panic!("no error in {:?}", source),
+            Err(e) => return e,
+        }
+    }
+}
+
+#[test]
+fn test_render_snippet_multiple_spans_on_one_line() {
+    let annotations = [
+        Annotation::new(span(0, 3), "first"),
+        Annotation::new(span(6, 9), ""),
+        Annotation::new(span(4, 5), "second"),
+    ];
+    assert_eq!(
+        render_annotations("foo | bar", &annotations),
+        " --> 1:1
+  |
+1 | foo | bar
+  | ^^^ first
+  |       ^^^
+  |     ^ second
+"
+    );
+}
+
+#[test]
+fn test_render_snippet_multi_line_span() {
+    let source = "{\n  foo\n}\nbar\n";
+    assert_eq!(
+        render_snippet(source, span(0, 10), "a brace group"),
+        " --> 1:1
+  |
+1 | {
+  | ^
+2 |   foo
+  | ^^^^^
+3 | }
+  | ^ a brace group
+"
+    );
+}
+
+#[test]
+fn test_render_snippet_span_ending_with_newline_stays_on_its_line() {
+    assert_eq!(
+        render_snippet("foo\nbar\n", span(0, 4), "line"),
+        " --> 1:1\n  |\n1 | foo\n  | ^^^ line\n"
+    );
+}
+
+#[test]
+fn test_render_snippet_elides_distant_lines() {
+    let source = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    let annotations = [
+        Annotation::new(span(0, 1), "one"),
+        Annotation::new(span(4, 5), "three"),
+        Annotation::new(span(18, 19), "ten"),
+    ];
+    assert_eq!(
+        render_annotations(source, &annotations),
+        "  --> 1:1
+   |
+ 1 | a
+   | ^ one
+ 2 | b
+ 3 | c
+   | ^ three
+...
+10 | j
+   | ^ ten
+"
+    );
+}
+
+#[test]
+fn test_render_snippet_expands_tabs_and_counts_chars() {
+    assert_eq!(
+        render_snippet("\techo é $x", span(9, 11), "var"),
+        " --> 1:10\n  |\n1 |     echo é $x\n  |            ^^ var\n"
+    );
+}
+
+#[test]
+fn test_render_snippet_empty_span_and_end_of_source() {
+    assert_eq!(
+        render_snippet("foo", span(3, 3), "here"),
+        " --> 1:4\n  |\n1 | foo\n  |    ^ here\n"
+    );
+    assert_eq!(
+        render_snippet("foo", span(10, 20), "clamped"),
+        " --> 1:4\n  |\n1 | foo\n  |    ^ clamped\n"
+    );
+}
+
+#[test]
+fn test_render_parse_errors() {
+    let source = "echo foo\necho $(bar";
+    assert_eq!(
+        render_parse_error(source, &parse_err(source)),
+        "error: unmatched `(` starting on line 2:7
+ --> 2:7
+  |
+2 | echo $(bar
+  |       ^ unmatched `(`
+"
+    );
+
+    let source = "foo |";
+    assert_eq!(
+        render_parse_error(source, &parse_err(source)),
+        "error: unexpected end of input
+ --> 1:6
+  |
+1 | foo |
+  |      ^ unexpected end of input
+"
+    );
+
+    let source = "foo ) bar";
+    assert_eq!(
+        render_parse_error(source, &parse_err(source)),
+        "error: found unexpected token on line 1:5: )
+ --> 1:5
+  |
+1 | foo ) bar
+  |     ^ unexpected token
+"
+    );
+
+    let err = ParseError::Custom("builder failed");
+    assert_eq!(render_parse_error("foo", &err), "error: builder failed\n");
+}
+
+#[test]
+fn test_render_parse_error_with_byte_offsets_only() {
+    let source = "echo foo\necho ${x";
+    let mut parser = DefaultParser::new(Lexer::new(source.chars()));
+    parser.set_position_tracking(PositionTracking::ByteOffsets);
+    parser.complete_command().unwrap();
+    let err = parser.complete_command().unwrap_err();
+
+    let rendered = render_parse_error(source, &err);
+    assert!(
+        rendered.ends_with(" --> 2:7\n  |\n2 | echo ${x\n  |       ^ unmatched `{`\n"),
+        "{}",
+        rendered
+    );
+}
+
+#[test]
+fn test_render_diagnostic() {
+    let diagnostic = Diagnostic::new(Severity::Warning, "some-check", "something is off");
+    assert_eq!(
+        render_diagnostic(
+            "rm -rf $dir/*",
+            &diagnostic,
+            &[Annotation::new(span(7, 11), "may be empty")]
+        ),
+        "warning[some-check]: something is off
+ --> 1:8
+  |
+1 | rm -rf $dir/*
+  |        ^^^^ may be empty
+"
+    );
+    assert_eq!(
+        render_diagnostic("foo", &diagnostic, &[]),
+        "warning[some-check]: something is off\n"
+    );
+}
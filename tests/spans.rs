@@ -0,0 +1,189 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{Event, EventLog};
+use conch_parser::ast::Span;
+use conch_parser::parse::SourcePos;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Parses the source and returns the kind of every node built, along with
+/// the source text its span covers, in the order they were built.
+fn spans(src: &str) -> Vec<(&'static str, &str)> {
+    let mut log = EventLog::new();
+    parse_with(src, &mut log);
+
+    log.into_events()
+        .into_iter()
+        .filter_map(|event| {
+            let (kind, span) = match event {
+                Event::CompleteCommand { span, .. } => ("command", span),
+                Event::AndOrList { span, .. } => ("and_or", span),
+                Event::Pipeline { span, .. } => ("pipeline", span),
+                Event::SimpleCommand { span, .. } => ("simple", span),
+                Event::BraceGroup { span, .. } => ("brace", span),
+                Event::Subshell { span, .. } => ("subshell", span),
+                Event::LoopCommand { span, .. } => ("loop", span),
+                Event::IfCommand { span, .. } => ("if", span),
+                Event::ForCommand { span, .. } => ("for", span),
+                Event::CaseCommand { span, .. } => ("case", span),
+                Event::ConditionalCommand { span, .. } => ("conditional", span),
+                Event::ArithmeticCommand { span, .. } => ("arithmetic", span),
+                Event::FunctionDeclaration { span, .. } => ("function", span),
+                Event::Arithmetic { span, .. } => ("arith", span),
+                Event::ParameterSubstitution { span, .. } => ("subst", span),
+                Event::Word { span, .. } => ("word", span),
+                Event::Redirect { span, .. } => ("redirect", span),
+                Event::CompoundCommandIntoPipeable { .. }
+                | Event::Comments { .. }
+                | Event::Unparsed { .. }
+                | Event::EnterContext { .. }
+                | Event::ExitContext { .. } => return None,
+            };
+            Some((kind, &src[span.start.byte..span.end.byte]))
+        })
+        .collect()
+}
+
+fn texts<'a>(spans: &[(&'static str, &'a str)], kind: &str) -> Vec<&'a str> {
+    spans
+        .iter()
+        .filter(|&&(k, _)| k == kind)
+        .map(|&(_, text)| text)
+        .collect()
+}
+
+#[test]
+fn test_simple_command_spans() {
+    let spans = spans("foo=bar  echo  hello >out 2>&1;  baz &\n");
+
+    assert_eq!(
+        texts(&spans, "word"),
+        vec!["bar", "echo", "hello", "out", "1", "baz"]
+    );
+    assert_eq!(texts(&spans, "redirect"), vec![">out", "2>&1"]);
+    assert_eq!(
+        texts(&spans, "simple"),
+        vec!["foo=bar  echo  hello >out 2>&1", "baz"]
+    );
+    assert_eq!(
+        texts(&spans, "command"),
+        vec!["foo=bar  echo  hello >out 2>&1;", "baz &"]
+    );
+}
+
+#[test]
+fn test_list_spans_exclude_trailing_comments() {
+    let spans = spans("  ! a | b && c ||\n  d # comment\n");
+
+    assert_eq!(texts(&spans, "pipeline"), vec!["! a | b", "c", "d"]);
+    assert_eq!(texts(&spans, "and_or"), vec!["! a | b && c ||\n  d"]);
+    assert_eq!(texts(&spans, "command"), vec!["! a | b && c ||\n  d"]);
+}
+
+#[test]
+fn test_compound_command_spans_include_redirects() {
+    let src = "\
+if true; then :; fi >out
+{ echo; } 2>&1
+(cd /tmp)
+for i in a b; do :; done
+while false; do :; done
+case x in y) :;; esac <in
+";
+    let spans = spans(src);
+
+    assert_eq!(texts(&spans, "if"), vec!["if true; then :; fi >out"]);
+    assert_eq!(texts(&spans, "brace"), vec!["{ echo; } 2>&1"]);
+    assert_eq!(texts(&spans, "subshell"), vec!["(cd /tmp)"]);
+    assert_eq!(texts(&spans, "for"), vec!["for i in a b; do :; done"]);
+    assert_eq!(texts(&spans, "loop"), vec!["while false; do :; done"]);
+    assert_eq!(texts(&spans, "case"), vec!["case x in y) :;; esac <in"]);
+}
+
+#[test]
+fn test_function_declaration_spans() {
+    let spans = spans("foo() { echo; }\nfunction bar (baz) >out\n");
+
+    assert_eq!(
+        texts(&spans, "function"),
+        vec!["foo() { echo; }", "function bar (baz) >out"]
+    );
+    assert_eq!(texts(&spans, "subshell"), vec!["(baz) >out"]);
+}
+
+#[test]
+fn test_heredoc_spans() {
+    let spans = spans("cat <<EOF | wc -l\nfoo $bar\nEOF\necho\n");
+
+    // The body is a word of its own, and not part of the redirect or command
+    assert_eq!(
+        texts(&spans, "word"),
+        vec!["cat", "foo $bar\n", "wc", "-l", "echo"]
+    );
+    assert_eq!(texts(&spans, "redirect"), vec!["<<EOF"]);
+    assert_eq!(texts(&spans, "simple"), vec!["cat <<EOF", "wc -l", "echo"]);
+    assert_eq!(texts(&spans, "pipeline"), vec!["cat <<EOF | wc -l", "echo"]);
+}
+
+#[test]
+fn test_spans_exclude_trailing_line_continuations() {
+    let spans = spans("foo \\\n  bar \\\n\nbaz");
+
+    assert_eq!(texts(&spans, "simple"), vec!["foo \\\n  bar", "baz"]);
+}
+
+#[test]
+fn test_spans_within_substitutions() {
+    let spans = spans("echo \"$(a b)\" `c d`");
+
+    // Words within substitutions are built before the words containing them
+    assert_eq!(
+        texts(&spans, "word"),
+        vec!["echo", "a", "b", "\"$(a b)\"", "c", "d", "`c d`"]
+    );
+    assert_eq!(
+        texts(&spans, "simple"),
+        vec!["a b", "c d", "echo \"$(a b)\" `c d`"]
+    );
+}
+
+#[test]
+fn test_arithmetic_spans() {
+    let spans = spans("echo $(( 1 + x ))\"$((y))\"${z:-$(( ))}");
+
+    assert_eq!(
+        texts(&spans, "arith"),
+        vec!["$(( 1 + x ))", "$((y))", "$(( ))"]
+    );
+}
+
+#[test]
+fn test_spans_track_lines_and_columns() {
+    let mut log = EventLog::new();
+    parse_with("\n  foo bar\n", &mut log);
+
+    let simple = log
+        .events()
+        .iter()
+        .find_map(|event| match *event {
+            Event::SimpleCommand { span, .. } => Some(span),
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(
+        simple,
+        Span {
+            start: SourcePos {
+                byte: 3,
+                line: 2,
+                col: 3
+            },
+            end: SourcePos {
+                byte: 10,
+                line: 2,
+                col: 10
+            },
+        }
+    );
+}
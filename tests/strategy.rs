@@ -0,0 +1,79 @@
+#![cfg(feature = "proptest")]
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::strategy;
+use conch_parser::ast::{
+    ComplexWord, DefaultWord, SimpleWord, TopLevelCommand, TopLevelWord, Word,
+};
+use conch_parser::parse::Dialect;
+use proptest::prelude::*;
+
+/// Debug representations of the nodes which can only be produced by bash extensions.
+const BASH_ONLY: &[&str] = &[
+    "pipe_stderr: true",
+    "ReadFile(",
+    "ReplaceString(",
+    "ReplaceStringAll(",
+    "Substring(",
+    "Lowercase(",
+    "Uppercase(",
+    "ProcessRead(",
+    "ProcessWrite(",
+    "terminator: FallThrough",
+    "terminator: Continue",
+    "Index(",
+    "Explicit(",
+];
+
+fn is_literal(word: &DefaultWord) -> bool {
+    matches!(word, Word::Simple(SimpleWord::Literal(_)))
+}
+
+proptest! {
+    #[test]
+    fn test_names_are_not_reserved_words(name in strategy::name()) {
+        prop_assert!(!["if", "then", "fi", "do", "done", "in", "case", "esac"].contains(&&*name));
+        prop_assert!(!name.starts_with(|c: char| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_posix_commands_have_no_bash_extensions(cmd in strategy::command(Dialect::Posix)) {
+        let debug = format!("{:?}", cmd);
+        for ext in BASH_ONLY {
+            prop_assert!(!debug.contains(ext), "{} found in {}", ext, debug);
+        }
+    }
+
+    #[test]
+    fn test_concatenated_words_are_normalized(word in strategy::word(Dialect::Bash)) {
+        let TopLevelWord(word) = word;
+        if let ComplexWord::Concat(parts) = word {
+            prop_assert!(parts.len() >= 2);
+            for pair in parts.windows(2) {
+                prop_assert!(!(is_literal(&pair[0]) && is_literal(&pair[1])));
+            }
+        }
+    }
+}
+
+proptest! {
+    // Printing and reparsing large trees is slow, especially without optimizations
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn test_commands_round_trip(cmd in any::<TopLevelCommand<String>>()) {
+        if let Err(violation) = conch_parser::testing::check_round_trip(&[cmd]) {
+            return Err(TestCaseError::fail(violation.to_string()));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn test_posix_commands_round_trip(
+        cmd in any_with::<TopLevelCommand<String>>(Dialect::Posix),
+    ) {
+        if let Err(violation) = conch_parser::testing::check_round_trip(&[cmd]) {
+            return Err(TestCaseError::fail(violation.to_string()));
+        }
+    }
+}
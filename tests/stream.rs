@@ -0,0 +1,231 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::ArcBuilder;
+use conch_parser::lexer::{Lexer, ReadChars};
+use conch_parser::parse::{DefaultParser, ParseError, Parser};
+use conch_parser::token::Token;
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// A reader which returns at most one byte per read, and
+/// is interrupted before every successful read.
+struct Trickle<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        }
+
+        match self.data.split_first() {
+            Some((&b, rest)) if !buf.is_empty() => {
+                buf[0] = b;
+                self.data = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+/// A reader which returns at most one byte per read,
+/// and keeps track of how many bytes were read.
+struct Counting<'a> {
+    data: &'a [u8],
+    read: Rc<Cell<usize>>,
+}
+
+impl Read for Counting<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = (&self.data[..self.data.len().min(1)]).read(buf)?;
+        self.data = &self.data[n..];
+        self.read.set(self.read.get() + n);
+        Ok(n)
+    }
+}
+
+/// A reader which fails after yielding its data.
+struct Failing<'a>(&'a [u8]);
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(io::Error::other("broken pipe"));
+        }
+
+        let n = self.0.read(buf)?;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_read_chars_decodes_across_read_boundaries() {
+    let source = "echo h\u{e9}llo \u{1F600}";
+    let chars = ReadChars::new(Trickle {
+        data: source.as_bytes(),
+        interrupt: false,
+    });
+
+    assert_eq!(chars.collect::<String>(), source);
+}
+
+#[test]
+fn test_read_chars_replaces_invalid_utf8() {
+    let bytes: &[u8] = b"a\xffb\xe2\x82";
+    let chars = ReadChars::new(bytes);
+    assert_eq!(chars.collect::<String>(), "a\u{FFFD}b\u{FFFD}");
+}
+
+#[test]
+fn test_read_chars_maps_offsets_to_original_input() {
+    let bytes: &[u8] = b"a\xffb\xe2\x82c\xe2\x82\xacd\xef\xbf\xbde";
+    let mut chars = ReadChars::new(bytes);
+    assert_eq!(
+        chars.by_ref().collect::<String>(),
+        "a\u{FFFD}b\u{FFFD}c\u{20AC}d\u{FFFD}e"
+    );
+
+    let decoded = [0, 1, 4, 5, 8, 9, 12, 13, 16];
+    let original = [0, 1, 2, 3, 5, 6, 9, 10, 13];
+    for (&decoded, &original) in decoded.iter().zip(&original) {
+        assert_eq!(chars.source_offset(decoded), original, "{}", decoded);
+    }
+}
+
+#[test]
+fn test_read_chars_retains_reader_error() {
+    let mut chars = ReadChars::new(Failing(b"foo"));
+    let cmds = DefaultParser::new(Lexer::new(chars.by_ref()))
+        .parse_all()
+        .unwrap();
+
+    assert_eq!(cmds, vec![cmd("foo")]);
+    assert_eq!(
+        chars.error().map(io::Error::kind),
+        Some(io::ErrorKind::Other)
+    );
+    assert!(chars.take_error().is_some());
+    assert!(chars.error().is_none());
+}
+
+#[test]
+fn test_parse_all_stops_at_shebang_of_next_document() {
+    let source = "#!/bin/sh\nfoo\n\n#!/bin/bash\nbar; baz\n  #!not a shebang\nqux\n";
+    let mut p = make_parser(source);
+
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo")]));
+    assert!(!p.is_exhausted());
+    assert_eq!(p.pos(), src(15, 4, 1));
+
+    assert_eq!(p.parse_all(), Ok(vec![cmd("bar"), cmd("baz"), cmd("qux")]));
+    assert!(p.is_exhausted());
+    assert_eq!(p.parse_all(), Ok(vec![]));
+}
+
+#[test]
+fn test_parse_all_shebang_within_document_start_is_not_a_boundary() {
+    let mut p = make_parser("\n#!/bin/sh\n# comment\n#!/bin/sh\nfoo");
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo")]));
+    assert!(p.is_exhausted());
+}
+
+#[test]
+fn test_parse_all_reset_pos_restarts_line_numbering() {
+    let source = "#!/bin/sh\nfoo\n#!/bin/sh\nbar\n)";
+    let mut p = make_parser(source);
+
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo")]));
+    p.reset_pos();
+    assert_eq!(p.pos(), src(0, 1, 1));
+    assert_eq!(
+        p.parse_all(),
+        Err(ParseError::Unexpected(Token::ParenClose, src(14, 3, 1)))
+    );
+}
+
+#[test]
+fn test_parse_all_from_reader_with_custom_builder() {
+    let source = "#!/bin/sh\necho a\n#!/bin/sh\necho b\necho c\n";
+    let mut chars = ReadChars::new(Trickle {
+        data: source.as_bytes(),
+        interrupt: false,
+    });
+
+    let mut p = Parser::with_builder(Lexer::new(chars.by_ref()), ArcBuilder::new());
+    let mut docs = Vec::new();
+    while !p.is_exhausted() {
+        docs.push(p.parse_all().unwrap().len());
+        p.reset_pos();
+    }
+
+    assert_eq!(docs, vec![1, 2]);
+}
+
+#[test]
+fn test_from_reader_parses_incrementally() {
+    let source = "echo a\necho b; echo c\n";
+    let read = Rc::new(Cell::new(0));
+    let mut p = DefaultParser::from_reader(Counting {
+        data: source.as_bytes(),
+        read: read.clone(),
+    });
+
+    assert_eq!(p.complete_command(), Ok(Some(cmd_args("echo", &["a"]))));
+    assert!(read.get() < source.len());
+
+    assert_eq!(
+        p.into_iter().collect::<Result<Vec<_>, _>>(),
+        Ok(vec![cmd_args("echo", &["b"]), cmd_args("echo", &["c"])])
+    );
+    assert_eq!(read.get(), source.len());
+}
+
+#[test]
+fn test_from_reader_retains_reader_error() {
+    let mut p = DefaultParser::from_reader(Failing(b"foo; bar"));
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo"), cmd("bar")]));
+    assert_eq!(
+        p.read_error().map(io::Error::kind),
+        Some(io::ErrorKind::Other)
+    );
+    assert!(p.take_read_error().is_some());
+    assert!(p.read_error().is_none());
+
+    let mut p = DefaultParser::from_reader(Trickle {
+        data: "foo\nbar".as_bytes(),
+        interrupt: false,
+    });
+    assert_eq!(p.parse_all(), Ok(vec![cmd("foo"), cmd("bar")]));
+    assert!(p.read_error().is_none());
+}
+
+#[test]
+fn test_commands_parses_incrementally_without_consuming_parser() {
+    let source = "echo a\necho b; )\necho c\n";
+    let read = Rc::new(Cell::new(0));
+    let mut p = DefaultParser::from_reader(Counting {
+        data: source.as_bytes(),
+        read: read.clone(),
+    });
+
+    let mut commands = p.commands();
+    assert_eq!(commands.next(), Some(Ok(cmd_args("echo", &["a"]))));
+    assert!(read.get() < source.len());
+    assert_eq!(commands.next(), Some(Ok(cmd_args("echo", &["b"]))));
+    assert_eq!(
+        commands.next(),
+        Some(Err(ParseError::Unexpected(Token::ParenClose, src(15, 2, 9))))
+    );
+    assert_eq!(commands.next(), None);
+
+    // The rest of the source is still available to the parser
+    assert!(read.get() < source.len());
+    assert_eq!(p.pos(), src(16, 2, 10));
+}
@@ -2,7 +2,10 @@
 use conch_parser::ast::ComplexWord::*;
 use conch_parser::ast::Parameter::*;
 use conch_parser::ast::ParameterSubstitution::*;
-use conch_parser::ast::{RedirectOrCmdWord, SimpleCommand, SimpleWord, TopLevelWord, Word};
+use conch_parser::ast::{
+    Redirect, RedirectOrCmdWord, RedirectOrEnvVar, SimpleCommand, SimpleWord, TopLevelWord, Word,
+};
+use conch_parser::parse::Dialect;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -602,7 +605,7 @@ fn test_parameter_substitution_command_close_paren_need_not_be_followed_by_word_
 #[test]
 fn test_parameter_substitution_invalid() {
     let cases = vec![
-        ("$(( x", UnexpectedEOF),
+        ("$(( x", Unmatched(Token::ParenOpen, src(1, 1, 2))),
         ("${foo", Unmatched(Token::CurlyOpen, src(1, 1, 2))),
         (
             "${ foo}",
@@ -752,3 +755,170 @@ fn test_parameter_substitution_special_tokens_in_words_become_literals() {
     let mut p = make_parser("${foo:-#(bar);&|&&||;; << >> <& >& <<- \\\n\n\t}");
     assert_eq!(Ok(correct), p.parameter());
 }
+
+#[test]
+fn test_parameter_substitution_read_file() {
+    let cases = vec![
+        ("$(< foo)", word("foo")),
+        ("$(<foo)", word("foo")),
+        ("$( <  foo.txt \n )", word("foo.txt")),
+        ("$(< \"$dir/a b\")", double_quoted_subst_path()),
+        ("$(< 'a)b')", single_quoted("a)b")),
+        (
+            "$(< $(echo foo))",
+            word_subst(Command(vec![cmd_args("echo", &["foo"])])),
+        ),
+    ];
+
+    for (src, path) in cases {
+        let correct = word_subst(ReadFile(path));
+        assert_eq!(Ok(correct), make_parser(src).parameter(), "source: {}", src);
+    }
+}
+
+fn double_quoted_subst_path() -> TopLevelWord<String> {
+    TopLevelWord(Single(Word::DoubleQuoted(vec![
+        SimpleWord::Param(Var("dir".to_owned())),
+        SimpleWord::Literal("/a b".to_owned()),
+    ])))
+}
+
+#[test]
+fn test_parameter_substitution_read_file_requires_single_redirect() {
+    let read = |path: &str| Redirect::Read(None, word(path));
+    let redirect_only = |path: &str| {
+        cmd_from_simple(SimpleCommand {
+            redirects_or_env_vars: vec![RedirectOrEnvVar::Redirect(read(path))],
+            redirects_or_cmd_words: vec![],
+        })
+    };
+
+    let cases = vec![
+        (
+            "$(< foo cat)",
+            vec![cmd_from_simple(SimpleCommand {
+                redirects_or_env_vars: vec![RedirectOrEnvVar::Redirect(read("foo"))],
+                redirects_or_cmd_words: vec![RedirectOrCmdWord::CmdWord(word("cat"))],
+            })],
+        ),
+        (
+            "$(< foo; < bar)",
+            vec![redirect_only("foo"), redirect_only("bar")],
+        ),
+        (
+            "$(cat < foo)",
+            vec![cmd_from_simple(SimpleCommand {
+                redirects_or_env_vars: vec![],
+                redirects_or_cmd_words: vec![
+                    RedirectOrCmdWord::CmdWord(word("cat")),
+                    RedirectOrCmdWord::Redirect(read("foo")),
+                ],
+            })],
+        ),
+    ];
+
+    for (src, cmds) in cases {
+        let correct = word_subst(Command(cmds));
+        assert_eq!(Ok(correct), make_parser(src).parameter(), "source: {}", src);
+    }
+}
+
+#[test]
+fn test_parameter_substitution_read_file_not_recognized_in_posix_dialect() {
+    let mut p = make_parser("$(< foo)");
+    p.set_dialect(Dialect::Posix);
+
+    let correct = word_subst(Command(vec![cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::Redirect(Redirect::Read(
+            None,
+            word("foo"),
+        ))],
+        redirects_or_cmd_words: vec![],
+    })]));
+    assert_eq!(Ok(correct), p.parameter());
+}
+
+#[test]
+fn test_parameter_substitution_command_starting_with_subshell() {
+    use conch_parser::ast::{AndOrList, Command, ListableCommand, TopLevelCommand};
+    use conch_parser::ast::{CompoundCommand, CompoundCommandKind, PipeableCommand};
+
+    let subshell = |cmds| {
+        TopLevelCommand(Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Compound(Box::new(CompoundCommand {
+                kind: CompoundCommandKind::Subshell(cmds),
+                io: vec![],
+                io_span: None,
+            }))),
+            rest: vec![],
+        }))
+    };
+
+    // Like bash, `$((` only starts arithmetic if closed by an adjacent `))`
+    for s in &["$((echo a) )", "$((echo a)\n)", "$(( echo a ) )"] {
+        let correct = word_subst(Command(vec![subshell(vec![cmd_args("echo", &["a"])])]));
+        assert_eq!(make_parser(s).parameter(), Ok(correct), "{:?}", s);
+    }
+
+    let quoted = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(single_quoted("))")),
+        ],
+    });
+    let correct = word_subst(Command(vec![subshell(vec![quoted])]));
+    assert_eq!(make_parser("$((echo '))') )").parameter(), Ok(correct));
+}
+
+#[test]
+fn test_process_substitution() {
+    let read = || ProcessRead(vec![cmd_args("foo", &["bar"])]);
+    let write = || ProcessWrite(vec![cmd("baz"), cmd("qux")]);
+
+    let correct = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("diff")),
+            RedirectOrCmdWord::CmdWord(word_subst(read())),
+            RedirectOrCmdWord::CmdWord(word_subst(write())),
+            RedirectOrCmdWord::CmdWord(TopLevelWord(Concat(vec![
+                lit("a"),
+                subst(read()),
+                lit("b"),
+            ]))),
+            RedirectOrCmdWord::Redirect(Redirect::Read(None, word_subst(read()))),
+            RedirectOrCmdWord::Redirect(Redirect::Write(Some(2), word_subst(write()))),
+        ],
+    });
+
+    let src = "diff <(foo bar) >(baz; qux) a<(foo bar)b < <(foo bar) 2> >(baz\nqux)";
+    assert_eq!(Ok(correct), make_parser(src).complete_command().map(Option::unwrap));
+}
+
+#[test]
+fn test_process_substitution_requires_adjacent_paren() {
+    let correct = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(TopLevelWord(Single(Word::DoubleQuoted(vec![
+                SimpleWord::Literal(String::from("<(foo)")),
+            ])))),
+        ],
+    });
+    assert_eq!(Ok(Some(correct)), make_parser("echo \"<(foo)\"").complete_command());
+
+    assert_eq!(
+        Err(Unexpected(Token::ParenOpen, src(6, 1, 7))),
+        make_parser("cat < (foo)").complete_command()
+    );
+}
+
+#[test]
+fn test_process_substitution_unmatched() {
+    assert_eq!(
+        Err(Unmatched(Token::ParenOpen, src(5, 1, 6))),
+        make_parser("cat <(foo").complete_command()
+    );
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "testing")]
+#![deny(rust_2018_idioms)]
+use conch_parser::assert_parses_same;
+use conch_parser::ast::builder::{CommandHooks, StringBuilder};
+use conch_parser::testing::{assert_round_trip, check_round_trip};
+use conch_parser::testing::{check_invariants, debug_diff, parse, NESTED_CORPUS};
+use conch_parser::testing::{load_corpus, replay, RegressionCase, Violation};
+use std::fs;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_testing_parse() {
+    assert_eq!(parse("foo; bar"), Ok(vec![cmd("foo"), cmd("bar")]));
+    assert!(parse("foo )").is_err());
+}
+
+#[test]
+fn test_assert_parses_same_ignores_trivia() {
+    assert_parses_same!("foo  bar;baz", "foo bar # comment\n\n\tbaz");
+    assert_parses_same!("if true; then :; fi", "if true\nthen\n  :\nfi\n",);
+    assert_parses_same!("a | b", "a | b # c", "pipelines with {} comments", "different");
+}
+
+#[test]
+#[should_panic(expected = "snippets do not parse the same: custom message")]
+fn test_assert_parses_same_fails_on_different_asts() {
+    assert_parses_same!("foo bar", "foo 'bar'", "custom {}", "message");
+}
+
+#[test]
+#[should_panic(expected = "failed to parse right snippet")]
+fn test_assert_parses_same_fails_on_parse_error() {
+    assert_parses_same!("foo", "foo )");
+}
+
+#[test]
+fn test_debug_diff() {
+    assert_eq!(debug_diff(&vec![1, 2], &vec![1, 2]), None);
+    assert_eq!(
+        debug_diff(&vec![1, 2, 3], &vec![1, 4, 3]).unwrap(),
+        "  [\n      1,\n-     2,\n+     4,\n      3,\n  ]\n"
+    );
+}
+
+#[test]
+fn test_nested_corpus_parses() {
+    for case in NESTED_CORPUS {
+        match parse(case.source) {
+            Ok(cmds) => assert_eq!(cmds.len(), case.commands, "{}", case.name),
+            Err(e) => panic!("failed to parse {}: {}", case.name, e),
+        }
+    }
+
+    let mut names = NESTED_CORPUS.iter().map(|c| c.name).collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), NESTED_CORPUS.len());
+}
+
+#[test]
+fn test_load_and_replay_corpus() {
+    let dir = std::env::temp_dir().join(format!("conch-parser-corpus-{}", std::process::id()));
+    fs::create_dir_all(dir.join("subdir")).unwrap();
+    for case in NESTED_CORPUS {
+        fs::write(dir.join(case.name), case.source).unwrap();
+    }
+    fs::write(dir.join(".hidden"), "echo )").unwrap();
+    fs::write(dir.join("invalid-utf8"), b"echo \xff\n").unwrap();
+
+    let corpus = load_corpus(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let corpus = corpus.unwrap();
+
+    assert_eq!(corpus.len(), NESTED_CORPUS.len() + 1);
+    assert!(corpus.windows(2).all(|w| w[0].name < w[1].name));
+    assert!(corpus.contains(&RegressionCase {
+        name: "invalid-utf8".into(),
+        source: "echo \u{FFFD}\n".into(),
+    }));
+
+    assert_eq!(replay(&corpus, &StringBuilder::new), vec![]);
+}
+
+#[test]
+fn test_replay_reports_violations() {
+    let factory = || {
+        let mut hooks = CommandHooks::new(StringBuilder::new());
+        hooks.on("boom", |_, _| panic!("boom"));
+        hooks
+    };
+
+    assert_eq!(check_invariants("echo; echo )", &factory), Ok(()));
+    assert_eq!(
+        check_invariants("echo; boom", &factory),
+        Err(Violation::Panic("boom".into()))
+    );
+
+    let cases = ["echo", "boom", "echo boom"]
+        .iter()
+        .map(|&src| RegressionCase {
+            name: src.into(),
+            source: src.into(),
+        })
+        .collect::<Vec<_>>();
+    let failures = replay(&cases, &factory);
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].name, "boom");
+    assert_eq!(failures[0].to_string(), "boom: panicked: boom");
+}
+
+#[test]
+fn test_check_round_trip() {
+    for case in NESTED_CORPUS {
+        assert_round_trip(&parse(case.source).unwrap());
+    }
+
+    assert_eq!(
+        check_round_trip(&parse("foo | bar &\nbaz").unwrap()),
+        Ok(())
+    );
+}
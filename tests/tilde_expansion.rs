@@ -0,0 +1,191 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::*;
+use conch_parser::parse::TildeExpansion;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn parse(src: &str, tilde_expansion: TildeExpansion) -> TopLevelCommand<String> {
+    let mut p = make_parser(src);
+    p.set_tilde_expansion(tilde_expansion);
+    p.complete_command().unwrap().unwrap()
+}
+
+fn prefixes(src: &str) -> TopLevelCommand<String> {
+    parse(src, TildeExpansion::Prefixes)
+}
+
+fn simple(cmd: &TopLevelCommand<String>) -> &DefaultSimpleCommand {
+    match cmd.0 {
+        Command::List(AndOrList {
+            first: ListableCommand::Single(PipeableCommand::Simple(ref cmd)),
+            ..
+        }) => cmd,
+        ref c => panic!("not a simple command: {:#?}", c),
+    }
+}
+
+/// Returns the arguments of a simple command (excluding its name).
+fn args(src: &str) -> Vec<TopLevelWord<String>> {
+    simple(&prefixes(src))
+        .redirects_or_cmd_words
+        .iter()
+        .skip(1)
+        .map(|w| match *w {
+            RedirectOrCmdWord::CmdWord(ref w) => w.clone(),
+            RedirectOrCmdWord::Redirect(ref r) => panic!("unexpected redirect: {:?}", r),
+        })
+        .collect()
+}
+
+/// Returns the value of the first assignment of a simple command.
+fn value(src: &str) -> TopLevelWord<String> {
+    match simple(&prefixes(src)).redirects_or_env_vars[0] {
+        RedirectOrEnvVar::EnvVar(_, Some(ref w)) => w.clone(),
+        ref v => panic!("unexpected assignment: {:?}", v),
+    }
+}
+
+fn prefix(login: Option<&str>) -> DefaultWord {
+    Word::Simple(SimpleWord::TildePrefix(login.map(String::from)))
+}
+
+fn tilde() -> DefaultWord {
+    Word::Simple(SimpleWord::Tilde)
+}
+
+fn colon() -> DefaultWord {
+    Word::Simple(SimpleWord::Colon)
+}
+
+fn single(part: DefaultWord) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Single(part))
+}
+
+fn concat(parts: Vec<DefaultWord>) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Concat(parts))
+}
+
+#[test]
+fn test_tilde_prefixes_at_the_start_of_words() {
+    assert_eq!(
+        args("echo ~ ~/bin ~user ~user/bin ~+ ~-/x"),
+        vec![
+            single(prefix(None)),
+            concat(vec![prefix(None), lit("/bin")]),
+            single(prefix(Some("user"))),
+            concat(vec![prefix(Some("user")), lit("/bin")]),
+            single(prefix(Some("+"))),
+            concat(vec![prefix(Some("-")), lit("/x")]),
+        ]
+    );
+}
+
+#[test]
+fn test_tildes_which_do_not_begin_a_tilde_prefix() {
+    assert_eq!(
+        args("echo a~ a/~ ~\"user\" ~$user ~'' a:~ \\~ \"~\""),
+        vec![
+            concat(vec![lit("a"), tilde()]),
+            concat(vec![lit("a/"), tilde()]),
+            concat(vec![
+                tilde(),
+                Word::DoubleQuoted(vec![SimpleWord::Literal("user".into())])
+            ]),
+            concat(vec![
+                tilde(),
+                Word::Simple(SimpleWord::Param(Parameter::Var("user".into())))
+            ]),
+            concat(vec![tilde(), Word::SingleQuoted(String::new())]),
+            concat(vec![lit("a"), colon(), tilde()]),
+            single(escaped("~")),
+            double_quoted("~"),
+        ]
+    );
+}
+
+#[test]
+fn test_tilde_prefixes_after_colons_in_assignments() {
+    assert_eq!(
+        value("PATH=~/bin:~user:x~:~ cmd"),
+        concat(vec![
+            prefix(None),
+            lit("/bin"),
+            colon(),
+            prefix(Some("user")),
+            colon(),
+            lit("x"),
+            tilde(),
+            colon(),
+            prefix(None),
+        ])
+    );
+
+    // Outside of assignments colons neither begin nor end a tilde prefix
+    assert_eq!(
+        args("echo ~a:~b"),
+        vec![concat(vec![tilde(), lit("a"), colon(), tilde(), lit("b")])]
+    );
+}
+
+#[test]
+fn test_tilde_prefixes_of_redirects_case_words_and_for_words() {
+    let cmd = prefixes("cat >~/out");
+    match simple(&cmd).redirects_or_cmd_words[1] {
+        RedirectOrCmdWord::Redirect(Redirect::Write(None, ref w)) => {
+            assert_eq!(*w, concat(vec![prefix(None), lit("/out")]))
+        }
+        ref w => panic!("unexpected word: {:?}", w),
+    }
+
+    let mut p = make_parser("for i in ~x; do :; done");
+    p.set_tilde_expansion(TildeExpansion::Prefixes);
+    match p.compound_command().unwrap().kind {
+        CompoundCommandKind::For { words, .. } => {
+            assert_eq!(words, Some(vec![single(prefix(Some("x")))]));
+        }
+        kind => panic!("unexpected command: {:?}", kind),
+    }
+
+    let mut p = make_parser("case ~ in ~/x) ;; esac");
+    p.set_tilde_expansion(TildeExpansion::Prefixes);
+    match p.compound_command().unwrap().kind {
+        CompoundCommandKind::Case { word, arms } => {
+            assert_eq!(word, single(prefix(None)));
+            assert_eq!(
+                arms[0].patterns,
+                vec![concat(vec![prefix(None), lit("/x")])]
+            );
+        }
+        kind => panic!("unexpected command: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_tildes_are_tokens_by_default() {
+    let src = "PATH=~:~ cmd ~user/bin";
+    let cmd = make_parser(src).complete_command().unwrap().unwrap();
+    assert_eq!(parse(src, TildeExpansion::default()), cmd);
+    assert_eq!(parse(src, TildeExpansion::Tokens), cmd);
+
+    match simple(&cmd).redirects_or_cmd_words[1] {
+        RedirectOrCmdWord::CmdWord(ref w) => {
+            assert_eq!(*w, concat(vec![tilde(), lit("user/bin")]))
+        }
+        ref w => panic!("unexpected word: {:?}", w),
+    }
+}
+
+#[test]
+fn test_tilde_prefixes_round_trip_through_printer() {
+    let srcs = [
+        "echo ~ ~/bin ~user/bin a~ ~\"b\"",
+        "PATH=~/bin:~user:x~ cmd >~/out",
+    ];
+
+    for src in &srcs {
+        let cmd = prefixes(src);
+        assert_eq!(prefixes(&cmd.to_shell_string()), cmd, "{}", src);
+    }
+}
@@ -0,0 +1,124 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::parse::{Dialect, ParseError, ReservedWords};
+use conch_parser::token::Token;
+use conch_parser::validate::{conformance, validate, CONFORMANCE_CORPUS, DIVERGENCES};
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+mod parse_support;
+use crate::parse_support::*;
+
+const DIALECTS: &[Dialect] = &[Dialect::Posix, Dialect::Bash];
+
+#[test]
+fn test_validate_accepts_valid_scripts() {
+    for &dialect in DIALECTS {
+        assert_eq!(validate("", dialect), Ok(()));
+        assert_eq!(validate("echo foo; bar | baz &", dialect), Ok(()));
+        assert_eq!(
+            validate("if a; then b; fi\nfor x in do done; do :; done", dialect),
+            Ok(())
+        );
+        assert_eq!(
+            validate("echo fi done esac then in do } {", dialect),
+            Ok(())
+        );
+    }
+}
+
+#[test]
+fn test_validate_rejects_closing_reserved_words_as_commands() {
+    for &dialect in DIALECTS {
+        for &(source, ref tok, byte) in &[
+            ("fi", Token::Name(String::from("fi")), 0),
+            ("done", Token::Name(String::from("done")), 0),
+            ("esac", Token::Name(String::from("esac")), 0),
+            ("in", Token::Name(String::from("in")), 0),
+            ("echo; then", Token::Name(String::from("then")), 6),
+            ("foo && elif", Token::Name(String::from("elif")), 7),
+            ("foo | else", Token::Name(String::from("else")), 6),
+            ("! do", Token::Name(String::from("do")), 2),
+            ("}", Token::CurlyClose, 0),
+        ] {
+            assert_eq!(
+                validate(source, dialect),
+                Err(ParseError::Unexpected(tok.clone(), src(byte, 1, byte + 1))),
+                "{:?}",
+                source
+            );
+        }
+    }
+}
+
+#[test]
+fn test_validate_respects_dialect() {
+    assert!(validate("[[ -n $x ]]", Dialect::Bash).is_ok());
+    assert!(validate("[[ -n $x ]]", Dialect::Posix).is_err());
+}
+
+#[test]
+fn test_reserved_words_are_lenient_by_default() {
+    let mut p = make_parser("fi");
+    assert_eq!(p.reserved_words(), ReservedWords::Lenient);
+    assert_eq!(p.complete_command(), Ok(Some(cmd("fi"))));
+
+    let mut p = make_parser("fi");
+    p.set_reserved_words(ReservedWords::Strict);
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unexpected(
+            Token::Name(String::from("fi")),
+            src(0, 1, 1)
+        ))
+    );
+}
+
+#[test]
+fn test_published_divergences_disagree_with_validate() {
+    for divergence in DIVERGENCES {
+        assert!(
+            CONFORMANCE_CORPUS.contains(&divergence.source),
+            "{:?}",
+            divergence
+        );
+        assert_eq!(
+            validate(divergence.source, divergence.dialect).is_ok(),
+            !divergence.shell_accepts,
+            "{:?}",
+            divergence
+        );
+    }
+}
+
+fn shell_accepts(shell: &str, source: &str) -> Option<bool> {
+    let mut child = Command::new(shell)
+        .arg("-n")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let _ = child.stdin.take().unwrap().write_all(source.as_bytes());
+    child.wait().ok().map(|status| status.success())
+}
+
+#[test]
+fn test_divergences_from_installed_shells_are_published() {
+    // The reference shells are optional, the comparison is skipped without them
+    if shell_accepts("dash", "").is_none() || shell_accepts("bash", "").is_none() {
+        return;
+    }
+
+    let divergences = conformance(|source, dialect| {
+        let shell = match dialect {
+            Dialect::Posix => "dash",
+            Dialect::Bash => "bash",
+            Dialect::Mksh => "mksh",
+        };
+        shell_accepts(shell, source).unwrap()
+    });
+
+    assert_eq!(divergences, DIVERGENCES);
+}
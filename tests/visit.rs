@@ -0,0 +1,197 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::printer::ToShellString;
+use conch_parser::ast::visit::{self, Visitor, VisitorMut};
+use conch_parser::ast::*;
+use conch_parser::parse::ErrorRecovery;
+
+mod parse_support;
+use crate::parse_support::*;
+
+/// Records a trace of the nodes visited.
+#[derive(Default)]
+struct Trace(Vec<String>);
+
+impl Visitor for Trace {
+    fn visit_simple_command(&mut self, cmd: &DefaultSimpleCommand) {
+        self.0.push("simple".to_owned());
+        visit::walk_simple_command(self, cmd);
+    }
+
+    fn visit_assignment(&mut self, name: &str, value: Option<&TopLevelWord<String>>) {
+        self.0.push(format!("assign {}", name));
+        visit::walk_assignment(self, name, value);
+    }
+
+    fn visit_function_def(&mut self, name: &str, body: &DefaultCompoundCommand) {
+        self.0.push(format!("function {}", name));
+        visit::walk_function_def(self, name, body);
+    }
+
+    fn visit_redirect(&mut self, redirect: &DefaultRedirect) {
+        self.0.push("redirect".to_owned());
+        visit::walk_redirect(self, redirect);
+    }
+
+    fn visit_simple_word(&mut self, word: &DefaultSimpleWord) {
+        if let SimpleWord::Literal(ref lit) = *word {
+            self.0.push(lit.clone());
+        }
+        visit::walk_simple_word(self, word);
+    }
+
+    fn visit_parameter(&mut self, param: &DefaultParameter) {
+        self.0.push(param.to_string());
+    }
+
+    fn visit_arithmetic(&mut self, arith: &DefaultArithmetic) {
+        if let Arithmetic::Var(ref var) = *arith {
+            self.0.push(format!("arith {}", var));
+        }
+        visit::walk_arithmetic(self, arith);
+    }
+
+    fn visit_unparsed(&mut self, _unparsed: &UnparsedSpan) {
+        self.0.push("unparsed".to_owned());
+    }
+}
+
+fn trace(src: &str) -> Vec<String> {
+    let cmds = make_parser(src).parse_all().unwrap();
+    let mut trace = Trace::default();
+    trace.visit_commands(&cmds);
+    trace.0
+}
+
+#[test]
+fn test_visits_in_source_order() {
+    let src = "x=$a cmd >$b \"q$c\" 2<<EOF\n$d\nEOF\n\
+               f() { case $e in p) g ${h:-i};; esac; } >out\n\
+               for v in $(while j; do k; done); do echo $((l + m[n])); done";
+
+    let expected = vec![
+        "simple",
+        "assign x",
+        "${a}",
+        "cmd",
+        "redirect",
+        "${b}",
+        "q",
+        "${c}",
+        "redirect",
+        "${d}",
+        "\n",
+        "function f",
+        "${e}",
+        "p",
+        "simple",
+        "g",
+        "${h}",
+        "i",
+        "redirect",
+        "out",
+        "simple",
+        "j",
+        "simple",
+        "k",
+        "simple",
+        "echo",
+        "arith l",
+        "arith n",
+    ];
+    assert_eq!(trace(src), expected);
+}
+
+#[test]
+fn test_visits_every_compound_command() {
+    let src = "{ a; }; (b); while c; do d; done; until e; do f; done; \
+               if g; then h; elif i; then j; else k; fi; ! l | m && n || o &";
+
+    let cmds: Vec<_> = trace(src).into_iter().filter(|s| s != "simple").collect();
+    let expected: Vec<_> = "abcdefghijklmno".chars().map(|c| c.to_string()).collect();
+    assert_eq!(cmds, expected);
+}
+
+#[test]
+fn test_visits_unparsed_commands() {
+    let mut p = make_parser("a\nb )\nc");
+    p.set_error_recovery(ErrorRecovery::Passthrough);
+    let cmds = p.parse_all().unwrap();
+
+    let mut trace = Trace::default();
+    trace.visit_commands(&cmds);
+    assert_eq!(trace.0, vec!["simple", "a", "simple", "b", "unparsed", "simple", "c"]);
+}
+
+#[test]
+fn test_overriding_without_walking_prunes() {
+    #[derive(Default)]
+    struct TopLevelNames(Vec<String>);
+
+    impl Visitor for TopLevelNames {
+        fn visit_simple_command(&mut self, cmd: &DefaultSimpleCommand) {
+            for item in &cmd.redirects_or_cmd_words {
+                if let RedirectOrCmdWord::CmdWord(ref word) = *item {
+                    self.0.push(word.to_string());
+                    break;
+                }
+            }
+        }
+
+        fn visit_parameter_substitution(&mut self, _subst: &DefaultParameterSubstitution) {}
+    }
+
+    let cmds = make_parser("a $(b); if c; then d; fi; e \"$(f)\"")
+        .parse_all()
+        .unwrap();
+    let mut names = TopLevelNames::default();
+    names.visit_commands(&cmds);
+    assert_eq!(names.0, vec!["a", "c", "d", "e"]);
+}
+
+#[test]
+fn test_visitor_mut_rewrites_in_place() {
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_parameter(&mut self, param: &mut DefaultParameter) {
+            if let Parameter::Var(ref mut name) = *param {
+                name.make_ascii_uppercase();
+            }
+        }
+
+        fn visit_arithmetic(&mut self, arith: &mut DefaultArithmetic) {
+            if let Arithmetic::Var(ref mut name) = *arith {
+                name.make_ascii_uppercase();
+            }
+            visit::walk_arithmetic_mut(self, arith);
+        }
+
+        fn visit_function_def(&mut self, name: &mut String, body: &mut DefaultCompoundCommand) {
+            name.insert_str(0, "my_");
+            visit::walk_function_def_mut(self, name, body);
+        }
+
+        fn visit_commands(&mut self, cmds: &mut Vec<TopLevelCommand<String>>) {
+            // Drop any `:` commands entirely
+            cmds.retain(|cmd| cmd.to_string() != ":");
+            visit::walk_commands_mut(self, cmds);
+        }
+    }
+
+    let mut cmds = make_parser("f() { :; echo $a ${b:-$c}; }; x=$((d + 1)) :")
+        .parse_all()
+        .unwrap();
+    let original = cmds.clone();
+
+    Rename.visit_commands(&mut cmds);
+    assert_eq!(
+        cmds.to_shell_string(),
+        "my_f() {\n    echo $A ${B:-$C}\n}\nx=$(( D + 1 )) :\n"
+    );
+
+    // Function bodies shared with another AST are left untouched
+    assert_eq!(
+        original.to_shell_string(),
+        "f() {\n    :\n    echo $a ${b:-$c}\n}\nx=$(( d + 1 )) :\n"
+    );
+}
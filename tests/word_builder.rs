@@ -0,0 +1,185 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    CommandGroup, ComplexWordKind, Event, EventLog, NodeId, ParameterSubstitutionKind,
+    SimpleWordKind, WordBuilder, WordKind,
+};
+use conch_parser::ast::{Braces, DefaultParameter};
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::{BraceExpansion, Parser, TildeExpansion};
+
+/// A minimal custom representation of words.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Part {
+    Lit(String),
+    Single(String),
+    Double(Vec<Part>),
+    Param(String),
+    Default(String, Option<Vec<Part>>),
+    Cmd(usize),
+    Special(&'static str),
+    Braces(Vec<Vec<Part>>),
+}
+
+use crate::Part::*;
+
+#[derive(Debug, Default)]
+struct Parts {
+    reject_params: bool,
+}
+
+impl WordBuilder<NodeId> for Parts {
+    type Part = Part;
+    type Word = Vec<Part>;
+    type Error = String;
+
+    fn literal(&mut self, literal: String) -> Result<Part, String> {
+        Ok(Lit(literal))
+    }
+
+    fn special(&mut self, token: &'static str) -> Result<Part, String> {
+        Ok(Special(token))
+    }
+
+    fn single_quoted(&mut self, contents: String) -> Result<Part, String> {
+        Ok(Single(contents))
+    }
+
+    fn double_quoted(&mut self, parts: Vec<Part>) -> Result<Part, String> {
+        Ok(Double(parts))
+    }
+
+    fn param(&mut self, param: DefaultParameter) -> Result<Part, String> {
+        if self.reject_params {
+            Err(format!("unexpected parameter: {}", param))
+        } else {
+            Ok(Param(param.to_string()))
+        }
+    }
+
+    fn subst(
+        &mut self,
+        subst: ParameterSubstitutionKind<Vec<Part>, NodeId>,
+    ) -> Result<Part, String> {
+        match subst {
+            ParameterSubstitutionKind::Default(_, p, w) => Ok(Default(p.to_string(), w)),
+            ParameterSubstitutionKind::Command(cmds) => Ok(Cmd(cmds.commands.len())),
+            s => Err(format!("unsupported substitution: {:?}", s)),
+        }
+    }
+
+    fn braces(&mut self, braces: Braces<Part>) -> Result<Part, String> {
+        match braces {
+            Braces::Alternatives(alternatives) => Ok(Braces(alternatives)),
+            b => Err(format!("unsupported braces: {:?}", b)),
+        }
+    }
+
+    fn concat(&mut self, parts: Vec<Part>) -> Result<Vec<Part>, String> {
+        Ok(parts)
+    }
+}
+
+/// Returns the kind of every word built while parsing the source.
+fn word_kinds(src: &str) -> Vec<ComplexWordKind<NodeId>> {
+    let mut log = EventLog::new();
+    let mut parser = Parser::with_builder(Lexer::new(src.chars()), &mut log);
+    parser.set_brace_expansion(BraceExpansion::Structured);
+    parser.set_tilde_expansion(TildeExpansion::Prefixes);
+    parser.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+
+    log.into_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Word { kind, .. } => Some(kind),
+            _ => None,
+        })
+        .collect()
+}
+
+fn words(src: &str) -> Vec<Vec<Part>> {
+    word_kinds(src)
+        .into_iter()
+        .map(|kind| Parts::default().build_word(kind).unwrap())
+        .collect()
+}
+
+fn lit(s: &str) -> Part {
+    Lit(String::from(s))
+}
+
+#[test]
+fn test_words_are_assembled_from_their_parts() {
+    assert_eq!(
+        words("echo foo\"bar $x\"'baz' \\$a *.txt"),
+        vec![
+            vec![lit("echo")],
+            vec![
+                lit("foo"),
+                Double(vec![lit("bar "), Param(String::from("${x}"))]),
+                Single(String::from("baz")),
+            ],
+            // Escaped tokens are literals by default
+            vec![lit("$"), lit("a")],
+            vec![Special("*"), lit(".txt")],
+        ]
+    );
+}
+
+#[test]
+fn test_nested_words_are_assembled_via_the_same_callbacks() {
+    assert_eq!(
+        words("echo ${x:-a$y} `foo; bar`"),
+        vec![
+            vec![lit("echo")],
+            vec![Default(
+                String::from("${x}"),
+                Some(vec![lit("a"), Param(String::from("${y}"))])
+            )],
+            // The words of nested commands are built first
+            vec![lit("foo")],
+            vec![lit("bar")],
+            vec![Cmd(2)],
+        ]
+    );
+}
+
+#[test]
+fn test_braces_and_tilde_prefixes() {
+    assert_eq!(
+        words("echo a{b,c$d} ~user/x"),
+        vec![
+            vec![lit("echo")],
+            vec![
+                lit("a"),
+                Braces(vec![
+                    vec![lit("b")],
+                    vec![lit("c"), Param(String::from("${d}"))]
+                ]),
+            ],
+            // Tilde prefixes are literals by default
+            vec![lit("~user"), lit("/x")],
+        ]
+    );
+}
+
+#[test]
+fn test_errors_are_propagated() {
+    let mut parts = Parts {
+        reject_params: true,
+    };
+
+    let kinds = word_kinds("echo \"${x:-$y}\"");
+    assert_eq!(parts.build_word(kinds[0].clone()), Ok(vec![lit("echo")]));
+    assert_eq!(
+        parts.build_word(kinds[1].clone()),
+        Err(String::from("unexpected parameter: ${y}"))
+    );
+
+    let kind = ComplexWordKind::Single(WordKind::Simple(SimpleWordKind::CommandSubst(
+        CommandGroup {
+            commands: vec![],
+            trailing_comments: vec![],
+        },
+    )));
+    assert_eq!(parts.build_word(kind), Ok(vec![Cmd(0)]));
+}